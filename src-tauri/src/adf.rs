@@ -0,0 +1,427 @@
+//! Atlassian Document Format, the rich text JIRA Cloud uses for worklog
+//! comments. Only the nodes the app writes are modelled; comments read back
+//! from JIRA stay untyped (`Worklog::comment`) since they can contain
+//! anything the JIRA editor supports, see [`text_of`]. Connections to REST
+//! v2 (Server and Data Center) send the same documents as plain text
+//! instead, see [`CommentFormat`].
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename = "doc")]
+pub struct Document {
+    pub version: u32,
+    pub content: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Block {
+    Paragraph {
+        #[serde(default)]
+        content: Vec<Inline>,
+    },
+    BulletList {
+        content: Vec<ListItem>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename = "listItem")]
+pub struct ListItem {
+    pub content: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Inline {
+    Text {
+        text: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        marks: Vec<Mark>,
+    },
+    /// `@user`; `text` is what is shown if the account can't be resolved.
+    Mention { attrs: MentionAttrs },
+    HardBreak,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MentionAttrs {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Mark {
+    Link { attrs: LinkAttrs },
+    Strong,
+    Em,
+    Code,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkAttrs {
+    pub href: String,
+}
+
+/// A worklog comment as commands accept it: plain text, or a document built
+/// by the frontend.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CommentInput {
+    Text(String),
+    Document(Document),
+}
+
+/// How a connection writes comment bodies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentFormat {
+    /// REST v3, JIRA Cloud.
+    #[default]
+    Adf,
+    /// REST v2, JIRA Server and Data Center.
+    PlainText,
+}
+
+/// A comment body as it goes over the wire.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CommentBody {
+    Adf(Document),
+    Text(String),
+}
+
+impl CommentFormat {
+    pub fn body(self, document: &Document) -> CommentBody {
+        match self {
+            CommentFormat::Adf => CommentBody::Adf(document.clone()),
+            CommentFormat::PlainText => CommentBody::Text(document.to_plain_text()),
+        }
+    }
+}
+
+impl Inline {
+    pub fn text(text: &str) -> Self {
+        Inline::Text {
+            text: text.to_string(),
+            marks: Vec::new(),
+        }
+    }
+
+    pub fn link(text: &str, href: &str) -> Self {
+        Inline::Text {
+            text: text.to_string(),
+            marks: vec![Mark::Link {
+                attrs: LinkAttrs { href: href.to_string() },
+            }],
+        }
+    }
+}
+
+/// Plain text with bare `http(s)://` addresses turned into links.
+fn push_text(content: &mut Vec<Inline>, line: &str) {
+    let mut rest = line;
+    while let Some(start) = ["https://", "http://"].iter().filter_map(|scheme| rest.find(scheme)).min() {
+        let end = rest[start..].find(char::is_whitespace).map_or(rest.len(), |i| start + i);
+        let url = rest[start..end].trim_end_matches(['.', ',', ';', ':', ')']);
+        if start > 0 {
+            content.push(Inline::text(&rest[..start]));
+        }
+        content.push(Inline::link(url, url));
+        rest = &rest[start + url.len()..];
+    }
+    // JIRA rejects empty text nodes.
+    if !rest.is_empty() {
+        content.push(Inline::text(rest));
+    }
+}
+
+/// Lines joined by hard breaks.
+fn paragraph(lines: &[&str]) -> Block {
+    let mut content = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            content.push(Inline::HardBreak);
+        }
+        push_text(&mut content, line);
+    }
+    Block::Paragraph { content }
+}
+
+fn bullet(line: &str) -> Option<&str> {
+    line.strip_prefix("- ").or_else(|| line.strip_prefix("* "))
+}
+
+impl Document {
+    pub fn new(content: Vec<Block>) -> Self {
+        Self { version: 1, content }
+    }
+
+    /// Blank lines separate paragraphs, single newlines become hard breaks,
+    /// runs of lines starting with `- ` or `* ` become a bullet list and bare
+    /// URLs become links.
+    pub fn from_text(text: &str) -> Self {
+        let mut blocks = Vec::new();
+        for chunk in text.split("\n\n").map(str::trim).filter(|chunk| !chunk.is_empty()) {
+            let lines: Vec<&str> = chunk.lines().map(str::trim_end).collect();
+            let mut plain: Vec<&str> = Vec::new();
+            let mut items: Vec<ListItem> = Vec::new();
+
+            for line in lines {
+                match bullet(line.trim_start()) {
+                    Some(item) => {
+                        if !plain.is_empty() {
+                            blocks.push(paragraph(&plain));
+                            plain.clear();
+                        }
+                        items.push(ListItem {
+                            content: vec![paragraph(&[item.trim()])],
+                        });
+                    }
+                    None => {
+                        if !items.is_empty() {
+                            blocks.push(Block::BulletList {
+                                content: std::mem::take(&mut items),
+                            });
+                        }
+                        plain.push(line);
+                    }
+                }
+            }
+            if !plain.is_empty() {
+                blocks.push(paragraph(&plain));
+            }
+            if !items.is_empty() {
+                blocks.push(Block::BulletList { content: items });
+            }
+        }
+        Self::new(blocks)
+    }
+
+    /// The document as `from_text` reads it back: blocks separated by blank
+    /// lines, hard breaks as newlines, list items as `* ` lines. Links keep
+    /// their address, formatting is dropped.
+    pub fn to_plain_text(&self) -> String {
+        fn inline_text(content: &[Inline]) -> String {
+            content
+                .iter()
+                .map(|inline| match inline {
+                    Inline::Text { text, marks } => match marks.iter().find_map(|mark| match mark {
+                        Mark::Link { attrs } if attrs.href != *text => Some(&attrs.href),
+                        _ => None,
+                    }) {
+                        Some(href) => format!("{} ({})", text, href),
+                        None => text.clone(),
+                    },
+                    Inline::Mention { attrs } => attrs.text.clone().unwrap_or_else(|| format!("@{}", attrs.id)),
+                    Inline::HardBreak => "\n".to_string(),
+                })
+                .collect()
+        }
+
+        fn block_text(block: &Block) -> String {
+            match block {
+                Block::Paragraph { content } => inline_text(content),
+                Block::BulletList { content } => content
+                    .iter()
+                    .map(|item| {
+                        let text: Vec<String> = item.content.iter().map(block_text).collect();
+                        format!("* {}", text.join(" ").replace('\n', " "))
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            }
+        }
+
+        self.content.iter().map(block_text).collect::<Vec<_>>().join("\n\n")
+    }
+
+    /// The document's text, with blocks and inline nodes separated by
+    /// spaces.
+    pub fn text(&self) -> String {
+        serde_json::to_value(self).map(|value| text_of(&value)).unwrap_or_default()
+    }
+
+    /// Catches what JIRA would reject with an unhelpful 400.
+    pub fn validate(&self) -> Result<(), String> {
+        fn check_block(block: &Block) -> Result<(), String> {
+            match block {
+                Block::Paragraph { content } => content.iter().try_for_each(check_inline),
+                Block::BulletList { content } => {
+                    if content.is_empty() {
+                        return Err("Bullet lists need at least one item".to_string());
+                    }
+                    content.iter().flat_map(|item| &item.content).try_for_each(check_block)
+                }
+            }
+        }
+
+        fn check_inline(inline: &Inline) -> Result<(), String> {
+            match inline {
+                Inline::Text { text, marks } => {
+                    if text.is_empty() {
+                        return Err("Text nodes must not be empty".to_string());
+                    }
+                    marks.iter().try_for_each(|mark| match mark {
+                        Mark::Link { attrs } if attrs.href.trim().is_empty() => {
+                            Err(format!("Link '{}' has no address", text))
+                        }
+                        _ => Ok(()),
+                    })
+                }
+                Inline::Mention { attrs } if attrs.id.trim().is_empty() => {
+                    Err("Mentions need an account id".to_string())
+                }
+                _ => Ok(()),
+            }
+        }
+
+        self.content.iter().try_for_each(check_block)
+    }
+}
+
+/// Flattens an untyped rich text value into plain text. Cloud returns ADF
+/// documents, Server returns plain strings.
+pub fn text_of(value: &serde_json::Value) -> String {
+    fn collect(node: &serde_json::Value, out: &mut Vec<String>) {
+        match node {
+            serde_json::Value::String(text) => out.push(text.clone()),
+            serde_json::Value::Object(map) => {
+                if let Some(serde_json::Value::String(text)) = map.get("text") {
+                    out.push(text.clone());
+                }
+                if let Some(serde_json::Value::Array(children)) = map.get("content") {
+                    children.iter().for_each(|child| collect(child, out));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut parts = Vec::new();
+    collect(value, &mut parts);
+    parts.join(" ")
+}
+
+impl CommentInput {
+    pub fn to_document(&self) -> Document {
+        match self {
+            CommentInput::Text(text) => Document::from_text(text),
+            CommentInput::Document(document) => document.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serializes_the_wire_format() {
+        let document = Document::new(vec![Block::Paragraph {
+            content: vec![Inline::text("Done"), Inline::HardBreak],
+        }]);
+        assert_eq!(
+            serde_json::to_value(&document).unwrap(),
+            json!({
+                "type": "doc",
+                "version": 1,
+                "content": [{
+                    "type": "paragraph",
+                    "content": [{ "type": "text", "text": "Done" }, { "type": "hardBreak" }],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn accepts_text_or_documents() {
+        let text: CommentInput = serde_json::from_value(json!("Review")).unwrap();
+        assert_eq!(text, CommentInput::Text("Review".to_string()));
+
+        let document: CommentInput = serde_json::from_value(json!({
+            "type": "doc",
+            "version": 1,
+            "content": [{
+                "type": "paragraph",
+                "content": [
+                    { "type": "mention", "attrs": { "id": "5b10a2844c20165700ede21g", "text": "@Ann" } },
+                    { "type": "text", "text": " docs", "marks": [{ "type": "link", "attrs": { "href": "https://example.com" } }] },
+                ],
+            }],
+        }))
+        .unwrap();
+        let CommentInput::Document(document) = document else {
+            panic!("expected a document");
+        };
+        assert!(document.validate().is_ok());
+    }
+
+    #[test]
+    fn builds_lists_and_links_from_text() {
+        let document = Document::from_text("Fixed login:\n- see https://example.com/PR-1.\n- tests\n\nDone");
+        assert_eq!(
+            document.content,
+            vec![
+                Block::Paragraph {
+                    content: vec![Inline::text("Fixed login:")],
+                },
+                Block::BulletList {
+                    content: vec![
+                        ListItem {
+                            content: vec![Block::Paragraph {
+                                content: vec![
+                                    Inline::text("see "),
+                                    Inline::link("https://example.com/PR-1", "https://example.com/PR-1"),
+                                    Inline::text("."),
+                                ],
+                            }],
+                        },
+                        ListItem {
+                            content: vec![Block::Paragraph {
+                                content: vec![Inline::text("tests")],
+                            }],
+                        },
+                    ],
+                },
+                Block::Paragraph {
+                    content: vec![Inline::text("Done")],
+                },
+            ]
+        );
+    }
+
+    const NOTE: &str = "Fixed login:\n- see https://example.com/PR-1.\n- tests\n\nDone\nfor now";
+
+    #[test]
+    fn round_trips_adf_bodies() {
+        let document = Document::from_text(NOTE);
+        let body = serde_json::to_value(CommentFormat::Adf.body(&document)).unwrap();
+        assert_eq!(body["type"], "doc");
+
+        let read_back: CommentInput = serde_json::from_value(body).unwrap();
+        assert_eq!(read_back.to_document(), document);
+    }
+
+    #[test]
+    fn round_trips_plain_text_bodies() {
+        let document = Document::from_text(NOTE);
+        let body = serde_json::to_value(CommentFormat::PlainText.body(&document)).unwrap();
+        assert_eq!(body, json!("Fixed login:\n\n* see https://example.com/PR-1.\n* tests\n\nDone\nfor now"));
+
+        let read_back: CommentInput = serde_json::from_value(body).unwrap();
+        assert_eq!(read_back.to_document(), document);
+    }
+
+    #[test]
+    fn rejects_empty_text_nodes() {
+        let document = Document::new(vec![Block::Paragraph {
+            content: vec![Inline::text("")],
+        }]);
+        assert!(document.validate().is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! Time analytics for charts, added up here from the worklog history in the
+//! cache database rather than by the frontend. The history holds my
+//! worklogs as they are created, synced from the offline queue, or read for
+//! a summary or export; `refresh_analytics` fetches a range that hasn't
+//! been read yet. Deleted worklogs are taken out again.
+
+use chrono::{Days, Local, NaiveDate};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::jira_types::UserWorklog;
+use crate::storage::{self, HistoryEntry, Storage};
+use crate::worklog_backend::{self, WorklogBackend};
+use crate::{client_for, clock, summary, JiraState};
+
+/// Longest range one refresh fetches.
+const MAX_REFRESH_DAYS: i64 = 366;
+/// Fetched a month at a time.
+const CHUNK_DAYS: u64 = 31;
+const MAX_CHART_DAYS: i64 = 10 * 366;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProjectTime {
+    pub project: String,
+    pub seconds: u64,
+    pub worklogs: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DayTime {
+    pub date: NaiveDate,
+    pub seconds: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Streak {
+    pub days: u32,
+    pub from_date: Option<NaiveDate>,
+    pub to_date: Option<NaiveDate>,
+}
+
+/// Runs of workdays with time logged; weekends neither break nor extend
+/// them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Streaks {
+    /// Up to today, or the workday before while today has nothing yet.
+    pub current: Streak,
+    pub longest: Streak,
+}
+
+fn entry(issue_key: &str, worklog_id: &str, started: &str, seconds: u32) -> Option<HistoryEntry> {
+    let started = JiraClient::parse_jira_datetime(started).ok()?;
+    Some(HistoryEntry {
+        worklog_id: worklog_id.to_string(),
+        issue_key: issue_key.to_string(),
+        day: started.with_timezone(&Local).date_naive(),
+        seconds,
+    })
+}
+
+/// Keeps `worklogs`, everything the backend has of mine in `[from, to]`, as
+/// the history of those days. Call before rolling them up.
+pub fn record_range(app_handle: &AppHandle<Wry>, client: &JiraClient, from: NaiveDate, to: NaiveDate, worklogs: &[UserWorklog]) {
+    let entries: Vec<HistoryEntry> = worklogs
+        .iter()
+        .filter_map(|user| entry(&user.issue_key, &user.worklog.id, &user.worklog.started, user.worklog.time_spent_seconds))
+        .filter(|entry| (from..=to).contains(&entry.day))
+        .collect();
+    if let Err(e) = app_handle.state::<Storage>().replace_history(&storage::account(client), from, to, &entries) {
+        tracing::warn!("Failed to keep the worklog history: {}", e);
+    }
+}
+
+/// Keeps a worklog I just created or changed.
+pub fn record(app_handle: &AppHandle<Wry>, client: &JiraClient, issue_key: &str, worklog_id: &str, started: &str, seconds: u32) {
+    let Some(entry) = entry(issue_key, worklog_id, started, seconds) else {
+        return;
+    };
+    if let Err(e) = app_handle.state::<Storage>().note_history(&storage::account(client), &entry) {
+        tracing::warn!("Failed to keep worklog {} in the history: {}", worklog_id, e);
+    }
+}
+
+pub fn forget(app_handle: &AppHandle<Wry>, client: &JiraClient, worklog_id: &str) {
+    if let Err(e) = app_handle.state::<Storage>().forget_history(&storage::account(client), worklog_id) {
+        tracing::warn!("Failed to drop worklog {} from the history: {}", worklog_id, e);
+    }
+}
+
+/// `APP` for `APP-12`.
+fn project_of(issue_key: &str) -> &str {
+    issue_key.rsplit_once('-').map_or(issue_key, |(project, _)| project)
+}
+
+/// Most time first.
+fn by_project(entries: &[HistoryEntry]) -> Vec<ProjectTime> {
+    let mut projects: BTreeMap<&str, ProjectTime> = BTreeMap::new();
+    for entry in entries {
+        let project = project_of(&entry.issue_key);
+        let total = projects.entry(project).or_insert_with(|| ProjectTime {
+            project: project.to_string(),
+            seconds: 0,
+            worklogs: 0,
+        });
+        total.seconds += entry.seconds as u64;
+        total.worklogs += 1;
+    }
+    let mut projects: Vec<ProjectTime> = projects.into_values().collect();
+    projects.sort_by(|a, b| b.seconds.cmp(&a.seconds).then_with(|| a.project.cmp(&b.project)));
+    projects
+}
+
+/// Every day of `[from, to]`, days without worklogs included.
+fn by_day(entries: &[HistoryEntry], from: NaiveDate, to: NaiveDate) -> Vec<DayTime> {
+    let mut days: BTreeMap<NaiveDate, u64> = from.iter_days().take_while(|date| *date <= to).map(|date| (date, 0)).collect();
+    for entry in entries {
+        if let Some(seconds) = days.get_mut(&entry.day) {
+            *seconds += entry.seconds as u64;
+        }
+    }
+    days.into_iter().map(|(date, seconds)| DayTime { date, seconds }).collect()
+}
+
+/// The workday before `date`.
+fn previous_workday(date: NaiveDate) -> NaiveDate {
+    let mut day = date - Days::new(1);
+    while !summary::is_workday(day) {
+        day = day - Days::new(1);
+    }
+    day
+}
+
+fn streaks(entries: &[HistoryEntry], today: NaiveDate) -> Streaks {
+    let mut logged: Vec<NaiveDate> = entries
+        .iter()
+        .filter(|entry| entry.seconds > 0 && summary::is_workday(entry.day) && entry.day <= today)
+        .map(|entry| entry.day)
+        .collect();
+    logged.sort();
+    logged.dedup();
+
+    let mut streaks = Streaks::default();
+    let mut run = Streak::default();
+    for day in logged {
+        if run.to_date.is_some_and(|last| previous_workday(day) == last) {
+            run.days += 1;
+        } else {
+            run = Streak {
+                days: 1,
+                from_date: Some(day),
+                to_date: None,
+            };
+        }
+        run.to_date = Some(day);
+        if run.days > streaks.longest.days {
+            streaks.longest = run.clone();
+        }
+    }
+
+    let last_counted = if summary::is_workday(today) { previous_workday(today) } else { previous_workday(today + Days::new(1)) };
+    if run.to_date.is_some_and(|last| last == today || last == last_counted) {
+        streaks.current = run;
+    }
+    streaks
+}
+
+fn check_range(from_date: NaiveDate, to_date: NaiveDate) -> Result<(), JiraError> {
+    if from_date > to_date {
+        return Err(JiraError::Validation("fromDate must not be after toDate".to_string()));
+    }
+    Ok(())
+}
+
+/// Time per project in `[fromDate, toDate]`, from the history.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_time_by_project(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<ProjectTime>, JiraError> {
+    check_range(from_date, to_date)?;
+    let client = client_for(&state, connection.as_deref()).await?;
+    let entries = app_handle.state::<Storage>().load_history(&storage::account(&client), Some((from_date, to_date)));
+    Ok(by_project(&entries))
+}
+
+/// Time per day in `[fromDate, toDate]`, from the history.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_time_by_day(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<DayTime>, JiraError> {
+    check_range(from_date, to_date)?;
+    if (to_date - from_date).num_days() >= MAX_CHART_DAYS {
+        return Err(JiraError::Validation(format!("Chart at most {} days at a time", MAX_CHART_DAYS)));
+    }
+    let client = client_for(&state, connection.as_deref()).await?;
+    let entries = app_handle.state::<Storage>().load_history(&storage::account(&client), Some((from_date, to_date)));
+    Ok(by_day(&entries, from_date, to_date))
+}
+
+/// The current and the longest streak in the whole history.
+#[tauri::command]
+pub async fn get_streaks(
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Streaks, JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    let entries = app_handle.state::<Storage>().load_history(&storage::account(&client), None);
+    Ok(streaks(&entries, clock::now(&app_handle).date_naive()))
+}
+
+/// Fetches my worklogs of `[fromDate, toDate]` into the history, e.g. the
+/// months before the app was installed. Returns how many there are.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn refresh_analytics(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<usize, JiraError> {
+    check_range(from_date, to_date)?;
+    if (to_date - from_date).num_days() >= MAX_REFRESH_DAYS {
+        return Err(JiraError::Validation(format!("Refresh at most {} days at a time", MAX_REFRESH_DAYS)));
+    }
+    let client = client_for(&state, connection.as_deref()).await?;
+    let backend = worklog_backend::for_app(&app_handle, client.clone())?;
+
+    let mut count = 0;
+    let mut chunk_from = from_date;
+    while chunk_from <= to_date {
+        let chunk_to = (chunk_from + Days::new(CHUNK_DAYS - 1)).min(to_date);
+        let worklogs = backend.user_worklogs(chunk_from, chunk_to).await?;
+        record_range(&app_handle, &client, chunk_from, chunk_to, &worklogs);
+        count += worklogs.len();
+        chunk_from = chunk_to + Days::new(1);
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logged(issue_key: &str, month: u32, day: u32, hours: u32) -> HistoryEntry {
+        HistoryEntry {
+            worklog_id: format!("{}-{}-{}", issue_key, month, day),
+            issue_key: issue_key.to_string(),
+            day: NaiveDate::from_ymd_opt(2024, month, day).unwrap(),
+            seconds: hours * 3600,
+        }
+    }
+
+    #[test]
+    fn adds_up_projects_and_streaks() {
+        // June 2024: the 7th is a Friday.
+        let entries = [
+            logged("APP-1", 6, 3, 2),
+            logged("APP-2", 6, 4, 3),
+            logged("OPS-7", 6, 4, 1),
+            // The 5th is missing, so the 6th starts over.
+            logged("APP-1", 6, 6, 4),
+            logged("APP-1", 6, 7, 4),
+            logged("OPS-7", 6, 10, 1),
+            logged("APP-3", 6, 11, 2),
+        ];
+
+        let projects = by_project(&entries);
+        let totals: Vec<_> = projects.iter().map(|project| (project.project.as_str(), project.seconds / 3600)).collect();
+        assert_eq!(totals, [("APP", 15), ("OPS", 2)]);
+
+        let date = |day| NaiveDate::from_ymd_opt(2024, 6, day).unwrap();
+        let days = by_day(&entries, date(4), date(5));
+        assert_eq!(days.iter().map(|day| day.seconds / 3600).collect::<Vec<_>>(), [4, 0]);
+
+        // Over the weekend, 6th to 11th.
+        let streaks = streaks(&entries, date(12));
+        assert_eq!((streaks.longest.days, streaks.longest.from_date), (4, Some(date(6))));
+        assert_eq!(streaks.current, streaks.longest);
+
+        assert_eq!(self::streaks(&entries, date(13)).current, Streak::default());
+        assert_eq!(previous_workday(date(10)), date(7));
+    }
+}
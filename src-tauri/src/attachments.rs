@@ -0,0 +1,145 @@
+//! Attaching files to issues, e.g. a screenshot of a bug, and saving
+//! attachments to disk. Downloads are written as they arrive, so large files
+//! never sit in memory whole.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::State;
+use tokio::io::AsyncWriteExt;
+
+use crate::error::JiraError;
+use crate::http;
+use crate::jira_api::JiraClient;
+use crate::{client_for, JiraState};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: String,
+    pub filename: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    #[serde(default)]
+    pub created: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DownloadedAttachment {
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+impl JiraClient {
+    /// Attaches the file at `path` to `issue_key`.
+    pub async fn upload_attachment(&self, issue_key: &str, path: &Path) -> Result<Vec<Attachment>, JiraError> {
+        let url = self.api_url(&format!("issue/{}/attachments", issue_key));
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| JiraError::Validation(format!("{} is not a file", path.display())))?
+            .to_string();
+        let contents = tokio::fs::read(path)
+            .await
+            .map_err(|e| JiraError::Other(format!("Failed to read {}: {}", path.display(), e)))?;
+        let form = reqwest::multipart::Form::new().part("file", reqwest::multipart::Part::bytes(contents).file_name(file_name));
+
+        let authorization = self.authorization().await?;
+        let request = self
+            .http_client()
+            .post(&url)
+            .header("Accept", "application/json")
+            // Attachments are refused as possible XSRF without it.
+            .header("X-Atlassian-Token", "no-check")
+            .header("Authorization", authorization)
+            .timeout(http::TRANSFER_TIMEOUT)
+            .multipart(form);
+        let response = self.send(request).await?;
+
+        Self::read_json("upload_attachment", response).await
+    }
+
+    /// Saves attachment `attachment_id` to `dest`, returning the bytes
+    /// written. The file only appears at `dest` once it's complete.
+    pub async fn download_attachment(&self, attachment_id: &str, dest: &Path) -> Result<u64, JiraError> {
+        let url = self.api_url(&format!("attachment/content/{}", attachment_id));
+
+        let authorization = self.authorization().await?;
+        let request = self
+            .http_client()
+            .get(&url)
+            .header("Accept", "*/*")
+            .header("Authorization", authorization)
+            .timeout(http::TRANSFER_TIMEOUT);
+        let mut response = self.send(request).await?;
+
+        let partial = dest.with_extension(match dest.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) => format!("{}.part", extension),
+            None => "part".to_string(),
+        });
+        let write_error = |e: std::io::Error| JiraError::Other(format!("Failed to write {}: {}", partial.display(), e));
+        let mut file = tokio::fs::File::create(&partial).await.map_err(write_error)?;
+
+        let mut bytes = 0;
+        let written: Result<(), JiraError> = async {
+            while let Some(chunk) = response.chunk().await? {
+                file.write_all(&chunk).await.map_err(write_error)?;
+                bytes += chunk.len() as u64;
+            }
+            file.flush().await.map_err(write_error)
+        }
+        .await;
+        drop(file);
+
+        if let Err(e) = written {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e);
+        }
+        tokio::fs::rename(&partial, dest)
+            .await
+            .map_err(|e| JiraError::Other(format!("Failed to save {}: {}", dest.display(), e)))?;
+        Ok(bytes)
+    }
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn upload_attachment(
+    issue_key: String,
+    file_path: PathBuf,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<Attachment>, JiraError> {
+    let issue_key = issue_key.trim().to_uppercase();
+    if !JiraClient::is_valid_issue_key(&issue_key) {
+        return Err(JiraError::Validation(format!("Invalid issue key: {}", issue_key)));
+    }
+    if !file_path.is_file() {
+        return Err(JiraError::Validation(format!("{} is not a file", file_path.display())));
+    }
+
+    client_for(&state, connection.as_deref()).await?
+        .upload_attachment(&issue_key, &file_path)
+        .await
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn download_attachment(
+    attachment_id: String,
+    dest_path: PathBuf,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<DownloadedAttachment, JiraError> {
+    let attachment_id = attachment_id.trim();
+    if attachment_id.is_empty() || !attachment_id.chars().all(|c| c.is_ascii_digit()) {
+        return Err(JiraError::Validation(format!("Invalid attachment id: {}", attachment_id)));
+    }
+    if dest_path.is_dir() {
+        return Err(JiraError::Validation(format!("{} is a folder", dest_path.display())));
+    }
+
+    let bytes = client_for(&state, connection.as_deref()).await?
+        .download_attachment(attachment_id, &dest_path)
+        .await?;
+    Ok(DownloadedAttachment { path: dest_path, bytes })
+}
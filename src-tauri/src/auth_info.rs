@@ -0,0 +1,124 @@
+//! What is known about the credentials of a connection: how it signs in,
+//! the scopes an OAuth session was granted, when tokens run out, and on
+//! Server and Data Center the personal access tokens of the account, so a
+//! token doesn't die unnoticed mid-sprint.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::error::JiraError;
+use crate::jira_api::{JiraAuth, JiraClient};
+use crate::{client_for, url, JiraState};
+
+/// Atlassian drops rotating refresh tokens left unused this long.
+const REFRESH_TOKEN_IDLE_DAYS: i64 = 90;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMethod {
+    /// Email and API token on Cloud.
+    ApiToken,
+    /// Username and password or token on Server and Data Center.
+    Basic,
+    #[serde(rename = "oauth")]
+    OAuth,
+}
+
+/// A personal access token of the account, as Data Center lists them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonalAccessToken {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    /// `None` for tokens that never expire.
+    #[serde(default)]
+    pub expiring_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_accessed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuthInfo {
+    pub method: AuthMethod,
+    pub account: String,
+    /// OAuth scopes; empty for the other methods.
+    pub scopes: Vec<String>,
+    /// When the current access token expires, if JIRA says.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Data Center only; which one is in use can't be told.
+    pub personal_access_tokens: Vec<PersonalAccessToken>,
+    /// What can't be found out for this method.
+    pub note: Option<String>,
+}
+
+impl JiraClient {
+    /// The personal access tokens of the signed-in account (Data Center 8.14
+    /// and later).
+    pub async fn get_personal_access_tokens(&self) -> Result<Vec<PersonalAccessToken>, JiraError> {
+        let url = url::join(&self.base_url, "rest/pat/latest", "tokens");
+
+        let authorization = self.authorization().await?;
+        let request = self
+            .http_client()
+            .get(&url)
+            .header("Accept", "application/json")
+            .header("Authorization", authorization);
+        let response = self.send(request).await?;
+        Self::read_json("personal_access_tokens", response).await
+    }
+
+    async fn is_cloud(&self) -> bool {
+        match self.server_info().await {
+            Ok(info) => info.deployment_type.as_deref() == Some("Cloud"),
+            Err(_) => self.base_url.contains(".atlassian.net") || self.base_url.contains("api.atlassian.com"),
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_auth_info(connection: Option<String>, state: State<'_, JiraState>) -> Result<AuthInfo, JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+
+    if let JiraAuth::OAuth(session) = &client.auth {
+        let tokens = session.tokens();
+        return Ok(AuthInfo {
+            method: AuthMethod::OAuth,
+            account: client.email.clone(),
+            scopes: tokens.scopes,
+            expires_at: Some(tokens.expires_at),
+            personal_access_tokens: Vec::new(),
+            note: Some(format!(
+                "Access tokens are refreshed automatically; the session ends after {} days without use",
+                REFRESH_TOKEN_IDLE_DAYS
+            )),
+        });
+    }
+
+    if client.is_cloud().await {
+        return Ok(AuthInfo {
+            method: AuthMethod::ApiToken,
+            account: client.email.clone(),
+            scopes: Vec::new(),
+            expires_at: None,
+            personal_access_tokens: Vec::new(),
+            note: Some("JIRA doesn't tell when an API token expires; see id.atlassian.com/manage-profile/security/api-tokens".to_string()),
+        });
+    }
+
+    let (personal_access_tokens, note) = match client.get_personal_access_tokens().await {
+        Ok(tokens) => (tokens, None),
+        Err(JiraError::NotFound(_)) => (Vec::new(), Some("This JIRA version has no personal access tokens".to_string())),
+        Err(e) => return Err(e),
+    };
+    Ok(AuthInfo {
+        method: AuthMethod::Basic,
+        account: client.email.clone(),
+        scopes: Vec::new(),
+        expires_at: None,
+        personal_access_tokens,
+        note,
+    })
+}
@@ -0,0 +1,65 @@
+//! Launching at login through the OS (a launch agent on macOS, the registry
+//! on Windows, an XDG autostart entry on Linux). Login launches carry
+//! `AUTOSTART_ARG` so `run()` can keep the main window hidden when
+//! `start_minimized` is set.
+
+use serde::Serialize;
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Wry};
+use tauri_plugin_autostart::{MacosLauncher, ManagerExt};
+
+use crate::settings;
+
+pub const AUTOSTART_ARG: &str = "--autostart";
+
+pub fn plugin() -> TauriPlugin<Wry> {
+    tauri_plugin_autostart::init(MacosLauncher::LaunchAgent, Some(vec![AUTOSTART_ARG]))
+}
+
+/// Whether this process was started at login.
+pub fn launched_at_login() -> bool {
+    std::env::args().skip(1).any(|arg| arg == AUTOSTART_ARG)
+}
+
+/// Whether to keep the main window hidden on this launch.
+pub fn start_hidden(app_handle: &AppHandle<Wry>) -> bool {
+    launched_at_login() && settings::current(app_handle).start_minimized
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AutostartStatus {
+    pub enabled: bool,
+    pub start_minimized: bool,
+}
+
+#[tauri::command]
+pub async fn get_autostart(app_handle: AppHandle<Wry>) -> Result<AutostartStatus, String> {
+    Ok(AutostartStatus {
+        enabled: app_handle.autolaunch().is_enabled().map_err(|e| e.to_string())?,
+        start_minimized: settings::current(&app_handle).start_minimized,
+    })
+}
+
+/// Registers or removes the login item; `start_minimized` is kept as is
+/// when not given.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_autostart(
+    enabled: bool,
+    start_minimized: Option<bool>,
+    app_handle: AppHandle<Wry>,
+) -> Result<AutostartStatus, String> {
+    let autolaunch = app_handle.autolaunch();
+    let result = if enabled {
+        autolaunch.enable()
+    } else {
+        autolaunch.disable()
+    };
+    result.map_err(|e| format!("Failed to {} launching at login: {}", if enabled { "enable" } else { "disable" }, e))?;
+
+    let mut current = settings::current(&app_handle);
+    if let Some(start_minimized) = start_minimized.filter(|&value| value != current.start_minimized) {
+        current.start_minimized = start_minimized;
+        settings::save(&app_handle, &mut current)?;
+    }
+    get_autostart(app_handle).await
+}
@@ -0,0 +1,380 @@
+//! Drafts the worklogs of a past stretch of days in one go: the day's
+//! meetings from the calendar, the `backfill.recurring` entries, what the
+//! day plan still has open, and with `fill_to_target` an unassigned entry
+//! for the rest of the daily target. Nothing is logged; the frontend shows
+//! the draft for editing and submits it.
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Weekday};
+use serde::{Deserialize, Serialize};
+use std::time::Duration as StdDuration;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::calendar::{self, Event};
+use crate::cron::CronSchedule;
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::jira_types::UserWorklog;
+use crate::plan::{DayPlan, PlanState};
+use crate::settings::{self, AppSettings};
+use crate::{client_for, clock, operations, JiraState};
+
+/// Longest range one draft covers.
+const MAX_DAYS: i64 = 31;
+/// Leftovers under this aren't drafted.
+const MIN_ENTRY_SECONDS: u32 = 15 * 60;
+const BACKFILL_BUDGET: StdDuration = StdDuration::from_secs(3 * 60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackfillStrategy {
+    /// Only what the calendar, recurring entries and plans account for.
+    SourcesOnly,
+    /// Also an unassigned entry for the rest of the daily target.
+    #[default]
+    FillToTarget,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DraftSource {
+    Calendar,
+    Recurring,
+    Plan,
+    Fill,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DraftEntry {
+    /// `None` for the fill entry, which still needs an issue.
+    pub issue_key: Option<String>,
+    pub description: String,
+    /// JIRA timestamp, ready for `create_worklog`.
+    pub started: String,
+    pub time_spent_seconds: u32,
+    pub source: DraftSource,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DraftDay {
+    pub date: NaiveDate,
+    pub logged_seconds: u32,
+    pub target_seconds: u32,
+    pub entries: Vec<DraftEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackfillDraft {
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub strategy: BackfillStrategy,
+    pub days: Vec<DraftDay>,
+    /// Drafted, not counting what was already logged.
+    pub total_seconds: u32,
+}
+
+/// Everything one day is drafted from.
+struct DayInputs<'a> {
+    date: NaiveDate,
+    settings: &'a AppSettings,
+    strategy: BackfillStrategy,
+    events: &'a [Event],
+    plan: Option<&'a DayPlan>,
+    /// The day's worklogs.
+    worklogs: &'a [UserWorklog],
+}
+
+fn is_workday(date: NaiveDate) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+fn at(date: NaiveDate, time: chrono::NaiveTime) -> Option<DateTime<Local>> {
+    Local.from_local_datetime(&date.and_time(time)).earliest()
+}
+
+fn draft_day(inputs: &DayInputs) -> DraftDay {
+    let DayInputs {
+        date,
+        settings,
+        strategy,
+        ..
+    } = *inputs;
+    let logged_on = |issue_key: &str| -> u32 {
+        inputs
+            .worklogs
+            .iter()
+            .filter(|entry| entry.issue_key == issue_key)
+            .map(|entry| entry.worklog.time_spent_seconds)
+            .sum()
+    };
+    let logged_seconds: u32 = inputs.worklogs.iter().map(|entry| entry.worklog.time_spent_seconds).sum();
+    let target_seconds = if is_workday(date) {
+        settings.daily_target_seconds()
+    } else {
+        0
+    };
+
+    let mut entries: Vec<DraftEntry> = calendar::suggest(inputs.events, date, &settings.calendar.rules, inputs.worklogs)
+        .entries
+        .into_iter()
+        .map(|meeting| DraftEntry {
+            issue_key: Some(meeting.issue_key),
+            description: meeting.title,
+            started: meeting.started,
+            time_spent_seconds: meeting.time_spent_seconds,
+            source: DraftSource::Calendar,
+        })
+        .collect();
+    let drafted_on = |entries: &[DraftEntry], issue_key: &str| -> u32 {
+        entries
+            .iter()
+            .filter(|entry| entry.issue_key.as_deref() == Some(issue_key))
+            .map(|entry| entry.time_spent_seconds)
+            .sum()
+    };
+
+    // Entries without a time of their own go one after another from the
+    // start of the day.
+    let mut cursor = at(date, settings.backfill.day_start);
+    let mut next_start = |seconds: u32| -> String {
+        let Some(start) = cursor else {
+            return String::new();
+        };
+        cursor = Some(start + Duration::seconds(seconds as i64));
+        JiraClient::format_jira_datetime(&start)
+    };
+
+    for recurring in &settings.backfill.recurring {
+        let cron = recurring.cron.as_deref().and_then(|expression| expression.parse::<CronSchedule>().ok());
+        let applies = match &cron {
+            Some(cron) => cron.matches_date(date),
+            None => recurring.weekdays.contains(&date.weekday()),
+        };
+        if !applies || logged_on(&recurring.issue_key) > 0 || drafted_on(&entries, &recurring.issue_key) > 0 {
+            continue;
+        }
+        let seconds = recurring.minutes * 60;
+        let start = recurring.start.or_else(|| cron.as_ref().and_then(|cron| cron.times().next()));
+        let started = match start.and_then(|start| at(date, start)) {
+            Some(start) => JiraClient::format_jira_datetime(&start),
+            None => next_start(seconds),
+        };
+        entries.push(DraftEntry {
+            issue_key: Some(recurring.issue_key.clone()),
+            description: recurring.description.clone(),
+            started,
+            time_spent_seconds: seconds,
+            source: DraftSource::Recurring,
+        });
+    }
+
+    for planned in inputs.plan.iter().flat_map(|plan| plan.entries.iter()) {
+        let planned_seconds = (planned.hours * 3600.0).round() as u32;
+        let open = planned_seconds
+            .saturating_sub(logged_on(&planned.issue_key))
+            .saturating_sub(drafted_on(&entries, &planned.issue_key));
+        if open < MIN_ENTRY_SECONDS {
+            continue;
+        }
+        entries.push(DraftEntry {
+            issue_key: Some(planned.issue_key.clone()),
+            description: String::new(),
+            started: next_start(open),
+            time_spent_seconds: open,
+            source: DraftSource::Plan,
+        });
+    }
+
+    let drafted: u32 = entries.iter().map(|entry| entry.time_spent_seconds).sum();
+    let rest = target_seconds.saturating_sub(logged_seconds + drafted);
+    if strategy == BackfillStrategy::FillToTarget && rest >= MIN_ENTRY_SECONDS {
+        entries.push(DraftEntry {
+            issue_key: None,
+            description: String::new(),
+            started: next_start(rest),
+            time_spent_seconds: rest,
+            source: DraftSource::Fill,
+        });
+    }
+
+    DraftDay {
+        date,
+        logged_seconds,
+        target_seconds,
+        entries,
+    }
+}
+
+fn local_date(entry: &UserWorklog) -> Option<NaiveDate> {
+    JiraClient::parse_jira_datetime(&entry.worklog.started)
+        .ok()
+        .map(|started| started.with_timezone(&Local).date_naive())
+}
+
+/// A draft of the worklogs still missing between `from_date` and `to_date`.
+/// Progress is reported under `operation`, one step per day.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn backfill_period(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    strategy: Option<BackfillStrategy>,
+    connection: Option<String>,
+    operation: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<BackfillDraft, JiraError> {
+    let today = clock::now(&app_handle).date_naive();
+    if from_date > to_date {
+        return Err(JiraError::Validation("fromDate must not be after toDate".to_string()));
+    }
+    if to_date > today {
+        return Err(JiraError::Validation("Only past days can be backfilled".to_string()));
+    }
+    if (to_date - from_date).num_days() >= MAX_DAYS {
+        return Err(JiraError::Validation(format!("A backfill covers at most {} days", MAX_DAYS)));
+    }
+
+    let client = client_for(&state, connection.as_deref()).await?;
+    let settings = settings::effective(&app_handle);
+    let strategy = strategy.unwrap_or_default();
+    let days: Vec<NaiveDate> = from_date.iter_days().take_while(|day| *day <= to_date).collect();
+    let operation = operations::start(&app_handle, operation, "Backfill", Some(days.len() as u32), BACKFILL_BUDGET);
+
+    let fetched = async {
+        let worklogs = client.get_user_worklogs(from_date, to_date).await?;
+        let events = calendar::load_events(&app_handle, &settings.calendar.feeds).await?;
+        Ok::<_, JiraError>((worklogs, events))
+    }
+    .await;
+    let (worklogs, events) = match fetched {
+        Ok(fetched) => fetched,
+        Err(e) => return operation.complete(Err(e)),
+    };
+    let plans = app_handle.state::<PlanState>().lock().map_err(|e| e.to_string())?.clone();
+
+    let mut draft = BackfillDraft {
+        from_date,
+        to_date,
+        strategy,
+        days: Vec::new(),
+        total_seconds: 0,
+    };
+    for date in days {
+        if operation.should_stop() {
+            break;
+        }
+        let day_worklogs: Vec<UserWorklog> =
+            worklogs.iter().filter(|entry| local_date(entry) == Some(date)).cloned().collect();
+        let day = draft_day(&DayInputs {
+            date,
+            settings: &settings,
+            strategy,
+            events: &events,
+            plan: plans.get(&date),
+            worklogs: &day_worklogs,
+        });
+        draft.total_seconds += day.entries.iter().map(|entry| entry.time_spent_seconds).sum::<u32>();
+        draft.days.push(day);
+        operation.advance(1);
+    }
+    operation.complete(Ok(draft))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::PlannedEntry;
+    use crate::settings::RecurringEntry;
+    use chrono::NaiveTime;
+
+    fn monday() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, 3).unwrap()
+    }
+
+    fn logged(issue_key: &str, seconds: u32) -> UserWorklog {
+        serde_json::from_value(serde_json::json!({
+            "issue_key": issue_key,
+            "issue_summary": "",
+            "worklog": {
+                "id": "1",
+                "issueId": "10001",
+                "timeSpentSeconds": seconds,
+                "started": "2024-06-03T09:00:00.000+0000",
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn drafts_recurring_and_open_planned_time_then_fills_the_day() {
+        let mut settings = AppSettings::default();
+        settings.backfill.recurring.push(RecurringEntry {
+            issue_key: "OPS-1".to_string(),
+            description: "Email".to_string(),
+            weekdays: vec![Weekday::Mon, Weekday::Wed],
+            start: None,
+            minutes: 30,
+            cron: None,
+        });
+        let plan = DayPlan {
+            date: monday(),
+            entries: vec![
+                PlannedEntry {
+                    issue_key: "APP-1".to_string(),
+                    hours: 4.0,
+                },
+                PlannedEntry {
+                    issue_key: "APP-2".to_string(),
+                    hours: 1.0,
+                },
+            ],
+            planned_at: Local::now(),
+        };
+        let worklogs = [logged("APP-1", 3600), logged("APP-2", 3600)];
+
+        let day = draft_day(&DayInputs {
+            date: monday(),
+            settings: &settings,
+            strategy: BackfillStrategy::FillToTarget,
+            events: &[],
+            plan: Some(&plan),
+            worklogs: &worklogs,
+        });
+
+        let drafted: Vec<(Option<&str>, u32, DraftSource)> = day
+            .entries
+            .iter()
+            .map(|entry| (entry.issue_key.as_deref(), entry.time_spent_seconds, entry.source))
+            .collect();
+        assert_eq!(
+            drafted,
+            [
+                (Some("OPS-1"), 1800, DraftSource::Recurring),
+                (Some("APP-1"), 3 * 3600, DraftSource::Plan),
+                (None, 8 * 3600 - 2 * 3600 - 1800 - 3 * 3600, DraftSource::Fill),
+            ]
+        );
+        let start = |index: usize| {
+            JiraClient::parse_jira_datetime(&day.entries[index].started)
+                .unwrap()
+                .with_timezone(&Local)
+                .time()
+        };
+        assert_eq!(start(0), NaiveTime::from_hms_opt(9, 0, 0).unwrap());
+        assert_eq!(start(1), NaiveTime::from_hms_opt(9, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn weekends_are_not_filled() {
+        let sunday = monday() - Duration::days(1);
+        let day = draft_day(&DayInputs {
+            date: sunday,
+            settings: &AppSettings::default(),
+            strategy: BackfillStrategy::FillToTarget,
+            events: &[],
+            plan: None,
+            worklogs: &[],
+        });
+        assert!(day.entries.is_empty());
+        assert_eq!(day.target_seconds, 0);
+    }
+}
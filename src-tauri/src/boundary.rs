@@ -0,0 +1,137 @@
+//! Keeps a bug in one command from taking the app down. Every panic is
+//! logged under a short reference; a command that panics while it's being
+//! invoked is rejected with an `internal` error carrying it, instead of
+//! unwinding into the webview's IPC callback and aborting the process.
+//! Async commands run in their own tasks, where a panic only ends that task;
+//! those, and panics in background tasks, reach the frontend as an
+//! `internal-error` event with the reference.
+
+use serde::Serialize;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::panic::AssertUnwindSafe;
+use std::sync::OnceLock;
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Emitter, Wry};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Serialize)]
+struct InternalError {
+    reference: String,
+    message: String,
+    location: Option<String>,
+}
+
+/// Panics to emit as `internal-error`; a channel because the panic hook
+/// must not take the locks emitting needs.
+static EVENTS: OnceLock<UnboundedSender<InternalError>> = OnceLock::new();
+
+thread_local! {
+    /// Set while a command is being invoked on this thread; its panic is
+    /// answered with a rejection rather than an event.
+    static INVOKING: Cell<bool> = const { Cell::new(false) };
+    /// The last panic on this thread, for the rejection.
+    static LAST_PANIC: RefCell<Option<InternalError>> = const { RefCell::new(None) };
+}
+
+fn new_reference() -> String {
+    let mut bytes = [0u8; 4];
+    if getrandom::getrandom(&mut bytes).is_err() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.subsec_nanos())
+            .unwrap_or_default();
+        bytes = nanos.to_le_bytes();
+    }
+    format!("E-{:08X}", u32::from_le_bytes(bytes))
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Unknown panic".to_string()
+    }
+}
+
+/// Logs every panic with a reference before the default hook runs. Called
+/// first thing, so panics during setup are covered too.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let error = InternalError {
+            reference: new_reference(),
+            message: panic_message(info.payload()),
+            location: info.location().map(|location| format!("{}:{}", location.file(), location.line())),
+        };
+        tracing::error!(
+            reference = %error.reference,
+            location = error.location.as_deref().unwrap_or("unknown"),
+            "Panic: {}",
+            error.message
+        );
+
+        if INVOKING.with(Cell::get) {
+            LAST_PANIC.with(|last| *last.borrow_mut() = Some(error));
+        } else if let Some(events) = EVENTS.get() {
+            let _ = events.send(error);
+        }
+        default_hook(info);
+    }));
+}
+
+/// Starts emitting `internal-error` events for panics outside of a command
+/// invocation.
+pub fn attach(app_handle: &AppHandle<Wry>) {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<InternalError>();
+    if EVENTS.set(sender).is_err() {
+        return;
+    }
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(error) = receiver.recv().await {
+            if let Err(e) = app_handle.emit("internal-error", &error) {
+                tracing::warn!("Failed to emit internal-error event: {}", e);
+            }
+        }
+    });
+}
+
+/// Wraps the handler from `generate_handler!` so panics while invoking a
+/// command are answered with `AppError::Internal`.
+pub fn catch_panics<F>(handler: F) -> impl Fn(Invoke<Wry>) -> bool + Send + Sync + 'static
+where
+    F: Fn(Invoke<Wry>) -> bool + Send + Sync + 'static,
+{
+    move |invoke: Invoke<Wry>| {
+        let command = invoke.message.command().to_string();
+        // Answering is first come, first served; the clone only gets to
+        // answer if the handler didn't.
+        let resolver = invoke.resolver.clone();
+
+        let was_invoking = INVOKING.with(|invoking| invoking.replace(true));
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| handler(invoke)));
+        INVOKING.with(|invoking| invoking.set(was_invoking));
+
+        match result {
+            Ok(handled) => handled,
+            Err(payload) => {
+                let error = LAST_PANIC.with(|last| last.borrow_mut().take()).unwrap_or_else(|| InternalError {
+                    reference: new_reference(),
+                    message: panic_message(payload.as_ref()),
+                    location: None,
+                });
+                tracing::error!(reference = %error.reference, "Command {} panicked", command);
+                resolver.reject(AppError::Internal {
+                    reference: error.reference,
+                    message: format!("{} failed unexpectedly", command),
+                });
+                true
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::{clock, settings, JiraState};
+use crate::notifications::NotificationKind;
+use crate::{notifications, store};
+
+const BREAK_STATS_FILE: &str = "break_stats.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DailyBreakStats {
+    pub reminders_sent: u32,
+    /// Breaks of at least the minimum length taken after a reminder.
+    pub breaks_taken: u32,
+    pub longest_stretch_minutes: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakStatsReport {
+    pub days: BTreeMap<NaiveDate, DailyBreakStats>,
+    pub reminders_sent: u32,
+    pub breaks_taken: u32,
+    /// Share of reminders that were followed by a break, `None` without reminders.
+    pub compliance_rate: Option<f64>,
+}
+
+#[derive(Default)]
+pub struct BreakTracker {
+    stretch_started: Option<DateTime<Local>>,
+    last_running: Option<DateTime<Local>>,
+    reminded: bool,
+    stats: BTreeMap<NaiveDate, DailyBreakStats>,
+}
+
+pub type BreakState = Mutex<BreakTracker>;
+
+pub fn load(app_handle: &AppHandle<Wry>) -> BreakTracker {
+    let stats = match store::data_file(app_handle, BREAK_STATS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(_) => BTreeMap::new(),
+    };
+
+    BreakTracker {
+        stats,
+        ..Default::default()
+    }
+}
+
+fn persist(app_handle: &AppHandle<Wry>, tracker: &BreakTracker) {
+    let result = store::data_file(app_handle, BREAK_STATS_FILE)
+        .and_then(|path| store::save_json(&path, &tracker.stats));
+    if let Err(e) = result {
+        tracing::warn!("Failed to save break stats: {}", e);
+    }
+}
+
+/// Follows how long timers have been running without a real break and raises
+/// a reminder once the configured threshold is passed.
+pub fn check(app_handle: &AppHandle<Wry>) {
+    let config = settings::current(app_handle).breaks;
+    if !config.enabled {
+        return;
+    }
+
+    let now = clock::now(app_handle);
+    let running = {
+        let state = app_handle.state::<JiraState>();
+        let active = state.timer().lock().unwrap_or_else(|e| e.into_inner());
+        active.as_ref().is_some_and(|timer| timer.resumed_at.is_some())
+    };
+
+    let state = app_handle.state::<BreakState>();
+    let mut tracker = state.lock().unwrap_or_else(|e| e.into_inner());
+    let min_break = chrono::Duration::minutes(config.min_break_minutes as i64);
+    let on_break = tracker
+        .last_running
+        .is_some_and(|last_running| now - last_running >= min_break);
+
+    if on_break {
+        if let Some(started) = tracker.stretch_started.take() {
+            let minutes = (tracker.last_running.unwrap_or(now) - started).num_minutes().max(0) as u32;
+            let reminded = tracker.reminded;
+            let day = tracker.stats.entry(started.date_naive()).or_default();
+            day.longest_stretch_minutes = day.longest_stretch_minutes.max(minutes);
+            if reminded {
+                day.breaks_taken += 1;
+            }
+            tracker.reminded = false;
+            tracker.last_running = None;
+            persist(app_handle, &tracker);
+        }
+    }
+
+    if !running {
+        return;
+    }
+
+    let started = *tracker.stretch_started.get_or_insert(now);
+    tracker.last_running = Some(now);
+
+    let threshold = chrono::Duration::minutes(config.threshold_minutes as i64);
+    if !tracker.reminded && now - started >= threshold {
+        tracker.reminded = true;
+        tracker.stats.entry(now.date_naive()).or_default().reminders_sent += 1;
+        persist(app_handle, &tracker);
+
+        notifications::notify(
+            app_handle,
+            NotificationKind::Timer,
+            "Time for a break",
+            &format!(
+                "You've been tracking time for {} minutes straight. Step away for a few minutes.",
+                (now - started).num_minutes()
+            ),
+        );
+    }
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_break_stats(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    state: State<'_, BreakState>,
+) -> Result<BreakStatsReport, String> {
+    if from_date > to_date {
+        return Err("fromDate must not be after toDate".to_string());
+    }
+
+    let tracker = state.lock().map_err(|e| e.to_string())?;
+    let days: BTreeMap<NaiveDate, DailyBreakStats> = tracker
+        .stats
+        .range(from_date..=to_date)
+        .map(|(date, stats)| (*date, stats.clone()))
+        .collect();
+
+    let reminders_sent = days.values().map(|d| d.reminders_sent).sum();
+    let breaks_taken = days.values().map(|d| d.breaks_taken).sum();
+
+    Ok(BreakStatsReport {
+        days,
+        reminders_sent,
+        breaks_taken,
+        compliance_rate: (reminders_sent > 0).then(|| breaks_taken as f64 / reminders_sent as f64),
+    })
+}
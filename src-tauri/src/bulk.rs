@@ -0,0 +1,141 @@
+//! Logging a day's or a week's worth of worklogs in one call. Entries are
+//! submitted a few at a time and each gets its own result; entries that went
+//! through stay logged when others fail.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, State, Wry};
+use tokio::task::JoinSet;
+
+use crate::adf::CommentInput;
+use crate::closed_guard;
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::offline_queue::{self, WorklogSubmission};
+use crate::{client_for, operations, settings, worklog_rules, JiraState};
+
+const MAX_ENTRIES: usize = 100;
+/// Submissions in flight at once.
+const MAX_CONCURRENT: usize = 4;
+const BULK_BUDGET: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorklogEntry {
+    pub issue_key: String,
+    pub description: CommentInput,
+    pub started: String,
+    pub time_spent: String,
+    /// Logs it even if the issue is done or it is a weekend; see
+    /// `closed_issue_guard` and `worklog_rules`.
+    #[serde(default)]
+    pub confirmed: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum BulkOutcome {
+    /// Created, or queued while JIRA is unreachable.
+    Submitted { submission: WorklogSubmission },
+    Failed { error: JiraError },
+    /// Not tried because the operation was cancelled or ran out of time.
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkWorklogResult {
+    /// Position in `entries`.
+    pub index: usize,
+    pub issue_key: String,
+    #[serde(flatten)]
+    pub outcome: BulkOutcome,
+}
+
+/// Submits `entries` through the offline queue like `create_worklog`, up to
+/// `MAX_CONCURRENT` at a time, and returns a result per entry in the order
+/// given. Progress is reported under `operation`; cancelling it lets the
+/// submissions in flight finish and skips the rest.
+#[tauri::command]
+pub async fn create_worklogs_bulk(
+    entries: Vec<WorklogEntry>,
+    connection: Option<String>,
+    operation: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<BulkWorklogResult>, JiraError> {
+    if entries.len() > MAX_ENTRIES {
+        return Err(JiraError::Validation(format!("At most {} worklogs can be logged at once", MAX_ENTRIES)));
+    }
+    let client = client_for(&state, connection.as_deref()).await?;
+    let time_units = settings::current(&app_handle).time_units;
+
+    let mut results: Vec<BulkWorklogResult> = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| BulkWorklogResult {
+            index,
+            issue_key: entry.issue_key.clone(),
+            outcome: BulkOutcome::Skipped,
+        })
+        .collect();
+
+    let operation = operations::start(&app_handle, operation, "Log worklogs", Some(entries.len() as u32), BULK_BUDGET);
+    let mut pending = entries.into_iter().enumerate();
+    let mut tasks = JoinSet::new();
+    loop {
+        while tasks.len() < MAX_CONCURRENT && !operation.should_stop() {
+            let Some((index, entry)) = pending.next() else {
+                break;
+            };
+            let seconds = match JiraClient::parse_time_to_seconds(&entry.time_spent, &time_units) {
+                Ok(seconds) => seconds,
+                Err(error) => {
+                    results[index].outcome = BulkOutcome::Failed { error };
+                    operation.advance(1);
+                    continue;
+                }
+            };
+
+            // Stays the outcome if the task dies before reporting back.
+            results[index].outcome = BulkOutcome::Failed {
+                error: JiraError::Other("The submission did not finish".to_string()),
+            };
+            let app_handle = app_handle.clone();
+            let client = client.clone();
+            let connection = connection.clone();
+            tasks.spawn(async move {
+                let submission = match worklog_rules::normalize(&app_handle, &entry.started, seconds, entry.confirmed) {
+                    Ok((started, seconds)) => match closed_guard::check(&app_handle, &client, &entry.issue_key, entry.confirmed).await {
+                        Ok(()) => {
+                            offline_queue::submit(
+                                &app_handle,
+                                &client,
+                                connection.as_deref(),
+                                &entry.issue_key,
+                                &entry.description,
+                                &started,
+                                seconds,
+                            )
+                            .await
+                        }
+                        Err(e) => Err(e),
+                    },
+                    Err(e) => Err(e),
+                };
+                (index, submission)
+            });
+        }
+
+        let Some(joined) = tasks.join_next().await else {
+            break;
+        };
+        match joined {
+            Ok((index, Ok(submission))) => results[index].outcome = BulkOutcome::Submitted { submission },
+            Ok((index, Err(error))) => results[index].outcome = BulkOutcome::Failed { error },
+            Err(e) => tracing::warn!("A bulk worklog submission failed to finish: {}", e),
+        }
+        operation.advance(1);
+    }
+
+    operation.complete(Ok(results))
+}
@@ -0,0 +1,469 @@
+//! Worklog suggestions from the meetings in ICS calendar feeds
+//! (`calendar.feeds`, local files or URLs). A meeting is matched to an issue
+//! by a key in its title, otherwise by the first of `calendar.rules` whose
+//! pattern matches it. Daily and weekly recurrences are expanded; times with
+//! a `TZID` are read as local time.
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use regex::RegexBuilder;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Wry};
+
+use crate::jira_api::JiraClient;
+use crate::jira_types::UserWorklog;
+use crate::settings::{self, CalendarRule};
+use crate::{connected_client, http, mentions, offline, JiraState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Recurrence {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<DateTime<Local>>,
+    /// Weekly only; the start's weekday when empty.
+    weekdays: Vec<Weekday>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    summary: String,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+    recurrence: Option<Recurrence>,
+    excluded: Vec<DateTime<Local>>,
+}
+
+/// Joins folded lines: a line starting with a space or tab continues the
+/// previous one.
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+/// `20240603T100000Z` in UTC, `20240603T100000` (floating or with a `TZID`)
+/// in local time. All-day dates give `None`.
+fn parse_time(value: &str) -> Option<DateTime<Local>> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(Utc.from_utc_datetime(&time).with_timezone(&Local));
+    }
+    let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    Local.from_local_datetime(&time).earliest()
+}
+
+/// `PT1H30M`, `P1D` and the like.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.strip_prefix('P')?;
+    let (date_part, time_part) = value.split_once('T').unwrap_or((value, ""));
+    let mut seconds = 0;
+    let date_units: &[(char, i64)] = &[('W', 7 * 86400), ('D', 86400)];
+    let time_units: &[(char, i64)] = &[('H', 3600), ('M', 60), ('S', 1)];
+    for (part, units) in [(date_part, date_units), (time_part, time_units)] {
+        let mut number = String::new();
+        for c in part.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let (_, unit) = units.iter().find(|(unit, _)| *unit == c)?;
+            seconds += number.parse::<i64>().ok()? * unit;
+            number.clear();
+        }
+    }
+    Some(Duration::seconds(seconds))
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    // `BYDAY` may carry a position, e.g. `1MO`; weekly rules don't use it.
+    let day = value.trim_start_matches(|c: char| c.is_ascii_digit() || c == '-' || c == '+');
+    match day {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// Daily and weekly `RRULE`s; other frequencies give `None`.
+fn parse_recurrence(value: &str) -> Option<Recurrence> {
+    let mut recurrence = Recurrence {
+        frequency: Frequency::Daily,
+        interval: 1,
+        count: None,
+        until: None,
+        weekdays: Vec::new(),
+    };
+    let mut frequency = None;
+    for part in value.split(';') {
+        let (name, value) = part.split_once('=')?;
+        match name {
+            "FREQ" => {
+                frequency = match value {
+                    "DAILY" => Some(Frequency::Daily),
+                    "WEEKLY" => Some(Frequency::Weekly),
+                    _ => return None,
+                }
+            }
+            "INTERVAL" => recurrence.interval = value.parse().ok().filter(|interval| *interval > 0)?,
+            "COUNT" => recurrence.count = Some(value.parse().ok()?),
+            "UNTIL" => {
+                recurrence.until = parse_time(value).or_else(|| {
+                    let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+                    Local.from_local_datetime(&date.and_hms_opt(23, 59, 59)?).earliest()
+                })
+            }
+            "BYDAY" => recurrence.weekdays = value.split(',').filter_map(parse_weekday).collect(),
+            _ => {}
+        }
+    }
+    recurrence.frequency = frequency?;
+    Some(recurrence)
+}
+
+/// The timed events of an ICS calendar; all-day and cancelled ones are left
+/// out.
+fn parse_ics(ics: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut current: Option<Vec<(String, String)>> = None;
+
+    for line in unfold(ics) {
+        match line.trim_end() {
+            "BEGIN:VEVENT" => current = Some(Vec::new()),
+            "END:VEVENT" => {
+                if let Some(event) = current.take().and_then(|properties| build_event(&properties)) {
+                    events.push(event);
+                }
+            }
+            line => {
+                let (Some(properties), Some((name, value))) = (current.as_mut(), line.split_once(':')) else {
+                    continue;
+                };
+                // Parameters like `;TZID=...` only matter for the value type.
+                let name = name.split(';').next().unwrap_or(name).to_ascii_uppercase();
+                properties.push((name, value.to_string()));
+            }
+        }
+    }
+    events
+}
+
+fn build_event(properties: &[(String, String)]) -> Option<Event> {
+    let property = |name: &str| properties.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str());
+    if property("STATUS") == Some("CANCELLED") {
+        return None;
+    }
+
+    let start = parse_time(property("DTSTART")?)?;
+    let end = match property("DTEND") {
+        Some(end) => parse_time(end)?,
+        None => start + parse_duration(property("DURATION")?)?,
+    };
+    let excluded = properties
+        .iter()
+        .filter(|(name, _)| name == "EXDATE")
+        .flat_map(|(_, value)| value.split(','))
+        .filter_map(parse_time)
+        .collect();
+
+    Some(Event {
+        summary: unescape(property("SUMMARY").unwrap_or_default()).trim().to_string(),
+        start,
+        end,
+        recurrence: property("RRULE").and_then(parse_recurrence),
+        excluded,
+    })
+}
+
+impl Recurrence {
+    fn matches(&self, first: NaiveDate, date: NaiveDate) -> bool {
+        let days = (date - first).num_days();
+        match self.frequency {
+            Frequency::Daily => days % self.interval as i64 == 0,
+            Frequency::Weekly => {
+                let week_start = |date: NaiveDate| date - Duration::days(date.weekday().num_days_from_monday() as i64);
+                let weeks = (week_start(date) - week_start(first)).num_days() / 7;
+                let on_day = if self.weekdays.is_empty() {
+                    date.weekday() == first.weekday()
+                } else {
+                    self.weekdays.contains(&date.weekday())
+                };
+                weeks % self.interval as i64 == 0 && on_day
+            }
+        }
+    }
+}
+
+impl Event {
+    /// The start and end of the event's occurrence on `date`.
+    fn on(&self, date: NaiveDate) -> Option<(DateTime<Local>, DateTime<Local>)> {
+        let first = self.start.date_naive();
+        let Some(recurrence) = &self.recurrence else {
+            return (first == date).then_some((self.start, self.end));
+        };
+        if date < first {
+            return None;
+        }
+
+        let start = Local.from_local_datetime(&date.and_time(self.start.time())).earliest()?;
+        if recurrence.until.is_some_and(|until| start > until) || self.excluded.contains(&start) {
+            return None;
+        }
+        if !recurrence.matches(first, date) {
+            return None;
+        }
+        if let Some(count) = recurrence.count {
+            let earlier = first
+                .iter_days()
+                .take_while(|day| *day < date)
+                .filter(|day| recurrence.matches(first, *day))
+                .count();
+            if earlier >= count as usize {
+                return None;
+            }
+        }
+        Some((start, start + (self.end - self.start)))
+    }
+}
+
+/// The issue a meeting titled `title` belongs to.
+fn match_issue(title: &str, rules: &[CalendarRule]) -> Option<String> {
+    if let Some(key) = mentions::find_issue_keys(title, "").into_iter().next() {
+        return Some(key);
+    }
+    rules
+        .iter()
+        .find(|rule| {
+            RegexBuilder::new(&rule.pattern)
+                .case_insensitive(true)
+                .build()
+                .is_ok_and(|pattern| pattern.is_match(title))
+        })
+        .map(|rule| rule.issue_key.clone())
+}
+
+async fn read_feed(app_handle: &AppHandle<Wry>, feed: &str) -> Result<String, String> {
+    if !feed.contains("://") {
+        return std::fs::read_to_string(feed).map_err(|e| format!("Failed to read {}: {}", feed, e));
+    }
+    let url = match feed.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{}", rest),
+        None => feed.to_string(),
+    };
+    offline::check(app_handle).map_err(|e| e.to_string())?;
+    http::client()?
+        .get(&url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch the calendar {}: {}", feed, e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read the calendar {}: {}", feed, e))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SuggestedWorklog {
+    pub issue_key: String,
+    /// The meeting title, as the worklog description.
+    pub title: String,
+    /// JIRA timestamp, ready for `create_worklog`.
+    pub started: String,
+    pub time_spent_seconds: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CalendarSuggestions {
+    pub date: NaiveDate,
+    pub entries: Vec<SuggestedWorklog>,
+    pub total_seconds: u32,
+    /// Titles of the day's meetings no issue was found for.
+    pub unmatched: Vec<String>,
+}
+
+/// Whether `worklogs` already cover the meeting on `issue_key` at `start`.
+fn already_logged(worklogs: &[UserWorklog], issue_key: &str, start: DateTime<Local>, end: DateTime<Local>) -> bool {
+    worklogs.iter().any(|entry| {
+        entry.issue_key == issue_key
+            && JiraClient::parse_jira_datetime(&entry.worklog.started)
+                .is_ok_and(|started| started >= start && started < end)
+    })
+}
+
+pub fn suggest(events: &[Event], date: NaiveDate, rules: &[CalendarRule], worklogs: &[UserWorklog]) -> CalendarSuggestions {
+    let mut meetings: Vec<(&Event, DateTime<Local>, DateTime<Local>)> = events
+        .iter()
+        .filter_map(|event| event.on(date).map(|(start, end)| (event, start, end)))
+        .filter(|(_, start, end)| end > start)
+        .collect();
+    meetings.sort_by_key(|(_, start, _)| *start);
+
+    let mut suggestions = CalendarSuggestions {
+        date,
+        entries: Vec::new(),
+        total_seconds: 0,
+        unmatched: Vec::new(),
+    };
+    for (event, start, end) in meetings {
+        let Some(issue_key) = match_issue(&event.summary, rules) else {
+            suggestions.unmatched.push(event.summary.clone());
+            continue;
+        };
+        if already_logged(worklogs, &issue_key, start, end) {
+            continue;
+        }
+        let seconds = (end - start).num_seconds() as u32;
+        suggestions.total_seconds += seconds;
+        suggestions.entries.push(SuggestedWorklog {
+            issue_key,
+            title: event.summary.clone(),
+            started: JiraClient::format_jira_datetime(&start),
+            time_spent_seconds: seconds,
+        });
+    }
+    suggestions
+}
+
+/// The events of all `feeds`.
+pub async fn load_events(app_handle: &AppHandle<Wry>, feeds: &[String]) -> Result<Vec<Event>, String> {
+    let mut events = Vec::new();
+    for feed in feeds {
+        events.extend(parse_ics(&read_feed(app_handle, feed).await?));
+    }
+    Ok(events)
+}
+
+/// Suggestions for `date` from the configured feeds, leaving out meetings
+/// already logged when connected. `None` without feeds.
+pub async fn suggestions_for(app_handle: &AppHandle<Wry>, date: NaiveDate) -> Result<Option<CalendarSuggestions>, String> {
+    let settings = settings::current(app_handle).calendar;
+    if settings.feeds.is_empty() {
+        return Ok(None);
+    }
+
+    let events = load_events(app_handle, &settings.feeds).await?;
+    let worklogs = match connected_client(&app_handle.state::<JiraState>()).await {
+        Ok(client) => client.get_user_worklogs(date, date).await.unwrap_or_else(|e| {
+            tracing::warn!("Failed to check logged meetings: {}", e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    };
+    Ok(Some(suggest(&events, date, &settings.rules, &worklogs)))
+}
+
+/// For the daily reminder, when there is something to log.
+pub fn reminder_line(suggestions: &CalendarSuggestions) -> Option<String> {
+    match suggestions.entries.len() {
+        0 => None,
+        1 => Some(format!("1 suggested entry from your calendar ({:.1}h), confirm?", suggestions.total_seconds as f64 / 3600.0)),
+        count => Some(format!(
+            "{} suggested entries from your calendar totaling {:.1}h, confirm?",
+            count,
+            suggestions.total_seconds as f64 / 3600.0
+        )),
+    }
+}
+
+/// Worklog entries proposed from the day's meetings; `None` when no feed is
+/// configured.
+#[tauri::command]
+pub async fn get_suggested_worklogs(date: NaiveDate, app_handle: AppHandle<Wry>) -> Result<Option<CalendarSuggestions>, String> {
+    suggestions_for(&app_handle, date).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ICS: &str = "BEGIN:VCALENDAR\r\n\
+BEGIN:VEVENT\r\n\
+SUMMARY:Standup\r\n\
+DTSTART;TZID=Europe/Berlin:20240603T093000\r\n\
+DTEND;TZID=Europe/Berlin:20240603T094500\r\n\
+RRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR;COUNT=7\r\n\
+EXDATE;TZID=Europe/Berlin:20240605T093000\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+SUMMARY:APP-12 design review with a very long title that the server \r\n \
+folds\r\n\
+DTSTART:20240604T140000\r\n\
+DURATION:PT1H30M\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+SUMMARY:Offsite\r\n\
+DTSTART;VALUE=DATE:20240604\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+SUMMARY:Cancelled sync\r\n\
+STATUS:CANCELLED\r\n\
+DTSTART:20240604T160000\r\n\
+DTEND:20240604T163000\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, day).unwrap()
+    }
+
+    fn rules() -> Vec<CalendarRule> {
+        vec![CalendarRule {
+            pattern: "^standup".to_string(),
+            issue_key: "OPS-1".to_string(),
+        }]
+    }
+
+    #[test]
+    fn reads_timed_events() {
+        let events = parse_ics(ICS);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].summary, "APP-12 design review with a very long title that the server folds");
+        assert_eq!((events[1].end - events[1].start).num_minutes(), 90);
+    }
+
+    #[test]
+    fn expands_weekly_recurrences() {
+        let events = parse_ics(ICS);
+        let standup = &events[0];
+        let days: Vec<u32> = (1..=30).filter(|day| standup.on(date(*day)).is_some()).collect();
+        // Seven weekdays from the 3rd, without the excluded 5th.
+        assert_eq!(days, [3, 4, 6, 7, 10, 11]);
+    }
+
+    #[test]
+    fn suggests_matched_meetings_not_yet_logged() {
+        let events = parse_ics(ICS);
+        let suggestions = suggest(&events, date(4), &rules(), &[]);
+
+        let keys: Vec<&str> = suggestions.entries.iter().map(|entry| entry.issue_key.as_str()).collect();
+        assert_eq!(keys, ["OPS-1", "APP-12"]);
+        assert_eq!(suggestions.total_seconds, 15 * 60 + 90 * 60);
+        assert!(suggestions.unmatched.is_empty());
+
+        let unmatched = suggest(&events, date(4), &[], &[]);
+        assert_eq!(unmatched.unmatched, ["Standup"]);
+    }
+}
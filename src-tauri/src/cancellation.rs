@@ -0,0 +1,83 @@
+//! Cancelling JIRA requests the frontend gave up on, e.g. a slow search it
+//! no longer needs. Commands that take a `request_id` register it while they
+//! run; `cancel_request(id)` makes the request in flight, and any later one
+//! of that command, fail right away with a `cancelled` error.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+use tokio_util::sync::CancellationToken;
+
+/// Registrations by request id, each with a number telling it apart from a
+/// later one under the same id.
+pub type CancelState = Mutex<HashMap<String, (u64, CancellationToken)>>;
+
+static NEXT_REGISTRATION: AtomicU64 = AtomicU64::new(0);
+
+/// A command's request id while it runs; unregisters it when dropped.
+pub struct Registration {
+    app_handle: AppHandle<Wry>,
+    id: Option<(String, u64)>,
+    token: Option<CancellationToken>,
+}
+
+impl Registration {
+    /// For `JiraClient::with_cancellation`; `None` without a request id.
+    pub fn token(&self) -> Option<CancellationToken> {
+        self.token.clone()
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let Some((id, number)) = &self.id else {
+            return;
+        };
+        let state = self.app_handle.state::<CancelState>();
+        let mut requests = state.lock().unwrap_or_else(|e| e.into_inner());
+        if requests.get(id).is_some_and(|(registered, _)| registered == number) {
+            requests.remove(id);
+        }
+    }
+}
+
+/// Registers `request_id` for `cancel_request`. A request still running
+/// under the same id can no longer be cancelled.
+pub fn register(app_handle: &AppHandle<Wry>, request_id: Option<String>) -> Registration {
+    let Some(id) = request_id.filter(|id| !id.is_empty()) else {
+        return Registration {
+            app_handle: app_handle.clone(),
+            id: None,
+            token: None,
+        };
+    };
+
+    let number = NEXT_REGISTRATION.fetch_add(1, Ordering::Relaxed);
+    let token = CancellationToken::new();
+    let state = app_handle.state::<CancelState>();
+    state
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(id.clone(), (number, token.clone()));
+
+    Registration {
+        app_handle: app_handle.clone(),
+        id: Some((id, number)),
+        token: Some(token),
+    }
+}
+
+/// Cancels the request registered as `request_id`. Returns whether one was
+/// running; it may have just finished.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn cancel_request(request_id: String, state: State<'_, CancelState>) -> Result<bool, String> {
+    let requests = state.lock().map_err(|e| e.to_string())?;
+    match requests.get(&request_id) {
+        Some((_, token)) => {
+            token.cancel();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
@@ -0,0 +1,163 @@
+//! What each connected JIRA site supports, probed once on connect: JIRA
+//! Software boards, Service Management, rich text as ADF, worklog
+//! properties and whether the saved Tempo token works. Features missing on
+//! a site can then be hidden instead of failing when used.
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::tempo::TempoClient;
+use crate::url::ApiVersion;
+use crate::{credentials, offline, settings, state, url, JiraState};
+
+/// Worklog properties came with JIRA Server 7.
+const WORKLOG_PROPERTIES_SINCE_MAJOR: u32 = 7;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    /// `Cloud` or `Server` (Data Center too), from `serverInfo`.
+    pub deployment_type: Option<String>,
+    pub version: Option<String>,
+    /// Boards and sprints (`/rest/agile/1.0`).
+    pub agile: bool,
+    /// Service desks, request types and SLAs (`/rest/servicedeskapi`).
+    pub service_desk: bool,
+    /// Rich text is ADF (Cloud); Server and Data Center take wiki markup.
+    pub adf: bool,
+    /// Taken from the version: Cloud, or Server 7 and later.
+    pub worklog_properties: bool,
+    /// A Tempo token is saved and Tempo accepts it.
+    pub tempo: bool,
+    pub checked_at: DateTime<Local>,
+}
+
+/// Capabilities per connection id.
+pub type CapabilityState = Mutex<HashMap<String, Capabilities>>;
+
+/// `8.20.1` gives 8.
+fn major_version(version: &str) -> Option<u32> {
+    version.split('.').next()?.trim().parse().ok()
+}
+
+fn worklog_properties(cloud: bool, version: Option<&str>) -> bool {
+    cloud || version.and_then(major_version).is_some_and(|major| major >= WORKLOG_PROPERTIES_SINCE_MAJOR)
+}
+
+impl JiraClient {
+    /// Whether `path` under `root` answers; a 404 or a refusal means the
+    /// feature isn't there (or not for me).
+    async fn answers(&self, root: &str, path: &str) -> bool {
+        let Ok(authorization) = self.authorization().await else {
+            return false;
+        };
+        let request = self
+            .http_client()
+            .get(url::join(&self.base_url, root, path))
+            .header("Accept", "application/json")
+            .header("Authorization", authorization);
+        self.send(request).await.is_ok()
+    }
+}
+
+async fn tempo_works(app_handle: &AppHandle<Wry>) -> bool {
+    let Ok(Some(token)) = credentials::load_tempo_token() else {
+        return false;
+    };
+    match TempoClient::new(&settings::current(app_handle).tempo.api_url, token, offline::flag(app_handle)) {
+        Ok(tempo) => tempo.get_work_attributes().await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+pub async fn probe(app_handle: &AppHandle<Wry>, client: &JiraClient) -> Capabilities {
+    let (server_info, agile, service_desk, tempo) = tokio::join!(
+        client.server_info(),
+        client.answers(url::AGILE_API, "board?maxResults=1"),
+        client.answers(url::SERVICE_DESK_API, "servicedesk?limit=1"),
+        tempo_works(app_handle),
+    );
+    let server_info = server_info
+        .inspect_err(|e| tracing::warn!("Failed to read the server info: {}", e))
+        .ok();
+    let cloud = ApiVersion::detect(server_info.as_ref(), &client.base_url) == ApiVersion::V3;
+    let deployment_type = server_info.as_ref().and_then(|info| info.deployment_type.clone());
+    let version = server_info.and_then(|info| info.version);
+
+    Capabilities {
+        worklog_properties: worklog_properties(cloud, version.as_deref()),
+        adf: cloud,
+        deployment_type,
+        version,
+        agile,
+        service_desk,
+        tempo,
+        checked_at: Local::now(),
+    }
+}
+
+/// Probes connection `id` in the background and remembers the result.
+pub fn detect(app_handle: &AppHandle<Wry>, id: &str, client: JiraClient) {
+    let app_handle = app_handle.clone();
+    let id = id.to_string();
+    forget(&app_handle, &id);
+    tauri::async_runtime::spawn(async move {
+        let capabilities = probe(&app_handle, &client).await;
+        tracing::info!("Capabilities of {}: {:?}", id, capabilities);
+        app_handle.state::<CapabilityState>().lock().unwrap_or_else(|e| e.into_inner()).insert(id, capabilities);
+    });
+}
+
+pub fn forget(app_handle: &AppHandle<Wry>, id: &str) {
+    app_handle.state::<CapabilityState>().lock().unwrap_or_else(|e| e.into_inner()).remove(id);
+}
+
+/// The active connection's capabilities, once probed.
+pub async fn active(app_handle: &AppHandle<Wry>) -> Option<Capabilities> {
+    let id = state::connections(&app_handle.state::<JiraState>()).await.active_id()?.to_string();
+    app_handle.state::<CapabilityState>().lock().unwrap_or_else(|e| e.into_inner()).get(&id).cloned()
+}
+
+/// What connection `connection` (the active one by default) supports.
+/// Probes right away when it hasn't been yet or with `refresh`.
+#[tauri::command]
+pub async fn get_capabilities(
+    connection: Option<String>,
+    refresh: Option<bool>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+    capabilities: State<'_, CapabilityState>,
+) -> Result<Capabilities, JiraError> {
+    let (id, client) = {
+        let registry = state::connections(&state).await;
+        let client = registry.get(connection.as_deref())?.clone();
+        let id = connection.or_else(|| registry.active_id().map(str::to_string)).ok_or(JiraError::NotConnected)?;
+        (id, client)
+    };
+    if !refresh.unwrap_or(false) {
+        if let Some(known) = capabilities.lock().map_err(|e| e.to_string())?.get(&id) {
+            return Ok(known.clone());
+        }
+    }
+
+    let probed = probe(&app_handle, &client).await;
+    capabilities.lock().map_err(|e| e.to_string())?.insert(id, probed.clone());
+    Ok(probed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worklog_properties_need_jira_7() {
+        assert!(worklog_properties(true, None));
+        assert!(worklog_properties(false, Some("8.20.1")));
+        assert!(!worklog_properties(false, Some("6.4.14")));
+        assert!(!worklog_properties(false, None));
+    }
+}
@@ -0,0 +1,94 @@
+//! Work that had no ticket: from the reminder's "New task" button, files an
+//! issue in the `catch_all` project with the configured type and summary,
+//! then logs the time on it right away. The time is what is left of today's
+//! target unless given.
+
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use serde::Serialize;
+use tauri::{AppHandle, State, Wry};
+
+use crate::adf::CommentInput;
+use crate::error::JiraError;
+use crate::issue_create::{CreatedIssue, MAX_SUMMARY_CHARS};
+use crate::jira_api::JiraClient;
+use crate::offline_queue::{self, WorklogSubmission};
+use crate::{client_for, clock, settings, summary, worklog_rules, JiraState};
+
+#[derive(Debug, Serialize)]
+pub struct CatchAllTask {
+    pub issue: CreatedIssue,
+    pub worklog: WorklogSubmission,
+}
+
+/// The summary from `template`, cut to what JIRA takes.
+fn summary_for(template: &str, description: &str, today: NaiveDate) -> String {
+    let first_line = description.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+    let summary = template
+        .replace("{date}", &today.to_string())
+        .replace("{description}", first_line);
+    summary.trim().chars().take(MAX_SUMMARY_CHARS).collect()
+}
+
+/// What is left of today's target, for a reminder that fired with nothing
+/// to log it on.
+async fn left_today(app_handle: &AppHandle<Wry>, client: JiraClient, now: DateTime<Local>) -> Result<u32, JiraError> {
+    let today = now.date_naive();
+    let day = summary::fetch(app_handle, client, today, today).await?;
+    Ok(day.target_seconds.saturating_sub(day.total_seconds))
+}
+
+/// Creates the catch-all issue for `description` and logs `timeSpent` on
+/// it, ending now unless `started` is given. Nothing is filed when the
+/// worklog would be refused; when JIRA takes the issue but not the worklog,
+/// the issue stays and the error is returned.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn create_catch_all_task(
+    description: CommentInput,
+    time_spent: Option<String>,
+    started: Option<String>,
+    confirmed: Option<bool>,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<CatchAllTask, JiraError> {
+    let settings = settings::effective(&app_handle);
+    let Some((project_key, issue_type)) = settings.catch_all.target() else {
+        return Err(JiraError::Validation("Set a catch-all project and issue type first".to_string()));
+    };
+    let document = offline_queue::checked_comment(&app_handle, &description)?;
+    let text = document.text();
+    if text.trim().is_empty() {
+        return Err(JiraError::Validation("Describe what you worked on".to_string()));
+    }
+
+    let client = client_for(&state, connection.as_deref()).await?;
+    let now = clock::now(&app_handle);
+    let seconds = match time_spent.as_deref().map(str::trim).filter(|time| !time.is_empty()) {
+        Some(time) => JiraClient::parse_time_to_seconds(time, &settings.time_units)?,
+        None => left_today(&app_handle, client.clone(), now).await?,
+    };
+    if seconds == 0 {
+        return Err(JiraError::Validation("Today's target is already logged; say how long it took".to_string()));
+    }
+    let started = started.unwrap_or_else(|| JiraClient::format_jira_datetime(&(now - Duration::seconds(seconds as i64))));
+    let (started, seconds) = worklog_rules::normalize(&app_handle, &started, seconds, confirmed.unwrap_or(false))?;
+
+    let summary = summary_for(&settings.catch_all.summary, &text, now.date_naive());
+    let issue = client.create_issue(project_key, issue_type, &summary, Some(&document)).await?;
+    let worklog =
+        offline_queue::submit(&app_handle, &client, connection.as_deref(), &issue.key, &description, &started, seconds).await?;
+    Ok(CatchAllTask { issue, worklog })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_in_the_summary() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 7).unwrap();
+        assert_eq!(summary_for("{description}", "\n  Server restart \nfor ops", today), "Server restart");
+        assert_eq!(summary_for("Support {date}: {description}", "Call", today), "Support 2024-06-07: Call");
+        assert_eq!(summary_for("{description}", &"x".repeat(300), today).len(), MAX_SUMMARY_CHARS);
+    }
+}
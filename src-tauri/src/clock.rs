@@ -0,0 +1,153 @@
+use chrono::{DateTime, Local};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::scheduler;
+
+/// Where the scheduler, timers and breaks get the current time from, so
+/// their timing can be tested without waiting and debug builds can move
+/// through the day on demand.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Local>;
+}
+
+pub type ClockState = Arc<dyn Clock>;
+/// The same clock as `ClockState`, for the dev build commands that move it.
+pub type DebugClockState = Arc<ShiftedClock>;
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+/// Another clock moved by an adjustable offset: after a jump, time keeps
+/// running from the new point.
+pub struct ShiftedClock<C = SystemClock> {
+    base: C,
+    offset: Mutex<chrono::Duration>,
+}
+
+impl<C: Clock> ShiftedClock<C> {
+    pub fn new(base: C) -> Self {
+        Self {
+            base,
+            offset: Mutex::new(chrono::Duration::zero()),
+        }
+    }
+
+    pub fn set_now(&self, now: DateTime<Local>) {
+        *self.offset.lock().unwrap_or_else(|e| e.into_inner()) = now - self.base.now();
+    }
+
+    pub fn advance(&self, by: chrono::Duration) {
+        *self.offset.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+
+    pub fn offset(&self) -> chrono::Duration {
+        *self.offset.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn reset(&self) {
+        *self.offset.lock().unwrap_or_else(|e| e.into_inner()) = chrono::Duration::zero();
+    }
+}
+
+impl<C: Clock> Clock for ShiftedClock<C> {
+    fn now(&self) -> DateTime<Local> {
+        self.base.now() + *self.offset.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The app's clock.
+pub fn now(app_handle: &AppHandle<Wry>) -> DateTime<Local> {
+    app_handle.state::<ClockState>().now()
+}
+
+fn dev_only() -> Result<(), String> {
+    if cfg!(debug_assertions) {
+        Ok(())
+    } else {
+        Err("The clock can only be moved in dev builds".to_string())
+    }
+}
+
+/// Dev builds only: fast-forwards the app's clock and lets the scheduler
+/// catch up. Returns the new time.
+#[tauri::command]
+pub async fn debug_advance_clock(
+    minutes: i64,
+    app_handle: AppHandle<Wry>,
+    clock: State<'_, DebugClockState>,
+) -> Result<DateTime<Local>, String> {
+    dev_only()?;
+    if minutes <= 0 {
+        return Err("The clock only moves forward".to_string());
+    }
+
+    clock.advance(chrono::Duration::minutes(minutes));
+    scheduler::wake(&app_handle);
+    Ok(clock.now())
+}
+
+/// Dev builds only: jumps to `datetime`, e.g. just before the 17:00
+/// reminder, midnight or the end of the week. Time keeps running from there.
+#[tauri::command]
+pub async fn debug_set_time(
+    datetime: DateTime<Local>,
+    app_handle: AppHandle<Wry>,
+    clock: State<'_, DebugClockState>,
+) -> Result<DateTime<Local>, String> {
+    dev_only()?;
+    clock.set_now(datetime);
+    scheduler::wake(&app_handle);
+    Ok(clock.now())
+}
+
+/// Dev builds only: back to the system time.
+#[tauri::command]
+pub async fn debug_reset_clock(
+    app_handle: AppHandle<Wry>,
+    clock: State<'_, DebugClockState>,
+) -> Result<DateTime<Local>, String> {
+    dev_only()?;
+    clock.reset();
+    scheduler::wake(&app_handle);
+    Ok(clock.now())
+}
+
+/// A clock that stands still unless moved, for tests.
+#[cfg(test)]
+pub struct FixedClock(pub DateTime<Local>);
+
+#[cfg(test)]
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Local> {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn shifted_clock_advances_and_resets() {
+        let start = Local.with_ymd_and_hms(2024, 3, 8, 9, 0, 0).unwrap();
+        let clock = ShiftedClock::new(FixedClock(start));
+
+        let monday = Local.with_ymd_and_hms(2024, 3, 11, 0, 0, 0).unwrap();
+        clock.set_now(monday);
+        assert_eq!(clock.now(), monday);
+
+        clock.reset();
+        clock.advance(chrono::Duration::hours(8));
+        assert_eq!(clock.now(), Local.with_ymd_and_hms(2024, 3, 8, 17, 0, 0).unwrap());
+
+        clock.reset();
+        assert_eq!(clock.now(), start);
+    }
+}
@@ -0,0 +1,37 @@
+//! The `closed_issue_guard` setting: logging time on an issue in a done
+//! status asks first (`warn`) or is refused (`block`), since admins have to
+//! reopen the ticket to account for it. Checked before a worklog is
+//! submitted; offline the check is skipped and the worklog queued as usual.
+
+use tauri::{AppHandle, Wry};
+
+use crate::error::JiraError;
+use crate::jira_api::{field_sets, JiraClient};
+use crate::settings::{self, ClosedIssueGuard};
+
+const DONE_CATEGORY: &str = "done";
+
+/// Whether time may be logged on `issue_key`.
+pub async fn check(app_handle: &AppHandle<Wry>, client: &JiraClient, issue_key: &str, confirmed: bool) -> Result<(), JiraError> {
+    let guard = settings::effective(app_handle).closed_issue_guard;
+    if guard == ClosedIssueGuard::Off || (guard == ClosedIssueGuard::Warn && confirmed) {
+        return Ok(());
+    }
+
+    let issue = match client.get_issue(issue_key, field_sets::STATUS, &[]).await {
+        Ok(issue) => issue,
+        Err(e) if e.is_connectivity() => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let status = issue.fields.status;
+    let done = status.category.as_ref().is_some_and(|category| category.key == DONE_CATEGORY);
+    if !done {
+        return Ok(());
+    }
+
+    let message = format!("{} is {}", issue_key, status.name);
+    match guard {
+        ClosedIssueGuard::Block => Err(JiraError::Validation(format!("{}; reopen it before logging time on it", message))),
+        _ => Err(JiraError::ConfirmationRequired(format!("{}. Log time on it anyway?", message))),
+    }
+}
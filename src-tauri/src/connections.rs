@@ -0,0 +1,359 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::adf::CommentFormat;
+use crate::error::JiraError;
+use crate::http::TlsOptions;
+use crate::jira_api::{JiraAuth, JiraClient};
+use crate::{capabilities, credentials, health, issue_cache, offline, store, sync, state, url, JiraState};
+
+const CONNECTIONS_FILE: &str = "connections.json";
+
+/// Id of the connection made through the login form (and OAuth); the only
+/// one before named connections existed.
+pub const DEFAULT_CONNECTION: &str = "default";
+const DEFAULT_NAME: &str = "Default";
+
+pub struct Connection {
+    pub id: String,
+    pub name: String,
+    pub client: JiraClient,
+}
+
+/// The open JIRA connections. Commands use the active one unless they are
+/// given a connection id; background features (sync, reminders, the issue
+/// cache) always follow the active one.
+#[derive(Default)]
+pub struct ConnectionRegistry {
+    connections: Vec<Connection>,
+    active: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionInfo {
+    pub id: String,
+    pub name: String,
+    pub base_url: String,
+    pub email: String,
+    pub oauth: bool,
+    pub comment_format: CommentFormat,
+    pub active: bool,
+}
+
+impl ConnectionRegistry {
+    /// The connection `id`, or the active one for `None`.
+    pub fn get(&self, id: Option<&str>) -> Result<&JiraClient, JiraError> {
+        let Some(id) = id.or(self.active.as_deref()) else {
+            return Err(JiraError::NotConnected);
+        };
+        self.connections
+            .iter()
+            .find(|connection| connection.id == id)
+            .map(|connection| &connection.client)
+            .ok_or_else(|| JiraError::NotFound(format!("No connection '{}'", id)))
+    }
+
+    pub fn active_id(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    /// Adds or replaces connection `id`. The first connection becomes the
+    /// active one.
+    pub fn insert(&mut self, id: &str, name: &str, client: JiraClient) {
+        let connection = Connection {
+            id: id.to_string(),
+            name: name.to_string(),
+            client,
+        };
+        match self.connections.iter_mut().find(|existing| existing.id == id) {
+            Some(existing) => *existing = connection,
+            None => self.connections.push(connection),
+        }
+        if self.active.is_none() {
+            self.active = Some(id.to_string());
+        }
+    }
+
+    /// Removes connection `id`; when it was the active one, the next
+    /// remaining connection takes over.
+    pub fn remove(&mut self, id: &str) -> Option<Connection> {
+        let index = self.connections.iter().position(|connection| connection.id == id)?;
+        let removed = self.connections.remove(index);
+        if self.active.as_deref() == Some(id) {
+            self.active = self.connections.first().map(|connection| connection.id.clone());
+        }
+        Some(removed)
+    }
+
+    pub fn set_active(&mut self, id: &str) -> Result<(), JiraError> {
+        if !self.connections.iter().any(|connection| connection.id == id) {
+            return Err(JiraError::NotFound(format!("No connection '{}'", id)));
+        }
+        self.active = Some(id.to_string());
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.connections.clear();
+        self.active = None;
+    }
+
+    pub fn clients(&self) -> impl Iterator<Item = &JiraClient> {
+        self.connections.iter().map(|connection| &connection.client)
+    }
+
+    pub fn clients_mut(&mut self) -> impl Iterator<Item = &mut JiraClient> {
+        self.connections.iter_mut().map(|connection| &mut connection.client)
+    }
+
+    /// The connection `client` was cloned from.
+    pub fn info_of(&self, client: &JiraClient) -> Option<ConnectionInfo> {
+        self.list()
+            .into_iter()
+            .zip(self.clients())
+            .find(|(_, registered)| registered.same_credentials(client))
+            .map(|(info, _)| info)
+    }
+
+    pub fn list(&self) -> Vec<ConnectionInfo> {
+        self.connections
+            .iter()
+            .map(|connection| ConnectionInfo {
+                id: connection.id.clone(),
+                name: connection.name.clone(),
+                base_url: connection.client.base_url.clone(),
+                email: connection.client.email.clone(),
+                oauth: matches!(connection.client.auth, JiraAuth::OAuth(_)),
+                comment_format: connection.client.comment_format,
+                active: self.active.as_deref() == Some(connection.id.as_str()),
+            })
+            .collect()
+    }
+}
+
+/// Named connections remembered across restarts. Their credentials are in
+/// the keychain; this file only lists them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SavedConnections {
+    connections: Vec<SavedConnection>,
+    active: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedConnection {
+    pub id: String,
+    pub name: String,
+}
+
+fn load_saved(app_handle: &AppHandle<Wry>) -> SavedConnections {
+    match store::config_file(app_handle, CONNECTIONS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(_) => SavedConnections::default(),
+    }
+}
+
+fn update_saved(app_handle: &AppHandle<Wry>, update: impl FnOnce(&mut SavedConnections)) -> Result<(), String> {
+    let mut saved = load_saved(app_handle);
+    update(&mut saved);
+    let path = store::config_file(app_handle, CONNECTIONS_FILE)?;
+    store::save_json(&path, &saved)
+}
+
+/// Every remembered connection, the default one first.
+pub fn saved(app_handle: &AppHandle<Wry>) -> Vec<SavedConnection> {
+    let default = SavedConnection {
+        id: DEFAULT_CONNECTION.to_string(),
+        name: DEFAULT_NAME.to_string(),
+    };
+    std::iter::once(default).chain(load_saved(app_handle).connections).collect()
+}
+
+/// Activates the connection that was active when the app last ran, if it
+/// reconnected.
+pub async fn restore_active(app_handle: &AppHandle<Wry>) {
+    let Some(active) = load_saved(app_handle).active else {
+        return;
+    };
+    let state = app_handle.state::<JiraState>();
+    let mut registry = state::connections_mut(&state).await;
+    if registry.set_active(&active).is_ok() {
+        drop(registry);
+        sync::reset(app_handle);
+        health::recheck(app_handle);
+        issue_cache::clear(app_handle);
+    }
+}
+
+/// Registers `client` as the default connection and makes it active, as the
+/// login form does.
+pub async fn set_default(app_handle: &AppHandle<Wry>, state: &JiraState, client: JiraClient) -> Result<(), JiraError> {
+    let mut registry = state::connections_mut(state).await;
+    registry.insert(DEFAULT_CONNECTION, DEFAULT_NAME, client.clone());
+    registry.set_active(DEFAULT_CONNECTION)?;
+    drop(registry);
+
+    capabilities::detect(app_handle, DEFAULT_CONNECTION, client);
+
+    sync::reset(app_handle);
+
+    health::recheck(app_handle);
+    issue_cache::clear(app_handle);
+    Ok(())
+}
+
+fn valid_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 40 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// A connection to add next to the default one.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewConnection {
+    /// Letters, digits, `-` and `_`; made from the name when left out.
+    pub id: Option<String>,
+    pub name: String,
+    pub base_url: String,
+    pub email: String,
+    pub access_token: String,
+    #[serde(default)]
+    pub tls: TlsOptions,
+    /// `plain_text` for Server and Data Center; detected when left out.
+    #[serde(default)]
+    pub comment_format: Option<CommentFormat>,
+    #[serde(default)]
+    pub remember: bool,
+}
+
+/// Connects to another JIRA instance with an API token and registers it
+/// under its id. The active connection stays as it is.
+#[tauri::command]
+pub async fn add_connection(
+    connection: NewConnection,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<ConnectionInfo, JiraError> {
+    let name = connection.name.trim().to_string();
+    if name.is_empty() {
+        return Err(JiraError::Validation("The connection needs a name".to_string()));
+    }
+    let id = connection.id.unwrap_or_else(|| {
+        name.to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect()
+    });
+    if !valid_id(&id) || id == DEFAULT_CONNECTION {
+        return Err(JiraError::Validation(format!("Invalid connection id: {}", id)));
+    }
+
+    let base_url = url::normalize_base_url(&connection.base_url).map_err(JiraError::Validation)?;
+    let tls = connection.tls.normalized();
+    let client = JiraClient::new(
+        base_url.clone(),
+        connection.email.clone(),
+        connection.access_token.clone(),
+        offline::flag(&app_handle),
+    )?
+    .with_tls(tls.clone())?;
+    let comment_format = match connection.comment_format {
+        Some(format) => format,
+        None => client.detect_api_version().await.comment_format(),
+    };
+    let client = client.with_comment_format(comment_format);
+    client.get_myself().await?;
+
+    if connection.remember {
+        let stored = credentials::StoredCredentials {
+            base_url,
+            email: connection.email,
+            access_token: connection.access_token,
+            tls,
+            comment_format,
+            oauth: None,
+        };
+        credentials::save_for(&id, &stored)?;
+        update_saved(&app_handle, |saved| {
+            saved.connections.retain(|existing| existing.id != id);
+            saved.connections.push(SavedConnection {
+                id: id.clone(),
+                name: name.clone(),
+            });
+        })?;
+    }
+
+    let mut registry = state::connections_mut(&state).await;
+    let was_empty = registry.is_empty();
+    registry.insert(&id, &name, client.clone());
+    let info = registry.list().into_iter().find(|info| info.id == id);
+    drop(registry);
+
+    capabilities::detect(&app_handle, &id, client);
+
+    if was_empty {
+        sync::reset(&app_handle);
+        health::recheck(&app_handle);
+        issue_cache::clear(&app_handle);
+    }
+    info.ok_or_else(|| JiraError::Other("Connection vanished while adding it".to_string()))
+}
+
+/// Closes connection `id` and forgets its saved credentials.
+#[tauri::command]
+pub async fn remove_connection(id: String, app_handle: AppHandle<Wry>, state: State<'_, JiraState>) -> Result<(), JiraError> {
+    let mut registry = state::connections_mut(&state).await;
+    let was_active = registry.active_id() == Some(id.as_str());
+    if registry.remove(&id).is_none() {
+        return Err(JiraError::NotFound(format!("No connection '{}'", id)));
+    }
+    drop(registry);
+
+    capabilities::forget(&app_handle, &id);
+    credentials::clear_for(&id)?;
+    update_saved(&app_handle, |saved| {
+        saved.connections.retain(|existing| existing.id != id);
+        if saved.active.as_deref() == Some(id.as_str()) {
+            saved.active = None;
+        }
+    })?;
+
+    if was_active {
+        sync::reset(&app_handle);
+        health::recheck(&app_handle);
+        issue_cache::clear(&app_handle);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_connections(state: State<'_, JiraState>) -> Result<Vec<ConnectionInfo>, String> {
+    Ok(state::connections(&state).await.list())
+}
+
+/// Makes `id` the connection commands and background features use by
+/// default; remembered for the next start.
+#[tauri::command]
+pub async fn set_active_connection(
+    id: String,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<(), JiraError> {
+    let mut registry = state::connections_mut(&state).await;
+    if registry.active_id() == Some(id.as_str()) {
+        return Ok(());
+    }
+    registry.set_active(&id)?;
+    drop(registry);
+
+    if let Err(e) = update_saved(&app_handle, |saved| saved.active = Some(id.clone())) {
+        tracing::warn!("Failed to remember the active connection: {}", e);
+    }
+    sync::reset(&app_handle);
+    health::recheck(&app_handle);
+    issue_cache::clear(&app_handle);
+    Ok(())
+}
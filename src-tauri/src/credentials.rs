@@ -0,0 +1,276 @@
+use keyring::Entry;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+
+use crate::adf::CommentFormat;
+use crate::connections::{self, DEFAULT_CONNECTION};
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::http::{self, TlsOptions};
+use crate::oauth::{self, OAuthApp};
+use crate::{capabilities, offline, state, url, JiraState};
+
+const KEYRING_SERVICE: &str = "com.ariefg.mini-jira-app";
+const KEYRING_USER: &str = "jira-credentials";
+const TEMPO_KEYRING_USER: &str = "tempo-token";
+const WEBHOOK_KEYRING_USER: &str = "webhook-secret";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredCredentials {
+    pub base_url: String,
+    pub email: String,
+    /// API token; empty for OAuth connections.
+    pub access_token: String,
+    #[serde(default)]
+    pub tls: TlsOptions,
+    #[serde(default)]
+    pub comment_format: CommentFormat,
+    #[serde(default)]
+    pub oauth: Option<StoredOAuth>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredOAuth {
+    pub app: OAuthApp,
+    pub cloud_id: String,
+    pub refresh_token: String,
+}
+
+impl StoredCredentials {
+    pub fn oauth(base_url: String, email: String, oauth: StoredOAuth) -> Self {
+        Self {
+            base_url,
+            email,
+            access_token: String::new(),
+            tls: TlsOptions::default(),
+            comment_format: CommentFormat::Adf,
+            oauth: Some(oauth),
+        }
+    }
+}
+
+/// What the frontend gets back: enough to pre-fill the login form, never the token.
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedCredentials {
+    pub base_url: String,
+    pub email: String,
+    pub tls: TlsOptions,
+    pub comment_format: CommentFormat,
+}
+
+fn entry_for(user: &str) -> Result<Entry, String> {
+    Entry::new(KEYRING_SERVICE, user).map_err(|e| format!("Keychain unavailable: {}", e))
+}
+
+/// The default connection keeps the entry it had before there were named
+/// connections.
+fn connection_entry(connection_id: &str) -> Result<Entry, String> {
+    if connection_id == DEFAULT_CONNECTION {
+        entry_for(KEYRING_USER)
+    } else {
+        entry_for(&format!("{}:{}", KEYRING_USER, connection_id))
+    }
+}
+
+pub fn save(credentials: &StoredCredentials) -> Result<(), String> {
+    save_for(DEFAULT_CONNECTION, credentials)
+}
+
+pub fn load() -> Result<Option<StoredCredentials>, String> {
+    load_for(DEFAULT_CONNECTION)
+}
+
+pub fn clear() -> Result<(), String> {
+    clear_for(DEFAULT_CONNECTION)
+}
+
+pub fn save_for(connection_id: &str, credentials: &StoredCredentials) -> Result<(), String> {
+    let secret = serde_json::to_string(credentials).map_err(|e| e.to_string())?;
+    connection_entry(connection_id)?
+        .set_password(&secret)
+        .map_err(|e| format!("Failed to save credentials: {}", e))
+}
+
+pub fn load_for(connection_id: &str) -> Result<Option<StoredCredentials>, String> {
+    match connection_entry(connection_id)?.get_password() {
+        Ok(secret) => serde_json::from_str(&secret)
+            .map(Some)
+            .map_err(|e| format!("Stored credentials are corrupt: {}", e)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to load credentials: {}", e)),
+    }
+}
+
+pub fn clear_for(connection_id: &str) -> Result<(), String> {
+    match connection_entry(connection_id)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to clear credentials: {}", e)),
+    }
+}
+
+pub fn save_tempo_token(token: &str) -> Result<(), String> {
+    entry_for(TEMPO_KEYRING_USER)?
+        .set_password(token)
+        .map_err(|e| format!("Failed to save the Tempo token: {}", e))
+}
+
+pub fn load_tempo_token() -> Result<Option<String>, String> {
+    match entry_for(TEMPO_KEYRING_USER)?.get_password() {
+        Ok(token) => Ok(Some(token)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to load the Tempo token: {}", e)),
+    }
+}
+
+pub fn clear_tempo_token() -> Result<(), String> {
+    match entry_for(TEMPO_KEYRING_USER)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to clear the Tempo token: {}", e)),
+    }
+}
+
+pub fn save_webhook_secret(secret: &str) -> Result<(), String> {
+    entry_for(WEBHOOK_KEYRING_USER)?
+        .set_password(secret)
+        .map_err(|e| format!("Failed to save the webhook secret: {}", e))
+}
+
+pub fn load_webhook_secret() -> Result<Option<String>, String> {
+    match entry_for(WEBHOOK_KEYRING_USER)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to load the webhook secret: {}", e)),
+    }
+}
+
+/// Stores the rotated refresh token of a saved OAuth connection.
+pub fn update_refresh_token(connection_id: &str, refresh_token: &str) -> Result<(), String> {
+    match load_for(connection_id)? {
+        Some(mut credentials) => match credentials.oauth.as_mut() {
+            Some(oauth) => {
+                oauth.refresh_token = refresh_token.to_string();
+                save_for(connection_id, &credentials)
+            }
+            None => Ok(()),
+        },
+        None => Ok(()),
+    }
+}
+
+/// Connects with the credentials of `connection_id` stored in the keychain,
+/// if any. Returns whether a connection is established afterwards.
+pub async fn connect_saved(app_handle: &AppHandle<Wry>, connection_id: &str, name: &str) -> Result<bool, JiraError> {
+    let Some(credentials) = load_for(connection_id)? else {
+        return Ok(false);
+    };
+
+    let client = match credentials.oauth {
+        Some(saved) => {
+            // Access tokens are short-lived and never stored; start from a
+            // fresh one.
+            let tokens = oauth::refresh_tokens(&http::client()?, &saved.app, &saved.refresh_token).await?;
+            update_refresh_token(connection_id, &tokens.refresh_token)?;
+
+            let session = oauth::new_session(saved.app, tokens, Some(connection_id));
+            JiraClient::with_oauth(oauth::api_base_url(&saved.cloud_id), credentials.email, session, offline::flag(app_handle))?
+        }
+        // Saved before base URLs were normalized, possibly with a slash or
+        // a pasted page path at the end.
+        None => JiraClient::new(
+            url::normalize_base_url(&credentials.base_url).unwrap_or(credentials.base_url),
+            credentials.email,
+            credentials.access_token,
+            offline::flag(app_handle),
+        )?
+            .with_tls(credentials.tls)?
+            .with_comment_format(credentials.comment_format),
+    };
+    let connected = client.test_connection().await?;
+
+    if connected {
+        let state = app_handle.state::<JiraState>();
+        let mut registry = state::connections_mut(&state).await;
+        registry.insert(connection_id, name, client.clone());
+        drop(registry);
+        capabilities::detect(app_handle, connection_id, client);
+    }
+    Ok(connected)
+}
+
+/// Connects every remembered connection. Failures of named connections are
+/// only logged; the default connection's is returned.
+async fn connect_all_saved(app_handle: &AppHandle<Wry>) -> Result<bool, JiraError> {
+    let mut connected = false;
+    let mut default_result = Ok(());
+
+    for saved in connections::saved(app_handle) {
+        match connect_saved(app_handle, &saved.id, &saved.name).await {
+            Ok(result) => connected |= result,
+            Err(e) if saved.id == DEFAULT_CONNECTION => default_result = Err(e),
+            Err(e) => tracing::warn!("Failed to reconnect {}: {}", saved.name, e),
+        }
+    }
+
+    connections::restore_active(app_handle).await;
+    default_result.map(|()| connected)
+}
+
+/// Startup hook: reconnect in the background so reminders and other
+/// background features work before the window is ever opened.
+pub async fn auto_connect(app_handle: AppHandle<Wry>) {
+    match connect_all_saved(&app_handle).await {
+        Ok(true) => {
+            if let Err(e) = app_handle.emit("jira-connected", ()) {
+                tracing::warn!("Failed to emit jira-connected event: {}", e);
+            }
+        }
+        Ok(false) => {}
+        Err(e) => tracing::warn!("Auto-reconnect failed: {}", e),
+    }
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn save_credentials(
+    base_url: String,
+    email: String,
+    access_token: String,
+    tls: Option<TlsOptions>,
+    comment_format: Option<CommentFormat>,
+) -> Result<(), String> {
+    save(&StoredCredentials {
+        base_url,
+        email,
+        access_token,
+        tls: tls.unwrap_or_default().normalized(),
+        comment_format: comment_format.unwrap_or_default(),
+        oauth: None,
+    })
+}
+
+#[tauri::command]
+pub async fn load_credentials() -> Result<Option<SavedCredentials>, String> {
+    Ok(load()?.map(|credentials| SavedCredentials {
+        base_url: credentials.base_url,
+        email: credentials.email,
+        tls: credentials.tls,
+        comment_format: credentials.comment_format,
+    }))
+}
+
+#[tauri::command]
+pub async fn clear_credentials() -> Result<(), String> {
+    clear()
+}
+
+/// Reconnects with the saved credentials unless a connection already exists.
+#[tauri::command]
+pub async fn reconnect_saved(app_handle: AppHandle<Wry>, state: State<'_, JiraState>) -> Result<bool, JiraError> {
+    let connected = {
+        !state::connections(&state).await.is_empty()
+    };
+
+    if connected {
+        return Ok(true);
+    }
+    connect_all_saved(&app_handle).await
+}
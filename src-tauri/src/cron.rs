@@ -0,0 +1,201 @@
+//! Cron expressions for reminder steps and recurring entries that the plain
+//! time-of-day and weekday pickers can't express, e.g. `30 16 * * FRI` or
+//! `0 9 1 * *` (the first of the month). As in cron, when both the day of
+//! month and the weekday are given, a day matching either one fires.
+//!
+//! Five fields: minute, hour, day of month, month and weekday (0 or 7 is
+//! Sunday), each `*`, a value, a range `a-b` or a list of those, optionally
+//! with a step (`*/15`). Months and weekdays also take names (`JAN`, `MON`).
+//! `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly` work too. Times
+//! are local; a time skipped by a DST change doesn't fire that day.
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, NaiveTime};
+use std::str::FromStr;
+
+/// Far enough ahead for every valid expression, including `0 0 29 2 *`.
+const SEARCH_DAYS: u64 = 8 * 366;
+
+const MONTHS: [&str; 12] = ["JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC"];
+const WEEKDAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    /// Bit `n` set when value `n` matches.
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    /// Whether the day of month and weekday fields are `*`; when both are
+    /// restricted, a day matching either one fires.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+/// Parses one field into a bit set of the values in `min..=max`.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |text: &str| -> Result<u32, String> {
+        let upper = text.to_ascii_uppercase();
+        let value = match names.iter().position(|name| *name == upper) {
+            // Month names start at 1, weekday names at 0.
+            Some(index) => index as u32 + min,
+            None => text.parse().map_err(|_| format!("'{}' is not a number", text))?,
+        };
+        if value < min || value > max {
+            return Err(format!("{} is outside {}-{}", value, min, max));
+        }
+        Ok(value)
+    };
+
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step.parse::<u32>().ok().filter(|step| *step > 0);
+                (range, step.ok_or_else(|| format!("Invalid step in '{}'", part))?)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                // `5/15` runs from 5 to the end.
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let single = value(range)?;
+                    (single, single)
+                }
+            },
+        };
+        if start > end {
+            return Err(format!("Empty range '{}'", range));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, String> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            expression => expression,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute_field, hour_field, day_field, month_field, weekday_field] = fields[..] else {
+            return Err(format!("'{}' needs five fields: minute hour day month weekday", expression));
+        };
+        let invalid = |name: &'static str| move |e: String| format!("Invalid {} in '{}': {}", name, expression, e);
+
+        let weekdays = parse_field(weekday_field, 0, 7, &WEEKDAYS).map_err(invalid("weekday"))?;
+        Ok(Self {
+            minutes: parse_field(minute_field, 0, 59, &[]).map_err(invalid("minute"))?,
+            hours: parse_field(hour_field, 0, 23, &[]).map_err(invalid("hour"))? as u32,
+            days: parse_field(day_field, 1, 31, &[]).map_err(invalid("day"))? as u32,
+            months: parse_field(month_field, 1, 12, &MONTHS).map_err(invalid("month"))? as u16,
+            // 7 is Sunday as well.
+            weekdays: ((weekdays | weekdays >> 7) & 0x7f) as u8,
+            any_day: day_field.starts_with('*'),
+            any_weekday: weekday_field.starts_with('*'),
+        })
+    }
+}
+
+impl CronSchedule {
+    pub fn matches_date(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// The times it fires at on a matching day, earliest first.
+    pub fn times(&self) -> impl Iterator<Item = NaiveTime> + '_ {
+        (0..24)
+            .filter(|hour| self.hours & (1 << hour) != 0)
+            .flat_map(move |hour| {
+                (0..60)
+                    .filter(|minute| self.minutes & (1 << minute) != 0)
+                    .filter_map(move |minute| NaiveTime::from_hms_opt(hour, minute, 0))
+            })
+    }
+
+    /// The first time it fires strictly after `after`.
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        (0..SEARCH_DAYS)
+            .filter_map(|offset| after.date_naive().checked_add_days(Days::new(offset)))
+            .filter(|date| self.matches_date(*date))
+            .find_map(|date| {
+                self.times()
+                    .filter_map(|time| date.and_time(time).and_local_timezone(Local).earliest())
+                    .find(|fire_at| *fire_at > after)
+            })
+    }
+}
+
+/// Checks an expression from the settings, for `validate`.
+pub fn validate(expression: &str) -> Result<(), String> {
+    let schedule: CronSchedule = expression.parse()?;
+    // `0 0 31 2 *` parses but never fires.
+    let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap_or_default();
+    if !(0..SEARCH_DAYS).filter_map(|offset| start.checked_add_days(Days::new(offset))).any(|date| schedule.matches_date(date)) {
+        return Err(format!("'{}' never fires", expression.trim()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        // June 2024: the 7th is a Friday.
+        Local.with_ymd_and_hms(2024, 6, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn finds_the_next_fire_time() {
+        let friday_afternoon: CronSchedule = "30 16 * * FRI".parse().unwrap();
+        assert_eq!(friday_afternoon.next_after(at(3, 9, 0)), Some(at(7, 16, 30)));
+        assert_eq!(friday_afternoon.next_after(at(7, 16, 30)), Some(at(14, 16, 30)));
+
+        let quarter_hours: CronSchedule = "*/15 9-10 * * 1-5".parse().unwrap();
+        assert_eq!(quarter_hours.next_after(at(7, 10, 50)), Some(at(10, 9, 0)));
+        assert_eq!(quarter_hours.times().count(), 8);
+
+        let sundays: CronSchedule = "0 18 * * 7".parse().unwrap();
+        assert_eq!(sundays.next_after(at(7, 9, 0)), Some(at(9, 18, 0)));
+    }
+
+    #[test]
+    fn matches_day_or_weekday_when_both_are_set() {
+        let schedule: CronSchedule = "0 9 1 * MON".parse().unwrap();
+        assert!(schedule.matches_date(NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()));
+        assert!(schedule.matches_date(NaiveDate::from_ymd_opt(2024, 6, 3).unwrap()));
+        assert!(!schedule.matches_date(NaiveDate::from_ymd_opt(2024, 6, 4).unwrap()));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert!(validate("@weekly").is_ok());
+        assert!(validate("0 9 * *").is_err());
+        assert!(validate("60 9 * * *").is_err());
+        assert!(validate("0 9 * * FOO").is_err());
+        assert!(validate("0 0 31 2 *").is_err());
+    }
+}
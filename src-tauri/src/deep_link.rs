@@ -0,0 +1,348 @@
+//! `minijira://` links, e.g. `minijira://log?issue=PROJ-123&time=30m` from a
+//! browser extension, open the app on an issue or with a worklog filled
+//! in. The running app receives them as a `deep-link` event; launching a
+//! second copy hands its link to the first and exits, so there's only ever
+//! one instance.
+//!
+//! Links come from anywhere, so they only ever pre-fill: nothing is logged
+//! until I confirm it in the app.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, Url, Wry};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::time::{timeout, Duration};
+
+use crate::jira_api::JiraClient;
+use crate::settings::{self, TimeUnitSettings};
+use crate::tray;
+
+pub const SCHEME: &str = "minijira";
+/// Where the first instance waits for the links of later launches.
+const INSTANCE_ADDRESS: &str = "127.0.0.1:42871";
+/// Sent before the link, so whatever else might listen on the port isn't
+/// mistaken for a running instance.
+const HELLO: &str = "mini-jira-app/1";
+const MAX_LINK_LEN: usize = 2048;
+const MAX_COMMENT_LEN: usize = 500;
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DeepLink {
+    /// `minijira://open?issue=PROJ-123`
+    Open { issue_key: String },
+    /// `minijira://log?issue=PROJ-123&time=30m&date=2024-06-03&comment=...`;
+    /// all but `issue` optional.
+    Log {
+        issue_key: String,
+        time_spent_seconds: Option<u32>,
+        date: Option<NaiveDate>,
+        comment: Option<String>,
+    },
+}
+
+/// A link that arrived with the launch, before a window could listen.
+pub type PendingDeepLink = Mutex<Option<DeepLink>>;
+
+pub fn is_deep_link(arg: &str) -> bool {
+    arg.get(..SCHEME.len()).is_some_and(|scheme| scheme.eq_ignore_ascii_case(SCHEME)) && arg[SCHEME.len()..].starts_with(':')
+}
+
+/// Reads and checks a `minijira://` link; unknown parameters are ignored.
+pub fn parse(link: &str, units: &TimeUnitSettings) -> Result<DeepLink, String> {
+    if link.len() > MAX_LINK_LEN {
+        return Err("The link is too long".to_string());
+    }
+    let url = Url::parse(link).map_err(|e| format!("Not a valid link: {}", e))?;
+    if url.scheme() != SCHEME {
+        return Err(format!("Not a {}:// link", SCHEME));
+    }
+
+    let mut issue_key = None;
+    let mut time = None;
+    let mut date = None;
+    let mut comment = None;
+    for (name, value) in url.query_pairs() {
+        match name.as_ref() {
+            "issue" => issue_key = Some(value.trim().to_uppercase()),
+            "time" => time = Some(value.trim().to_string()),
+            "date" => date = Some(value.trim().to_string()),
+            "comment" => comment = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    let issue_key = issue_key.ok_or("The link names no issue")?;
+    if !JiraClient::is_valid_issue_key(&issue_key) {
+        return Err(format!("'{}' is not an issue key", issue_key));
+    }
+
+    // `minijira://log` has the action as its host, `minijira:log` as path.
+    let action = url.host_str().filter(|host| !host.is_empty()).unwrap_or_else(|| url.path().trim_matches('/'));
+    match action.to_ascii_lowercase().as_str() {
+        "open" => Ok(DeepLink::Open { issue_key }),
+        "log" => {
+            let time_spent_seconds = match time.filter(|time| !time.is_empty()) {
+                Some(time) => Some(JiraClient::parse_time_to_seconds(&time, units).map_err(|e| e.to_string())?),
+                None => None,
+            };
+            let date = match date.filter(|date| !date.is_empty()) {
+                Some(date) => Some(
+                    NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|_| format!("'{}' is not a YYYY-MM-DD date", date))?,
+                ),
+                None => None,
+            };
+            let comment = comment.filter(|comment| !comment.is_empty());
+            if comment.as_ref().is_some_and(|comment| comment.chars().count() > MAX_COMMENT_LEN) {
+                return Err(format!("The comment is longer than {} characters", MAX_COMMENT_LEN));
+            }
+            Ok(DeepLink::Log {
+                issue_key,
+                time_spent_seconds,
+                date,
+                comment,
+            })
+        }
+        other => Err(format!("Unknown link action '{}'", other)),
+    }
+}
+
+/// Shows the main window and hands `link` to the frontend, as `deep-link`
+/// or, when it doesn't check out, `deep-link-rejected`.
+fn handle(app_handle: &AppHandle<Wry>, link: &str) -> Option<DeepLink> {
+    tray::show_main_window(app_handle);
+    if link.is_empty() {
+        return None;
+    }
+
+    match parse(link, &settings::current(app_handle).time_units) {
+        Ok(parsed) => {
+            if let Err(e) = app_handle.emit("deep-link", &parsed) {
+                tracing::warn!("Failed to emit deep-link event: {}", e);
+            }
+            Some(parsed)
+        }
+        Err(e) => {
+            tracing::warn!("Ignoring deep link: {}", e);
+            if let Err(e) = app_handle.emit("deep-link-rejected", &e) {
+                tracing::warn!("Failed to emit deep-link-rejected event: {}", e);
+            }
+            None
+        }
+    }
+}
+
+/// Links macOS delivers to the running app.
+#[cfg(target_os = "macos")]
+pub fn open_urls(app_handle: &AppHandle<Wry>, urls: &[Url]) {
+    for url in urls.iter().filter(|url| url.scheme() == SCHEME) {
+        handle(app_handle, url.as_str());
+    }
+}
+
+/// Keeps a link the app was launched with for `take_pending_deep_link`.
+pub fn handle_launch_args(app_handle: &AppHandle<Wry>, args: &[String]) {
+    let Some(link) = args.iter().find(|arg| is_deep_link(arg)) else {
+        return;
+    };
+    if let Some(parsed) = handle(app_handle, link) {
+        *app_handle.state::<PendingDeepLink>().lock().unwrap_or_else(|e| e.into_inner()) = Some(parsed);
+    }
+}
+
+/// In a second launch: hands its link, if any, to the running instance,
+/// which comes to the front. `true` when one answered and this process
+/// should exit.
+pub fn forward_to_running(args: &[String]) -> bool {
+    let Ok(address) = INSTANCE_ADDRESS.parse() else {
+        return false;
+    };
+    let Ok(mut stream) = std::net::TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) else {
+        return false;
+    };
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(READ_TIMEOUT));
+
+    let link = args.iter().find(|arg| is_deep_link(arg)).map_or("", String::as_str);
+    // Line breaks would end the line early; a link has no business with
+    // them.
+    let link: String = link.chars().filter(|c| !c.is_control()).collect();
+    if writeln!(stream, "{} {}", HELLO, link).is_err() {
+        return false;
+    }
+    let mut answer = String::new();
+    BufReader::new(stream).read_line(&mut answer).is_ok() && answer.trim_end() == "OK"
+}
+
+async fn serve_launch(app_handle: &AppHandle<Wry>, stream: tokio::net::TcpStream) -> Result<(), String> {
+    let (reader, mut writer) = stream.into_split();
+    let mut line = String::new();
+    let mut reader = tokio::io::BufReader::new(reader).take((HELLO.len() + MAX_LINK_LEN + 2) as u64);
+    timeout(READ_TIMEOUT, reader.read_line(&mut line))
+        .await
+        .map_err(|_| "Timed out".to_string())?
+        .map_err(|e| e.to_string())?;
+
+    let Some(link) = line.trim_end().strip_prefix(HELLO) else {
+        return Err("Not a launch of this app".to_string());
+    };
+    writer.write_all(b"OK\n").await.map_err(|e| e.to_string())?;
+    handle(app_handle, link.trim());
+    Ok(())
+}
+
+/// Waits for later launches to hand over their links. Without the port,
+/// later launches start a second instance, as they did before.
+pub fn listen(app_handle: &AppHandle<Wry>) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let listener = match TcpListener::bind(INSTANCE_ADDRESS).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                tracing::warn!("Failed to listen for later launches on {}: {}", INSTANCE_ADDRESS, e);
+                return;
+            }
+        };
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!("Failed to accept a launch: {}", e);
+                    continue;
+                }
+            };
+            let app_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = serve_launch(&app_handle, stream).await {
+                    tracing::warn!("Ignoring a connection on the instance port: {}", e);
+                }
+            });
+        }
+    });
+}
+
+/// Makes this executable the handler of `minijira://` links for the
+/// current user. macOS reads it from `Info.plist` instead.
+#[cfg(target_os = "windows")]
+pub fn register_scheme(_app_handle: &AppHandle<Wry>) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let key = format!(r"HKCU\Software\Classes\{}", SCHEME);
+    let command = format!("\"{}\" \"%1\"", exe.display());
+    let entries = [
+        (key.clone(), None, "URL:Mini Jira App".to_string()),
+        (key.clone(), Some("URL Protocol"), String::new()),
+        (format!(r"{}\shell\open\command", key), None, command),
+    ];
+    for (key, name, value) in entries {
+        let mut reg = std::process::Command::new("reg");
+        reg.args(["add", &key, "/f", "/d", &value]).creation_flags(CREATE_NO_WINDOW);
+        match name {
+            Some(name) => reg.args(["/v", name]),
+            None => reg.arg("/ve"),
+        };
+        let status = reg.status().map_err(|e| e.to_string())?;
+        if !status.success() {
+            return Err(format!("reg add {} failed with {}", key, status));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub fn register_scheme(app_handle: &AppHandle<Wry>) -> Result<(), String> {
+    const DESKTOP_FILE: &str = "mini-jira-app-url-handler.desktop";
+
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let dir = app_handle.path().data_dir().map_err(|e| e.to_string())?.join("applications");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let entry = format!(
+        "[Desktop Entry]\nType=Application\nName=Mini Jira App\nExec=\"{}\" %u\nMimeType=x-scheme-handler/{};\nNoDisplay=true\nTerminal=false\n",
+        exe.display(),
+        SCHEME
+    );
+    std::fs::write(dir.join(DESKTOP_FILE), entry).map_err(|e| e.to_string())?;
+
+    let status = std::process::Command::new("xdg-mime")
+        .args(["default", DESKTOP_FILE, &format!("x-scheme-handler/{}", SCHEME)])
+        .status()
+        .map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!("xdg-mime failed with {}", status));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn register_scheme(_app_handle: &AppHandle<Wry>) -> Result<(), String> {
+    Ok(())
+}
+
+/// The link the app was launched with, once; later links arrive as
+/// `deep-link` events.
+#[tauri::command]
+pub async fn take_pending_deep_link(state: State<'_, PendingDeepLink>) -> Result<Option<DeepLink>, String> {
+    Ok(state.lock().map_err(|e| e.to_string())?.take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_link(link: &str) -> Result<DeepLink, String> {
+        parse(link, &TimeUnitSettings::default())
+    }
+
+    #[test]
+    fn reads_log_links() {
+        assert_eq!(
+            parse_link("minijira://log?issue=proj-123&time=1h%2030m&date=2024-06-03&comment=Code%20review&utm=x"),
+            Ok(DeepLink::Log {
+                issue_key: "PROJ-123".to_string(),
+                time_spent_seconds: Some(5400),
+                date: NaiveDate::from_ymd_opt(2024, 6, 3),
+                comment: Some("Code review".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_link("minijira:log?issue=PROJ-1"),
+            Ok(DeepLink::Log {
+                issue_key: "PROJ-1".to_string(),
+                time_spent_seconds: None,
+                date: None,
+                comment: None,
+            })
+        );
+        assert_eq!(
+            parse_link("MINIJIRA://Open?issue=PROJ-7"),
+            Ok(DeepLink::Open {
+                issue_key: "PROJ-7".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_bad_links() {
+        assert!(parse_link("https://log?issue=PROJ-1").is_err());
+        assert!(parse_link("minijira://log").is_err());
+        assert!(parse_link("minijira://log?issue=not a key").is_err());
+        assert!(parse_link("minijira://log?issue=PROJ-1&time=soon").is_err());
+        assert!(parse_link("minijira://log?issue=PROJ-1&date=03/06/2024").is_err());
+        assert!(parse_link("minijira://delete?issue=PROJ-1").is_err());
+        assert!(parse_link(&format!("minijira://log?issue=PROJ-1&comment={}", "a".repeat(501))).is_err());
+    }
+
+    #[test]
+    fn spots_links_among_arguments() {
+        assert!(is_deep_link("minijira://log?issue=PROJ-1"));
+        assert!(is_deep_link("MiniJira:open?issue=PROJ-1"));
+        assert!(!is_deep_link("--start-minimized"));
+        assert!(!is_deep_link("minijira"));
+    }
+}
@@ -0,0 +1,166 @@
+//! Splits what is left of a day across a few issues and logs it, for
+//! accounting for exactly the daily target: evenly, by what the timer
+//! tracked on each issue that day, or in proportion to what is already
+//! logged on them. Shares are whole steps of `backfill.granularity_minutes`
+//! except the largest, which takes what doesn't divide.
+
+use chrono::{Duration, Local, NaiveDate};
+use serde::Deserialize;
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::adf::CommentInput;
+use crate::bulk::{self, BulkWorklogResult, WorklogEntry};
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::storage::Storage;
+use crate::summary;
+use crate::worklog_backend::{self, WorklogBackend};
+use crate::{client_for, settings, timer, JiraState};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistributeStrategy {
+    #[default]
+    Even,
+    /// By the time the timer tracked on each issue that day.
+    Timer,
+    /// By the time already logged on each issue that day.
+    Existing,
+}
+
+/// Splits `total_minutes` by `weights` in steps of `step` minutes, with
+/// largest remainders deciding where the steps left over go. Without any
+/// weight it splits evenly.
+fn split(total_minutes: u32, weights: &[f64], step: u32) -> Vec<u32> {
+    if weights.is_empty() {
+        return Vec::new();
+    }
+    let step = step.max(1);
+    let weights: Vec<f64> = match weights.iter().any(|weight| *weight > 0.0) {
+        true => weights.iter().map(|weight| weight.max(0.0)).collect(),
+        false => vec![1.0; weights.len()],
+    };
+    let sum: f64 = weights.iter().sum();
+    let steps = total_minutes / step;
+
+    let quotas: Vec<f64> = weights.iter().map(|weight| steps as f64 * weight / sum).collect();
+    let mut shares: Vec<u32> = quotas.iter().map(|quota| quota.floor() as u32).collect();
+    let mut by_remainder: Vec<usize> = (0..shares.len()).collect();
+    by_remainder.sort_by(|a, b| (quotas[*b] - quotas[*b].floor()).total_cmp(&(quotas[*a] - quotas[*a].floor())));
+    let left = steps - shares.iter().sum::<u32>();
+    for index in by_remainder.into_iter().take(left as usize) {
+        shares[index] += 1;
+    }
+
+    let mut shares: Vec<u32> = shares.into_iter().map(|share| share * step).collect();
+    if let Some(largest) = (0..shares.len()).max_by_key(|index| (shares[*index], std::cmp::Reverse(*index))) {
+        shares[largest] += total_minutes % step;
+    }
+    shares
+}
+
+/// Logs `total` (by default what is left of the daily target) on `date`,
+/// split across `issue_keys` by `strategy`. The worklogs follow each other
+/// from the end of the day's last worklog, or from `backfill.day_start`,
+/// and go through `create_worklogs_bulk`.
+#[tauri::command(rename_all = "camelCase")]
+#[allow(clippy::too_many_arguments)]
+pub async fn distribute_time(
+    date: NaiveDate,
+    total: Option<String>,
+    issue_keys: Vec<String>,
+    strategy: Option<DistributeStrategy>,
+    description: Option<String>,
+    connection: Option<String>,
+    operation: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<BulkWorklogResult>, JiraError> {
+    let mut keys: Vec<String> = Vec::new();
+    for issue_key in &issue_keys {
+        let issue_key = timer::normalize_issue_key(issue_key).map_err(JiraError::Validation)?;
+        if !keys.contains(&issue_key) {
+            keys.push(issue_key);
+        }
+    }
+    if keys.is_empty() {
+        return Err(JiraError::Validation("Choose at least one issue to log time on".to_string()));
+    }
+
+    let settings = settings::effective(&app_handle);
+    let client = client_for(&state, connection.as_deref()).await?;
+    let backend = worklog_backend::for_app(&app_handle, client)?;
+    let logged = backend.user_worklogs(date, date).await?;
+    let day = summary::summarize(&logged, date, date, &settings);
+
+    let total_seconds = match total.as_deref().map(str::trim).filter(|total| !total.is_empty()) {
+        Some(total) => JiraClient::parse_time_to_seconds(total, &settings.time_units)?,
+        None => day.target_seconds.saturating_sub(day.total_seconds),
+    };
+    let total_minutes = (total_seconds + 30) / 60;
+    if total_minutes == 0 {
+        return Err(JiraError::Validation(format!("Nothing is left to log on {}", date)));
+    }
+
+    let weights: Vec<f64> = match strategy.unwrap_or_default() {
+        DistributeStrategy::Even => vec![1.0; keys.len()],
+        DistributeStrategy::Timer => {
+            let timed = app_handle.state::<Storage>().timed_on(date);
+            keys.iter().map(|key| timed.get(key).copied().unwrap_or(0) as f64).collect()
+        }
+        DistributeStrategy::Existing => {
+            let per_issue: HashMap<&str, u32> =
+                day.issues.iter().map(|issue| (issue.issue_key.as_str(), issue.seconds)).collect();
+            keys.iter().map(|key| per_issue.get(key.as_str()).copied().unwrap_or(0) as f64).collect()
+        }
+    };
+    let shares = split(total_minutes, &weights, settings.backfill.granularity_minutes);
+
+    let day_start = date.and_time(settings.backfill.day_start).and_local_timezone(Local).earliest();
+    let last_end = logged
+        .iter()
+        .filter_map(|entry| {
+            let started = JiraClient::parse_jira_datetime(&entry.worklog.started).ok()?.with_timezone(&Local);
+            let end = started + Duration::seconds(entry.worklog.time_spent_seconds as i64);
+            (started.date_naive() == date).then_some(end)
+        })
+        .max();
+    let mut cursor = match (day_start, last_end) {
+        (Some(day_start), Some(last_end)) => day_start.max(last_end),
+        (day_start, last_end) => day_start.or(last_end).unwrap_or_else(Local::now),
+    };
+
+    let description = description.unwrap_or_default();
+    let mut entries = Vec::new();
+    for (issue_key, minutes) in keys.into_iter().zip(shares) {
+        if minutes == 0 {
+            continue;
+        }
+        entries.push(WorklogEntry {
+            issue_key,
+            description: CommentInput::Text(description.clone()),
+            started: JiraClient::format_jira_datetime(&cursor),
+            time_spent: format!("{}m", minutes),
+            confirmed: false,
+        });
+        cursor += Duration::minutes(minutes as i64);
+    }
+    bulk::create_worklogs_bulk(entries, connection, operation, app_handle, state).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_in_steps_and_keeps_the_total() {
+        assert_eq!(split(480, &[1.0, 1.0, 1.0], 15), [165, 165, 150]);
+        assert_eq!(split(100, &[3.0, 1.0], 15), [85, 15]);
+        assert_eq!(split(60, &[0.0, 0.0], 15), [30, 30]);
+        assert_eq!(split(60, &[2.0, 0.0], 15), [60, 0]);
+
+        // Seven minutes don't make a step; the largest share takes them.
+        assert_eq!(split(487, &[5.0, 2.0, 1.0], 15), [307, 120, 60]);
+    }
+}
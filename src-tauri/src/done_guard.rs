@@ -0,0 +1,64 @@
+//! The `done_guard` setting: closing an issue I never logged time on through
+//! the app asks first (`warn`) or is refused (`block`). Worklogs still
+//! waiting in the offline queue count as logged.
+
+use tauri::{AppHandle, Manager, Wry};
+
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::offline_queue::OfflineQueue;
+use crate::settings::{self, DoneGuard};
+
+const DONE_CATEGORY: &str = "done";
+
+async fn logged_seconds(app_handle: &AppHandle<Wry>, client: &JiraClient, issue_key: &str) -> Result<u32, JiraError> {
+    let queued: u32 = app_handle
+        .state::<OfflineQueue>()
+        .entries()
+        .iter()
+        .filter(|pending| pending.issue_key == issue_key)
+        .map(|pending| pending.time_spent_seconds)
+        .sum();
+    if queued > 0 {
+        return Ok(queued);
+    }
+
+    let myself = client.get_myself().await?;
+    let worklogs = client.get_issue_worklogs(issue_key, None, None).await?;
+    Ok(worklogs
+        .iter()
+        .filter(|worklog| worklog.author.as_ref().is_some_and(|author| author.account_id == myself.account_id))
+        .map(|worklog| worklog.time_spent_seconds)
+        .sum())
+}
+
+/// Whether `transition_id` may be applied to `issue_key`. Only transitions
+/// into a done-category status are checked.
+pub async fn check(
+    app_handle: &AppHandle<Wry>,
+    client: &JiraClient,
+    issue_key: &str,
+    transition_id: &str,
+    confirmed: bool,
+) -> Result<(), JiraError> {
+    let guard = settings::effective(app_handle).done_guard;
+    if guard == DoneGuard::Off || (guard == DoneGuard::Warn && confirmed) {
+        return Ok(());
+    }
+
+    let transitions = client.get_transitions(issue_key).await?;
+    let Some(transition) = transitions.iter().find(|transition| transition.id == transition_id) else {
+        // JIRA reports unknown transitions itself.
+        return Ok(());
+    };
+    let into_done = transition.to.category.as_ref().is_some_and(|category| category.key == DONE_CATEGORY);
+    if !into_done || logged_seconds(app_handle, client, issue_key).await? > 0 {
+        return Ok(());
+    }
+
+    let message = format!("You haven't logged any time on {}", issue_key);
+    match guard {
+        DoneGuard::Block => Err(JiraError::Validation(format!("{}; log it before moving it to {}", message, transition.to.name))),
+        _ => Err(JiraError::ConfirmationRequired(format!("{}. Move it to {} anyway?", message, transition.to.name))),
+    }
+}
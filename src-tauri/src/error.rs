@@ -0,0 +1,222 @@
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::worklog_rules::{self, RuleViolation};
+
+/// Errors talking to JIRA. Commands return it as is, so the frontend gets
+/// `{ kind, message, status, retry_after_secs }` (plus `violations` for
+/// `rules_broken`) and can tell an expired token from a typo in an issue
+/// key or a dropped VPN.
+#[derive(Debug, thiserror::Error)]
+pub enum JiraError {
+    #[error("Not connected to JIRA")]
+    NotConnected,
+    /// 401 and 403, and OAuth sessions that can no longer be refreshed.
+    #[error("{message}")]
+    Unauthorized { status: Option<u16>, message: String },
+    #[error("{0}")]
+    NotFound(String),
+    #[error("JIRA rate limit reached")]
+    RateLimited { retry_after_secs: Option<u64> },
+    /// JIRA could not be reached, or the connection broke off.
+    #[error("Network error: {}", root_cause(.0))]
+    Network(reqwest::Error),
+    #[error("Unexpected response from JIRA: {0}")]
+    Deserialize(String),
+    /// Rejected input, ours (bad durations, keys) or JIRA's (400).
+    #[error("{0}")]
+    Validation(String),
+    /// Any other error status.
+    #[error("JIRA API error {status}: {message}")]
+    Api { status: u16, message: String },
+    /// Refused until the user confirms; repeating the call with
+    /// `confirmed` goes ahead.
+    #[error("{0}")]
+    ConfirmationRequired(String),
+    /// Aborted through `cancel_request`.
+    #[error("The request was cancelled")]
+    Cancelled,
+    /// Broke the `worklog_rules`.
+    #[error("{}", worklog_rules::describe(.0))]
+    RulesBroken(Vec<RuleViolation>),
+    /// Not sent while working offline; see `offline`.
+    #[error("Working offline")]
+    Offline,
+    #[error("{0}")]
+    Other(String),
+}
+
+/// Failures of the app itself rather than of JIRA.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum AppError {
+    /// A bug: a command panicked. `reference` is logged with the panic, so
+    /// a report quoting it can be matched to the log.
+    #[error("Internal error ({reference}): {message}")]
+    Internal { reference: String, message: String },
+    /// The session locked after inactivity; `unlock_session` lifts it.
+    #[error("The session is locked; unlock it to make changes")]
+    Locked,
+}
+
+/// reqwest's own message only says the request failed; the reason (refused,
+/// DNS, certificate) is at the bottom of the source chain.
+fn root_cause(error: &reqwest::Error) -> String {
+    let mut cause: &dyn std::error::Error = error;
+    while let Some(source) = cause.source() {
+        cause = source;
+    }
+    cause.to_string()
+}
+
+/// Body of JIRA error responses.
+#[derive(Deserialize)]
+struct ErrorBody {
+    #[serde(default, rename = "errorMessages")]
+    error_messages: Vec<String>,
+    #[serde(default)]
+    errors: std::collections::BTreeMap<String, String>,
+}
+
+impl JiraError {
+    /// Maps an error status to its variant; `body` is the response body,
+    /// from which JIRA's own messages are taken when it has any.
+    pub fn from_status(status: reqwest::StatusCode, retry_after_secs: Option<u64>, body: &str) -> Self {
+        let message = serde_json::from_str::<ErrorBody>(body)
+            .ok()
+            .map(|body| {
+                body.error_messages
+                    .into_iter()
+                    .chain(body.errors.into_iter().map(|(field, message)| format!("{}: {}", field, message)))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            })
+            .filter(|message| !message.is_empty());
+        Self::with_message(status, retry_after_secs, message)
+    }
+
+    /// Like `from_status`, for services whose error bodies look different.
+    /// Without `message` the status's reason phrase is used.
+    pub fn with_message(status: reqwest::StatusCode, retry_after_secs: Option<u64>, message: Option<String>) -> Self {
+        let message =
+            message.unwrap_or_else(|| status.canonical_reason().unwrap_or("Request failed").to_string());
+
+        match status.as_u16() {
+            401 | 403 => JiraError::Unauthorized {
+                status: Some(status.as_u16()),
+                message,
+            },
+            404 => JiraError::NotFound(message),
+            429 => JiraError::RateLimited { retry_after_secs },
+            400 => JiraError::Validation(message),
+            status => JiraError::Api { status, message },
+        }
+    }
+
+    /// Whether JIRA could not be reached at all, as opposed to JIRA
+    /// rejecting the request.
+    pub fn is_connectivity(&self) -> bool {
+        match self {
+            JiraError::Offline => true,
+            JiraError::Network(e) => e.is_connect() || e.is_timeout() || e.is_request(),
+            _ => false,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            JiraError::NotConnected => "not_connected",
+            JiraError::Unauthorized { .. } => "unauthorized",
+            JiraError::NotFound(_) => "not_found",
+            JiraError::RateLimited { .. } => "rate_limited",
+            JiraError::Network(_) => "network",
+            JiraError::Deserialize(_) => "deserialize",
+            JiraError::Validation(_) => "validation",
+            JiraError::Api { .. } => "api",
+            JiraError::ConfirmationRequired(_) => "confirmation_required",
+            JiraError::Cancelled => "cancelled",
+            JiraError::Offline => "offline",
+            JiraError::RulesBroken(_) => "rules_broken",
+            JiraError::Other(_) => "other",
+        }
+    }
+
+    fn status(&self) -> Option<u16> {
+        match self {
+            JiraError::Unauthorized { status, .. } => *status,
+            JiraError::NotFound(_) => Some(404),
+            JiraError::RateLimited { .. } => Some(429),
+            JiraError::Api { status, .. } => Some(*status),
+            JiraError::Network(e) => e.status().map(|status| status.as_u16()),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for JiraError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_decode() {
+            JiraError::Deserialize(error.to_string())
+        } else {
+            JiraError::Network(error)
+        }
+    }
+}
+
+impl From<serde_json::Error> for JiraError {
+    fn from(error: serde_json::Error) -> Self {
+        JiraError::Deserialize(error.to_string())
+    }
+}
+
+impl From<String> for JiraError {
+    fn from(message: String) -> Self {
+        JiraError::Other(message)
+    }
+}
+
+impl From<&str> for JiraError {
+    fn from(message: &str) -> Self {
+        JiraError::Other(message.to_string())
+    }
+}
+
+/// For commands and helpers that still report plain strings.
+impl From<JiraError> for String {
+    fn from(error: JiraError) -> Self {
+        error.to_string()
+    }
+}
+
+/// `{ kind: "internal", message, reference }`.
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (kind, reference) = match self {
+            AppError::Internal { reference, .. } => ("internal", Some(reference)),
+            AppError::Locked => ("locked", None),
+        };
+        let mut error = serializer.serialize_struct("AppError", 3)?;
+        error.serialize_field("kind", kind)?;
+        error.serialize_field("message", &self.to_string())?;
+        error.serialize_field("reference", &reference)?;
+        error.end()
+    }
+}
+
+impl Serialize for JiraError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("JiraError", 5)?;
+        error.serialize_field("kind", self.kind())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.serialize_field("status", &self.status())?;
+        let retry_after_secs = match self {
+            JiraError::RateLimited { retry_after_secs } => *retry_after_secs,
+            _ => None,
+        };
+        error.serialize_field("retry_after_secs", &retry_after_secs)?;
+        match self {
+            JiraError::RulesBroken(violations) => error.serialize_field("violations", violations)?,
+            _ => error.skip_field("violations")?,
+        }
+        error.end()
+    }
+}
@@ -0,0 +1,460 @@
+use chrono::{Local, NaiveDate, NaiveTime};
+use rust_xlsxwriter::{Format, Workbook};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::analytics;
+use crate::error::JiraError;
+use crate::hierarchy::{self, ReportRollup};
+use crate::jira_api::JiraClient;
+use crate::jira_types::UserWorklog;
+use crate::settings::{self, AppSettings};
+use crate::storage::{RecordedSegment, Storage};
+use crate::summary::ReportGrouping;
+use crate::timer::SegmentKind;
+use crate::worklog_backend::{self, WorklogBackend};
+use crate::{client_for, JiraState};
+
+const HEADERS: [&str; 7] = ["Client", "Issue", "Summary", "Date", "Started", "Hours", "Comment"];
+const SESSION_HEADERS: [&str; 7] = ["Issue", "Session", "Kind", "Date", "Started", "Ended", "Minutes"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Xlsx => "Excel workbook",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportResult {
+    pub path: PathBuf,
+    pub rows: usize,
+}
+
+/// One timesheet line, in local time.
+struct Row {
+    client: Option<String>,
+    issue_key: String,
+    summary: String,
+    date: NaiveDate,
+    started: String,
+    hours: f64,
+    comment: String,
+}
+
+fn rows(mut worklogs: Vec<UserWorklog>, settings: &AppSettings, group_by: ReportGrouping) -> Vec<Row> {
+    worklogs.sort_by(|a, b| a.worklog.started.cmp(&b.worklog.started));
+    let mut rows: Vec<Row> = worklogs
+        .into_iter()
+        .filter_map(|entry| {
+            let started = JiraClient::parse_jira_datetime(&entry.worklog.started)
+                .ok()?
+                .with_timezone(&Local);
+            Some(Row {
+                client: settings.client_of(&entry.issue_key).map(str::to_string),
+                comment: settings.redaction.comment(entry.worklog.comment_text()),
+                issue_key: entry.issue_key,
+                summary: settings.redaction.summary(entry.issue_summary),
+                date: started.date_naive(),
+                started: started.format("%H:%M").to_string(),
+                hours: entry.worklog.time_spent_seconds as f64 / 3600.0,
+            })
+        })
+        .collect();
+
+    if group_by == ReportGrouping::Client {
+        // Stable: rows stay in date order within a client.
+        rows.sort_by(|a, b| (a.client.is_none(), &a.client).cmp(&(b.client.is_none(), &b.client)));
+    }
+    rows
+}
+
+fn csv_field(value: &str) -> String {
+    // Spreadsheets run cells starting like this as formulas; JIRA text is
+    // anyone's to write.
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn write_csv(path: &Path, rows: &[Row]) -> Result<(), String> {
+    let mut out = HEADERS.join(",");
+    out.push_str("\r\n");
+    for row in rows {
+        let fields = [
+            csv_field(row.client.as_deref().unwrap_or_default()),
+            csv_field(&row.issue_key),
+            csv_field(&row.summary),
+            row.date.to_string(),
+            row.started.clone(),
+            format!("{:.2}", row.hours),
+            csv_field(&row.comment),
+        ];
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    std::fs::write(path, out).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn write_xlsx(path: &Path, rows: &[Row]) -> Result<(), String> {
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+    let hours = Format::new().set_num_format("0.00");
+    let sheet = workbook.add_worksheet();
+    let xlsx_error = |e: rust_xlsxwriter::XlsxError| e.to_string();
+
+    sheet.set_name("Worklogs").map_err(xlsx_error)?;
+    for (col, header) in HEADERS.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, &bold).map_err(xlsx_error)?;
+    }
+    for (i, row) in rows.iter().enumerate() {
+        let r = i as u32 + 1;
+        sheet.write_string(r, 0, row.client.as_deref().unwrap_or_default()).map_err(xlsx_error)?;
+        sheet.write_string(r, 1, &row.issue_key).map_err(xlsx_error)?;
+        sheet.write_string(r, 2, &row.summary).map_err(xlsx_error)?;
+        sheet.write_string(r, 3, row.date.to_string()).map_err(xlsx_error)?;
+        sheet.write_string(r, 4, &row.started).map_err(xlsx_error)?;
+        sheet.write_number_with_format(r, 5, row.hours, &hours).map_err(xlsx_error)?;
+        sheet.write_string(r, 6, &row.comment).map_err(xlsx_error)?;
+    }
+    for (col, width) in [20, 12, 40, 12, 8, 8, 60].into_iter().enumerate() {
+        sheet.set_column_width(col as u16, width).map_err(xlsx_error)?;
+    }
+    sheet.set_freeze_panes(1, 0).map_err(xlsx_error)?;
+
+    // Hours per client, for invoicing.
+    let mut by_client: BTreeMap<&str, f64> = BTreeMap::new();
+    for row in rows {
+        *by_client.entry(row.client.as_deref().unwrap_or("No client")).or_default() += row.hours;
+    }
+    let totals = workbook.add_worksheet();
+    totals.set_name("Clients").map_err(xlsx_error)?;
+    totals.write_string_with_format(0, 0, "Client", &bold).map_err(xlsx_error)?;
+    totals.write_string_with_format(0, 1, "Hours", &bold).map_err(xlsx_error)?;
+    for (i, (client, client_hours)) in by_client.into_iter().enumerate() {
+        let r = i as u32 + 1;
+        totals.write_string(r, 0, client).map_err(xlsx_error)?;
+        totals.write_number_with_format(r, 1, client_hours, &hours).map_err(xlsx_error)?;
+    }
+    totals.set_column_width(0, 30).map_err(xlsx_error)?;
+
+    workbook
+        .save(path)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Asks where to save the export; `None` when the dialog is cancelled.
+async fn ask_for_path(
+    app_handle: &AppHandle<Wry>,
+    name: &str,
+    format: ExportFormat,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Option<PathBuf> {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    app_handle
+        .dialog()
+        .file()
+        .add_filter(format.label(), &[format.extension()])
+        .set_file_name(format!("{}_{}_{}.{}", name, from, to, format.extension()))
+        .save_file(move |path| {
+            let _ = sender.send(path);
+        });
+
+    let path = receiver.await.ok().flatten()?.into_path().ok()?;
+    if path.extension().is_some() {
+        Some(path)
+    } else {
+        Some(path.with_extension(format.extension()))
+    }
+}
+
+/// Writes my worklogs in `[from_date, to_date]` as a timesheet, by date or
+/// grouped by client, and optionally rolled up to parents (see
+/// `hierarchy`). Without a `path` a save dialog asks for one; returns
+/// `None` when it is cancelled.
+#[tauri::command(rename_all = "camelCase")]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_worklogs(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    format: ExportFormat,
+    path: Option<PathBuf>,
+    group_by: Option<ReportGrouping>,
+    rollup: Option<ReportRollup>,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+) -> Result<Option<ExportResult>, JiraError> {
+    if from_date > to_date {
+        return Err(JiraError::Validation("fromDate must not be after toDate".to_string()));
+    }
+
+    let client = client_for(&app_handle.state::<JiraState>(), connection.as_deref()).await?;
+    let backend = worklog_backend::for_app(&app_handle, client.clone())?;
+    let mut worklogs = backend.user_worklogs(from_date, to_date).await?;
+    analytics::record_range(&app_handle, &client, from_date, to_date, &worklogs);
+    hierarchy::apply(&app_handle, &client, &mut worklogs, rollup.unwrap_or_default()).await;
+
+    let path = match path {
+        Some(path) => path,
+        None => match ask_for_path(&app_handle, "worklogs", format, from_date, to_date).await {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+
+    let rows = write_timesheet(&path, format, worklogs, &settings::effective(&app_handle), group_by.unwrap_or_default())?;
+    Ok(Some(ExportResult { path, rows }))
+}
+
+/// Writes `worklogs` to `path` as `export_worklogs` does; returns the rows
+/// written.
+pub fn write_timesheet(
+    path: &Path,
+    format: ExportFormat,
+    worklogs: Vec<UserWorklog>,
+    settings: &AppSettings,
+    grouping: ReportGrouping,
+) -> Result<usize, String> {
+    let rows = rows(worklogs, settings, grouping);
+    match format {
+        ExportFormat::Csv => write_csv(path, &rows)?,
+        ExportFormat::Xlsx => write_xlsx(path, &rows)?,
+    }
+    Ok(rows.len())
+}
+
+/// One timer segment, in local time.
+struct SessionRow {
+    issue_key: String,
+    session: String,
+    kind: SegmentKind,
+    date: NaiveDate,
+    started: String,
+    ended: String,
+    minutes: f64,
+}
+
+fn session_rows(segments: Vec<RecordedSegment>) -> Vec<SessionRow> {
+    segments
+        .into_iter()
+        .map(|recorded| SessionRow {
+            session: recorded.session.format("%Y-%m-%d %H:%M").to_string(),
+            issue_key: recorded.issue_key,
+            kind: recorded.segment.kind,
+            date: recorded.segment.started.date_naive(),
+            started: recorded.segment.started.format("%H:%M:%S").to_string(),
+            ended: recorded.segment.ended.format("%H:%M:%S").to_string(),
+            minutes: (recorded.segment.ended - recorded.segment.started).num_seconds() as f64 / 60.0,
+        })
+        .collect()
+}
+
+fn write_sessions_csv(path: &Path, rows: &[SessionRow]) -> Result<(), String> {
+    let mut out = SESSION_HEADERS.join(",");
+    out.push_str("\r\n");
+    for row in rows {
+        let fields = [
+            csv_field(&row.issue_key),
+            row.session.clone(),
+            row.kind.as_str().to_string(),
+            row.date.to_string(),
+            row.started.clone(),
+            row.ended.clone(),
+            format!("{:.1}", row.minutes),
+        ];
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    std::fs::write(path, out).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn write_sessions_xlsx(path: &Path, rows: &[SessionRow]) -> Result<(), String> {
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+    let minutes = Format::new().set_num_format("0.0");
+    let sheet = workbook.add_worksheet();
+    let xlsx_error = |e: rust_xlsxwriter::XlsxError| e.to_string();
+
+    sheet.set_name("Sessions").map_err(xlsx_error)?;
+    for (col, header) in SESSION_HEADERS.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, &bold).map_err(xlsx_error)?;
+    }
+    for (i, row) in rows.iter().enumerate() {
+        let r = i as u32 + 1;
+        sheet.write_string(r, 0, &row.issue_key).map_err(xlsx_error)?;
+        sheet.write_string(r, 1, &row.session).map_err(xlsx_error)?;
+        sheet.write_string(r, 2, row.kind.as_str()).map_err(xlsx_error)?;
+        sheet.write_string(r, 3, row.date.to_string()).map_err(xlsx_error)?;
+        sheet.write_string(r, 4, &row.started).map_err(xlsx_error)?;
+        sheet.write_string(r, 5, &row.ended).map_err(xlsx_error)?;
+        sheet.write_number_with_format(r, 6, row.minutes, &minutes).map_err(xlsx_error)?;
+    }
+    for (col, width) in [12, 16, 14, 12, 10, 10, 10].into_iter().enumerate() {
+        sheet.set_column_width(col as u16, width).map_err(xlsx_error)?;
+    }
+    sheet.set_freeze_panes(1, 0).map_err(xlsx_error)?;
+
+    // Minutes per kind: tracked against idle and discarded time.
+    let mut by_kind: BTreeMap<&str, f64> = BTreeMap::new();
+    for row in rows {
+        *by_kind.entry(row.kind.as_str()).or_default() += row.minutes;
+    }
+    let totals = workbook.add_worksheet();
+    totals.set_name("Kinds").map_err(xlsx_error)?;
+    totals.write_string_with_format(0, 0, "Kind", &bold).map_err(xlsx_error)?;
+    totals.write_string_with_format(0, 1, "Minutes", &bold).map_err(xlsx_error)?;
+    for (i, (kind, kind_minutes)) in by_kind.into_iter().enumerate() {
+        let r = i as u32 + 1;
+        totals.write_string(r, 0, kind).map_err(xlsx_error)?;
+        totals.write_number_with_format(r, 1, kind_minutes, &minutes).map_err(xlsx_error)?;
+    }
+    totals.set_column_width(0, 16).map_err(xlsx_error)?;
+
+    workbook
+        .save(path)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Writes the raw timer sessions started in `[from_date, to_date]`, one row
+/// per segment: tracked time, idle time kept or trimmed, and timers that
+/// were discarded. Unlike `export_worklogs` this is what the timer saw, not
+/// what was logged. Without a `path` a save dialog asks for one.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn export_timer_sessions(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    format: ExportFormat,
+    path: Option<PathBuf>,
+    app_handle: AppHandle<Wry>,
+) -> Result<Option<ExportResult>, JiraError> {
+    if from_date > to_date {
+        return Err(JiraError::Validation("fromDate must not be after toDate".to_string()));
+    }
+
+    let path = match path {
+        Some(path) => path,
+        None => match ask_for_path(&app_handle, "timer_sessions", format, from_date, to_date).await {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+
+    let rows = write_timer_sessions(&app_handle, &path, format, from_date, to_date)?;
+    Ok(Some(ExportResult { path, rows }))
+}
+
+/// Writes the timer sessions started in `[from_date, to_date]` to `path` as
+/// `export_timer_sessions` does; returns the rows written.
+pub fn write_timer_sessions(
+    app_handle: &AppHandle<Wry>,
+    path: &Path,
+    format: ExportFormat,
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+) -> Result<usize, JiraError> {
+    let day_start = |date: NaiveDate| date.and_time(NaiveTime::MIN).and_local_timezone(Local).earliest();
+    let (Some(from), Some(to)) = (day_start(from_date), to_date.succ_opt().and_then(day_start)) else {
+        return Err(JiraError::Validation("The range has no local midnight to start from".to_string()));
+    };
+
+    let rows = session_rows(app_handle.state::<Storage>().load_timer_segments(from, to));
+    match format {
+        ExportFormat::Csv => write_sessions_csv(path, &rows)?,
+        ExportFormat::Xlsx => write_sessions_xlsx(path, &rows)?,
+    }
+    Ok(rows.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redaction;
+
+    fn worklog(issue_key: &str, started: &str, comment: &str) -> UserWorklog {
+        serde_json::from_value(serde_json::json!({
+            "issue_key": issue_key,
+            "issue_summary": format!("{} summary", issue_key),
+            "worklog": {
+                "id": "1",
+                "issueId": "10001",
+                "timeSpentSeconds": 5400,
+                "started": started,
+                "comment": comment,
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn quotes_and_defuses_csv_fields() {
+        assert_eq!(csv_field("APP-1"), "APP-1");
+        assert_eq!(csv_field("Fix login, then test"), "\"Fix login, then test\"");
+        assert_eq!(csv_field("The \"new\" form"), "\"The \"\"new\"\" form\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("=HYPERLINK(\"http://evil\")"), "\"'=HYPERLINK(\"\"http://evil\"\")\"");
+        assert_eq!(csv_field("+1 for the fix"), "'+1 for the fix");
+        assert_eq!(csv_field("-2h review"), "'-2h review");
+        assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(csv_field("a = b"), "a = b");
+    }
+
+    #[test]
+    fn groups_rows_by_client_with_unmapped_projects_last() {
+        let mut settings = AppSettings::default();
+        settings.project_clients.insert("APP".to_string(), "Acme".to_string());
+        settings.project_clients.insert("WEB".to_string(), "Beta".to_string());
+        let worklogs = || {
+            vec![
+                worklog("OPS-1", "2024-06-03T08:00:00.000+0000", "Deploy"),
+                worklog("WEB-2", "2024-06-03T09:00:00.000+0000", "Styles"),
+                worklog("APP-3", "2024-06-03T11:00:00.000+0000", "Login"),
+                worklog("APP-4", "2024-06-03T10:00:00.000+0000", "Signup"),
+            ]
+        };
+        let keys = |rows: &[Row]| rows.iter().map(|row| row.issue_key.clone()).collect::<Vec<_>>();
+
+        let by_date = rows(worklogs(), &settings, ReportGrouping::Date);
+        assert_eq!(keys(&by_date), ["OPS-1", "WEB-2", "APP-4", "APP-3"]);
+
+        let by_client = rows(worklogs(), &settings, ReportGrouping::Client);
+        assert_eq!(keys(&by_client), ["APP-4", "APP-3", "WEB-2", "OPS-1"]);
+        assert_eq!(by_client[0].client.as_deref(), Some("Acme"));
+        assert_eq!(by_client[3].client, None);
+        assert_eq!((by_client[0].summary.as_str(), by_client[0].comment.as_str()), ("APP-4 summary", "Signup"));
+        assert_eq!(by_client[0].hours, 1.5);
+
+        settings.redaction.hash_summaries = true;
+        settings.redaction.strip_descriptions = true;
+        let redacted = rows(worklogs(), &settings, ReportGrouping::Client);
+        assert_eq!(keys(&redacted), ["APP-4", "APP-3", "WEB-2", "OPS-1"]);
+        assert_eq!(redacted[0].summary, redaction::hash_summary("APP-4 summary"));
+        assert!(redacted.iter().all(|row| row.comment.is_empty()));
+    }
+}
@@ -0,0 +1,224 @@
+//! Recorded JIRA responses under `fixtures/jira/<variant>/`, one directory
+//! per deployment (`cloud` speaks REST v3, `server` v2). Each file is named
+//! after the endpoint it was recorded from, optionally followed by
+//! `_<label>`, and the tests below parse every file into that endpoint's
+//! type. New payloads are recorded with `record_fixture` in dev builds.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::State;
+
+use crate::error::JiraError;
+use crate::{connected_client, JiraState};
+
+pub const VARIANTS: [&str; 2] = ["cloud", "server"];
+
+/// Endpoints with a fixture type; longest names first so prefixes match
+/// unambiguously.
+pub const ENDPOINTS: [&str; 5] = ["issue_worklogs", "create_worklog", "transitions", "myself", "search"];
+
+/// Values that identify people, replaced by stable placeholders so recorded
+/// fixtures can be committed.
+const PERSONAL_FIELDS: [&str; 3] = ["accountId", "emailAddress", "displayName"];
+
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("jira")
+}
+
+#[derive(Default)]
+struct Redactor {
+    replacements: HashMap<(String, String), String>,
+}
+
+impl Redactor {
+    fn placeholder(&mut self, field: &str, value: &str) -> String {
+        let next = self.replacements.len() + 1;
+        self.replacements
+            .entry((field.to_string(), value.to_string()))
+            .or_insert_with(|| match field {
+                "emailAddress" => format!("user{}@example.com", next),
+                "displayName" => format!("User {}", next),
+                _ => format!("redacted-{}", next),
+            })
+            .clone()
+    }
+
+    fn redact(&mut self, value: &mut Value, site: &str) {
+        match value {
+            Value::Object(map) => {
+                map.remove("avatarUrls");
+                for (field, value) in map.iter_mut() {
+                    match value {
+                        Value::String(text) if PERSONAL_FIELDS.contains(&field.as_str()) => {
+                            *text = self.placeholder(field, text);
+                        }
+                        _ => self.redact(value, site),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact(item, site)),
+            Value::String(text) if text.contains(site) => {
+                *text = text.replace(site, "https://example.atlassian.net");
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Dev builds only: GETs `path` from the connected site, redacts personal
+/// data and saves it as `fixtures/jira/<variant>/<endpoint>[_<label>].json`.
+/// Returns the written path.
+#[tauri::command]
+pub async fn record_fixture(
+    variant: String,
+    endpoint: String,
+    label: Option<String>,
+    path: String,
+    state: State<'_, JiraState>,
+) -> Result<String, JiraError> {
+    if !cfg!(debug_assertions) {
+        return Err(JiraError::Validation("Fixtures can only be recorded in dev builds".to_string()));
+    }
+    if !VARIANTS.contains(&variant.as_str()) {
+        return Err(JiraError::Validation(format!("Unknown variant '{}', use cloud or server", variant)));
+    }
+    if !ENDPOINTS.contains(&endpoint.as_str()) {
+        return Err(JiraError::Validation(format!("No fixture type for endpoint '{}'", endpoint)));
+    }
+    let label = label.filter(|label| !label.is_empty());
+    if label
+        .as_deref()
+        .is_some_and(|label| !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+    {
+        return Err(JiraError::Validation("Labels may only contain letters, digits and '_'".to_string()));
+    }
+
+    let client = connected_client(&state).await?;
+    let mut payload = client.get_raw(&path).await?;
+    Redactor::default().redact(&mut payload, client.base_url.trim_end_matches('/'));
+
+    let file_name = match label {
+        Some(label) => format!("{}_{}.json", endpoint, label),
+        None => format!("{}.json", endpoint),
+    };
+    let dir = fixtures_dir().join(&variant);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let file = dir.join(file_name);
+    let json = serde_json::to_string_pretty(&payload)?;
+    std::fs::write(&file, json + "\n").map_err(|e| format!("Failed to write {}: {}", file.display(), e))?;
+    Ok(file.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jira_types::*;
+    use serde::de::DeserializeOwned;
+    use std::path::Path;
+
+    fn endpoint_of(file_stem: &str) -> Option<&'static str> {
+        ENDPOINTS
+            .into_iter()
+            .find(|endpoint| file_stem == *endpoint || file_stem.starts_with(&format!("{}_", endpoint)))
+    }
+
+    fn parse<T: DeserializeOwned>(path: &Path) -> T {
+        let json = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        serde_json::from_str(&json).unwrap_or_else(|e| panic!("{} does not deserialize: {}", path.display(), e))
+    }
+
+    fn fixture<T: DeserializeOwned>(variant: &str, name: &str) -> T {
+        parse(&fixtures_dir().join(variant).join(format!("{}.json", name)))
+    }
+
+    #[test]
+    fn every_fixture_deserializes() {
+        let mut checked = 0;
+        for variant in VARIANTS {
+            let dir = fixtures_dir().join(variant);
+            for entry in std::fs::read_dir(&dir).unwrap_or_else(|e| panic!("{}: {}", dir.display(), e)) {
+                let path = entry.unwrap().path();
+                if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                    continue;
+                }
+                let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+                match endpoint_of(stem) {
+                    Some("issue_worklogs") => drop(parse::<WorklogListResponse>(&path)),
+                    Some("create_worklog") => {
+                        parse::<WorklogResponse>(&path);
+                        parse::<Worklog>(&path);
+                    }
+                    Some("transitions") => drop(parse::<JiraTransitionsResponse>(&path)),
+                    Some("myself") => drop(parse::<JiraUser>(&path)),
+                    Some("search") => drop(parse::<JiraSearchResponse>(&path)),
+                    _ => panic!("{} is not named after a known endpoint", path.display()),
+                }
+                checked += 1;
+            }
+        }
+        assert!(checked > 0, "no fixtures found");
+    }
+
+    #[test]
+    fn every_endpoint_has_fixtures_for_every_variant() {
+        for variant in VARIANTS {
+            for endpoint in ENDPOINTS {
+                let path = fixtures_dir().join(variant).join(format!("{}.json", endpoint));
+                assert!(path.exists(), "missing {}", path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn cloud_comments_flatten_from_adf() {
+        let worklogs: WorklogListResponse = fixture("cloud", "issue_worklogs");
+        assert_eq!(worklogs.worklogs[0].comment_text(), "Reproduced the restart bug and wrote a fix");
+        assert_eq!(worklogs.worklogs[1].comment_text(), "");
+    }
+
+    #[test]
+    fn server_comments_are_plain_strings() {
+        let worklogs: WorklogListResponse = fixture("server", "issue_worklogs");
+        assert_eq!(worklogs.worklogs[0].comment_text(), "Renewed certificates and restarted ingress");
+    }
+
+    #[test]
+    fn server_users_are_identified_by_key() {
+        let myself: JiraUser = fixture("server", "myself");
+        let worklogs: WorklogListResponse = fixture("server", "issue_worklogs");
+        assert_eq!(myself.account_id, "JIRAUSER10100");
+        assert_eq!(worklogs.worklogs[0].author.as_ref().unwrap().account_id, myself.account_id);
+    }
+
+    #[test]
+    fn hidden_assignee_emails_are_empty() {
+        let search: JiraSearchResponse = fixture("cloud", "search");
+        assert_eq!(search.issues[1].fields.assignee.as_ref().unwrap().email_address, "");
+    }
+
+    #[test]
+    fn endpoint_is_taken_from_the_file_name_prefix() {
+        assert_eq!(endpoint_of("search"), Some("search"));
+        assert_eq!(endpoint_of("search_with_sprint"), Some("search"));
+        assert_eq!(endpoint_of("issue_worklogs_paged"), Some("issue_worklogs"));
+        assert_eq!(endpoint_of("searches"), None);
+    }
+
+    #[test]
+    fn redaction_replaces_people_consistently() {
+        let mut payload = serde_json::json!({
+            "self": "https://corp.atlassian.net/rest/api/3/issue/1",
+            "author": { "accountId": "abc", "displayName": "Real Name", "avatarUrls": {} },
+            "updateAuthor": { "accountId": "abc", "emailAddress": "real@corp.com" },
+        });
+        Redactor::default().redact(&mut payload, "https://corp.atlassian.net");
+
+        assert_eq!(payload["self"], "https://example.atlassian.net/rest/api/3/issue/1");
+        assert_eq!(payload["author"]["accountId"], payload["updateAuthor"]["accountId"]);
+        assert_ne!(payload["author"]["accountId"], "abc");
+        assert_ne!(payload["author"]["displayName"], "Real Name");
+        assert!(payload["author"].get("avatarUrls").is_none());
+        assert!(!payload.to_string().contains("real@corp.com"));
+    }
+}
@@ -0,0 +1,227 @@
+//! The focus list: the issues I mean to work on today, in my own order and
+//! independent of JIRA rank. It starts empty every day; the top item shows
+//! in the tray tooltip and is the suggestion when starting a timer.
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::jira_api::JiraClient;
+use crate::{clock, store, tray, vault};
+
+pub const FOCUS_FILE: &str = "focus_list.json";
+
+pub type FocusListState = Mutex<FocusList>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FocusItem {
+    pub issue_key: String,
+    pub summary: Option<String>,
+    pub added_at: DateTime<Local>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FocusList {
+    /// The day the list was made for.
+    date: Option<NaiveDate>,
+    items: Vec<FocusItem>,
+}
+
+impl FocusList {
+    /// The items for `today`; a list made on another day is dropped first.
+    fn for_day(&mut self, today: NaiveDate) -> &mut Vec<FocusItem> {
+        if self.date != Some(today) {
+            self.date = Some(today);
+            self.items.clear();
+        }
+        &mut self.items
+    }
+
+    fn add(&mut self, item: FocusItem, position: Option<usize>, today: NaiveDate) -> Result<(), String> {
+        let items = self.for_day(today);
+        if items.iter().any(|existing| existing.issue_key == item.issue_key) {
+            return Err(format!("{} is already on the focus list", item.issue_key));
+        }
+        let position = position.unwrap_or(items.len()).min(items.len());
+        items.insert(position, item);
+        Ok(())
+    }
+
+    fn remove(&mut self, issue_key: &str, today: NaiveDate) -> Result<(), String> {
+        let items = self.for_day(today);
+        let before = items.len();
+        items.retain(|item| item.issue_key != issue_key);
+        if items.len() == before {
+            return Err(format!("{} is not on the focus list", issue_key));
+        }
+        Ok(())
+    }
+
+    /// Reorders the list to `issue_keys`, which must name every item once.
+    fn reorder(&mut self, issue_keys: &[String], today: NaiveDate) -> Result<(), String> {
+        let items = self.for_day(today);
+        if issue_keys.len() != items.len() {
+            return Err("The new order must list every focus item once".to_string());
+        }
+
+        let mut reordered: Vec<FocusItem> = Vec::with_capacity(items.len());
+        for issue_key in issue_keys {
+            if reordered.iter().any(|item| &item.issue_key == issue_key) {
+                return Err(format!("{} is listed twice", issue_key));
+            }
+            let item = items
+                .iter()
+                .find(|item| &item.issue_key == issue_key)
+                .ok_or_else(|| format!("{} is not on the focus list", issue_key))?;
+            reordered.push(item.clone());
+        }
+        *items = reordered;
+        Ok(())
+    }
+}
+
+pub fn load(app_handle: &AppHandle<Wry>) -> FocusList {
+    match store::data_file(app_handle, FOCUS_FILE) {
+        Ok(path) => vault::load(app_handle, &path),
+        Err(_) => FocusList::default(),
+    }
+}
+
+/// Reads the list again once the vault is unlocked.
+pub fn reload(app_handle: &AppHandle<Wry>) {
+    let list = load(app_handle);
+    let state = app_handle.state::<FocusListState>();
+    *state.lock().unwrap_or_else(|e| e.into_inner()) = list;
+    tray::show_focus(app_handle, top(app_handle).as_ref());
+}
+
+fn persist(app_handle: &AppHandle<Wry>, list: &FocusList) -> Result<(), String> {
+    let path = store::data_file(app_handle, FOCUS_FILE)?;
+    vault::save(app_handle, &path, list)
+}
+
+/// Today's first focus item.
+pub fn top(app_handle: &AppHandle<Wry>) -> Option<FocusItem> {
+    let today = clock::now(app_handle).date_naive();
+    let state = app_handle.state::<FocusListState>();
+    let mut list = state.lock().unwrap_or_else(|e| e.into_inner());
+    list.for_day(today).first().cloned()
+}
+
+/// Today's focus items, in order.
+pub fn today(app_handle: &AppHandle<Wry>) -> Vec<FocusItem> {
+    let today = clock::now(app_handle).date_naive();
+    let state = app_handle.state::<FocusListState>();
+    let mut list = state.lock().unwrap_or_else(|e| e.into_inner());
+    list.for_day(today).clone()
+}
+
+/// Applies `change` to today's list, saves it and updates the tray.
+fn update(
+    app_handle: &AppHandle<Wry>,
+    change: impl FnOnce(&mut FocusList, NaiveDate) -> Result<(), String>,
+) -> Result<Vec<FocusItem>, String> {
+    let today = clock::now(app_handle).date_naive();
+    let state = app_handle.state::<FocusListState>();
+    let mut list = state.lock().map_err(|e| e.to_string())?;
+    change(&mut list, today)?;
+    persist(app_handle, &list)?;
+    let items = list.for_day(today).clone();
+    drop(list);
+
+    tray::show_focus(app_handle, items.first());
+    Ok(items)
+}
+
+#[tauri::command]
+pub async fn get_focus_list(app_handle: AppHandle<Wry>, state: State<'_, FocusListState>) -> Result<Vec<FocusItem>, String> {
+    let today = clock::now(&app_handle).date_naive();
+    let mut list = state.lock().map_err(|e| e.to_string())?;
+    Ok(list.for_day(today).clone())
+}
+
+/// Adds `issue_key` at `position`, or at the end.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn add_to_focus_list(
+    issue_key: String,
+    summary: Option<String>,
+    position: Option<usize>,
+    app_handle: AppHandle<Wry>,
+) -> Result<Vec<FocusItem>, String> {
+    let issue_key = issue_key.trim().to_uppercase();
+    if !JiraClient::is_valid_issue_key(&issue_key) {
+        return Err(format!("Invalid issue key: {}", issue_key));
+    }
+    let item = FocusItem {
+        issue_key,
+        summary: summary.filter(|summary| !summary.trim().is_empty()),
+        added_at: clock::now(&app_handle),
+    };
+    update(&app_handle, |list, today| list.add(item, position, today))
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn remove_from_focus_list(issue_key: String, app_handle: AppHandle<Wry>) -> Result<Vec<FocusItem>, String> {
+    update(&app_handle, |list, today| list.remove(&issue_key, today))
+}
+
+/// Puts the focus list in the order of `issue_keys`.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn reorder_focus_list(issue_keys: Vec<String>, app_handle: AppHandle<Wry>) -> Result<Vec<FocusItem>, String> {
+    update(&app_handle, |list, today| list.reorder(&issue_keys, today))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, day).unwrap()
+    }
+
+    fn item(issue_key: &str) -> FocusItem {
+        FocusItem {
+            issue_key: issue_key.to_string(),
+            summary: None,
+            added_at: Local::now(),
+        }
+    }
+
+    fn keys(list: &mut FocusList, today: NaiveDate) -> Vec<String> {
+        list.for_day(today).iter().map(|item| item.issue_key.clone()).collect()
+    }
+
+    #[test]
+    fn starts_over_every_day() {
+        let mut list = FocusList::default();
+        list.add(item("APP-1"), None, day(7)).unwrap();
+
+        assert_eq!(keys(&mut list, day(7)), ["APP-1"]);
+        assert!(keys(&mut list, day(8)).is_empty());
+    }
+
+    #[test]
+    fn reorders_to_the_given_keys() {
+        let mut list = FocusList::default();
+        for key in ["APP-1", "APP-2", "APP-3"] {
+            list.add(item(key), None, day(7)).unwrap();
+        }
+        let order = ["APP-3".to_string(), "APP-1".to_string(), "APP-2".to_string()];
+        list.reorder(&order, day(7)).unwrap();
+
+        assert_eq!(keys(&mut list, day(7)), order);
+    }
+
+    #[test]
+    fn rejects_an_incomplete_order_without_losing_items() {
+        let mut list = FocusList::default();
+        list.add(item("APP-1"), None, day(7)).unwrap();
+        list.add(item("APP-2"), None, day(7)).unwrap();
+
+        assert!(list.reorder(&["APP-1".to_string()], day(7)).is_err());
+        assert!(list.reorder(&["APP-1".to_string(), "APP-9".to_string()], day(7)).is_err());
+        assert_eq!(keys(&mut list, day(7)).len(), 2);
+    }
+}
@@ -0,0 +1,204 @@
+//! Daily and weekly time targets. Towards the end of a workday the logged
+//! time is checked now and then; while it's short of the daily target the
+//! reminder names the missing amount, and gets more insistent each time.
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::settings::GoalSettings;
+use crate::{clock, client_for, connected_client, notification_actions, settings, summary, tray, JiraState};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalPeriod {
+    Day,
+    Week,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalProgress {
+    pub period: GoalPeriod,
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub target_seconds: u32,
+    pub logged_seconds: u32,
+    pub missing_seconds: u32,
+    /// Of the target, capped at 100; 100 without a target.
+    pub percent: u8,
+}
+
+impl GoalProgress {
+    fn new(period: GoalPeriod, from_date: NaiveDate, to_date: NaiveDate, target_seconds: u32, logged_seconds: u32) -> Self {
+        let percent = match target_seconds {
+            0 => 100,
+            target => (logged_seconds as u64 * 100 / target as u64).min(100) as u8,
+        };
+        Self {
+            period,
+            from_date,
+            to_date,
+            target_seconds,
+            logged_seconds,
+            missing_seconds: target_seconds.saturating_sub(logged_seconds),
+            percent,
+        }
+    }
+}
+
+/// The last warning, so each level fires once a day.
+pub type GoalState = Mutex<Option<(NaiveDate, u32)>>;
+
+/// Monday to Sunday around `date`.
+fn week_of(date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let monday = date - Days::new(date.weekday().num_days_from_monday() as u64);
+    (monday, monday + Days::new(6))
+}
+
+/// How insistent the warning at `now` is: 1 from `warn_at`, one more every
+/// `repeat_minutes`; `None` outside the warning hours and off workdays.
+fn warning_level(goals: &GoalSettings, now: DateTime<Local>) -> Option<u32> {
+    let time = now.time();
+    if !summary::is_workday(now.date_naive()) || time < goals.warn_at || time >= goals.stop_at {
+        return None;
+    }
+    let minutes = (time - goals.warn_at).num_minutes().max(0) as u32;
+    Some(1 + minutes / goals.repeat_minutes)
+}
+
+async fn progress(
+    app_handle: &AppHandle<Wry>,
+    client: JiraClient,
+    period: GoalPeriod,
+    date: NaiveDate,
+) -> Result<GoalProgress, JiraError> {
+    let (from, to) = match period {
+        GoalPeriod::Day => (date, date),
+        GoalPeriod::Week => week_of(date),
+    };
+    let summary = summary::fetch(app_handle, client, from, to).await?;
+    let target = match period {
+        GoalPeriod::Day => summary.target_seconds,
+        GoalPeriod::Week => (settings::current(app_handle).goals.weekly_target_hours * 3600.0) as u32,
+    };
+    Ok(GoalProgress::new(period, from, to, target, summary.total_seconds))
+}
+
+fn warning_body(app_handle: &AppHandle<Wry>, today: &GoalProgress, week: Option<&GoalProgress>) -> String {
+    let units = settings::current(app_handle).time_units;
+    let format = |seconds: u32| JiraClient::format_seconds_to_jira_duration(seconds, &units);
+    let mut body = format!(
+        "{} missing from today's {} target.",
+        format(today.missing_seconds),
+        format(today.target_seconds)
+    );
+    if let Some(week) = week.filter(|week| week.missing_seconds > 0) {
+        body.push_str(&format!(" {} of {} logged this week.", format(week.logged_seconds), format(week.target_seconds)));
+    }
+    body
+}
+
+/// Warns when the level at `now` is new today and today is short of its
+/// target.
+async fn check(app_handle: &AppHandle<Wry>, now: DateTime<Local>) {
+    let goals = settings::current(app_handle).goals;
+    let Some(level) = warning_level(&goals, now) else {
+        return;
+    };
+    let today = now.date_naive();
+    let state = app_handle.state::<GoalState>();
+    let already_warned = state
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_some_and(|(date, warned)| date == today && warned >= level);
+    if already_warned {
+        return;
+    }
+    let Ok(client) = connected_client(&app_handle.state::<JiraState>()).await else {
+        return;
+    };
+
+    let day = match progress(app_handle, client.clone(), GoalPeriod::Day, today).await {
+        Ok(day) => day,
+        Err(e) => {
+            tracing::warn!("Failed to check today's goal: {}", e);
+            return;
+        }
+    };
+    *state.lock().unwrap_or_else(|e| e.into_inner()) = Some((today, level));
+    if day.missing_seconds == 0 {
+        return;
+    }
+    let week = progress(app_handle, client, GoalPeriod::Week, today).await.ok();
+
+    let body = warning_body(app_handle, &day, week.as_ref());
+    let title = if level == 1 { "Under today's target" } else { "Still under today's target" };
+    notification_actions::show_worklog_reminder(app_handle, title, &body);
+    if level > 1 && goals.escalate {
+        tray::start_flashing(app_handle);
+    }
+}
+
+pub async fn start_goal_monitor(app_handle: AppHandle<Wry>) {
+    let mut ticker = interval(CHECK_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        check(&app_handle, clock::now(&app_handle)).await;
+    }
+}
+
+/// Logged time against the target of the day or week around `date`
+/// (today by default).
+#[tauri::command]
+pub async fn get_progress(
+    period: GoalPeriod,
+    date: Option<NaiveDate>,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<GoalProgress, JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    let date = date.unwrap_or_else(|| clock::now(&app_handle).date_naive());
+    progress(&app_handle, client, period, date).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        // June 2024: the 7th is a Friday.
+        Local.with_ymd_and_hms(2024, 6, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn escalates_while_the_day_goes_on() {
+        let goals = GoalSettings::default();
+        assert_eq!(warning_level(&goals, at(7, 16, 29)), None);
+        assert_eq!(warning_level(&goals, at(7, 16, 30)), Some(1));
+        assert_eq!(warning_level(&goals, at(7, 17, 29)), Some(1));
+        assert_eq!(warning_level(&goals, at(7, 17, 30)), Some(2));
+        assert_eq!(warning_level(&goals, at(7, 20, 0)), None);
+        assert_eq!(warning_level(&goals, at(8, 17, 0)), None);
+    }
+
+    #[test]
+    fn measures_against_the_target() {
+        let day = NaiveDate::from_ymd_opt(2024, 6, 7).unwrap();
+        let progress = GoalProgress::new(GoalPeriod::Day, day, day, 8 * 3600, 6 * 3600);
+        assert_eq!((progress.missing_seconds, progress.percent), (2 * 3600, 75));
+
+        let over = GoalProgress::new(GoalPeriod::Day, day, day, 8 * 3600, 9 * 3600);
+        assert_eq!((over.missing_seconds, over.percent), (0, 100));
+        assert_eq!(week_of(day), (NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(), NaiveDate::from_ymd_opt(2024, 6, 9).unwrap()));
+    }
+}
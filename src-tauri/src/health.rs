@@ -0,0 +1,207 @@
+//! Background check of the active connection. Tokens get revoked and VPNs
+//! drop while the app sits in the tray; pinging `/myself` now and then
+//! notices before the next command fails, and the tray shows it.
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+use tokio::sync::Notify;
+use tokio::time::Duration;
+
+use crate::connections::ConnectionInfo;
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::notifications::NotificationKind;
+use crate::{connected_client, notifications, offline, state, tray, JiraState};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Checked more often while the connection is down, to notice it's back.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// Rejected credentials only fix themselves rarely, and Server puts logins
+/// behind a CAPTCHA after repeated failures.
+const UNAUTHORIZED_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// Slower answers than this count as degraded.
+const SLOW_RESPONSE: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionStatus {
+    /// Nothing to check yet.
+    #[default]
+    NotConnected,
+    Connected,
+    /// Reachable, but slow, overloaded or rate limited.
+    Degraded,
+    /// The token was revoked or the session expired; sign in again.
+    Unauthorized,
+    /// JIRA can't be reached.
+    Offline,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConnectionHealth {
+    pub status: ConnectionStatus,
+    pub checked_at: Option<DateTime<Local>>,
+    pub latency_ms: Option<u64>,
+    /// Why the last check failed.
+    pub message: Option<String>,
+}
+
+/// Sent as `auth-required` once per rejected login, for the frontend to ask
+/// for a new one.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthRequired {
+    pub connection: ConnectionInfo,
+    pub message: String,
+}
+
+#[derive(Default)]
+pub struct HealthState {
+    health: Mutex<ConnectionHealth>,
+    /// Cuts the wait short, e.g. after signing in again.
+    wake: Notify,
+}
+
+pub fn status(app_handle: &AppHandle<Wry>) -> ConnectionStatus {
+    let state = app_handle.state::<HealthState>();
+    let health = state.health.lock().unwrap_or_else(|e| e.into_inner());
+    health.status
+}
+
+/// Checks the connection again right away, e.g. because it was replaced.
+pub fn recheck(app_handle: &AppHandle<Wry>) {
+    app_handle.state::<HealthState>().wake.notify_one();
+}
+
+fn status_of(error: &JiraError) -> ConnectionStatus {
+    match error {
+        JiraError::NotConnected => ConnectionStatus::NotConnected,
+        JiraError::Unauthorized { .. } => ConnectionStatus::Unauthorized,
+        JiraError::Network(_) | JiraError::Offline => ConnectionStatus::Offline,
+        _ => ConnectionStatus::Degraded,
+    }
+}
+
+/// Called by `JiraClient` the first time JIRA rejects a connection's
+/// credentials. The connection and its cached data are kept, and queued
+/// worklogs wait, until it is replaced by signing in again. Clients that
+/// aren't registered, such as one checking a login, are left to their
+/// caller. The hook runs inside the request, so the connection is looked
+/// up on a task of its own.
+pub fn auth_required(app_handle: &AppHandle<Wry>, client: &JiraClient, error: &JiraError) {
+    let app_handle = app_handle.clone();
+    let client = client.clone();
+    let message = error.to_string();
+    tauri::async_runtime::spawn(async move {
+        let connection = state::connections(&app_handle.state::<JiraState>()).await.info_of(&client);
+        if let Some(connection) = connection {
+            announce_auth_required(&app_handle, connection, message);
+        }
+    });
+}
+
+fn announce_auth_required(app_handle: &AppHandle<Wry>, connection: ConnectionInfo, message: String) {
+
+    if connection.active {
+        record(
+            app_handle,
+            ConnectionHealth {
+                status: ConnectionStatus::Unauthorized,
+                checked_at: Some(Local::now()),
+                latency_ms: None,
+                message: Some(message.clone()),
+            },
+        );
+    }
+    let required = AuthRequired { connection, message };
+    if let Err(e) = app_handle.emit("auth-required", &required) {
+        tracing::warn!("Failed to emit auth-required event: {}", e);
+    }
+}
+
+/// Pings the active connection once and records the result. While working
+/// offline by hand nothing is sent.
+pub async fn check(app_handle: &AppHandle<Wry>) -> ConnectionHealth {
+    let health = match connected_client(&app_handle.state::<JiraState>()).await {
+        Err(_) => {
+            offline::set_detected(app_handle, false);
+            ConnectionHealth::default()
+        }
+        Ok(_) if offline::state(app_handle).manual => ConnectionHealth {
+            status: ConnectionStatus::Offline,
+            checked_at: Some(Local::now()),
+            latency_ms: None,
+            message: Some("Working offline".to_string()),
+        },
+        Ok(client) => {
+            let started = Instant::now();
+            let result = client.probing().get_myself().await;
+            offline::set_detected(app_handle, matches!(&result, Err(e) if e.is_connectivity()));
+            let latency = started.elapsed();
+            let (status, message) = match result {
+                Ok(_) if latency >= SLOW_RESPONSE => (ConnectionStatus::Degraded, Some("JIRA is answering slowly".to_string())),
+                Ok(_) => (ConnectionStatus::Connected, None),
+                Err(e) => (status_of(&e), Some(e.to_string())),
+            };
+            ConnectionHealth {
+                status,
+                checked_at: Some(Local::now()),
+                latency_ms: Some(latency.as_millis() as u64),
+                message,
+            }
+        }
+    };
+
+    record(app_handle, health.clone());
+    health
+}
+
+/// Stores `health` and, when the status changed, tells the frontend and
+/// the tray.
+fn record(app_handle: &AppHandle<Wry>, health: ConnectionHealth) {
+    let previous = {
+        let state = app_handle.state::<HealthState>();
+        let mut current = state.health.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::replace(&mut *current, health.clone()).status
+    };
+    if previous == health.status {
+        return;
+    }
+
+    if let Err(e) = app_handle.emit("connection-status-changed", &health) {
+        tracing::warn!("Failed to emit connection-status-changed event: {}", e);
+    }
+    tray::show_connection_status(app_handle, health.status);
+    if health.status == ConnectionStatus::Unauthorized {
+        notifications::notify(
+            app_handle,
+            NotificationKind::Sync,
+            "JIRA session expired",
+            "Sign in again to keep logging work.",
+        );
+    }
+}
+
+pub async fn start_health_monitor(app_handle: AppHandle<Wry>) {
+    loop {
+        let health = check(&app_handle).await;
+        let wait = match health.status {
+            ConnectionStatus::Connected | ConnectionStatus::NotConnected => CHECK_INTERVAL,
+            ConnectionStatus::Unauthorized => UNAUTHORIZED_INTERVAL,
+            ConnectionStatus::Degraded | ConnectionStatus::Offline => RECHECK_INTERVAL,
+        };
+        let state = app_handle.state::<HealthState>();
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = state.wake.notified() => {}
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_connection_status(state: State<'_, HealthState>) -> Result<ConnectionHealth, String> {
+    let health = state.health.lock().map_err(|e| e.to_string())?;
+    Ok(health.clone())
+}
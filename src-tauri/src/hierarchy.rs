@@ -0,0 +1,227 @@
+//! Rolling report time up the issue hierarchy, for reports that count time
+//! per story or epic rather than per ticket: sub-tasks go to their parent,
+//! and with `epics` on up to the top of the `parent` chain. Stories only
+//! name their epic as `parent` on Cloud; on Server the chain ends at the
+//! story. Parents are looked up once per issue and kept in the cache
+//! database.
+
+use chrono::{Duration, Local};
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use tauri::{AppHandle, Manager, Wry};
+use tokio::task::JoinSet;
+
+use crate::jira_api::{field_sets, JiraClient};
+use crate::jira_types::UserWorklog;
+use crate::storage::{self, Storage};
+
+/// Looked-up parents older than this are fetched again; until that
+/// succeeds the old one is still used.
+const MAX_AGE_DAYS: i64 = 7;
+/// Issues fetched for one report; the rest are reported as they are.
+const MAX_LOOKUPS: usize = 200;
+const MAX_CONCURRENT: usize = 4;
+/// Sub-task, story, epic and the levels Premium adds above.
+const MAX_DEPTH: usize = 5;
+
+/// Which issue a report counts time under.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportRollup {
+    /// The issue it was logged on.
+    #[default]
+    None,
+    /// Sub-tasks count towards their parent.
+    Subtasks,
+    /// Everything counts towards the top of its parent chain.
+    Epics,
+}
+
+/// Where one issue sits in the hierarchy.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IssueLink {
+    pub subtask: bool,
+    pub parent_key: Option<String>,
+    pub parent_summary: String,
+}
+
+/// The issue `issue_key`'s time is reported under, if not its own.
+fn target<'a>(issue_key: &str, links: &'a HashMap<String, IssueLink>, rollup: ReportRollup) -> Option<(&'a str, &'a str)> {
+    let parent = |link: &'a IssueLink| link.parent_key.as_deref().map(|key| (key, link.parent_summary.as_str()));
+    match rollup {
+        ReportRollup::None => None,
+        ReportRollup::Subtasks => links.get(issue_key).filter(|link| link.subtask).and_then(parent),
+        ReportRollup::Epics => {
+            let mut top = None;
+            let mut current = issue_key;
+            for _ in 0..MAX_DEPTH {
+                let Some(next) = links.get(current).and_then(parent) else {
+                    break;
+                };
+                top = Some(next);
+                current = next.0;
+            }
+            top
+        }
+    }
+}
+
+/// Moves each worklog onto the issue it is reported under.
+pub fn roll_up(worklogs: &mut [UserWorklog], links: &HashMap<String, IssueLink>, rollup: ReportRollup) {
+    for worklog in worklogs {
+        if let Some((key, summary)) = target(&worklog.issue_key, links, rollup) {
+            worklog.issue_key = key.to_string();
+            worklog.issue_summary = summary.to_string();
+        }
+    }
+}
+
+/// Looks up `issue_keys` a few at a time.
+async fn fetch(client: &JiraClient, issue_keys: Vec<String>) -> Vec<(String, IssueLink)> {
+    let mut pending = issue_keys.into_iter();
+    let mut tasks = JoinSet::new();
+    let mut fetched = Vec::new();
+    loop {
+        while tasks.len() < MAX_CONCURRENT {
+            let Some(issue_key) = pending.next() else {
+                break;
+            };
+            let client = client.clone();
+            tasks.spawn(async move {
+                let issue = client.get_issue(&issue_key, field_sets::HIERARCHY, &[]).await;
+                (issue_key, issue)
+            });
+        }
+
+        let Some(joined) = tasks.join_next().await else {
+            break;
+        };
+        match joined {
+            Ok((issue_key, Ok(issue))) => {
+                let link = IssueLink {
+                    subtask: issue.fields.issue_type.is_some_and(|kind| kind.subtask),
+                    parent_key: issue.fields.parent.as_ref().map(|parent| parent.key.clone()),
+                    parent_summary: issue.fields.parent.map(|parent| parent.fields.summary).unwrap_or_default(),
+                };
+                fetched.push((issue_key, link));
+            }
+            Ok((issue_key, Err(e))) => tracing::warn!("Failed to look up the parent of {}: {}", issue_key, e),
+            Err(e) => tracing::warn!("A parent lookup failed to finish: {}", e),
+        }
+    }
+    fetched
+}
+
+/// Links of `issue_keys` and, for `epics`, of their ancestors, from the
+/// cache or JIRA. Issues that can't be looked up are left out.
+async fn resolve(
+    app_handle: &AppHandle<Wry>,
+    client: &JiraClient,
+    issue_keys: BTreeSet<String>,
+    rollup: ReportRollup,
+) -> HashMap<String, IssueLink> {
+    let account = storage::account(client);
+    let storage = app_handle.state::<Storage>();
+    let cached = storage.load_parents(&account);
+    let expired = Local::now() - Duration::days(MAX_AGE_DAYS);
+
+    let mut links = HashMap::new();
+    let mut wanted = issue_keys;
+    let mut lookups = 0;
+    for _ in 0..MAX_DEPTH {
+        let mut missing = Vec::new();
+        for key in &wanted {
+            match cached.get(key) {
+                Some(link) => {
+                    links.insert(key.clone(), link.value.clone());
+                    if link.fetched_at < expired {
+                        missing.push(key.clone());
+                    }
+                }
+                None => missing.push(key.clone()),
+            }
+        }
+        missing.truncate(MAX_LOOKUPS - lookups);
+        lookups += missing.len();
+
+        let fetched = fetch(client, missing).await;
+        if !fetched.is_empty() {
+            if let Err(e) = storage.save_parents(&account, &fetched, Local::now()) {
+                tracing::warn!("Failed to cache issue parents: {}", e);
+            }
+        }
+        links.extend(fetched);
+
+        if rollup != ReportRollup::Epics {
+            break;
+        }
+        wanted = wanted
+            .iter()
+            .filter_map(|key| links.get(key)?.parent_key.clone())
+            .filter(|key| !links.contains_key(key))
+            .collect();
+        if wanted.is_empty() {
+            break;
+        }
+    }
+    links
+}
+
+/// Rolls `worklogs` up for a report; see `roll_up`.
+pub async fn apply(app_handle: &AppHandle<Wry>, client: &JiraClient, worklogs: &mut [UserWorklog], rollup: ReportRollup) {
+    if rollup == ReportRollup::None {
+        return;
+    }
+    let issue_keys = worklogs.iter().map(|worklog| worklog.issue_key.clone()).collect();
+    let links = resolve(app_handle, client, issue_keys, rollup).await;
+    roll_up(worklogs, &links, rollup);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(subtask: bool, parent: &str) -> IssueLink {
+        IssueLink {
+            subtask,
+            parent_key: Some(parent.to_string()),
+            parent_summary: format!("{} summary", parent),
+        }
+    }
+
+    fn worklog(issue_key: &str) -> UserWorklog {
+        serde_json::from_value(serde_json::json!({
+            "issue_key": issue_key,
+            "issue_summary": "",
+            "worklog": {
+                "id": "1",
+                "issueId": "10001",
+                "timeSpentSeconds": 3600,
+                "started": "2024-06-03T09:00:00.000+0000",
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn rolls_time_up_to_stories_or_epics() {
+        let links = HashMap::from([
+            ("APP-3".to_string(), link(true, "APP-2")),
+            ("APP-2".to_string(), link(false, "APP-1")),
+            ("APP-1".to_string(), IssueLink::default()),
+        ]);
+        let keys = |rollup| {
+            let mut worklogs = vec![worklog("APP-3"), worklog("APP-2"), worklog("OPS-9")];
+            roll_up(&mut worklogs, &links, rollup);
+            worklogs.into_iter().map(|worklog| worklog.issue_key).collect::<Vec<_>>()
+        };
+
+        assert_eq!(keys(ReportRollup::None), ["APP-3", "APP-2", "OPS-9"]);
+        assert_eq!(keys(ReportRollup::Subtasks), ["APP-2", "APP-2", "OPS-9"]);
+        assert_eq!(keys(ReportRollup::Epics), ["APP-1", "APP-1", "OPS-9"]);
+
+        let mut worklogs = vec![worklog("APP-3")];
+        roll_up(&mut worklogs, &links, ReportRollup::Subtasks);
+        assert_eq!(worklogs[0].issue_summary, "APP-2 summary");
+    }
+}
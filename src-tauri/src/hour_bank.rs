@@ -0,0 +1,256 @@
+//! The hour bank: time logged over or under the contract, added up week by
+//! week and month by month from `hour_bank.start_date` on, for employers
+//! that let overtime be banked. Contract hours are spread over Monday to
+//! Friday. With `weekly_digest` on, Monday morning brings a notification
+//! with last week and the balance.
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, NaiveTime, Weekday};
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::notifications::{self, NotificationKind};
+use crate::settings::{HourBankSettings, TimeUnitSettings};
+use crate::{clock, client_for, connected_client, settings, summary, JiraState};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// Fetched a quarter at a time, well within what one summary covers.
+const CHUNK_DAYS: u64 = 92;
+/// Longest the bank runs; older balances go into the opening balance.
+const MAX_BANK_DAYS: i64 = 3 * 366;
+
+/// The digest is sent from then on Mondays.
+fn digest_at() -> NaiveTime {
+    NaiveTime::from_hms_opt(9, 0, 0).unwrap()
+}
+
+/// The Monday the last digest was sent for.
+pub type DigestState = Mutex<Option<NaiveDate>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BankPeriod {
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub logged_seconds: u32,
+    pub contract_seconds: u32,
+    /// Logged minus contract.
+    pub difference_seconds: i64,
+    /// The bank at the end of the period.
+    pub balance_seconds: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HourBank {
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub opening_balance_seconds: i64,
+    pub balance_seconds: i64,
+    /// Monday to Sunday, the first and last cut to the range.
+    pub weeks: Vec<BankPeriod>,
+    pub months: Vec<BankPeriod>,
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Days::new(date.weekday().num_days_from_monday() as u64)
+}
+
+fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// Adds `days` (logged seconds per day, in order) up into periods that
+/// start where `period_start` says, the balance running on from `opening`.
+fn tally(days: &[(NaiveDate, u32)], daily_contract: u32, opening: i64, period_start: fn(NaiveDate) -> NaiveDate) -> Vec<BankPeriod> {
+    let mut periods: Vec<BankPeriod> = Vec::new();
+    let mut balance = opening;
+    for &(date, logged) in days {
+        let contract = if summary::is_workday(date) { daily_contract } else { 0 };
+        balance += logged as i64 - contract as i64;
+        match periods.last_mut() {
+            Some(period) if period_start(period.from_date) == period_start(date) => {
+                period.to_date = date;
+                period.logged_seconds += logged;
+                period.contract_seconds += contract;
+            }
+            _ => periods.push(BankPeriod {
+                from_date: date,
+                to_date: date,
+                logged_seconds: logged,
+                contract_seconds: contract,
+                difference_seconds: 0,
+                balance_seconds: 0,
+            }),
+        }
+        if let Some(period) = periods.last_mut() {
+            period.difference_seconds = period.logged_seconds as i64 - period.contract_seconds as i64;
+            period.balance_seconds = balance;
+        }
+    }
+    periods
+}
+
+fn compute(days: &[(NaiveDate, u32)], settings: &HourBankSettings, from: NaiveDate, to: NaiveDate) -> HourBank {
+    let daily_contract = (settings.contract_hours_per_week * 3600.0 / 5.0).round() as u32;
+    let opening = settings.opening_balance_minutes * 60;
+    let weeks = tally(days, daily_contract, opening, week_start);
+    HourBank {
+        from_date: from,
+        to_date: to,
+        opening_balance_seconds: opening,
+        balance_seconds: weeks.last().map_or(opening, |week| week.balance_seconds),
+        months: tally(days, daily_contract, opening, month_start),
+        weeks,
+    }
+}
+
+/// The bank from the start date through `to`.
+async fn fetch(app_handle: &AppHandle<Wry>, client: JiraClient, to: NaiveDate) -> Result<HourBank, JiraError> {
+    let settings = settings::effective(app_handle).hour_bank;
+    let from = settings
+        .start_date
+        .unwrap_or_else(|| NaiveDate::from_ymd_opt(to.year(), 1, 1).unwrap_or(to));
+    if from > to {
+        return Err(JiraError::Validation(format!("The hour bank starts on {}, after {}", from, to)));
+    }
+    if (to - from).num_days() >= MAX_BANK_DAYS {
+        return Err(JiraError::Validation(
+            "The hour bank can cover at most three years; move its start date and carry the rest over".to_string(),
+        ));
+    }
+
+    let mut days = Vec::new();
+    let mut chunk_from = from;
+    while chunk_from <= to {
+        let chunk_to = (chunk_from + Days::new(CHUNK_DAYS - 1)).min(to);
+        let summary = summary::fetch(app_handle, client.clone(), chunk_from, chunk_to).await?;
+        days.extend(summary.days.iter().map(|day| (day.date, day.seconds)));
+        chunk_from = chunk_to + Days::new(1);
+    }
+    Ok(compute(&days, &settings, from, to))
+}
+
+/// E.g. "+5h 30m", "-2h", or "even".
+fn signed(seconds: i64, units: &TimeUnitSettings) -> String {
+    let amount = JiraClient::format_seconds_to_jira_duration(seconds.unsigned_abs().min(u32::MAX as u64) as u32, units);
+    match seconds {
+        _ if amount == "0m" => "even".to_string(),
+        seconds if seconds < 0 => format!("-{}", amount),
+        _ => format!("+{}", amount),
+    }
+}
+
+fn digest_body(bank: &HourBank, units: &TimeUnitSettings) -> String {
+    let format = |seconds: u32| JiraClient::format_seconds_to_jira_duration(seconds, units);
+    let mut body = String::new();
+    if let Some(week) = bank.weeks.last() {
+        body.push_str(&format!(
+            "Last week: {} of {} logged ({}). ",
+            format(week.logged_seconds),
+            format(week.contract_seconds),
+            signed(week.difference_seconds, units)
+        ));
+    }
+    body.push_str(&format!("Hour bank: {}.", signed(bank.balance_seconds, units)));
+    body
+}
+
+/// Sends last week's digest once on Monday from `digest_at` on.
+async fn check(app_handle: &AppHandle<Wry>, now: DateTime<Local>) {
+    if !settings::current(app_handle).hour_bank.weekly_digest {
+        return;
+    }
+    let today = now.date_naive();
+    if today.weekday() != Weekday::Mon || now.time() < digest_at() {
+        return;
+    }
+    let state = app_handle.state::<DigestState>();
+    if *state.lock().unwrap_or_else(|e| e.into_inner()) == Some(today) {
+        return;
+    }
+    let Ok(client) = connected_client(&app_handle.state::<JiraState>()).await else {
+        return;
+    };
+
+    let sunday = today - Days::new(1);
+    let bank = match fetch(app_handle, client, sunday).await {
+        Ok(bank) => bank,
+        Err(e) => {
+            tracing::warn!("Failed to add up the hour bank for the weekly digest: {}", e);
+            return;
+        }
+    };
+    *state.lock().unwrap_or_else(|e| e.into_inner()) = Some(today);
+    let units = settings::current(app_handle).time_units;
+    notifications::notify(app_handle, NotificationKind::Reminder, "Weekly Digest", &digest_body(&bank, &units));
+}
+
+pub async fn start_digest_monitor(app_handle: AppHandle<Wry>) {
+    let mut ticker = interval(CHECK_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        check(&app_handle, clock::now(&app_handle)).await;
+    }
+}
+
+/// The hour bank through `to_date` (today by default, with today's
+/// contract counted in full).
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_hour_bank(
+    to_date: Option<NaiveDate>,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<HourBank, JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    let to = to_date.unwrap_or_else(|| clock::now(&app_handle).date_naive());
+    fetch(&app_handle, client, to).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    #[test]
+    fn banks_by_week_and_month() {
+        // Friday May 31st to Sunday June 9th, 9h on workdays and 2h on the
+        // Saturday, against 8h a day with 1h carried over.
+        let days: Vec<(NaiveDate, u32)> = date(5, 31)
+            .iter_days()
+            .take_while(|day| *day <= date(6, 9))
+            .map(|day| {
+                let logged = match day.weekday() {
+                    Weekday::Sat if day.day() == 1 => 2 * 3600,
+                    Weekday::Sat | Weekday::Sun => 0,
+                    _ => 9 * 3600,
+                };
+                (day, logged)
+            })
+            .collect();
+        let settings = HourBankSettings {
+            opening_balance_minutes: 60,
+            ..Default::default()
+        };
+        let bank = compute(&days, &settings, date(5, 31), date(6, 9));
+
+        let weeks: Vec<_> = bank.weeks.iter().map(|week| (week.from_date, week.difference_seconds, week.balance_seconds)).collect();
+        assert_eq!(
+            weeks,
+            [
+                (date(5, 31), 3 * 3600, 4 * 3600),
+                (date(6, 3), 5 * 3600, 9 * 3600),
+            ]
+        );
+        let months: Vec<_> = bank.months.iter().map(|month| (month.to_date, month.balance_seconds)).collect();
+        assert_eq!(months, [(date(5, 31), 2 * 3600), (date(6, 9), 9 * 3600)]);
+        assert_eq!(bank.balance_seconds, 9 * 3600);
+    }
+}
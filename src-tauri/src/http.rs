@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::error::JiraError;
+use crate::settings::{HttpSettings, HttpVersionPreference, ProxyMode, RetrySettings};
+
+const USER_AGENT: &str = concat!("mini-jira-app/", env!("CARGO_PKG_VERSION"));
+
+/// Whole-request timeout of attachment transfers, which can be large.
+pub const TRANSFER_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Certificate checks for one JIRA connection.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsOptions {
+    /// Skips certificate verification entirely. Only meant for intercepting
+    /// proxies whose root certificate can't be supplied as a bundle.
+    pub allow_invalid_certs: bool,
+    /// PEM file with extra root certificates, e.g. a corporate CA, trusted in
+    /// addition to the system roots.
+    pub ca_bundle_path: Option<PathBuf>,
+}
+
+impl TlsOptions {
+    /// Treats a blank bundle path, as sent by an empty form field, as none.
+    pub fn normalized(mut self) -> Self {
+        self.ca_bundle_path = self
+            .ca_bundle_path
+            .filter(|path| !path.as_os_str().is_empty());
+        self
+    }
+}
+
+struct Pool {
+    settings: HttpSettings,
+    clients: HashMap<TlsOptions, reqwest::Client>,
+}
+
+/// One client (and so one connection pool) per set of TLS options. Clients
+/// carry no credentials or cookies, so connections for different JIRA sites
+/// and auth methods can safely share one; this avoids repeated TLS
+/// handshakes, which are what make flaky VPNs hurt the most.
+static SHARED: OnceLock<Mutex<Pool>> = OnceLock::new();
+
+/// When the last JIRA request was sent, so background work can wait for a
+/// quiet moment.
+static LAST_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
+
+fn build(settings: &HttpSettings, tls: &TlsOptions) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder()
+        .danger_accept_invalid_certs(tls.allow_invalid_certs)
+        .pool_max_idle_per_host(settings.pool_max_idle_per_host as usize)
+        .pool_idle_timeout((settings.pool_idle_timeout_secs > 0).then(|| Duration::from_secs(settings.pool_idle_timeout_secs as u64)))
+        .tcp_keepalive((settings.tcp_keepalive_secs > 0).then(|| Duration::from_secs(settings.tcp_keepalive_secs as u64)))
+        .user_agent(settings.user_agent.as_deref().unwrap_or(USER_AGENT));
+
+    if settings.connect_timeout_secs > 0 {
+        builder = builder.connect_timeout(Duration::from_secs(settings.connect_timeout_secs as u64));
+    }
+    if settings.request_timeout_secs > 0 {
+        builder = builder.timeout(Duration::from_secs(settings.request_timeout_secs as u64));
+    }
+    builder = match settings.proxy_mode {
+        // reqwest reads the environment unless told otherwise.
+        ProxyMode::System => builder,
+        ProxyMode::Off => builder.no_proxy(),
+        ProxyMode::Manual => {
+            let url = settings.proxy_url.as_deref().unwrap_or_default();
+            let proxy = reqwest::Proxy::all(url).map_err(|e| format!("Invalid proxy URL '{}': {}", url, e))?;
+            builder.proxy(proxy)
+        }
+    };
+
+    if let Some(path) = &tls.ca_bundle_path {
+        let pem = std::fs::read(path)
+            .map_err(|e| format!("Failed to read CA bundle {}: {}", path.display(), e))?;
+        let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("Invalid CA bundle {}: {}", path.display(), e))?;
+        if certificates.is_empty() {
+            return Err(format!("No certificates found in CA bundle {}", path.display()));
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    builder = match settings.http_version {
+        HttpVersionPreference::Auto => builder,
+        HttpVersionPreference::Http1 => builder.http1_only(),
+        HttpVersionPreference::Http2 => builder.http2_prior_knowledge(),
+    };
+
+    builder.build().map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+fn shared() -> &'static Mutex<Pool> {
+    SHARED.get_or_init(|| {
+        Mutex::new(Pool {
+            settings: HttpSettings::default(),
+            clients: HashMap::new(),
+        })
+    })
+}
+
+/// The shared client for `tls`, built on first use. Fails when the CA
+/// bundle can't be read.
+pub fn client_for(tls: &TlsOptions) -> Result<reqwest::Client, String> {
+    let mut pool = shared().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(client) = pool.clients.get(tls) {
+        return Ok(client.clone());
+    }
+
+    let client = build(&pool.settings, tls)?;
+    pool.clients.insert(tls.clone(), client.clone());
+    Ok(client)
+}
+
+/// The shared client with full certificate verification.
+pub fn client() -> Result<reqwest::Client, String> {
+    client_for(&TlsOptions::default())
+}
+
+/// Drops the shared clients when the settings changed so they are rebuilt
+/// with them. Returns whether the clients changed; connections in the old
+/// pools close once idle. Retry settings apply without a rebuild.
+pub fn configure(settings: &HttpSettings) -> bool {
+    let mut pool = shared().lock().unwrap_or_else(|e| e.into_inner());
+    let rebuild = HttpSettings {
+        retry: settings.retry.clone(),
+        ..pool.settings.clone()
+    } != *settings;
+    pool.settings = settings.clone();
+    if rebuild {
+        pool.clients.clear();
+    }
+    rebuild
+}
+
+pub fn note_request() {
+    *LAST_REQUEST.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+}
+
+/// Whether no JIRA request was sent for `quiet`.
+pub fn quiet_for(quiet: Duration) -> bool {
+    LAST_REQUEST
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_none_or(|at| at.elapsed() >= quiet)
+}
+
+pub fn retry_settings() -> RetrySettings {
+    shared().lock().unwrap_or_else(|e| e.into_inner()).settings.retry.clone()
+}
+
+/// A random factor in `[0.5, 1)` that keeps clients which failed together
+/// from retrying together.
+pub fn jitter() -> f64 {
+    let mut bytes = [0u8; 4];
+    if getrandom::getrandom(&mut bytes).is_err() {
+        return 0.75;
+    }
+    0.5 + u32::from_le_bytes(bytes) as f64 / (u32::MAX as f64 + 1.0) / 2.0
+}
+
+impl RetrySettings {
+    /// How long to wait after `error` on try `attempt` (1-based) before the
+    /// next one, or `None` to give up. Only requests that did nothing on
+    /// JIRA's side are retried, unless `idempotent` says repeating is safe.
+    pub fn delay(&self, attempt: u32, error: &JiraError, idempotent: bool, jitter: f64) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let max_delay = Duration::from_secs(self.max_delay_secs as u64);
+
+        let retry = match error {
+            JiraError::RateLimited {
+                retry_after_secs: Some(secs),
+            } => {
+                let wait = Duration::from_secs(*secs);
+                return (wait <= max_delay).then_some(wait);
+            }
+            JiraError::RateLimited { retry_after_secs: None } => true,
+            JiraError::Api { status: 503, .. } => true,
+            JiraError::Api { status: 502 | 504, .. } => idempotent,
+            JiraError::Network(e) => e.is_connect() || (idempotent && e.is_timeout()),
+            _ => false,
+        };
+        if !retry {
+            return None;
+        }
+
+        let backoff = Duration::from_millis(self.base_delay_ms as u64).saturating_mul(1 << (attempt - 1).min(16));
+        Some(backoff.min(max_delay).mul_f64(jitter))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overloaded() -> JiraError {
+        JiraError::Api {
+            status: 503,
+            message: "Service Unavailable".to_string(),
+        }
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_cap() {
+        let retry = RetrySettings {
+            max_attempts: 10,
+            base_delay_ms: 500,
+            max_delay_secs: 3,
+        };
+        let delays: Vec<Option<Duration>> = (1..=4).map(|attempt| retry.delay(attempt, &overloaded(), true, 1.0)).collect();
+
+        assert_eq!(delays, [500, 1000, 2000, 3000].map(|ms| Some(Duration::from_millis(ms))));
+        assert_eq!(retry.delay(1, &overloaded(), true, 0.5), Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn honors_retry_after_within_the_cap() {
+        let retry = RetrySettings::default();
+        let limited = |secs| JiraError::RateLimited {
+            retry_after_secs: Some(secs),
+        };
+
+        assert_eq!(retry.delay(1, &limited(7), false, 1.0), Some(Duration::from_secs(7)));
+        assert_eq!(retry.delay(1, &limited(120), false, 1.0), None);
+    }
+
+    #[test]
+    fn gives_up_on_the_last_attempt_and_on_unsafe_retries() {
+        let retry = RetrySettings::default();
+        let bad_gateway = JiraError::Api {
+            status: 502,
+            message: "Bad Gateway".to_string(),
+        };
+
+        assert_eq!(retry.delay(retry.max_attempts, &overloaded(), true, 1.0), None);
+        assert_eq!(retry.delay(1, &bad_gateway, false, 1.0), None);
+        assert!(retry.delay(1, &bad_gateway, true, 1.0).is_some());
+        assert_eq!(retry.delay(1, &JiraError::NotFound("APP-1".to_string()), true, 1.0), None);
+    }
+}
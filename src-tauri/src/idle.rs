@@ -0,0 +1,359 @@
+use serde::Serialize;
+use tauri::State;
+
+/// Time since the last keyboard or mouse input, system wide.
+pub trait IdleDetector: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn idle_seconds(&self) -> Result<u64, String>;
+}
+
+pub struct IdleMonitor {
+    detector: Option<Box<dyn IdleDetector>>,
+}
+
+impl IdleMonitor {
+    /// Idle time in seconds, or `None` when no backend works on this system.
+    pub fn idle_seconds(&self) -> Option<u64> {
+        let detector = self.detector.as_ref()?;
+        match detector.idle_seconds() {
+            Ok(seconds) => Some(seconds),
+            Err(e) => {
+                tracing::warn!("Idle detection ({}) failed: {}", detector.name(), e);
+                None
+            }
+        }
+    }
+}
+
+/// Picks the first backend that works on the running session.
+pub fn detect() -> IdleMonitor {
+    let detector = backends()
+        .into_iter()
+        .find_map(|backend| match backend() {
+            Ok(detector) => Some(detector),
+            Err(e) => {
+                tracing::warn!("Idle backend unavailable: {}", e);
+                None
+            }
+        });
+
+    if detector.is_none() {
+        tracing::warn!("No idle detection backend available; idle features are disabled");
+    }
+    IdleMonitor { detector }
+}
+
+type Backend = fn() -> Result<Box<dyn IdleDetector>, String>;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn backends() -> Vec<Backend> {
+    // XWayland only sees input sent to X clients, so on Wayland sessions the
+    // compositor has to be asked directly.
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        vec![wayland::ExtIdleNotify::connect, wayland::MutterIdleMonitor::connect, x11::XScreenSaver::connect]
+    } else {
+        vec![x11::XScreenSaver::connect]
+    }
+}
+
+#[cfg(windows)]
+fn backends() -> Vec<Backend> {
+    vec![windows::LastInputInfo::connect]
+}
+
+#[cfg(target_os = "macos")]
+fn backends() -> Vec<Backend> {
+    vec![macos::EventSource::connect]
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod x11 {
+    use super::IdleDetector;
+    use std::ptr;
+    use x11_dl::{xlib::Xlib, xss::Xss};
+
+    /// The MIT-SCREEN-SAVER extension, loaded at runtime so the app still
+    /// starts on systems without libXss.
+    pub struct XScreenSaver {
+        xlib: Xlib,
+        xss: Xss,
+    }
+
+    impl XScreenSaver {
+        pub fn connect() -> Result<Box<dyn IdleDetector>, String> {
+            let detector = XScreenSaver {
+                xlib: Xlib::open().map_err(|e| e.to_string())?,
+                xss: Xss::open().map_err(|e| e.to_string())?,
+            };
+            detector.idle_seconds()?;
+            Ok(Box::new(detector))
+        }
+    }
+
+    impl IdleDetector for XScreenSaver {
+        fn name(&self) -> &'static str {
+            "X11 screen saver"
+        }
+
+        fn idle_seconds(&self) -> Result<u64, String> {
+            // SAFETY: the display is opened and closed within this call and
+            // the info struct is allocated by Xss and freed by Xlib.
+            unsafe {
+                let display = (self.xlib.XOpenDisplay)(ptr::null());
+                if display.is_null() {
+                    return Err("Cannot open X display".to_string());
+                }
+
+                let info = (self.xss.XScreenSaverAllocInfo)();
+                let root = (self.xlib.XDefaultRootWindow)(display);
+                let status = (self.xss.XScreenSaverQueryInfo)(display, root, info);
+                let idle_ms = (*info).idle;
+
+                (self.xlib.XFree)(info.cast());
+                (self.xlib.XCloseDisplay)(display);
+
+                if status == 0 {
+                    return Err("Screen saver extension not supported".to_string());
+                }
+                // `c_ulong` is only 32 bits wide on some targets.
+                #[allow(clippy::useless_conversion)]
+                Ok(u64::from(idle_ms) / 1000)
+            }
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod wayland {
+    use super::IdleDetector;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use wayland_client::globals::{registry_queue_init, GlobalListContents};
+    use wayland_client::protocol::{wl_registry, wl_seat};
+    use wayland_client::{Connection, Dispatch, QueueHandle};
+    use wayland_protocols::ext::idle_notify::v1::client::{
+        ext_idle_notification_v1::{self, ExtIdleNotificationV1},
+        ext_idle_notifier_v1::ExtIdleNotifierV1,
+    };
+
+    /// Resolution of the ext-idle-notify backend: the compositor reports idle
+    /// once no input arrived for this long.
+    const GRANULARITY: Duration = Duration::from_secs(1);
+
+    /// Start of the current idle period, `None` while the user is active.
+    type IdleSince = Arc<Mutex<Option<Instant>>>;
+
+    struct Listener {
+        idle_since: IdleSince,
+    }
+
+    impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for Listener {
+        fn event(
+            _: &mut Self,
+            _: &wl_registry::WlRegistry,
+            _: wl_registry::Event,
+            _: &GlobalListContents,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+        }
+    }
+
+    impl Dispatch<wl_seat::WlSeat, ()> for Listener {
+        fn event(_: &mut Self, _: &wl_seat::WlSeat, _: wl_seat::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {}
+    }
+
+    impl Dispatch<ExtIdleNotifierV1, ()> for Listener {
+        fn event(
+            _: &mut Self,
+            _: &ExtIdleNotifierV1,
+            _: <ExtIdleNotifierV1 as wayland_client::Proxy>::Event,
+            _: &(),
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+        }
+    }
+
+    impl Dispatch<ExtIdleNotificationV1, ()> for Listener {
+        fn event(
+            state: &mut Self,
+            _: &ExtIdleNotificationV1,
+            event: ext_idle_notification_v1::Event,
+            _: &(),
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+            let mut idle_since = state.idle_since.lock().unwrap_or_else(|e| e.into_inner());
+            match event {
+                ext_idle_notification_v1::Event::Idled => *idle_since = Instant::now().checked_sub(GRANULARITY),
+                ext_idle_notification_v1::Event::Resumed => *idle_since = None,
+                _ => {}
+            }
+        }
+    }
+
+    /// ext-idle-notify-v1 (KDE, Sway and other wlroots compositors). The
+    /// protocol is event based, so a thread follows the idle/resume events.
+    pub struct ExtIdleNotify {
+        idle_since: IdleSince,
+    }
+
+    impl ExtIdleNotify {
+        pub fn connect() -> Result<Box<dyn IdleDetector>, String> {
+            let connection = Connection::connect_to_env().map_err(|e| e.to_string())?;
+            let (globals, mut queue) = registry_queue_init::<Listener>(&connection).map_err(|e| e.to_string())?;
+            let qh = queue.handle();
+
+            let seat: wl_seat::WlSeat = globals.bind(&qh, 1..=1, ()).map_err(|e| e.to_string())?;
+            let notifier: ExtIdleNotifierV1 = globals
+                .bind(&qh, 1..=1, ())
+                .map_err(|e| format!("ext-idle-notify not supported by the compositor: {}", e))?;
+            notifier.get_idle_notification(GRANULARITY.as_millis() as u32, &seat, &qh, ());
+
+            let idle_since = IdleSince::default();
+            let mut listener = Listener {
+                idle_since: idle_since.clone(),
+            };
+            std::thread::spawn(move || {
+                // The connection has to outlive the event loop.
+                let _connection = connection;
+                while queue.blocking_dispatch(&mut listener).is_ok() {}
+                tracing::warn!("Wayland idle notification stream ended");
+            });
+
+            Ok(Box::new(ExtIdleNotify { idle_since }))
+        }
+    }
+
+    impl IdleDetector for ExtIdleNotify {
+        fn name(&self) -> &'static str {
+            "Wayland ext-idle-notify"
+        }
+
+        fn idle_seconds(&self) -> Result<u64, String> {
+            let idle_since = self.idle_since.lock().map_err(|e| e.to_string())?;
+            Ok(idle_since.map_or(0, |since| since.elapsed().as_secs()))
+        }
+    }
+
+    /// GNOME Shell does not implement ext-idle-notify but exposes the idle
+    /// time over D-Bus.
+    pub struct MutterIdleMonitor {
+        connection: zbus::blocking::Connection,
+    }
+
+    impl MutterIdleMonitor {
+        pub fn connect() -> Result<Box<dyn IdleDetector>, String> {
+            let detector = MutterIdleMonitor {
+                connection: zbus::blocking::Connection::session().map_err(|e| e.to_string())?,
+            };
+            detector.idle_seconds()?;
+            Ok(Box::new(detector))
+        }
+    }
+
+    impl IdleDetector for MutterIdleMonitor {
+        fn name(&self) -> &'static str {
+            "GNOME Mutter idle monitor"
+        }
+
+        fn idle_seconds(&self) -> Result<u64, String> {
+            let reply = self
+                .connection
+                .call_method(
+                    Some("org.gnome.Mutter.IdleMonitor"),
+                    "/org/gnome/Mutter/IdleMonitor/Core",
+                    Some("org.gnome.Mutter.IdleMonitor"),
+                    "GetIdletime",
+                    &(),
+                )
+                .map_err(|e| e.to_string())?;
+            let idle_ms: u64 = reply.body().deserialize().map_err(|e| e.to_string())?;
+            Ok(idle_ms / 1000)
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::IdleDetector;
+    use windows_sys::Win32::System::SystemInformation::GetTickCount;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    pub struct LastInputInfo;
+
+    impl LastInputInfo {
+        pub fn connect() -> Result<Box<dyn IdleDetector>, String> {
+            Ok(Box::new(LastInputInfo))
+        }
+    }
+
+    impl IdleDetector for LastInputInfo {
+        fn name(&self) -> &'static str {
+            "Windows GetLastInputInfo"
+        }
+
+        fn idle_seconds(&self) -> Result<u64, String> {
+            let mut info = LASTINPUTINFO {
+                cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+                dwTime: 0,
+            };
+            // SAFETY: `info` is a properly sized LASTINPUTINFO.
+            if unsafe { GetLastInputInfo(&mut info) } == 0 {
+                return Err("GetLastInputInfo failed".to_string());
+            }
+            // Both are tick counts that wrap after ~49 days.
+            let idle_ms = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+            Ok(idle_ms as u64 / 1000)
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::IdleDetector;
+
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const ANY_INPUT_EVENT_TYPE: u32 = u32::MAX;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(source_state: i32, event_type: u32) -> f64;
+    }
+
+    pub struct EventSource;
+
+    impl EventSource {
+        pub fn connect() -> Result<Box<dyn IdleDetector>, String> {
+            Ok(Box::new(EventSource))
+        }
+    }
+
+    impl IdleDetector for EventSource {
+        fn name(&self) -> &'static str {
+            "macOS CGEventSource"
+        }
+
+        fn idle_seconds(&self) -> Result<u64, String> {
+            // SAFETY: a pure query without pointers.
+            let seconds = unsafe { CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT_TYPE) };
+            Ok(seconds.max(0.0) as u64)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IdleReport {
+    pub backend: Option<&'static str>,
+    pub idle_seconds: Option<u64>,
+}
+
+/// Debug helper: which idle backend is active and what it currently reports.
+#[tauri::command]
+pub async fn get_idle_seconds(monitor: State<'_, IdleMonitor>) -> Result<IdleReport, String> {
+    Ok(IdleReport {
+        backend: monitor.detector.as_ref().map(|detector| detector.name()),
+        idle_seconds: monitor.idle_seconds(),
+    })
+}
@@ -0,0 +1,305 @@
+//! Moving time tracked in Toggl Track into JIRA: entries come from a Toggl
+//! CSV export or the Toggl API, each is matched to the issue whose key its
+//! description (or project, task or tags) names, and after a preview the
+//! matched and hand-assigned ones are logged in bulk.
+
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, State, Wry};
+
+use crate::adf::CommentInput;
+use crate::bulk::{self, BulkWorklogResult, WorklogEntry};
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::{http, mentions, JiraState};
+
+const TOGGL_API: &str = "https://api.track.toggl.com/api/v9";
+/// The API's range limit, and more than a bulk import takes anyway.
+const MAX_API_DAYS: u64 = 92;
+
+/// One Toggl time entry.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TogglEntry {
+    pub description: String,
+    pub project: Option<String>,
+    /// Tags, and the task when the export has one.
+    pub tags: Vec<String>,
+    /// JIRA timestamp, ready for a worklog.
+    pub started: String,
+    pub time_spent_seconds: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchedEntry {
+    pub issue_key: String,
+    #[serde(flatten)]
+    pub entry: TogglEntry,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TogglImportPreview {
+    pub matched: Vec<MatchedEntry>,
+    /// Entries naming no issue, to be assigned by hand.
+    pub unmatched: Vec<TogglEntry>,
+    /// Rows that couldn't be read, as `line: reason`.
+    pub skipped: Vec<String>,
+}
+
+/// An entry to log, as the preview returned it or assigned by hand.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportEntry {
+    pub issue_key: String,
+    pub description: String,
+    pub started: String,
+    pub time_spent_seconds: u32,
+}
+
+/// Splits CSV text into records, with quoted fields holding commas, quotes
+/// (doubled) and line breaks.
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records.retain(|record| record.iter().any(|field| !field.trim().is_empty()));
+    records
+}
+
+/// `1:02:03` (hours past 24 are fine) in seconds.
+fn parse_duration(value: &str) -> Option<u32> {
+    let mut parts = value.trim().split(':').map(|part| part.parse::<u32>().ok());
+    let (hours, minutes, seconds) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || minutes >= 60 || seconds >= 60 {
+        return None;
+    }
+    Some(hours * 3600 + minutes * 60 + seconds)
+}
+
+/// Reads a Toggl detailed report export; columns are found by header, so
+/// exports with more or fewer columns work too. Start times are local.
+fn entries_from_csv(text: &str) -> Result<(Vec<TogglEntry>, Vec<String>), String> {
+    let mut records = parse_csv(text).into_iter();
+    let headers: Vec<String> = records.next().ok_or("The file is empty")?.iter().map(|h| h.trim().to_lowercase()).collect();
+    let column = |name: &str| headers.iter().position(|header| header == name);
+    let (Some(description), Some(start_date), Some(start_time), Some(duration)) =
+        (column("description"), column("start date"), column("start time"), column("duration"))
+    else {
+        return Err("Not a Toggl export: it needs Description, Start date, Start time and Duration columns".to_string());
+    };
+    let project = column("project");
+    let task = column("task");
+    let tags = column("tags");
+
+    let mut entries = Vec::new();
+    let mut skipped = Vec::new();
+    for (index, record) in records.enumerate() {
+        let field = |column: usize| record.get(column).map(|value| value.trim()).unwrap_or_default();
+        let line = index + 2;
+        let started = NaiveDateTime::parse_from_str(&format!("{} {}", field(start_date), field(start_time)), "%Y-%m-%d %H:%M:%S")
+            .ok()
+            .and_then(|started| Local.from_local_datetime(&started).earliest());
+        let (Some(started), Some(seconds)) = (started, parse_duration(field(duration))) else {
+            skipped.push(format!("{}: unreadable start or duration", line));
+            continue;
+        };
+        if seconds == 0 {
+            continue;
+        }
+
+        let mut tags: Vec<String> = tags
+            .map(field)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+        tags.extend(task.map(field).filter(|task| !task.is_empty()).map(str::to_string));
+        entries.push(TogglEntry {
+            description: field(description).to_string(),
+            project: project.map(field).filter(|project| !project.is_empty()).map(str::to_string),
+            tags,
+            started: JiraClient::format_jira_datetime(&started),
+            time_spent_seconds: seconds,
+        });
+    }
+    Ok((entries, skipped))
+}
+
+/// The first key in the description, else in the project, tags or task.
+fn issue_key_of(entry: &TogglEntry) -> Option<String> {
+    std::iter::once(entry.description.as_str())
+        .chain(entry.project.as_deref())
+        .chain(entry.tags.iter().map(String::as_str))
+        .find_map(|text| mentions::find_issue_keys(text, "").into_iter().next())
+}
+
+fn preview(entries: Vec<TogglEntry>, skipped: Vec<String>) -> TogglImportPreview {
+    let mut matched = Vec::new();
+    let mut unmatched = Vec::new();
+    for entry in entries {
+        match issue_key_of(&entry) {
+            Some(issue_key) => matched.push(MatchedEntry { issue_key, entry }),
+            None => unmatched.push(entry),
+        }
+    }
+    TogglImportPreview {
+        matched,
+        unmatched,
+        skipped,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiEntry {
+    #[serde(default)]
+    description: Option<String>,
+    start: DateTime<chrono::FixedOffset>,
+    /// Negative while the entry is still running.
+    duration: i64,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// My finished Toggl entries between the dates (inclusive).
+async fn entries_from_api(token: &str, from: NaiveDate, to: NaiveDate) -> Result<Vec<TogglEntry>, String> {
+    let end = to + Days::new(1);
+    let response = http::client()?
+        .get(format!("{}/me/time_entries", TOGGL_API))
+        .basic_auth(token, Some("api_token"))
+        .query(&[("start_date", from.to_string()), ("end_date", end.to_string())])
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach Toggl: {}", e))?;
+    if matches!(response.status().as_u16(), 401 | 403) {
+        return Err("Toggl didn't accept the API token".to_string());
+    }
+    let entries: Vec<ApiEntry> = response
+        .error_for_status()
+        .map_err(|e| format!("Failed to fetch Toggl entries: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to read Toggl entries: {}", e))?;
+
+    Ok(entries
+        .into_iter()
+        .filter(|entry| entry.duration > 0)
+        .map(|entry| TogglEntry {
+            description: entry.description.unwrap_or_default(),
+            project: None,
+            tags: entry.tags,
+            started: JiraClient::format_jira_datetime(&entry.start.with_timezone(&Local)),
+            time_spent_seconds: entry.duration.min(u32::MAX as i64) as u32,
+        })
+        .collect())
+}
+
+/// Reads a Toggl CSV export and shows which issue each entry would be
+/// logged on; nothing is logged yet.
+#[tauri::command]
+pub async fn preview_toggl_import(path: PathBuf) -> Result<TogglImportPreview, JiraError> {
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| JiraError::Validation(format!("Failed to read {}: {}", path.display(), e)))?;
+    let (entries, skipped) = entries_from_csv(&text).map_err(JiraError::Validation)?;
+    Ok(preview(entries, skipped))
+}
+
+/// Like `preview_toggl_import`, with the entries fetched from the Toggl
+/// API; the token is only used for this call.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn preview_toggl_api_import(
+    token: String,
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+) -> Result<TogglImportPreview, JiraError> {
+    if from_date > to_date {
+        return Err(JiraError::Validation("fromDate must not be after toDate".to_string()));
+    }
+    if (to_date - from_date).num_days() as u64 >= MAX_API_DAYS {
+        return Err(JiraError::Validation(format!("At most {} days can be imported at once", MAX_API_DAYS)));
+    }
+    let entries = entries_from_api(token.trim(), from_date, to_date).await.map_err(JiraError::Other)?;
+    Ok(preview(entries, Vec::new()))
+}
+
+/// Logs the entries, matched or assigned by hand, like
+/// `create_worklogs_bulk`. Toggl keeps seconds, JIRA minutes: each is
+/// rounded to the nearest minute, at least one.
+#[tauri::command]
+pub async fn import_toggl_entries(
+    entries: Vec<ImportEntry>,
+    connection: Option<String>,
+    operation: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<BulkWorklogResult>, JiraError> {
+    let entries = entries
+        .into_iter()
+        .map(|entry| WorklogEntry {
+            issue_key: entry.issue_key,
+            description: CommentInput::Text(entry.description),
+            started: entry.started,
+            time_spent: format!("{}m", ((entry.time_spent_seconds + 30) / 60).max(1)),
+            confirmed: false,
+        })
+        .collect();
+    bulk::create_worklogs_bulk(entries, connection, operation, app_handle, state).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = "\u{feff}User,Email,Client,Project,Task,Description,Billable,Start date,Start time,End date,End time,Duration,Tags\r\n\
+        Ann,ann@example.com,Acme,Website,,\"PROJ-12 fix login, again\",Yes,2024-06-03,09:00:00,2024-06-03,10:30:00,01:30:00,\r\n\
+        Ann,ann@example.com,Acme,OPS-7 Maintenance,,Server patching,Yes,2024-06-03,11:00:00,2024-06-03,11:20:00,00:20:00,\r\n\
+        Ann,ann@example.com,,,,Lunch walk,No,2024-06-03,12:00:00,2024-06-03,12:45:00,00:45:00,\r\n\
+        Ann,ann@example.com,,,,Standup,No,2024-06-03,12:50:00,2024-06-03,13:00:00,00:10:00,\"Meetings, WEB-3\"\r\n\
+        Ann,ann@example.com,,,,Broken,No,someday,12:00:00,2024-06-03,12:45:00,00:45:00,\r\n";
+
+    #[test]
+    fn reads_quoted_csv() {
+        let records = parse_csv("a,\"b, \"\"c\"\"\",\"d\ne\"\r\n\r\nf,g");
+        assert_eq!(records, [vec!["a", "b, \"c\"", "d\ne"], vec!["f", "g"]]);
+        assert_eq!(parse_duration("25:01:02"), Some(90062));
+        assert_eq!(parse_duration("1:61:00"), None);
+    }
+
+    #[test]
+    fn matches_entries_to_issues() {
+        let (entries, skipped) = entries_from_csv(EXPORT).unwrap();
+        let preview = preview(entries, skipped);
+
+        let keys: Vec<&str> = preview.matched.iter().map(|entry| entry.issue_key.as_str()).collect();
+        assert_eq!(keys, ["PROJ-12", "OPS-7", "WEB-3"]);
+        assert_eq!(preview.matched[0].entry.description, "PROJ-12 fix login, again");
+        assert_eq!(preview.matched[0].entry.time_spent_seconds, 5400);
+        assert_eq!(preview.unmatched.len(), 1);
+        assert_eq!(preview.unmatched[0].description, "Lunch walk");
+        assert_eq!(preview.skipped, ["6: unreadable start or duration"]);
+    }
+}
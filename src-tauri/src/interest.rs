@@ -0,0 +1,85 @@
+//! Issues I touched lately: timed, logged on or opened. The background
+//! refresh starts with them, so the issues I'm actually working on stay
+//! fresh while the long tail waits for a quiet moment.
+
+use chrono::{DateTime, Duration, Local};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Wry};
+
+/// Issues remembered; touching one more forgets the least recent.
+const CAPACITY: usize = 20;
+/// Interest older than this no longer counts.
+const MAX_AGE_HOURS: i64 = 48;
+
+#[derive(Debug, Clone)]
+struct Touch {
+    issue_key: String,
+    at: DateTime<Local>,
+}
+
+/// Most recently touched first.
+#[derive(Default)]
+pub struct Interest {
+    touches: VecDeque<Touch>,
+}
+
+pub type InterestState = Mutex<Interest>;
+
+impl Interest {
+    fn touch(&mut self, issue_key: &str, at: DateTime<Local>) {
+        self.touches.retain(|touch| touch.issue_key != issue_key);
+        self.touches.push_front(Touch {
+            issue_key: issue_key.to_string(),
+            at,
+        });
+        self.touches.truncate(CAPACITY);
+    }
+
+    /// Up to `limit` issues touched within `MAX_AGE_HOURS`, the most recent
+    /// first.
+    fn hot(&self, limit: usize, now: DateTime<Local>) -> Vec<String> {
+        self.touches
+            .iter()
+            .take_while(|touch| now - touch.at < Duration::hours(MAX_AGE_HOURS))
+            .take(limit)
+            .map(|touch| touch.issue_key.clone())
+            .collect()
+    }
+}
+
+pub fn touch(app_handle: &AppHandle<Wry>, issue_key: &str) {
+    let state = app_handle.state::<InterestState>();
+    let mut interest = state.lock().unwrap_or_else(|e| e.into_inner());
+    interest.touch(issue_key, Local::now());
+}
+
+pub fn hot(app_handle: &AppHandle<Wry>, limit: usize) -> Vec<String> {
+    let state = app_handle.state::<InterestState>();
+    let interest = state.lock().unwrap_or_else(|e| e.into_inner());
+    interest.hot(limit, Local::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_by_last_touch() {
+        let now = Local::now();
+        let mut interest = Interest::default();
+        interest.touch("APP-1", now - Duration::hours(50));
+        interest.touch("APP-2", now - Duration::hours(2));
+        interest.touch("APP-3", now - Duration::hours(1));
+        interest.touch("APP-2", now);
+
+        assert_eq!(interest.hot(5, now), ["APP-2", "APP-3"]);
+        assert_eq!(interest.hot(1, now), ["APP-2"]);
+
+        for number in 0..CAPACITY {
+            interest.touch(&format!("OPS-{}", number), now);
+        }
+        assert_eq!(interest.touches.len(), CAPACITY);
+        assert!(!interest.hot(CAPACITY, now).contains(&"APP-2".to_string()));
+    }
+}
@@ -0,0 +1,163 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::error::JiraError;
+use crate::jira_api::field_sets;
+use crate::jira_types::JiraIssue;
+use crate::{connected_client, JiraState};
+
+/// Upper bound for one window so a single call can't ship the whole cache.
+const MAX_WINDOW: u32 = 500;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueSortField {
+    /// Project first, then the issue number numerically (ABC-9 before ABC-10).
+    Key,
+    Summary,
+    Status,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct IssueSort {
+    pub field: IssueSortField,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl Default for IssueSort {
+    fn default() -> Self {
+        Self {
+            field: IssueSortField::Key,
+            descending: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct IssueWindow {
+    pub issues: Vec<JiraIssue>,
+    pub offset: u32,
+    /// Size of the whole cache, for sizing the virtual list.
+    pub total: u32,
+    pub fetched_at: Option<DateTime<Local>>,
+}
+
+/// The assigned issues as last fetched for the issue list.
+#[derive(Default)]
+pub struct IssueCache {
+    issues: Vec<JiraIssue>,
+    /// `None` until the cache has been filled once.
+    fetched_at: Option<DateTime<Local>>,
+}
+
+pub type IssueCacheState = Mutex<IssueCache>;
+
+fn compare_keys(a: &str, b: &str) -> Ordering {
+    let split = |key: &str| match key.rsplit_once('-') {
+        Some((project, number)) => (project.to_string(), number.parse::<u64>().ok()),
+        None => (key.to_string(), None),
+    };
+    split(a).cmp(&split(b)).then_with(|| a.cmp(b))
+}
+
+fn compare(a: &JiraIssue, b: &JiraIssue, field: IssueSortField) -> Ordering {
+    match field {
+        IssueSortField::Key => compare_keys(&a.key, &b.key),
+        IssueSortField::Summary => a
+            .fields
+            .summary
+            .to_lowercase()
+            .cmp(&b.fields.summary.to_lowercase())
+            .then_with(|| compare_keys(&a.key, &b.key)),
+        IssueSortField::Status => a
+            .fields
+            .status
+            .name
+            .cmp(&b.fields.status.name)
+            .then_with(|| compare_keys(&a.key, &b.key)),
+    }
+}
+
+impl IssueCache {
+    pub fn len(&self) -> usize {
+        self.issues.len()
+    }
+
+    pub fn fetched_at(&self) -> Option<DateTime<Local>> {
+        self.fetched_at
+    }
+
+    fn window(&self, offset: u32, limit: u32, sort: IssueSort) -> IssueWindow {
+        let mut order: Vec<&JiraIssue> = self.issues.iter().collect();
+        order.sort_by(|a, b| {
+            let ordering = compare(a, b, sort.field);
+            if sort.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        IssueWindow {
+            issues: order
+                .into_iter()
+                .skip(offset as usize)
+                .take(limit.min(MAX_WINDOW) as usize)
+                .cloned()
+                .collect(),
+            offset,
+            total: self.issues.len() as u32,
+            fetched_at: self.fetched_at,
+        }
+    }
+}
+
+/// Replaces the cached issues with a fresh fetch.
+pub fn store(app_handle: &AppHandle<Wry>, issues: &[JiraIssue]) {
+    let state = app_handle.state::<IssueCacheState>();
+    let mut cache = state.lock().unwrap_or_else(|e| e.into_inner());
+    cache.issues = issues.to_vec();
+    cache.fetched_at = Some(Local::now());
+}
+
+/// Drops the cached issues, e.g. when the connection changes.
+pub fn clear(app_handle: &AppHandle<Wry>) {
+    let state = app_handle.state::<IssueCacheState>();
+    *state.lock().unwrap_or_else(|e| e.into_inner()) = IssueCache::default();
+}
+
+async fn refresh(app_handle: &AppHandle<Wry>) -> Result<u32, JiraError> {
+    let client = connected_client(&app_handle.state::<JiraState>()).await?;
+    let issues = client.get_assigned_issues(field_sets::ISSUE_LIST).await?;
+    store(app_handle, &issues);
+    Ok(issues.len() as u32)
+}
+
+/// Fetches the assigned issues into the cache and returns how many there are.
+#[tauri::command]
+pub async fn refresh_issue_cache(app_handle: AppHandle<Wry>) -> Result<u32, JiraError> {
+    refresh(&app_handle).await
+}
+
+/// A slice of the cached issues for a virtualized list. The cache is filled
+/// on first use; later calls never touch JIRA.
+#[tauri::command]
+pub async fn get_cached_issues_window(
+    offset: u32,
+    limit: u32,
+    sort: Option<IssueSort>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, IssueCacheState>,
+) -> Result<IssueWindow, JiraError> {
+    let filled = state.lock().map_err(|e| e.to_string())?.fetched_at.is_some();
+    if !filled {
+        refresh(&app_handle).await?;
+    }
+
+    let cache = state.lock().map_err(|e| e.to_string())?;
+    Ok(cache.window(offset, limit, sort.unwrap_or_default()))
+}
@@ -0,0 +1,207 @@
+//! Filing a quick issue, e.g. for unplanned support work, so time can be
+//! logged against it right away. `get_projects` and `get_issue_types` fill
+//! the pickers.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::State;
+
+use crate::adf::{CommentInput, Document};
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::{client_for, JiraState};
+
+const PAGE_SIZE: u32 = 50;
+/// JIRA rejects longer summaries.
+pub const MAX_SUMMARY_CHARS: usize = 255;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectPage {
+    #[serde(default)]
+    values: Vec<Project>,
+    #[serde(default = "last_page")]
+    is_last: bool,
+}
+
+fn last_page() -> bool {
+    true
+}
+
+/// Cloud lists issue types as `issueTypes`, Data Center as `values`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", bound(deserialize = "T: DeserializeOwned"))]
+struct IssueTypePage<T> {
+    #[serde(default, alias = "values")]
+    issue_types: Vec<T>,
+    #[serde(default)]
+    total: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub id: String,
+    pub key: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueType {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub subtask: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedIssue {
+    pub id: String,
+    pub key: String,
+}
+
+impl JiraClient {
+    async fn api_get<T: DeserializeOwned>(&self, endpoint: &'static str, path: &str, params: &[(&str, String)]) -> Result<T, JiraError> {
+        let url = self.api_url(path);
+
+        let authorization = self.authorization().await?;
+        let request = self
+            .http_client()
+            .get(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Authorization", authorization)
+            .query(params);
+        let response = self.send(request).await?;
+        Self::read_json(endpoint, response).await
+    }
+
+    /// Projects the user can create issues in, by name.
+    pub async fn get_projects(&self) -> Result<Vec<Project>, JiraError> {
+        let mut projects = Vec::new();
+
+        loop {
+            let params = [
+                ("startAt", projects.len().to_string()),
+                ("maxResults", PAGE_SIZE.to_string()),
+                ("action", "create".to_string()),
+                ("orderBy", "name".to_string()),
+            ];
+            let page: ProjectPage = self.api_get("projects", "project/search", &params).await?;
+            let fetched = page.values.len();
+            projects.extend(page.values);
+
+            if page.is_last || fetched == 0 {
+                break;
+            }
+        }
+
+        Ok(projects)
+    }
+
+    /// Issue types that can be created in `project_key`. Sub-tasks are left
+    /// out since they need a parent.
+    pub async fn get_issue_types(&self, project_key: &str) -> Result<Vec<IssueType>, JiraError> {
+        let path = format!("issue/createmeta/{}/issuetypes", project_key);
+        let mut issue_types: Vec<IssueType> = Vec::new();
+        let mut seen = 0;
+
+        loop {
+            let params = [("startAt", seen.to_string()), ("maxResults", PAGE_SIZE.to_string())];
+            let page: IssueTypePage<IssueType> = self.api_get("issue_types", &path, &params).await?;
+            let fetched = page.issue_types.len() as u32;
+            seen += fetched;
+            issue_types.extend(page.issue_types.into_iter().filter(|issue_type| !issue_type.subtask));
+
+            if fetched == 0 || seen >= page.total {
+                break;
+            }
+        }
+
+        Ok(issue_types)
+    }
+
+    /// Creates an issue; `issue_type` is the id from `get_issue_types`.
+    pub async fn create_issue(
+        &self,
+        project_key: &str,
+        issue_type: &str,
+        summary: &str,
+        description: Option<&Document>,
+    ) -> Result<CreatedIssue, JiraError> {
+        let url = self.api_url("issue");
+        let mut fields = json!({
+            "project": { "key": project_key },
+            "issuetype": { "id": issue_type },
+            "summary": summary,
+        });
+        if let Some(description) = description {
+            fields["description"] = json!(self.comment_format.body(description));
+        }
+
+        let authorization = self.authorization().await?;
+        let request = self
+            .http_client()
+            .post(&url)
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .header("Authorization", authorization)
+            .json(&json!({ "fields": fields }));
+        let response = self.send(request).await?;
+
+        Self::read_json("create_issue", response).await
+    }
+}
+
+#[tauri::command]
+pub async fn get_projects(connection: Option<String>, state: State<'_, JiraState>) -> Result<Vec<Project>, JiraError> {
+    client_for(&state, connection.as_deref()).await?.get_projects().await
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_issue_types(
+    project_key: String,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<IssueType>, JiraError> {
+    client_for(&state, connection.as_deref()).await?
+        .get_issue_types(&project_key)
+        .await
+}
+
+/// Creates an issue and returns its key, ready for `create_worklog`.
+/// `description` is plain text or an ADF document.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn create_issue(
+    project_key: String,
+    issue_type: String,
+    summary: String,
+    description: Option<CommentInput>,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<CreatedIssue, JiraError> {
+    let summary = summary.trim();
+    if summary.is_empty() {
+        return Err(JiraError::Validation("The summary is empty".to_string()));
+    }
+    if summary.chars().count() > MAX_SUMMARY_CHARS {
+        return Err(JiraError::Validation(format!("The summary is longer than {} characters", MAX_SUMMARY_CHARS)));
+    }
+    if project_key.trim().is_empty() || issue_type.trim().is_empty() {
+        return Err(JiraError::Validation("Pick a project and an issue type".to_string()));
+    }
+
+    let document = description
+        .map(|description| description.to_document())
+        .filter(|document| !document.text().trim().is_empty());
+    if let Some(document) = &document {
+        document.validate().map_err(JiraError::Validation)?;
+    }
+
+    let client = client_for(&state, connection.as_deref()).await?;
+    client
+        .create_issue(project_key.trim(), issue_type.trim(), summary, document.as_ref())
+        .await
+}
@@ -0,0 +1,175 @@
+//! Assigned issues grouped by status category, each with when I last logged
+//! work on it and when its status last changed, so in-progress issues that
+//! have gone quiet stand out.
+
+use chrono::{DateTime, Duration, Local};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::jira_types::JiraIssue;
+use crate::storage::{self, Storage};
+use crate::{cancellation, client_for, clock, JiraState};
+
+/// In-progress issues without a worklog or status change for this long are
+/// flagged stale.
+const STALE_AFTER: Duration = Duration::days(3);
+/// Group order; issues without a category go last.
+const CATEGORIES: [&str; 3] = ["indeterminate", "new", "done"];
+const IN_PROGRESS: &str = "indeterminate";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueActivity {
+    #[serde(flatten)]
+    pub issue: JiraIssue,
+    /// Start of my latest worklog the app has seen.
+    pub last_logged_at: Option<DateTime<Local>>,
+    pub status_changed_at: Option<DateTime<Local>>,
+    pub stale: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueGroup {
+    /// The status category key, empty for issues without one.
+    pub category: String,
+    pub name: String,
+    pub issues: Vec<IssueActivity>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupedIssues {
+    pub groups: Vec<IssueGroup>,
+    /// As in `get_assigned_issues`.
+    pub stale: bool,
+    pub fetched_at: DateTime<Local>,
+}
+
+fn activity(issue: JiraIssue, last_logged_at: Option<DateTime<Local>>, now: DateTime<Local>) -> IssueActivity {
+    let status_changed_at = issue
+        .fields
+        .status_category_changed
+        .as_deref()
+        .and_then(|changed| JiraClient::parse_jira_datetime(changed).ok())
+        .map(|changed| changed.with_timezone(&Local));
+    let in_progress = issue.fields.status.category.as_ref().is_some_and(|category| category.key == IN_PROGRESS);
+    let last_activity = last_logged_at.max(status_changed_at);
+    let stale = in_progress && last_activity.is_none_or(|at| now - at >= STALE_AFTER);
+
+    IssueActivity {
+        issue,
+        last_logged_at,
+        status_changed_at,
+        stale,
+    }
+}
+
+fn group(activities: Vec<IssueActivity>) -> Vec<IssueGroup> {
+    let mut groups: Vec<IssueGroup> = Vec::new();
+    for activity in activities {
+        let (category, name) = match &activity.issue.fields.status.category {
+            Some(category) => (category.key.clone(), category.name.clone()),
+            None => (String::new(), "Other".to_string()),
+        };
+        match groups.iter_mut().find(|group| group.category == category) {
+            Some(group) => group.issues.push(activity),
+            None => groups.push(IssueGroup {
+                category,
+                name,
+                issues: vec![activity],
+            }),
+        }
+    }
+
+    groups.sort_by_key(|group| CATEGORIES.iter().position(|key| *key == group.category).unwrap_or(CATEGORIES.len()));
+    for group in &mut groups {
+        // Stale first, then the longest untouched.
+        group.issues.sort_by_key(|activity| (!activity.stale, activity.last_logged_at.max(activity.status_changed_at)));
+    }
+    groups
+}
+
+/// `get_assigned_issues`, grouped by status category with the staleness of
+/// each issue.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_assigned_issues_grouped(
+    connection: Option<String>,
+    request_id: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<GroupedIssues, JiraError> {
+    let request = cancellation::register(&app_handle, request_id);
+    let client = client_for(&state, connection.as_deref()).await?.with_cancellation(request.token());
+    let last_logged = app_handle.state::<Storage>().last_logged(&storage::account(&client));
+    let assigned = storage::assigned_issues(&app_handle, client, connection).await?;
+
+    let now = clock::now(&app_handle);
+    let activities = assigned
+        .issues
+        .into_iter()
+        .map(|issue| {
+            let logged = last_logged.get(&issue.key).copied();
+            activity(issue, logged, now)
+        })
+        .collect();
+    Ok(GroupedIssues {
+        groups: group(activities),
+        stale: assigned.stale,
+        fetched_at: assigned.fetched_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jira_types::{IssueFields, IssueStatus, StatusCategory};
+
+    fn issue(key: &str, category: &str, changed: &str) -> JiraIssue {
+        JiraIssue {
+            id: String::new(),
+            key: key.to_string(),
+            fields: IssueFields {
+                summary: key.to_string(),
+                status: IssueStatus {
+                    name: category.to_string(),
+                    category: Some(StatusCategory {
+                        key: category.to_string(),
+                        name: category.to_string(),
+                    }),
+                },
+                status_category_changed: Some(changed.to_string()),
+                ..Default::default()
+            },
+            rendered_fields: None,
+        }
+    }
+
+    #[test]
+    fn flags_quiet_in_progress_issues_and_orders_groups() {
+        let now = JiraClient::parse_jira_datetime("2024-06-10T12:00:00.000+0000").unwrap().with_timezone(&Local);
+        let long_ago = "2024-06-01T12:00:00.000+0000";
+        let activities = vec![
+            activity(issue("APP-1", "new", long_ago), None, now),
+            activity(issue("APP-2", "indeterminate", long_ago), Some(now - Duration::hours(2)), now),
+            activity(issue("APP-3", "indeterminate", long_ago), None, now),
+            activity(issue("APP-4", "done", long_ago), None, now),
+        ];
+
+        let groups = group(activities);
+        let summary: Vec<(&str, Vec<(&str, bool)>)> = groups
+            .iter()
+            .map(|group| {
+                let issues = group.issues.iter().map(|a| (a.issue.key.as_str(), a.stale)).collect();
+                (group.category.as_str(), issues)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("indeterminate", vec![("APP-3", true), ("APP-2", false)]),
+                ("new", vec![("APP-1", false)]),
+                ("done", vec![("APP-4", false)]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::jira_api::JiraClient;
+use crate::notifications::NotificationKind;
+use crate::{clock, notifications, scheduler, store, vault};
+
+pub const REMINDERS_FILE: &str = "issue_reminders.json";
+
+pub type IssueReminderState = Mutex<Vec<IssueReminder>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueReminder {
+    pub id: u64,
+    pub issue_key: String,
+    pub fire_at: DateTime<Local>,
+    pub note: Option<String>,
+}
+
+pub fn load(app_handle: &AppHandle<Wry>) -> Vec<IssueReminder> {
+    match store::data_file(app_handle, REMINDERS_FILE) {
+        Ok(path) => vault::load(app_handle, &path),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Reads the reminders again once the vault is unlocked.
+pub fn reload(app_handle: &AppHandle<Wry>) {
+    let reminders = load(app_handle);
+    let state = app_handle.state::<IssueReminderState>();
+    *state.lock().unwrap_or_else(|e| e.into_inner()) = reminders;
+    scheduler::wake(app_handle);
+}
+
+fn persist(app_handle: &AppHandle<Wry>, reminders: &[IssueReminder]) -> Result<(), String> {
+    let path = store::data_file(app_handle, REMINDERS_FILE)?;
+    vault::save(app_handle, &path, &reminders)
+}
+
+pub fn next_fire_at(app_handle: &AppHandle<Wry>) -> Option<DateTime<Local>> {
+    let state = app_handle.state::<IssueReminderState>();
+    let reminders = state.lock().unwrap_or_else(|e| e.into_inner());
+    reminders.iter().map(|r| r.fire_at).min()
+}
+
+/// Shows and removes every reminder whose time has come, including ones that
+/// came due while the app was not running.
+pub fn fire_due(app_handle: &AppHandle<Wry>, now: DateTime<Local>) {
+    let state = app_handle.state::<IssueReminderState>();
+    let mut reminders = state.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (due, pending): (Vec<_>, Vec<_>) = reminders.drain(..).partition(|r| r.fire_at <= now);
+    *reminders = pending;
+
+    if due.is_empty() {
+        return;
+    }
+
+    for reminder in &due {
+        let body = reminder
+            .note
+            .clone()
+            .unwrap_or_else(|| format!("Time to look at {}", reminder.issue_key));
+        notifications::notify(app_handle, NotificationKind::Reminder, &format!("Reminder: {}", reminder.issue_key), &body);
+    }
+
+    if let Err(e) = persist(app_handle, &reminders) {
+        tracing::warn!("Failed to save issue reminders: {}", e);
+    }
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn schedule_issue_reminder(
+    issue_key: String,
+    datetime: DateTime<Local>,
+    note: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, IssueReminderState>,
+) -> Result<IssueReminder, String> {
+    let issue_key = issue_key.trim().to_uppercase();
+    if !JiraClient::is_valid_issue_key(&issue_key) {
+        return Err(format!("Invalid issue key: {}", issue_key));
+    }
+    if datetime <= clock::now(&app_handle) {
+        return Err("Reminder time must be in the future".to_string());
+    }
+
+    let mut reminders = state.lock().map_err(|e| e.to_string())?;
+    let reminder = IssueReminder {
+        id: reminders.iter().map(|r| r.id).max().unwrap_or(0) + 1,
+        issue_key,
+        fire_at: datetime,
+        note: note.filter(|note| !note.trim().is_empty()),
+    };
+
+    reminders.push(reminder.clone());
+    reminders.sort_by_key(|r| r.fire_at);
+    persist(&app_handle, &reminders)?;
+    drop(reminders);
+
+    scheduler::wake(&app_handle);
+    Ok(reminder)
+}
+
+#[tauri::command]
+pub async fn list_issue_reminders(
+    state: State<'_, IssueReminderState>,
+) -> Result<Vec<IssueReminder>, String> {
+    let reminders = state.lock().map_err(|e| e.to_string())?;
+    Ok(reminders.clone())
+}
+
+#[tauri::command]
+pub async fn cancel_issue_reminder(
+    id: u64,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, IssueReminderState>,
+) -> Result<(), String> {
+    let mut reminders = state.lock().map_err(|e| e.to_string())?;
+    let before = reminders.len();
+    reminders.retain(|r| r.id != id);
+
+    if reminders.len() == before {
+        return Err(format!("Reminder {} not found", id));
+    }
+
+    persist(&app_handle, &reminders)
+}
@@ -0,0 +1,148 @@
+//! JIRA Software boards and sprints, through `/rest/agile/1.0`, so the issue
+//! list can be narrowed to the current sprint.
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tauri::State;
+
+use crate::error::JiraError;
+use crate::jira_api::{field_sets, JiraClient};
+use crate::jira_types::{Board, JiraIssue, JiraSearchResponse, Sprint};
+use crate::{client_for, url, JiraState};
+
+const PAGE_SIZE: u32 = 50;
+
+/// `/rest/agile/1.0` lists page by `startAt`/`maxResults` and flag the last
+/// page.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", bound(deserialize = "T: DeserializeOwned"))]
+struct PagedValues<T> {
+    #[serde(default)]
+    values: Vec<T>,
+    #[serde(default = "last_page")]
+    is_last: bool,
+}
+
+fn last_page() -> bool {
+    true
+}
+
+impl JiraClient {
+    async fn agile_get<T: DeserializeOwned>(
+        &self,
+        endpoint: &'static str,
+        path: &str,
+        params: &[(&str, String)],
+    ) -> Result<T, JiraError> {
+        let url = url::join(&self.base_url, url::AGILE_API, path);
+
+        let authorization = self.authorization().await?;
+        let request = self
+            .http_client()
+            .get(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Authorization", authorization)
+            .query(params);
+        let response = self.send(request).await?;
+        Self::read_json(endpoint, response).await
+    }
+
+    async fn agile_get_all<T: DeserializeOwned>(
+        &self,
+        endpoint: &'static str,
+        path: &str,
+        params: &[(&str, String)],
+    ) -> Result<Vec<T>, JiraError> {
+        let mut values = Vec::new();
+
+        loop {
+            let mut page_params = vec![("startAt", values.len().to_string()), ("maxResults", PAGE_SIZE.to_string())];
+            page_params.extend(params.iter().cloned());
+            let page: PagedValues<T> = self.agile_get(endpoint, path, &page_params).await?;
+            let fetched = page.values.len();
+            values.extend(page.values);
+
+            if page.is_last || fetched == 0 {
+                break;
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Boards the user can see; empty on sites without JIRA Software.
+    pub async fn get_boards(&self) -> Result<Vec<Board>, JiraError> {
+        match self.agile_get_all("boards", "board", &[]).await {
+            Err(JiraError::NotFound(_)) => Ok(Vec::new()),
+            result => result,
+        }
+    }
+
+    /// The board's active sprint. Kanban boards have none; JIRA answers them
+    /// with a 400.
+    pub async fn get_active_sprint(&self, board_id: u64) -> Result<Option<Sprint>, JiraError> {
+        let path = format!("board/{}/sprint", board_id);
+        let params = [("state", "active".to_string())];
+        match self.agile_get_all::<Sprint>("board_sprints", &path, &params).await {
+            Ok(sprints) => Ok(sprints.into_iter().next()),
+            Err(JiraError::Validation(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Issues of `sprint_id`, optionally only those assigned to me.
+    pub async fn get_sprint_issues(&self, sprint_id: u64, assigned_to_me: bool, fields: &str) -> Result<Vec<JiraIssue>, JiraError> {
+        let path = format!("sprint/{}/issue", sprint_id);
+        let mut issues = Vec::new();
+
+        loop {
+            let mut params = vec![
+                ("startAt", issues.len().to_string()),
+                ("maxResults", PAGE_SIZE.to_string()),
+                ("fields", fields.to_string()),
+            ];
+            if assigned_to_me {
+                params.push(("jql", "assignee = currentUser()".to_string()));
+            }
+            let page: JiraSearchResponse = self.agile_get("sprint_issues", &path, &params).await?;
+            let fetched = page.issues.len();
+            issues.extend(page.issues);
+
+            if fetched == 0 || issues.len() as u32 >= page.total {
+                break;
+            }
+        }
+
+        Ok(issues)
+    }
+}
+
+#[tauri::command]
+pub async fn get_boards(connection: Option<String>, state: State<'_, JiraState>) -> Result<Vec<Board>, JiraError> {
+    client_for(&state, connection.as_deref()).await?.get_boards().await
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_active_sprint(
+    board_id: u64,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<Option<Sprint>, JiraError> {
+    client_for(&state, connection.as_deref()).await?
+        .get_active_sprint(board_id)
+        .await
+}
+
+/// The sprint's issues, by default only mine, with the issue list fields.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_sprint_issues(
+    sprint_id: u64,
+    assigned_to_me: Option<bool>,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<JiraIssue>, JiraError> {
+    client_for(&state, connection.as_deref()).await?
+        .get_sprint_issues(sprint_id, assigned_to_me.unwrap_or(true), field_sets::ISSUE_LIST)
+        .await
+}
@@ -1,136 +1,1197 @@
-use reqwest;
+use base64::Engine;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime};
+use serde::de::DeserializeOwned;
 use std::collections::HashMap;
+use std::future::Future;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
 
+use crate::adf;
+use crate::error::JiraError;
+use crate::settings::{TimeUnit, TimeUnitSettings};
 use crate::jira_types::*;
+use crate::http::{self, TlsOptions};
+use crate::offline::OfflineFlag;
+use crate::{metrics, response_cache, url};
+use crate::oauth::OAuthSession;
+
+/// `fields=` sets for issue searches, one per call site, so each view only
+/// downloads what it shows. Summary and status are always needed to parse
+/// the issue.
+pub mod field_sets {
+    /// The assigned issues list in the main window.
+    pub const ISSUE_LIST: &str = "summary,status,assignee,statuscategorychangedate";
+    /// Background change detection.
+    pub const SYNC: &str = "summary,status";
+    /// Issues looked up for their worklogs.
+    pub const WORKLOG_ISSUES: &str = "summary,status";
+    /// Titles looked up for keys outside the assigned list.
+    pub const SUMMARY: &str = "summary,status";
+    /// Parents looked up to roll reports up to stories and epics.
+    pub const HIERARCHY: &str = "summary,status,parent,issuetype";
+    /// Status checks before logging time.
+    pub const STATUS: &str = "summary,status";
+    /// The watched and recently viewed lists.
+    pub const WATCHLIST: &str = "summary,status,assignee,statuscategorychangedate,watches";
+    /// The issue detail panel, plus the site's sprint field.
+    pub const DETAIL: &str = "summary,status,assignee,description,priority,labels,duedate,subtasks,comment";
+}
+
+const SPRINT_FIELD_TYPE: &str = "com.pyxis.greenhopper.jira:gh-sprint";
+
+/// Sprint field ids by site URL, `None` for sites without JIRA Software.
+static SPRINT_FIELDS: OnceLock<Mutex<HashMap<String, Option<String>>>> = OnceLock::new();
+
+type AuthRequiredHook = Box<dyn Fn(&JiraClient, &JiraError) + Send + Sync>;
+
+/// Told once per set of credentials when JIRA rejects them; see
+/// `on_auth_required`.
+static AUTH_REQUIRED: OnceLock<AuthRequiredHook> = OnceLock::new();
+
+/// Server and Data Center answer some rejected logins with a 403 and this
+/// header instead of a 401.
+const LOGIN_REASON_HEADER: &str = "X-Seraph-LoginReason";
+
+/// Calls `hook` the first time a client's credentials are rejected, e.g. to
+/// ask for a new login. Set once at startup.
+pub fn on_auth_required(hook: impl Fn(&JiraClient, &JiraError) + Send + Sync + 'static) {
+    if AUTH_REQUIRED.set(Box::new(hook)).is_err() {
+        tracing::warn!("The auth-required hook was already set");
+    }
+}
+
+/// What a refresh of the main window needs from JIRA. The REST client is the
+/// default; `jira_graphql::JiraGraphqlClient` is an experimental backend that
+/// answers the same questions through the Atlassian GraphQL gateway.
+pub trait JiraApi {
+    fn assigned_issues(&self) -> impl Future<Output = Result<Vec<JiraIssue>, JiraError>> + Send;
+
+    fn user_worklogs(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> impl Future<Output = Result<Vec<UserWorklog>, JiraError>> + Send;
+
+    /// Assigned issues, the user's worklogs in `[from, to]` and the active
+    /// sprint.
+    fn overview(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> impl Future<Output = Result<JiraOverview, JiraError>> + Send;
+}
+
+#[derive(Clone)]
+pub enum JiraAuth {
+    /// Email and API token.
+    Basic { access_token: String },
+    /// OAuth 2.0 (3LO); the access token is refreshed when it expires.
+    OAuth(Arc<OAuthSession>),
+}
 
 #[derive(Clone)]
 pub struct JiraClient {
     pub base_url: String,
     pub email: String,
-    pub access_token: String,
+    pub auth: JiraAuth,
+    pub tls: TlsOptions,
+    pub comment_format: adf::CommentFormat,
+    /// Fails requests with `JiraError::Cancelled` once cancelled; see
+    /// `cancellation`.
+    cancel: Option<CancellationToken>,
+    /// Sends requests even while working offline, to notice JIRA is back.
+    probe: bool,
+    offline: OfflineFlag,
+    /// Set once JIRA rejected the credentials, shared by every clone. From
+    /// then on requests fail without being sent, so background tasks don't
+    /// lock the account, until the connection is replaced.
+    rejected: Arc<AtomicBool>,
     client: reqwest::Client,
 }
 
 impl JiraClient {
-    pub fn new(base_url: String, email: String, access_token: String) -> Self {
-        let client = reqwest::Client::builder()
-            .danger_accept_invalid_certs(true)
-            .build()
-            .expect("Failed to create HTTP client");
-            
-        Self {
+    pub fn new(base_url: String, email: String, access_token: String, offline: OfflineFlag) -> Result<Self, String> {
+        Self::with_auth(base_url, email, JiraAuth::Basic { access_token }, offline)
+    }
+
+    /// `base_url` is the API gateway for the site, not the site itself:
+    /// OAuth requests go through api.atlassian.com.
+    pub fn with_oauth(base_url: String, email: String, session: Arc<OAuthSession>, offline: OfflineFlag) -> Result<Self, String> {
+        Self::with_auth(base_url, email, JiraAuth::OAuth(session), offline)
+    }
+
+    fn with_auth(base_url: String, email: String, auth: JiraAuth, offline: OfflineFlag) -> Result<Self, String> {
+        Ok(Self {
             base_url,
             email,
-            access_token,
-            client,
+            auth,
+            tls: TlsOptions::default(),
+            comment_format: adf::CommentFormat::default(),
+            cancel: None,
+            probe: false,
+            offline,
+            rejected: Arc::default(),
+            client: http::client()?,
+        })
+    }
+
+    /// Writes comments as `format`, over the REST API that goes with it;
+    /// see `url::ApiVersion`.
+    pub fn with_comment_format(mut self, format: adf::CommentFormat) -> Self {
+        self.comment_format = format;
+        self
+    }
+
+    /// Lets requests through while working offline; see `offline`.
+    pub fn probing(mut self) -> Self {
+        self.probe = true;
+        self
+    }
+
+    /// Lets `token` abort this client's requests.
+    pub fn with_cancellation(mut self, token: Option<CancellationToken>) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Switches to the certificate checks in `tls`.
+    pub fn with_tls(mut self, tls: TlsOptions) -> Result<Self, String> {
+        self.client = http::client_for(&tls)?;
+        self.tls = tls;
+        Ok(self)
+    }
+
+    /// Picks up the shared client again after the HTTP settings changed.
+    pub fn reload_http_client(&mut self) -> Result<(), String> {
+        self.client = http::client_for(&self.tls)?;
+        Ok(())
+    }
+
+    /// Value of the `Authorization` header, refreshing OAuth tokens first
+    /// when they are about to expire.
+    pub(crate) async fn authorization(&self) -> Result<String, JiraError> {
+        match &self.auth {
+            JiraAuth::Basic { access_token } => {
+                let credentials = format!("{}:{}", self.email, access_token);
+                Ok(format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials)))
+            }
+            // Refreshing the token would go out to the network.
+            JiraAuth::OAuth(_) if !self.probe && self.offline.is_offline() => Err(JiraError::Offline),
+            JiraAuth::OAuth(session) => match session.access_token(&self.client).await {
+                Ok(token) => Ok(format!("Bearer {}", token)),
+                Err(e) => {
+                    if matches!(e, JiraError::Unauthorized { .. }) {
+                        self.reject_credentials(&e);
+                    }
+                    Err(e)
+                }
+            },
         }
     }
 
-    pub async fn get_assigned_issues(&self) -> Result<Vec<JiraIssue>, Box<dyn std::error::Error>> {
-        let url = format!("{}/rest/api/3/search", self.base_url);
-        
-        let mut params = HashMap::new();
-        params.insert("jql", "assignee=currentUser()");
-        params.insert("fields", "summary,status,assignee");
-        
-        let response = self.client
-            .get(&url)
-            .header("Accept", "application/json")
-            .basic_auth(&self.email, Some(&self.access_token))
-            .query(&params)
-            .send()
-            .await?;
+    pub fn http_client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// Whether the app works offline, for other clients made from this one.
+    pub fn offline(&self) -> &OfflineFlag {
+        &self.offline
+    }
+
+    /// Whether JIRA rejected these credentials; they stay rejected.
+    pub fn is_rejected(&self) -> bool {
+        self.rejected.load(Ordering::SeqCst)
+    }
+
+    /// Whether `other` is a clone of this client, with the same credentials.
+    pub fn same_credentials(&self, other: &JiraClient) -> bool {
+        Arc::ptr_eq(&self.rejected, &other.rejected)
+    }
+
+    /// Whether `response` says the credentials, rather than the account's
+    /// permissions, were the problem.
+    fn rejects_credentials(response: &reqwest::Response) -> bool {
+        match response.status() {
+            reqwest::StatusCode::UNAUTHORIZED => true,
+            reqwest::StatusCode::FORBIDDEN => response
+                .headers()
+                .get(LOGIN_REASON_HEADER)
+                .and_then(|reason| reason.to_str().ok())
+                .is_some_and(|reason| reason.starts_with("AUTHENTICATED_FAILED") || reason.starts_with("AUTHENTICATION_DENIED")),
+            _ => false,
+        }
+    }
+
+    /// Gets OAuth sessions a new access token for `request`, in case the
+    /// old one was revoked early. Other credentials can't be renewed.
+    async fn renew_authorization(&self, request: &mut reqwest::Request) -> bool {
+        let JiraAuth::OAuth(session) = &self.auth else {
+            return false;
+        };
+        let token = match session.refresh(&self.client).await {
+            Ok(tokens) => tokens.access_token,
+            Err(e) => {
+                tracing::warn!("Failed to refresh the OAuth session: {}", e);
+                return false;
+            }
+        };
+        match reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token)) {
+            Ok(value) => {
+                request.headers_mut().insert(reqwest::header::AUTHORIZATION, value);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Marks the credentials rejected, telling `on_auth_required` the first
+    /// time.
+    fn reject_credentials(&self, error: &JiraError) {
+        if self.rejected.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        tracing::warn!(url = %self.base_url, "JIRA rejected the credentials: {}", error);
+        if let Some(hook) = AUTH_REQUIRED.get() {
+            hook(self, error);
+        }
+    }
 
-        if !response.status().is_success() {
-            return Err(format!("JIRA API error: {}", response.status()).into());
+    /// Sends `request` and checks its status. Rate limits, overload and
+    /// dropped connections are retried as the `http.retry` settings allow.
+    pub(crate) async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, JiraError> {
+        if !self.probe {
+            self.offline.check()?;
+        }
+        if self.is_rejected() {
+            return Err(JiraError::Unauthorized {
+                status: None,
+                message: "JIRA rejected the credentials; sign in again".to_string(),
+            });
+        }
+        let Some(cancel) = &self.cancel else {
+            return self.send_with_retries(request).await;
+        };
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => Err(JiraError::Cancelled),
+            response = self.send_with_retries(request) => response,
         }
+    }
+
+    async fn send_with_retries(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, JiraError> {
+        let mut request = request.build()?;
+        http::note_request();
+        let idempotent = request.method() != reqwest::Method::POST;
+        let retry = http::retry_settings();
+        let mut attempt = 1;
+        let mut renewed = false;
 
-        let search_response: JiraSearchResponse = response.json().await?;
-        Ok(search_response.issues)
+        loop {
+            // Streaming bodies, such as attachment uploads, can't be replayed.
+            let Some(this_try) = request.try_clone() else {
+                let response = self.execute(request, attempt).await?;
+                let rejected = Self::rejects_credentials(&response);
+                let result = Self::check(response).await;
+                if let (true, Err(e)) = (rejected, &result) {
+                    self.reject_credentials(e);
+                }
+                return result;
+            };
+            let error = match self.execute(this_try, attempt).await {
+                Ok(response) => {
+                    let rejected = Self::rejects_credentials(&response);
+                    match Self::check(response).await {
+                        Ok(response) => return Ok(response),
+                        Err(_) if rejected && !renewed && self.renew_authorization(&mut request).await => {
+                            renewed = true;
+                            continue;
+                        }
+                        Err(e) if rejected => {
+                            self.reject_credentials(&e);
+                            return Err(e);
+                        }
+                        Err(e) => e,
+                    }
+                }
+                Err(e) => JiraError::from(e),
+            };
+
+            let Some(delay) = retry.delay(attempt, &error, idempotent, http::jitter()) else {
+                return Err(error);
+            };
+            tracing::warn!(
+                method = %request.method(),
+                url = %Self::log_url(request.url()),
+                attempt,
+                delay_ms = delay.as_millis() as u64,
+                "Retrying JIRA request: {}",
+                error
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// The URL without its query, which can hold JQL with names in it.
+    fn log_url(url: &reqwest::Url) -> String {
+        format!("{}{}", url.origin().ascii_serialization(), url.path())
+    }
+
+    /// Sends one try and logs its method, URL, status and latency. Headers,
+    /// and so the credentials, are never logged.
+    async fn execute(&self, request: reqwest::Request, attempt: u32) -> Result<reqwest::Response, reqwest::Error> {
+        let method = request.method().clone();
+        let url = Self::log_url(request.url());
+        let started = Instant::now();
+        let result = self.client.execute(request).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        match &result {
+            Ok(response) if response.status().is_success() || response.status() == reqwest::StatusCode::NOT_MODIFIED => {
+                tracing::info!(%method, %url, status = response.status().as_u16(), latency_ms, attempt, "JIRA request")
+            }
+            Ok(response) => {
+                tracing::warn!(%method, %url, status = response.status().as_u16(), latency_ms, attempt, "JIRA request failed")
+            }
+            Err(e) => {
+                // The error's own message repeats the full URL.
+                let cause = std::error::Error::source(e).map_or_else(|| "request error".to_string(), ToString::to_string);
+                tracing::warn!(%method, %url, latency_ms, attempt, "JIRA request failed: {}", cause)
+            }
+        }
+        result
+    }
+
+    /// Turns error statuses into the matching `JiraError`.
+    pub(crate) async fn check(response: reqwest::Response) -> Result<reqwest::Response, JiraError> {
+        let status = response.status();
+        // Only conditional requests get a 304, and they handle it.
+        if status.is_success() || status == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(response);
+        }
+
+        let retry_after_secs = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        let body = response.text().await.unwrap_or_default();
+        Err(JiraError::from_status(status, retry_after_secs, &body))
+    }
+
+    /// Reads a JSON body, decompressing it when the server answered with
+    /// gzip, and records its size under `endpoint`.
+    pub(crate) async fn read_json<T: DeserializeOwned>(
+        endpoint: &'static str,
+        response: reqwest::Response,
+    ) -> Result<T, JiraError> {
+        Ok(serde_json::from_slice(&Self::read_body(endpoint, response).await?)?)
+    }
+
+    /// The decompressed body, recorded under `endpoint`.
+    async fn read_body(endpoint: &'static str, response: reqwest::Response) -> Result<Vec<u8>, JiraError> {
+        let gzipped = response
+            .headers()
+            .get(reqwest::header::CONTENT_ENCODING)
+            .is_some_and(|encoding| encoding.as_bytes().eq_ignore_ascii_case(b"gzip"));
+        let body = response.bytes().await?;
+
+        let json = if gzipped {
+            let mut json = Vec::new();
+            flate2::read::GzDecoder::new(body.as_ref())
+                .read_to_end(&mut json)
+                .map_err(|e| JiraError::Deserialize(format!("Invalid gzip body: {}", e)))?;
+            json
+        } else {
+            body.to_vec()
+        };
+
+        metrics::record_payload(endpoint, body.len(), json.len());
+        Ok(json)
+    }
+
+    /// Sends a GET and reads its JSON like `send` and `read_json`, as a
+    /// conditional request when the last answer for the same URL came
+    /// with an `ETag` or `Last-Modified`; see `response_cache`.
+    pub(crate) async fn get_json_cached<T: DeserializeOwned>(
+        &self,
+        endpoint: &'static str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, JiraError> {
+        let (client, request) = request.build_split();
+        let mut request = request?;
+        // Two accounts on one site get different results for the same URL.
+        let key = format!("{} {}", self.email, request.url());
+        let cached = response_cache::get(&key);
+        if let Some(cached) = &cached {
+            response_cache::add_validators(request.headers_mut(), cached);
+        }
+
+        let response = self.send(reqwest::RequestBuilder::from_parts(client, request)).await?;
+        if let (reqwest::StatusCode::NOT_MODIFIED, Some(cached)) = (response.status(), &cached) {
+            metrics::record_payload(endpoint, 0, cached.body.len());
+            return Ok(serde_json::from_slice(&cached.body)?);
+        }
+        let headers = response.headers().clone();
+        let body = Self::read_body(endpoint, response).await?;
+        response_cache::store(&key, &headers, &body);
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    /// Fetches every issue assigned to the current user, following pagination.
+    pub async fn get_assigned_issues(&self, fields: &str) -> Result<Vec<JiraIssue>, JiraError> {
+        let mut issues = Vec::new();
+
+        loop {
+            let page = self.get_assigned_issues_page(issues.len() as u32, None, fields).await?;
+            let fetched = page.issues.len();
+            issues.extend(page.issues);
+
+            if fetched == 0 || issues.len() as u32 >= page.total {
+                break;
+            }
+        }
+
+        Ok(issues)
+    }
+
+    pub async fn get_assigned_issues_page(
+        &self,
+        start_at: u32,
+        max_results: Option<u32>,
+        fields: &str,
+    ) -> Result<JiraSearchResponse, JiraError> {
+        self.search_issues("assignee=currentUser()", fields, start_at, max_results)
+            .await
+    }
+
+    /// Issues assigned to the current user whose status is in the "In
+    /// Progress" category, at most `max_results`.
+    pub async fn get_in_progress_issues(&self, max_results: u32, fields: &str) -> Result<Vec<JiraIssue>, JiraError> {
+        let jql = "assignee=currentUser() AND statusCategory = \"In Progress\" ORDER BY updated DESC";
+        let page = self.search_issues(jql, fields, 0, Some(max_results)).await?;
+        Ok(page.issues)
+    }
+
+    /// Issues the current user watches, recently updated first.
+    pub async fn get_watched_issues(&self, max_results: u32, fields: &str) -> Result<Vec<JiraIssue>, JiraError> {
+        let jql = "watcher = currentUser() ORDER BY updated DESC";
+        let page = self.search_issues(jql, fields, 0, Some(max_results)).await?;
+        Ok(page.issues)
+    }
+
+    /// Issues the current user viewed lately, as JIRA's own "Recent issues"
+    /// menu lists them, at most `max_results`.
+    pub async fn get_recent_issues(&self, max_results: u32, fields: &str) -> Result<Vec<JiraIssue>, JiraError> {
+        let jql = "issuekey in issueHistory() ORDER BY lastViewed DESC";
+        let page = self.search_issues(jql, fields, 0, Some(max_results)).await?;
+        Ok(page.issues)
+    }
+
+    /// Adds the current user to `issue_key`'s watchers; without a body JIRA
+    /// adds whoever is calling.
+    pub async fn watch_issue(&self, issue_key: &str) -> Result<(), JiraError> {
+        let url = self.api_url(&format!("issue/{}/watchers", issue_key));
+
+        let authorization = self.authorization().await?;
+        let request = self.client
+            .post(&url)
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .header("Authorization", authorization);
+        self.send(request).await?;
+
+        Ok(())
+    }
+
+    /// Removes `user` from `issue_key`'s watchers. Cloud identifies them by
+    /// account id, Server and Data Center by username.
+    pub async fn unwatch_issue(&self, issue_key: &str, user: &JiraUser) -> Result<(), JiraError> {
+        let url = self.api_url(&format!("issue/{}/watchers", issue_key));
+        let query = match &user.name {
+            Some(username) => [("username", username.as_str())],
+            None => [("accountId", user.account_id.as_str())],
+        };
+
+        let authorization = self.authorization().await?;
+        let request = self.client
+            .delete(&url)
+            .header("Authorization", authorization)
+            .query(&query);
+        self.send(request).await?;
+
+        Ok(())
     }
 
     pub async fn create_worklog(
         &self,
         issue_key: &str,
-        description: &str,
+        comment: &adf::Document,
         started: &str,
         time_spent_seconds: u32,
         visibility: Option<WorklogVisibility>,
-    ) -> Result<WorklogResponse, Box<dyn std::error::Error>> {
-        let url = format!("{}/rest/api/3/issue/{}/worklog", self.base_url, issue_key);
-        
-        let worklog_request = WorklogRequest {
-            comment: WorklogComment {
-                doc_type: "doc".to_string(),
-                version: 1,
-                content: vec![WorklogParagraph {
-                    paragraph_type: "paragraph".to_string(),
-                    content: vec![WorklogText {
-                        text_type: "text".to_string(),
-                        text: description.to_string(),
-                    }],
-                }],
-            },
+    ) -> Result<WorklogResponse, JiraError> {
+        let url = self.api_url(&format!("issue/{}/worklog", issue_key));
+        let worklog_request = self.worklog_request(comment, started, time_spent_seconds, visibility);
+
+        let authorization = self.authorization().await?;
+        let request = self.client
+            .post(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Content-Type", "application/json")
+            .header("Authorization", authorization)
+            .json(&worklog_request);
+        let response = self.send(request).await?;
+
+        let worklog_response: WorklogResponse = Self::read_json("create_worklog", response).await?;
+        Ok(worklog_response)
+    }
+
+    /// The body `create_worklog` posts.
+    pub fn worklog_request(
+        &self,
+        comment: &adf::Document,
+        started: &str,
+        time_spent_seconds: u32,
+        visibility: Option<WorklogVisibility>,
+    ) -> WorklogRequest {
+        WorklogRequest {
+            comment: self.comment_format.body(comment),
             started: started.to_string(),
             time_spent_seconds,
             visibility,
+        }
+    }
+
+    pub async fn get_worklog(&self, issue_key: &str, worklog_id: &str) -> Result<Worklog, JiraError> {
+        let url = self.api_url(&format!("issue/{}/worklog/{}", issue_key, worklog_id));
+
+        let authorization = self.authorization().await?;
+        let request = self.client
+            .get(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Authorization", authorization);
+        let response = self.send(request).await?;
+
+        let worklog: Worklog = Self::read_json("worklog", response).await?;
+        Ok(worklog)
+    }
+
+    pub async fn update_worklog(
+        &self,
+        issue_key: &str,
+        worklog_id: &str,
+        update: &WorklogUpdateRequest,
+    ) -> Result<Worklog, JiraError> {
+        let url = self.api_url(&format!("issue/{}/worklog/{}", issue_key, worklog_id));
+
+        let authorization = self.authorization().await?;
+        let request = self.client
+            .put(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Content-Type", "application/json")
+            .header("Authorization", authorization)
+            .json(update);
+        let response = self.send(request).await?;
+
+        let worklog: Worklog = Self::read_json("update_worklog", response).await?;
+        Ok(worklog)
+    }
+
+    pub async fn delete_worklog(&self, issue_key: &str, worklog_id: &str) -> Result<(), JiraError> {
+        let url = self.api_url(&format!("issue/{}/worklog/{}", issue_key, worklog_id));
+
+        let authorization = self.authorization().await?;
+        let request = self.client
+            .delete(&url)
+            .header("Authorization", authorization);
+        self.send(request).await?;
+
+        Ok(())
+    }
+
+    pub async fn get_transitions(&self, issue_key: &str) -> Result<Vec<JiraTransition>, JiraError> {
+        let url = self.api_url(&format!("issue/{}/transitions", issue_key));
+
+        let authorization = self.authorization().await?;
+        let request = self.client
+            .get(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Authorization", authorization);
+        let response = self.send(request).await?;
+
+        let transitions: JiraTransitionsResponse = Self::read_json("transitions", response).await?;
+        Ok(transitions.transitions)
+    }
+
+    pub async fn transition_issue(&self, issue_key: &str, transition_id: &str) -> Result<(), JiraError> {
+        let url = self.api_url(&format!("issue/{}/transitions", issue_key));
+        let request = TransitionRequest {
+            transition: TransitionId {
+                id: transition_id.to_string(),
+            },
         };
 
-        let response = self.client
+        let authorization = self.authorization().await?;
+        let request = self.client
             .post(&url)
             .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
             .header("Content-Type", "application/json")
-            .basic_auth(&self.email, Some(&self.access_token))
-            .json(&worklog_request)
-            .send()
-            .await?;
+            .header("Authorization", authorization)
+            .json(&request);
+        self.send(request).await?;
+
+        Ok(())
+    }
+
+    pub async fn get_myself(&self) -> Result<JiraUser, JiraError> {
+        let url = self.api_url("myself");
+
+        let authorization = self.authorization().await?;
+        let request = self.client
+            .get(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Authorization", authorization);
+        let response = self.send(request).await?;
+
+        let user: JiraUser = Self::read_json("myself", response).await?;
+        Ok(user)
+    }
+
+    /// What the site says about itself; sent without credentials. Always
+    /// over REST v2, which every deployment has, since it tells which one
+    /// this is.
+    pub async fn server_info(&self) -> Result<ServerInfo, JiraError> {
+        let request = self
+            .client
+            .get(url::join(&self.base_url, url::ApiVersion::V2.root(), "serverInfo"))
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip");
+        let response = self.send(request).await?;
 
-        if !response.status().is_success() {
-            return Err(format!("JIRA API error: {}", response.status()).into());
+        Self::read_json("server_info", response).await
+    }
+
+    /// Fetches a single issue. `expand` takes JIRA's expand options, e.g.
+    /// `renderedFields` for HTML versions of the rich text fields.
+    pub async fn get_issue(&self, issue_key: &str, fields: &str, expand: &[&str]) -> Result<JiraIssue, JiraError> {
+        let url = self.api_url(&format!("issue/{}", issue_key));
+        let expand = expand.join(",");
+
+        let mut params = vec![("fields", fields)];
+        if !expand.is_empty() {
+            params.push(("expand", expand.as_str()));
         }
 
-        let worklog_response: WorklogResponse = response.json().await?;
-        Ok(worklog_response)
+        let authorization = self.authorization().await?;
+        let request = self.client
+            .get(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Authorization", authorization)
+            .query(&params);
+        let response = self.send(request).await?;
+
+        let issue: JiraIssue = Self::read_json("issue", response).await?;
+        Ok(issue)
     }
 
-    pub fn parse_time_to_seconds(time_str: &str) -> Result<u32, Box<dyn std::error::Error>> {
-        if time_str.is_empty() {
-            return Err("Time string is empty".into());
+    /// A page of `issue_key`'s comments, newest first.
+    pub async fn get_comments(
+        &self,
+        issue_key: &str,
+        start_at: u32,
+        max_results: u32,
+    ) -> Result<CommentListResponse, JiraError> {
+        let url = self.api_url(&format!("issue/{}/comment", issue_key));
+        let params = [
+            ("startAt", start_at.to_string()),
+            ("maxResults", max_results.to_string()),
+            ("orderBy", "-created".to_string()),
+            ("expand", "renderedBody".to_string()),
+        ];
+
+        let authorization = self.authorization().await?;
+        let request = self.client
+            .get(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Authorization", authorization)
+            .query(&params);
+        let response = self.send(request).await?;
+
+        Self::read_json("issue_comments", response).await
+    }
+
+    pub async fn add_comment(&self, issue_key: &str, body: &adf::Document) -> Result<IssueComment, JiraError> {
+        let url = self.api_url(&format!("issue/{}/comment", issue_key));
+        let comment_request = CommentRequest {
+            body: self.comment_format.body(body),
+        };
+
+        let authorization = self.authorization().await?;
+        let request = self.client
+            .post(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Content-Type", "application/json")
+            .header("Authorization", authorization)
+            .query(&[("expand", "renderedBody")])
+            .json(&comment_request);
+        let response = self.send(request).await?;
+
+        Self::read_json("add_comment", response).await
+    }
+
+    /// The id of the sprint custom field, which differs per site. Looked up
+    /// once per site.
+    pub async fn sprint_field_id(&self) -> Result<Option<String>, JiraError> {
+        let cache = SPRINT_FIELDS.get_or_init(Default::default);
+        if let Some(field_id) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(&self.base_url) {
+            return Ok(field_id.clone());
+        }
+
+        let url = self.api_url("field");
+
+        let authorization = self.authorization().await?;
+        let request = self.client
+            .get(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Authorization", authorization);
+        let response = self.send(request).await?;
+
+        let fields: Vec<JiraField> = Self::read_json("fields", response).await?;
+        let field_id = fields
+            .into_iter()
+            .find(|field| {
+                field
+                    .schema
+                    .as_ref()
+                    .and_then(|schema| schema.custom.as_deref())
+                    == Some(SPRINT_FIELD_TYPE)
+            })
+            .map(|field| field.id);
+        cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(self.base_url.clone(), field_id.clone());
+        Ok(field_id)
+    }
+
+    /// GETs `path` (relative to the site, query included) as untyped JSON.
+    pub async fn get_raw(&self, path: &str) -> Result<serde_json::Value, JiraError> {
+        let url = format!("{}{}", self.base_url, path);
+
+        let authorization = self.authorization().await?;
+        let request = self.client
+            .get(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Authorization", authorization);
+        let response = self.send(request).await?;
+        Self::read_json("raw", response).await
+    }
+
+    pub async fn search_issues(
+        &self,
+        jql: &str,
+        fields: &str,
+        start_at: u32,
+        max_results: Option<u32>,
+    ) -> Result<JiraSearchResponse, JiraError> {
+        let url = self.api_url("search");
+        let start_at = start_at.to_string();
+        let max_results = max_results.map(|max| max.to_string());
+
+        let mut params = HashMap::new();
+        params.insert("jql", jql);
+        params.insert("fields", fields);
+        params.insert("startAt", start_at.as_str());
+        if let Some(max_results) = &max_results {
+            params.insert("maxResults", max_results.as_str());
+        }
+
+        let authorization = self.authorization().await?;
+        let request = self.client
+            .get(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Authorization", authorization)
+            .query(&params);
+
+        // Polled every minute by the sync, usually unchanged.
+        self.get_json_cached("search", request).await
+    }
+
+    /// Fetches the worklogs of an issue, optionally restricted to those started
+    /// within `[started_after, started_before)` (epoch milliseconds).
+    pub async fn get_issue_worklogs(
+        &self,
+        issue_key: &str,
+        started_after: Option<i64>,
+        started_before: Option<i64>,
+    ) -> Result<Vec<Worklog>, JiraError> {
+        let url = self.api_url(&format!("issue/{}/worklog", issue_key));
+        let mut worklogs = Vec::new();
+
+        loop {
+            let mut params = vec![("startAt", worklogs.len().to_string())];
+            if let Some(after) = started_after {
+                params.push(("startedAfter", after.to_string()));
+            }
+            if let Some(before) = started_before {
+                params.push(("startedBefore", before.to_string()));
+            }
+
+            let authorization = self.authorization().await?;
+            let request = self.client
+                .get(&url)
+                .header("Accept", "application/json")
+                .header("Accept-Encoding", "gzip")
+                .header("Authorization", authorization)
+                .query(&params);
+            let response = self.send(request).await?;
+
+            let page: WorklogListResponse = Self::read_json("issue_worklogs", response).await?;
+            let fetched = page.worklogs.len();
+            worklogs.extend(page.worklogs);
+
+            if fetched == 0 || worklogs.len() as u32 >= page.total {
+                break;
+            }
+        }
+
+        Ok(worklogs)
+    }
+
+    /// Collects the current user's worklogs started between `from` and `to`
+    /// (both inclusive, local dates) across all issues.
+    pub async fn get_user_worklogs(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<UserWorklog>, JiraError> {
+        let myself = self.get_myself().await?;
+        let jql = format!(
+            "worklogAuthor = currentUser() AND worklogDate >= \"{}\" AND worklogDate <= \"{}\"",
+            from.format("%Y-%m-%d"),
+            to.format("%Y-%m-%d"),
+        );
+
+        let started_after = from
+            .and_time(NaiveTime::MIN)
+            .and_local_timezone(chrono::Local)
+            .earliest()
+            .map(|start| start.timestamp_millis());
+        let started_before = to
+            .succ_opt()
+            .and_then(|day| day.and_time(NaiveTime::MIN).and_local_timezone(chrono::Local).earliest())
+            .map(|end| end.timestamp_millis());
+
+        let mut issues = Vec::new();
+        loop {
+            let page = self.search_issues(&jql, field_sets::WORKLOG_ISSUES, issues.len() as u32, None).await?;
+            let fetched = page.issues.len();
+            issues.extend(page.issues);
+
+            if fetched == 0 || issues.len() as u32 >= page.total {
+                break;
+            }
         }
 
+        let mut user_worklogs = Vec::new();
+        for issue in issues {
+            let worklogs = self
+                .get_issue_worklogs(&issue.key, started_after, started_before)
+                .await?;
+
+            for worklog in worklogs {
+                let is_mine = worklog
+                    .author
+                    .as_ref()
+                    .is_some_and(|author| author.account_id == myself.account_id);
+                if is_mine {
+                    user_worklogs.push(UserWorklog {
+                        issue_key: issue.key.clone(),
+                        issue_summary: issue.fields.summary.clone(),
+                        worklog,
+                    });
+                }
+            }
+        }
+
+        user_worklogs.sort_by(|a, b| a.worklog.started.cmp(&b.worklog.started));
+        Ok(user_worklogs)
+    }
+
+    pub fn parse_jira_datetime(value: &str) -> Result<DateTime<FixedOffset>, JiraError> {
+        DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f%z")
+            .or_else(|_| DateTime::parse_from_rfc3339(value))
+            .map_err(|e| JiraError::Validation(format!("Invalid JIRA timestamp '{}': {}", value, e)))
+    }
+
+    pub fn format_jira_datetime<Tz: chrono::TimeZone>(value: &DateTime<Tz>) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        value.format("%Y-%m-%dT%H:%M:%S%.3f%z").to_string()
+    }
+
+    /// Checks for the `PROJECT-123` shape of a JIRA issue key.
+    pub fn is_valid_issue_key(key: &str) -> bool {
+        let Some((project, number)) = key.split_once('-') else {
+            return false;
+        };
+
+        project.starts_with(|c: char| c.is_ascii_uppercase())
+            && project.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+            && !number.is_empty()
+            && number.chars().all(|c| c.is_ascii_digit())
+    }
+
+    /// Reads JIRA-style durations: `1d 2h 30m` (also without spaces), `1.5h`,
+    /// `1:30` for hours and minutes, or a bare number in the default unit.
+    /// Days are `hours_per_day` long. The result is rounded to the second and
+    /// has to be positive.
+    pub fn parse_time_to_seconds(time_str: &str, units: &TimeUnitSettings) -> Result<u32, JiraError> {
+        let invalid = |reason: &str| JiraError::Validation(format!("Invalid time '{}': {}", time_str.trim(), reason));
         let time_str = time_str.trim();
-        let (number_part, unit_part) = if time_str.ends_with('h') {
-            (&time_str[..time_str.len()-1], "h")
-        } else if time_str.ends_with('m') {
-            (&time_str[..time_str.len()-1], "m")
-        } else if time_str.ends_with('d') {
-            (&time_str[..time_str.len()-1], "d")
+        if time_str.is_empty() {
+            return Err(JiraError::Validation("Time string is empty".to_string()));
+        }
+        if time_str.starts_with('-') {
+            return Err(invalid("it must be positive"));
+        }
+
+        let seconds = if let Some((hours, minutes)) = time_str.split_once(':') {
+            let all_digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+            if !all_digits(hours) || !all_digits(minutes) || minutes.len() != 2 {
+                return Err(invalid("use hours:minutes, e.g. 1:30"));
+            }
+            let hours: f64 = hours.parse().map_err(|_| invalid("too many hours"))?;
+            let minutes: f64 = minutes.parse().map_err(|_| invalid("use hours:minutes, e.g. 1:30"))?;
+            if minutes >= 60.0 {
+                return Err(invalid("minutes must be below 60"));
+            }
+            hours * 3600.0 + minutes * 60.0
+        } else if time_str.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            let number: f64 = time_str.parse().map_err(|_| invalid("not a number"))?;
+            number * units.seconds_per(units.default_unit)
         } else {
-            return Err("Invalid time format. Use 'h' for hours, 'm' for minutes, 'd' for days".into());
+            Self::parse_compound_duration(time_str, units).map_err(|reason| invalid(&reason))?
         };
 
-        let number: f32 = number_part.parse()?;
-        
-        let seconds = match unit_part {
-            "h" => (number * 3600.0) as u32,
-            "m" => (number * 60.0) as u32,
-            "d" => (number * 8.0 * 3600.0) as u32,
-            _ => return Err("Invalid time unit".into()),
-        };
+        if !seconds.is_finite() || seconds > u32::MAX as f64 {
+            return Err(invalid("it is too long"));
+        }
+        match seconds.round() as u32 {
+            0 => Err(invalid("it must be greater than zero")),
+            seconds => Ok(seconds),
+        }
+    }
+
+    /// Sums `<number><unit>` parts, each unit at most once.
+    fn parse_compound_duration(time_str: &str, units: &TimeUnitSettings) -> Result<f64, String> {
+        let mut seen = Vec::new();
+        let mut seconds = 0.0;
+        let mut rest = time_str;
+
+        while !rest.is_empty() {
+            let number_len = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            let (number, after) = rest.split_at(number_len);
+            let after = after.trim_start();
+            let unit = match after.chars().next().map(|c| c.to_ascii_lowercase()) {
+                Some('d') => TimeUnit::Days,
+                Some('h') => TimeUnit::Hours,
+                Some('m') => TimeUnit::Minutes,
+                _ => return Err("use d for days, h for hours and m for minutes".to_string()),
+            };
+            let number: f64 = number
+                .parse()
+                .map_err(|_| format!("'{}' is not a number", number))?;
+            if seen.contains(&unit) {
+                return Err("each unit may appear only once".to_string());
+            }
+            seen.push(unit);
+
+            seconds += number * units.seconds_per(unit);
+            rest = after[1..].trim_start();
+        }
 
         Ok(seconds)
     }
 
-    pub async fn test_connection(&self) -> Result<bool, Box<dyn std::error::Error>> {
-        let url = format!("{}/rest/api/3/myself", self.base_url);
+    /// The reverse of `parse_time_to_seconds`, e.g. `1d 2h 30m`, rounded to
+    /// the minute. Days are `hours_per_day` long.
+    pub fn format_seconds_to_jira_duration(seconds: u32, units: &TimeUnitSettings) -> String {
+        let minutes = (seconds as u64 + 30) / 60;
+        let minutes_per_day = (units.hours_per_day * 60.0).round().max(1.0) as u64;
+        let days = minutes / minutes_per_day;
+        let hours = minutes % minutes_per_day / 60;
+        let minutes = minutes % minutes_per_day % 60;
+
+        let parts: Vec<String> = [(days, 'd'), (hours, 'h'), (minutes, 'm')]
+            .into_iter()
+            .filter(|(value, _)| *value > 0)
+            .map(|(value, unit)| format!("{}{}", value, unit))
+            .collect();
+        if parts.is_empty() {
+            "0m".to_string()
+        } else {
+            parts.join(" ")
+        }
+    }
+
+    pub async fn test_connection(&self) -> Result<bool, JiraError> {
+        let url = self.api_url("myself");
         
-        let response = self.client
+        let authorization = self.authorization().await?;
+        let request = self.client
             .get(&url)
             .header("Accept", "application/json")
-            .basic_auth(&self.email, Some(&self.access_token))
-            .send()
-            .await?;
+            .header("Accept-Encoding", "gzip")
+            .header("Authorization", authorization);
 
-        Ok(response.status().is_success())
+        match self.send(request).await {
+            Ok(_) => Ok(true),
+            Err(JiraError::Network(e)) => Err(JiraError::Network(e)),
+            Err(JiraError::Offline) => Err(JiraError::Offline),
+            Err(_) => Ok(false),
+        }
     }
-}
\ No newline at end of file
+}
+
+impl JiraApi for JiraClient {
+    async fn assigned_issues(&self) -> Result<Vec<JiraIssue>, JiraError> {
+        self.get_assigned_issues(field_sets::ISSUE_LIST).await
+    }
+
+    async fn user_worklogs(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<UserWorklog>, JiraError> {
+        self.get_user_worklogs(from, to).await
+    }
+
+    /// Sprints live in the agile API, which REST refreshes don't query, so
+    /// `sprint` is always empty here.
+    async fn overview(&self, from: NaiveDate, to: NaiveDate) -> Result<JiraOverview, JiraError> {
+        let issues = self.assigned_issues().await?;
+        let worklogs = self.user_worklogs(from, to).await?;
+        Ok(JiraOverview {
+            issues,
+            worklogs,
+            sprint: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(time_str: &str) -> Result<u32, JiraError> {
+        JiraClient::parse_time_to_seconds(time_str, &TimeUnitSettings::default())
+    }
+
+    fn format(seconds: u32) -> String {
+        JiraClient::format_seconds_to_jira_duration(seconds, &TimeUnitSettings::default())
+    }
+
+    #[test]
+    fn parses_single_units() {
+        assert_eq!(parse("2h").unwrap(), 7200);
+        assert_eq!(parse("30m").unwrap(), 1800);
+        assert_eq!(parse("1d").unwrap(), 8 * 3600);
+        assert_eq!(parse(" 2H ").unwrap(), 7200);
+    }
+
+    #[test]
+    fn parses_compound_durations() {
+        assert_eq!(parse("1d 2h 30m").unwrap(), 8 * 3600 + 2 * 3600 + 1800);
+        assert_eq!(parse("1h30m").unwrap(), 5400);
+        assert_eq!(parse("30m 1h").unwrap(), 5400);
+        assert_eq!(parse("1 h 15 m").unwrap(), 4500);
+    }
+
+    #[test]
+    fn keeps_fractions() {
+        assert_eq!(parse("1.5h").unwrap(), 5400);
+        assert_eq!(parse("0.25h").unwrap(), 900);
+        assert_eq!(parse("1.5m").unwrap(), 90);
+        assert_eq!(parse(".5d").unwrap(), 4 * 3600);
+    }
+
+    #[test]
+    fn bare_numbers_use_the_default_unit() {
+        assert_eq!(parse("45").unwrap(), 45 * 3600);
+        let minutes = TimeUnitSettings {
+            default_unit: TimeUnit::Minutes,
+            ..Default::default()
+        };
+        assert_eq!(JiraClient::parse_time_to_seconds("45", &minutes).unwrap(), 2700);
+    }
+
+    #[test]
+    fn parses_colon_notation() {
+        assert_eq!(parse("1:30").unwrap(), 5400);
+        assert_eq!(parse("0:05").unwrap(), 300);
+        assert_eq!(parse("10:00").unwrap(), 36000);
+        assert!(parse("1:5").is_err());
+        assert!(parse("1:60").is_err());
+        assert!(parse(":30").is_err());
+        assert!(parse("1:30:00").is_err());
+    }
+
+    #[test]
+    fn days_follow_the_configured_length() {
+        let units = TimeUnitSettings {
+            hours_per_day: 7.5,
+            ..Default::default()
+        };
+        assert_eq!(JiraClient::parse_time_to_seconds("1d", &units).unwrap(), 27000);
+        assert_eq!(JiraClient::parse_time_to_seconds("2d 1h", &units).unwrap(), 57600);
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for time_str in ["", "  ", "-1h", "-30", "0", "0h 0m", "0:00", "h", "2x", "2 hours", "1h 2h", "1.2.3h", "nan", "inf", "1h-5m"] {
+            assert!(parse(time_str).is_err(), "accepted '{}'", time_str);
+        }
+        assert!(parse("999999999d").is_err());
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format(0), "0m");
+        assert_eq!(format(29), "0m");
+        assert_eq!(format(30), "1m");
+        assert_eq!(format(1800), "30m");
+        assert_eq!(format(5400), "1h 30m");
+        assert_eq!(format(8 * 3600), "1d");
+        assert_eq!(format(8 * 3600 + 2 * 3600 + 1800), "1d 2h 30m");
+        assert_eq!(format(20 * 3600 + 60), "2d 4h 1m");
+
+        let units = TimeUnitSettings {
+            hours_per_day: 7.5,
+            ..Default::default()
+        };
+        assert_eq!(JiraClient::format_seconds_to_jira_duration(27000 + 3600, &units), "1d 1h");
+    }
+
+    #[test]
+    fn formatting_round_trips() {
+        for seconds in [60, 900, 5400, 8 * 3600, 37800, 100 * 3600 + 60] {
+            assert_eq!(parse(&format(seconds)).unwrap(), seconds);
+        }
+    }
+}
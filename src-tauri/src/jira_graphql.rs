@@ -0,0 +1,402 @@
+//! Experimental backend that fetches a whole refresh through the Atlassian
+//! GraphQL gateway: assigned issues, their worklogs and sprints come back in
+//! one request instead of one search plus one request per issue. Cloud only.
+
+use chrono::{Local, NaiveDate};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use crate::error::JiraError;
+use crate::jira_api::{JiraApi, JiraAuth, JiraClient};
+use crate::jira_types::*;
+
+const OAUTH_GRAPHQL_URL: &str = "https://api.atlassian.com/graphql";
+/// Issues per search; a refresh is a single page.
+const PAGE_SIZE: u32 = 100;
+/// Worklogs per issue; older ones are cut off.
+const WORKLOGS_PER_ISSUE: u32 = 100;
+
+const OVERVIEW_QUERY: &str = r#"
+query MiniJiraOverview($cloudId: ID!, $jql: String!, $first: Int!, $worklogs: Int!) {
+  me { user { accountId name } }
+  jira {
+    issueSearchStable(cloudId: $cloudId, issueSearchInput: { jql: $jql }, first: $first) {
+      edges {
+        node {
+          key
+          issueId
+          fieldsById(ids: ["summary", "status", "assignee", "sprint"]) {
+            edges {
+              node {
+                __typename
+                ... on JiraSingleLineTextField { text }
+                ... on JiraStatusField { status { name } }
+                ... on JiraSingleSelectUserPickerField { user { accountId name } }
+                ... on JiraSprintField {
+                  selectedSprintsConnection { edges { node { name state endDate } } }
+                }
+              }
+            }
+          }
+          worklogs(first: $worklogs) {
+            edges {
+              node {
+                worklogId
+                author { accountId name }
+                timeSpent { timeInSeconds }
+                startDate
+              }
+            }
+          }
+        }
+      }
+    }
+  }
+}
+"#;
+
+/// Cloud ids by site URL; they never change, so each site is looked up once.
+static CLOUD_IDS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+#[derive(Deserialize)]
+struct Connection<T> {
+    edges: Vec<Edge<T>>,
+}
+
+#[derive(Deserialize)]
+struct Edge<T> {
+    node: Option<T>,
+}
+
+impl<T> Connection<T> {
+    fn into_nodes(self) -> impl Iterator<Item = T> {
+        self.edges.into_iter().filter_map(|edge| edge.node)
+    }
+}
+
+#[derive(Deserialize)]
+struct GraphqlResponse {
+    data: Option<OverviewData>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphqlError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct OverviewData {
+    me: Me,
+    jira: JiraRoot,
+}
+
+#[derive(Deserialize)]
+struct Me {
+    user: GraphqlUser,
+}
+
+#[derive(Deserialize)]
+struct GraphqlUser {
+    #[serde(rename = "accountId")]
+    account_id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct JiraRoot {
+    #[serde(rename = "issueSearchStable")]
+    issue_search: Connection<GraphqlIssue>,
+}
+
+#[derive(Deserialize)]
+struct GraphqlIssue {
+    key: String,
+    #[serde(rename = "issueId")]
+    issue_id: String,
+    #[serde(rename = "fieldsById")]
+    fields: Connection<GraphqlField>,
+    worklogs: Connection<GraphqlWorklog>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "__typename")]
+enum GraphqlField {
+    JiraSingleLineTextField {
+        text: Option<String>,
+    },
+    JiraStatusField {
+        status: Option<IssueStatus>,
+    },
+    JiraSingleSelectUserPickerField {
+        user: Option<GraphqlUser>,
+    },
+    JiraSprintField {
+        #[serde(rename = "selectedSprintsConnection")]
+        sprints: Option<Connection<GraphqlSprint>>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct GraphqlSprint {
+    name: String,
+    state: String,
+    #[serde(rename = "endDate")]
+    end_date: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GraphqlWorklog {
+    #[serde(rename = "worklogId")]
+    worklog_id: String,
+    author: Option<GraphqlUser>,
+    #[serde(rename = "timeSpent")]
+    time_spent: Option<TimeSpent>,
+    #[serde(rename = "startDate")]
+    start_date: String,
+}
+
+#[derive(Deserialize)]
+struct TimeSpent {
+    #[serde(rename = "timeInSeconds")]
+    time_in_seconds: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct TenantInfo {
+    #[serde(rename = "cloudId")]
+    cloud_id: String,
+}
+
+/// An issue with the fields the overview needs, before it is split into
+/// assigned issues and worklogs.
+struct OverviewIssue {
+    issue: JiraIssue,
+    issue_id: String,
+    assignee_id: Option<String>,
+    sprints: Vec<GraphqlSprint>,
+    worklogs: Vec<GraphqlWorklog>,
+}
+
+impl From<GraphqlIssue> for OverviewIssue {
+    fn from(node: GraphqlIssue) -> Self {
+        let mut summary = String::new();
+        let mut status = IssueStatus::default();
+        let mut assignee = None;
+        let mut sprints = Vec::new();
+
+        for field in node.fields.into_nodes() {
+            match field {
+                GraphqlField::JiraSingleLineTextField { text: Some(text) } => summary = text,
+                GraphqlField::JiraStatusField { status: Some(value) } => status = value,
+                GraphqlField::JiraSingleSelectUserPickerField { user: Some(user) } => assignee = Some(user),
+                GraphqlField::JiraSprintField { sprints: Some(selected) } => sprints.extend(selected.into_nodes()),
+                _ => {}
+            }
+        }
+
+        OverviewIssue {
+            issue: JiraIssue {
+                id: node.issue_id.clone(),
+                key: node.key,
+                fields: IssueFields {
+                    summary,
+                    status,
+                    assignee: assignee.as_ref().map(|user| IssueAssignee {
+                        display_name: user.name.clone(),
+                        // Not exposed by the gateway without extra scopes.
+                        email_address: String::new(),
+                        avatar_urls: None,
+                    }),
+                    ..Default::default()
+                },
+                rendered_fields: None,
+            },
+            issue_id: node.issue_id,
+            assignee_id: assignee.map(|user| user.account_id),
+            sprints,
+            worklogs: node.worklogs.into_nodes().collect(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct JiraGraphqlClient {
+    rest: JiraClient,
+    endpoint: String,
+    cloud_id: String,
+}
+
+impl JiraGraphqlClient {
+    /// Wraps a connected REST client. API token connections talk to the
+    /// gateway of their site, OAuth connections to api.atlassian.com.
+    pub async fn connect(rest: JiraClient) -> Result<Self, JiraError> {
+        let (endpoint, cloud_id) = match &rest.auth {
+            JiraAuth::OAuth(_) => {
+                let cloud_id = rest
+                    .base_url
+                    .rsplit('/')
+                    .next()
+                    .filter(|id| !id.is_empty())
+                    .ok_or("OAuth connection has no cloud id")?
+                    .to_string();
+                (OAUTH_GRAPHQL_URL.to_string(), cloud_id)
+            }
+            JiraAuth::Basic { .. } => {
+                let site = rest.base_url.trim_end_matches('/');
+                (format!("{}/gateway/api/graphql", site), Self::site_cloud_id(&rest, site).await?)
+            }
+        };
+
+        Ok(Self {
+            rest,
+            endpoint,
+            cloud_id,
+        })
+    }
+
+    async fn site_cloud_id(rest: &JiraClient, site: &str) -> Result<String, JiraError> {
+        let cache = CLOUD_IDS.get_or_init(Default::default);
+        if let Some(cloud_id) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(site) {
+            return Ok(cloud_id.clone());
+        }
+
+        rest.offline().check()?;
+        let response = rest
+            .http_client()
+            .get(format!("{}/_edge/tenant_info", site))
+            .header("Accept", "application/json")
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(format!("Not a JIRA Cloud site ({}), GraphQL is unavailable", response.status()).into());
+        }
+
+        let tenant: TenantInfo = response.json().await?;
+        cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(site.to_string(), tenant.cloud_id.clone());
+        Ok(tenant.cloud_id)
+    }
+
+    async fn query_overview(&self, jql: &str) -> Result<(GraphqlUser, Vec<OverviewIssue>), JiraError> {
+        let body = json!({
+            "query": OVERVIEW_QUERY,
+            "variables": {
+                "cloudId": self.cloud_id,
+                "jql": jql,
+                "first": PAGE_SIZE,
+                "worklogs": WORKLOGS_PER_ISSUE,
+            },
+        });
+
+        let authorization = self.rest.authorization().await?;
+        let request = self.rest
+            .http_client()
+            .post(&self.endpoint)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Authorization", authorization)
+            .json(&body);
+        let response = self.rest.send(request).await?;
+
+        let response: GraphqlResponse = JiraClient::read_json("graphql_overview", response).await?;
+        if !response.errors.is_empty() {
+            let messages: Vec<String> = response.errors.into_iter().map(|error| error.message).collect();
+            return Err(format!("JIRA GraphQL error: {}", messages.join("; ")).into());
+        }
+
+        let data = response
+            .data
+            .ok_or_else(|| JiraError::Deserialize("GraphQL response has no data".to_string()))?;
+        let issues = data.jira.issue_search.into_nodes().map(OverviewIssue::from).collect();
+        Ok((data.me.user, issues))
+    }
+}
+
+fn overview_jql(from: NaiveDate, to: NaiveDate) -> String {
+    format!(
+        "assignee = currentUser() OR (worklogAuthor = currentUser() AND worklogDate >= \"{}\" AND worklogDate <= \"{}\")",
+        from.format("%Y-%m-%d"),
+        to.format("%Y-%m-%d"),
+    )
+}
+
+fn split_overview(me: &GraphqlUser, issues: Vec<OverviewIssue>, from: NaiveDate, to: NaiveDate) -> JiraOverview {
+    let mut overview = JiraOverview {
+        issues: Vec::new(),
+        worklogs: Vec::new(),
+        sprint: None,
+    };
+
+    for issue in issues {
+        for worklog in issue.worklogs {
+            let Some(author) = worklog.author.filter(|author| author.account_id == me.account_id) else {
+                continue;
+            };
+            let in_range = JiraClient::parse_jira_datetime(&worklog.start_date)
+                .map(|started| started.with_timezone(&Local).date_naive())
+                .is_ok_and(|day| (from..=to).contains(&day));
+            if !in_range {
+                continue;
+            }
+
+            overview.worklogs.push(UserWorklog {
+                issue_key: issue.issue.key.clone(),
+                issue_summary: issue.issue.fields.summary.clone(),
+                worklog: Worklog {
+                    id: worklog.worklog_id,
+                    issue_id: issue.issue_id.clone(),
+                    author: Some(JiraUser {
+                        account_id: author.account_id,
+                        display_name: author.name,
+                        email_address: None,
+                        name: None,
+                    }),
+                    // Comments need a second, per-worklog query; they are
+                    // left to the REST endpoints that show them.
+                    comment: None,
+                    started: worklog.start_date,
+                    time_spent_seconds: worklog.time_spent.and_then(|spent| spent.time_in_seconds).unwrap_or(0),
+                },
+            });
+        }
+
+        if issue.assignee_id.as_deref() != Some(me.account_id.as_str()) {
+            continue;
+        }
+        if overview.sprint.is_none() {
+            overview.sprint = issue.sprints.into_iter().find(|sprint| sprint.state == "ACTIVE").map(|sprint| SprintInfo {
+                name: sprint.name,
+                state: sprint.state,
+                end_date: sprint.end_date,
+            });
+        }
+        overview.issues.push(issue.issue);
+    }
+
+    overview.worklogs.sort_by(|a, b| a.worklog.started.cmp(&b.worklog.started));
+    overview
+}
+
+impl JiraApi for JiraGraphqlClient {
+    async fn assigned_issues(&self) -> Result<Vec<JiraIssue>, JiraError> {
+        let today = Local::now().date_naive();
+        Ok(self.overview(today, today).await?.issues)
+    }
+
+    async fn user_worklogs(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<UserWorklog>, JiraError> {
+        Ok(self.overview(from, to).await?.worklogs)
+    }
+
+    async fn overview(&self, from: NaiveDate, to: NaiveDate) -> Result<JiraOverview, JiraError> {
+        let (me, issues) = self.query_overview(&overview_jql(from, to)).await?;
+        Ok(split_overview(&me, issues, from, to))
+    }
+}
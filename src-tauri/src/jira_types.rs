@@ -1,34 +1,151 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::adf;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JiraIssue {
+    /// Numeric id; Tempo refers to issues by it.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
     pub key: String,
     pub fields: IssueFields,
+    /// HTML renderings of the fields, only present with `expand=renderedFields`.
+    #[serde(rename = "renderedFields", default, skip_serializing_if = "Option::is_none")]
+    pub rendered_fields: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Summary and status are part of every `fields=` set; everything else is
+/// only filled in when its field was requested.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct IssueFields {
     pub summary: String,
     pub status: IssueStatus,
+    #[serde(default)]
     pub assignee: Option<IssueAssignee>,
+    /// ADF on Cloud, wiki markup on Server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<IssuePriority>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    #[serde(rename = "duedate", default, skip_serializing_if = "Option::is_none")]
+    pub due_date: Option<String>,
+    /// When the issue last moved between To Do, In Progress and Done.
+    #[serde(rename = "statuscategorychangedate", default, skip_serializing_if = "Option::is_none")]
+    pub status_category_changed: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subtasks: Vec<JiraIssue>,
+    /// A sub-task's issue, or on Cloud a story's epic.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<IssueParent>,
+    #[serde(rename = "issuetype", default, skip_serializing_if = "Option::is_none")]
+    pub issue_type: Option<IssueKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<IssueCommentPage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watches: Option<IssueWatches>,
+    /// Custom fields by id (`customfield_10020`); their ids differ per site.
+    #[serde(flatten)]
+    pub custom: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl IssueFields {
+    /// Sprints stored in the sprint custom field `field_id`. Cloud returns
+    /// objects, older Server versions `Sprint@1a2b[id=1,state=ACTIVE,...]`
+    /// strings.
+    pub fn sprints(&self, field_id: &str) -> Vec<SprintInfo> {
+        let Some(serde_json::Value::Array(values)) = self.custom.get(field_id) else {
+            return Vec::new();
+        };
+
+        values
+            .iter()
+            .filter_map(|value| match value {
+                serde_json::Value::String(text) => SprintInfo::from_server_string(text),
+                value => serde_json::from_value::<SprintInfo>(value.clone()).ok(),
+            })
+            .map(|mut sprint| {
+                sprint.state = sprint.state.to_uppercase();
+                sprint
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct IssueStatus {
     pub name: String,
+    #[serde(rename = "statusCategory", default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<StatusCategory>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct IssueAssignee {
-    #[serde(rename = "displayName")]
-    pub display_name: String,
-    #[serde(rename = "emailAddress")]
-    pub email_address: String,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusCategory {
+    /// `new`, `indeterminate` or `done`.
+    pub key: String,
+    pub name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct JiraSearchResponse {
-    pub issues: Vec<JiraIssue>,
+/// The `watches` field.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueWatches {
+    #[serde(rename = "isWatching")]
+    pub is_watching: bool,
+    #[serde(rename = "watchCount", default)]
+    pub watch_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuePriority {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueParent {
+    pub key: String,
+    #[serde(default)]
+    pub fields: IssueParentFields,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IssueParentFields {
+    #[serde(default)]
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueKind {
+    pub name: String,
+    #[serde(default)]
+    pub subtask: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueComment {
+    pub id: String,
+    pub author: Option<JiraUser>,
+    pub body: Option<serde_json::Value>,
+    pub created: String,
+    pub updated: Option<String>,
+    /// With `expand=renderedBody`.
+    #[serde(rename = "renderedBody")]
+    pub rendered_body: Option<String>,
+}
+
+/// The `comment` field: the issue's comments, oldest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueCommentPage {
+    pub comments: Vec<IssueComment>,
+    #[serde(default)]
+    pub total: u32,
+}
+
+/// A page of `/rest/api/3/issue/{key}/comment`.
+#[derive(Debug, Deserialize)]
+pub struct CommentListResponse {
+    pub comments: Vec<IssueComment>,
     pub total: u32,
     #[serde(rename = "startAt")]
     pub start_at: u32,
@@ -36,26 +153,104 @@ pub struct JiraSearchResponse {
     pub max_results: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct WorklogComment {
-    #[serde(rename = "type")]
-    pub doc_type: String,
-    pub version: u32,
-    pub content: Vec<WorklogParagraph>,
+#[derive(Debug, Serialize)]
+pub struct CommentRequest {
+    pub body: adf::CommentBody,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct WorklogParagraph {
-    #[serde(rename = "type")]
-    pub paragraph_type: String,
-    pub content: Vec<WorklogText>,
+/// A comment as `get_comments` and `add_comment` return it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Comment {
+    pub id: String,
+    pub author: Option<JiraUser>,
+    /// The body as plain text.
+    pub body: String,
+    pub body_html: Option<String>,
+    pub created: String,
+    pub updated: Option<String>,
+}
+
+impl From<IssueComment> for Comment {
+    fn from(comment: IssueComment) -> Self {
+        Self {
+            body: comment.body.as_ref().map(adf::text_of).unwrap_or_default(),
+            body_html: comment.rendered_body,
+            id: comment.id,
+            author: comment.author,
+            created: comment.created,
+            updated: comment.updated,
+        }
+    }
+}
+
+/// One page of an issue's comments, newest first.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommentPage {
+    pub comments: Vec<Comment>,
+    pub start_at: u32,
+    pub max_results: u32,
+    pub total: u32,
+}
+
+impl From<CommentListResponse> for CommentPage {
+    fn from(page: CommentListResponse) -> Self {
+        Self {
+            comments: page.comments.into_iter().map(Comment::from).collect(),
+            start_at: page.start_at,
+            max_results: page.max_results,
+            total: page.total,
+        }
+    }
+}
+
+/// `/rest/api/3/serverInfo`, readable without signing in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerInfo {
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    /// `Cloud` or `Server`; Data Center reports `Server` too.
+    #[serde(default)]
+    pub deployment_type: Option<String>,
+    #[serde(default)]
+    pub server_title: Option<String>,
+}
+
+/// An entry of `/rest/api/3/field`.
+#[derive(Debug, Deserialize)]
+pub struct JiraField {
+    pub id: String,
+    pub schema: Option<JiraFieldSchema>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JiraFieldSchema {
+    /// Plugin key of custom fields, e.g. `com.pyxis.greenhopper.jira:gh-sprint`.
+    pub custom: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueAssignee {
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+    /// Empty when the user hides their email, the Cloud default.
+    #[serde(rename = "emailAddress", default)]
+    pub email_address: String,
+    /// Avatar URL by size, e.g. `48x48`.
+    #[serde(rename = "avatarUrls", default, skip_serializing_if = "Option::is_none")]
+    pub avatar_urls: Option<HashMap<String, String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct WorklogText {
-    #[serde(rename = "type")]
-    pub text_type: String,
-    pub text: String,
+pub struct JiraSearchResponse {
+    pub issues: Vec<JiraIssue>,
+    pub total: u32,
+    #[serde(rename = "startAt")]
+    pub start_at: u32,
+    #[serde(rename = "maxResults")]
+    pub max_results: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -67,13 +262,25 @@ pub struct WorklogVisibility {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WorklogRequest {
-    pub comment: WorklogComment,
+    pub comment: adf::CommentBody,
     pub started: String,
     #[serde(rename = "timeSpentSeconds")]
     pub time_spent_seconds: u32,
     pub visibility: Option<WorklogVisibility>,
 }
 
+/// Partial update of an existing worklog; fields left as `None` keep their
+/// current value.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct WorklogUpdateRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<adf::CommentBody>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started: Option<String>,
+    #[serde(rename = "timeSpentSeconds", skip_serializing_if = "Option::is_none")]
+    pub time_spent_seconds: Option<u32>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WorklogResponse {
     pub id: String,
@@ -83,3 +290,243 @@ pub struct WorklogResponse {
     #[serde(rename = "timeSpentSeconds")]
     pub time_spent_seconds: u32,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JiraUser {
+    /// Server and Data Center have no account ids; their user key is just as
+    /// unique.
+    #[serde(rename = "accountId", alias = "key")]
+    pub account_id: String,
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+    #[serde(rename = "emailAddress")]
+    pub email_address: Option<String>,
+    /// The username on Server and Data Center, which some endpoints take
+    /// instead of the key; absent on Cloud.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Worklog {
+    pub id: String,
+    #[serde(rename = "issueId")]
+    pub issue_id: String,
+    pub author: Option<JiraUser>,
+    pub comment: Option<serde_json::Value>,
+    pub started: String,
+    #[serde(rename = "timeSpentSeconds")]
+    pub time_spent_seconds: u32,
+}
+
+impl Worklog {
+    /// Flattens the comment into plain text.
+    pub fn comment_text(&self) -> String {
+        self.comment.as_ref().map(adf::text_of).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorklogListResponse {
+    pub worklogs: Vec<Worklog>,
+    pub total: u32,
+    #[serde(rename = "startAt")]
+    pub start_at: u32,
+    #[serde(rename = "maxResults")]
+    pub max_results: u32,
+}
+
+/// A worklog of the current user together with the issue it was logged on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserWorklog {
+    pub issue_key: String,
+    pub issue_summary: String,
+    pub worklog: Worklog,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SprintInfo {
+    pub name: String,
+    /// `ACTIVE`, `FUTURE` or `CLOSED`.
+    pub state: String,
+    #[serde(alias = "endDate", default)]
+    pub end_date: Option<String>,
+}
+
+impl SprintInfo {
+    fn from_server_string(text: &str) -> Option<Self> {
+        let attributes = text.split_once('[')?.1.trim_end_matches(']');
+        let mut name = None;
+        let mut state = None;
+        let mut end_date = None;
+        for pair in attributes.split(',') {
+            match pair.split_once('=') {
+                Some(("name", value)) => name = Some(value.to_string()),
+                Some(("state", value)) => state = Some(value.to_string()),
+                Some(("endDate", value)) if value != "<null>" => end_date = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        Some(Self {
+            name: name?,
+            state: state?,
+            end_date,
+        })
+    }
+}
+
+/// A board of `/rest/agile/1.0/board`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Board {
+    pub id: u64,
+    pub name: String,
+    /// `scrum`, `kanban` or `simple`; only scrum boards have sprints.
+    #[serde(rename = "type")]
+    pub board_type: String,
+    #[serde(default)]
+    pub location: Option<BoardLocation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardLocation {
+    #[serde(default)]
+    pub project_key: Option<String>,
+    #[serde(default)]
+    pub project_name: Option<String>,
+}
+
+/// A sprint of the agile API; `SprintInfo` is the sprint field of an issue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sprint {
+    pub id: u64,
+    pub name: String,
+    /// `active`, `future` or `closed`.
+    pub state: String,
+    #[serde(default)]
+    pub start_date: Option<String>,
+    #[serde(default)]
+    pub end_date: Option<String>,
+    #[serde(default)]
+    pub goal: Option<String>,
+    #[serde(default)]
+    pub origin_board_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueDetailComment {
+    pub id: String,
+    pub author: Option<String>,
+    pub body: String,
+    pub body_html: Option<String>,
+    pub created: String,
+}
+
+/// What the issue detail panel shows.
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueDetail {
+    pub key: String,
+    pub summary: String,
+    pub status: String,
+    pub assignee: Option<IssueAssignee>,
+    pub description: String,
+    pub description_html: Option<String>,
+    pub priority: Option<String>,
+    pub labels: Vec<String>,
+    /// The active sprint, otherwise the latest one the issue was in.
+    pub sprint: Option<SprintInfo>,
+    pub due_date: Option<String>,
+    pub subtasks: Vec<JiraIssue>,
+    /// Newest first.
+    pub comments: Vec<IssueDetailComment>,
+    pub comment_total: u32,
+}
+
+impl IssueDetail {
+    /// Builds the panel from an issue fetched with `field_sets::DETAIL` and
+    /// rendered fields, keeping the `latest_comments` newest comments.
+    pub fn from_issue(issue: JiraIssue, sprint_field: Option<&str>, latest_comments: usize) -> Self {
+        let rendered = issue.rendered_fields.unwrap_or_default();
+        let rendered_html = |pointer: &str| rendered.pointer(pointer).and_then(|html| html.as_str()).map(str::to_string);
+
+        let fields = issue.fields;
+        let mut sprints = sprint_field.map(|id| fields.sprints(id)).unwrap_or_default();
+        let active = sprints.iter().position(|sprint| sprint.state == "ACTIVE");
+        let sprint = match active {
+            Some(index) => Some(sprints.swap_remove(index)),
+            None => sprints.pop(),
+        };
+
+        let (comments, comment_total) = match fields.comment {
+            Some(page) => {
+                let total = page.total.max(page.comments.len() as u32);
+                let skip = page.comments.len().saturating_sub(latest_comments);
+                let comments = page
+                    .comments
+                    .into_iter()
+                    .enumerate()
+                    .skip(skip)
+                    .rev()
+                    .map(|(index, comment)| IssueDetailComment {
+                        body: comment.body.as_ref().map(adf::text_of).unwrap_or_default(),
+                        body_html: rendered_html(&format!("/comment/comments/{}/body", index)),
+                        author: comment.author.map(|author| author.display_name),
+                        id: comment.id,
+                        created: comment.created,
+                    })
+                    .collect();
+                (comments, total)
+            }
+            None => (Vec::new(), 0),
+        };
+
+        Self {
+            key: issue.key,
+            summary: fields.summary,
+            status: fields.status.name,
+            assignee: fields.assignee,
+            description: fields.description.as_ref().map(adf::text_of).unwrap_or_default(),
+            description_html: rendered_html("/description"),
+            priority: fields.priority.map(|priority| priority.name),
+            labels: fields.labels,
+            sprint,
+            due_date: fields.due_date,
+            subtasks: fields.subtasks,
+            comments,
+            comment_total,
+        }
+    }
+}
+
+/// Everything the main window shows after a refresh.
+#[derive(Debug, Serialize)]
+pub struct JiraOverview {
+    pub issues: Vec<JiraIssue>,
+    pub worklogs: Vec<UserWorklog>,
+    pub sprint: Option<SprintInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JiraTransition {
+    pub id: String,
+    pub name: String,
+    /// The status the issue ends up in.
+    pub to: IssueStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JiraTransitionsResponse {
+    pub transitions: Vec<JiraTransition>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransitionId {
+    pub id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransitionRequest {
+    pub transition: TransitionId,
+}
@@ -0,0 +1,273 @@
+//! Jira Service Management: service desks, request types and the SLA clocks
+//! of requests, through `/rest/servicedeskapi`. Requests are ordinary issues
+//! otherwise, so time is logged against them with the regular worklog
+//! commands.
+
+use chrono::{DateTime, Local};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::State;
+
+use crate::error::JiraError;
+use crate::jira_api::{field_sets, JiraClient};
+use crate::{client_for, url, JiraState};
+
+const PAGE_LIMIT: u32 = 50;
+/// Requests whose SLAs are looked up per call, one request each.
+const MAX_SLA_REQUESTS: usize = 50;
+
+/// `/rest/servicedeskapi` pages by `start`/`limit` and flags the last page.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", bound(deserialize = "T: DeserializeOwned"))]
+struct PagedResponse<T> {
+    #[serde(default)]
+    values: Vec<T>,
+    #[serde(default = "last_page")]
+    is_last_page: bool,
+}
+
+fn last_page() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceDesk {
+    pub id: String,
+    pub project_id: String,
+    pub project_key: String,
+    pub project_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestType {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub issue_type_id: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SlaDate {
+    epoch_millis: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SlaDuration {
+    millis: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SlaCycle {
+    breach_time: Option<SlaDate>,
+    #[serde(default)]
+    breached: bool,
+    #[serde(default)]
+    paused: bool,
+    remaining_time: Option<SlaDuration>,
+    goal_duration: Option<SlaDuration>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SlaMetric {
+    name: String,
+    ongoing_cycle: Option<SlaCycle>,
+    #[serde(default)]
+    completed_cycles: Vec<SlaCycle>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CustomerRequest {
+    issue_key: String,
+    service_desk_id: String,
+    request_type: Option<RequestType>,
+    sla: Option<PagedResponse<SlaMetric>>,
+}
+
+/// One SLA as shown next to a request.
+#[derive(Debug, Clone, Serialize)]
+pub struct SlaClock {
+    pub name: String,
+    /// `false` once the last cycle completed.
+    pub running: bool,
+    pub paused: bool,
+    pub breached: bool,
+    pub breach_time: Option<DateTime<Local>>,
+    /// Negative once breached.
+    pub remaining_seconds: Option<i64>,
+    pub goal_seconds: Option<i64>,
+}
+
+impl SlaClock {
+    fn from_metric(metric: SlaMetric) -> Self {
+        let running = metric.ongoing_cycle.is_some();
+        let cycle = metric.ongoing_cycle.or_else(|| metric.completed_cycles.into_iter().last());
+        let seconds = |duration: &Option<SlaDuration>| duration.as_ref().map(|duration| duration.millis / 1000);
+
+        SlaClock {
+            name: metric.name,
+            running,
+            paused: cycle.as_ref().is_some_and(|cycle| cycle.paused),
+            breached: cycle.as_ref().is_some_and(|cycle| cycle.breached),
+            breach_time: cycle
+                .as_ref()
+                .and_then(|cycle| cycle.breach_time.as_ref())
+                .and_then(|date| DateTime::from_timestamp_millis(date.epoch_millis))
+                .map(|date| date.with_timezone(&Local)),
+            remaining_seconds: cycle.as_ref().and_then(|cycle| seconds(&cycle.remaining_time)),
+            goal_seconds: cycle.as_ref().and_then(|cycle| seconds(&cycle.goal_duration)),
+        }
+    }
+}
+
+/// A request assigned to the current user.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceRequest {
+    pub key: String,
+    pub summary: String,
+    pub status: String,
+    pub service_desk_id: String,
+    pub request_type: Option<String>,
+    pub slas: Vec<SlaClock>,
+}
+
+impl JiraClient {
+    async fn servicedesk_get<T: DeserializeOwned>(
+        &self,
+        endpoint: &'static str,
+        path: &str,
+        params: &[(&str, String)],
+    ) -> Result<T, JiraError> {
+        let url = url::join(&self.base_url, url::SERVICE_DESK_API, path);
+
+        let authorization = self.authorization().await?;
+        let request = self
+            .http_client()
+            .get(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Authorization", authorization)
+            .query(params);
+        let response = self.send(request).await?;
+        Self::read_json(endpoint, response).await
+    }
+
+    async fn servicedesk_get_all<T: DeserializeOwned>(&self, endpoint: &'static str, path: &str) -> Result<Vec<T>, JiraError> {
+        let mut values = Vec::new();
+
+        loop {
+            let params = [("start", values.len().to_string()), ("limit", PAGE_LIMIT.to_string())];
+            let page: PagedResponse<T> = self.servicedesk_get(endpoint, path, &params).await?;
+            let fetched = page.values.len();
+            values.extend(page.values);
+
+            if page.is_last_page || fetched == 0 {
+                break;
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Service desks the user can see; empty on sites without JSM.
+    pub async fn get_service_desks(&self) -> Result<Vec<ServiceDesk>, JiraError> {
+        match self.servicedesk_get_all("service_desks", "servicedesk").await {
+            Err(JiraError::NotFound(_)) => Ok(Vec::new()),
+            result => result,
+        }
+    }
+
+    pub async fn get_request_types(&self, service_desk_id: &str) -> Result<Vec<RequestType>, JiraError> {
+        let path = format!("servicedesk/{}/requesttype", service_desk_id);
+        self.servicedesk_get_all("request_types", &path).await
+    }
+
+    pub async fn get_request_slas(&self, issue_key: &str) -> Result<Vec<SlaClock>, JiraError> {
+        let path = format!("request/{}/sla", issue_key);
+        let metrics: Vec<SlaMetric> = self.servicedesk_get_all("request_slas", &path).await?;
+        Ok(metrics.into_iter().map(SlaClock::from_metric).collect())
+    }
+
+    /// Assigned issues in service desk projects, with request type and SLAs.
+    pub async fn get_assigned_requests(&self) -> Result<Vec<ServiceRequest>, JiraError> {
+        let desks = self.get_service_desks().await?;
+        if desks.is_empty() {
+            return Ok(Vec::new());
+        }
+        let projects: HashSet<&str> = desks.iter().map(|desk| desk.project_key.as_str()).collect();
+
+        let issues = self.get_assigned_issues(field_sets::SYNC).await?;
+        let mut requests = Vec::new();
+        for issue in issues
+            .into_iter()
+            .filter(|issue| issue.key.split_once('-').is_some_and(|(project, _)| projects.contains(project)))
+            .take(MAX_SLA_REQUESTS)
+        {
+            let path = format!("request/{}", issue.key);
+            let params = [("expand", "requestType,sla".to_string())];
+            let request: CustomerRequest = self.servicedesk_get("request", &path, &params).await?;
+
+            requests.push(ServiceRequest {
+                key: request.issue_key,
+                summary: issue.fields.summary,
+                status: issue.fields.status.name,
+                service_desk_id: request.service_desk_id,
+                request_type: request.request_type.map(|request_type| request_type.name),
+                slas: request
+                    .sla
+                    .map(|page| page.values.into_iter().map(SlaClock::from_metric).collect())
+                    .unwrap_or_default(),
+            });
+        }
+        Ok(requests)
+    }
+}
+
+#[tauri::command]
+pub async fn get_service_desks(
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<ServiceDesk>, JiraError> {
+    client_for(&state, connection.as_deref()).await?.get_service_desks().await
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_request_types(
+    service_desk_id: String,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<RequestType>, JiraError> {
+    client_for(&state, connection.as_deref()).await?
+        .get_request_types(&service_desk_id)
+        .await
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_request_slas(
+    issue_key: String,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<SlaClock>, JiraError> {
+    client_for(&state, connection.as_deref()).await?
+        .get_request_slas(&issue_key)
+        .await
+}
+
+/// My queue: assigned service desk requests with their SLA clocks.
+#[tauri::command]
+pub async fn get_assigned_requests(
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<ServiceRequest>, JiraError> {
+    client_for(&state, connection.as_deref()).await?
+        .get_assigned_requests()
+        .await
+}
@@ -1,34 +1,153 @@
-use std::sync::Mutex;
-use tauri::{AppHandle, Manager, State, Wry, Emitter};
+use tauri::{AppHandle, Manager, RunEvent, State, WebviewUrl, WebviewWindowBuilder, Wry, Emitter};
 use tauri::WindowEvent;
-use tauri::tray::TrayIconEvent;
-use tauri::menu::{Menu, MenuItem};
-use tokio::time::{interval, Duration};
-use chrono::{Local, Timelike};
+use chrono::NaiveDate;
 
+mod adf;
+mod analytics;
+mod attachments;
+mod auth_info;
+mod autostart;
+mod backfill;
+mod boundary;
+mod breaks;
+mod bulk;
+mod calendar;
+mod cancellation;
+mod capabilities;
+mod catch_all;
+mod clock;
+mod closed_guard;
+mod connections;
+mod credentials;
+mod cron;
+mod deep_link;
+mod diagnostics;
+mod distribute;
+mod done_guard;
+mod error;
+mod export;
+mod fixtures;
+mod focus;
+mod goals;
+mod health;
+mod hierarchy;
+mod hour_bank;
+mod http;
+mod idle;
+mod import;
+mod interest;
+mod issue_cache;
+mod issue_create;
+mod issue_groups;
+mod issue_reminders;
 mod jira_types;
 mod jira_api;
-use jira_api::JiraClient;
-use jira_types::{JiraIssue, WorklogResponse};
-
-type JiraState = Mutex<Option<JiraClient>>;
-
-async fn start_notification_scheduler(app_handle: AppHandle<Wry>) {
-    let mut interval = interval(Duration::from_secs(60));
-    
-    loop {
-        interval.tick().await;
-        
-        let now = Local::now();
-        if now.hour() == 17 && now.minute() == 0 {
-            if let Some(main_window) = app_handle.get_webview_window("main") {
-                if let Err(e) = main_window.emit("daily-reminder", ()) {
-                    eprintln!("Failed to emit daily reminder event: {}", e);
-                }
-            }
-        }
-    }
-}
+mod jira_agile;
+mod jira_graphql;
+mod jsm;
+mod lint;
+mod logging;
+mod lunch;
+mod mentions;
+mod metrics;
+mod month_close;
+mod notification_actions;
+mod notifications;
+mod oauth;
+mod offline;
+mod offline_queue;
+mod operations;
+mod plan;
+mod prefetch;
+mod platform;
+mod pomodoro;
+mod quit;
+mod redaction;
+mod resources;
+mod response_cache;
+mod rules;
+mod scheduler;
+mod screenshot;
+mod session_lock;
+mod settings;
+mod shortcuts;
+mod sla_warnings;
+mod state;
+mod storage;
+mod store;
+mod summaries;
+mod summary;
+mod sync;
+mod timer;
+mod timezone_audit;
+mod tempo;
+mod team_config;
+mod templates;
+mod transfer;
+mod tray;
+mod updater;
+mod url;
+mod vault;
+mod webhook;
+mod window_state;
+mod worklog_backend;
+mod worklog_rules;
+use breaks::BreakState;
+use cancellation::CancelState;
+use capabilities::CapabilityState;
+use clock::{ClockState, DebugClockState, ShiftedClock, SystemClock};
+use deep_link::PendingDeepLink;
+use focus::FocusListState;
+use goals::GoalState;
+use health::HealthState;
+use hour_bank::DigestState;
+use interest::InterestState;
+use idle::IdleMonitor;
+use issue_cache::IssueCacheState;
+use issue_reminders::IssueReminderState;
+use error::JiraError;
+use jira_api::{field_sets, JiraApi, JiraClient};
+use jira_graphql::JiraGraphqlClient;
+use worklog_backend::WorklogBackend;
+use jira_types::{Comment, CommentPage, IssueDetail, JiraIssue, JiraOverview, JiraSearchResponse, JiraTransition, Worklog, WorklogUpdateRequest};
+use adf::{CommentFormat, CommentInput};
+use lint::WorklogAnomaly;
+use lunch::LunchState;
+use month_close::MonthCloseState;
+use notifications::NotificationState;
+use offline::OfflineState;
+use offline_queue::{OfflineQueue, WorklogSubmission};
+use operations::OperationState;
+use plan::PlanState;
+use pomodoro::PomodoroState;
+use prefetch::PrefetchState;
+use quit::QuitGuardState;
+use scheduler::SchedulerState;
+use resources::ResourceMonitorState;
+use session_lock::SessionLockState;
+use sla_warnings::SlaWatchState;
+use state::{client_for, connected_client, JiraState};
+use storage::{AssignedIssues, Storage};
+use team_config::TeamConfigState;
+use templates::TemplateState;
+use tray::{PopoverState, TrayFlashState, TrayLoggedState, TrayMenuState};
+use updater::UpdateState;
+use vault::VaultState;
+use webhook::WebhookState;
+use window_state::WindowStateStore;
+
+/// Comments shown in the issue detail panel.
+const DETAIL_COMMENTS: usize = 5;
+/// Comments per `get_comments` page unless asked otherwise.
+const COMMENT_PAGE_SIZE: u32 = 20;
+
+/// The small always-on-top window the quick log shortcut opens.
+const QUICK_LOG_WINDOW: &str = "quick-log";
+/// In-progress issues offered in the quick log window.
+const QUICK_LOG_ISSUES: u32 = 20;
+/// Issues in the watched and recently viewed lists; JIRA's history holds
+/// no more than 50 anyway.
+const WATCHLIST_ISSUES: u32 = 50;
 
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -40,81 +159,499 @@ async fn connect_to_jira(
     base_url: String,
     email: String,
     access_token: String,
-    state: State<'_, JiraState>,
-) -> Result<bool, String> {
-    let client = JiraClient::new(base_url, email, access_token);
-    
-    match client.test_connection().await {
-        Ok(is_connected) => {
-            if is_connected {
-                let mut jira_state = state.lock().map_err(|e| e.to_string())?;
-                *jira_state = Some(client);
-                Ok(true)
-            } else {
-                Err("Failed to connect to JIRA".to_string())
-            }
+    remember: Option<bool>,
+    tls: Option<http::TlsOptions>,
+    comment_format: Option<CommentFormat>,
+    app_handle: AppHandle<Wry>,
+) -> Result<bool, JiraError> {
+    let base_url = url::normalize_base_url(&base_url).map_err(JiraError::Validation)?;
+    let tls = tls.unwrap_or_default().normalized();
+    let client = JiraClient::new(base_url.clone(), email.clone(), access_token.clone(), offline::flag(&app_handle))?.with_tls(tls.clone())?;
+    // Server and Data Center 8.x have no REST v3.
+    let comment_format = match comment_format {
+        Some(format) => format,
+        None => client.detect_api_version().await.comment_format(),
+    };
+    let client = client.with_comment_format(comment_format);
+    // Unlike `test_connection`, this tells bad credentials from an
+    // unreachable server.
+    client.get_myself().await?;
+
+    connections::set_default(&app_handle, &app_handle.state::<JiraState>(), client).await?;
+
+    if remember.unwrap_or(false) {
+        let stored = credentials::StoredCredentials {
+            base_url,
+            email,
+            access_token,
+            tls,
+            comment_format,
+            oauth: None,
+        };
+        if let Err(e) = credentials::save(&stored) {
+            tracing::warn!("{}", e);
         }
-        Err(e) => Err(format!("Connection error: {}", e)),
     }
+    Ok(true)
 }
 
-#[tauri::command]
+/// Assigned issues from the local cache when there is one (`stale`), with
+/// the fresh list following in an `issues-updated` event.
+#[tauri::command(rename_all = "camelCase")]
 async fn get_assigned_issues(
+    connection: Option<String>,
+    request_id: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<AssignedIssues, JiraError> {
+    let request = cancellation::register(&app_handle, request_id);
+    let client = client_for(&state, connection.as_deref()).await?.with_cancellation(request.token());
+    storage::assigned_issues(&app_handle, client, connection).await
+}
+
+/// One page of assigned issues plus `total`, for frontends that page
+/// instead of loading everything.
+#[tauri::command(rename_all = "camelCase")]
+async fn get_assigned_issues_page(
+    start_at: Option<u32>,
+    max_results: Option<u32>,
+    connection: Option<String>,
+    request_id: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<JiraSearchResponse, JiraError> {
+    let request = cancellation::register(&app_handle, request_id);
+    let client = client_for(&state, connection.as_deref()).await?.with_cancellation(request.token());
+    client
+        .get_assigned_issues_page(start_at.unwrap_or(0), max_results, field_sets::ISSUE_LIST)
+        .await
+}
+
+/// My issues in an "In Progress" status, for the quick log window.
+#[tauri::command(rename_all = "camelCase")]
+async fn get_in_progress_issues(
+    connection: Option<String>,
+    request_id: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<JiraIssue>, JiraError> {
+    let request = cancellation::register(&app_handle, request_id);
+    let client = client_for(&state, connection.as_deref()).await?.with_cancellation(request.token());
+    client
+        .get_in_progress_issues(QUICK_LOG_ISSUES, field_sets::ISSUE_LIST)
+        .await
+}
+
+/// Issues I watch, recently updated first.
+#[tauri::command(rename_all = "camelCase")]
+async fn get_watched_issues(
+    connection: Option<String>,
+    request_id: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<JiraIssue>, JiraError> {
+    let request = cancellation::register(&app_handle, request_id);
+    let client = client_for(&state, connection.as_deref()).await?.with_cancellation(request.token());
+    client
+        .get_watched_issues(WATCHLIST_ISSUES, field_sets::WATCHLIST)
+        .await
+}
+
+/// Issues I viewed in JIRA lately, the most recent first.
+#[tauri::command(rename_all = "camelCase")]
+async fn get_recent_issues(
+    connection: Option<String>,
+    request_id: Option<String>,
+    app_handle: AppHandle<Wry>,
     state: State<'_, JiraState>,
-) -> Result<Vec<JiraIssue>, String> {
-    let client = {
-        let jira_state = state.lock().map_err(|e| e.to_string())?;
-        jira_state.as_ref().cloned()
+) -> Result<Vec<JiraIssue>, JiraError> {
+    let request = cancellation::register(&app_handle, request_id);
+    let client = client_for(&state, connection.as_deref()).await?.with_cancellation(request.token());
+    client
+        .get_recent_issues(WATCHLIST_ISSUES, field_sets::WATCHLIST)
+        .await
+}
+
+#[tauri::command(rename_all = "camelCase")]
+async fn watch_issue(
+    issue_key: String,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<(), JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    client.watch_issue(&issue_key).await
+}
+
+#[tauri::command(rename_all = "camelCase")]
+async fn unwatch_issue(
+    issue_key: String,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<(), JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    let myself = client.get_myself().await?;
+    client.unwatch_issue(&issue_key, &myself).await
+}
+
+/// Shows the quick log window, creating it on first use. It floats above
+/// other windows, stays out of the taskbar and hides again when it loses
+/// focus.
+fn show_quick_log_window(app_handle: &AppHandle<Wry>) {
+    let window = match app_handle.get_webview_window(QUICK_LOG_WINDOW) {
+        Some(window) => window,
+        None => {
+            let built = WebviewWindowBuilder::new(app_handle, QUICK_LOG_WINDOW, WebviewUrl::App("quick-log".into()))
+                .title("Quick Log")
+                .inner_size(380.0, 260.0)
+                .resizable(false)
+                .always_on_top(true)
+                .skip_taskbar(true)
+                .center()
+                .visible(false)
+                .build();
+            match built {
+                Ok(window) => window,
+                Err(e) => {
+                    tracing::warn!("Failed to create the quick log window: {}", e);
+                    return;
+                }
+            }
+        }
     };
-    
-    match client {
-        Some(client) => {
-            client.get_assigned_issues()
-                .await
-                .map_err(|e| format!("Failed to get issues: {}", e))
+
+    let _ = window.show();
+    let _ = window.set_focus();
+    if let Err(e) = window.emit("quick-log-opened", ()) {
+        tracing::warn!("Failed to emit quick-log-opened event: {}", e);
+    }
+}
+
+/// What the quick log shortcut does: opens the window, or hides it when it
+/// is already in front.
+fn toggle_quick_log_window(app_handle: &AppHandle<Wry>) {
+    match app_handle.get_webview_window(QUICK_LOG_WINDOW) {
+        Some(window) if window.is_visible().unwrap_or(false) && window.is_focused().unwrap_or(false) => {
+            let _ = window.hide();
         }
-        None => Err("Not connected to JIRA".to_string()),
+        _ => show_quick_log_window(app_handle),
+    }
+}
+
+async fn fetch_issue_detail(client: &JiraClient, issue_key: &str) -> Result<IssueDetail, JiraError> {
+    // Without JIRA Software there is no sprint field; the rest still works.
+    let sprint_field = client.sprint_field_id().await.unwrap_or_else(|e| {
+        tracing::warn!("Failed to look up the sprint field: {}", e);
+        None
+    });
+    let fields = match &sprint_field {
+        Some(field_id) => format!("{},{}", field_sets::DETAIL, field_id),
+        None => field_sets::DETAIL.to_string(),
+    };
+
+    let issue = client.get_issue(issue_key, &fields, &["renderedFields"]).await?;
+    Ok(IssueDetail::from_issue(issue, sprint_field.as_deref(), DETAIL_COMMENTS))
+}
+
+/// Description, priority, labels, sprint, due date, subtasks and the latest
+/// comments of a single issue. A recent prefetched copy is served as is.
+#[tauri::command(rename_all = "camelCase")]
+async fn get_issue_detail(
+    issue_key: String,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<IssueDetail, JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    interest::touch(&app_handle, &issue_key);
+    if let Some(detail) = prefetch::cached_detail(&app_handle, &client, &issue_key) {
+        return Ok(detail);
     }
+
+    let detail = fetch_issue_detail(&client, &issue_key).await?;
+    prefetch::store_detail(&app_handle, &client, detail.clone());
+    Ok(detail)
+}
+
+/// A page of `issue_key`'s comments, newest first; `start_at` pages further
+/// back.
+#[tauri::command(rename_all = "camelCase")]
+async fn get_comments(
+    issue_key: String,
+    start_at: Option<u32>,
+    max_results: Option<u32>,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<CommentPage, JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    let page = client
+        .get_comments(&issue_key, start_at.unwrap_or(0), max_results.unwrap_or(COMMENT_PAGE_SIZE))
+        .await?;
+    Ok(page.into())
 }
 
+/// Replies on `issue_key`. `body` is plain text or an ADF document.
 #[tauri::command(rename_all = "camelCase")]
+async fn add_comment(
+    issue_key: String,
+    body: CommentInput,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Comment, JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    let document = body.to_document();
+    document.validate().map_err(JiraError::Validation)?;
+    if document.text().trim().is_empty() {
+        return Err(JiraError::Validation("The comment is empty".to_string()));
+    }
+
+    let comment = client.add_comment(&issue_key, &document).await?;
+    prefetch::forget_detail(&app_handle, &issue_key);
+    Ok(comment.into())
+}
+
+/// Submits a worklog. `description` is plain text or an ADF document. When
+/// JIRA is unreachable the entry is queued and retried in the background
+/// instead of being lost. The `worklog_rules` round it first, and issues in
+/// a done status are subject to the `closed_issue_guard` setting.
+#[tauri::command(rename_all = "camelCase")]
+#[allow(clippy::too_many_arguments)]
 async fn create_worklog(
     issue_key: String,
-    description: String,
+    description: CommentInput,
     started: String,
     time_spent: String,
+    confirmed: Option<bool>,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<WorklogSubmission, JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    let time_spent_seconds = JiraClient::parse_time_to_seconds(&time_spent, &settings::current(&app_handle).time_units)?;
+    let confirmed = confirmed.unwrap_or(false);
+    let (started, time_spent_seconds) = worklog_rules::normalize(&app_handle, &started, time_spent_seconds, confirmed)?;
+    closed_guard::check(&app_handle, &client, &issue_key, confirmed).await?;
+
+    offline_queue::submit(&app_handle, &client, connection.as_deref(), &issue_key, &description, &started, time_spent_seconds).await
+}
+
+/// `seconds` the way JIRA writes durations, e.g. `1d 2h 30m`, with the
+/// configured day length.
+#[tauri::command]
+fn format_duration(seconds: u32, app_handle: AppHandle<Wry>) -> String {
+    JiraClient::format_seconds_to_jira_duration(seconds, &settings::current(&app_handle).time_units)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+async fn get_worklogs(
+    issue_key: String,
+    connection: Option<String>,
+    request_id: Option<String>,
+    app_handle: AppHandle<Wry>,
     state: State<'_, JiraState>,
-) -> Result<WorklogResponse, String> {
-    let client = {
-        let jira_state = state.lock().map_err(|e| e.to_string())?;
-        jira_state.as_ref().cloned()
+) -> Result<Vec<Worklog>, JiraError> {
+    let request = cancellation::register(&app_handle, request_id);
+    let client = client_for(&state, connection.as_deref()).await?.with_cancellation(request.token());
+    let storage = app_handle.state::<Storage>();
+    let account = storage::account(&client);
+
+    match client.get_issue_worklogs(&issue_key, None, None).await {
+        Ok(worklogs) => {
+            if let Err(e) = storage.save_worklogs(&account, &issue_key, &worklogs, chrono::Local::now()) {
+                tracing::warn!("Failed to cache worklogs: {}", e);
+            }
+            Ok(worklogs)
+        }
+        // Offline: the worklogs as last seen.
+        Err(e) if e.is_connectivity() => match storage.load_worklogs(&account, &issue_key) {
+            Some(cached) => Ok(cached.value),
+            None => Err(e),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+#[tauri::command(rename_all = "camelCase")]
+async fn update_worklog(
+    issue_key: String,
+    worklog_id: String,
+    description: Option<CommentInput>,
+    started: Option<String>,
+    time_spent: Option<String>,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+) -> Result<Worklog, JiraError> {
+    let client = client_for(&app_handle.state::<JiraState>(), connection.as_deref()).await?;
+
+    let time_spent_seconds = time_spent
+        .map(|time_spent| JiraClient::parse_time_to_seconds(&time_spent, &settings::current(&app_handle).time_units))
+        .transpose()?;
+    let comment = description.as_ref().map(CommentInput::to_document);
+    if let Some(comment) = &comment {
+        comment.validate().map_err(JiraError::Validation)?;
+        lint::check_description(&settings::current(&app_handle).description_rules, &comment.text())
+            .map_err(JiraError::Validation)?;
+    }
+    month_close::check_started(&app_handle, &client.get_worklog(&issue_key, &worklog_id).await?.started)?;
+    if let Some(started) = &started {
+        month_close::check_started(&app_handle, started)?;
+    }
+    let update = WorklogUpdateRequest {
+        comment: comment.map(|comment| client.comment_format.body(&comment)),
+        started,
+        time_spent_seconds,
     };
-    
-    match client {
-        Some(client) => {
-            let time_spent_seconds = JiraClient::parse_time_to_seconds(&time_spent)
-                .map_err(|e| format!("Invalid time format: {}", e))?;
-            
-            client.create_worklog(
-                &issue_key,
-                &description,
-                &started,
-                time_spent_seconds,
-                None,
-            )
-            .await
-            .map_err(|e| format!("Failed to create worklog: {}", e))
+
+    let worklog = client
+        .update_worklog(&issue_key, &worklog_id, &update)
+        .await?;
+    analytics::record(&app_handle, &client, &issue_key, &worklog.id, &worklog.started, worklog.time_spent_seconds);
+    Ok(worklog)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+async fn delete_worklog(
+    issue_key: String,
+    worklog_id: String,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<(), JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    let backend = worklog_backend::for_app(&app_handle, client.clone())?;
+    month_close::check_started(&app_handle, &backend.worklog_started(&issue_key, &worklog_id).await?)?;
+    backend
+        .delete_worklog(&issue_key, &worklog_id)
+        .await?;
+    analytics::forget(&app_handle, &client, &worklog_id);
+    Ok(())
+}
+
+#[tauri::command(rename_all = "camelCase")]
+async fn get_transitions(
+    issue_key: String,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<JiraTransition>, JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    client
+        .get_transitions(&issue_key)
+        .await
+}
+
+/// Applies a transition, subject to the `done_guard` setting when it leads
+/// to a done status.
+#[tauri::command(rename_all = "camelCase")]
+async fn transition_issue(
+    issue_key: String,
+    transition_id: String,
+    confirmed: Option<bool>,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<(), JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    done_guard::check(&app_handle, &client, &issue_key, &transition_id, confirmed.unwrap_or(false)).await?;
+    client
+        .transition_issue(&issue_key, &transition_id)
+        .await?;
+    prefetch::forget_detail(&app_handle, &issue_key);
+    Ok(())
+}
+
+/// The user's worklogs for long ranges, streamed over `channel` (see
+/// `transfer`) and gzipped unless `gzip` is false.
+#[tauri::command(rename_all = "camelCase")]
+async fn stream_user_worklogs(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    gzip: Option<bool>,
+    channel: tauri::ipc::Channel,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<transfer::TransferSummary, JiraError> {
+    if from_date > to_date {
+        return Err(JiraError::Validation("fromDate must not be after toDate".to_string()));
+    }
+
+    let client = client_for(&state, connection.as_deref()).await?;
+    let worklogs = client.get_user_worklogs(from_date, to_date).await?;
+
+    Ok(transfer::send_json(&channel, &worklogs, gzip.unwrap_or(true))?)
+}
+
+/// Assigned issues, worklogs and the active sprint in one call. With the
+/// experimental GraphQL backend this is a single request; when the gateway
+/// fails the REST endpoints are used instead.
+#[tauri::command(rename_all = "camelCase")]
+async fn get_overview(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    connection: Option<String>,
+    request_id: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<JiraOverview, JiraError> {
+    if from_date > to_date {
+        return Err(JiraError::Validation("fromDate must not be after toDate".to_string()));
+    }
+
+    let request = cancellation::register(&app_handle, request_id);
+    let client = client_for(&state, connection.as_deref()).await?.with_cancellation(request.token());
+    if settings::current(&app_handle).experimental_graphql {
+        let overview = match JiraGraphqlClient::connect(client.clone()).await {
+            Ok(graphql) => graphql.overview(from_date, to_date).await,
+            Err(e) => Err(e),
+        };
+        match overview {
+            Ok(overview) => return Ok(overview),
+            Err(e) => tracing::warn!("GraphQL overview failed, falling back to REST: {}", e),
         }
-        None => Err("Not connected to JIRA".to_string()),
     }
+
+    client
+        .overview(from_date, to_date)
+        .await
+}
+
+#[tauri::command(rename_all = "camelCase")]
+async fn lint_worklogs(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<WorklogAnomaly>, JiraError> {
+    if from_date > to_date {
+        return Err(JiraError::Validation("fromDate must not be after toDate".to_string()));
+    }
+
+    let client = client_for(&state, connection.as_deref()).await?;
+    let worklogs = client.get_user_worklogs(from_date, to_date).await?;
+
+    Ok(lint::lint_worklogs(&worklogs, &settings::current(&app_handle).description_rules))
 }
 
 #[tauri::command]
 async fn disconnect_from_jira(
+    app_handle: AppHandle<Wry>,
     state: State<'_, JiraState>,
 ) -> Result<(), String> {
-    let mut jira_state = state.lock().map_err(|e| e.to_string())?;
-    *jira_state = None;
+    let mut registry = state::connections_mut(&state).await;
+    let accounts: Vec<String> = registry.clients().map(storage::account).collect();
+    registry.clear();
+    drop(registry);
+
+    let storage = app_handle.state::<Storage>();
+    for account in accounts {
+        if let Err(e) = storage.forget(&account) {
+            tracing::warn!("Failed to clear cached issues: {}", e);
+        }
+    }
+    response_cache::clear();
+    sync::reset(&app_handle);
+    health::recheck(&app_handle);
+    issue_cache::clear(&app_handle);
     Ok(())
 }
 
@@ -135,6 +672,14 @@ async fn hide_to_tray(app_handle: AppHandle<Wry>) -> Result<(), String> {
     Ok(())
 }
 
+#[tauri::command]
+async fn hide_quick_log_window(app_handle: AppHandle<Wry>) -> Result<(), String> {
+    if let Some(window) = app_handle.get_webview_window(QUICK_LOG_WINDOW) {
+        window.hide().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
 #[tauri::command]
 async fn send_test_notification(_app_handle: AppHandle<Wry>) -> Result<(), String> {
     Ok(())
@@ -142,45 +687,160 @@ async fn send_test_notification(_app_handle: AppHandle<Wry>) -> Result<(), Strin
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Runs on system time; dev builds can move it through `debug_*` commands.
+    let clock = std::sync::Arc::new(ShiftedClock::new(SystemClock));
+    boundary::install_panic_hook();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // A second launch hands its link to the running app and is done.
+    if deep_link::forward_to_running(&args) {
+        return;
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_process::init())
-        .setup(|app| {
-            let show_item = MenuItem::with_id(app, "show", "Show", true, None::<&str>)?;
-            let hide_item = MenuItem::with_id(app, "hide", "Hide", true, None::<&str>)?;
-            let separator1 = MenuItem::with_id(app, "separator1", "---", false, None::<&str>)?;
-            let test_notification_item = MenuItem::with_id(app, "test_notification", "Test Notification", true, None::<&str>)?;
-            let separator2 = MenuItem::with_id(app, "separator2", "---", false, None::<&str>)?;
-            let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-            
-            let menu = Menu::with_items(app, &[
-                &show_item,
-                &hide_item,
-                &separator1,
-                &test_notification_item,
-                &separator2,
-                &quit_item,
-            ])?;
-
-            app.tray_by_id("main")
-                .expect("Failed to get tray")
-                .set_menu(Some(menu))?;
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(shortcuts::plugin())
+        .plugin(autostart::plugin())
+        .setup(move |app| {
+            logging::init(app.handle());
+            boundary::attach(app.handle());
+            let app_settings = settings::load(app.handle());
+            tray::apply_settings(app.handle(), &app_settings);
+            http::configure(&app_settings.http);
+            if let Err(e) = platform::apply_menubar_only(app.handle(), app_settings.menubar_only) {
+                tracing::warn!("Failed to apply menu bar mode: {}", e);
+            }
+            *app.state::<JiraState>().settings().lock().unwrap_or_else(|e| e.into_inner()) = app_settings;
+            app.manage::<IssueReminderState>(IssueReminderState::new(issue_reminders::load(app.handle())));
+            app.manage::<BreakState>(BreakState::new(breaks::load(app.handle())));
+            app.manage::<PomodoroState>(pomodoro::load(app.handle()));
+            app.manage::<WindowStateStore>(window_state::load(app.handle()));
+            app.manage::<OfflineQueue>(offline_queue::load(app.handle()));
+            app.manage::<IdleMonitor>(idle::detect());
+            app.manage::<FocusListState>(FocusListState::new(focus::load(app.handle())));
+            app.manage::<TemplateState>(templates::load(app.handle()));
+            app.manage::<PlanState>(PlanState::new(plan::load(app.handle())));
+            app.manage::<MonthCloseState>(month_close::load(app.handle()));
+            app.manage::<LunchState>(LunchState::new(lunch::load(app.handle())));
+            app.manage::<Storage>(Storage::open(app.handle()));
+            app.manage::<TeamConfigState>(TeamConfigState::new(team_config::load(app.handle())));
+            team_config::spawn_refresh(app.handle());
+            tray::refresh_menu(app.handle());
+
+            let app_handle = app.handle().clone();
+            jira_api::on_auth_required(move |client, error| health::auth_required(&app_handle, client, error));
+
+            if let Some(window) = app.get_webview_window("main") {
+                window_state::restore(&window);
+                // The window starts hidden (see tauri.conf.json) so a login
+                // launch doesn't flash it.
+                if !autostart::start_hidden(app.handle()) {
+                    let _ = window.show();
+                }
+            }
+            deep_link::listen(app.handle());
+            if let Err(e) = deep_link::register_scheme(app.handle()) {
+                tracing::warn!("Failed to register the {}:// scheme: {}", deep_link::SCHEME, e);
+            }
+            deep_link::handle_launch_args(app.handle(), &args);
 
             let app_handle = app.handle().clone();
             
             tauri::async_runtime::spawn(async move {
-                start_notification_scheduler(app_handle).await;
+                scheduler::start_notification_scheduler(app_handle).await;
+            });
+
+            let app_handle = app.handle().clone();
+
+            tauri::async_runtime::spawn(async move {
+                timer::start_timer_ticker(app_handle).await;
+            });
+
+            let app_handle = app.handle().clone();
+
+            tauri::async_runtime::spawn(async move {
+                credentials::auto_connect(app_handle).await;
+            });
+
+            let app_handle = app.handle().clone();
+
+            tauri::async_runtime::spawn(async move {
+                window_state::start_display_watcher(app_handle).await;
+            });
+
+            let app_handle = app.handle().clone();
+
+            tauri::async_runtime::spawn(async move {
+                offline_queue::start_sync_task(app_handle).await;
+            });
+
+            let app_handle = app.handle().clone();
+
+            tauri::async_runtime::spawn(async move {
+                resources::start_resource_monitor(app_handle).await;
             });
+
+            let app_handle = app.handle().clone();
+
+            tauri::async_runtime::spawn(async move {
+                tray::start_menu_refresh(app_handle).await;
+            });
+
+            let app_handle = app.handle().clone();
+
+            tauri::async_runtime::spawn(async move {
+                prefetch::start_prefetcher(app_handle).await;
+            });
+
+            let app_handle = app.handle().clone();
+
+            tauri::async_runtime::spawn(async move {
+                health::start_health_monitor(app_handle).await;
+            });
+
+            let app_handle = app.handle().clone();
+
+            tauri::async_runtime::spawn(async move {
+                goals::start_goal_monitor(app_handle).await;
+            });
+
+            let app_handle = app.handle().clone();
+
+            tauri::async_runtime::spawn(async move {
+                hour_bank::start_digest_monitor(app_handle).await;
+            });
+
+            let app_handle = app.handle().clone();
+
+            tauri::async_runtime::spawn(async move {
+                vault::start_flusher(app_handle).await;
+            });
+
+            let app_handle = app.handle().clone();
+
+            tauri::async_runtime::spawn(async move {
+                updater::start_update_checker(app_handle).await;
+            });
+
+            let app_handle = app.handle().clone();
+
+            tauri::async_runtime::spawn(async move {
+                session_lock::start_lock_monitor(app_handle).await;
+            });
+
+            sync::restart(app.handle());
+            webhook::restart(app.handle());
+            shortcuts::register_from_settings(app.handle());
             
             Ok(())
         })
         .on_menu_event(|app, event| match event.id().as_ref() {
             "show" => {
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.show();
-                    let _ = window.set_focus();
-                }
+                tray::stop_flashing(app);
+                tray::show_main_window(app);
             }
             "hide" => {
                 if let Some(window) = app.get_webview_window("main") {
@@ -193,41 +853,249 @@ pub fn run() {
                 }
             }
             "quit" => {
-                std::process::exit(0);
+                quit::request_quit(app);
             }
-            _ => {}
+            id => tray::handle_menu_event(app, id),
         })
-        .on_tray_icon_event(|app, event| match event {
-            TrayIconEvent::Click {
-                button: tauri::tray::MouseButton::Left,
-                button_state: tauri::tray::MouseButtonState::Up,
-                ..
-            } => {
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.show();
-                    let _ = window.set_focus();
-                }
-            }
-            _ => {}
-        })
-        .on_window_event(|_window, event| match event {
+        .on_tray_icon_event(tray::handle_event)
+        .on_window_event(|window, event| match event {
             WindowEvent::CloseRequested { api, .. } => {
-                _window.hide().unwrap();
+                if let Err(e) = window.hide() {
+                    tracing::warn!("Failed to hide {} window: {}", window.label(), e);
+                }
                 api.prevent_close();
             }
+            WindowEvent::Moved(_) | WindowEvent::Resized(_) if window.label() == "main" => {
+                window_state::record(window);
+            }
+            WindowEvent::Focused(true) => {
+                tray::stop_flashing(window.app_handle());
+            }
+            WindowEvent::Focused(false) => {
+                let app_handle = window.app_handle();
+                if window.label() == "main" && settings::current(app_handle).auto_hide_on_blur {
+                    tray::mark_hidden_on_blur(app_handle);
+                    let _ = window.hide();
+                } else if window.label() == QUICK_LOG_WINDOW {
+                    let _ = window.hide();
+                }
+            }
             _ => {}
         })
+        .manage::<ClockState>(clock.clone())
+        .manage::<DebugClockState>(clock)
         .manage(JiraState::default())
-        .invoke_handler(tauri::generate_handler![
+        .manage(TrayFlashState::default())
+        .manage(HealthState::default())
+        .manage(CancelState::default())
+        .manage(CapabilityState::default())
+        .manage(PopoverState::default())
+        .manage(TrayMenuState::default())
+        .manage(TrayLoggedState::default())
+        .manage(QuitGuardState::default())
+        .manage(ResourceMonitorState::default())
+        .manage(IssueCacheState::default())
+        .manage(PrefetchState::default())
+        .manage(InterestState::default())
+        .manage(GoalState::default())
+        .manage(DigestState::default())
+        .manage(OperationState::default())
+        .manage(SchedulerState::default())
+        .manage(NotificationState::default())
+        .manage(SlaWatchState::default())
+        .manage(WebhookState::default())
+        .manage(VaultState::default())
+        .manage(OfflineState::default())
+        .manage(UpdateState::default())
+        .manage(SessionLockState::default())
+        .manage(PendingDeepLink::default())
+        .invoke_handler(boundary::catch_panics(session_lock::guard(tauri::generate_handler![
             greet,
             connect_to_jira,
             get_assigned_issues,
+            get_assigned_issues_page,
+            get_in_progress_issues,
+            get_watched_issues,
+            get_recent_issues,
+            watch_issue,
+            unwatch_issue,
+            get_issue_detail,
+            get_comments,
+            add_comment,
             create_worklog,
+            format_duration,
+            get_worklogs,
+            update_worklog,
+            delete_worklog,
+            get_transitions,
+            transition_issue,
+            get_overview,
+            stream_user_worklogs,
+            lint_worklogs,
+            month_close::run_month_close,
+            month_close::list_closed_months,
+            month_close::reopen_month,
             disconnect_from_jira,
             show_main_window,
             hide_to_tray,
-            send_test_notification
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+            hide_quick_log_window,
+            send_test_notification,
+            settings::get_settings,
+            settings::update_settings,
+            issue_reminders::schedule_issue_reminder,
+            issue_reminders::list_issue_reminders,
+            issue_reminders::cancel_issue_reminder,
+            timer::start_timer,
+            timer::pause_timer,
+            timer::resume_timer,
+            timer::stop_timer,
+            timer::switch_and_log,
+            analytics::get_time_by_project,
+            analytics::get_time_by_day,
+            analytics::get_streaks,
+            analytics::refresh_analytics,
+            timer::start_timebox,
+            pomodoro::start_pomodoro,
+            pomodoro::get_pomodoro_stats,
+            timer::get_active_timer,
+            timer::resolve_idle,
+            timer::discard_timer,
+            focus::get_focus_list,
+            focus::add_to_focus_list,
+            focus::remove_from_focus_list,
+            focus::reorder_focus_list,
+            plan::plan_day,
+            plan::suggest_plan,
+            plan::get_day_plan,
+            plan::get_plan_variance,
+            shortcuts::register_quick_log_shortcut,
+            shortcuts::unregister_quick_log_shortcut,
+            breaks::get_break_stats,
+            tray::show_window_near_tray,
+            credentials::save_credentials,
+            credentials::load_credentials,
+            credentials::clear_credentials,
+            credentials::reconnect_saved,
+            quit::handle_window_shortcut,
+            quit::force_quit,
+            offline_queue::list_pending_worklogs,
+            offline_queue::retry_pending_worklogs,
+            offline_queue::discard_pending_worklog,
+            idle::get_idle_seconds,
+            lunch::get_lunch_window,
+            rules::export_rules,
+            rules::import_rules,
+            team_config::get_team_config,
+            team_config::refresh_team_config,
+            prefetch::get_avatar,
+            mentions::find_mentioned_issues,
+            mentions::link_mentioned_issue,
+            mentions::split_worklog,
+            calendar::get_suggested_worklogs,
+            operations::get_operation_status,
+            operations::list_operations,
+            operations::cancel_operation,
+            backfill::backfill_period,
+            issue_create::get_projects,
+            issue_create::get_issue_types,
+            issue_create::create_issue,
+            catch_all::create_catch_all_task,
+            autostart::get_autostart,
+            autostart::set_autostart,
+            issue_groups::get_assigned_issues_grouped,
+            bulk::create_worklogs_bulk,
+            import::preview_toggl_import,
+            import::preview_toggl_api_import,
+            import::import_toggl_entries,
+            distribute::distribute_time,
+            templates::list_templates,
+            templates::save_template,
+            templates::delete_template,
+            templates::log_from_template,
+            summaries::resolve_issue_summaries,
+            attachments::upload_attachment,
+            attachments::download_attachment,
+            screenshot::capture_screenshot,
+            screenshot::capture_and_attach,
+            url::validate_base_url,
+            health::get_connection_status,
+            offline::get_offline_state,
+            offline::set_offline,
+            auth_info::get_auth_info,
+            cancellation::cancel_request,
+            webhook::get_webhook_status,
+            timezone_audit::audit_timezones,
+            timezone_audit::fix_timezones,
+            goals::get_progress,
+            hour_bank::get_hour_bank,
+            updater::get_update_status,
+            updater::check_for_updates,
+            updater::install_update,
+            vault::get_vault_status,
+            vault::unlock_vault,
+            vault::enable_encryption,
+            vault::disable_encryption,
+            session_lock::session_status,
+            session_lock::lock_session,
+            session_lock::unlock_session,
+            deep_link::take_pending_deep_link,
+            webhook::rotate_webhook_secret,
+            resources::get_resource_usage,
+            oauth::connect_with_oauth,
+            oauth::refresh_session,
+            sync::sync_issues_now,
+            sync::get_issue_snapshot,
+            metrics::get_payload_metrics,
+            metrics::reset_payload_metrics,
+            scheduler::get_reminder_schedule,
+            scheduler::set_reminder_schedule,
+            scheduler::snooze_reminder,
+            scheduler::get_next_reminder,
+            notifications::get_notification_policy,
+            notifications::snooze_notifications,
+            issue_cache::refresh_issue_cache,
+            issue_cache::get_cached_issues_window,
+            fixtures::record_fixture,
+            tempo::set_tempo_token,
+            tempo::clear_tempo_token,
+            tempo::get_tempo_accounts,
+            tempo::get_tempo_work_attributes,
+            worklog_backend::get_backend_worklogs,
+            worklog_backend::preview_worklog,
+            worklog_rules::check_worklogs,
+            clock::debug_advance_clock,
+            clock::debug_set_time,
+            clock::debug_reset_clock,
+            connections::add_connection,
+            connections::remove_connection,
+            connections::list_connections,
+            connections::set_active_connection,
+            capabilities::get_capabilities,
+            diagnostics::debug_dump_state,
+            diagnostics::export_diagnostics,
+            summary::get_worklog_summary,
+            export::export_worklogs,
+            export::export_timer_sessions,
+            jsm::get_service_desks,
+            jsm::get_request_types,
+            jsm::get_request_slas,
+            jsm::get_assigned_requests,
+            jira_agile::get_boards,
+            jira_agile::get_active_sprint,
+            jira_agile::get_sprint_issues,
+            sla_warnings::get_sla_deadlines
+        ])))
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // `code` is only set for exits requested through `AppHandle::exit`;
+            // anything else (Cmd+Q, session logout) goes through the guard.
+            match event {
+                RunEvent::ExitRequested { code: None, api, .. } if !quit::allow_quit(app) => api.prevent_exit(),
+                RunEvent::Exit => vault::flush(app),
+                #[cfg(target_os = "macos")]
+                RunEvent::Opened { urls } => deep_link::open_urls(app, &urls),
+                _ => {}
+            }
+        });
 }
@@ -0,0 +1,262 @@
+use chrono::{Datelike, Local, NaiveDate, Weekday};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use crate::jira_api::JiraClient;
+use crate::jira_types::UserWorklog;
+use crate::settings::DescriptionRules;
+
+const MIN_SECONDS: u32 = 60;
+const MAX_SECONDS: u32 = 12 * 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    DuplicateDescription,
+    ZeroDuration,
+    ExcessiveDuration,
+    WeekendEntry,
+    /// Breaks one of the configured description rules.
+    DescriptionRule,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorklogAnomaly {
+    pub kind: AnomalyKind,
+    pub issue_key: String,
+    pub worklog_id: String,
+    pub started: String,
+    pub time_spent_seconds: u32,
+    pub message: String,
+}
+
+impl WorklogAnomaly {
+    fn new(kind: AnomalyKind, entry: &UserWorklog, message: String) -> Self {
+        Self {
+            kind,
+            issue_key: entry.issue_key.clone(),
+            worklog_id: entry.worklog.id.clone(),
+            started: entry.worklog.started.clone(),
+            time_spent_seconds: entry.worklog.time_spent_seconds,
+            message,
+        }
+    }
+}
+
+fn local_date(entry: &UserWorklog) -> Option<NaiveDate> {
+    JiraClient::parse_jira_datetime(&entry.worklog.started)
+        .ok()
+        .map(|started| started.with_timezone(&Local).date_naive())
+}
+
+fn normalize_description(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `pattern` compiled; the last one is kept, since it only changes with the
+/// settings.
+fn required_pattern(pattern: &str) -> Result<Regex, String> {
+    static LAST: OnceLock<Mutex<Option<Regex>>> = OnceLock::new();
+    let mut last = LAST.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(regex) = last.as_ref().filter(|regex| regex.as_str() == pattern) {
+        return Ok(regex.clone());
+    }
+    let regex = Regex::new(pattern).map_err(|e| format!("Invalid description pattern: {}", e))?;
+    *last = Some(regex.clone());
+    Ok(regex)
+}
+
+/// Checks a worklog description against `rules`; the error names the first
+/// rule it breaks.
+pub fn check_description(rules: &DescriptionRules, text: &str) -> Result<(), String> {
+    if !rules.enabled {
+        return Ok(());
+    }
+
+    let text = text.trim();
+    if text.chars().count() < rules.min_length {
+        return Err(format!(
+            "The description needs at least {} characters, say what was done",
+            rules.min_length
+        ));
+    }
+
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if let Some(word) = rules
+        .forbidden_words
+        .iter()
+        .find(|forbidden| words.contains(&forbidden.to_lowercase()))
+    {
+        return Err(format!("'{}' is too vague for a worklog description", word));
+    }
+
+    if let Some(pattern) = &rules.required_pattern {
+        let pattern = required_pattern(pattern)?;
+        if !pattern.is_match(text) {
+            return Err(format!("The description must match {}", pattern.as_str()));
+        }
+    }
+    Ok(())
+}
+
+/// Flags entries worth a second look before a timesheet review.
+pub fn lint_worklogs(entries: &[UserWorklog], rules: &DescriptionRules) -> Vec<WorklogAnomaly> {
+    let mut anomalies = Vec::new();
+
+    for entry in entries {
+        if let Err(message) = check_description(rules, &entry.worklog.comment_text()) {
+            anomalies.push(WorklogAnomaly::new(
+                AnomalyKind::DescriptionRule,
+                entry,
+                format!("{}: {}", entry.issue_key, message),
+            ));
+        }
+
+        let seconds = entry.worklog.time_spent_seconds;
+        if seconds < MIN_SECONDS {
+            anomalies.push(WorklogAnomaly::new(
+                AnomalyKind::ZeroDuration,
+                entry,
+                format!("{} has a worklog shorter than a minute", entry.issue_key),
+            ));
+        } else if seconds > MAX_SECONDS {
+            anomalies.push(WorklogAnomaly::new(
+                AnomalyKind::ExcessiveDuration,
+                entry,
+                format!(
+                    "{} has a worklog of {:.1}h, more than 12h",
+                    entry.issue_key,
+                    seconds as f64 / 3600.0
+                ),
+            ));
+        }
+
+        if let Some(date) = local_date(entry) {
+            if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+                anomalies.push(WorklogAnomaly::new(
+                    AnomalyKind::WeekendEntry,
+                    entry,
+                    format!("{} was logged on {} ({})", entry.issue_key, date, date.weekday()),
+                ));
+            }
+        }
+    }
+
+    let mut by_description: HashMap<String, Vec<(&UserWorklog, Option<NaiveDate>)>> = HashMap::new();
+    for entry in entries {
+        let description = normalize_description(&entry.worklog.comment_text());
+        if !description.is_empty() {
+            by_description
+                .entry(description)
+                .or_default()
+                .push((entry, local_date(entry)));
+        }
+    }
+
+    for group in by_description.values() {
+        let first_date = group[0].1;
+        if group.iter().all(|(_, date)| *date == first_date) {
+            continue;
+        }
+
+        for (entry, _) in group {
+            anomalies.push(WorklogAnomaly::new(
+                AnomalyKind::DuplicateDescription,
+                entry,
+                format!(
+                    "{} reuses a description that appears on {} different entries",
+                    entry.issue_key,
+                    group.len()
+                ),
+            ));
+        }
+    }
+
+    anomalies.sort_by_cached_key(|anomaly| JiraClient::parse_jira_datetime(&anomaly.started).ok());
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn worklog(issue_key: &str, started: &str, seconds: u32, comment: &str) -> UserWorklog {
+        serde_json::from_value(serde_json::json!({
+            "issue_key": issue_key,
+            "issue_summary": "",
+            "worklog": {
+                "id": issue_key,
+                "issueId": "10001",
+                "timeSpentSeconds": seconds,
+                "started": started,
+                "comment": comment,
+            },
+        }))
+        .unwrap()
+    }
+
+    fn kinds(anomalies: &[WorklogAnomaly]) -> Vec<(&str, AnomalyKind)> {
+        anomalies.iter().map(|anomaly| (anomaly.issue_key.as_str(), anomaly.kind)).collect()
+    }
+
+    #[test]
+    fn flags_durations_weekends_and_repeated_descriptions() {
+        let rules = DescriptionRules::default();
+        let entries = [
+            worklog("APP-1", "2024-06-03T12:00:00.000+0000", 30, "Fixed the login form"),
+            worklog("APP-2", "2024-06-04T12:00:00.000+0000", 13 * 3600, "Standup"),
+            worklog("APP-3", "2024-06-05T12:00:00.000+0000", 900, "standup"),
+            worklog("APP-4", "2024-06-05T12:00:00.000+0000", 900, "Pairing"),
+            worklog("APP-5", "2024-06-05T12:00:00.000+0000", 900, "Pairing"),
+            worklog("APP-6", "2024-06-08T12:00:00.000+0000", 3600, "Release"),
+        ];
+
+        assert_eq!(
+            kinds(&lint_worklogs(&entries, &rules)),
+            [
+                ("APP-1", AnomalyKind::ZeroDuration),
+                ("APP-2", AnomalyKind::ExcessiveDuration),
+                ("APP-2", AnomalyKind::DuplicateDescription),
+                ("APP-3", AnomalyKind::DuplicateDescription),
+                ("APP-6", AnomalyKind::WeekendEntry),
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_descriptions_against_the_rules() {
+        let rules = DescriptionRules {
+            enabled: true,
+            min_length: 5,
+            required_pattern: Some(r"[A-Z]+-\d+".to_string()),
+            ..Default::default()
+        };
+        assert!(check_description(&rules, "Reviewed APP-12").is_ok());
+        assert!(check_description(&rules, "fix").unwrap_err().contains("at least 5"));
+        assert!(check_description(&rules, "Misc things for APP-12").unwrap_err().contains("too vague"));
+        assert!(check_description(&rules, "Reviewed the PR").unwrap_err().contains("must match"));
+        assert!(check_description(&DescriptionRules::default(), "").is_ok());
+
+        let entries = [worklog("APP-1", "2024-06-03T12:00:00.000+0000", 3600, "fix")];
+        assert_eq!(kinds(&lint_worklogs(&entries, &rules)), [("APP-1", AnomalyKind::DescriptionRule)]);
+    }
+
+    #[test]
+    fn orders_findings_by_time_across_offsets() {
+        let entries = [
+            worklog("APP-1", "2024-06-05T09:00:00.000+0000", 0, ""),
+            worklog("APP-2", "2024-06-05T10:00:00.000+0200", 0, ""),
+        ];
+        let anomalies = lint_worklogs(&entries, &DescriptionRules::default());
+        assert_eq!(kinds(&anomalies), [("APP-2", AnomalyKind::ZeroDuration), ("APP-1", AnomalyKind::ZeroDuration)]);
+    }
+}
@@ -0,0 +1,57 @@
+//! Logs go to stderr and to a daily file in the app log directory, the last
+//! `LOG_FILES` of which are kept for `diagnostics::export_diagnostics`.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager, Wry};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, Layer};
+
+pub const LOG_FILE_PREFIX: &str = "mini-jira";
+const LOG_FILES: usize = 7;
+
+/// Flushes the file writer when the app exits.
+static GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+pub fn log_dir(app_handle: &AppHandle<Wry>) -> Result<PathBuf, String> {
+    app_handle.path().app_log_dir().map_err(|e| e.to_string())
+}
+
+fn file_appender(app_handle: &AppHandle<Wry>) -> Result<RollingFileAppender, String> {
+    RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix("log")
+        .max_log_files(LOG_FILES)
+        .build(log_dir(app_handle)?)
+        .map_err(|e| e.to_string())
+}
+
+/// Installs the subscriber; without a writable log directory only stderr
+/// is used.
+pub fn init(app_handle: &AppHandle<Wry>) {
+    let (file, file_error) = match file_appender(app_handle) {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let _ = GUARD.set(guard);
+            (Some(fmt::layer().with_writer(writer).with_ansi(false)), None)
+        }
+        Err(e) => (None, Some(e)),
+    };
+
+    let result = tracing_subscriber::registry()
+        .with(file)
+        .with(fmt::layer().with_writer(std::io::stderr).with_filter(LevelFilter::WARN))
+        .with(LevelFilter::INFO)
+        .try_init();
+    if let Err(e) = result {
+        eprintln!("Failed to set up logging: {}", e);
+    }
+    if let Some(e) = file_error {
+        tracing::warn!("Logging to stderr only, the log directory is unavailable: {}", e);
+    }
+}
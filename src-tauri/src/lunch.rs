@@ -0,0 +1,207 @@
+//! Lunch detection: long idle spans around midday are recorded, and once
+//! they recur the typical lunch window is learned from them. During that
+//! window the timer pauses without asking about the idle time (see
+//! `timer::check_lunch`). A fixed window in `lunch.window` overrides it.
+
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::settings::{self, LunchWindow};
+use crate::{clock, store};
+
+const LUNCH_LOG_FILE: &str = "lunch_log.json";
+/// Hours an idle span has to start in to count as lunch.
+const MIDDAY_HOURS: Range<u32> = 11..15;
+const MIN_LUNCH_MINUTES: i64 = 20;
+const MAX_LUNCH_MINUTES: i64 = 120;
+/// Days of the log the window is learned from.
+const LEARN_DAYS: i64 = 21;
+/// Lunches within `LEARN_DAYS` needed before a window is trusted.
+const MIN_LUNCHES: usize = 3;
+
+pub type LunchState = Mutex<LunchTracker>;
+
+/// A day's longest midday idle span.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct IdleSpan {
+    pub start: NaiveTime,
+    pub minutes: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct LunchTracker {
+    days: BTreeMap<NaiveDate, IdleSpan>,
+    /// Start of the idle span in progress, once it is long enough for lunch.
+    away_since: Option<DateTime<Local>>,
+}
+
+impl LunchTracker {
+    /// Follows the idle time and records a finished span that looks like
+    /// lunch. Returns whether one was recorded.
+    fn observe(&mut self, idle_seconds: u64, now: DateTime<Local>) -> bool {
+        let idle_since = now - Duration::seconds(idle_seconds as i64);
+        if idle_seconds as i64 >= MIN_LUNCH_MINUTES * 60 {
+            self.away_since.get_or_insert(idle_since);
+            return false;
+        }
+        let Some(start) = self.away_since.take() else {
+            return false;
+        };
+
+        let minutes = (idle_since - start).num_minutes();
+        let midday = MIDDAY_HOURS.contains(&start.hour()) && start.date_naive() == idle_since.date_naive();
+        if !midday || !(MIN_LUNCH_MINUTES..=MAX_LUNCH_MINUTES).contains(&minutes) {
+            return false;
+        }
+
+        let span = IdleSpan {
+            start: start.time(),
+            minutes,
+        };
+        let day = self.days.entry(start.date_naive()).or_insert(span);
+        if span.minutes > day.minutes {
+            *day = span;
+        }
+        let oldest = now.date_naive() - Duration::days(LEARN_DAYS);
+        self.days.retain(|date, _| *date >= oldest);
+        true
+    }
+
+    /// The median start and length of the recent lunches, once there are
+    /// enough of them.
+    fn learned(&self, today: NaiveDate) -> Option<LunchWindow> {
+        let spans: Vec<&IdleSpan> = self.days.range(today - Duration::days(LEARN_DAYS)..).map(|(_, span)| span).collect();
+        if spans.len() < MIN_LUNCHES {
+            return None;
+        }
+
+        let mut starts: Vec<u32> = spans.iter().map(|span| span.start.num_seconds_from_midnight()).collect();
+        let mut lengths: Vec<i64> = spans.iter().map(|span| span.minutes).collect();
+        starts.sort_unstable();
+        lengths.sort_unstable();
+
+        let start = NaiveTime::from_num_seconds_from_midnight_opt(starts[starts.len() / 2], 0)?;
+        Some(LunchWindow {
+            start,
+            end: start + Duration::minutes(lengths[lengths.len() / 2]),
+        })
+    }
+}
+
+pub fn load(app_handle: &AppHandle<Wry>) -> LunchTracker {
+    let days = match store::data_file(app_handle, LUNCH_LOG_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(_) => BTreeMap::new(),
+    };
+    LunchTracker {
+        days,
+        away_since: None,
+    }
+}
+
+fn persist(app_handle: &AppHandle<Wry>, tracker: &LunchTracker) {
+    let result = store::data_file(app_handle, LUNCH_LOG_FILE).and_then(|path| store::save_json(&path, &tracker.days));
+    if let Err(e) = result {
+        tracing::warn!("Failed to save the lunch log: {}", e);
+    }
+}
+
+/// Called with every idle check, whether or not a timer runs.
+pub fn observe(app_handle: &AppHandle<Wry>, idle_seconds: u64, now: DateTime<Local>) {
+    if !settings::current(app_handle).lunch.learn {
+        return;
+    }
+    let state = app_handle.state::<LunchState>();
+    let mut tracker = state.lock().unwrap_or_else(|e| e.into_inner());
+    if tracker.observe(idle_seconds, now) {
+        persist(app_handle, &tracker);
+    }
+}
+
+/// The lunch window in effect: the configured one, otherwise the learned
+/// one if learning is on.
+pub fn window(app_handle: &AppHandle<Wry>) -> Option<LunchWindow> {
+    let settings = settings::current(app_handle).lunch;
+    if settings.window.is_some() || !settings.learn {
+        return settings.window;
+    }
+    let today = clock::now(app_handle).date_naive();
+    let state = app_handle.state::<LunchState>();
+    let tracker = state.lock().unwrap_or_else(|e| e.into_inner());
+    tracker.learned(today)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LunchReport {
+    /// The window in effect.
+    pub window: Option<LunchWindow>,
+    pub learned: Option<LunchWindow>,
+    /// Lunches seen in the last `LEARN_DAYS` days.
+    pub lunches_observed: usize,
+}
+
+#[tauri::command]
+pub async fn get_lunch_window(app_handle: AppHandle<Wry>, state: State<'_, LunchState>) -> Result<LunchReport, String> {
+    let today = clock::now(&app_handle).date_naive();
+    let (learned, lunches_observed) = {
+        let tracker = state.lock().map_err(|e| e.to_string())?;
+        let lunches_observed = tracker.days.range(today - Duration::days(LEARN_DAYS)..).count();
+        (tracker.learned(today), lunches_observed)
+    };
+
+    Ok(LunchReport {
+        window: window(&app_handle),
+        learned,
+        lunches_observed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 6, day, hour, minute, 0).unwrap()
+    }
+
+    /// Idle from `start` for `minutes`, checked once a minute.
+    fn step_away(tracker: &mut LunchTracker, start: DateTime<Local>, minutes: i64) -> bool {
+        for minute in 1..minutes {
+            tracker.observe((minute * 60) as u64, start + Duration::minutes(minute));
+        }
+        tracker.observe(0, start + Duration::minutes(minutes))
+    }
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn records_midday_spans_only() {
+        let mut tracker = LunchTracker::default();
+
+        assert!(step_away(&mut tracker, at(3, 12, 0), 45));
+        assert!(!step_away(&mut tracker, at(4, 9, 0), 45));
+        assert!(!step_away(&mut tracker, at(5, 12, 0), 10));
+        assert_eq!(tracker.days.len(), 1);
+    }
+
+    #[test]
+    fn learns_the_typical_window_once_lunch_recurs() {
+        let mut tracker = LunchTracker::default();
+        step_away(&mut tracker, at(3, 12, 0), 45);
+        step_away(&mut tracker, at(4, 12, 30), 30);
+        assert_eq!(tracker.learned(at(5, 9, 0).date_naive()), None);
+
+        step_away(&mut tracker, at(5, 12, 15), 60);
+        let window = tracker.learned(at(6, 9, 0).date_naive()).unwrap();
+
+        assert_eq!(window.start, time(12, 15));
+        assert_eq!(window.end, time(13, 0));
+    }
+}
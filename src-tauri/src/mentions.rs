@@ -0,0 +1,271 @@
+//! Other issues named in a worklog description, e.g. "paired with PROJ-99".
+//! The frontend offers to link them to the logged issue or to split the time
+//! between them.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::{AppHandle, State, Wry};
+
+use crate::adf::CommentInput;
+use crate::closed_guard;
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::offline_queue::{self, WorklogSubmission};
+use crate::{operations, settings, worklog_rules};
+use crate::{client_for, JiraState};
+
+/// Mentions looked up per description.
+const MAX_MENTIONS: usize = 5;
+const DEFAULT_LINK_TYPE: &str = "Relates";
+const SPLIT_BUDGET: Duration = Duration::from_secs(2 * 60);
+
+fn issue_key_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\b[A-Z][A-Z0-9_]+-[1-9][0-9]*\b").expect("valid issue key pattern"))
+}
+
+/// Issue keys in `text` other than `own_key`, in order of appearance.
+pub fn find_issue_keys(text: &str, own_key: &str) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    for found in issue_key_pattern().find_iter(text) {
+        let key = found.as_str();
+        if key != own_key && !keys.iter().any(|k| k == key) {
+            keys.push(key.to_string());
+        }
+    }
+    keys.truncate(MAX_MENTIONS);
+    keys
+}
+
+/// `total` split evenly over `parts` issues; the first gets the remainder.
+fn split_seconds(total: u32, parts: usize) -> Vec<u32> {
+    let parts = parts.max(1) as u32;
+    let share = total / parts;
+    let mut shares = vec![share; parts as usize];
+    shares[0] += total % parts;
+    shares
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MentionedIssue {
+    pub key: String,
+    pub summary: String,
+    pub status: String,
+    /// Already linked to the logged issue, either way round.
+    pub linked: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct LinkedIssue {
+    key: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IssueLink {
+    inward_issue: Option<LinkedIssue>,
+    outward_issue: Option<LinkedIssue>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct IssueLinkFields {
+    issuelinks: Vec<IssueLink>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueLinks {
+    #[serde(default)]
+    fields: IssueLinkFields,
+}
+
+impl JiraClient {
+    /// Keys of the issues linked to `issue_key`.
+    async fn get_linked_keys(&self, issue_key: &str) -> Result<Vec<String>, JiraError> {
+        let url = self.api_url(&format!("issue/{}", issue_key));
+
+        let authorization = self.authorization().await?;
+        let request = self
+            .http_client()
+            .get(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Authorization", authorization)
+            .query(&[("fields", "issuelinks")]);
+        let response = self.send(request).await?;
+
+        let issue: IssueLinks = Self::read_json("issue_links", response).await?;
+        Ok(issue
+            .fields
+            .issuelinks
+            .into_iter()
+            .filter_map(|link| link.inward_issue.or(link.outward_issue))
+            .map(|issue| issue.key)
+            .collect())
+    }
+
+    /// Links `outward` to `inward` with the link type named `link_type`,
+    /// e.g. "Relates".
+    async fn link_issues(&self, link_type: &str, inward: &str, outward: &str) -> Result<(), JiraError> {
+        let url = self.api_url("issueLink");
+        let body = json!({
+            "type": { "name": link_type },
+            "inwardIssue": { "key": inward },
+            "outwardIssue": { "key": outward },
+        });
+
+        let authorization = self.authorization().await?;
+        let request = self
+            .http_client()
+            .post(&url)
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .header("Authorization", authorization)
+            .json(&body);
+        self.send(request).await?;
+
+        Ok(())
+    }
+}
+
+/// The issues `description` mentions besides `issue_key`. Keys that turn
+/// out not to be issues, like "UTF-8", are left out.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn find_mentioned_issues(
+    issue_key: String,
+    description: CommentInput,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<MentionedIssue>, JiraError> {
+    let keys = find_issue_keys(&description.to_document().text(), &issue_key);
+    if keys.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let client = client_for(&state, connection.as_deref()).await?;
+    let linked = client.get_linked_keys(&issue_key).await?;
+    let mut mentioned = Vec::new();
+    for key in keys {
+        match client.get_issue(&key, "summary,status", &[]).await {
+            Ok(issue) => mentioned.push(MentionedIssue {
+                linked: linked.contains(&issue.key),
+                key: issue.key,
+                summary: issue.fields.summary,
+                status: issue.fields.status.name,
+            }),
+            Err(JiraError::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(mentioned)
+}
+
+/// Links `other_key` to `issue_key`, by default as "Relates".
+#[tauri::command(rename_all = "camelCase")]
+pub async fn link_mentioned_issue(
+    issue_key: String,
+    other_key: String,
+    link_type: Option<String>,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<(), JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    client
+        .link_issues(link_type.as_deref().unwrap_or(DEFAULT_LINK_TYPE), &issue_key, &other_key)
+        .await
+}
+
+/// A worklog to split between `issue_key` and `other_keys`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitWorklog {
+    pub issue_key: String,
+    pub other_keys: Vec<String>,
+    pub description: CommentInput,
+    pub started: String,
+    pub time_spent: String,
+    /// Logs it even if an issue is done or it is a weekend; see
+    /// `closed_issue_guard` and `worklog_rules`.
+    #[serde(default)]
+    pub confirmed: bool,
+}
+
+/// Logs `time_spent` split evenly between the issues, each with the same
+/// description. Entries go through the offline queue like single worklogs.
+/// Progress is reported under `operation`; when it is cancelled the entries
+/// made so far are returned.
+#[tauri::command]
+pub async fn split_worklog(
+    worklog: SplitWorklog,
+    connection: Option<String>,
+    operation: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<WorklogSubmission>, JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    let SplitWorklog {
+        issue_key,
+        other_keys,
+        description,
+        started,
+        time_spent,
+        confirmed,
+    } = worklog;
+    let total = JiraClient::parse_time_to_seconds(&time_spent, &settings::current(&app_handle).time_units)?;
+
+    let mut keys = vec![issue_key];
+    for key in other_keys {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    if let Some(key) = keys.iter().find(|key| !JiraClient::is_valid_issue_key(key)) {
+        return Err(JiraError::Validation(format!("'{}' is not an issue key", key)));
+    }
+    let shares = split_seconds(total, keys.len());
+    if shares.contains(&0) {
+        return Err(JiraError::Validation(format!("{} can't be split {} ways", time_spent, keys.len())));
+    }
+    let mut normalized = Vec::new();
+    for share in shares {
+        normalized.push(worklog_rules::normalize(&app_handle, &started, share, confirmed)?);
+    }
+    for key in &keys {
+        closed_guard::check(&app_handle, &client, key, confirmed).await?;
+    }
+
+    let operation = operations::start(&app_handle, operation, "Split worklog", Some(keys.len() as u32), SPLIT_BUDGET);
+    let mut submissions = Vec::new();
+    for (key, (started, seconds)) in keys.iter().zip(normalized) {
+        if operation.should_stop() {
+            break;
+        }
+        match offline_queue::submit(&app_handle, &client, connection.as_deref(), key, &description, &started, seconds).await {
+            Ok(submission) => submissions.push(submission),
+            Err(e) => return operation.complete(Err(e)),
+        }
+        operation.advance(1);
+    }
+    operation.complete(Ok(submissions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_other_issue_keys_once() {
+        let text = "Paired with PROJ-99 on WEB-1, then PROJ-99 again. Not APP-0, proj-5 or PROJ-99x.";
+        assert_eq!(find_issue_keys(text, "WEB-1"), ["PROJ-99"]);
+        assert_eq!(find_issue_keys("(OPS-12), A_B-3;", "WEB-1"), ["OPS-12", "A_B-3"]);
+    }
+
+    #[test]
+    fn splits_time_evenly() {
+        assert_eq!(split_seconds(3600, 2), [1800, 1800]);
+        assert_eq!(split_seconds(3601, 3), [1201, 1200, 1200]);
+    }
+}
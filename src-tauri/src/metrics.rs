@@ -0,0 +1,42 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Response sizes per JIRA endpoint, to see what slow refreshes over thin
+/// connections are actually downloading.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PayloadMetrics {
+    pub responses: u64,
+    /// Bytes received, compressed when the server used gzip.
+    pub wire_bytes: u64,
+    /// Bytes of JSON after decompression.
+    pub json_bytes: u64,
+    pub largest_json_bytes: u64,
+}
+
+static PAYLOADS: OnceLock<Mutex<BTreeMap<&'static str, PayloadMetrics>>> = OnceLock::new();
+
+fn payloads() -> &'static Mutex<BTreeMap<&'static str, PayloadMetrics>> {
+    PAYLOADS.get_or_init(Default::default)
+}
+
+pub fn record_payload(endpoint: &'static str, wire_bytes: usize, json_bytes: usize) {
+    let mut payloads = payloads().lock().unwrap_or_else(|e| e.into_inner());
+    let metrics = payloads.entry(endpoint).or_default();
+    metrics.responses += 1;
+    metrics.wire_bytes += wire_bytes as u64;
+    metrics.json_bytes += json_bytes as u64;
+    metrics.largest_json_bytes = metrics.largest_json_bytes.max(json_bytes as u64);
+}
+
+#[tauri::command]
+pub async fn get_payload_metrics() -> Result<BTreeMap<&'static str, PayloadMetrics>, String> {
+    let payloads = payloads().lock().map_err(|e| e.to_string())?;
+    Ok(payloads.clone())
+}
+
+#[tauri::command]
+pub async fn reset_payload_metrics() -> Result<(), String> {
+    payloads().lock().map_err(|e| e.to_string())?.clear();
+    Ok(())
+}
@@ -0,0 +1,300 @@
+//! The month-end routine. `run_month_close(month)` goes through the
+//! checklist (every workday at its target, nothing of the month still
+//! waiting in the offline queue, no lint findings that need fixing) and,
+//! when all of it passes, writes the export bundle and closes the month:
+//! from then on worklogs starting in it can't be logged, edited, moved in
+//! or out, or deleted until `reopen_month`. A failed check closes nothing;
+//! the checklist says what is left to do.
+
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::error::JiraError;
+use crate::export::{self, ExportFormat};
+use crate::jira_api::JiraClient;
+use crate::lint::AnomalyKind;
+use crate::offline_queue::{OfflineQueue, PendingWorklog};
+use crate::summary::{self, DaySummary, ReportGrouping};
+use crate::worklog_backend::{self, WorklogBackend};
+use crate::{analytics, client_for, clock, lint, settings, store, vault, JiraState};
+
+const CLOSED_FILE: &str = "closed_months.json";
+/// Days and worklogs named in a check's message; the rest are counted.
+const LISTED: usize = 5;
+
+pub type MonthCloseState = Mutex<Vec<ClosedMonth>>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClosedMonth {
+    /// `2024-06`.
+    pub month: String,
+    pub closed_at: DateTime<Local>,
+    /// The directory the export bundle went to.
+    pub bundle: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseCheckKind {
+    Targets,
+    Queue,
+    Lint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CloseCheck {
+    pub kind: CloseCheckKind,
+    pub passed: bool,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthClose {
+    pub month: String,
+    pub checks: Vec<CloseCheck>,
+    /// Set when every check passed and the month was closed.
+    pub closed: Option<ClosedMonth>,
+}
+
+pub fn load(app_handle: &AppHandle<Wry>) -> MonthCloseState {
+    let closed = match store::data_file(app_handle, CLOSED_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(_) => Vec::new(),
+    };
+    Mutex::new(closed)
+}
+
+fn persist(app_handle: &AppHandle<Wry>, closed: &[ClosedMonth]) -> Result<(), String> {
+    let path = store::data_file(app_handle, CLOSED_FILE)?;
+    store::save_json(&path, &closed)
+}
+
+/// The first and last day of `2024-06`.
+fn parse_month(month: &str) -> Result<(NaiveDate, NaiveDate), JiraError> {
+    let invalid = || JiraError::Validation(format!("Not a month: {} (use 2024-06)", month));
+    let first = NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d").map_err(|_| invalid())?;
+    let last = first
+        .checked_add_months(chrono::Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .ok_or_else(invalid)?;
+    Ok((first, last))
+}
+
+fn month_of(date: NaiveDate) -> String {
+    format!("{:04}-{:02}", date.year(), date.month())
+}
+
+/// Refuses changes to worklogs starting on `date` once its month is closed.
+pub fn check_open(app_handle: &AppHandle<Wry>, date: NaiveDate) -> Result<(), JiraError> {
+    let month = month_of(date);
+    let state = app_handle.state::<MonthCloseState>();
+    if state.lock().unwrap_or_else(|e| e.into_inner()).iter().any(|closed| closed.month == month) {
+        return Err(JiraError::Validation(format!("{} is closed; reopen it to change worklogs on {}", month, date)));
+    }
+    Ok(())
+}
+
+/// `check_open` for a JIRA `started` timestamp.
+pub fn check_started(app_handle: &AppHandle<Wry>, started: &str) -> Result<(), JiraError> {
+    check_open(app_handle, JiraClient::parse_jira_datetime(started)?.with_timezone(&Local).date_naive())
+}
+
+/// Findings that hold the month open. A description repeated on another day
+/// (a daily standup) or a weekend worklog can be right, so they don't.
+fn blocks_close(kind: AnomalyKind) -> bool {
+    matches!(kind, AnomalyKind::ZeroDuration | AnomalyKind::ExcessiveDuration | AnomalyKind::DescriptionRule)
+}
+
+/// " and 3 more" past the first `LISTED`.
+fn listed(items: &[String]) -> String {
+    let mut text = items.iter().take(LISTED).cloned().collect::<Vec<_>>().join(", ");
+    if items.len() > LISTED {
+        text.push_str(&format!(" and {} more", items.len() - LISTED));
+    }
+    text
+}
+
+fn targets_check(days: &[DaySummary], format: impl Fn(u32) -> String) -> CloseCheck {
+    let short: Vec<String> = days
+        .iter()
+        .filter(|day| day.seconds < day.target_seconds)
+        .map(|day| format!("{} ({} of {})", day.date, format(day.seconds), format(day.target_seconds)))
+        .collect();
+    CloseCheck {
+        kind: CloseCheckKind::Targets,
+        passed: short.is_empty(),
+        message: match short.len() {
+            0 => "Every workday is at its target".to_string(),
+            1 => format!("1 workday is under its target: {}", listed(&short)),
+            count => format!("{} workdays are under their target: {}", count, listed(&short)),
+        },
+    }
+}
+
+fn queue_check(pending: &[PendingWorklog], from: NaiveDate, to: NaiveDate) -> CloseCheck {
+    let waiting: Vec<String> = pending
+        .iter()
+        .filter(|entry| {
+            JiraClient::parse_jira_datetime(&entry.started)
+                .is_ok_and(|started| (from..=to).contains(&started.with_timezone(&Local).date_naive()))
+        })
+        .map(|entry| entry.issue_key.clone())
+        .collect();
+    CloseCheck {
+        kind: CloseCheckKind::Queue,
+        passed: waiting.is_empty(),
+        message: match waiting.len() {
+            0 => "Every worklog is submitted".to_string(),
+            count => format!("{} still waiting in the offline queue: {}", crate::offline_queue::worklogs(count), listed(&waiting)),
+        },
+    }
+}
+
+/// Checks `month` and, when everything is in order, writes the export
+/// bundle (`worklogs.xlsx`, `timer_sessions.csv` and `checklist.json`) to
+/// `dir`, by default `month_close/<month>` in the app data directory, and
+/// closes the month. Months that aren't over yet can't be closed. The bundle
+/// isn't encrypted, so with the vault on `dir` has to be given.
+#[tauri::command]
+pub async fn run_month_close(
+    month: String,
+    dir: Option<PathBuf>,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<MonthClose, JiraError> {
+    let (from, to) = parse_month(&month)?;
+    let month = month_of(from);
+    if to >= clock::now(&app_handle).date_naive() {
+        return Err(JiraError::Validation(format!("{} isn't over yet", month)));
+    }
+    if app_handle.state::<MonthCloseState>().lock().map_err(|e| e.to_string())?.iter().any(|closed| closed.month == month) {
+        return Err(JiraError::Validation(format!("{} is already closed", month)));
+    }
+    if dir.is_none() && vault::is_enabled(&app_handle) {
+        return Err(JiraError::Validation(
+            "The bundle isn't encrypted; choose where it goes while the vault is on".to_string(),
+        ));
+    }
+
+    let settings = settings::effective(&app_handle);
+    let client = client_for(&state, connection.as_deref()).await?;
+    let backend = worklog_backend::for_app(&app_handle, client.clone())?;
+    let worklogs = backend.user_worklogs(from, to).await?;
+    analytics::record_range(&app_handle, &client, from, to, &worklogs);
+
+    let summary = summary::summarize(&worklogs, from, to, &settings);
+    let anomalies = lint::lint_worklogs(&worklogs, &settings.description_rules);
+    let findings: Vec<String> = anomalies
+        .iter()
+        .filter(|anomaly| blocks_close(anomaly.kind))
+        .map(|anomaly| anomaly.message.clone())
+        .collect();
+    let checks = vec![
+        targets_check(&summary.days, |seconds| JiraClient::format_seconds_to_jira_duration(seconds, &settings.time_units)),
+        queue_check(&app_handle.state::<OfflineQueue>().entries(), from, to),
+        CloseCheck {
+            kind: CloseCheckKind::Lint,
+            passed: findings.is_empty(),
+            message: match findings.len() {
+                0 => "Lint finds nothing".to_string(),
+                count => format!("Lint finds {} to look at: {}", count, listed(&findings)),
+            },
+        },
+    ];
+    if checks.iter().any(|check| !check.passed) {
+        return Ok(MonthClose { month, checks, closed: None });
+    }
+
+    let bundle = match dir {
+        Some(dir) => dir,
+        None => store::data_file(&app_handle, "month_close")?.join(&month),
+    };
+    let write_error = |e: &dyn std::fmt::Display| JiraError::Other(format!("Failed to write the bundle to {}: {}", bundle.display(), e));
+    fs::create_dir_all(&bundle).map_err(|e| write_error(&e))?;
+    export::write_timesheet(&bundle.join("worklogs.xlsx"), ExportFormat::Xlsx, worklogs, &settings, ReportGrouping::default())?;
+    export::write_timer_sessions(&app_handle, &bundle.join("timer_sessions.csv"), ExportFormat::Csv, from, to)?;
+    let checklist = serde_json::to_string_pretty(&checks).map_err(|e| e.to_string())?;
+    fs::write(bundle.join("checklist.json"), checklist).map_err(|e| write_error(&e))?;
+
+    let closed = ClosedMonth {
+        month: month.clone(),
+        closed_at: clock::now(&app_handle),
+        bundle,
+    };
+    {
+        let state = app_handle.state::<MonthCloseState>();
+        let mut months = state.lock().map_err(|e| e.to_string())?;
+        let mut closed_months = months.clone();
+        closed_months.push(closed.clone());
+        closed_months.sort_by(|a, b| a.month.cmp(&b.month));
+        // Written first, so a failed write doesn't leave the month closed
+        // only until the next start.
+        persist(&app_handle, &closed_months)?;
+        *months = closed_months;
+    }
+    Ok(MonthClose {
+        month,
+        checks,
+        closed: Some(closed),
+    })
+}
+
+#[tauri::command]
+pub async fn list_closed_months(state: State<'_, MonthCloseState>) -> Result<Vec<ClosedMonth>, String> {
+    Ok(state.lock().map_err(|e| e.to_string())?.clone())
+}
+
+/// Takes the lock off `month`; the bundle stays where it is.
+#[tauri::command]
+pub async fn reopen_month(month: String, app_handle: AppHandle<Wry>, state: State<'_, MonthCloseState>) -> Result<(), JiraError> {
+    let month = month_of(parse_month(&month)?.0);
+    let mut months = state.lock().map_err(|e| e.to_string())?;
+    let open: Vec<ClosedMonth> = months.iter().filter(|closed| closed.month != month).cloned().collect();
+    if open.len() == months.len() {
+        return Err(JiraError::NotFound(format!("{} isn't closed", month)));
+    }
+    persist(&app_handle, &open)?;
+    *months = open;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(date: u32, hours: u32, target_hours: u32) -> DaySummary {
+        DaySummary {
+            date: NaiveDate::from_ymd_opt(2024, 6, date).unwrap(),
+            seconds: hours * 3600,
+            target_seconds: target_hours * 3600,
+            issues: Vec::new(),
+            clients: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn checks_the_month() {
+        let (from, to) = parse_month("2024-02").unwrap();
+        assert_eq!((from.day(), to.day()), (1, 29));
+        assert!(parse_month("2024-13").is_err());
+        assert_eq!(month_of(to), "2024-02");
+
+        let hours = |seconds: u32| format!("{}h", seconds / 3600);
+        let passed = targets_check(&[day(7, 8, 8), day(8, 0, 0)], hours);
+        assert!(passed.passed);
+        let short = targets_check(&[day(6, 6, 8), day(7, 9, 8)], hours);
+        assert_eq!(short.message, "1 workday is under its target: 2024-06-06 (6h of 8h)");
+
+        assert!(blocks_close(AnomalyKind::ZeroDuration));
+        assert!(!blocks_close(AnomalyKind::DuplicateDescription));
+        assert!(!blocks_close(AnomalyKind::WeekendEntry));
+
+        let items: Vec<String> = (1..=7).map(|n| n.to_string()).collect();
+        assert_eq!(listed(&items), "1, 2, 3, 4, 5 and 2 more");
+    }
+}
@@ -0,0 +1,263 @@
+use tauri::{AppHandle, Emitter, Wry};
+
+use crate::settings::ReminderAction;
+use crate::notifications::NotificationKind;
+use crate::{notifications, scheduler, settings, tray};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationAction {
+    LogNow,
+    Snooze,
+    /// Files a catch-all issue for the time; see `catch_all`.
+    NewTask,
+}
+
+impl NotificationAction {
+    pub const ALL: [NotificationAction; 3] = [NotificationAction::LogNow, NotificationAction::Snooze, NotificationAction::NewTask];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            NotificationAction::LogNow => "log_now",
+            NotificationAction::Snooze => "snooze",
+            NotificationAction::NewTask => "new_task",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            NotificationAction::LogNow => "Log now",
+            NotificationAction::Snooze => "Snooze",
+            NotificationAction::NewTask => "New task",
+        }
+    }
+
+    /// Accepts either the action id or its label, since macOS reports the
+    /// button title rather than an id.
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|action| action.id() == value || action.label() == value)
+    }
+}
+
+pub type ActionCallback = Box<dyn FnOnce(NotificationAction) + Send + 'static>;
+
+/// A native notification with buttons. Each platform has its own mechanism
+/// (toast buttons, D-Bus actions, NSUserNotification buttons); the callback
+/// runs at most once, and not at all when the notification is dismissed.
+pub trait ActionNotifier {
+    fn show(
+        &self,
+        title: &str,
+        body: &str,
+        actions: &[NotificationAction],
+        on_action: ActionCallback,
+    ) -> Result<(), String>;
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use super::{ActionCallback, ActionNotifier, NotificationAction};
+
+    /// org.freedesktop.Notifications actions via notify-rust.
+    pub struct PlatformNotifier {
+        pub app_name: String,
+    }
+
+    impl ActionNotifier for PlatformNotifier {
+        fn show(
+            &self,
+            title: &str,
+            body: &str,
+            actions: &[NotificationAction],
+            on_action: ActionCallback,
+        ) -> Result<(), String> {
+            let mut notification = notify_rust::Notification::new();
+            notification.appname(&self.app_name).summary(title).body(body);
+            for action in actions {
+                notification.action(action.id(), action.label());
+            }
+
+            let handle = notification.show().map_err(|e| e.to_string())?;
+            // Waiting blocks until the notification is acted on or closed.
+            std::thread::spawn(move || {
+                handle.wait_for_action(|id| {
+                    if let Some(action) = NotificationAction::parse(id) {
+                        on_action(action);
+                    }
+                });
+            });
+            Ok(())
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{ActionCallback, ActionNotifier, NotificationAction};
+    use std::sync::Mutex;
+    use tauri_winrt_notification::Toast;
+
+    /// Toast notification buttons.
+    pub struct PlatformNotifier {
+        /// AppUserModelID, `None` while running from a build directory where
+        /// the app is not registered and toasts must borrow PowerShell's.
+        pub app_id: Option<String>,
+    }
+
+    impl ActionNotifier for PlatformNotifier {
+        fn show(
+            &self,
+            title: &str,
+            body: &str,
+            actions: &[NotificationAction],
+            on_action: ActionCallback,
+        ) -> Result<(), String> {
+            let app_id = self.app_id.as_deref().unwrap_or(Toast::POWERSHELL_APP_ID);
+            let mut toast = Toast::new(app_id).title(title).text1(body);
+            for action in actions {
+                toast = toast.add_button(action.label(), action.id());
+            }
+
+            let callback = Mutex::new(Some(on_action));
+            toast
+                .on_activated(move |arguments| {
+                    let action = arguments.as_deref().and_then(NotificationAction::parse);
+                    let callback = callback.lock().ok().and_then(|mut callback| callback.take());
+                    if let (Some(action), Some(callback)) = (action, callback) {
+                        callback(action);
+                    }
+                    Ok(())
+                })
+                .show()
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{ActionCallback, ActionNotifier, NotificationAction};
+    use mac_notification_sys::{MainButton, Notification, NotificationResponse};
+
+    /// NSUserNotification action buttons via mac-notification-sys.
+    pub struct PlatformNotifier {
+        pub bundle_id: String,
+    }
+
+    impl ActionNotifier for PlatformNotifier {
+        fn show(
+            &self,
+            title: &str,
+            body: &str,
+            actions: &[NotificationAction],
+            on_action: ActionCallback,
+        ) -> Result<(), String> {
+            let _ = mac_notification_sys::set_application(&self.bundle_id);
+
+            let title = title.to_string();
+            let body = body.to_string();
+            let labels: Vec<&'static str> = actions.iter().map(|action| action.label()).collect();
+
+            // `send` blocks until the user responds to the notification.
+            std::thread::spawn(move || {
+                let mut notification = Notification::new();
+                notification.title(&title).message(&body).wait_for_click(true);
+                match labels.as_slice() {
+                    [] => {}
+                    [label] => {
+                        notification.main_button(MainButton::SingleAction(label));
+                    }
+                    labels => {
+                        notification.main_button(MainButton::DropdownActions("Actions", labels));
+                    }
+                }
+
+                match notification.send() {
+                    Ok(NotificationResponse::ActionButton(label)) => {
+                        if let Some(action) = NotificationAction::parse(&label) {
+                            on_action(action);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Failed to show notification: {}", e),
+                }
+            });
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn notifier(app_handle: &AppHandle<Wry>) -> platform::PlatformNotifier {
+    platform::PlatformNotifier {
+        app_name: app_handle.package_info().name.clone(),
+    }
+}
+
+#[cfg(windows)]
+fn notifier(app_handle: &AppHandle<Wry>) -> platform::PlatformNotifier {
+    // Same rule as the notification plugin: only the installed app has a
+    // registered AppUserModelID.
+    let installed = tauri::utils::platform::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+        .is_some_and(|dir| !dir.ends_with("target/debug") && !dir.ends_with("target/release"));
+
+    platform::PlatformNotifier {
+        app_id: installed.then(|| app_handle.config().identifier.clone()),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn notifier(app_handle: &AppHandle<Wry>) -> platform::PlatformNotifier {
+    platform::PlatformNotifier {
+        bundle_id: if tauri::is_dev() {
+            "com.apple.Terminal".to_string()
+        } else {
+            app_handle.config().identifier.clone()
+        },
+    }
+}
+
+/// Shows the worklog reminder with "Log now" and "Snooze" buttons, and "New
+/// task" once a catch-all project is set. Falls back to a plain
+/// notification when the platform refuses actionable ones.
+pub fn show_worklog_reminder(app_handle: &AppHandle<Wry>, title: &str, body: &str) {
+    if !notifications::allowed(app_handle, NotificationKind::Reminder, title) {
+        return;
+    }
+    let handle = app_handle.clone();
+    let on_action: ActionCallback = Box::new(move |action| handle_action(&handle, action));
+    let catch_all = settings::current(app_handle).catch_all.target().is_some();
+    let actions: Vec<NotificationAction> = NotificationAction::ALL
+        .into_iter()
+        .filter(|action| catch_all || *action != NotificationAction::NewTask)
+        .collect();
+
+    if let Err(e) = notifier(app_handle).show(title, body, &actions, on_action) {
+        tracing::warn!("Failed to show actionable notification: {}", e);
+        notifications::notify_with_sound(app_handle, NotificationKind::Reminder, title, body);
+    }
+}
+
+fn handle_action(app_handle: &AppHandle<Wry>, action: NotificationAction) {
+    match action {
+        NotificationAction::LogNow => {
+            tray::stop_flashing(app_handle);
+            crate::show_quick_log_window(app_handle);
+        }
+        NotificationAction::Snooze => {
+            scheduler::snooze(app_handle, ReminderAction::Sound);
+        }
+        NotificationAction::NewTask => {
+            tray::stop_flashing(app_handle);
+            crate::show_quick_log_window(app_handle);
+            // The quick log window asks for a description and calls
+            // `create_catch_all_task`.
+            if let Err(e) = app_handle.emit("catch-all-requested", settings::current(app_handle).catch_all) {
+                tracing::warn!("Failed to emit catch-all-requested event: {}", e);
+            }
+        }
+    }
+}
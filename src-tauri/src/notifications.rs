@@ -0,0 +1,193 @@
+//! Every desktop notification goes through here, so the `notifications`
+//! setting applies to all of them: quiet hours, muting while another app is
+//! full screen, a switch per kind, and `snooze_notifications`. Held-back
+//! notifications are dropped, not shown later.
+
+use chrono::{DateTime, Duration, Local};
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::settings::{self, NotificationSettings};
+use crate::{clock, platform};
+
+/// Longest snooze; a day's quiet is what quiet hours are for.
+const MAX_SNOOZE_MINUTES: u32 = 24 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    Reminder,
+    Sync,
+    Timer,
+    /// The answer to something I just did, e.g. a tray action; always
+    /// shown.
+    Feedback,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MuteReason {
+    /// The kind is switched off.
+    Disabled,
+    Snoozed,
+    QuietHours,
+    Fullscreen,
+}
+
+/// Until when notifications are snoozed.
+pub type NotificationState = Mutex<Option<DateTime<Local>>>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationPolicy {
+    #[serde(flatten)]
+    pub settings: NotificationSettings,
+    pub snoozed_until: Option<DateTime<Local>>,
+    /// Why reminders are held back right now, if they are.
+    pub muted: Option<MuteReason>,
+}
+
+/// Why a notification of `kind` would be held back at `now`. Full screen
+/// is only checked when nothing else decides it.
+fn muted_by(
+    settings: &NotificationSettings,
+    kind: NotificationKind,
+    snoozed_until: Option<DateTime<Local>>,
+    now: DateTime<Local>,
+    fullscreen: impl FnOnce() -> bool,
+) -> Option<MuteReason> {
+    let enabled = match kind {
+        NotificationKind::Reminder => settings.reminders,
+        NotificationKind::Sync => settings.sync,
+        NotificationKind::Timer => settings.timer,
+        NotificationKind::Feedback => return None,
+    };
+    if !enabled {
+        Some(MuteReason::Disabled)
+    } else if snoozed_until.is_some_and(|until| now < until) {
+        Some(MuteReason::Snoozed)
+    } else if settings.quiet_hours.is_some_and(|quiet| quiet.contains(now.time())) {
+        Some(MuteReason::QuietHours)
+    } else if settings.mute_when_fullscreen && fullscreen() {
+        Some(MuteReason::Fullscreen)
+    } else {
+        None
+    }
+}
+
+fn muted(app_handle: &AppHandle<Wry>, kind: NotificationKind) -> Option<MuteReason> {
+    let snoozed_until = *app_handle.state::<NotificationState>().lock().unwrap_or_else(|e| e.into_inner());
+    let settings = settings::current(app_handle).notifications;
+    muted_by(&settings, kind, snoozed_until, clock::now(app_handle), platform::fullscreen_app_active)
+}
+
+/// Whether a notification of `kind` may be shown now, for notifications
+/// shown some other way, such as the actionable reminders.
+pub fn allowed(app_handle: &AppHandle<Wry>, kind: NotificationKind, title: &str) -> bool {
+    match muted(app_handle, kind) {
+        Some(reason) => {
+            tracing::debug!("Held back the notification '{}': {:?}", title, reason);
+            false
+        }
+        None => true,
+    }
+}
+
+pub fn notify(app_handle: &AppHandle<Wry>, kind: NotificationKind, title: &str, body: &str) {
+    if !allowed(app_handle, kind, title) {
+        return;
+    }
+    if let Err(e) = app_handle.notification().builder().title(title).body(body).show() {
+        tracing::warn!("Failed to show notification: {}", e);
+    }
+}
+
+pub fn notify_with_sound(app_handle: &AppHandle<Wry>, kind: NotificationKind, title: &str, body: &str) {
+    if !allowed(app_handle, kind, title) {
+        return;
+    }
+    let result = app_handle
+        .notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .sound("default")
+        .show();
+
+    if let Err(e) = result {
+        tracing::warn!("Failed to show notification: {}", e);
+    }
+}
+
+fn policy(app_handle: &AppHandle<Wry>, snoozed_until: Option<DateTime<Local>>) -> NotificationPolicy {
+    let settings = settings::current(app_handle).notifications;
+    let now = clock::now(app_handle);
+    NotificationPolicy {
+        muted: muted_by(&settings, NotificationKind::Reminder, snoozed_until, now, platform::fullscreen_app_active),
+        snoozed_until: snoozed_until.filter(|until| now < *until),
+        settings,
+    }
+}
+
+/// The notification settings and whether notifications are held back now.
+#[tauri::command]
+pub async fn get_notification_policy(
+    app_handle: AppHandle<Wry>,
+    state: State<'_, NotificationState>,
+) -> Result<NotificationPolicy, String> {
+    let snoozed_until = *state.lock().map_err(|e| e.to_string())?;
+    Ok(policy(&app_handle, snoozed_until))
+}
+
+/// Holds every notification but feedback back for `minutes`; 0 ends a
+/// snooze.
+#[tauri::command]
+pub async fn snooze_notifications(
+    minutes: u32,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, NotificationState>,
+) -> Result<NotificationPolicy, String> {
+    if minutes > MAX_SNOOZE_MINUTES {
+        return Err(format!("Notifications can be snoozed for at most {} minutes", MAX_SNOOZE_MINUTES));
+    }
+    let snoozed_until = (minutes > 0).then(|| clock::now(&app_handle) + Duration::minutes(minutes as i64));
+    *state.lock().map_err(|e| e.to_string())? = snoozed_until;
+    Ok(policy(&app_handle, snoozed_until))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::QuietHours;
+    use chrono::{NaiveTime, TimeZone};
+
+    fn at(hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 6, 7, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn holds_notifications_back_by_policy() {
+        let mut settings = NotificationSettings {
+            quiet_hours: Some(QuietHours {
+                start: NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            }),
+            ..Default::default()
+        };
+        let muted = |settings: &NotificationSettings, kind, snoozed_until, now, fullscreen: bool| {
+            muted_by(settings, kind, snoozed_until, now, || fullscreen)
+        };
+
+        assert_eq!(muted(&settings, NotificationKind::Sync, None, at(12, 0), false), None);
+        assert_eq!(muted(&settings, NotificationKind::Sync, None, at(7, 59), false), Some(MuteReason::QuietHours));
+        assert_eq!(muted(&settings, NotificationKind::Sync, None, at(22, 0), false), Some(MuteReason::QuietHours));
+        assert_eq!(muted(&settings, NotificationKind::Timer, None, at(12, 0), true), Some(MuteReason::Fullscreen));
+        assert_eq!(muted(&settings, NotificationKind::Reminder, Some(at(12, 30)), at(12, 0), false), Some(MuteReason::Snoozed));
+        assert_eq!(muted(&settings, NotificationKind::Reminder, Some(at(12, 30)), at(12, 30), false), None);
+        assert_eq!(muted(&settings, NotificationKind::Feedback, Some(at(23, 0)), at(22, 0), true), None);
+
+        settings.timer = false;
+        assert_eq!(muted(&settings, NotificationKind::Timer, None, at(12, 0), false), Some(MuteReason::Disabled));
+    }
+}
@@ -0,0 +1,391 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, State, Wry};
+use tauri_plugin_opener::OpenerExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use crate::error::JiraError;
+use crate::jira_api::{JiraAuth, JiraClient};
+use crate::connections::{self, DEFAULT_CONNECTION};
+use crate::{connected_client, credentials, http, offline, JiraState};
+
+const AUTHORIZE_URL: &str = "https://auth.atlassian.com/authorize";
+const TOKEN_URL: &str = "https://auth.atlassian.com/oauth/token";
+const RESOURCES_URL: &str = "https://api.atlassian.com/oauth/token/accessible-resources";
+const API_GATEWAY: &str = "https://api.atlassian.com/ex/jira";
+const SCOPES: &str = "read:jira-work write:jira-work read:jira-user offline_access";
+/// Must match the callback URL registered for the app in the Atlassian
+/// developer console.
+pub const DEFAULT_REDIRECT_PORT: u16 = 8976;
+const REDIRECT_PATH: &str = "/callback";
+const REDIRECT_TIMEOUT_SECS: u64 = 300;
+/// Tokens are refreshed this long before they actually expire.
+const REFRESH_MARGIN_SECS: i64 = 60;
+
+const CALLBACK_PAGE: &str = "<html><body style=\"font-family: sans-serif\">\
+    <h3>Signed in to JIRA</h3><p>You can close this window and return to the app.</p></body></html>";
+
+/// The OAuth app registered in the Atlassian developer console.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthApp {
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_port: u16,
+}
+
+impl OAuthApp {
+    fn redirect_uri(&self) -> String {
+        format!("http://localhost:{}{}", self.redirect_port, REDIRECT_PATH)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_at: DateTime<Utc>,
+    /// Scopes the access token was granted.
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: i64,
+    /// Space-separated.
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct AccessibleResource {
+    id: String,
+    url: String,
+}
+
+type RefreshHook = Box<dyn Fn(&OAuthTokens) + Send + Sync>;
+
+/// Token state shared by every clone of an OAuth `JiraClient`.
+pub struct OAuthSession {
+    app: OAuthApp,
+    tokens: Mutex<OAuthTokens>,
+    /// Serializes refreshes: Atlassian rotates refresh tokens, so two
+    /// concurrent refreshes would invalidate each other.
+    refresh_lock: tokio::sync::Mutex<()>,
+    on_refresh: Option<RefreshHook>,
+}
+
+impl OAuthSession {
+    pub fn new(app: OAuthApp, tokens: OAuthTokens) -> Self {
+        Self {
+            app,
+            tokens: Mutex::new(tokens),
+            refresh_lock: tokio::sync::Mutex::new(()),
+            on_refresh: None,
+        }
+    }
+
+    /// Called with the new tokens after every refresh, e.g. to persist the
+    /// rotated refresh token.
+    pub fn on_refresh(mut self, hook: impl Fn(&OAuthTokens) + Send + Sync + 'static) -> Self {
+        self.on_refresh = Some(Box::new(hook));
+        self
+    }
+
+    pub fn tokens(&self) -> OAuthTokens {
+        self.tokens.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn fresh_access_token(&self) -> Option<String> {
+        let tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        (tokens.expires_at - Duration::seconds(REFRESH_MARGIN_SECS) > Utc::now()).then(|| tokens.access_token.clone())
+    }
+
+    pub async fn access_token(&self, http: &reqwest::Client) -> Result<String, JiraError> {
+        if let Some(token) = self.fresh_access_token() {
+            return Ok(token);
+        }
+
+        let _guard = self.refresh_lock.lock().await;
+        // Another request may have refreshed while this one waited.
+        if let Some(token) = self.fresh_access_token() {
+            return Ok(token);
+        }
+        Ok(self.refresh_locked(http).await?.access_token)
+    }
+
+    /// Refreshes unconditionally.
+    pub async fn refresh(&self, http: &reqwest::Client) -> Result<OAuthTokens, JiraError> {
+        let _guard = self.refresh_lock.lock().await;
+        self.refresh_locked(http).await
+    }
+
+    async fn refresh_locked(&self, http: &reqwest::Client) -> Result<OAuthTokens, JiraError> {
+        let refresh_token = self.tokens().refresh_token;
+        let tokens = refresh_tokens(http, &self.app, &refresh_token).await?;
+
+        *self.tokens.lock().unwrap_or_else(|e| e.into_inner()) = tokens.clone();
+        if let Some(hook) = &self.on_refresh {
+            hook(&tokens);
+        }
+        Ok(tokens)
+    }
+}
+
+fn tokens_from(response: TokenResponse, previous_refresh_token: Option<&str>) -> Result<OAuthTokens, String> {
+    let refresh_token = response
+        .refresh_token
+        .or_else(|| previous_refresh_token.map(str::to_string))
+        .ok_or("No refresh token returned; is the offline_access scope enabled?")?;
+
+    Ok(OAuthTokens {
+        access_token: response.access_token,
+        refresh_token,
+        expires_at: Utc::now() + Duration::seconds(response.expires_in),
+        scopes: response
+            .scope
+            .as_deref()
+            .unwrap_or(SCOPES)
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
+    })
+}
+
+async fn request_tokens(
+    http: &reqwest::Client,
+    body: serde_json::Value,
+    previous_refresh_token: Option<&str>,
+) -> Result<OAuthTokens, JiraError> {
+    let response = http
+        .post(TOKEN_URL)
+        .header("Accept", "application/json")
+        .json(&body)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        // Revoked or expired refresh tokens and bad client credentials all
+        // mean signing in again.
+        return Err(JiraError::Unauthorized {
+            status: Some(response.status().as_u16()),
+            message: format!("OAuth token error: {}", response.status()),
+        });
+    }
+
+    let response: TokenResponse = response.json().await?;
+    Ok(tokens_from(response, previous_refresh_token)?)
+}
+
+async fn exchange_code(
+    http: &reqwest::Client,
+    app: &OAuthApp,
+    code: &str,
+) -> Result<OAuthTokens, JiraError> {
+    let body = serde_json::json!({
+        "grant_type": "authorization_code",
+        "client_id": app.client_id,
+        "client_secret": app.client_secret,
+        "code": code,
+        "redirect_uri": app.redirect_uri(),
+    });
+    request_tokens(http, body, None).await
+}
+
+pub async fn refresh_tokens(
+    http: &reqwest::Client,
+    app: &OAuthApp,
+    refresh_token: &str,
+) -> Result<OAuthTokens, JiraError> {
+    let body = serde_json::json!({
+        "grant_type": "refresh_token",
+        "client_id": app.client_id,
+        "client_secret": app.client_secret,
+        "refresh_token": refresh_token,
+    });
+    request_tokens(http, body, Some(refresh_token)).await
+}
+
+/// Finds the cloud id of `site_url`, or of the only site the user granted
+/// access to when no site is given.
+async fn resolve_cloud_id(
+    http: &reqwest::Client,
+    access_token: &str,
+    site_url: Option<&str>,
+) -> Result<(String, String), JiraError> {
+    let response = http
+        .get(RESOURCES_URL)
+        .header("Accept", "application/json")
+        .bearer_auth(access_token)
+        .send()
+        .await?;
+
+    let response = JiraClient::check(response).await?;
+
+    let resources: Vec<AccessibleResource> = response.json().await?;
+    let resource = match site_url.map(|url| url.trim_end_matches('/')) {
+        Some(site) => resources.into_iter().find(|r| r.url.trim_end_matches('/') == site),
+        None => resources.into_iter().next(),
+    };
+
+    resource
+        .map(|r| (r.id, r.url))
+        .ok_or_else(|| "The authorized account has no access to this JIRA site".into())
+}
+
+fn random_state() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Answers requests on the loopback listener until the authorization
+/// redirect arrives, then returns its `code`.
+async fn wait_for_redirect(listener: TcpListener, expected_state: &str) -> Result<String, String> {
+    loop {
+        let (mut stream, _) = listener.accept().await.map_err(|e| e.to_string())?;
+
+        let mut buffer = vec![0u8; 8192];
+        let read = stream.read(&mut buffer).await.map_err(|e| e.to_string())?;
+        let request = String::from_utf8_lossy(&buffer[..read]);
+        let target = request.split_whitespace().nth(1).unwrap_or_default().to_string();
+
+        let Ok(url) = reqwest::Url::parse(&format!("http://localhost{}", target)) else {
+            continue;
+        };
+        if url.path() != REDIRECT_PATH {
+            // Browsers also ask for a favicon.
+            let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n").await;
+            continue;
+        }
+
+        let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+        let reply = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            CALLBACK_PAGE.len(),
+            CALLBACK_PAGE
+        );
+        let _ = stream.write_all(reply.as_bytes()).await;
+
+        if let Some(error) = param("error") {
+            return Err(format!("Authorization denied: {}", error));
+        }
+        if param("state").as_deref() != Some(expected_state) {
+            return Err("Authorization response did not match the request".to_string());
+        }
+        return param("code").ok_or_else(|| "Authorization response had no code".to_string());
+    }
+}
+
+/// Starts a session on `tokens`. With `remember_as` set, every rotated
+/// refresh token is written back to that connection's keychain entry.
+pub fn new_session(app: OAuthApp, tokens: OAuthTokens, remember_as: Option<&str>) -> Arc<OAuthSession> {
+    let mut session = OAuthSession::new(app, tokens);
+    if let Some(connection_id) = remember_as.map(str::to_string) {
+        session = session.on_refresh(move |tokens| {
+            if let Err(e) = credentials::update_refresh_token(&connection_id, &tokens.refresh_token) {
+                tracing::warn!("{}", e);
+            }
+        });
+    }
+    Arc::new(session)
+}
+
+/// REST base URL for a site accessed with OAuth.
+pub fn api_base_url(cloud_id: &str) -> String {
+    format!("{}/{}", API_GATEWAY, cloud_id)
+}
+
+/// Runs the authorization code flow: opens the consent page in the browser,
+/// waits for the redirect on a loopback port and connects with the tokens.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn connect_with_oauth(
+    client_id: String,
+    client_secret: String,
+    site_url: Option<String>,
+    redirect_port: Option<u16>,
+    remember: Option<bool>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<bool, JiraError> {
+    let app = OAuthApp {
+        client_id,
+        client_secret,
+        redirect_port: redirect_port.unwrap_or(DEFAULT_REDIRECT_PORT),
+    };
+
+    let listener = TcpListener::bind(("127.0.0.1", app.redirect_port))
+        .await
+        .map_err(|e| format!("Cannot listen on port {}: {}", app.redirect_port, e))?;
+    let expected_state = random_state()?;
+
+    let mut authorize_url = reqwest::Url::parse(AUTHORIZE_URL).map_err(|e| e.to_string())?;
+    authorize_url
+        .query_pairs_mut()
+        .append_pair("audience", "api.atlassian.com")
+        .append_pair("client_id", &app.client_id)
+        .append_pair("scope", SCOPES)
+        .append_pair("redirect_uri", &app.redirect_uri())
+        .append_pair("state", &expected_state)
+        .append_pair("response_type", "code")
+        .append_pair("prompt", "consent");
+    app_handle
+        .opener()
+        .open_url(authorize_url.as_str(), None::<&str>)
+        .map_err(|e| format!("Failed to open browser: {}", e))?;
+
+    let code = tokio::time::timeout(
+        std::time::Duration::from_secs(REDIRECT_TIMEOUT_SECS),
+        wait_for_redirect(listener, &expected_state),
+    )
+    .await
+    .map_err(|_| "Timed out waiting for authorization".to_string())??;
+
+    let http = http::client()?;
+    let tokens = exchange_code(&http, &app, &code).await?;
+    let (cloud_id, resolved_site) = resolve_cloud_id(&http, &tokens.access_token, site_url.as_deref()).await?;
+
+    let remember = remember.unwrap_or(false);
+    let session = new_session(app.clone(), tokens, remember.then_some(DEFAULT_CONNECTION));
+    let mut client = JiraClient::with_oauth(api_base_url(&cloud_id), String::new(), session.clone(), offline::flag(&app_handle))?;
+    let myself = client.get_myself().await?;
+    client.email = myself.email_address.unwrap_or_default();
+
+    if remember {
+        let stored = credentials::StoredCredentials::oauth(
+            resolved_site,
+            client.email.clone(),
+            credentials::StoredOAuth {
+                app,
+                cloud_id,
+                refresh_token: session.tokens().refresh_token,
+            },
+        );
+        if let Err(e) = credentials::save(&stored) {
+            tracing::warn!("{}", e);
+        }
+    }
+
+    connections::set_default(&app_handle, &state, client).await?;
+    Ok(true)
+}
+
+/// Forces a token refresh for the OAuth connection and returns when the new
+/// access token expires.
+#[tauri::command]
+pub async fn refresh_session(state: State<'_, JiraState>) -> Result<DateTime<Utc>, JiraError> {
+    let client = connected_client(&state).await?;
+    match &client.auth {
+        JiraAuth::OAuth(session) => session
+            .refresh(client.http_client())
+            .await
+            .map(|tokens| tokens.expires_at),
+        JiraAuth::Basic { .. } => Err(JiraError::Validation(
+            "The current connection uses an API token, not OAuth".to_string(),
+        )),
+    }
+}
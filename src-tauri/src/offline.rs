@@ -0,0 +1,119 @@
+//! Working offline, e.g. on a plane: while JIRA is known to be out of
+//! reach, requests fail at once with `JiraError::Offline` instead of
+//! timing out one by one, and worklogs go to the offline queue. It is
+//! detected by the connection health check, or switched on by hand with
+//! `set_offline`; then nothing is sent until it is switched off again.
+//!
+//! Clients don't hold the app handle; they get an `OfflineFlag` from the
+//! managed `OfflineState` when they are made and check that.
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+use crate::error::JiraError;
+use crate::{health, offline_queue};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct OfflineStatus {
+    pub offline: bool,
+    /// Switched on with `set_offline`.
+    pub manual: bool,
+    /// The health check couldn't reach JIRA.
+    pub detected: bool,
+    pub since: Option<DateTime<Local>>,
+}
+
+/// `OfflineStatus::offline`, shared with the clients made while it's set.
+#[derive(Debug, Clone, Default)]
+pub struct OfflineFlag(Arc<AtomicBool>);
+
+impl OfflineFlag {
+    pub fn is_offline(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Refuses to go out to the network while working offline.
+    pub fn check(&self) -> Result<(), JiraError> {
+        if self.is_offline() {
+            return Err(JiraError::Offline);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct OfflineState {
+    status: Mutex<OfflineStatus>,
+    flag: OfflineFlag,
+}
+
+pub fn state(app_handle: &AppHandle<Wry>) -> OfflineStatus {
+    *app_handle.state::<OfflineState>().status.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The flag to hand to a new client.
+pub fn flag(app_handle: &AppHandle<Wry>) -> OfflineFlag {
+    app_handle.state::<OfflineState>().flag.clone()
+}
+
+/// Refuses to go out to the network while working offline.
+pub fn check(app_handle: &AppHandle<Wry>) -> Result<(), JiraError> {
+    flag(app_handle).check()
+}
+
+/// Applies `change` and, when that goes on or off line, tells the frontend
+/// and, back online, sends the queued worklogs.
+fn update(app_handle: &AppHandle<Wry>, change: impl FnOnce(&mut OfflineStatus)) -> OfflineStatus {
+    let (was_offline, current) = {
+        let state = app_handle.state::<OfflineState>();
+        let mut status = state.status.lock().unwrap_or_else(|e| e.into_inner());
+        let was_offline = status.offline;
+        change(&mut status);
+        status.offline = status.manual || status.detected;
+        if status.offline != was_offline {
+            status.since = status.offline.then(Local::now);
+        }
+        state.flag.0.store(status.offline, Ordering::SeqCst);
+        (was_offline, *status)
+    };
+    if current.offline == was_offline {
+        return current;
+    }
+
+    tracing::info!("Working {}", if current.offline { "offline" } else { "online" });
+    if let Err(e) = app_handle.emit("offline-mode-changed", current) {
+        tracing::warn!("Failed to emit offline-mode-changed event: {}", e);
+    }
+    if !current.offline {
+        offline_queue::sync_soon(app_handle);
+    }
+    current
+}
+
+/// Records what the health check found.
+pub fn set_detected(app_handle: &AppHandle<Wry>, detected: bool) {
+    update(app_handle, |status| status.detected = detected);
+}
+
+#[tauri::command]
+pub async fn get_offline_state(app_handle: AppHandle<Wry>) -> Result<OfflineStatus, String> {
+    Ok(state(&app_handle))
+}
+
+/// Works offline until called with `false`. Switching it off checks the
+/// connection again right away.
+#[tauri::command]
+pub async fn set_offline(offline: bool, app_handle: AppHandle<Wry>) -> Result<OfflineStatus, String> {
+    let status = update(&app_handle, |status| {
+        status.manual = offline;
+        if !offline {
+            // Until the check says otherwise.
+            status.detected = false;
+        }
+    });
+    health::recheck(&app_handle);
+    Ok(status)
+}
@@ -0,0 +1,375 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+use tokio::time::{interval, Duration};
+
+use crate::adf::{self, CommentInput};
+use crate::analytics;
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::jira_types::WorklogResponse;
+use crate::worklog_backend::{self, WorklogBackend};
+use crate::{client_for, interest, lint, notifications, settings, storage, store, tray, vault, JiraState};
+use crate::notifications::NotificationKind;
+
+pub const QUEUE_FILE: &str = "pending_worklogs.json";
+const SYNC_INTERVAL_SECS: u64 = 60;
+/// Entries rejected by JIRA this many times are no longer retried
+/// automatically; they stay in the queue until retried or discarded by hand.
+const MAX_AUTO_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingWorklog {
+    pub id: u64,
+    /// The connection it was logged against; `None` for the active one
+    /// (and entries queued before named connections).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection: Option<String>,
+    pub issue_key: String,
+    /// Plain text in queue files written before rich comments.
+    pub description: CommentInput,
+    pub started: String,
+    pub time_spent_seconds: u32,
+    pub queued_at: DateTime<Local>,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+/// Outcome of a worklog submission that may have been deferred.
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum WorklogSubmission {
+    Created { worklog: WorklogResponse },
+    Queued { pending: PendingWorklog },
+}
+
+/// How many worklogs wait in the queue, sent as `worklog-queue-changed`
+/// whenever that changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct QueueStatus {
+    pub pending: usize,
+    /// Of those, the ones no longer retried automatically.
+    pub failed: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorklogSynced {
+    pub pending: PendingWorklog,
+    pub worklog_id: String,
+}
+
+#[derive(Default)]
+pub struct OfflineQueue {
+    pending: Mutex<Vec<PendingWorklog>>,
+    syncing: AtomicBool,
+    /// Held for a whole round, so rounds never overlap.
+    round: tokio::sync::Mutex<()>,
+}
+
+impl OfflineQueue {
+    pub fn len(&self) -> usize {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn entries(&self) -> Vec<PendingWorklog> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn is_syncing(&self) -> bool {
+        self.syncing.load(Ordering::SeqCst)
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        status_of(&self.pending.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+fn status_of(pending: &[PendingWorklog]) -> QueueStatus {
+    QueueStatus {
+        pending: pending.len(),
+        failed: pending.iter().filter(|p| p.attempts >= MAX_AUTO_ATTEMPTS).count(),
+    }
+}
+
+pub fn load(app_handle: &AppHandle<Wry>) -> OfflineQueue {
+    let pending = match store::data_file(app_handle, QUEUE_FILE) {
+        Ok(path) => vault::load(app_handle, &path),
+        Err(_) => Vec::new(),
+    };
+
+    OfflineQueue {
+        pending: Mutex::new(pending),
+        syncing: AtomicBool::new(false),
+        round: tokio::sync::Mutex::new(()),
+    }
+}
+
+/// Reads the queue again once the vault is unlocked. Worklogs queued while
+/// it was locked couldn't be saved and are kept on top.
+pub fn reload(app_handle: &AppHandle<Wry>) {
+    let saved: Vec<PendingWorklog> = match store::data_file(app_handle, QUEUE_FILE) {
+        Ok(path) => vault::load(app_handle, &path),
+        Err(_) => Vec::new(),
+    };
+    let queue = app_handle.state::<OfflineQueue>();
+    let mut pending = queue.pending.lock().unwrap_or_else(|e| e.into_inner());
+    let unsaved = std::mem::replace(&mut *pending, saved);
+    pending.extend(unsaved);
+    persist(app_handle, &pending);
+}
+
+/// Saves the queue and tells the frontend and the tray about it.
+fn persist(app_handle: &AppHandle<Wry>, pending: &[PendingWorklog]) {
+    let result = store::data_file(app_handle, QUEUE_FILE).and_then(|path| vault::save(app_handle, &path, &pending));
+    if let Err(e) = result {
+        tracing::warn!("Failed to save pending worklogs: {}", e);
+    }
+
+    if let Err(e) = app_handle.emit("worklog-queue-changed", status_of(pending)) {
+        tracing::warn!("Failed to emit worklog-queue-changed event: {}", e);
+    }
+    // Later, since the menu reads the queue and callers still hold its lock.
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move { tray::refresh_menu(&app_handle) });
+}
+
+fn enqueue(
+    app_handle: &AppHandle<Wry>,
+    connection: Option<&str>,
+    issue_key: &str,
+    description: &CommentInput,
+    started: &str,
+    time_spent_seconds: u32,
+    error: String,
+) -> PendingWorklog {
+    let queue = app_handle.state::<OfflineQueue>();
+    let mut pending = queue.pending.lock().unwrap_or_else(|e| e.into_inner());
+
+    let entry = PendingWorklog {
+        id: pending.iter().map(|p| p.id).max().unwrap_or(0) + 1,
+        connection: connection.map(str::to_string),
+        issue_key: issue_key.to_string(),
+        description: description.clone(),
+        started: started.to_string(),
+        time_spent_seconds,
+        queued_at: Local::now(),
+        attempts: 0,
+        last_error: Some(error),
+    };
+
+    pending.push(entry.clone());
+    persist(app_handle, &pending);
+    entry
+}
+
+/// The worklog comment as it will be sent, once it passes the description
+/// rules.
+pub fn checked_comment(app_handle: &AppHandle<Wry>, description: &CommentInput) -> Result<adf::Document, JiraError> {
+    let comment = description.to_document();
+    comment.validate().map_err(JiraError::Validation)?;
+    lint::check_description(&settings::current(app_handle).description_rules, &comment.text())
+        .map_err(JiraError::Validation)?;
+    Ok(comment)
+}
+
+/// Creates a worklog through the configured backend, or queues it for a
+/// later retry when the backend is unreachable. Errors returned by the
+/// backend itself are passed through unchanged.
+pub async fn submit(
+    app_handle: &AppHandle<Wry>,
+    client: &JiraClient,
+    connection: Option<&str>,
+    issue_key: &str,
+    description: &CommentInput,
+    started: &str,
+    time_spent_seconds: u32,
+) -> Result<WorklogSubmission, JiraError> {
+    let comment = checked_comment(app_handle, description)?;
+    interest::touch(app_handle, issue_key);
+
+    let backend = worklog_backend::for_app(app_handle, client.clone())?;
+    match backend
+        .create_worklog(issue_key, &comment, started, time_spent_seconds)
+        .await
+    {
+        Ok(worklog) => {
+            storage::note_logged(app_handle, client, issue_key, started);
+            analytics::record(app_handle, client, issue_key, &worklog.id, &worklog.started, worklog.time_spent_seconds);
+            Ok(WorklogSubmission::Created { worklog })
+        }
+        Err(e) if e.is_connectivity() => {
+            let pending = enqueue(app_handle, connection, issue_key, description, started, time_spent_seconds, e.to_string());
+            if let Err(e) = app_handle.emit("worklog-queued", &pending) {
+                tracing::warn!("Failed to emit worklog-queued event: {}", e);
+            }
+            Ok(WorklogSubmission::Queued { pending })
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Submits queued worklogs in order. Stops at the first connectivity failure,
+/// since the rest would fail the same way. With `manual` set, entries that
+/// exhausted their automatic attempts are tried again as well, and a round
+/// already running is waited for rather than skipped, so a retry by hand
+/// always makes its own attempt.
+async fn sync(app_handle: &AppHandle<Wry>, manual: bool) {
+    let queue = app_handle.state::<OfflineQueue>();
+    let _round = if manual {
+        queue.round.lock().await
+    } else {
+        match queue.round.try_lock() {
+            Ok(round) => round,
+            Err(_) => return,
+        }
+    };
+    queue.syncing.store(true, Ordering::SeqCst);
+
+    let candidates: Vec<PendingWorklog> = {
+        let pending = queue.pending.lock().unwrap_or_else(|e| e.into_inner());
+        pending
+            .iter()
+            .filter(|p| manual || p.attempts < MAX_AUTO_ATTEMPTS)
+            .cloned()
+            .collect()
+    };
+
+    let jira_state = app_handle.state::<JiraState>();
+    for entry in candidates {
+        // Entries for a connection that is closed wait until it is back.
+        let Ok(client) = client_for(&jira_state, entry.connection.as_deref()).await else {
+            continue;
+        };
+        // Rejected credentials don't count against the entries; they wait
+        // for the next login.
+        if client.is_rejected() {
+            continue;
+        }
+        let Ok(backend) = worklog_backend::for_app(app_handle, client.clone()) else {
+            continue;
+        };
+        let result = backend
+            .create_worklog(
+                &entry.issue_key,
+                &entry.description.to_document(),
+                &entry.started,
+                entry.time_spent_seconds,
+            )
+            .await;
+
+        let mut pending = queue.pending.lock().unwrap_or_else(|e| e.into_inner());
+        match result {
+            Ok(worklog) => {
+                pending.retain(|p| p.id != entry.id);
+                persist(app_handle, &pending);
+                drop(pending);
+                storage::note_logged(app_handle, &client, &entry.issue_key, &entry.started);
+                analytics::record(app_handle, &client, &entry.issue_key, &worklog.id, &worklog.started, worklog.time_spent_seconds);
+
+                let synced = WorklogSynced {
+                    pending: entry,
+                    worklog_id: worklog.id,
+                };
+                if let Err(e) = app_handle.emit("worklog-synced", &synced) {
+                    tracing::warn!("Failed to emit worklog-synced event: {}", e);
+                }
+            }
+            Err(e) if e.is_connectivity() => break,
+            Err(_) if client.is_rejected() => {}
+            Err(e) => {
+                let failed = pending.iter_mut().find(|p| p.id == entry.id).map(|p| {
+                    p.attempts += 1;
+                    p.last_error = Some(e.to_string());
+                    p.clone()
+                });
+                persist(app_handle, &pending);
+                drop(pending);
+
+                if let Some(failed) = failed {
+                    if let Err(e) = app_handle.emit("worklog-sync-failed", &failed) {
+                        tracing::warn!("Failed to emit worklog-sync-failed event: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    queue.syncing.store(false, Ordering::SeqCst);
+}
+
+/// Sends the queued worklogs in the background now rather than at the next
+/// round, e.g. once back online.
+pub fn sync_soon(app_handle: &AppHandle<Wry>) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move { sync(&app_handle, false).await });
+}
+
+/// The tray's "Retry Now": retries every queued worklog and says how it
+/// went.
+pub fn retry_from_tray(app_handle: &AppHandle<Wry>) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        sync(&app_handle, true).await;
+        let status = app_handle.state::<OfflineQueue>().status();
+        let body = match status.pending {
+            0 => "All worklogs are synced".to_string(),
+            pending => format!("{} still pending sync", worklogs(pending)),
+        };
+        notifications::notify(&app_handle, NotificationKind::Feedback, "Pending Worklogs", &body);
+    });
+}
+
+/// "1 worklog", "3 worklogs".
+pub fn worklogs(count: usize) -> String {
+    match count {
+        1 => "1 worklog".to_string(),
+        count => format!("{} worklogs", count),
+    }
+}
+
+pub async fn start_sync_task(app_handle: AppHandle<Wry>) {
+    let mut interval = interval(Duration::from_secs(SYNC_INTERVAL_SECS));
+
+    loop {
+        interval.tick().await;
+        sync(&app_handle, false).await;
+    }
+}
+
+#[tauri::command]
+pub async fn list_pending_worklogs(queue: State<'_, OfflineQueue>) -> Result<Vec<PendingWorklog>, String> {
+    let pending = queue.pending.lock().map_err(|e| e.to_string())?;
+    Ok(pending.clone())
+}
+
+/// Retries every queued worklog, after a background round still running,
+/// and returns what is still pending.
+#[tauri::command]
+pub async fn retry_pending_worklogs(
+    app_handle: AppHandle<Wry>,
+    queue: State<'_, OfflineQueue>,
+) -> Result<Vec<PendingWorklog>, String> {
+    sync(&app_handle, true).await;
+    let pending = queue.pending.lock().map_err(|e| e.to_string())?;
+    Ok(pending.clone())
+}
+
+#[tauri::command]
+pub async fn discard_pending_worklog(
+    id: u64,
+    app_handle: AppHandle<Wry>,
+    queue: State<'_, OfflineQueue>,
+) -> Result<(), String> {
+    let mut pending = queue.pending.lock().map_err(|e| e.to_string())?;
+    let before = pending.len();
+    pending.retain(|p| p.id != id);
+
+    if pending.len() == before {
+        return Err(format!("Pending worklog {} not found", id));
+    }
+
+    persist(&app_handle, &pending);
+    Ok(())
+}
@@ -0,0 +1,218 @@
+//! Progress of long-running commands. The frontend passes an id of its own
+//! choosing as `operation` and polls `get_operation_status(id)` while the
+//! command runs; `cancel_operation(id)` stops it after the current step.
+//! Every operation also has a time budget after which it stops by itself.
+//! Stopped commands return what they finished so far.
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State, Wry};
+
+/// How long finished operations can still be looked up.
+const KEEP_FINISHED: Duration = Duration::from_secs(10 * 60);
+
+pub type OperationState = Mutex<Operations>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationPhase {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+    /// Ran out of its time budget.
+    TimedOut,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationStatus {
+    pub id: String,
+    pub label: String,
+    pub phase: OperationPhase,
+    pub done: u32,
+    /// `None` while unknown, e.g. before the first page came back.
+    pub total: Option<u32>,
+    pub started_at: DateTime<Local>,
+    pub elapsed_ms: u64,
+    pub budget_ms: u64,
+    pub error: Option<String>,
+}
+
+struct Operation {
+    label: String,
+    phase: OperationPhase,
+    done: u32,
+    total: Option<u32>,
+    started_at: DateTime<Local>,
+    started: Instant,
+    finished: Option<Instant>,
+    budget: Duration,
+    error: Option<String>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Operation {
+    fn status(&self, id: &str) -> OperationStatus {
+        let elapsed = self.finished.unwrap_or_else(Instant::now) - self.started;
+        OperationStatus {
+            id: id.to_string(),
+            label: self.label.clone(),
+            phase: self.phase,
+            done: self.done,
+            total: self.total,
+            started_at: self.started_at,
+            elapsed_ms: elapsed.as_millis() as u64,
+            budget_ms: self.budget.as_millis() as u64,
+            error: self.error.clone(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Operations {
+    operations: HashMap<String, Operation>,
+}
+
+impl Operations {
+    fn prune(&mut self) {
+        self.operations
+            .retain(|_, operation| operation.finished.is_none_or(|finished| finished.elapsed() < KEEP_FINISHED));
+    }
+}
+
+/// The running side of an operation. Dropping it unfinished marks the
+/// operation as failed.
+pub struct OperationHandle {
+    app_handle: AppHandle<Wry>,
+    id: String,
+    started: Instant,
+    budget: Duration,
+    cancel: Arc<AtomicBool>,
+    finished: bool,
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Registers an operation under `id`, or a generated id when the caller
+/// doesn't follow it.
+pub fn start(app_handle: &AppHandle<Wry>, id: Option<String>, label: &str, total: Option<u32>, budget: Duration) -> OperationHandle {
+    let id = id.unwrap_or_else(|| format!("op-{}", NEXT_ID.fetch_add(1, Ordering::Relaxed)));
+    let cancel = Arc::new(AtomicBool::new(false));
+    let started = Instant::now();
+
+    let state = app_handle.state::<OperationState>();
+    let mut operations = state.lock().unwrap_or_else(|e| e.into_inner());
+    operations.prune();
+    operations.operations.insert(
+        id.clone(),
+        Operation {
+            label: label.to_string(),
+            phase: OperationPhase::Running,
+            done: 0,
+            total,
+            started_at: Local::now(),
+            started,
+            finished: None,
+            budget,
+            error: None,
+            cancel: cancel.clone(),
+        },
+    );
+
+    OperationHandle {
+        app_handle: app_handle.clone(),
+        id,
+        started,
+        budget,
+        cancel,
+        finished: false,
+    }
+}
+
+impl OperationHandle {
+    fn update(&self, change: impl FnOnce(&mut Operation)) {
+        let state = self.app_handle.state::<OperationState>();
+        let mut operations = state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(operation) = operations.operations.get_mut(&self.id) {
+            change(operation);
+        }
+    }
+
+    /// Records `steps` more steps done.
+    pub fn advance(&self, steps: u32) {
+        self.update(|operation| operation.done += steps);
+    }
+
+    /// Whether to stop: cancelled, or over the time budget.
+    pub fn should_stop(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed) || self.started.elapsed() >= self.budget
+    }
+
+    fn finish(&mut self, phase: OperationPhase, error: Option<String>) {
+        self.finished = true;
+        let cancelled = self.cancel.load(Ordering::Relaxed);
+        let over_budget = self.started.elapsed() >= self.budget;
+        self.update(|operation| {
+            let cut_short = operation.total.is_none_or(|total| operation.done < total);
+            operation.phase = match phase {
+                OperationPhase::Completed if cut_short && cancelled => OperationPhase::Cancelled,
+                OperationPhase::Completed if cut_short && over_budget => OperationPhase::TimedOut,
+                phase => phase,
+            };
+            operation.error = error;
+            operation.finished = Some(Instant::now());
+        });
+    }
+
+    /// Marks the operation done, or stopped if `should_stop` cut it short,
+    /// and passes `result` through.
+    pub fn complete<T, E: ToString>(mut self, result: Result<T, E>) -> Result<T, E> {
+        match &result {
+            Ok(_) => self.finish(OperationPhase::Completed, None),
+            Err(e) => self.finish(OperationPhase::Failed, Some(e.to_string())),
+        }
+        result
+    }
+}
+
+impl Drop for OperationHandle {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.finish(OperationPhase::Failed, Some("The operation ended unexpectedly".to_string()));
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_operation_status(id: String, state: State<'_, OperationState>) -> Result<Option<OperationStatus>, String> {
+    let operations = state.lock().map_err(|e| e.to_string())?;
+    Ok(operations.operations.get(&id).map(|operation| operation.status(&id)))
+}
+
+/// Running operations and the ones finished in the last minutes.
+#[tauri::command]
+pub async fn list_operations(state: State<'_, OperationState>) -> Result<Vec<OperationStatus>, String> {
+    let mut operations = state.lock().map_err(|e| e.to_string())?;
+    operations.prune();
+    let mut statuses: Vec<OperationStatus> =
+        operations.operations.iter().map(|(id, operation)| operation.status(id)).collect();
+    statuses.sort_by_key(|status| status.started_at);
+    Ok(statuses)
+}
+
+/// Asks a running operation to stop; returns whether it was running.
+#[tauri::command]
+pub async fn cancel_operation(id: String, state: State<'_, OperationState>) -> Result<bool, String> {
+    let operations = state.lock().map_err(|e| e.to_string())?;
+    match operations.operations.get(&id) {
+        Some(operation) if operation.phase == OperationPhase::Running => {
+            operation.cancel.store(true, Ordering::Relaxed);
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
@@ -0,0 +1,375 @@
+//! Daily planning: in the morning I say how many hours each issue should
+//! get; during the day the plan is compared with what was actually logged,
+//! and the reminders mention how far off it is.
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::summary::{self, WorklogSummary};
+use crate::{client_for, clock, store, JiraState};
+
+const PLANS_FILE: &str = "day_plans.json";
+/// Plans older than this are dropped when a new one is saved.
+const KEEP_DAYS: i64 = 31;
+/// Leftovers under this aren't worth carrying over.
+const MIN_CARRY_SECONDS: i64 = 15 * 60;
+
+pub type PlanState = Mutex<BTreeMap<NaiveDate, DayPlan>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedEntry {
+    pub issue_key: String,
+    pub hours: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DayPlan {
+    pub date: NaiveDate,
+    pub entries: Vec<PlannedEntry>,
+    pub planned_at: DateTime<Local>,
+}
+
+impl DayPlan {
+    pub fn planned_seconds(&self) -> u32 {
+        self.entries.iter().map(|entry| (entry.hours * 3600.0).round() as u32).sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IssueVariance {
+    pub issue_key: String,
+    pub planned_seconds: u32,
+    pub actual_seconds: u32,
+    /// Actual minus planned; negative while behind.
+    pub variance_seconds: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlanVariance {
+    pub date: NaiveDate,
+    pub planned_seconds: u32,
+    pub actual_seconds: u32,
+    pub variance_seconds: i64,
+    /// Planned issues in plan order, then unplanned ones that got time.
+    pub issues: Vec<IssueVariance>,
+}
+
+fn issue_variance(issue_key: &str, planned_seconds: u32, actual_seconds: u32) -> IssueVariance {
+    IssueVariance {
+        issue_key: issue_key.to_string(),
+        planned_seconds,
+        actual_seconds,
+        variance_seconds: actual_seconds as i64 - planned_seconds as i64,
+    }
+}
+
+/// Compares `plan` with the worklogs of its day in `summary`.
+pub fn variance(plan: &DayPlan, summary: &WorklogSummary) -> PlanVariance {
+    let mut actual: BTreeMap<&str, u32> = summary
+        .days
+        .iter()
+        .filter(|day| day.date == plan.date)
+        .flat_map(|day| day.issues.iter())
+        .map(|issue| (issue.issue_key.as_str(), issue.seconds))
+        .collect();
+
+    let mut issues: Vec<IssueVariance> = plan
+        .entries
+        .iter()
+        .map(|entry| {
+            let planned = (entry.hours * 3600.0).round() as u32;
+            issue_variance(&entry.issue_key, planned, actual.remove(entry.issue_key.as_str()).unwrap_or(0))
+        })
+        .collect();
+    issues.extend(actual.into_iter().map(|(issue_key, seconds)| issue_variance(issue_key, 0, seconds)));
+
+    let planned_seconds = plan.planned_seconds();
+    let actual_seconds = issues.iter().map(|issue| issue.actual_seconds).sum();
+    PlanVariance {
+        date: plan.date,
+        planned_seconds,
+        actual_seconds,
+        variance_seconds: actual_seconds as i64 - planned_seconds as i64,
+        issues,
+    }
+}
+
+/// A proposed plan: the one already saved for the day, plus what is left of
+/// the previous plan.
+#[derive(Debug, Clone, Serialize)]
+pub struct SuggestedPlan {
+    pub date: NaiveDate,
+    /// The day of the previous plan, when anything was carried over.
+    pub carried_from: Option<NaiveDate>,
+    pub entries: Vec<SuggestedEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SuggestedEntry {
+    pub issue_key: String,
+    pub hours: f64,
+    /// Left over from the previous plan rather than already planned.
+    pub carried_over: bool,
+}
+
+/// The unfinished part of each planned issue, in quarter hours.
+pub fn carry_over(variance: &PlanVariance) -> Vec<PlannedEntry> {
+    variance
+        .issues
+        .iter()
+        .filter(|issue| issue.planned_seconds > 0 && -issue.variance_seconds >= MIN_CARRY_SECONDS)
+        .map(|issue| PlannedEntry {
+            issue_key: issue.issue_key.clone(),
+            hours: (-issue.variance_seconds as f64 / 900.0).round() / 4.0,
+        })
+        .collect()
+}
+
+fn hours(seconds: i64) -> String {
+    format!("{:+.1}h", seconds as f64 / 3600.0)
+}
+
+/// One line for the reminder: the overall variance and the issues furthest
+/// off the plan.
+pub fn reminder_line(variance: &PlanVariance) -> String {
+    let mut off: Vec<&IssueVariance> = variance
+        .issues
+        .iter()
+        .filter(|issue| issue.variance_seconds.abs() >= 15 * 60)
+        .collect();
+    off.sort_by_key(|issue| std::cmp::Reverse(issue.variance_seconds.abs()));
+
+    let mut line = format!(
+        "Plan: {:.1}h of {:.1}h ({})",
+        variance.actual_seconds as f64 / 3600.0,
+        variance.planned_seconds as f64 / 3600.0,
+        hours(variance.variance_seconds)
+    );
+    if !off.is_empty() {
+        let details: Vec<String> = off
+            .iter()
+            .take(3)
+            .map(|issue| format!("{} {}", issue.issue_key, hours(issue.variance_seconds)))
+            .collect();
+        line.push_str(&format!(": {}", details.join(", ")));
+    }
+    line
+}
+
+pub fn load(app_handle: &AppHandle<Wry>) -> BTreeMap<NaiveDate, DayPlan> {
+    match store::data_file(app_handle, PLANS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+fn persist(app_handle: &AppHandle<Wry>, plans: &BTreeMap<NaiveDate, DayPlan>) -> Result<(), String> {
+    let path = store::data_file(app_handle, PLANS_FILE)?;
+    store::save_json(&path, plans)
+}
+
+pub fn plan_for(app_handle: &AppHandle<Wry>, date: NaiveDate) -> Option<DayPlan> {
+    let state = app_handle.state::<PlanState>();
+    let plans = state.lock().unwrap_or_else(|e| e.into_inner());
+    plans.get(&date).cloned()
+}
+
+fn normalize(entries: Vec<PlannedEntry>) -> Result<Vec<PlannedEntry>, String> {
+    let mut normalized: Vec<PlannedEntry> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let issue_key = entry.issue_key.trim().to_uppercase();
+        if !JiraClient::is_valid_issue_key(&issue_key) {
+            return Err(format!("Invalid issue key: {}", issue_key));
+        }
+        if !(entry.hours > 0.0 && entry.hours <= 24.0) {
+            return Err(format!("Planned hours for {} must be between 0 and 24", issue_key));
+        }
+        if normalized.iter().any(|existing| existing.issue_key == issue_key) {
+            return Err(format!("{} is planned twice", issue_key));
+        }
+        normalized.push(PlannedEntry {
+            issue_key,
+            hours: entry.hours,
+        });
+    }
+
+    if normalized.iter().map(|entry| entry.hours).sum::<f64>() > 24.0 {
+        return Err("A day can't hold more than 24 planned hours".to_string());
+    }
+    Ok(normalized)
+}
+
+/// Saves the plan for `date`, replacing an earlier one; an empty `entries`
+/// removes it.
+#[tauri::command]
+pub async fn plan_day(
+    date: NaiveDate,
+    entries: Vec<PlannedEntry>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, PlanState>,
+) -> Result<Option<DayPlan>, String> {
+    let entries = normalize(entries)?;
+    let now = clock::now(&app_handle);
+
+    let mut plans = state.lock().map_err(|e| e.to_string())?;
+    let plan = if entries.is_empty() {
+        plans.remove(&date);
+        None
+    } else {
+        let plan = DayPlan {
+            date,
+            entries,
+            planned_at: now,
+        };
+        plans.insert(date, plan.clone());
+        Some(plan)
+    };
+    let oldest = now.date_naive() - chrono::Duration::days(KEEP_DAYS);
+    plans.retain(|day, _| *day >= oldest);
+    persist(&app_handle, &plans)?;
+    Ok(plan)
+}
+
+/// Today's plan so far plus the unfinished items of the latest earlier plan
+/// (yesterday's, or Friday's on a Monday), with the time still left on
+/// them. Nothing is saved; `plan_day` does that.
+#[tauri::command]
+pub async fn suggest_plan(
+    date: NaiveDate,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<SuggestedPlan, JiraError> {
+    let (current, previous) = {
+        let plans_state = app_handle.state::<PlanState>();
+        let plans = plans_state.lock().map_err(|e| e.to_string())?;
+        (plans.get(&date).cloned(), plans.range(..date).next_back().map(|(_, plan)| plan.clone()))
+    };
+
+    let mut entries: Vec<SuggestedEntry> = current
+        .iter()
+        .flat_map(|plan| plan.entries.iter())
+        .map(|entry| SuggestedEntry {
+            issue_key: entry.issue_key.clone(),
+            hours: entry.hours,
+            carried_over: false,
+        })
+        .collect();
+
+    let mut carried_from = None;
+    if let Some(previous) = previous {
+        let client = client_for(&state, connection.as_deref()).await?;
+        let summary = summary::fetch(&app_handle, client, previous.date, previous.date).await?;
+        for entry in carry_over(&variance(&previous, &summary)) {
+            if entries.iter().any(|existing| existing.issue_key == entry.issue_key) {
+                continue;
+            }
+            carried_from = Some(previous.date);
+            entries.push(SuggestedEntry {
+                issue_key: entry.issue_key,
+                hours: entry.hours,
+                carried_over: true,
+            });
+        }
+    }
+
+    Ok(SuggestedPlan {
+        date,
+        carried_from,
+        entries,
+    })
+}
+
+#[tauri::command]
+pub async fn get_day_plan(date: NaiveDate, app_handle: AppHandle<Wry>) -> Result<Option<DayPlan>, String> {
+    Ok(plan_for(&app_handle, date))
+}
+
+/// Logged time on `date` against its plan; `None` when the day has no plan.
+#[tauri::command]
+pub async fn get_plan_variance(
+    date: NaiveDate,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Option<PlanVariance>, JiraError> {
+    let Some(plan) = plan_for(&app_handle, date) else {
+        return Ok(None);
+    };
+    let client = client_for(&state, connection.as_deref()).await?;
+    let summary = summary::fetch(&app_handle, client, date, date).await?;
+    Ok(Some(variance(&plan, &summary)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::AppSettings;
+
+    #[test]
+    fn compares_planned_and_unplanned_issues() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 7).unwrap();
+        let plan = DayPlan {
+            date,
+            entries: vec![
+                PlannedEntry {
+                    issue_key: "APP-1".to_string(),
+                    hours: 4.0,
+                },
+                PlannedEntry {
+                    issue_key: "APP-2".to_string(),
+                    hours: 2.0,
+                },
+            ],
+            planned_at: Local::now(),
+        };
+        let mut summary = summary::summarize(&[], date, date, &AppSettings::default());
+        summary.days[0].issues = [("APP-1", 3 * 3600), ("APP-9", 1800)]
+            .into_iter()
+            .map(|(issue_key, seconds)| summary::IssueTotal {
+                issue_key: issue_key.to_string(),
+                issue_summary: String::new(),
+                client: None,
+                seconds,
+            })
+            .collect();
+
+        let variance = variance(&plan, &summary);
+        let by_key: Vec<(&str, i64)> = variance
+            .issues
+            .iter()
+            .map(|issue| (issue.issue_key.as_str(), issue.variance_seconds))
+            .collect();
+
+        assert_eq!(by_key, [("APP-1", -3600), ("APP-2", -7200), ("APP-9", 1800)]);
+        assert_eq!(variance.variance_seconds, 3 * 3600 + 1800 - 6 * 3600);
+        assert_eq!(reminder_line(&variance), "Plan: 3.5h of 6.0h (-2.5h): APP-2 -2.0h, APP-1 -1.0h, APP-9 +0.5h");
+    }
+
+    #[test]
+    fn carries_over_what_is_left_of_planned_issues() {
+        let variance = PlanVariance {
+            date: NaiveDate::from_ymd_opt(2024, 6, 7).unwrap(),
+            planned_seconds: 6 * 3600,
+            actual_seconds: 4 * 3600,
+            variance_seconds: -2 * 3600,
+            issues: vec![
+                issue_variance("APP-1", 4 * 3600, 2 * 3600 + 1000),
+                issue_variance("APP-2", 3600, 3600 - 600),
+                issue_variance("APP-3", 3600, 2 * 3600),
+                issue_variance("APP-9", 0, 1800),
+            ],
+        };
+        let carried: Vec<(String, f64)> = carry_over(&variance)
+            .into_iter()
+            .map(|entry| (entry.issue_key, entry.hours))
+            .collect();
+
+        assert_eq!(carried, [("APP-1".to_string(), 1.75)]);
+    }
+}
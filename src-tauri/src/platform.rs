@@ -0,0 +1,97 @@
+use tauri::{AppHandle, Wry};
+
+/// Hides the Dock icon on macOS so the app lives in the menu bar only.
+/// Other platforms have no Dock and ignore the setting.
+#[cfg(target_os = "macos")]
+pub fn apply_menubar_only(app_handle: &AppHandle<Wry>, enabled: bool) -> Result<(), String> {
+    let policy = if enabled {
+        tauri::ActivationPolicy::Accessory
+    } else {
+        tauri::ActivationPolicy::Regular
+    };
+    app_handle.set_activation_policy(policy).map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn apply_menubar_only(_app_handle: &AppHandle<Wry>, _enabled: bool) -> Result<(), String> {
+    Ok(())
+}
+
+/// Whether the focused window of another app is full screen or presenting,
+/// so notifications can wait. Not detected on macOS, whose Focus modes hold
+/// notifications back already, nor on Wayland.
+#[cfg(windows)]
+pub fn fullscreen_app_active() -> bool {
+    use windows_sys::Win32::UI::Shell::{
+        SHQueryUserNotificationState, QUNS_BUSY, QUNS_PRESENTATION_MODE, QUNS_RUNNING_D3D_FULL_SCREEN,
+    };
+
+    let mut state = 0;
+    // SAFETY: `state` is a valid out pointer for the call.
+    if unsafe { SHQueryUserNotificationState(&mut state) } != 0 {
+        return false;
+    }
+    matches!(state, QUNS_BUSY | QUNS_RUNNING_D3D_FULL_SCREEN | QUNS_PRESENTATION_MODE)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn fullscreen_app_active() -> bool {
+    use std::ffi::CStr;
+    use std::os::raw::{c_int, c_uchar, c_ulong};
+    use std::ptr;
+    use x11_dl::xlib::{self, Xlib};
+
+    let Ok(xlib) = Xlib::open() else {
+        return false;
+    };
+    // SAFETY: the display is opened and closed within this block, and every
+    // property buffer Xlib returns is freed with XFree.
+    unsafe {
+        let display = (xlib.XOpenDisplay)(ptr::null());
+        if display.is_null() {
+            return false;
+        }
+        let atom = |name: &CStr| (xlib.XInternAtom)(display, name.as_ptr(), xlib::False);
+        let property = |window: xlib::Window, name: &CStr, kind: xlib::Atom| -> Vec<c_ulong> {
+            let (mut actual_type, mut format, mut count, mut after) = (0, 0 as c_int, 0, 0);
+            let mut data: *mut c_uchar = ptr::null_mut();
+            let status = (xlib.XGetWindowProperty)(
+                display,
+                window,
+                atom(name),
+                0,
+                64,
+                xlib::False,
+                kind,
+                &mut actual_type,
+                &mut format,
+                &mut count,
+                &mut after,
+                &mut data,
+            );
+            if status != xlib::Success as c_int || data.is_null() {
+                return Vec::new();
+            }
+            // Format 32 properties come as longs.
+            let values = std::slice::from_raw_parts(data.cast::<c_ulong>(), count as usize).to_vec();
+            (xlib.XFree)(data.cast());
+            values
+        };
+
+        let root = (xlib.XDefaultRootWindow)(display);
+        let fullscreen = match property(root, c"_NET_ACTIVE_WINDOW", xlib::XA_WINDOW).first() {
+            Some(&active) if active != 0 => {
+                let wanted = atom(c"_NET_WM_STATE_FULLSCREEN");
+                property(active, c"_NET_WM_STATE", xlib::XA_ATOM).contains(&wanted)
+            }
+            _ => false,
+        };
+        (xlib.XCloseDisplay)(display);
+        fullscreen
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn fullscreen_app_active() -> bool {
+    false
+}
@@ -0,0 +1,315 @@
+//! Pomodoro mode of the timer: work intervals alternate with short breaks,
+//! and every few pomodoros a long one. The timer pauses for breaks and runs
+//! again when they're over, notifying at each change so it works with the
+//! window closed to the tray. Completed pomodoros are counted per day and
+//! issue.
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::settings::PomodoroSettings;
+use crate::timer::{self, ActiveTimer, TimerSnapshot};
+use crate::notifications::NotificationKind;
+use crate::{clock, focus, notifications, settings, store, JiraState};
+
+const POMODORO_STATS_FILE: &str = "pomodoro_stats.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PomodoroPhase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+/// A running pomodoro session, kept on the timer. Phase lengths are taken
+/// from the settings when it starts.
+#[derive(Debug, Clone)]
+pub struct Pomodoro {
+    pub phase: PomodoroPhase,
+    /// Tracked seconds of the timer when the work phase began, so pauses
+    /// don't count towards it.
+    work_from: u64,
+    /// When the current break began.
+    break_from: DateTime<Local>,
+    pub completed: u32,
+    work_seconds: u64,
+    short_break_seconds: u64,
+    long_break_seconds: u64,
+    long_break_every: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transition {
+    WorkDone,
+    BreakOver,
+    /// The timer was resumed by hand during the break.
+    BreakSkipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PomodoroSnapshot {
+    pub phase: PomodoroPhase,
+    pub remaining_seconds: u64,
+    /// Pomodoros finished in this session.
+    pub completed: u32,
+}
+
+impl Pomodoro {
+    pub fn new(config: &PomodoroSettings, now: DateTime<Local>) -> Self {
+        Self {
+            phase: PomodoroPhase::Work,
+            work_from: 0,
+            break_from: now,
+            completed: 0,
+            work_seconds: config.work_minutes as u64 * 60,
+            short_break_seconds: config.short_break_minutes as u64 * 60,
+            long_break_seconds: config.long_break_minutes as u64 * 60,
+            long_break_every: config.long_break_every.max(1),
+        }
+    }
+
+    fn break_seconds(&self) -> u64 {
+        match self.phase {
+            PomodoroPhase::LongBreak => self.long_break_seconds,
+            _ => self.short_break_seconds,
+        }
+    }
+
+    pub fn snapshot(&self, elapsed_seconds: u64, now: DateTime<Local>) -> PomodoroSnapshot {
+        let remaining_seconds = match self.phase {
+            PomodoroPhase::Work => self.work_seconds.saturating_sub(elapsed_seconds.saturating_sub(self.work_from)),
+            _ => self.break_seconds().saturating_sub((now - self.break_from).num_seconds().max(0) as u64),
+        };
+        PomodoroSnapshot {
+            phase: self.phase,
+            remaining_seconds,
+            completed: self.completed,
+        }
+    }
+
+    /// Moves to the next phase once the current one is over; `elapsed_seconds`
+    /// and `running` describe the timer.
+    fn advance(&mut self, elapsed_seconds: u64, running: bool, now: DateTime<Local>) -> Option<Transition> {
+        match self.phase {
+            PomodoroPhase::Work => {
+                if elapsed_seconds.saturating_sub(self.work_from) < self.work_seconds {
+                    return None;
+                }
+                self.completed += 1;
+                self.phase = if self.completed.is_multiple_of(self.long_break_every) {
+                    PomodoroPhase::LongBreak
+                } else {
+                    PomodoroPhase::ShortBreak
+                };
+                self.break_from = now;
+                Some(Transition::WorkDone)
+            }
+            _ => {
+                let transition = if running {
+                    Transition::BreakSkipped
+                } else if (now - self.break_from).num_seconds() >= self.break_seconds() as i64 {
+                    Transition::BreakOver
+                } else {
+                    return None;
+                };
+                self.phase = PomodoroPhase::Work;
+                self.work_from = elapsed_seconds;
+                Some(transition)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IssuePomodoros {
+    pub completed: u32,
+    pub focused_minutes: u32,
+}
+
+pub type PomodoroStats = BTreeMap<NaiveDate, BTreeMap<String, IssuePomodoros>>;
+
+pub type PomodoroState = Mutex<PomodoroStats>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PomodoroStatsReport {
+    /// Completed pomodoros per day and issue.
+    pub days: PomodoroStats,
+    pub issues: BTreeMap<String, IssuePomodoros>,
+    pub completed: u32,
+    pub focused_minutes: u32,
+}
+
+pub fn load(app_handle: &AppHandle<Wry>) -> PomodoroState {
+    let stats = match store::data_file(app_handle, POMODORO_STATS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(_) => BTreeMap::new(),
+    };
+    Mutex::new(stats)
+}
+
+fn record(app_handle: &AppHandle<Wry>, issue_key: &str, date: NaiveDate, focused_minutes: u32) {
+    let state = app_handle.state::<PomodoroState>();
+    let mut stats = state.lock().unwrap_or_else(|e| e.into_inner());
+    let issue = stats.entry(date).or_default().entry(issue_key.to_string()).or_default();
+    issue.completed += 1;
+    issue.focused_minutes += focused_minutes;
+
+    let result = store::data_file(app_handle, POMODORO_STATS_FILE).and_then(|path| store::save_json(&path, &*stats));
+    if let Err(e) = result {
+        tracing::warn!("Failed to save pomodoro stats: {}", e);
+    }
+}
+
+/// Ends the current phase of a pomodoro timer once it's over, pausing the
+/// timer for breaks and running it again afterwards.
+pub fn check(app_handle: &AppHandle<Wry>) {
+    let now = clock::now(app_handle);
+    let (transition, issue_key, pomodoro) = {
+        let state = app_handle.state::<JiraState>();
+        let mut active = state.timer().lock().unwrap_or_else(|e| e.into_inner());
+        let Some(timer) = active.as_mut() else {
+            return;
+        };
+        let elapsed = timer.elapsed_seconds(now);
+        let running = timer.resumed_at.is_some();
+        let Some(pomodoro) = timer.pomodoro.as_mut() else {
+            return;
+        };
+        let Some(transition) = pomodoro.advance(elapsed, running, now) else {
+            return;
+        };
+        let pomodoro = pomodoro.clone();
+
+        match transition {
+            Transition::WorkDone => timer.pause_at(now),
+            Transition::BreakOver => timer.resumed_at = Some(now),
+            Transition::BreakSkipped => {}
+        }
+        (transition, timer.issue_key.clone(), pomodoro)
+    };
+
+    match transition {
+        Transition::WorkDone => {
+            record(app_handle, &issue_key, now.date_naive(), (pomodoro.work_seconds / 60) as u32);
+            let kind = match pomodoro.phase {
+                PomodoroPhase::LongBreak => "long break",
+                _ => "break",
+            };
+            notifications::notify(
+                app_handle,
+                NotificationKind::Timer,
+                &format!("Pomodoro {} done: {}", pomodoro.completed, issue_key),
+                &format!("Take a {} minute {}.", pomodoro.break_seconds() / 60, kind),
+            );
+        }
+        Transition::BreakOver => notifications::notify(
+            app_handle,
+            NotificationKind::Timer,
+            "Break over",
+            &format!("Back to {}; the timer is running again.", issue_key),
+        ),
+        Transition::BreakSkipped => {}
+    }
+}
+
+/// Starts a pomodoro timer on `issue_key`, or the top focus item when none
+/// is given. Resuming the timer during a break skips the rest of it.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn start_pomodoro(issue_key: Option<String>, app_handle: AppHandle<Wry>) -> Result<TimerSnapshot, String> {
+    let issue_key = match issue_key {
+        Some(issue_key) => issue_key,
+        None => focus::top(&app_handle)
+            .map(|item| item.issue_key)
+            .ok_or_else(|| "No issue given and the focus list is empty".to_string())?,
+    };
+    let issue_key = timer::normalize_issue_key(&issue_key)?;
+    let config = settings::current(&app_handle).pomodoro;
+
+    let state = app_handle.state::<JiraState>();
+    let mut active = state.timer().lock().map_err(|e| e.to_string())?;
+    if let Some(timer) = active.as_ref() {
+        return Err(format!("A timer is already running for {}", timer.issue_key));
+    }
+
+    let now = clock::now(&app_handle);
+    let mut timer = ActiveTimer::new(issue_key, now);
+    timer.pomodoro = Some(Pomodoro::new(&config, now));
+    let snapshot = timer.snapshot(now);
+    *active = Some(timer);
+    Ok(snapshot)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_pomodoro_stats(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    state: State<'_, PomodoroState>,
+) -> Result<PomodoroStatsReport, String> {
+    if from_date > to_date {
+        return Err("fromDate must not be after toDate".to_string());
+    }
+
+    let stats = state.lock().map_err(|e| e.to_string())?;
+    let days: PomodoroStats = stats
+        .range(from_date..=to_date)
+        .map(|(date, issues)| (*date, issues.clone()))
+        .collect();
+
+    let mut issues: BTreeMap<String, IssuePomodoros> = BTreeMap::new();
+    for (issue_key, day) in days.values().flatten() {
+        let total = issues.entry(issue_key.clone()).or_default();
+        total.completed += day.completed;
+        total.focused_minutes += day.focused_minutes;
+    }
+
+    Ok(PomodoroStatsReport {
+        completed: issues.values().map(|issue| issue.completed).sum(),
+        focused_minutes: issues.values().map(|issue| issue.focused_minutes).sum(),
+        days,
+        issues,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 6, 7, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn alternates_work_and_breaks() {
+        let config = PomodoroSettings {
+            long_break_every: 2,
+            ..Default::default()
+        };
+        let mut pomodoro = Pomodoro::new(&config, at(9, 0));
+
+        assert_eq!(pomodoro.advance(24 * 60, true, at(9, 24)), None);
+        assert_eq!(pomodoro.advance(25 * 60, true, at(9, 25)), Some(Transition::WorkDone));
+        assert_eq!(pomodoro.phase, PomodoroPhase::ShortBreak);
+        assert_eq!(pomodoro.advance(25 * 60, false, at(9, 29)), None);
+        assert_eq!(pomodoro.advance(25 * 60, false, at(9, 30)), Some(Transition::BreakOver));
+
+        // Paused for ten minutes in the middle; only tracked time counts.
+        assert_eq!(pomodoro.advance(45 * 60, true, at(10, 0)), None);
+        assert_eq!(pomodoro.advance(50 * 60, true, at(10, 5)), Some(Transition::WorkDone));
+        assert_eq!(pomodoro.phase, PomodoroPhase::LongBreak);
+        assert_eq!(pomodoro.snapshot(50 * 60, at(10, 10)).remaining_seconds, 10 * 60);
+    }
+
+    #[test]
+    fn resuming_skips_the_break() {
+        let mut pomodoro = Pomodoro::new(&PomodoroSettings::default(), at(9, 0));
+        assert_eq!(pomodoro.advance(25 * 60, true, at(9, 25)), Some(Transition::WorkDone));
+        assert_eq!(pomodoro.advance(25 * 60, true, at(9, 26)), Some(Transition::BreakSkipped));
+        assert_eq!(pomodoro.phase, PomodoroPhase::Work);
+        assert_eq!(pomodoro.snapshot(30 * 60, at(9, 31)).remaining_seconds, 20 * 60);
+    }
+}
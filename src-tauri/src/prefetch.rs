@@ -0,0 +1,224 @@
+//! Warms the issue detail panel in the background: details of the issues I
+//! touched lately, today's focus items and the in-progress issues in the
+//! tray menu, then their assignees' avatars. One request goes out at a time, and only once JIRA has not been
+//! asked anything for a while, so refreshes always come first.
+
+use base64::Engine;
+use chrono::{Duration as ChronoDuration, Local};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State, Wry};
+use tokio::time::{interval, MissedTickBehavior};
+
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::jira_types::IssueDetail;
+use crate::storage::{self, Cached};
+use crate::{connected_client, fetch_issue_detail, focus, http, interest, tray, JiraState};
+
+const TICK_SECONDS: u64 = 10;
+/// How long JIRA has to be left alone before the next prefetch.
+const QUIET_SECONDS: u64 = 15;
+/// Issues kept warm.
+const TOP_ISSUES: usize = 8;
+/// Of those, at most this many are recently touched ones, so the focus
+/// list always gets a share.
+const HOT_ISSUES: usize = 5;
+/// How long a prefetched detail is served without asking JIRA.
+const DETAIL_TTL_MINUTES: i64 = 5;
+const AVATAR_SIZE: &str = "48x48";
+const MAX_AVATARS: usize = 100;
+
+pub type PrefetchState = Mutex<PrefetchCache>;
+
+#[derive(Default)]
+pub struct PrefetchCache {
+    /// The `storage::account` the entries belong to.
+    account: Option<String>,
+    /// `None` when the fetch failed, so it isn't retried before the TTL.
+    details: HashMap<String, Cached<Option<IssueDetail>>>,
+    /// Avatar URL to data URL; `None` when it couldn't be loaded.
+    avatars: HashMap<String, Option<String>>,
+}
+
+impl PrefetchCache {
+    /// The cache for `client`'s account, emptied if it held another one's.
+    fn of(&mut self, client: &JiraClient) -> &mut Self {
+        let account = storage::account(client);
+        if self.account.as_ref() != Some(&account) {
+            *self = Self {
+                account: Some(account),
+                ..Default::default()
+            };
+        }
+        self
+    }
+
+    fn fresh_detail(&self, issue_key: &str) -> Option<&Cached<Option<IssueDetail>>> {
+        self.details
+            .get(issue_key)
+            .filter(|cached| Local::now() - cached.fetched_at < ChronoDuration::minutes(DETAIL_TTL_MINUTES))
+    }
+}
+
+enum Job {
+    Detail(String),
+    Avatar(String),
+}
+
+fn avatar_url(detail: &IssueDetail) -> Option<&String> {
+    detail.assignee.as_ref()?.avatar_urls.as_ref()?.get(AVATAR_SIZE)
+}
+
+fn with_cache<T>(app_handle: &AppHandle<Wry>, client: &JiraClient, f: impl FnOnce(&mut PrefetchCache) -> T) -> T {
+    let state = app_handle.state::<PrefetchState>();
+    let mut cache = state.lock().unwrap_or_else(|e| e.into_inner());
+    f(cache.of(client))
+}
+
+/// A recently fetched detail of `issue_key`, if there is one.
+pub fn cached_detail(app_handle: &AppHandle<Wry>, client: &JiraClient, issue_key: &str) -> Option<IssueDetail> {
+    with_cache(app_handle, client, |cache| cache.fresh_detail(issue_key)?.value.clone())
+}
+
+pub fn store_detail(app_handle: &AppHandle<Wry>, client: &JiraClient, detail: IssueDetail) {
+    with_cache(app_handle, client, |cache| {
+        cache.details.insert(
+            detail.key.clone(),
+            Cached {
+                value: Some(detail),
+                fetched_at: Local::now(),
+            },
+        );
+    });
+}
+
+/// Drops the cached detail of `issue_key` after it was changed from here.
+pub fn forget_detail(app_handle: &AppHandle<Wry>, issue_key: &str) {
+    let state = app_handle.state::<PrefetchState>();
+    state.lock().unwrap_or_else(|e| e.into_inner()).details.remove(issue_key);
+}
+
+impl JiraClient {
+    /// An avatar as a data URL. Only avatars served by the site itself get
+    /// the credentials; Cloud serves them from a public CDN.
+    async fn get_avatar(&self, url: &str) -> Result<String, JiraError> {
+        let mut request = self.http_client().get(url).header("Accept", "image/*");
+        if url.starts_with(&format!("{}/", self.base_url)) {
+            request = request.header("Authorization", self.authorization().await?);
+        }
+        let response = self.send(request).await?;
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("image/png")
+            .to_string();
+        let bytes = response.bytes().await?;
+        Ok(format!(
+            "data:{};base64,{}",
+            content_type,
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        ))
+    }
+}
+
+/// Recently touched issues, today's focus items, then the in-progress
+/// issues, without duplicates.
+fn top_issues(app_handle: &AppHandle<Wry>) -> Vec<String> {
+    let mut keys = interest::hot(app_handle, HOT_ISSUES);
+    let rest = focus::today(app_handle)
+        .into_iter()
+        .map(|item| item.issue_key)
+        .chain(tray::menu_issue_keys(app_handle));
+    for key in rest {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys.truncate(TOP_ISSUES);
+    keys
+}
+
+fn next_job(cache: &PrefetchCache, keys: &[String]) -> Option<Job> {
+    if let Some(key) = keys.iter().find(|key| cache.fresh_detail(key).is_none()) {
+        return Some(Job::Detail(key.clone()));
+    }
+    if cache.avatars.len() >= MAX_AVATARS {
+        return None;
+    }
+    keys.iter()
+        .filter_map(|key| cache.details.get(key)?.value.as_ref())
+        .filter_map(avatar_url)
+        .find(|url| !cache.avatars.contains_key(*url))
+        .map(|url| Job::Avatar(url.clone()))
+}
+
+async fn run_job(app_handle: &AppHandle<Wry>, client: &JiraClient, job: Job) {
+    match job {
+        Job::Detail(issue_key) => {
+            let detail = fetch_issue_detail(client, &issue_key)
+                .await
+                .map_err(|e| tracing::warn!("Failed to prefetch {}: {}", issue_key, e))
+                .ok();
+            with_cache(app_handle, client, |cache| {
+                cache.details.insert(
+                    issue_key,
+                    Cached {
+                        value: detail,
+                        fetched_at: Local::now(),
+                    },
+                );
+            });
+        }
+        Job::Avatar(url) => {
+            let avatar = client.get_avatar(&url).await.map_err(|e| tracing::warn!("Failed to prefetch an avatar: {}", e)).ok();
+            with_cache(app_handle, client, |cache| {
+                cache.avatars.insert(url, avatar);
+            });
+        }
+    }
+}
+
+pub async fn start_prefetcher(app_handle: AppHandle<Wry>) {
+    let mut ticker = interval(Duration::from_secs(TICK_SECONDS));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        if !http::quiet_for(Duration::from_secs(QUIET_SECONDS)) {
+            continue;
+        }
+        let Ok(client) = connected_client(&app_handle.state::<JiraState>()).await else {
+            continue;
+        };
+
+        let keys = top_issues(&app_handle);
+        if let Some(job) = with_cache(&app_handle, &client, |cache| next_job(cache, &keys)) {
+            run_job(&app_handle, &client, job).await;
+        }
+    }
+}
+
+/// The avatar at `url` as a data URL, from the cache if it was prefetched.
+/// `None` when it can't be loaded.
+#[tauri::command]
+pub async fn get_avatar(
+    url: String,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Option<String>, JiraError> {
+    let client = connected_client(&state).await?;
+    if let Some(avatar) = with_cache(&app_handle, &client, |cache| cache.avatars.get(&url).cloned()) {
+        return Ok(avatar);
+    }
+
+    let avatar = client.get_avatar(&url).await.ok();
+    with_cache(&app_handle, &client, |cache| {
+        if cache.avatars.len() < MAX_AVATARS {
+            cache.avatars.insert(url, avatar.clone());
+        }
+    });
+    Ok(avatar)
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+use crate::offline_queue::OfflineQueue;
+use crate::{settings, JiraState};
+
+/// A second quit request within this window confirms the first one.
+const CONFIRM_WINDOW: Duration = Duration::from_secs(3);
+
+#[derive(Default)]
+pub struct QuitGuardState {
+    last_request: Mutex<Option<Instant>>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowShortcut {
+    Escape,
+    Quit,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuitConfirmation {
+    pub reasons: Vec<String>,
+}
+
+/// Work that would be lost or left dangling by quitting right now.
+pub fn quit_blockers(app_handle: &AppHandle<Wry>) -> Vec<String> {
+    let mut reasons = Vec::new();
+
+    let state = app_handle.state::<JiraState>();
+    let active = state.timer().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(timer) = active.as_ref() {
+        reasons.push(format!("A timer is running for {}", timer.issue_key));
+    }
+
+    let pending = app_handle.state::<OfflineQueue>().len();
+    if pending > 0 {
+        reasons.push(format!("{} worklog(s) are waiting to be submitted to JIRA", pending));
+    }
+
+    reasons
+}
+
+/// Decides whether a user-initiated quit may go ahead. When there is pending
+/// work the first request is refused and `quit-confirmation-required` is
+/// emitted; repeating the request shortly after confirms it.
+pub fn allow_quit(app_handle: &AppHandle<Wry>) -> bool {
+    if !settings::current(app_handle).keyboard.confirm_quit_when_busy {
+        return true;
+    }
+
+    let reasons = quit_blockers(app_handle);
+    if reasons.is_empty() {
+        return true;
+    }
+
+    let guard = app_handle.state::<QuitGuardState>();
+    let mut last_request = guard.last_request.lock().unwrap_or_else(|e| e.into_inner());
+    if last_request.is_some_and(|at| at.elapsed() < CONFIRM_WINDOW) {
+        return true;
+    }
+    *last_request = Some(Instant::now());
+
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    if let Err(e) = app_handle.emit("quit-confirmation-required", QuitConfirmation { reasons }) {
+        tracing::warn!("Failed to emit quit-confirmation-required event: {}", e);
+    }
+    false
+}
+
+pub fn request_quit(app_handle: &AppHandle<Wry>) {
+    if allow_quit(app_handle) {
+        app_handle.exit(0);
+    }
+}
+
+#[tauri::command]
+pub async fn handle_window_shortcut(shortcut: WindowShortcut, app_handle: AppHandle<Wry>) -> Result<(), String> {
+    match shortcut {
+        WindowShortcut::Escape => {
+            if settings::current(&app_handle).keyboard.esc_hides_to_tray {
+                if let Some(window) = app_handle.get_webview_window("main") {
+                    window.hide().map_err(|e| e.to_string())?;
+                }
+            }
+        }
+        WindowShortcut::Quit => request_quit(&app_handle),
+    }
+    Ok(())
+}
+
+/// Quits without the pending-work check, for a confirmation dialog's "Quit anyway".
+#[tauri::command]
+pub async fn force_quit(app_handle: AppHandle<Wry>) -> Result<(), String> {
+    app_handle.exit(0);
+    Ok(())
+}
@@ -0,0 +1,119 @@
+//! Takes ticket contents out of data that leaves the app, timesheet exports
+//! and webhook payloads, so time can be shared with a client or a payroll
+//! tool without the text of the tickets. Keys, dates and hours stay.
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::settings::RedactionSettings;
+
+/// Hex digits kept of a summary's hash; enough to group rows by issue.
+const HASH_LEN: usize = 12;
+
+/// Stands in for `summary`: the same summary always gives the same value,
+/// so rows still group, but the text can't be read back.
+pub fn hash_summary(summary: &str) -> String {
+    let digest = Sha256::digest(summary.as_bytes());
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("#{}", &hex[..HASH_LEN])
+}
+
+impl RedactionSettings {
+    pub fn summary(&self, summary: String) -> String {
+        if self.hash_summaries && !summary.is_empty() {
+            hash_summary(&summary)
+        } else {
+            summary
+        }
+    }
+
+    /// Worklog comments, which describe the work as much as descriptions do.
+    pub fn comment(&self, comment: String) -> String {
+        if self.strip_descriptions {
+            String::new()
+        } else {
+            comment
+        }
+    }
+
+    /// Redacts a JIRA payload in place, wherever its fields appear: issue
+    /// fields, comments, worklogs and changelog items.
+    pub fn payload(&self, value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                for (key, field) in object.iter_mut() {
+                    match key.as_str() {
+                        "description" | "body" | "comment" | "environment" if self.strip_descriptions => *field = Value::Null,
+                        "summary" if self.hash_summaries => {
+                            if let Value::String(summary) = field {
+                                *summary = hash_summary(summary);
+                            }
+                        }
+                        _ => self.payload(field),
+                    }
+                }
+                // Changelog items name the field they changed and carry its
+                // old and new text.
+                let changed = object.get("field").and_then(Value::as_str).map(str::to_ascii_lowercase);
+                for key in ["fromString", "toString"] {
+                    let Some(text) = object.get_mut(key) else {
+                        continue;
+                    };
+                    match (changed.as_deref(), &mut *text) {
+                        (Some("summary"), Value::String(summary)) if self.hash_summaries => *summary = hash_summary(summary),
+                        (Some("description" | "environment"), _) if self.strip_descriptions => *text = Value::Null,
+                        _ => {}
+                    }
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(|value| self.payload(value)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ALL: RedactionSettings = RedactionSettings {
+        strip_descriptions: true,
+        hash_summaries: true,
+    };
+
+    #[test]
+    fn hashes_summaries_stably() {
+        assert_eq!(hash_summary("Acme workshop"), hash_summary("Acme workshop"));
+        assert_ne!(hash_summary("Acme workshop"), hash_summary("Acme offsite"));
+        assert_eq!(hash_summary("Acme workshop").len(), HASH_LEN + 1);
+        assert_eq!(ALL.summary(String::new()), "");
+        assert_eq!(ALL.comment("Call with Acme".to_string()), "");
+    }
+
+    #[test]
+    fn redacts_webhook_payloads() {
+        let mut payload = json!({
+            "webhookEvent": "jira:issue_updated",
+            "issue": {
+                "key": "ACME-1",
+                "fields": { "summary": "Acme workshop", "description": "Agenda", "comment": { "comments": [] } }
+            },
+            "changelog": { "items": [
+                { "field": "summary", "fromString": "Acme offsite", "toString": "Acme workshop" },
+                { "field": "description", "fromString": "Draft", "toString": "Agenda" },
+                { "field": "status", "fromString": "To Do", "toString": "Done" }
+            ] }
+        });
+        ALL.payload(&mut payload);
+
+        let fields = &payload["issue"]["fields"];
+        assert_eq!(fields["summary"], hash_summary("Acme workshop"));
+        assert!(fields["description"].is_null() && fields["comment"].is_null());
+        let items = &payload["changelog"]["items"];
+        assert_eq!(items[0]["toString"], hash_summary("Acme workshop"));
+        assert!(items[1]["fromString"].is_null());
+        assert_eq!(items[2]["toString"], "Done");
+        assert_eq!(payload["issue"]["key"], "ACME-1");
+    }
+}
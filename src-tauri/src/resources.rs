@@ -0,0 +1,167 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+use tokio::time::{interval, Duration};
+
+const SAMPLE_INTERVAL_SECS: u64 = 300;
+/// One hour of samples.
+const HISTORY_LEN: usize = 12;
+const RSS_WARN_BYTES: u64 = 500 * 1024 * 1024;
+const HANDLES_WARN: usize = 1000;
+const TASKS_WARN: usize = 200;
+/// Growth over a full history window that counts as a leak even below the
+/// absolute limits.
+const GROWTH_WARN_RATIO: f64 = 1.5;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceSample {
+    pub taken_at: DateTime<Local>,
+    pub rss_bytes: Option<u64>,
+    /// File descriptors on Unix, kernel handles on Windows.
+    pub open_handles: Option<usize>,
+    /// Linux only; sockets include pooled HTTP connections.
+    pub open_sockets: Option<usize>,
+    pub threads: Option<usize>,
+    pub alive_tasks: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceUsage {
+    pub current: ResourceSample,
+    pub history: Vec<ResourceSample>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct ResourceMonitor {
+    history: VecDeque<ResourceSample>,
+    /// Warnings already reported, so each is raised once until it clears.
+    reported: Vec<String>,
+}
+
+pub type ResourceMonitorState = Mutex<ResourceMonitor>;
+
+#[cfg(target_os = "linux")]
+fn open_sockets() -> Option<usize> {
+    let entries = std::fs::read_dir("/proc/self/fd").ok()?;
+    Some(
+        entries
+            .filter_map(|entry| std::fs::read_link(entry.ok()?.path()).ok())
+            .filter(|target| target.to_string_lossy().starts_with("socket:"))
+            .count(),
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_sockets() -> Option<usize> {
+    None
+}
+
+fn sample() -> ResourceSample {
+    let mut system = System::new();
+    let process = sysinfo::get_current_pid().ok().and_then(|pid| {
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            false,
+            ProcessRefreshKind::nothing().with_memory().with_tasks(),
+        );
+        system.process(pid)
+    });
+
+    ResourceSample {
+        taken_at: Local::now(),
+        rss_bytes: process.map(|p| p.memory()),
+        open_handles: process.and_then(|p| p.open_files()),
+        open_sockets: open_sockets(),
+        threads: process.and_then(|p| p.tasks()).map(|tasks| tasks.len()),
+        alive_tasks: tokio::runtime::Handle::try_current()
+            .ok()
+            .map(|handle| handle.metrics().num_alive_tasks()),
+    }
+}
+
+fn warnings(history: &VecDeque<ResourceSample>) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let Some(latest) = history.back() else {
+        return warnings;
+    };
+
+    if let Some(rss) = latest.rss_bytes.filter(|rss| *rss > RSS_WARN_BYTES) {
+        warnings.push(format!("Memory usage is high: {} MiB", rss / 1024 / 1024));
+    }
+    if let Some(handles) = latest.open_handles.filter(|handles| *handles > HANDLES_WARN) {
+        warnings.push(format!("{} open handles", handles));
+    }
+    if let Some(tasks) = latest.alive_tasks.filter(|tasks| *tasks > TASKS_WARN) {
+        warnings.push(format!("{} background tasks alive", tasks));
+    }
+
+    // Steady growth over the whole window is the usual signature of a leak
+    // in a long-running background task.
+    if history.len() == HISTORY_LEN {
+        let rss: Vec<u64> = history.iter().filter_map(|s| s.rss_bytes).collect();
+        let growing = rss.len() == HISTORY_LEN && rss.windows(2).all(|pair| pair[1] >= pair[0]);
+        if growing && rss[HISTORY_LEN - 1] as f64 > rss[0] as f64 * GROWTH_WARN_RATIO {
+            warnings.push(format!(
+                "Memory grew from {} MiB to {} MiB over the last hour",
+                rss[0] / 1024 / 1024,
+                rss[HISTORY_LEN - 1] / 1024 / 1024
+            ));
+        }
+    }
+
+    warnings
+}
+
+fn record(app_handle: &AppHandle<Wry>) {
+    let state = app_handle.state::<ResourceMonitorState>();
+    let mut monitor = state.lock().unwrap_or_else(|e| e.into_inner());
+
+    if monitor.history.len() == HISTORY_LEN {
+        monitor.history.pop_front();
+    }
+    monitor.history.push_back(sample());
+
+    let current = warnings(&monitor.history);
+    let new: Vec<String> = current.iter().filter(|w| !monitor.reported.contains(w)).cloned().collect();
+    monitor.reported = current;
+    drop(monitor);
+
+    for warning in new {
+        tracing::warn!("Resource warning: {}", warning);
+        if let Err(e) = app_handle.emit("resource-warning", &warning) {
+            tracing::warn!("Failed to emit resource-warning event: {}", e);
+        }
+    }
+}
+
+pub async fn start_resource_monitor(app_handle: AppHandle<Wry>) {
+    let mut interval = interval(Duration::from_secs(SAMPLE_INTERVAL_SECS));
+
+    loop {
+        interval.tick().await;
+        record(&app_handle);
+    }
+}
+
+#[tauri::command]
+pub async fn get_resource_usage(state: State<'_, ResourceMonitorState>) -> Result<ResourceUsage, String> {
+    let monitor = state.lock().map_err(|e| e.to_string())?;
+    let mut history: VecDeque<ResourceSample> = monitor.history.clone();
+    drop(monitor);
+
+    let current = sample();
+    if history.len() == HISTORY_LEN {
+        history.pop_front();
+    }
+    history.push_back(current.clone());
+
+    Ok(ResourceUsage {
+        current,
+        warnings: warnings(&history),
+        history: history.into(),
+    })
+}
@@ -0,0 +1,104 @@
+//! Conditional requests for responses fetched over and over, such as the
+//! background sync's issue search: the `ETag` and `Last-Modified` of the
+//! last response per URL are sent back, and on `304 Not Modified` the body
+//! kept from then is used instead of downloading it again. Kept in memory
+//! only.
+
+use reqwest::header::{HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+const MAX_ENTRIES: usize = 64;
+/// Larger bodies aren't kept; they are rare and would crowd out the rest.
+const MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+    /// The decoded JSON.
+    pub body: Arc<Vec<u8>>,
+    /// When it was stored, counting stores; the oldest is evicted first.
+    stored: u64,
+}
+
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<String, CachedResponse>,
+    stores: u64,
+}
+
+static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+
+fn cache() -> &'static Mutex<Cache> {
+    CACHE.get_or_init(Mutex::default)
+}
+
+pub fn get(key: &str) -> Option<CachedResponse> {
+    cache().lock().unwrap_or_else(|e| e.into_inner()).entries.get(key).cloned()
+}
+
+/// Adds the conditional headers for `cached` to a request's headers.
+pub fn add_validators(headers: &mut HeaderMap, cached: &CachedResponse) {
+    if let Some(etag) = &cached.etag {
+        headers.insert(IF_NONE_MATCH, etag.clone());
+    }
+    if let Some(last_modified) = &cached.last_modified {
+        headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+    }
+}
+
+/// Keeps `body` for `key` when the response came with a validator to ask
+/// about it next time.
+pub fn store(key: &str, headers: &HeaderMap, body: &[u8]) {
+    let etag = headers.get(ETAG).cloned();
+    let last_modified = headers.get(LAST_MODIFIED).cloned();
+    let mut cache = cache().lock().unwrap_or_else(|e| e.into_inner());
+    if (etag.is_none() && last_modified.is_none()) || body.len() > MAX_BODY_BYTES {
+        cache.entries.remove(key);
+        return;
+    }
+
+    cache.stores += 1;
+    let entry = CachedResponse {
+        etag,
+        last_modified,
+        body: Arc::new(body.to_vec()),
+        stored: cache.stores,
+    };
+    cache.entries.insert(key.to_string(), entry);
+    if cache.entries.len() > MAX_ENTRIES {
+        let oldest = cache.entries.iter().min_by_key(|(_, entry)| entry.stored).map(|(key, _)| key.clone());
+        if let Some(oldest) = oldest {
+            cache.entries.remove(&oldest);
+        }
+    }
+}
+
+/// Forgets every response, e.g. when the signed-in account changes.
+pub fn clear() {
+    let mut cache = cache().lock().unwrap_or_else(|e| e.into_inner());
+    cache.entries.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_responses_with_validators() {
+        let mut headers = HeaderMap::new();
+        store("test plain", &headers, b"[]");
+        assert!(get("test plain").is_none());
+
+        headers.insert(ETAG, HeaderValue::from_static("\"v1\""));
+        store("test etag", &headers, b"[1]");
+        let cached = get("test etag").unwrap();
+        assert_eq!(cached.body.as_slice(), b"[1]");
+
+        let mut request = HeaderMap::new();
+        add_validators(&mut request, &cached);
+        assert_eq!(request[IF_NONE_MATCH], "\"v1\"");
+        assert!(!request.contains_key(IF_MODIFIED_SINCE));
+    }
+}
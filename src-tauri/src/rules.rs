@@ -0,0 +1,115 @@
+//! Shareable bundles of logging conventions, so a team can hand the same
+//! rules around: the description rules and the project to client mapping,
+//! written to a JSON file without any personal settings.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Wry};
+
+use crate::settings::{self, AppSettings, DescriptionRules};
+
+const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RulesBundle {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description_rules: Option<DescriptionRules>,
+    /// JIRA project key to client, as in `project_clients`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_clients: Option<BTreeMap<String, String>>,
+}
+
+impl RulesBundle {
+    fn of(settings: &AppSettings) -> Self {
+        Self {
+            version: BUNDLE_VERSION,
+            description_rules: Some(settings.description_rules.clone()),
+            project_clients: Some(settings.project_clients.clone()),
+        }
+    }
+}
+
+/// What `import_rules` changed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RulesImport {
+    pub description_rules: bool,
+    /// Project mappings added or changed.
+    pub project_clients: usize,
+}
+
+/// Takes over the description rules of `bundle` and adds its project
+/// mappings to the existing ones; a project in both gets the bundle's client.
+fn merge(bundle: RulesBundle, settings: &mut AppSettings) -> RulesImport {
+    let mut import = RulesImport::default();
+    if let Some(rules) = bundle.description_rules {
+        import.description_rules = rules != settings.description_rules;
+        settings.description_rules = rules;
+    }
+    for (project, client) in bundle.project_clients.unwrap_or_default() {
+        if settings.project_clients.get(&project) != Some(&client) {
+            settings.project_clients.insert(project, client);
+            import.project_clients += 1;
+        }
+    }
+    import
+}
+
+#[tauri::command]
+pub async fn export_rules(path: PathBuf, app_handle: AppHandle<Wry>) -> Result<RulesBundle, String> {
+    let bundle = RulesBundle::of(&settings::current(&app_handle));
+    let json = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(bundle)
+}
+
+#[tauri::command]
+pub async fn import_rules(path: PathBuf, app_handle: AppHandle<Wry>) -> Result<RulesImport, String> {
+    let json = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let bundle: RulesBundle =
+        serde_json::from_str(&json).map_err(|e| format!("{} is not a rules bundle: {}", path.display(), e))?;
+    if bundle.version > BUNDLE_VERSION {
+        return Err(format!(
+            "The rules bundle is version {}; this app reads up to version {}",
+            bundle.version, BUNDLE_VERSION
+        ));
+    }
+
+    let mut settings = settings::current(&app_handle);
+    let import = merge(bundle, &mut settings);
+    settings::update_settings(settings, app_handle).await?;
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_project_mappings_and_replaces_description_rules() {
+        let mut settings = AppSettings::default();
+        settings.project_clients.insert("APP".to_string(), "Acme".to_string());
+        settings.project_clients.insert("WEB".to_string(), "Globex".to_string());
+
+        let bundle: RulesBundle = serde_json::from_value(serde_json::json!({
+            "version": 1,
+            "description_rules": { "enabled": true, "min_length": 20 },
+            "project_clients": { "WEB": "Globex", "OPS": "Initech", "APP": "Umbrella" },
+        }))
+        .unwrap();
+        let import = merge(bundle, &mut settings);
+
+        assert_eq!(
+            import,
+            RulesImport {
+                description_rules: true,
+                project_clients: 2,
+            }
+        );
+        assert_eq!(settings.project_clients["APP"], "Umbrella");
+        assert_eq!(settings.project_clients.len(), 3);
+        assert!(settings.description_rules.enabled);
+        assert_eq!(settings.description_rules.min_length, 20);
+    }
+}
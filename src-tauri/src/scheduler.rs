@@ -0,0 +1,363 @@
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, Weekday};
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tokio::sync::Notify;
+use tokio::time::{sleep, Duration};
+
+use crate::settings::{self, AppSettings, ReminderAction, ReminderSchedule, ReminderStep};
+use crate::clock::{self, ClockState};
+use crate::cron::CronSchedule;
+use crate::summary::WorklogSummary;
+use crate::{calendar, connected_client, issue_reminders, notification_actions, plan, sla_warnings, summary, tray, JiraState};
+
+/// Longest single sleep. Sleeps run on a monotonic clock that may stand
+/// still while the machine is suspended, so the next fire time is
+/// recalculated at least this often to notice wall-clock jumps.
+const MAX_SLEEP_SECS: u64 = 300;
+/// Reminders missed by more than this (e.g. while the lid was closed) are
+/// dropped instead of firing late.
+const MAX_LATENESS_MINUTES: i64 = 30;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnoozedReminder {
+    pub until: DateTime<Local>,
+    pub action: ReminderAction,
+}
+
+#[derive(Default)]
+pub struct SchedulerState {
+    wake: Notify,
+    snoozed: Mutex<Option<SnoozedReminder>>,
+}
+
+/// Makes the scheduler recalculate its next fire time, e.g. after the
+/// schedule or the issue reminders changed.
+pub fn wake(app_handle: &AppHandle<Wry>) {
+    app_handle.state::<SchedulerState>().wake.notify_one();
+}
+
+fn is_reminder_day(schedule: &ReminderSchedule, date: NaiveDate) -> bool {
+    !schedule.weekdays_only || !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// First time `step` fires strictly after `after`.
+fn next_step_fire(schedule: &ReminderSchedule, step: &ReminderStep, after: DateTime<Local>) -> Option<DateTime<Local>> {
+    if let Some(expression) = &step.cron {
+        return expression.parse::<CronSchedule>().ok()?.next_after(after);
+    }
+    // A week ahead always contains a reminder day; the extra day covers
+    // times that already passed today.
+    (0..8)
+        .filter_map(|offset| after.date_naive().checked_add_days(Days::new(offset)))
+        .filter(|date| is_reminder_day(schedule, *date))
+        .filter_map(|date| date.and_time(step.time).and_local_timezone(Local).earliest())
+        .find(|fire_at| *fire_at > after)
+}
+
+fn due_steps(schedule: &ReminderSchedule, last_check: DateTime<Local>, now: DateTime<Local>) -> Vec<ReminderStep> {
+    if !schedule.enabled {
+        return Vec::new();
+    }
+
+    let since = last_check.max(now - chrono::Duration::minutes(MAX_LATENESS_MINUTES));
+    schedule
+        .steps
+        .iter()
+        .filter(|step| next_step_fire(schedule, step, since).is_some_and(|fire_at| fire_at <= now))
+        .cloned()
+        .collect()
+}
+
+fn next_fire(app_handle: &AppHandle<Wry>, schedule: &ReminderSchedule, after: DateTime<Local>) -> Option<DateTime<Local>> {
+    let steps = schedule
+        .steps
+        .iter()
+        .filter(|_| schedule.enabled)
+        .filter_map(|step| next_step_fire(schedule, step, after));
+    let snoozed = app_handle
+        .state::<SchedulerState>()
+        .snoozed
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|snoozed| snoozed.until);
+
+    steps
+        .chain(snoozed)
+        .chain(issue_reminders::next_fire_at(app_handle))
+        .chain(sla_warnings::next_fire_at(app_handle))
+        .min()
+}
+
+/// When each schedule step fires next, plus the snoozed reminder.
+#[derive(Debug, Clone, Serialize)]
+pub struct UpcomingReminders {
+    pub steps: Vec<(ReminderAction, DateTime<Local>)>,
+    pub snoozed: Option<SnoozedReminder>,
+    pub next_fire: Option<DateTime<Local>>,
+}
+
+pub fn upcoming(app_handle: &AppHandle<Wry>, schedule: &ReminderSchedule, now: DateTime<Local>) -> UpcomingReminders {
+    let steps = schedule
+        .steps
+        .iter()
+        .filter(|_| schedule.enabled)
+        .filter_map(|step| next_step_fire(schedule, step, now).map(|fire_at| (step.action, fire_at)))
+        .collect();
+    let snoozed = app_handle
+        .state::<SchedulerState>()
+        .snoozed
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+
+    UpcomingReminders {
+        steps,
+        snoozed,
+        next_fire: next_fire(app_handle, schedule, now),
+    }
+}
+
+/// Today's worklogs of the connected user, or `None` when they cannot be
+/// fetched (not connected, network failure).
+async fn summary_today(app_handle: &AppHandle<Wry>, today: NaiveDate) -> Option<WorklogSummary> {
+    let client = connected_client(&app_handle.state::<JiraState>()).await.ok()?;
+
+    match summary::fetch(app_handle, client, today, today).await {
+        Ok(summary) => Some(summary),
+        Err(e) => {
+            tracing::warn!("Failed to check today's logged time: {}", e);
+            None
+        }
+    }
+}
+
+fn reminder_body(settings: &AppSettings, logged_seconds: Option<u32>, extra_lines: &[String]) -> String {
+    let body = match logged_seconds {
+        Some(seconds) => format!(
+            "You've logged {:.1}h of {:.1}h today. Don't forget your JIRA worklog!",
+            seconds as f64 / 3600.0,
+            settings.daily_target_hours
+        ),
+        None => "Don't forget to log today's work in JIRA!".to_string(),
+    };
+    std::iter::once(body).chain(extra_lines.iter().cloned()).collect::<Vec<_>>().join("\n")
+}
+
+fn fire(app_handle: &AppHandle<Wry>, action: ReminderAction, body: &str) {
+    match action {
+        ReminderAction::Gentle => {
+            if let Some(main_window) = app_handle.get_webview_window("main") {
+                if let Err(e) = main_window.emit("daily-reminder", ()) {
+                    tracing::warn!("Failed to emit daily reminder event: {}", e);
+                }
+            }
+        }
+        ReminderAction::Sound => {
+            notification_actions::show_worklog_reminder(app_handle, "JIRA Work Log Reminder", body);
+        }
+        ReminderAction::TrayFlash => {
+            tray::start_flashing(app_handle);
+        }
+    }
+}
+
+/// Fires the schedule steps that came due since `last_check`, plus a
+/// snoozed reminder whose time has come.
+async fn fire_due(app_handle: &AppHandle<Wry>, last_check: DateTime<Local>, now: DateTime<Local>) {
+    let settings = settings::current(app_handle);
+    let snoozed = {
+        let state = app_handle.state::<SchedulerState>();
+        let mut snoozed = state.snoozed.lock().unwrap_or_else(|e| e.into_inner());
+        match snoozed.as_ref() {
+            Some(reminder) if reminder.until <= now => snoozed.take(),
+            _ => None,
+        }
+    };
+    let due = due_steps(&settings.reminder_schedule, last_check, now);
+
+    if due.is_empty() && snoozed.is_none() {
+        return;
+    }
+
+    let today = now.date_naive();
+    let summary = summary_today(app_handle, today).await;
+    let logged_seconds = summary.as_ref().map(|summary| summary.total_seconds);
+    let target_met = logged_seconds.is_some_and(|seconds| seconds >= settings.daily_target_seconds());
+    let plan_line = summary
+        .as_ref()
+        .zip(plan::plan_for(app_handle, today))
+        .map(|(summary, day_plan)| plan::reminder_line(&plan::variance(&day_plan, summary)));
+    let calendar_line = match calendar::suggestions_for(app_handle, today).await {
+        Ok(suggestions) => suggestions.as_ref().and_then(calendar::reminder_line),
+        Err(e) => {
+            tracing::warn!("Failed to read the calendar: {}", e);
+            None
+        }
+    };
+    let extra_lines: Vec<String> = plan_line.into_iter().chain(calendar_line).collect();
+    let body = reminder_body(&settings, logged_seconds, &extra_lines);
+
+    for step in due {
+        if step.skip_when_target_met && target_met {
+            continue;
+        }
+        fire(app_handle, step.action, &body);
+    }
+    if let Some(snoozed) = snoozed {
+        fire(app_handle, snoozed.action, &body);
+    }
+}
+
+pub async fn start_notification_scheduler(app_handle: AppHandle<Wry>) {
+    let clock = app_handle.state::<ClockState>().inner().clone();
+    let mut last_check = clock.now();
+
+    loop {
+        let schedule = settings::current(&app_handle).reminder_schedule;
+        let now = clock.now();
+        let wait = match next_fire(&app_handle, &schedule, last_check) {
+            // A fire time in the past is due right away.
+            Some(fire_at) => (fire_at - now).to_std().unwrap_or(Duration::ZERO),
+            None => Duration::from_secs(MAX_SLEEP_SECS),
+        }
+        .min(Duration::from_secs(MAX_SLEEP_SECS));
+
+        let state = app_handle.state::<SchedulerState>();
+        tokio::select! {
+            _ = sleep(wait) => {}
+            _ = state.wake.notified() => {}
+        }
+
+        let now = clock.now();
+        // The clock went back (`debug_set_time`, or a manual change): start
+        // counting from here so the earlier reminders can fire again.
+        if now < last_check {
+            last_check = now;
+        }
+        issue_reminders::fire_due(&app_handle, now);
+        sla_warnings::fire_due(&app_handle, now);
+        fire_due(&app_handle, last_check, now).await;
+        last_check = now;
+    }
+}
+
+/// Shows the reminder again after the configured snooze delay.
+pub fn snooze(app_handle: &AppHandle<Wry>, action: ReminderAction) -> SnoozedReminder {
+    let minutes = settings::current(app_handle).reminder_schedule.snooze_minutes;
+    let reminder = SnoozedReminder {
+        until: clock::now(app_handle) + chrono::Duration::minutes(minutes as i64),
+        action,
+    };
+
+    let state = app_handle.state::<SchedulerState>();
+    *state.snoozed.lock().unwrap_or_else(|e| e.into_inner()) = Some(reminder.clone());
+    wake(app_handle);
+    reminder
+}
+
+#[tauri::command]
+pub async fn get_reminder_schedule(app_handle: AppHandle<Wry>) -> Result<ReminderSchedule, String> {
+    Ok(settings::current(&app_handle).reminder_schedule)
+}
+
+#[tauri::command]
+pub async fn set_reminder_schedule(
+    schedule: ReminderSchedule,
+    app_handle: AppHandle<Wry>,
+) -> Result<ReminderSchedule, String> {
+    let mut settings = settings::current(&app_handle);
+    settings.reminder_schedule = schedule;
+    settings::save(&app_handle, &mut settings)?;
+
+    wake(&app_handle);
+    Ok(settings.reminder_schedule)
+}
+
+/// Snoozes the in-app (gentle) reminder.
+#[tauri::command]
+pub async fn snooze_reminder(app_handle: AppHandle<Wry>) -> Result<SnoozedReminder, String> {
+    Ok(snooze(&app_handle, ReminderAction::Gentle))
+}
+
+/// When the scheduler fires next, for display in the settings view.
+#[tauri::command]
+pub async fn get_next_reminder(app_handle: AppHandle<Wry>) -> Result<Option<DateTime<Local>>, String> {
+    let schedule = settings::current(&app_handle).reminder_schedule;
+    Ok(next_fire(&app_handle, &schedule, clock::now(&app_handle)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveTime, TimeZone};
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        // June 2024: the 7th is a Friday.
+        Local.with_ymd_and_hms(2024, 6, day, hour, minute, 0).unwrap()
+    }
+
+    fn schedule_at(hour: u32) -> ReminderSchedule {
+        ReminderSchedule {
+            steps: vec![ReminderStep {
+                time: NaiveTime::from_hms_opt(hour, 0, 0).unwrap(),
+                action: ReminderAction::Gentle,
+                skip_when_target_met: true,
+                cron: None,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fires_later_the_same_day() {
+        let schedule = schedule_at(17);
+        assert_eq!(next_step_fire(&schedule, &schedule.steps[0], at(7, 9, 30)), Some(at(7, 17, 0)));
+    }
+
+    #[test]
+    fn skips_the_weekend_after_friday() {
+        let schedule = schedule_at(17);
+        assert_eq!(next_step_fire(&schedule, &schedule.steps[0], at(7, 17, 0)), Some(at(10, 17, 0)));
+
+        let every_day = ReminderSchedule {
+            weekdays_only: false,
+            ..schedule
+        };
+        assert_eq!(next_step_fire(&every_day, &every_day.steps[0], at(7, 17, 0)), Some(at(8, 17, 0)));
+    }
+
+    #[test]
+    fn due_once_the_time_has_passed() {
+        let schedule = schedule_at(17);
+        assert!(due_steps(&schedule, at(7, 16, 59), at(7, 16, 59)).is_empty());
+        assert_eq!(due_steps(&schedule, at(7, 16, 59), at(7, 17, 0)).len(), 1);
+        // Already fired on the previous check.
+        assert!(due_steps(&schedule, at(7, 17, 0), at(7, 17, 1)).is_empty());
+    }
+
+    #[test]
+    fn drops_reminders_missed_for_too_long() {
+        let schedule = schedule_at(17);
+        assert_eq!(due_steps(&schedule, at(7, 16, 0), at(7, 17, 20)).len(), 1);
+        assert!(due_steps(&schedule, at(7, 16, 0), at(7, 17, 45)).is_empty());
+    }
+
+    #[test]
+    fn cron_steps_ignore_the_weekday_setting() {
+        let mut schedule = schedule_at(17);
+        schedule.steps[0].cron = Some("0 10 * * SAT".to_string());
+        assert_eq!(next_step_fire(&schedule, &schedule.steps[0], at(7, 17, 0)), Some(at(8, 10, 0)));
+        assert_eq!(due_steps(&schedule, at(8, 9, 59), at(8, 10, 0)).len(), 1);
+    }
+
+    #[test]
+    fn disabled_schedules_never_fire() {
+        let schedule = ReminderSchedule {
+            enabled: false,
+            ..schedule_at(17)
+        };
+        assert!(due_steps(&schedule, at(7, 16, 0), at(7, 17, 0)).is_empty());
+    }
+}
@@ -0,0 +1,245 @@
+//! Screenshots attached straight to an issue, for bugs found while testing.
+//! The screen is captured with the platform's own tool (`screencapture` on
+//! macOS, PowerShell on Windows, and on Linux the first of `grim`,
+//! `gnome-screenshot`, `spectacle`, `scrot` and ImageMagick's `import` that
+//! works), with the main window hidden meanwhile. The frontend can show the
+//! capture and pass back the part to keep.
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::adf;
+use crate::attachments::Attachment;
+use crate::error::JiraError;
+use crate::{client_for, timer, JiraState};
+
+const SCREENSHOT_DIR: &str = "screenshots";
+/// Lets the hidden window disappear from the screen before capturing.
+const HIDE_DELAY: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Screenshot {
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The part of a screenshot to keep, in its pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CapturedAttachment {
+    pub attachments: Vec<Attachment>,
+    /// The id of the comment pointing at it.
+    pub comment_id: String,
+}
+
+#[cfg(target_os = "macos")]
+async fn run_capture(path: &Path) -> Result<(), String> {
+    // -x: no shutter sound.
+    let status = tokio::process::Command::new("screencapture")
+        .arg("-x")
+        .arg(path)
+        .status()
+        .await
+        .map_err(|e| format!("Failed to run screencapture: {}", e))?;
+    if !status.success() {
+        return Err(format!("screencapture failed with {}; is Screen Recording allowed?", status));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+async fn run_capture(path: &Path) -> Result<(), String> {
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let script = format!(
+        "Add-Type -AssemblyName System.Windows.Forms,System.Drawing; \
+         $b = [System.Windows.Forms.SystemInformation]::VirtualScreen; \
+         $bmp = New-Object System.Drawing.Bitmap $b.Width, $b.Height; \
+         [System.Drawing.Graphics]::FromImage($bmp).CopyFromScreen($b.Left, $b.Top, 0, 0, $bmp.Size); \
+         $bmp.Save('{}', [System.Drawing.Imaging.ImageFormat]::Png)",
+        path.display().to_string().replace('\'', "''")
+    );
+    let status = tokio::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .status()
+        .await
+        .map_err(|e| format!("Failed to run PowerShell: {}", e))?;
+    if !status.success() {
+        return Err(format!("The screen capture failed with {}", status));
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+async fn run_capture(path: &Path) -> Result<(), String> {
+    let path = path.to_string_lossy().to_string();
+    let tools: [(&str, Vec<&str>); 5] = [
+        ("grim", vec![&path]),
+        ("gnome-screenshot", vec!["-f", &path]),
+        ("spectacle", vec!["-b", "-n", "-f", "-o", &path]),
+        ("scrot", vec!["-o", &path]),
+        ("import", vec!["-window", "root", &path]),
+    ];
+    for (tool, args) in tools {
+        match tokio::process::Command::new(tool).args(&args).status().await {
+            Ok(status) if status.success() && Path::new(&path).is_file() => return Ok(()),
+            Ok(status) => tracing::debug!("{} failed with {}", tool, status),
+            Err(e) => tracing::debug!("{} isn't available: {}", tool, e),
+        }
+    }
+    Err("No screenshot tool worked; install grim (Wayland) or scrot (X11)".to_string())
+}
+
+fn png_error(e: impl std::fmt::Display) -> String {
+    format!("Failed to read the screenshot: {}", e)
+}
+
+/// The size of the PNG at `path`.
+fn dimensions(path: &Path) -> Result<(u32, u32), String> {
+    let file = std::fs::File::open(path).map_err(png_error)?;
+    let reader = png::Decoder::new(std::io::BufReader::new(file)).read_info().map_err(png_error)?;
+    let info = reader.info();
+    Ok((info.width, info.height))
+}
+
+/// Cuts `rect` out of a row-major image `stride` bytes wide with
+/// `pixel_bytes` bytes per pixel.
+fn crop_rows(data: &[u8], stride: usize, pixel_bytes: usize, rect: CropRect) -> Vec<u8> {
+    let start = rect.x as usize * pixel_bytes;
+    let width = rect.width as usize * pixel_bytes;
+    data.chunks(stride)
+        .skip(rect.y as usize)
+        .take(rect.height as usize)
+        .flat_map(|row| &row[start..start + width])
+        .copied()
+        .collect()
+}
+
+/// Rewrites the PNG at `path` with only `rect` of it.
+fn crop(path: &Path, rect: CropRect) -> Result<(), String> {
+    let file = std::fs::File::open(path).map_err(png_error)?;
+    let mut decoder = png::Decoder::new(std::io::BufReader::new(file));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(png_error)?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut data).map_err(png_error)?;
+
+    let fits = |offset: u32, length: u32, size: u32| length > 0 && offset.checked_add(length).is_some_and(|end| end <= size);
+    if !fits(rect.x, rect.width, frame.width) || !fits(rect.y, rect.height, frame.height) {
+        return Err(format!("The crop doesn't fit the {}x{} screenshot", frame.width, frame.height));
+    }
+    let pixel_bytes = frame.color_type.samples();
+    let cropped = crop_rows(&data[..frame.buffer_size()], frame.line_size, pixel_bytes, rect);
+
+    let file = std::fs::File::create(path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), rect.width, rect.height);
+    encoder.set_color(frame.color_type);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    writer.write_image_data(&cropped).map_err(png_error)
+}
+
+/// Captures the whole screen into the app's cache, with the main window out
+/// of the way.
+async fn capture(app_handle: &AppHandle<Wry>) -> Result<Screenshot, String> {
+    let dir = app_handle.path().app_cache_dir().map_err(|e| e.to_string())?.join(SCREENSHOT_DIR);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(format!("screenshot-{}.png", Local::now().format("%Y%m%d-%H%M%S")));
+
+    let window = app_handle.get_webview_window("main").filter(|window| window.is_visible().unwrap_or(false));
+    if let Some(window) = &window {
+        window.hide().map_err(|e| e.to_string())?;
+        tokio::time::sleep(HIDE_DELAY).await;
+    }
+    let captured = run_capture(&path).await;
+    if let Some(window) = &window {
+        if let Err(e) = window.show() {
+            tracing::warn!("Failed to show the main window again: {}", e);
+        }
+    }
+    captured?;
+
+    let (width, height) = dimensions(&path)?;
+    Ok(Screenshot { path, width, height })
+}
+
+/// Takes a screenshot for the frontend to show, e.g. to pick a crop before
+/// `capture_and_attach`.
+#[tauri::command]
+pub async fn capture_screenshot(app_handle: AppHandle<Wry>) -> Result<Screenshot, String> {
+    capture(&app_handle).await
+}
+
+/// Attaches a screenshot to `issue_key`, the one `capture_screenshot` took
+/// at `path` or a fresh one, cut down to `crop` if given, and comments on
+/// the issue with its name. The file is removed once uploaded.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn capture_and_attach(
+    issue_key: String,
+    path: Option<PathBuf>,
+    crop: Option<CropRect>,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<CapturedAttachment, JiraError> {
+    let issue_key = timer::normalize_issue_key(&issue_key).map_err(JiraError::Validation)?;
+    let client = client_for(&state, connection.as_deref()).await?;
+
+    let path = match path {
+        Some(path) => {
+            let dir = app_handle.path().app_cache_dir().map_err(|e| e.to_string())?.join(SCREENSHOT_DIR);
+            if !path.starts_with(&dir) || !path.is_file() {
+                return Err(JiraError::Validation(format!("{} is not a screenshot taken by the app", path.display())));
+            }
+            path
+        }
+        None => capture(&app_handle).await?.path,
+    };
+    if let Some(rect) = crop {
+        let cropped = path.clone();
+        tauri::async_runtime::spawn_blocking(move || self::crop(&cropped, rect))
+            .await
+            .map_err(|e| e.to_string())??;
+    }
+
+    let attachments = client.upload_attachment(&issue_key, &path).await?;
+    if let Err(e) = std::fs::remove_file(&path) {
+        tracing::warn!("Failed to remove {}: {}", path.display(), e);
+    }
+    let name = attachments.first().map(|attachment| attachment.filename.as_str()).unwrap_or("a screenshot");
+    let text = format!("Screenshot attached: {} (taken {})", name, Local::now().format("%Y-%m-%d %H:%M"));
+    let comment = client.add_comment(&issue_key, &adf::Document::from_text(&text)).await?;
+    Ok(CapturedAttachment {
+        attachments,
+        comment_id: comment.id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crops_rows_and_columns() {
+        // A 4x3 image, one byte per pixel, numbered row by row.
+        let data: Vec<u8> = (0..12).collect();
+        let rect = CropRect {
+            x: 1,
+            y: 1,
+            width: 2,
+            height: 2,
+        };
+        assert_eq!(crop_rows(&data, 4, 1, rect), [5, 6, 9, 10]);
+    }
+}
@@ -0,0 +1,136 @@
+//! Locks the session after a stretch of inactivity, as a screen lock would:
+//! until the master password is entered again, commands that change
+//! anything are refused with a `locked` error. Reading goes on, and so does
+//! a running timer, so time keeps accumulating while I'm away.
+
+use std::sync::Mutex;
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+
+use crate::error::AppError;
+use crate::idle::IdleMonitor;
+use crate::{settings, vault};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Commands that only read, or look after windows, and so run while
+/// locked; besides these, everything starting with `READ_ONLY_PREFIXES`.
+const ALWAYS_ALLOWED: &[&str] = &[
+    "greet",
+    "session_status",
+    "lock_session",
+    "unlock_session",
+    "unlock_vault",
+    "show_main_window",
+    "hide_to_tray",
+    "hide_quick_log_window",
+    "cancel_request",
+    "take_pending_deep_link",
+];
+const READ_ONLY_PREFIXES: &[&str] = &["get_", "list_", "format_", "validate_", "search_"];
+
+/// Whether the session is locked.
+pub type SessionLockState = Mutex<bool>;
+
+fn allowed_while_locked(command: &str) -> bool {
+    ALWAYS_ALLOWED.contains(&command) || READ_ONLY_PREFIXES.iter().any(|prefix| command.starts_with(prefix))
+}
+
+pub fn is_locked(app_handle: &AppHandle<Wry>) -> bool {
+    *app_handle.state::<SessionLockState>().lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn set_locked(app_handle: &AppHandle<Wry>, locked: bool) {
+    let changed = {
+        let state = app_handle.state::<SessionLockState>();
+        let mut current = state.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::replace(&mut *current, locked) != locked
+    };
+    if !changed {
+        return;
+    }
+    let event = if locked { "session-locked" } else { "session-unlocked" };
+    if let Err(e) = app_handle.emit(event, ()) {
+        tracing::warn!("Failed to emit {} event: {}", event, e);
+    }
+}
+
+/// Wraps the handler from `generate_handler!` so changing commands are
+/// refused with `AppError::Locked` while the session is locked.
+pub fn guard<F>(handler: F) -> impl Fn(Invoke<Wry>) -> bool + Send + Sync + 'static
+where
+    F: Fn(Invoke<Wry>) -> bool + Send + Sync + 'static,
+{
+    move |invoke: Invoke<Wry>| {
+        let command = invoke.message.command();
+        if !allowed_while_locked(command) && is_locked(invoke.message.webview_ref().app_handle()) {
+            tracing::info!("Refused {} while the session is locked", command);
+            invoke.resolver.reject(AppError::Locked);
+            return true;
+        }
+        handler(invoke)
+    }
+}
+
+/// Locks once the computer has been idle for `session_lock.idle_minutes`.
+/// Without a master password, or an idle backend, there's nothing to do.
+fn check(app_handle: &AppHandle<Wry>) {
+    let lock = settings::current(app_handle).session_lock;
+    if !lock.enabled || is_locked(app_handle) || !vault::is_enabled(app_handle) {
+        return;
+    }
+    let Some(idle_seconds) = app_handle.state::<IdleMonitor>().idle_seconds() else {
+        return;
+    };
+    if idle_seconds >= lock.idle_minutes as u64 * 60 {
+        tracing::info!("Locking the session after {} idle seconds", idle_seconds);
+        set_locked(app_handle, true);
+    }
+}
+
+pub async fn start_lock_monitor(app_handle: AppHandle<Wry>) {
+    let mut ticker = interval(CHECK_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        check(&app_handle);
+    }
+}
+
+#[tauri::command]
+pub async fn session_status(state: State<'_, SessionLockState>) -> Result<bool, String> {
+    Ok(*state.lock().map_err(|e| e.to_string())?)
+}
+
+/// Locks right away, e.g. before stepping away.
+#[tauri::command]
+pub async fn lock_session(app_handle: AppHandle<Wry>) -> Result<(), String> {
+    if !vault::is_enabled(&app_handle) {
+        return Err("Set a master password to lock the session".to_string());
+    }
+    set_locked(&app_handle, true);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn unlock_session(password: String, app_handle: AppHandle<Wry>) -> Result<(), String> {
+    vault::verify(&app_handle, &password).await?;
+    set_locked(&app_handle, false);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lets_reads_through() {
+        assert!(allowed_while_locked("get_assigned_issues"));
+        assert!(allowed_while_locked("unlock_session"));
+        assert!(!allowed_while_locked("create_worklog"));
+        assert!(!allowed_while_locked("update_settings"));
+        assert!(!allowed_while_locked("disable_encryption"));
+    }
+}
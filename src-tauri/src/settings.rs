@@ -0,0 +1,1152 @@
+use chrono::{NaiveDate, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+
+use crate::jira_api::JiraClient;
+use crate::tempo::TempoAttributeValue;
+use crate::{cron, http, platform, scheduler, shortcuts, state, store, sync, team_config, tray, vault, webhook, JiraState};
+
+pub const SETTINGS_FILE: &str = "settings.json";
+
+pub type SettingsState = Mutex<AppSettings>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReminderAction {
+    /// Lets the frontend show its regular reminder notification.
+    Gentle,
+    /// Native notification with sound.
+    Sound,
+    /// Flashes the tray icon until the main window is opened.
+    TrayFlash,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReminderStep {
+    /// Ignored when `cron` is set.
+    #[serde(default)]
+    pub time: NaiveTime,
+    pub action: ReminderAction,
+    #[serde(default = "default_true")]
+    pub skip_when_target_met: bool,
+    /// Fires the step on a cron expression instead of daily at `time`;
+    /// `weekdays_only` doesn't apply to it.
+    #[serde(default)]
+    pub cron: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReminderSchedule {
+    pub enabled: bool,
+    /// Skip Saturdays and Sundays.
+    pub weekdays_only: bool,
+    /// Kept sorted by time.
+    pub steps: Vec<ReminderStep>,
+    /// Delay before a snoozed reminder fires again.
+    pub snooze_minutes: u32,
+}
+
+impl Default for ReminderSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            weekdays_only: true,
+            steps: vec![ReminderStep {
+                time: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+                action: ReminderAction::Gentle,
+                skip_when_target_met: true,
+                cron: None,
+            }],
+            snooze_minutes: 15,
+        }
+    }
+}
+
+impl ReminderSchedule {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.snooze_minutes == 0 {
+            return Err("Snooze duration must be greater than zero".to_string());
+        }
+        for expression in self.steps.iter().filter_map(|step| step.cron.as_deref()) {
+            cron::validate(expression)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BreakSettings {
+    pub enabled: bool,
+    /// Continuous tracked time before a break reminder is raised.
+    pub threshold_minutes: u32,
+    /// Shortest pause that counts as a break and resets the stretch.
+    pub min_break_minutes: u32,
+}
+
+impl Default for BreakSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_minutes: 90,
+            min_break_minutes: 5,
+        }
+    }
+}
+
+/// Targets beyond `daily_target_hours`, and when being under the daily one
+/// gets escalated; see `goals`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GoalSettings {
+    pub weekly_target_hours: f64,
+    /// Warns about missing time on workdays from then on.
+    pub warn_at: NaiveTime,
+    /// Warns again, more insistently, this often while still under target.
+    pub repeat_minutes: u32,
+    /// No more warnings after this.
+    pub stop_at: NaiveTime,
+    pub escalate: bool,
+}
+
+impl Default for GoalSettings {
+    fn default() -> Self {
+        Self {
+            weekly_target_hours: 40.0,
+            warn_at: NaiveTime::from_hms_opt(16, 30, 0).unwrap(),
+            repeat_minutes: 60,
+            stop_at: NaiveTime::from_hms_opt(20, 0, 0).unwrap(),
+            escalate: true,
+        }
+    }
+}
+
+impl GoalSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=168.0).contains(&self.weekly_target_hours) {
+            return Err("Weekly target must be between 0 and 168 hours".to_string());
+        }
+        if self.repeat_minutes == 0 {
+            return Err("The goal warning interval must be greater than zero".to_string());
+        }
+        if self.warn_at >= self.stop_at {
+            return Err("Goal warnings must stop after they start".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Overtime and undertime against the contract, banked from `start_date`
+/// on; see `hour_bank`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HourBankSettings {
+    /// Spread evenly over Monday to Friday.
+    pub contract_hours_per_week: f64,
+    /// The first day counted; the first of January when not set.
+    pub start_date: Option<NaiveDate>,
+    /// Carried over from before `start_date`; negative when owed.
+    pub opening_balance_minutes: i64,
+    /// A notification on Monday morning with last week and the balance.
+    pub weekly_digest: bool,
+}
+
+impl Default for HourBankSettings {
+    fn default() -> Self {
+        Self {
+            contract_hours_per_week: 40.0,
+            start_date: None,
+            opening_balance_minutes: 0,
+            weekly_digest: false,
+        }
+    }
+}
+
+/// Phase lengths of a pomodoro timer; see `pomodoro`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PomodoroSettings {
+    pub work_minutes: u32,
+    pub short_break_minutes: u32,
+    pub long_break_minutes: u32,
+    /// Pomodoros between long breaks.
+    pub long_break_every: u32,
+}
+
+impl Default for PomodoroSettings {
+    fn default() -> Self {
+        Self {
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            long_break_every: 4,
+        }
+    }
+}
+
+impl PomodoroSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.work_minutes == 0 || self.short_break_minutes == 0 || self.long_break_minutes == 0 {
+            return Err("Pomodoro intervals must be greater than zero".to_string());
+        }
+        if self.long_break_every == 0 {
+            return Err("Long breaks need to come every one or more pomodoros".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdleSettings {
+    /// Pause the running timer when the computer sits idle.
+    pub pause_timer: bool,
+    /// Minutes without keyboard or mouse input before the timer pauses.
+    pub threshold_minutes: u32,
+}
+
+impl Default for IdleSettings {
+    fn default() -> Self {
+        Self {
+            pause_timer: true,
+            threshold_minutes: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LunchWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl LunchWindow {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        self.start <= time && time < self.end
+    }
+}
+
+/// Meetings whose title matches `pattern` (a case-insensitive regex) are
+/// suggested for `issue_key`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalendarRule {
+    pub pattern: String,
+    pub issue_key: String,
+}
+
+/// ICS feeds worklogs are suggested from; see `calendar`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CalendarSettings {
+    /// Local paths or `https://` / `webcal://` URLs.
+    pub feeds: Vec<String>,
+    /// Tried in order when a title names no issue key.
+    pub rules: Vec<CalendarRule>,
+}
+
+/// Time logged every week on the same issue, e.g. half an hour of email
+/// each weekday morning.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecurringEntry {
+    pub issue_key: String,
+    #[serde(default)]
+    pub description: String,
+    /// `["Mon", "Tue", ...]`
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+    /// Without a start time the entry goes after the day's other entries.
+    #[serde(default)]
+    pub start: Option<NaiveTime>,
+    pub minutes: u32,
+    /// The days to draft the entry on, as a cron expression, instead of
+    /// `weekdays`; without `start`, it starts at the expression's first
+    /// time that day.
+    #[serde(default)]
+    pub cron: Option<String>,
+}
+
+/// What `backfill_period` drafts worklogs from besides the calendar and
+/// the day plans.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackfillSettings {
+    pub recurring: Vec<RecurringEntry>,
+    /// Where entries without a time of their own start.
+    pub day_start: NaiveTime,
+    /// `distribute_time` hands out time in steps of this many minutes.
+    pub granularity_minutes: u32,
+}
+
+impl Default for BackfillSettings {
+    fn default() -> Self {
+        Self {
+            recurring: Vec::new(),
+            day_start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            granularity_minutes: 15,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LunchSettings {
+    /// Pause a running timer when I step away during lunch, without asking
+    /// about the idle time, and resume it when I'm back.
+    pub pause_timer: bool,
+    /// Learn the lunch window from recurring midday idle spans.
+    pub learn: bool,
+    /// A fixed lunch window instead of the learned one.
+    pub window: Option<LunchWindow>,
+}
+
+impl Default for LunchSettings {
+    fn default() -> Self {
+        Self {
+            pause_timer: true,
+            learn: true,
+            window: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlaWarningSettings {
+    /// Warn before the SLA clocks of assigned service desk requests breach.
+    pub enabled: bool,
+    /// Minutes before a breach to warn at, e.g. `[60, 15]`.
+    pub thresholds_minutes: Vec<u32>,
+}
+
+impl Default for SlaWarningSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            thresholds_minutes: vec![30],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrayClickAction {
+    Nothing,
+    ShowWindow,
+    ToggleWindow,
+    QuickLog,
+    ToggleTimer,
+    CopyTodaySummary,
+    /// Left click only: open the tray menu (the platform default).
+    ShowMenu,
+}
+
+/// Right click always opens the tray menu, so only left and middle clicks
+/// are configurable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrayClickSettings {
+    pub left: TrayClickAction,
+    pub middle: TrayClickAction,
+}
+
+impl Default for TrayClickSettings {
+    fn default() -> Self {
+        Self {
+            left: TrayClickAction::ShowWindow,
+            middle: TrayClickAction::Nothing,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyboardSettings {
+    pub esc_hides_to_tray: bool,
+    /// Ask before quitting while a timer runs or work is unsynced.
+    pub confirm_quit_when_busy: bool,
+    /// System-wide hotkey for the quick log window; `None` turns it off.
+    pub quick_log_shortcut: Option<String>,
+}
+
+impl Default for KeyboardSettings {
+    fn default() -> Self {
+        Self {
+            esc_hides_to_tray: true,
+            confirm_quit_when_busy: true,
+            quick_log_shortcut: Some("CommandOrControl+Shift+L".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IssueSyncSettings {
+    /// Minutes between polls of the assigned issues; 0 turns syncing off.
+    pub interval_minutes: u32,
+    /// Native notifications for new, changed and removed issues.
+    pub notify: bool,
+}
+
+impl Default for IssueSyncSettings {
+    fn default() -> Self {
+        Self {
+            interval_minutes: 5,
+            notify: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HttpVersionPreference {
+    /// Negotiated with the server.
+    Auto,
+    Http1,
+    /// HTTP/2 without negotiation; only for servers known to support it.
+    Http2,
+}
+
+/// Which proxy requests go through.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyMode {
+    /// `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` from the environment.
+    #[default]
+    System,
+    /// Straight to JIRA, whatever the environment says.
+    Off,
+    /// `proxy_url`.
+    Manual,
+}
+
+/// Advanced: timeouts, proxy, connection pool and keep-alive tuning for
+/// requests to JIRA.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    /// How long to wait for a connection; 0 waits as long as the system does.
+    pub connect_timeout_secs: u32,
+    /// How long a whole request may take; 0 lets it take forever. Attachment
+    /// transfers get longer.
+    pub request_timeout_secs: u32,
+    pub proxy_mode: ProxyMode,
+    /// E.g. `http://proxy.corp:3128`, with `user:password@` if it needs them.
+    pub proxy_url: Option<String>,
+    /// Sent instead of `mini-jira-app/<version>`, for proxies that only let
+    /// known clients through.
+    pub user_agent: Option<String>,
+    /// Idle pooled connections are closed after this long; 0 keeps them open.
+    pub pool_idle_timeout_secs: u32,
+    pub pool_max_idle_per_host: u32,
+    /// TCP keep-alive probe interval; 0 disables it.
+    pub tcp_keepalive_secs: u32,
+    pub http_version: HttpVersionPreference,
+    pub retry: RetrySettings,
+}
+
+impl HttpSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.proxy_mode == ProxyMode::Manual {
+            let url = self.proxy_url.as_deref().unwrap_or_default();
+            reqwest::Proxy::all(url).map_err(|e| format!("Invalid proxy URL '{}': {}", url, e))?;
+        }
+        if let Some(user_agent) = &self.user_agent {
+            reqwest::header::HeaderValue::from_str(user_agent).map_err(|_| "The user agent must be plain text".to_string())?;
+        }
+        if self.retry.max_attempts == 0 {
+            return Err("Requests need at least one attempt".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Retries of requests JIRA turned away (429, 503) or that never reached
+/// it. Waits double from `base_delay_ms` with random jitter, or follow
+/// `Retry-After`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetrySettings {
+    /// Tries per request, the first one included; 1 turns retrying off.
+    pub max_attempts: u32,
+    pub base_delay_ms: u32,
+    /// Longest single wait. A `Retry-After` beyond it fails right away with
+    /// a rate limit error instead.
+    pub max_delay_secs: u32,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay_ms: 500,
+            max_delay_secs: 30,
+        }
+    }
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            request_timeout_secs: 60,
+            proxy_mode: ProxyMode::System,
+            proxy_url: None,
+            user_agent: None,
+            pool_idle_timeout_secs: 90,
+            pool_max_idle_per_host: 4,
+            tcp_keepalive_secs: 60,
+            http_version: HttpVersionPreference::Auto,
+            retry: RetrySettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    /// Follows the operating system.
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeUnit {
+    #[serde(rename = "m")]
+    Minutes,
+    #[default]
+    #[serde(rename = "h")]
+    Hours,
+    #[serde(rename = "d")]
+    Days,
+}
+
+/// How time entered as text is read.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeUnitSettings {
+    /// Unit of a bare number such as `1.5`, and preselected in the log form.
+    pub default_unit: TimeUnit,
+    /// Length of a `d`, as in JIRA's time tracking settings.
+    pub hours_per_day: f64,
+}
+
+impl Default for TimeUnitSettings {
+    fn default() -> Self {
+        Self {
+            default_unit: TimeUnit::Hours,
+            hours_per_day: 8.0,
+        }
+    }
+}
+
+impl TimeUnitSettings {
+    pub fn seconds_per(&self, unit: TimeUnit) -> f64 {
+        match unit {
+            TimeUnit::Minutes => 60.0,
+            TimeUnit::Hours => 3600.0,
+            TimeUnit::Days => self.hours_per_day * 3600.0,
+        }
+    }
+}
+
+/// Where the team configuration is published; see `team_config`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TeamConfigSettings {
+    pub url: Option<String>,
+    /// Base64 Ed25519 public key the configuration must be signed with.
+    pub public_key: Option<String>,
+}
+
+/// Where work without a ticket goes: the reminders offer "New task", which
+/// files an issue like this and logs the time on it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CatchAllSettings {
+    pub project_key: Option<String>,
+    /// The id from `get_issue_types`.
+    pub issue_type: Option<String>,
+    /// `{description}` is the first line of the worklog description,
+    /// `{date}` today.
+    pub summary: String,
+}
+
+impl Default for CatchAllSettings {
+    fn default() -> Self {
+        Self {
+            project_key: None,
+            issue_type: None,
+            summary: "{description}".to_string(),
+        }
+    }
+}
+
+impl CatchAllSettings {
+    /// The project and issue type, once both are set.
+    pub fn target(&self) -> Option<(&str, &str)> {
+        let project_key = self.project_key.as_deref().map(str::trim).filter(|key| !key.is_empty())?;
+        let issue_type = self.issue_type.as_deref().map(str::trim).filter(|kind| !kind.is_empty())?;
+        Some((project_key, issue_type))
+    }
+}
+
+/// Looking for new releases on GitHub; see `updater`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateSettings {
+    /// Checks in the background; `check_for_updates` works either way.
+    pub enabled: bool,
+    pub check_interval_hours: u32,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            check_interval_hours: 24,
+        }
+    }
+}
+
+/// The listener for JIRA webhooks; see `webhook`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookSettings {
+    pub enabled: bool,
+    /// `0.0.0.0` to accept webhooks from JIRA on another machine.
+    pub address: String,
+    pub port: u16,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1".to_string(),
+            port: 8790,
+        }
+    }
+}
+
+/// What's taken out of exports and webhook payloads; see `redaction`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionSettings {
+    /// Drops issue descriptions and comments, and worklog comments.
+    pub strip_descriptions: bool,
+    /// Replaces issue summaries with a short hash of them.
+    pub hash_summaries: bool,
+}
+
+/// Locking changes away after inactivity; see `session_lock`. Needs a
+/// master password (`vault`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionLockSettings {
+    pub enabled: bool,
+    pub idle_minutes: u32,
+}
+
+impl Default for SessionLockSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_minutes: 15,
+        }
+    }
+}
+
+/// When notifications are held back; see `notifications`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub quiet_hours: Option<QuietHours>,
+    /// Nothing while another app is full screen or presenting (Windows, and
+    /// X11 on Linux).
+    pub mute_when_fullscreen: bool,
+    /// Worklog, goal and issue reminders.
+    pub reminders: bool,
+    /// Issue changes, SLA warnings and connection problems.
+    pub sync: bool,
+    /// Timebox, pomodoro and break notices.
+    pub timer: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            quiet_hours: None,
+            mute_when_fullscreen: true,
+            reminders: true,
+            sync: true,
+            timer: true,
+        }
+    }
+}
+
+/// From `start` until `end`, past midnight when `end` comes first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// What moving an issue to a done status through the app does when I've
+/// logged no time on it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DoneGuard {
+    #[default]
+    Off,
+    /// Asks for confirmation first.
+    Warn,
+    /// Refuses the transition.
+    Block,
+}
+
+/// Which way the worklog rules round durations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    #[default]
+    Nearest,
+    Up,
+    Down,
+}
+
+/// Rounding and limits every worklog goes through before it is submitted,
+/// e.g. for billing in quarter hours.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorklogRules {
+    pub enabled: bool,
+    /// Durations become whole steps of this many minutes, at least one;
+    /// 0 leaves them as they are.
+    pub round_to_minutes: u32,
+    pub rounding: Rounding,
+    /// Start times go to the nearest step as well.
+    pub round_start: bool,
+    /// Longest single worklog; 0 for no limit.
+    pub max_minutes: u32,
+    /// Saturday and Sunday worklogs need `confirmed`.
+    pub confirm_weekends: bool,
+}
+
+impl Default for WorklogRules {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            round_to_minutes: 15,
+            rounding: Rounding::Nearest,
+            round_start: true,
+            max_minutes: 12 * 60,
+            confirm_weekends: true,
+        }
+    }
+}
+
+/// What logging time on an issue in a done status (Done, Closed,
+/// Resolved, ...) does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClosedIssueGuard {
+    Off,
+    /// Asks for confirmation first.
+    #[default]
+    Warn,
+    /// Refuses the worklog.
+    Block,
+}
+
+/// Where new worklogs go.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorklogBackendKind {
+    /// Native JIRA worklogs.
+    #[default]
+    Jira,
+    /// Tempo Timesheets; the API token is kept in the keychain.
+    Tempo,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TempoSettings {
+    pub api_url: String,
+    /// Work attribute that holds the Tempo account.
+    pub account_attribute_key: String,
+    /// Account key booked on new worklogs, if any.
+    pub default_account: Option<String>,
+    /// Further work attributes set on new worklogs.
+    pub attributes: Vec<TempoAttributeValue>,
+}
+
+impl Default for TempoSettings {
+    fn default() -> Self {
+        Self {
+            api_url: "https://api.tempo.io/4".to_string(),
+            account_attribute_key: "_Account_".to_string(),
+            default_account: None,
+            attributes: Vec::new(),
+        }
+    }
+}
+
+/// What a worklog description has to look like before it is submitted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DescriptionRules {
+    pub enabled: bool,
+    /// Characters, ignoring surrounding whitespace; 0 for no minimum.
+    pub min_length: usize,
+    /// Placeholder words rejected as whole words, case-insensitively.
+    pub forbidden_words: Vec<String>,
+    /// Regular expression that must match somewhere in the description,
+    /// e.g. `[A-Z][A-Z0-9]+-\d+` for a ticket reference.
+    pub required_pattern: Option<String>,
+}
+
+impl Default for DescriptionRules {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_length: 15,
+            forbidden_words: ["stuff", "things", "misc", "various"]
+                .map(str::to_string)
+                .to_vec(),
+            required_pattern: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub daily_target_hours: f64,
+    pub goals: GoalSettings,
+    pub hour_bank: HourBankSettings,
+    pub reminder_schedule: ReminderSchedule,
+    pub breaks: BreakSettings,
+    pub pomodoro: PomodoroSettings,
+    pub idle: IdleSettings,
+    pub lunch: LunchSettings,
+    pub calendar: CalendarSettings,
+    pub backfill: BackfillSettings,
+    /// Popover behavior: hide the main window when it loses focus and open it
+    /// next to the tray icon.
+    pub auto_hide_on_blur: bool,
+    pub tray_clicks: TrayClickSettings,
+    pub keyboard: KeyboardSettings,
+    /// macOS: no Dock icon, the app lives in the menu bar only.
+    pub menubar_only: bool,
+    /// When launched at login, stay in the tray instead of opening the main
+    /// window.
+    pub start_minimized: bool,
+    pub http: HttpSettings,
+    pub issue_sync: IssueSyncSettings,
+    /// Refresh through the Atlassian GraphQL gateway (Cloud only).
+    pub experimental_graphql: bool,
+    pub worklog_backend: WorklogBackendKind,
+    pub tempo: TempoSettings,
+    pub description_rules: DescriptionRules,
+    pub done_guard: DoneGuard,
+    pub closed_issue_guard: ClosedIssueGuard,
+    pub worklog_rules: WorklogRules,
+    pub catch_all: CatchAllSettings,
+    /// JIRA project key to the client its time is billed to, for reports
+    /// and exports grouped by client.
+    pub project_clients: BTreeMap<String, String>,
+    pub redaction: RedactionSettings,
+    pub sla_warnings: SlaWarningSettings,
+    pub time_units: TimeUnitSettings,
+    pub theme: Theme,
+    pub team_config: TeamConfigSettings,
+    pub updates: UpdateSettings,
+    pub webhook: WebhookSettings,
+    pub session_lock: SessionLockSettings,
+    pub notifications: NotificationSettings,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            daily_target_hours: 8.0,
+            goals: GoalSettings::default(),
+            hour_bank: HourBankSettings::default(),
+            reminder_schedule: ReminderSchedule::default(),
+            breaks: BreakSettings::default(),
+            pomodoro: PomodoroSettings::default(),
+            idle: IdleSettings::default(),
+            lunch: LunchSettings::default(),
+            calendar: CalendarSettings::default(),
+            backfill: BackfillSettings::default(),
+            auto_hide_on_blur: false,
+            tray_clicks: TrayClickSettings::default(),
+            keyboard: KeyboardSettings::default(),
+            menubar_only: false,
+            start_minimized: true,
+            http: HttpSettings::default(),
+            issue_sync: IssueSyncSettings::default(),
+            experimental_graphql: false,
+            worklog_backend: WorklogBackendKind::Jira,
+            tempo: TempoSettings::default(),
+            description_rules: DescriptionRules::default(),
+            done_guard: DoneGuard::Off,
+            closed_issue_guard: ClosedIssueGuard::Warn,
+            worklog_rules: WorklogRules::default(),
+            catch_all: CatchAllSettings::default(),
+            project_clients: BTreeMap::new(),
+            redaction: RedactionSettings::default(),
+            sla_warnings: SlaWarningSettings::default(),
+            time_units: TimeUnitSettings::default(),
+            theme: Theme::System,
+            team_config: TeamConfigSettings::default(),
+            updates: UpdateSettings::default(),
+            webhook: WebhookSettings::default(),
+            session_lock: SessionLockSettings::default(),
+            notifications: NotificationSettings::default(),
+        }
+    }
+}
+
+impl AppSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=24.0).contains(&self.daily_target_hours) {
+            return Err("Daily target must be between 0 and 24 hours".to_string());
+        }
+        if !(self.time_units.hours_per_day > 0.0 && self.time_units.hours_per_day <= 24.0) {
+            return Err("A day must be between 0 and 24 hours long".to_string());
+        }
+        if self.tray_clicks.middle == TrayClickAction::ShowMenu {
+            return Err("Only the left click can open the tray menu".to_string());
+        }
+        if self.breaks.threshold_minutes == 0 || self.breaks.min_break_minutes == 0 {
+            return Err("Break reminder durations must be greater than zero".to_string());
+        }
+        if self.idle.threshold_minutes == 0 {
+            return Err("The idle threshold must be greater than zero".to_string());
+        }
+        if self.lunch.window.is_some_and(|window| window.start >= window.end) {
+            return Err("The lunch window must end after it starts".to_string());
+        }
+        if let Some(feed) = self.calendar.feeds.iter().find(|feed| feed.starts_with("http://")) {
+            return Err(format!("Calendar feeds must use https: {}", feed));
+        }
+        for rule in &self.calendar.rules {
+            regex::Regex::new(&rule.pattern).map_err(|e| format!("Invalid calendar pattern: {}", e))?;
+            if !JiraClient::is_valid_issue_key(&rule.issue_key) {
+                return Err(format!("'{}' is not an issue key", rule.issue_key));
+            }
+        }
+        for entry in &self.backfill.recurring {
+            if !JiraClient::is_valid_issue_key(&entry.issue_key) {
+                return Err(format!("'{}' is not an issue key", entry.issue_key));
+            }
+            if entry.minutes == 0 || (entry.weekdays.is_empty() && entry.cron.is_none()) {
+                return Err(format!("The recurring entry for {} needs minutes and weekdays", entry.issue_key));
+            }
+            if let Some(expression) = &entry.cron {
+                cron::validate(expression)?;
+            }
+        }
+        if !(1..=240).contains(&self.backfill.granularity_minutes) {
+            return Err("The time granularity must be between 1 and 240 minutes".to_string());
+        }
+        if self.worklog_backend == WorklogBackendKind::Tempo && !self.tempo.api_url.starts_with("https://") {
+            return Err("The Tempo API URL must start with https://".to_string());
+        }
+        self.http.validate()?;
+        if self.sla_warnings.thresholds_minutes.contains(&0) {
+            return Err("SLA warning thresholds must be greater than zero".to_string());
+        }
+        if let Some(url) = &self.team_config.url {
+            if !url.starts_with("https://") {
+                return Err("The team config URL must start with https://".to_string());
+            }
+            let key = self.team_config.public_key.as_deref().ok_or("The team config needs a public key")?;
+            team_config::public_key(key)?;
+        }
+        if self.webhook.address.parse::<std::net::IpAddr>().is_err() {
+            return Err(format!("'{}' is not an IP address to listen on", self.webhook.address));
+        }
+        if self.webhook.enabled && self.webhook.port == 0 {
+            return Err("The webhook listener needs a port".to_string());
+        }
+        if !(1..=480).contains(&self.session_lock.idle_minutes) {
+            return Err("The session must lock after 1 to 480 idle minutes".to_string());
+        }
+        if self.notifications.quiet_hours.is_some_and(|quiet| quiet.start == quiet.end) {
+            return Err("Quiet hours must end at a different time than they start".to_string());
+        }
+        if let Some(shortcut) = &self.keyboard.quick_log_shortcut {
+            shortcuts::parse(shortcut)?;
+        }
+        if let Some(pattern) = &self.description_rules.required_pattern {
+            regex::Regex::new(pattern).map_err(|e| format!("Invalid description pattern: {}", e))?;
+        }
+        if self.worklog_rules.round_to_minutes > 240 {
+            return Err("Worklogs can be rounded to at most 240 minutes".to_string());
+        }
+        if self.worklog_rules.max_minutes > 24 * 60 {
+            return Err("The longest worklog can be at most 24 hours".to_string());
+        }
+        if let Some((project, _)) = self.project_clients.iter().find(|(_, client)| client.trim().is_empty()) {
+            return Err(format!("Project {} needs a client name", project));
+        }
+        if !self.catch_all.summary.contains("{description}") {
+            return Err("The catch-all summary needs {description}".to_string());
+        }
+        if !(1..=30 * 24).contains(&self.updates.check_interval_hours) {
+            return Err("Check for updates every 1 to 720 hours".to_string());
+        }
+        if !(0.0..=168.0).contains(&self.hour_bank.contract_hours_per_week) {
+            return Err("Contract hours must be between 0 and 168 a week".to_string());
+        }
+        self.goals.validate()?;
+        self.pomodoro.validate()?;
+        self.reminder_schedule.validate()
+    }
+
+    /// The client `issue_key`'s project is billed to, if mapped.
+    pub fn client_of(&self, issue_key: &str) -> Option<&str> {
+        let project = issue_key.split_once('-').map_or(issue_key, |(project, _)| project);
+        self.project_clients.get(project).map(String::as_str)
+    }
+
+    pub fn daily_target_seconds(&self) -> u32 {
+        (self.daily_target_hours * 3600.0) as u32
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// With encryption on, the defaults until the vault is unlocked.
+pub fn load(app_handle: &AppHandle<Wry>) -> AppSettings {
+    if vault::is_enabled(app_handle) {
+        return AppSettings::default();
+    }
+    match store::config_file(app_handle, SETTINGS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(_) => AppSettings::default(),
+    }
+}
+
+/// Activates settings read after startup, once the vault is unlocked.
+pub fn replace(app_handle: &AppHandle<Wry>, settings: AppSettings) -> Result<(), String> {
+    let state = app_handle.state::<JiraState>();
+    let previous = {
+        let mut current = state.settings().lock().map_err(|e| e.to_string())?;
+        std::mem::replace(&mut *current, settings.clone())
+    };
+    if let Err(e) = app_handle.emit("settings-changed", &settings) {
+        tracing::warn!("Failed to emit settings-changed event: {}", e);
+    }
+    apply(app_handle, &previous, &settings)
+}
+
+/// Applies pool settings; the connections move to the new pool too, once
+/// the commands using them let go.
+pub fn apply_http(app_handle: &AppHandle<Wry>, settings: &HttpSettings) {
+    if !http::configure(settings) {
+        return;
+    }
+
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app_handle.state::<JiraState>();
+        let mut registry = state::connections_mut(&state).await;
+        for client in registry.clients_mut() {
+            if let Err(e) = client.reload_http_client() {
+                tracing::warn!("Keeping the previous HTTP client: {}", e);
+            }
+        }
+    });
+}
+
+pub fn current(app_handle: &AppHandle<Wry>) -> AppSettings {
+    let state = app_handle.state::<JiraState>();
+    let settings = state.settings().lock().unwrap_or_else(|e| e.into_inner());
+    settings.clone()
+}
+
+/// The settings with the team configuration merged in under them, for
+/// reading only; changes start from `current`.
+pub fn effective(app_handle: &AppHandle<Wry>) -> AppSettings {
+    let mut settings = current(app_handle);
+    if let Some(team) = team_config::current(app_handle) {
+        team.merge_into(&mut settings);
+    }
+    settings
+}
+
+#[tauri::command]
+pub async fn get_settings(state: State<'_, JiraState>) -> Result<AppSettings, String> {
+    let settings = state.settings().lock().map_err(|e| e.to_string())?;
+    Ok(settings.clone())
+}
+
+/// Validates, persists and activates `settings`, returning the settings they
+/// replaced. Windows are told through a `settings-changed` event.
+pub fn save(app_handle: &AppHandle<Wry>, settings: &mut AppSettings) -> Result<AppSettings, String> {
+    settings.validate()?;
+    settings.reminder_schedule.steps.sort_by_key(|step| step.time);
+
+    let path = store::config_file(app_handle, SETTINGS_FILE)?;
+    vault::save(app_handle, &path, &settings)?;
+
+    let state = app_handle.state::<JiraState>();
+    let previous = {
+        let mut current = state.settings().lock().map_err(|e| e.to_string())?;
+        std::mem::replace(&mut *current, settings.clone())
+    };
+
+    if let Err(e) = app_handle.emit("settings-changed", &*settings) {
+        tracing::warn!("Failed to emit settings-changed event: {}", e);
+    }
+    Ok(previous)
+}
+
+#[tauri::command]
+pub async fn update_settings(mut settings: AppSettings, app_handle: AppHandle<Wry>) -> Result<AppSettings, String> {
+    let previous = save(&app_handle, &mut settings)?;
+    apply(&app_handle, &previous, &settings)?;
+    Ok(settings)
+}
+
+/// Puts `settings` into effect where they differ from `previous`.
+pub fn apply(app_handle: &AppHandle<Wry>, previous: &AppSettings, settings: &AppSettings) -> Result<(), String> {
+    tray::apply_settings(app_handle, settings);
+    platform::apply_menubar_only(app_handle, settings.menubar_only)?;
+    apply_http(app_handle, &settings.http);
+    if previous.keyboard.quick_log_shortcut != settings.keyboard.quick_log_shortcut {
+        shortcuts::apply(app_handle, settings.keyboard.quick_log_shortcut.as_deref())?;
+    }
+    if previous.issue_sync.interval_minutes != settings.issue_sync.interval_minutes {
+        sync::restart(app_handle);
+    }
+    if previous.team_config != settings.team_config {
+        team_config::spawn_refresh(app_handle);
+    }
+    if previous.webhook != settings.webhook {
+        webhook::restart(app_handle);
+    }
+    scheduler::wake(app_handle);
+    Ok(())
+}
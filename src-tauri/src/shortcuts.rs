@@ -0,0 +1,64 @@
+//! The system-wide hotkey that opens the quick-log window. Only one shortcut
+//! is registered at a time; it follows `keyboard.quick_log_shortcut`.
+
+use std::str::FromStr;
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Wry};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+use crate::settings;
+
+pub fn plugin() -> TauriPlugin<Wry> {
+    tauri_plugin_global_shortcut::Builder::new()
+        .with_handler(|app_handle, _shortcut, event| {
+            if event.state == ShortcutState::Pressed {
+                crate::toggle_quick_log_window(app_handle);
+            }
+        })
+        .build()
+}
+
+/// Parses an accelerator such as `CommandOrControl+Shift+L`.
+pub fn parse(shortcut: &str) -> Result<Shortcut, String> {
+    Shortcut::from_str(shortcut.trim()).map_err(|e| format!("Invalid shortcut '{}': {}", shortcut, e))
+}
+
+/// Replaces whatever is registered with `shortcut`; `None` leaves no hotkey.
+pub fn apply(app_handle: &AppHandle<Wry>, shortcut: Option<&str>) -> Result<(), String> {
+    let global_shortcut = app_handle.global_shortcut();
+    global_shortcut.unregister_all().map_err(|e| e.to_string())?;
+
+    if let Some(shortcut) = shortcut {
+        global_shortcut
+            .register(parse(shortcut)?)
+            .map_err(|e| format!("Failed to register {}: {}", shortcut, e))?;
+    }
+    Ok(())
+}
+
+/// Registers the configured shortcut at startup.
+pub fn register_from_settings(app_handle: &AppHandle<Wry>) {
+    let shortcut = settings::current(app_handle).keyboard.quick_log_shortcut;
+    if let Err(e) = apply(app_handle, shortcut.as_deref()) {
+        tracing::warn!("Failed to register the quick log shortcut: {}", e);
+    }
+}
+
+async fn save_shortcut(app_handle: &AppHandle<Wry>, shortcut: Option<String>) -> Result<(), String> {
+    let mut updated = settings::current(app_handle);
+    updated.keyboard.quick_log_shortcut = shortcut;
+    settings::update_settings(updated, app_handle.clone()).await.map(|_| ())
+}
+
+/// Makes `shortcut` the quick log hotkey, now and on later starts.
+#[tauri::command]
+pub async fn register_quick_log_shortcut(shortcut: String, app_handle: AppHandle<Wry>) -> Result<(), String> {
+    parse(&shortcut)?;
+    save_shortcut(&app_handle, Some(shortcut.trim().to_string())).await
+}
+
+/// Turns the quick log hotkey off.
+#[tauri::command]
+pub async fn unregister_quick_log_shortcut(app_handle: AppHandle<Wry>) -> Result<(), String> {
+    save_shortcut(&app_handle, None).await
+}
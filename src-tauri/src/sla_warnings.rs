@@ -0,0 +1,228 @@
+//! Warnings before the SLA clocks of assigned service desk requests breach.
+//! Issue sync refreshes the deadlines; the scheduler fires the warnings at
+//! the thresholds in `sla_warnings`.
+
+use chrono::{DateTime, Duration, Local};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::jsm::ServiceRequest;
+use crate::notifications::NotificationKind;
+use crate::{clock, notifications, scheduler, settings};
+
+pub type SlaWatchState = Mutex<SlaWatch>;
+
+/// When a running SLA clock breaches.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlaDeadline {
+    pub issue_key: String,
+    pub summary: String,
+    pub sla_name: String,
+    pub breach_at: DateTime<Local>,
+}
+
+impl SlaDeadline {
+    fn id(&self) -> (String, String) {
+        (self.issue_key.clone(), self.sla_name.clone())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SlaWatch {
+    deadlines: Vec<SlaDeadline>,
+    /// Issue key, SLA name and threshold of the warnings already shown.
+    warned: HashSet<(String, String, u32)>,
+}
+
+fn warn_at(deadline: &SlaDeadline, minutes: u32) -> DateTime<Local> {
+    deadline.breach_at - Duration::minutes(minutes as i64)
+}
+
+/// Deadlines of the clocks that are running: not paused, completed or
+/// already breached.
+fn deadlines(requests: &[ServiceRequest], now: DateTime<Local>) -> Vec<SlaDeadline> {
+    let mut deadlines: Vec<SlaDeadline> = requests
+        .iter()
+        .flat_map(|request| request.slas.iter().map(move |sla| (request, sla)))
+        .filter(|(_, sla)| sla.running && !sla.paused && !sla.breached)
+        .filter_map(|(request, sla)| {
+            let breach_at = sla
+                .breach_time
+                .or_else(|| sla.remaining_seconds.map(|seconds| now + Duration::seconds(seconds)))?;
+            Some(SlaDeadline {
+                issue_key: request.key.clone(),
+                summary: request.summary.clone(),
+                sla_name: sla.name.clone(),
+                breach_at,
+            })
+        })
+        .collect();
+    deadlines.sort_by_key(|deadline| deadline.breach_at);
+    deadlines
+}
+
+impl SlaWatch {
+    /// Takes new deadlines and forgets the warnings of clocks that stopped.
+    fn replace(&mut self, deadlines: Vec<SlaDeadline>) {
+        let running: HashSet<(String, String)> = deadlines.iter().map(SlaDeadline::id).collect();
+        self.warned
+            .retain(|(issue_key, sla_name, _)| running.contains(&(issue_key.clone(), sla_name.clone())));
+        self.deadlines = deadlines;
+    }
+
+    fn is_warned(&self, deadline: &SlaDeadline, minutes: u32) -> bool {
+        self.warned
+            .contains(&(deadline.issue_key.clone(), deadline.sla_name.clone(), minutes))
+    }
+
+    fn next_warning(&self, thresholds: &[u32]) -> Option<DateTime<Local>> {
+        self.deadlines
+            .iter()
+            .flat_map(|deadline| thresholds.iter().map(move |minutes| (deadline, *minutes)))
+            .filter(|(deadline, minutes)| !self.is_warned(deadline, *minutes))
+            .map(|(deadline, minutes)| warn_at(deadline, minutes))
+            .min()
+    }
+
+    /// The deadlines with a threshold passed since the last warning, each
+    /// with the tightest threshold it passed. Every passed threshold counts
+    /// as warned, so a clock found late warns once rather than once per
+    /// threshold.
+    fn take_due(&mut self, thresholds: &[u32], now: DateTime<Local>) -> Vec<(SlaDeadline, u32)> {
+        let mut due = Vec::new();
+        for deadline in self.deadlines.iter().filter(|deadline| deadline.breach_at > now) {
+            let passed: Vec<u32> = thresholds
+                .iter()
+                .copied()
+                .filter(|minutes| warn_at(deadline, *minutes) <= now)
+                .collect();
+            if passed.iter().all(|minutes| self.is_warned(deadline, *minutes)) {
+                continue;
+            }
+            if let Some(tightest) = passed.iter().min() {
+                due.push((deadline.clone(), *tightest));
+            }
+            for minutes in passed {
+                self.warned
+                    .insert((deadline.issue_key.clone(), deadline.sla_name.clone(), minutes));
+            }
+        }
+        due
+    }
+}
+
+/// Reloads the deadlines from the assigned requests of `client`.
+pub async fn refresh(app_handle: &AppHandle<Wry>, client: &JiraClient) -> Result<(), JiraError> {
+    let requests = client.get_assigned_requests().await?;
+    let deadlines = deadlines(&requests, clock::now(app_handle));
+
+    let state = app_handle.state::<SlaWatchState>();
+    state.lock().unwrap_or_else(|e| e.into_inner()).replace(deadlines);
+    scheduler::wake(app_handle);
+    Ok(())
+}
+
+/// Forgets every deadline, e.g. after switching connections.
+pub fn clear(app_handle: &AppHandle<Wry>) {
+    let state = app_handle.state::<SlaWatchState>();
+    *state.lock().unwrap_or_else(|e| e.into_inner()) = SlaWatch::default();
+}
+
+pub fn next_fire_at(app_handle: &AppHandle<Wry>) -> Option<DateTime<Local>> {
+    let settings = settings::current(app_handle).sla_warnings;
+    if !settings.enabled {
+        return None;
+    }
+    let state = app_handle.state::<SlaWatchState>();
+    let watch = state.lock().unwrap_or_else(|e| e.into_inner());
+    watch.next_warning(&settings.thresholds_minutes)
+}
+
+/// Shows the warnings whose threshold has been reached.
+pub fn fire_due(app_handle: &AppHandle<Wry>, now: DateTime<Local>) {
+    let settings = settings::current(app_handle).sla_warnings;
+    if !settings.enabled {
+        return;
+    }
+    let due = {
+        let state = app_handle.state::<SlaWatchState>();
+        let mut watch = state.lock().unwrap_or_else(|e| e.into_inner());
+        watch.take_due(&settings.thresholds_minutes, now)
+    };
+
+    for (deadline, _) in due {
+        let minutes = (deadline.breach_at - now).num_minutes().max(1);
+        notifications::notify(
+            app_handle,
+            NotificationKind::Sync,
+            &format!("SLA: {} breaches in {} min", deadline.issue_key, minutes),
+            &format!("{} · {}", deadline.sla_name, deadline.summary),
+        );
+    }
+}
+
+/// Running SLA clocks of my requests as of the last sync, soonest breach
+/// first.
+#[tauri::command]
+pub async fn get_sla_deadlines(state: State<'_, SlaWatchState>) -> Result<Vec<SlaDeadline>, String> {
+    let watch = state.lock().map_err(|e| e.to_string())?;
+    Ok(watch.deadlines.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 6, 7, hour, minute, 0).unwrap()
+    }
+
+    fn watch(breach_at: DateTime<Local>) -> SlaWatch {
+        let mut watch = SlaWatch::default();
+        watch.replace(vec![SlaDeadline {
+            issue_key: "HELP-1".to_string(),
+            summary: "Printer on fire".to_string(),
+            sla_name: "Time to resolution".to_string(),
+            breach_at,
+        }]);
+        watch
+    }
+
+    #[test]
+    fn warns_once_per_threshold() {
+        let mut watch = watch(at(12, 0));
+        let thresholds = [60, 15];
+
+        assert_eq!(watch.next_warning(&thresholds), Some(at(11, 0)));
+        assert!(watch.take_due(&thresholds, at(10, 59)).is_empty());
+        assert_eq!(watch.take_due(&thresholds, at(11, 0)).len(), 1);
+        assert!(watch.take_due(&thresholds, at(11, 1)).is_empty());
+        assert_eq!(watch.next_warning(&thresholds), Some(at(11, 45)));
+        assert_eq!(watch.take_due(&thresholds, at(11, 50))[0].1, 15);
+        assert_eq!(watch.next_warning(&thresholds), None);
+    }
+
+    #[test]
+    fn a_late_find_warns_once_with_the_tightest_threshold() {
+        let mut watch = watch(at(12, 0));
+        let due = watch.take_due(&[60, 30, 15], at(11, 50));
+
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].1, 15);
+        assert!(watch.take_due(&[60, 30, 15], at(11, 55)).is_empty());
+    }
+
+    #[test]
+    fn forgets_warnings_of_stopped_clocks() {
+        let mut watch = watch(at(12, 0));
+        watch.take_due(&[30], at(11, 40));
+        watch.replace(Vec::new());
+
+        assert!(watch.warned.is_empty());
+    }
+}
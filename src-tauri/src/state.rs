@@ -0,0 +1,69 @@
+//! The state commands and background tasks share: the JIRA connections
+//! (and with them the clients), the settings, the timer and the issue sync
+//! status, in one `AppState` behind an `Arc`. Each part has its own lock,
+//! so a slow caller of one never holds up the others.
+//!
+//! The connections sit behind a tokio `RwLock`, since they are used around
+//! requests; lookups don't wait on each other and only connecting,
+//! switching and removing take the write lock. Never hold a guard across a
+//! request: take the client with `client_for` and let the guard go.
+//!
+//! The settings, the timer and the sync status are also read from sync
+//! code (the tray menu, `RunEvent` handlers, quit and diagnostics), where a
+//! tokio lock can't be awaited and `blocking_read` panics inside the
+//! runtime, so they keep short `std` locks. None of those is held across an
+//! `.await`.
+
+use std::sync::Arc;
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::connections::ConnectionRegistry;
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::settings::SettingsState;
+use crate::sync::SyncState;
+use crate::timer::TimerState;
+
+#[derive(Default)]
+pub struct AppState {
+    connections: RwLock<ConnectionRegistry>,
+    settings: SettingsState,
+    timer: TimerState,
+    sync: SyncState,
+}
+
+impl AppState {
+    pub fn settings(&self) -> &SettingsState {
+        &self.settings
+    }
+
+    pub fn timer(&self) -> &TimerState {
+        &self.timer
+    }
+
+    pub fn sync(&self) -> &SyncState {
+        &self.sync
+    }
+}
+
+pub type JiraState = Arc<AppState>;
+
+/// Shared with other readers.
+pub async fn connections(state: &JiraState) -> RwLockReadGuard<'_, ConnectionRegistry> {
+    state.connections.read().await
+}
+
+/// For connecting, switching and removing; waits for the readers.
+pub async fn connections_mut(state: &JiraState) -> RwLockWriteGuard<'_, ConnectionRegistry> {
+    state.connections.write().await
+}
+
+/// The active connection's client.
+pub async fn connected_client(state: &JiraState) -> Result<JiraClient, JiraError> {
+    client_for(state, None).await
+}
+
+/// The client for connection `connection`, or the active one for `None`.
+pub async fn client_for(state: &JiraState, connection: Option<&str>) -> Result<JiraClient, JiraError> {
+    connections(state).await.get(connection).cloned()
+}
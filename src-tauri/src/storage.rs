@@ -0,0 +1,702 @@
+//! Local SQLite cache of assigned issues and worklogs, so the issue list
+//! shows right away on launch and stays readable offline. Rows are keyed
+//! by account (email and site), not connection id, so switching accounts
+//! never shows someone else's issues.
+
+use chrono::{DateTime, Local, NaiveDate, SecondsFormat, Utc};
+use rusqlite::serialize::OwnedData;
+use rusqlite::{ffi, params, Connection, DatabaseName, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ptr::NonNull;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+use crate::error::JiraError;
+use crate::hierarchy::IssueLink;
+use crate::jira_api::{field_sets, JiraClient};
+use crate::jira_types::{JiraIssue, Worklog};
+use crate::timer::{SegmentKind, TimerSegment};
+use crate::{issue_cache, store, vault};
+
+const DATABASE_FILE: &str = "cache.sqlite3";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS issue_lists (
+        account TEXT PRIMARY KEY,
+        fetched_at TEXT NOT NULL,
+        issues TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS worklogs (
+        account TEXT NOT NULL,
+        issue_key TEXT NOT NULL,
+        fetched_at TEXT NOT NULL,
+        worklogs TEXT NOT NULL,
+        PRIMARY KEY (account, issue_key)
+    );
+    CREATE TABLE IF NOT EXISTS last_logged (
+        account TEXT NOT NULL,
+        issue_key TEXT NOT NULL,
+        started TEXT NOT NULL,
+        PRIMARY KEY (account, issue_key)
+    );
+    CREATE TABLE IF NOT EXISTS issue_summaries (
+        account TEXT NOT NULL,
+        issue_key TEXT NOT NULL,
+        summary TEXT NOT NULL,
+        fetched_at TEXT NOT NULL,
+        PRIMARY KEY (account, issue_key)
+    );
+    CREATE TABLE IF NOT EXISTS timed (
+        day TEXT NOT NULL,
+        issue_key TEXT NOT NULL,
+        seconds INTEGER NOT NULL,
+        PRIMARY KEY (day, issue_key)
+    );
+    CREATE TABLE IF NOT EXISTS timer_segments (
+        session TEXT NOT NULL,
+        issue_key TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        started TEXT NOT NULL,
+        ended TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS timer_segments_started ON timer_segments (started);
+    CREATE TABLE IF NOT EXISTS issue_parents (
+        account TEXT NOT NULL,
+        issue_key TEXT NOT NULL,
+        subtask INTEGER NOT NULL,
+        parent_key TEXT,
+        parent_summary TEXT NOT NULL,
+        fetched_at TEXT NOT NULL,
+        PRIMARY KEY (account, issue_key)
+    );
+    CREATE TABLE IF NOT EXISTS worklog_history (
+        account TEXT NOT NULL,
+        worklog_id TEXT NOT NULL,
+        issue_key TEXT NOT NULL,
+        day TEXT NOT NULL,
+        seconds INTEGER NOT NULL,
+        PRIMARY KEY (account, worklog_id)
+    );
+    CREATE INDEX IF NOT EXISTS worklog_history_day ON worklog_history (account, day);
+";
+
+pub struct Storage {
+    connection: Mutex<Connection>,
+}
+
+/// What the cache holds for one account.
+pub struct Cached<T> {
+    pub value: T,
+    pub fetched_at: DateTime<Local>,
+}
+
+fn open_file(app_handle: &AppHandle<Wry>) -> Result<Connection, String> {
+    let path = store::data_file(app_handle, DATABASE_FILE)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let connection = Connection::open(&path).map_err(|e| e.to_string())?;
+    connection.execute_batch(SCHEMA).map_err(|e| e.to_string())?;
+    Ok(connection)
+}
+
+fn in_memory() -> Connection {
+    let connection = Connection::open_in_memory().expect("Failed to open in-memory database");
+    connection.execute_batch(SCHEMA).expect("Failed to create cache tables");
+    connection
+}
+
+/// `bytes` in memory SQLite allocated, which `deserialize` takes over.
+fn sqlite_copy(bytes: &[u8]) -> Result<OwnedData, String> {
+    // SAFETY: the buffer comes from `sqlite3_malloc64` and is at least
+    // `bytes.len()` long; `OwnedData` frees it with `sqlite3_free`.
+    unsafe {
+        let ptr = NonNull::new(ffi::sqlite3_malloc64(bytes.len().max(1) as u64).cast::<u8>())
+            .ok_or_else(|| "Out of memory".to_string())?;
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.as_ptr(), bytes.len());
+        Ok(OwnedData::from_raw_nonnull(ptr, bytes.len()))
+    }
+}
+
+/// Removes the database file and what SQLite keeps next to it.
+pub fn remove_file(app_handle: &AppHandle<Wry>) -> Result<(), String> {
+    let path = store::data_file(app_handle, DATABASE_FILE)?;
+    for suffix in ["", "-journal", "-wal", "-shm"] {
+        let mut file = path.clone().into_os_string();
+        file.push(suffix);
+        match std::fs::remove_file(&file) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    Ok(())
+}
+
+/// The cache key of the account `client` is signed in as.
+pub fn account(client: &JiraClient) -> String {
+    format!("{} {}", client.email, client.base_url)
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| e.to_string())
+}
+
+fn from_row<T: DeserializeOwned>(fetched_at: String, json: String) -> Option<Cached<T>> {
+    let fetched_at = DateTime::parse_from_rfc3339(&fetched_at).ok()?.with_timezone(&Local);
+    match serde_json::from_str(&json) {
+        Ok(value) => Some(Cached { value, fetched_at }),
+        Err(e) => {
+            tracing::warn!("Ignoring unreadable cache entry: {}", e);
+            None
+        }
+    }
+}
+
+impl Storage {
+    /// Opens the cache database in the app data directory. When that fails
+    /// the cache lives in memory for this run, so the app still starts.
+    /// With encryption on it's empty and in memory until the vault is
+    /// unlocked.
+    pub fn open(app_handle: &AppHandle<Wry>) -> Self {
+        let connection = if vault::is_enabled(app_handle) {
+            in_memory()
+        } else {
+            open_file(app_handle).unwrap_or_else(|e| {
+                tracing::warn!("Failed to open the issue cache, keeping it in memory: {}", e);
+                in_memory()
+            })
+        };
+
+        Self {
+            connection: Mutex::new(connection),
+        }
+    }
+
+    /// The whole database as an SQLite file image.
+    pub fn export(&self) -> Result<Vec<u8>, String> {
+        let connection = self.connection();
+        let data = connection.serialize(DatabaseName::Main).map_err(|e| e.to_string())?;
+        Ok(data.to_vec())
+    }
+
+    /// Replaces the cache with the file image `bytes`, kept in memory.
+    pub fn import(&self, bytes: &[u8]) -> Result<(), String> {
+        let mut connection = Connection::open_in_memory().map_err(|e| e.to_string())?;
+        connection
+            .deserialize(DatabaseName::Main, sqlite_copy(bytes)?, false)
+            .map_err(|e| e.to_string())?;
+        connection.execute_batch(SCHEMA).map_err(|e| e.to_string())?;
+        *self.connection() = connection;
+        Ok(())
+    }
+
+    /// Moves the cache back to the database file, written from `bytes`.
+    pub fn reopen_file(&self, app_handle: &AppHandle<Wry>, bytes: &[u8]) -> Result<(), String> {
+        let path = store::data_file(app_handle, DATABASE_FILE)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(&path, bytes).map_err(|e| e.to_string())?;
+        *self.connection() = open_file(app_handle)?;
+        Ok(())
+    }
+
+    /// Rows changed since the connection was opened, to tell whether the
+    /// cache needs writing out.
+    pub fn changes(&self) -> u64 {
+        self.connection().total_changes()
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn save_issues(&self, account: &str, issues: &[JiraIssue], fetched_at: DateTime<Local>) -> Result<(), String> {
+        self.connection()
+            .execute(
+                "INSERT OR REPLACE INTO issue_lists (account, fetched_at, issues) VALUES (?1, ?2, ?3)",
+                params![account, fetched_at.to_rfc3339(), to_json(&issues)?],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn load_issues(&self, account: &str) -> Option<Cached<Vec<JiraIssue>>> {
+        let row = self
+            .connection()
+            .query_row(
+                "SELECT fetched_at, issues FROM issue_lists WHERE account = ?1",
+                params![account],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional();
+        match row {
+            Ok(row) => row.and_then(|(fetched_at, json)| from_row(fetched_at, json)),
+            Err(e) => {
+                tracing::warn!("Failed to read cached issues: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn save_worklogs(
+        &self,
+        account: &str,
+        issue_key: &str,
+        worklogs: &[Worklog],
+        fetched_at: DateTime<Local>,
+    ) -> Result<(), String> {
+        self.connection()
+            .execute(
+                "INSERT OR REPLACE INTO worklogs (account, issue_key, fetched_at, worklogs) VALUES (?1, ?2, ?3, ?4)",
+                params![account, issue_key, fetched_at.to_rfc3339(), to_json(&worklogs)?],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn load_worklogs(&self, account: &str, issue_key: &str) -> Option<Cached<Vec<Worklog>>> {
+        let row = self
+            .connection()
+            .query_row(
+                "SELECT fetched_at, worklogs FROM worklogs WHERE account = ?1 AND issue_key = ?2",
+                params![account, issue_key],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional();
+        match row {
+            Ok(row) => row.and_then(|(fetched_at, json)| from_row(fetched_at, json)),
+            Err(e) => {
+                tracing::warn!("Failed to read cached worklogs: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Remembers that I logged work on `issue_key` starting at `started`,
+    /// unless a later worklog is already known.
+    pub fn note_logged(&self, account: &str, issue_key: &str, started: &str) -> Result<(), String> {
+        let started = JiraClient::parse_jira_datetime(started)
+            .map_err(|e| e.to_string())?
+            .with_timezone(&Utc)
+            .to_rfc3339();
+        self.connection()
+            .execute(
+                "INSERT INTO last_logged (account, issue_key, started) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (account, issue_key) DO UPDATE SET started = excluded.started
+                 WHERE excluded.started > last_logged.started",
+                params![account, issue_key, started],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// The start of my latest known worklog per issue.
+    pub fn last_logged(&self, account: &str) -> HashMap<String, DateTime<Local>> {
+        let connection = self.connection();
+        let rows = connection
+            .prepare("SELECT issue_key, started FROM last_logged WHERE account = ?1")
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params![account], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+                    .collect::<Result<Vec<_>, _>>()
+            });
+        match rows {
+            Ok(rows) => rows
+                .into_iter()
+                .filter_map(|(issue_key, started)| {
+                    let started = DateTime::parse_from_rfc3339(&started).ok()?.with_timezone(&Local);
+                    Some((issue_key, started))
+                })
+                .collect(),
+            Err(e) => {
+                tracing::warn!("Failed to read last logged times: {}", e);
+                HashMap::new()
+            }
+        }
+    }
+
+    pub fn save_summaries(&self, account: &str, summaries: &[(String, String)], fetched_at: DateTime<Local>) -> Result<(), String> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(|e| e.to_string())?;
+        for (issue_key, summary) in summaries {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO issue_summaries (account, issue_key, summary, fetched_at) VALUES (?1, ?2, ?3, ?4)",
+                    params![account, issue_key, summary, fetched_at.to_rfc3339()],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        transaction.commit().map_err(|e| e.to_string())
+    }
+
+    /// Summaries looked up for issues outside the assigned list, by key.
+    pub fn load_summaries(&self, account: &str) -> HashMap<String, Cached<String>> {
+        let connection = self.connection();
+        let rows = connection
+            .prepare("SELECT issue_key, summary, fetched_at FROM issue_summaries WHERE account = ?1")
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params![account], |row| {
+                        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+                    })?
+                    .collect::<Result<Vec<_>, _>>()
+            });
+        match rows {
+            Ok(rows) => rows
+                .into_iter()
+                .filter_map(|(issue_key, summary, fetched_at)| {
+                    let fetched_at = DateTime::parse_from_rfc3339(&fetched_at).ok()?.with_timezone(&Local);
+                    Some((issue_key, Cached { value: summary, fetched_at }))
+                })
+                .collect(),
+            Err(e) => {
+                tracing::warn!("Failed to read cached summaries: {}", e);
+                HashMap::new()
+            }
+        }
+    }
+
+    /// Adds a stopped timer's time to what `issue_key` was timed on `day`.
+    /// The timer isn't tied to an account, so neither are these rows.
+    pub fn note_timed(&self, day: NaiveDate, issue_key: &str, seconds: u64) -> Result<(), String> {
+        self.connection()
+            .execute(
+                "INSERT INTO timed (day, issue_key, seconds) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (day, issue_key) DO UPDATE SET seconds = seconds + excluded.seconds",
+                params![day.to_string(), issue_key, seconds],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Seconds timed per issue on `day`.
+    pub fn timed_on(&self, day: NaiveDate) -> HashMap<String, u64> {
+        let connection = self.connection();
+        let rows = connection
+            .prepare("SELECT issue_key, seconds FROM timed WHERE day = ?1")
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params![day.to_string()], |row| Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?)))?
+                    .collect::<Result<HashMap<_, _>, _>>()
+            });
+        rows.unwrap_or_else(|e| {
+            tracing::warn!("Failed to read timed seconds: {}", e);
+            HashMap::new()
+        })
+    }
+
+    /// Keeps the segments of a timer on `issue_key` started at `session`.
+    pub fn save_timer_session(&self, issue_key: &str, session: DateTime<Local>, segments: &[TimerSegment]) -> Result<(), String> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(|e| e.to_string())?;
+        for segment in segments {
+            transaction
+                .execute(
+                    "INSERT INTO timer_segments (session, issue_key, kind, started, ended) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        utc_timestamp(session),
+                        issue_key,
+                        segment.kind.as_str(),
+                        utc_timestamp(segment.started),
+                        utc_timestamp(segment.ended)
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        transaction.commit().map_err(|e| e.to_string())
+    }
+
+    /// Timer segments started in `[from, to)`, oldest first.
+    pub fn load_timer_segments(&self, from: DateTime<Local>, to: DateTime<Local>) -> Vec<RecordedSegment> {
+        let connection = self.connection();
+        let rows = connection
+            .prepare(
+                "SELECT session, issue_key, kind, started, ended FROM timer_segments
+                 WHERE started >= ?1 AND started < ?2 ORDER BY started",
+            )
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params![utc_timestamp(from), utc_timestamp(to)], |row| {
+                        Ok([row.get::<_, String>(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?])
+                    })?
+                    .collect::<Result<Vec<_>, _>>()
+            });
+        let local = |timestamp: &str| DateTime::parse_from_rfc3339(timestamp).ok().map(|at| at.with_timezone(&Local));
+        match rows {
+            Ok(rows) => rows
+                .into_iter()
+                .filter_map(|[session, issue_key, kind, started, ended]| {
+                    Some(RecordedSegment {
+                        session: local(&session)?,
+                        issue_key,
+                        segment: TimerSegment {
+                            kind: SegmentKind::parse(&kind)?,
+                            started: local(&started)?,
+                            ended: local(&ended)?,
+                        },
+                    })
+                })
+                .collect(),
+            Err(e) => {
+                tracing::warn!("Failed to read timer sessions: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    pub fn save_parents(&self, account: &str, links: &[(String, IssueLink)], fetched_at: DateTime<Local>) -> Result<(), String> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(|e| e.to_string())?;
+        for (issue_key, link) in links {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO issue_parents (account, issue_key, subtask, parent_key, parent_summary, fetched_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![account, issue_key, link.subtask, link.parent_key, link.parent_summary, fetched_at.to_rfc3339()],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        transaction.commit().map_err(|e| e.to_string())
+    }
+
+    /// Where each looked-up issue sits in the hierarchy, by key.
+    pub fn load_parents(&self, account: &str) -> HashMap<String, Cached<IssueLink>> {
+        let connection = self.connection();
+        let rows = connection
+            .prepare("SELECT issue_key, subtask, parent_key, parent_summary, fetched_at FROM issue_parents WHERE account = ?1")
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params![account], |row| {
+                        let link = IssueLink {
+                            subtask: row.get(1)?,
+                            parent_key: row.get(2)?,
+                            parent_summary: row.get(3)?,
+                        };
+                        Ok((row.get::<_, String>(0)?, link, row.get::<_, String>(4)?))
+                    })?
+                    .collect::<Result<Vec<_>, _>>()
+            });
+        match rows {
+            Ok(rows) => rows
+                .into_iter()
+                .filter_map(|(issue_key, link, fetched_at)| {
+                    let fetched_at = DateTime::parse_from_rfc3339(&fetched_at).ok()?.with_timezone(&Local);
+                    Some((issue_key, Cached { value: link, fetched_at }))
+                })
+                .collect(),
+            Err(e) => {
+                tracing::warn!("Failed to read cached issue parents: {}", e);
+                HashMap::new()
+            }
+        }
+    }
+
+    /// Replaces the history of `[from, to]` with `entries`, what the
+    /// backend has for those days now.
+    pub fn replace_history(&self, account: &str, from: NaiveDate, to: NaiveDate, entries: &[HistoryEntry]) -> Result<(), String> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(|e| e.to_string())?;
+        transaction
+            .execute(
+                "DELETE FROM worklog_history WHERE account = ?1 AND day >= ?2 AND day <= ?3",
+                params![account, from.to_string(), to.to_string()],
+            )
+            .map_err(|e| e.to_string())?;
+        for entry in entries {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO worklog_history (account, worklog_id, issue_key, day, seconds) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![account, entry.worklog_id, entry.issue_key, entry.day.to_string(), entry.seconds],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        transaction.commit().map_err(|e| e.to_string())
+    }
+
+    pub fn note_history(&self, account: &str, entry: &HistoryEntry) -> Result<(), String> {
+        self.connection()
+            .execute(
+                "INSERT OR REPLACE INTO worklog_history (account, worklog_id, issue_key, day, seconds) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![account, entry.worklog_id, entry.issue_key, entry.day.to_string(), entry.seconds],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn forget_history(&self, account: &str, worklog_id: &str) -> Result<(), String> {
+        self.connection()
+            .execute(
+                "DELETE FROM worklog_history WHERE account = ?1 AND worklog_id = ?2",
+                params![account, worklog_id],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// The history of `[from, to]`, oldest first; all of it without a range.
+    pub fn load_history(&self, account: &str, range: Option<(NaiveDate, NaiveDate)>) -> Vec<HistoryEntry> {
+        let (from, to) = match range {
+            Some((from, to)) => (from.to_string(), to.to_string()),
+            None => (String::new(), "9999-12-31".to_string()),
+        };
+        let connection = self.connection();
+        let rows = connection
+            .prepare(
+                "SELECT worklog_id, issue_key, day, seconds FROM worklog_history
+                 WHERE account = ?1 AND day >= ?2 AND day <= ?3 ORDER BY day",
+            )
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params![account, from, to], |row| {
+                        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, u32>(3)?))
+                    })?
+                    .collect::<Result<Vec<_>, _>>()
+            });
+        match rows {
+            Ok(rows) => rows
+                .into_iter()
+                .filter_map(|(worklog_id, issue_key, day, seconds)| {
+                    Some(HistoryEntry {
+                        worklog_id,
+                        issue_key,
+                        day: day.parse().ok()?,
+                        seconds,
+                    })
+                })
+                .collect(),
+            Err(e) => {
+                tracing::warn!("Failed to read the worklog history: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Drops everything cached for `account`, e.g. on disconnect.
+    pub fn forget(&self, account: &str) -> Result<(), String> {
+        let connection = self.connection();
+        connection
+            .execute("DELETE FROM issue_lists WHERE account = ?1", params![account])
+            .and_then(|_| connection.execute("DELETE FROM worklogs WHERE account = ?1", params![account]))
+            .and_then(|_| connection.execute("DELETE FROM last_logged WHERE account = ?1", params![account]))
+            .and_then(|_| connection.execute("DELETE FROM issue_summaries WHERE account = ?1", params![account]))
+            .and_then(|_| connection.execute("DELETE FROM issue_parents WHERE account = ?1", params![account]))
+            .and_then(|_| connection.execute("DELETE FROM worklog_history WHERE account = ?1", params![account]))
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// A stored timer segment and the session it belongs to.
+#[derive(Debug, Clone)]
+pub struct RecordedSegment {
+    /// When the timer was started.
+    pub session: DateTime<Local>,
+    pub issue_key: String,
+    pub segment: TimerSegment,
+}
+
+/// One of my worklogs as the analytics keep it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub worklog_id: String,
+    pub issue_key: String,
+    /// The local day it was started on.
+    pub day: NaiveDate,
+    pub seconds: u32,
+}
+
+/// UTC with whole seconds, so stored timestamps sort as text.
+fn utc_timestamp(at: DateTime<Local>) -> String {
+    at.with_timezone(&Utc).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Records a worklog of mine so stale issues can be told apart; failures
+/// are only logged.
+pub fn note_logged(app_handle: &AppHandle<Wry>, client: &JiraClient, issue_key: &str, started: &str) {
+    if let Err(e) = app_handle.state::<Storage>().note_logged(&account(client), issue_key, started) {
+        tracing::warn!("Failed to remember when {} was last logged: {}", issue_key, e);
+    }
+}
+
+/// Assigned issues as `get_assigned_issues` returns them.
+#[derive(Debug, Serialize)]
+pub struct AssignedIssues {
+    pub issues: Vec<JiraIssue>,
+    /// Served from the cache; fresh issues follow in an `issues-updated`
+    /// event.
+    pub stale: bool,
+    pub fetched_at: DateTime<Local>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuesUpdated {
+    /// `None` for the active connection.
+    pub connection: Option<String>,
+    pub issues: Vec<JiraIssue>,
+    pub fetched_at: DateTime<Local>,
+}
+
+/// Fetches the assigned issues of `client` and caches them, on disk and,
+/// for the active connection, in the issue list cache.
+pub async fn fetch_assigned_issues(
+    app_handle: &AppHandle<Wry>,
+    client: &JiraClient,
+    connection: Option<&str>,
+) -> Result<Cached<Vec<JiraIssue>>, JiraError> {
+    let issues = client.get_assigned_issues(field_sets::ISSUE_LIST).await?;
+    let fetched_at = Local::now();
+
+    if let Err(e) = app_handle.state::<Storage>().save_issues(&account(client), &issues, fetched_at) {
+        tracing::warn!("Failed to cache issues: {}", e);
+    }
+    if connection.is_none() {
+        issue_cache::store(app_handle, &issues);
+    }
+    Ok(Cached {
+        value: issues,
+        fetched_at,
+    })
+}
+
+/// The cached issues of `client` right away, refreshed in the background;
+/// without a cache, fetched first.
+pub async fn assigned_issues(
+    app_handle: &AppHandle<Wry>,
+    client: JiraClient,
+    connection: Option<String>,
+) -> Result<AssignedIssues, JiraError> {
+    let Some(cached) = app_handle.state::<Storage>().load_issues(&account(&client)) else {
+        let fresh = fetch_assigned_issues(app_handle, &client, connection.as_deref()).await?;
+        return Ok(AssignedIssues {
+            issues: fresh.value,
+            stale: false,
+            fetched_at: fresh.fetched_at,
+        });
+    };
+
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        match fetch_assigned_issues(&app_handle, &client, connection.as_deref()).await {
+            Ok(fresh) => {
+                let update = IssuesUpdated {
+                    connection,
+                    issues: fresh.value,
+                    fetched_at: fresh.fetched_at,
+                };
+                if let Err(e) = app_handle.emit("issues-updated", update) {
+                    tracing::warn!("Failed to emit issues-updated event: {}", e);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to refresh issues: {}", e),
+        }
+    });
+
+    Ok(AssignedIssues {
+        issues: cached.value,
+        stale: true,
+        fetched_at: cached.fetched_at,
+    })
+}
@@ -0,0 +1,42 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Wry};
+
+pub fn config_file(app_handle: &AppHandle<Wry>, name: &str) -> Result<PathBuf, String> {
+    let dir = app_handle.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join(name))
+}
+
+pub fn data_file(app_handle: &AppHandle<Wry>, name: &str) -> Result<PathBuf, String> {
+    let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join(name))
+}
+
+/// Reads a JSON file, falling back to the default value when the file is
+/// missing or unreadable so a corrupt file never prevents startup.
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> T {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return T::default(),
+    };
+
+    serde_json::from_str(&contents).unwrap_or_else(|e| {
+        tracing::warn!("Failed to parse {}: {}", path.display(), e);
+        T::default()
+    })
+}
+
+/// Writes a JSON file through a temporary file so a crash mid-write cannot
+/// leave a truncated file behind.
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let contents = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, contents).map_err(|e| e.to_string())?;
+    fs::rename(&tmp_path, path).map_err(|e| e.to_string())
+}
@@ -0,0 +1,114 @@
+//! Titles for issue keys that aren't among my assigned issues, e.g. in
+//! worklogs, drafts, templates or the timer, so lists never show a bare key.
+//! They are looked up the first time they are asked for and kept in the
+//! cache database.
+
+use chrono::{Duration, Local};
+use std::collections::{BTreeSet, HashMap};
+use tauri::{AppHandle, Manager, State, Wry};
+use tokio::task::JoinSet;
+
+use crate::error::JiraError;
+use crate::jira_api::{field_sets, JiraClient};
+use crate::storage::{self, Storage};
+use crate::{client_for, JiraState};
+
+/// Looked-up summaries older than this are fetched again; until that
+/// succeeds the old one is still shown.
+const MAX_AGE_DAYS: i64 = 7;
+/// Issues fetched for one call; the rest wait for the next one.
+const MAX_LOOKUPS: usize = 50;
+const MAX_CONCURRENT: usize = 4;
+
+/// Summaries of `issue_keys` by key, from the assigned issues, the cache,
+/// or JIRA for the keys neither knows. Keys that can't be looked up are
+/// left out.
+pub async fn resolve(app_handle: &AppHandle<Wry>, client: &JiraClient, issue_keys: &[String]) -> HashMap<String, String> {
+    let wanted: BTreeSet<String> = issue_keys
+        .iter()
+        .map(|key| key.trim().to_uppercase())
+        .filter(|key| JiraClient::is_valid_issue_key(key))
+        .collect();
+
+    let account = storage::account(client);
+    let storage = app_handle.state::<Storage>();
+    let mut summaries = HashMap::new();
+    if let Some(assigned) = storage.load_issues(&account) {
+        for issue in assigned.value {
+            if wanted.contains(&issue.key) {
+                summaries.insert(issue.key, issue.fields.summary);
+            }
+        }
+    }
+
+    let cached = storage.load_summaries(&account);
+    let expired = Local::now() - Duration::days(MAX_AGE_DAYS);
+    let mut missing = Vec::new();
+    for key in &wanted {
+        if summaries.contains_key(key) {
+            continue;
+        }
+        match cached.get(key) {
+            Some(summary) => {
+                summaries.insert(key.clone(), summary.value.clone());
+                if summary.fetched_at < expired {
+                    missing.push(key.clone());
+                }
+            }
+            None => missing.push(key.clone()),
+        }
+    }
+    // Never looked up first, then the oldest.
+    missing.sort_by_key(|key| cached.get(key).map(|summary| summary.fetched_at));
+    missing.truncate(MAX_LOOKUPS);
+
+    let fetched = fetch(client, missing).await;
+    if !fetched.is_empty() {
+        if let Err(e) = storage.save_summaries(&account, &fetched, Local::now()) {
+            tracing::warn!("Failed to cache issue summaries: {}", e);
+        }
+    }
+    summaries.extend(fetched);
+    summaries
+}
+
+/// Looks up `issue_keys` a few at a time.
+async fn fetch(client: &JiraClient, issue_keys: Vec<String>) -> Vec<(String, String)> {
+    let mut pending = issue_keys.into_iter();
+    let mut tasks = JoinSet::new();
+    let mut fetched = Vec::new();
+    loop {
+        while tasks.len() < MAX_CONCURRENT {
+            let Some(issue_key) = pending.next() else {
+                break;
+            };
+            let client = client.clone();
+            tasks.spawn(async move {
+                let issue = client.get_issue(&issue_key, field_sets::SUMMARY, &[]).await;
+                (issue_key, issue)
+            });
+        }
+
+        let Some(joined) = tasks.join_next().await else {
+            break;
+        };
+        match joined {
+            Ok((_, Ok(issue))) => fetched.push((issue.key, issue.fields.summary)),
+            Ok((issue_key, Err(e))) => tracing::warn!("Failed to look up the summary of {}: {}", issue_key, e),
+            Err(e) => tracing::warn!("A summary lookup failed to finish: {}", e),
+        }
+    }
+    fetched
+}
+
+/// Summaries of `issue_keys` by key; see `resolve`.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn resolve_issue_summaries(
+    issue_keys: Vec<String>,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<HashMap<String, String>, JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    Ok(resolve(&app_handle, &client, &issue_keys).await)
+}
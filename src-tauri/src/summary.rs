@@ -0,0 +1,210 @@
+use chrono::{Datelike, Local, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::{AppHandle, State, Wry};
+
+use crate::analytics;
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::hierarchy::{self, ReportRollup};
+use crate::jira_types::UserWorklog;
+use crate::worklog_backend::{self, WorklogBackend};
+use crate::settings::{self, AppSettings};
+use crate::{client_for, JiraState};
+
+/// Longest range one summary covers.
+const MAX_SUMMARY_DAYS: i64 = 366;
+
+/// How exported rows are ordered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportGrouping {
+    #[default]
+    Date,
+    /// By client, unmapped projects last, then by date.
+    Client,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueTotal {
+    pub issue_key: String,
+    pub issue_summary: String,
+    /// From the `project_clients` setting.
+    pub client: Option<String>,
+    pub seconds: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientTotal {
+    /// `None` for projects not mapped to a client.
+    pub client: Option<String>,
+    pub seconds: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DaySummary {
+    pub date: NaiveDate,
+    pub seconds: u32,
+    /// The daily target on weekdays, 0 on weekends.
+    pub target_seconds: u32,
+    /// Most time first.
+    pub issues: Vec<IssueTotal>,
+    pub clients: Vec<ClientTotal>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorklogSummary {
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub daily_target_hours: f64,
+    pub total_seconds: u32,
+    pub target_seconds: u32,
+    /// Every day of the range, including days without worklogs.
+    pub days: Vec<DaySummary>,
+    /// Totals per issue over the whole range, most time first.
+    pub issues: Vec<IssueTotal>,
+    /// Totals per client over the whole range, most time first.
+    pub clients: Vec<ClientTotal>,
+}
+
+pub fn is_workday(date: NaiveDate) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// The local day a worklog was started on.
+fn local_date(worklog: &UserWorklog) -> Option<NaiveDate> {
+    JiraClient::parse_jira_datetime(&worklog.worklog.started)
+        .ok()
+        .map(|started| started.with_timezone(&Local).date_naive())
+}
+
+fn sorted_totals(totals: BTreeMap<&str, IssueTotal>) -> Vec<IssueTotal> {
+    let mut totals: Vec<IssueTotal> = totals.into_values().collect();
+    totals.sort_by(|a, b| b.seconds.cmp(&a.seconds).then_with(|| a.issue_key.cmp(&b.issue_key)));
+    totals
+}
+
+pub fn client_totals(issues: &[IssueTotal]) -> Vec<ClientTotal> {
+    let mut by_client: BTreeMap<Option<&str>, u32> = BTreeMap::new();
+    for issue in issues {
+        *by_client.entry(issue.client.as_deref()).or_default() += issue.seconds;
+    }
+
+    let mut totals: Vec<ClientTotal> = by_client
+        .into_iter()
+        .map(|(client, seconds)| ClientTotal {
+            client: client.map(str::to_string),
+            seconds,
+        })
+        .collect();
+    totals.sort_by_key(|total| std::cmp::Reverse(total.seconds));
+    totals
+}
+
+fn add<'a>(totals: &mut BTreeMap<&'a str, IssueTotal>, worklog: &'a UserWorklog, client: Option<&str>) {
+    totals
+        .entry(worklog.issue_key.as_str())
+        .or_insert_with(|| IssueTotal {
+            issue_key: worklog.issue_key.clone(),
+            issue_summary: worklog.issue_summary.clone(),
+            client: client.map(str::to_string),
+            seconds: 0,
+        })
+        .seconds += worklog.worklog.time_spent_seconds;
+}
+
+/// Groups `worklogs` by day, issue and client. Worklogs outside `[from, to]`
+/// (in local time) are left out.
+pub fn summarize(worklogs: &[UserWorklog], from: NaiveDate, to: NaiveDate, settings: &AppSettings) -> WorklogSummary {
+    let daily_target = settings.daily_target_seconds();
+    let mut per_day: BTreeMap<NaiveDate, BTreeMap<&str, IssueTotal>> = BTreeMap::new();
+    let mut overall_by_key = BTreeMap::new();
+
+    for worklog in worklogs {
+        let Some(date) = local_date(worklog).filter(|date| (from..=to).contains(date)) else {
+            continue;
+        };
+        let client = settings.client_of(&worklog.issue_key);
+        add(per_day.entry(date).or_default(), worklog, client);
+        add(&mut overall_by_key, worklog, client);
+    }
+
+    let overall = sorted_totals(overall_by_key);
+    let days: Vec<DaySummary> = from
+        .iter_days()
+        .take_while(|date| *date <= to)
+        .map(|date| {
+            let issues = per_day.remove(&date).map(sorted_totals).unwrap_or_default();
+            DaySummary {
+                date,
+                seconds: issues.iter().map(|issue| issue.seconds).sum(),
+                target_seconds: if is_workday(date) { daily_target } else { 0 },
+                clients: client_totals(&issues),
+                issues,
+            }
+        })
+        .collect();
+
+    WorklogSummary {
+        from_date: from,
+        to_date: to,
+        daily_target_hours: settings.daily_target_hours,
+        total_seconds: days.iter().map(|day| day.seconds).sum(),
+        target_seconds: days.iter().map(|day| day.target_seconds).sum(),
+        days,
+        clients: client_totals(&overall),
+        issues: overall,
+    }
+}
+
+/// The summary of `[from, to]` from the configured worklog backend.
+pub async fn fetch(
+    app_handle: &AppHandle<Wry>,
+    client: JiraClient,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<WorklogSummary, JiraError> {
+    fetch_rolled_up(app_handle, client, from, to, ReportRollup::None).await
+}
+
+/// Like `fetch`, with time counted towards parents as `rollup` says.
+pub async fn fetch_rolled_up(
+    app_handle: &AppHandle<Wry>,
+    client: JiraClient,
+    from: NaiveDate,
+    to: NaiveDate,
+    rollup: ReportRollup,
+) -> Result<WorklogSummary, JiraError> {
+    let settings = settings::effective(app_handle);
+    let backend = worklog_backend::for_app(app_handle, client.clone())?;
+    let mut worklogs = backend.user_worklogs(from, to).await?;
+    analytics::record_range(app_handle, &client, from, to, &worklogs);
+    hierarchy::apply(app_handle, &client, &mut worklogs, rollup).await;
+    Ok(summarize(&worklogs, from, to, &settings))
+}
+
+/// My logged time in `[from_date, to_date]` per day and issue, against the
+/// daily target. With a `rollup`, sub-tasks (or everything below an epic)
+/// count towards their parent.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_worklog_summary(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    rollup: Option<ReportRollup>,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<WorklogSummary, JiraError> {
+    if from_date > to_date {
+        return Err(JiraError::Validation("fromDate must not be after toDate".to_string()));
+    }
+    if (to_date - from_date).num_days() >= MAX_SUMMARY_DAYS {
+        return Err(JiraError::Validation(format!(
+            "Summaries cover at most {} days",
+            MAX_SUMMARY_DAYS
+        )));
+    }
+
+    let client = client_for(&state, connection.as_deref()).await?;
+    fetch_rolled_up(&app_handle, client, from_date, to_date, rollup.unwrap_or_default()).await
+}
@@ -0,0 +1,217 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+
+use crate::error::JiraError;
+use crate::jira_api::field_sets;
+use crate::jira_types::JiraIssue;
+use crate::notifications::NotificationKind;
+use crate::{capabilities, connected_client, notifications, settings, sla_warnings, JiraState};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IssueSnapshot {
+    pub key: String,
+    pub summary: String,
+    pub status: String,
+}
+
+impl From<&JiraIssue> for IssueSnapshot {
+    fn from(issue: &JiraIssue) -> Self {
+        Self {
+            key: issue.key.clone(),
+            summary: issue.fields.summary.clone(),
+            status: issue.fields.status.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueStatusChange {
+    pub issue: IssueSnapshot,
+    pub from: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct IssueChanges {
+    pub assigned: Vec<IssueSnapshot>,
+    pub status_changed: Vec<IssueStatusChange>,
+    pub removed: Vec<IssueSnapshot>,
+}
+
+impl IssueChanges {
+    fn is_empty(&self) -> bool {
+        self.assigned.is_empty() && self.status_changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+#[derive(Default)]
+pub struct SyncState {
+    /// `None` until the first successful poll, which only sets the baseline.
+    snapshot: Mutex<Option<HashMap<String, IssueSnapshot>>>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+fn diff(previous: &HashMap<String, IssueSnapshot>, current: &HashMap<String, IssueSnapshot>) -> IssueChanges {
+    let mut changes = IssueChanges::default();
+
+    for (key, issue) in current {
+        match previous.get(key) {
+            None => changes.assigned.push(issue.clone()),
+            Some(old) if old.status != issue.status => changes.status_changed.push(IssueStatusChange {
+                issue: issue.clone(),
+                from: old.status.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (key, issue) in previous {
+        if !current.contains_key(key) {
+            changes.removed.push(issue.clone());
+        }
+    }
+
+    changes.assigned.sort_by(|a, b| a.key.cmp(&b.key));
+    changes.status_changed.sort_by(|a, b| a.issue.key.cmp(&b.issue.key));
+    changes.removed.sort_by(|a, b| a.key.cmp(&b.key));
+    changes
+}
+
+fn emit<T: Serialize + Clone>(app_handle: &AppHandle<Wry>, event: &str, payload: &T) {
+    if let Err(e) = app_handle.emit(event, payload.clone()) {
+        tracing::warn!("Failed to emit {} event: {}", event, e);
+    }
+}
+
+fn announce(app_handle: &AppHandle<Wry>, changes: &IssueChanges, notify: bool) {
+    for issue in &changes.assigned {
+        emit(app_handle, "issue-assigned", issue);
+        if notify {
+            notifications::notify(app_handle, NotificationKind::Sync, &format!("Assigned: {}", issue.key), &issue.summary);
+        }
+    }
+    for change in &changes.status_changed {
+        emit(app_handle, "issue-status-changed", change);
+        if notify {
+            notifications::notify(
+                app_handle,
+                NotificationKind::Sync,
+                &format!("{}: {} → {}", change.issue.key, change.from, change.issue.status),
+                &change.issue.summary,
+            );
+        }
+    }
+    for issue in &changes.removed {
+        emit(app_handle, "issue-removed", issue);
+        if notify {
+            notifications::notify(app_handle, NotificationKind::Sync, &format!("No longer assigned: {}", issue.key), &issue.summary);
+        }
+    }
+}
+
+/// Fetches assigned issues and compares them with the previous snapshot,
+/// then refreshes the SLA deadlines of service desk requests.
+pub async fn poll(app_handle: &AppHandle<Wry>) -> Result<IssueChanges, JiraError> {
+    let client = connected_client(&app_handle.state::<JiraState>()).await?;
+    let issues = client.get_assigned_issues(field_sets::SYNC).await?;
+    let current: HashMap<String, IssueSnapshot> = issues
+        .iter()
+        .map(|issue| (issue.key.clone(), IssueSnapshot::from(issue)))
+        .collect();
+
+    let state = app_handle.state::<JiraState>();
+    let previous = state
+        .sync()
+        .snapshot
+        .lock()
+        .map_err(|e| e.to_string())?
+        .replace(current.clone());
+
+    let changes = match previous {
+        Some(previous) => diff(&previous, &current),
+        None => IssueChanges::default(),
+    };
+    let settings = settings::current(app_handle);
+    if !changes.is_empty() {
+        announce(app_handle, &changes, settings.issue_sync.notify);
+    }
+    // Sites without Service Management have no SLAs to watch.
+    if settings.sla_warnings.enabled && capabilities::active(app_handle).await.is_none_or(|capabilities| capabilities.service_desk) {
+        if let Err(e) = sla_warnings::refresh(app_handle, &client).await {
+            tracing::warn!("Failed to refresh SLA deadlines: {}", e);
+        }
+    }
+    Ok(changes)
+}
+
+async fn run(app_handle: AppHandle<Wry>, minutes: u32) {
+    let mut interval = interval(Duration::from_secs(minutes as u64 * 60));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+        if connected_client(&app_handle.state::<JiraState>()).await.is_err() {
+            continue;
+        }
+        if let Err(e) = poll(&app_handle).await {
+            tracing::warn!("Failed to sync issues: {}", e);
+        }
+    }
+}
+
+/// (Re)starts the polling task with the configured interval; an interval of
+/// zero stops it.
+pub fn restart(app_handle: &AppHandle<Wry>) {
+    let minutes = settings::current(app_handle).issue_sync.interval_minutes;
+    let state = app_handle.state::<JiraState>();
+    let mut task = state.sync().task.lock().unwrap_or_else(|e| e.into_inner());
+
+    if let Some(handle) = task.take() {
+        handle.abort();
+    }
+    if minutes > 0 {
+        *task = Some(tauri::async_runtime::spawn(run(app_handle.clone(), minutes)));
+    }
+}
+
+/// Forgets the snapshot, e.g. after switching accounts, so the next poll sets
+/// a new baseline instead of reporting every issue as changed.
+pub fn reset(app_handle: &AppHandle<Wry>) {
+    let state = app_handle.state::<JiraState>();
+    *state.sync().snapshot.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    sla_warnings::clear(app_handle);
+}
+
+/// Issues in the snapshot (`None` before the first poll) and whether
+/// polling is switched on.
+pub fn status(app_handle: &AppHandle<Wry>) -> (Option<usize>, bool) {
+    let state = app_handle.state::<JiraState>();
+    let sync = state.sync();
+    let tracked = sync
+        .snapshot
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(HashMap::len);
+    let running = sync
+        .task
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_some();
+    (tracked, running)
+}
+
+#[tauri::command]
+pub async fn sync_issues_now(app_handle: AppHandle<Wry>) -> Result<IssueChanges, JiraError> {
+    poll(&app_handle).await
+}
+
+#[tauri::command]
+pub async fn get_issue_snapshot(state: State<'_, JiraState>) -> Result<Vec<IssueSnapshot>, String> {
+    let snapshot = state.sync().snapshot.lock().map_err(|e| e.to_string())?;
+    let mut issues: Vec<IssueSnapshot> = snapshot.iter().flat_map(|s| s.values().cloned()).collect();
+    issues.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(issues)
+}
@@ -0,0 +1,262 @@
+//! A read-only configuration an admin publishes at an HTTPS URL: recommended
+//! issue filters, the project to client mapping and the team's rounding
+//! policy. It has to be signed with the Ed25519 key in
+//! `team_config.public_key`, and personal settings win wherever both say
+//! something. The last verified copy is kept for offline starts.
+
+use base64::Engine;
+use chrono::{DateTime, Local};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::settings::{self, AppSettings, Rounding, WorklogRules};
+use crate::{http, offline, store};
+
+const TEAM_CONFIG_FILE: &str = "team_config.json";
+
+pub type TeamConfigState = Mutex<Option<CachedTeamConfig>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecommendedFilter {
+    pub name: String,
+    pub jql: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundingPolicy {
+    /// Logged time is a multiple of this.
+    pub minutes: u32,
+    #[serde(default)]
+    pub mode: Rounding,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TeamConfig {
+    pub filters: Vec<RecommendedFilter>,
+    /// JIRA project key to client, under the personal `project_clients`.
+    pub project_clients: BTreeMap<String, String>,
+    pub rounding: Option<RoundingPolicy>,
+}
+
+impl TeamConfig {
+    /// Fills in what `settings` leaves open.
+    pub fn merge_into(&self, settings: &mut AppSettings) {
+        for (project, client) in &self.project_clients {
+            settings
+                .project_clients
+                .entry(project.clone())
+                .or_insert_with(|| client.clone());
+        }
+        // Only rounding: the personal limits and weekend confirmation stay
+        // off unless they were turned on.
+        if let Some(policy) = self.rounding.as_ref().filter(|policy| policy.minutes > 0) {
+            let rules = &mut settings.worklog_rules;
+            if !rules.enabled {
+                *rules = WorklogRules {
+                    enabled: true,
+                    round_to_minutes: 0,
+                    round_start: false,
+                    max_minutes: 0,
+                    confirm_weekends: false,
+                    ..WorklogRules::default()
+                };
+            }
+            if rules.round_to_minutes == 0 {
+                rules.round_to_minutes = policy.minutes;
+                rules.rounding = policy.mode;
+            }
+        }
+    }
+}
+
+/// What the URL serves: the configuration as a JSON string and a base64
+/// Ed25519 signature over its UTF-8 bytes.
+#[derive(Debug, Deserialize)]
+struct SignedConfig {
+    config: String,
+    signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedTeamConfig {
+    pub url: String,
+    pub fetched_at: DateTime<Local>,
+    pub config: TeamConfig,
+}
+
+pub fn public_key(encoded: &str) -> Result<VerifyingKey, String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| format!("The team config key is not base64: {}", e))?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| "The team config key must be a 32-byte Ed25519 public key".to_string())?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("Invalid team config key: {}", e))
+}
+
+/// Checks the signature of a served configuration and reads it.
+fn verify(body: &str, key: &VerifyingKey) -> Result<TeamConfig, String> {
+    let signed: SignedConfig =
+        serde_json::from_str(body).map_err(|e| format!("The team config is not a signed configuration: {}", e))?;
+    let signature = base64::engine::general_purpose::STANDARD
+        .decode(signed.signature.trim())
+        .map_err(|e| format!("The team config signature is not base64: {}", e))?;
+    let signature = Signature::from_slice(&signature).map_err(|e| format!("Invalid team config signature: {}", e))?;
+    key.verify(signed.config.as_bytes(), &signature)
+        .map_err(|_| "The team config signature does not match its key".to_string())?;
+
+    serde_json::from_str(&signed.config).map_err(|e| format!("Invalid team config: {}", e))
+}
+
+pub fn load(app_handle: &AppHandle<Wry>) -> Option<CachedTeamConfig> {
+    let path = store::data_file(app_handle, TEAM_CONFIG_FILE).ok()?;
+    store::load_json(&path)
+}
+
+fn store_cached(app_handle: &AppHandle<Wry>, cached: Option<CachedTeamConfig>) -> Result<(), String> {
+    let path = store::data_file(app_handle, TEAM_CONFIG_FILE)?;
+    store::save_json(&path, &cached)?;
+    *app_handle.state::<TeamConfigState>().lock().map_err(|e| e.to_string())? = cached;
+    Ok(())
+}
+
+/// The team configuration for the configured URL, if one was verified.
+pub fn current(app_handle: &AppHandle<Wry>) -> Option<TeamConfig> {
+    let url = settings::current(app_handle).team_config.url?;
+    let state = app_handle.state::<TeamConfigState>();
+    let cached = state.lock().unwrap_or_else(|e| e.into_inner());
+    cached.as_ref().filter(|cached| cached.url == url).map(|cached| cached.config.clone())
+}
+
+/// Fetches and verifies the configuration at the configured URL. On failure
+/// the last verified copy stays in use.
+pub async fn refresh(app_handle: &AppHandle<Wry>) -> Result<Option<CachedTeamConfig>, String> {
+    let team = settings::current(app_handle).team_config;
+    let (Some(url), Some(key)) = (team.url, team.public_key) else {
+        store_cached(app_handle, None)?;
+        return Ok(None);
+    };
+    let key = public_key(&key)?;
+
+    offline::check(app_handle).map_err(|e| e.to_string())?;
+    let response = http::client()?
+        .get(&url)
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch the team config: {}", e))?;
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read the team config: {}", e))?;
+
+    let cached = CachedTeamConfig {
+        url,
+        fetched_at: Local::now(),
+        config: verify(&body, &key)?,
+    };
+    store_cached(app_handle, Some(cached.clone()))?;
+    Ok(Some(cached))
+}
+
+/// Refreshes in the background, e.g. on startup.
+pub fn spawn_refresh(app_handle: &AppHandle<Wry>) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = refresh(&app_handle).await {
+            tracing::warn!("{}", e);
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_team_config(app_handle: AppHandle<Wry>, state: State<'_, TeamConfigState>) -> Result<Option<CachedTeamConfig>, String> {
+    let url = settings::current(&app_handle).team_config.url;
+    let cached = state.lock().map_err(|e| e.to_string())?;
+    Ok(cached.clone().filter(|cached| Some(&cached.url) == url.as_ref()))
+}
+
+#[tauri::command]
+pub async fn refresh_team_config(app_handle: AppHandle<Wry>) -> Result<Option<CachedTeamConfig>, String> {
+    refresh(&app_handle).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn signed(key: &SigningKey, config: &str) -> String {
+        let signature = base64::engine::general_purpose::STANDARD.encode(key.sign(config.as_bytes()).to_bytes());
+        serde_json::json!({ "config": config, "signature": signature }).to_string()
+    }
+
+    #[test]
+    fn accepts_only_configs_signed_with_the_key() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let config = r#"{"project_clients":{"APP":"Acme"},"rounding":{"minutes":15}}"#;
+
+        let verified = verify(&signed(&key, config), &key.verifying_key()).unwrap();
+        assert_eq!(verified.project_clients["APP"], "Acme");
+        assert_eq!(verified.rounding.unwrap().mode, Rounding::Nearest);
+
+        let tampered = signed(&key, config).replace("Acme", "Evil");
+        assert!(verify(&tampered, &key.verifying_key()).is_err());
+
+        let other = SigningKey::from_bytes(&[8; 32]);
+        assert!(verify(&signed(&other, config), &key.verifying_key()).is_err());
+    }
+
+    #[test]
+    fn personal_mappings_win() {
+        let mut settings = AppSettings::default();
+        settings.project_clients.insert("APP".to_string(), "Mine".to_string());
+        let team = TeamConfig {
+            project_clients: [("APP", "Team"), ("WEB", "Team")]
+                .map(|(project, client)| (project.to_string(), client.to_string()))
+                .into(),
+            ..Default::default()
+        };
+        team.merge_into(&mut settings);
+
+        assert_eq!(settings.project_clients["APP"], "Mine");
+        assert_eq!(settings.project_clients["WEB"], "Team");
+    }
+
+    #[test]
+    fn team_rounding_fills_in_for_mine() {
+        let team = TeamConfig {
+            rounding: Some(RoundingPolicy {
+                minutes: 30,
+                mode: Rounding::Up,
+            }),
+            ..Default::default()
+        };
+
+        let mut off = AppSettings::default();
+        team.merge_into(&mut off);
+        let rules = &off.worklog_rules;
+        assert!(rules.enabled);
+        assert_eq!((rules.round_to_minutes, rules.rounding), (30, Rounding::Up));
+        assert_eq!((rules.max_minutes, rules.confirm_weekends, rules.round_start), (0, false, false));
+
+        let mut unset = AppSettings::default();
+        unset.worklog_rules.enabled = true;
+        unset.worklog_rules.round_to_minutes = 0;
+        team.merge_into(&mut unset);
+        assert_eq!(unset.worklog_rules.round_to_minutes, 30);
+        assert_eq!(unset.worklog_rules.max_minutes, 12 * 60);
+
+        let mut mine = AppSettings::default();
+        mine.worklog_rules.enabled = true;
+        team.merge_into(&mut mine);
+        assert_eq!((mine.worklog_rules.round_to_minutes, mine.worklog_rules.rounding), (15, Rounding::Nearest));
+    }
+}
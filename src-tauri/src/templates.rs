@@ -0,0 +1,218 @@
+//! Worklog templates for entries logged over and over, like "Daily standup
+//! 15m" or "Code review 1h". Favorites are also offered in the tray menu.
+
+use chrono::{Duration, NaiveDate, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::adf::CommentInput;
+use crate::closed_guard;
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::offline_queue::{self, WorklogSubmission};
+use crate::notifications::NotificationKind;
+use crate::{client_for, clock, notifications, settings, store, tray, vault, worklog_rules, JiraState};
+
+pub const TEMPLATES_FILE: &str = "worklog_templates.json";
+
+pub type TemplateState = Mutex<Vec<WorklogTemplate>>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorklogTemplate {
+    pub id: u64,
+    pub name: String,
+    pub issue_key: String,
+    pub description: CommentInput,
+    /// A duration as `create_worklog` takes it, e.g. `15m`.
+    pub time_spent: String,
+    /// When the entry starts; without one it ends now, or starts at
+    /// `backfill.day_start` on other days.
+    #[serde(default)]
+    pub start: Option<NaiveTime>,
+    /// Offered in the tray menu.
+    #[serde(default)]
+    pub favorite: bool,
+}
+
+/// A template to save; without an `id` it is added as a new one.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateInput {
+    pub id: Option<u64>,
+    pub name: String,
+    pub issue_key: String,
+    pub description: CommentInput,
+    pub time_spent: String,
+    #[serde(default)]
+    pub start: Option<NaiveTime>,
+    #[serde(default)]
+    pub favorite: bool,
+}
+
+fn read(app_handle: &AppHandle<Wry>) -> Vec<WorklogTemplate> {
+    match store::config_file(app_handle, TEMPLATES_FILE) {
+        Ok(path) => vault::load(app_handle, &path),
+        Err(_) => Vec::new(),
+    }
+}
+
+pub fn load(app_handle: &AppHandle<Wry>) -> TemplateState {
+    Mutex::new(read(app_handle))
+}
+
+/// Reads the templates again once the vault is unlocked.
+pub fn reload(app_handle: &AppHandle<Wry>) {
+    let templates = read(app_handle);
+    let state = app_handle.state::<TemplateState>();
+    *state.lock().unwrap_or_else(|e| e.into_inner()) = templates;
+    tray::refresh_menu(app_handle);
+}
+
+fn persist(app_handle: &AppHandle<Wry>, templates: &[WorklogTemplate]) -> Result<(), String> {
+    let path = store::config_file(app_handle, TEMPLATES_FILE)?;
+    vault::save(app_handle, &path, &templates)
+}
+
+/// Favorite templates as `(id, name)`, for the tray menu.
+pub fn favorites(app_handle: &AppHandle<Wry>) -> Vec<(u64, String)> {
+    let state = app_handle.state::<TemplateState>();
+    let templates = state.lock().unwrap_or_else(|e| e.into_inner());
+    templates
+        .iter()
+        .filter(|template| template.favorite)
+        .map(|template| (template.id, template.name.clone()))
+        .collect()
+}
+
+#[tauri::command]
+pub async fn list_templates(state: State<'_, TemplateState>) -> Result<Vec<WorklogTemplate>, String> {
+    let templates = state.lock().map_err(|e| e.to_string())?;
+    Ok(templates.clone())
+}
+
+#[tauri::command]
+pub async fn save_template(template: TemplateInput, app_handle: AppHandle<Wry>) -> Result<WorklogTemplate, String> {
+    let name = template.name.trim().to_string();
+    if name.is_empty() {
+        return Err("The template needs a name".to_string());
+    }
+    let issue_key = template.issue_key.trim().to_uppercase();
+    if !JiraClient::is_valid_issue_key(&issue_key) {
+        return Err(format!("Invalid issue key: {}", issue_key));
+    }
+    JiraClient::parse_time_to_seconds(&template.time_spent, &settings::current(&app_handle).time_units)?;
+
+    let state = app_handle.state::<TemplateState>();
+    let mut templates = state.lock().map_err(|e| e.to_string())?;
+    let id = match template.id {
+        Some(id) if !templates.iter().any(|existing| existing.id == id) => {
+            return Err(format!("No template {}", id));
+        }
+        Some(id) => id,
+        None => templates.iter().map(|existing| existing.id).max().unwrap_or(0) + 1,
+    };
+    let saved = WorklogTemplate {
+        id,
+        name,
+        issue_key,
+        description: template.description,
+        time_spent: template.time_spent.trim().to_string(),
+        start: template.start,
+        favorite: template.favorite,
+    };
+    match templates.iter_mut().find(|existing| existing.id == id) {
+        Some(existing) => *existing = saved.clone(),
+        None => templates.push(saved.clone()),
+    }
+    persist(&app_handle, &templates)?;
+    drop(templates);
+
+    tray::refresh_menu(&app_handle);
+    Ok(saved)
+}
+
+#[tauri::command]
+pub async fn delete_template(id: u64, app_handle: AppHandle<Wry>) -> Result<(), String> {
+    let state = app_handle.state::<TemplateState>();
+    let mut templates = state.lock().map_err(|e| e.to_string())?;
+    let before = templates.len();
+    templates.retain(|template| template.id != id);
+    if templates.len() == before {
+        return Err(format!("No template {}", id));
+    }
+    persist(&app_handle, &templates)?;
+    drop(templates);
+
+    tray::refresh_menu(&app_handle);
+    Ok(())
+}
+
+/// JIRA timestamp `template` starts at on `date`.
+fn started(app_handle: &AppHandle<Wry>, template: &WorklogTemplate, date: NaiveDate, seconds: u32) -> Result<String, JiraError> {
+    let now = clock::now(app_handle);
+    let start = match template.start {
+        Some(start) => date.and_time(start),
+        None if date == now.date_naive() => {
+            let start = now.naive_local() - Duration::seconds(seconds as i64);
+            start.max(date.and_time(NaiveTime::MIN))
+        }
+        None => date.and_time(settings::current(app_handle).backfill.day_start),
+    };
+    let start = chrono::Local
+        .from_local_datetime(&start)
+        .earliest()
+        .ok_or_else(|| JiraError::Validation(format!("{} does not exist in the local time zone", start)))?;
+    Ok(JiraClient::format_jira_datetime(&start))
+}
+
+/// Logs template `template_id` on `date`, today unless given. `confirmed`
+/// logs it even if the issue is done or it is a weekend; see
+/// `closed_issue_guard` and `worklog_rules`.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn log_from_template(
+    template_id: u64,
+    date: Option<NaiveDate>,
+    confirmed: Option<bool>,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<WorklogSubmission, JiraError> {
+    let template = {
+        let templates = app_handle.state::<TemplateState>();
+        let templates = templates.lock().map_err(|e| e.to_string())?;
+        templates
+            .iter()
+            .find(|template| template.id == template_id)
+            .cloned()
+            .ok_or_else(|| JiraError::NotFound(format!("No template {}", template_id)))?
+    };
+
+    let client = client_for(&state, connection.as_deref()).await?;
+    let seconds = JiraClient::parse_time_to_seconds(&template.time_spent, &settings::current(&app_handle).time_units)?;
+    let date = date.unwrap_or_else(|| clock::now(&app_handle).date_naive());
+    let started = started(&app_handle, &template, date, seconds)?;
+    let confirmed = confirmed.unwrap_or(false);
+    let (started, seconds) = worklog_rules::normalize(&app_handle, &started, seconds, confirmed)?;
+    closed_guard::check(&app_handle, &client, &template.issue_key, confirmed).await?;
+
+    offline_queue::submit(&app_handle, &client, connection.as_deref(), &template.issue_key, &template.description, &started, seconds).await
+}
+
+/// The tray menu's "Log Template" entries.
+pub fn log_from_tray(app_handle: &AppHandle<Wry>, template_id: u64) {
+    let name = favorites(app_handle)
+        .into_iter()
+        .find(|(id, _)| *id == template_id)
+        .map_or_else(|| "Log template".to_string(), |(_, name)| name);
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app_handle.state::<JiraState>();
+        let body = match log_from_template(template_id, None, None, None, app_handle.clone(), state).await {
+            Ok(WorklogSubmission::Created { .. }) => "Logged".to_string(),
+            Ok(WorklogSubmission::Queued { .. }) => "Queued until JIRA is reachable".to_string(),
+            Err(e) => e.to_string(),
+        };
+        notifications::notify(&app_handle, NotificationKind::Feedback, &name, &body);
+    });
+}
@@ -0,0 +1,455 @@
+//! Tempo Timesheets, for teams that log time through Tempo instead of native
+//! JIRA worklogs. Tempo still needs the JIRA connection: it refers to issues
+//! and users by their JIRA ids.
+
+use chrono::{NaiveDate, NaiveTime, Timelike};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State, Wry};
+
+use crate::adf;
+use crate::error::JiraError;
+use crate::jira_api::{field_sets, JiraClient};
+use crate::jira_types::{UserWorklog, Worklog, WorklogResponse};
+use crate::offline::OfflineFlag;
+use crate::settings::{self, TempoSettings};
+use crate::worklog_backend::WorklogBackend;
+use crate::{connected_client, credentials, http, offline, JiraState};
+
+const PAGE_LIMIT: u32 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TempoAttributeValue {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TempoAccount {
+    pub id: u64,
+    pub key: String,
+    pub name: String,
+    /// `OPEN`, `CLOSED` or `ARCHIVED`.
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TempoWorkAttribute {
+    pub key: String,
+    pub name: String,
+    /// `ACCOUNT`, `STATIC_LIST`, `INPUT_FIELD`, ...
+    #[serde(rename = "type")]
+    pub attribute_type: String,
+    #[serde(default)]
+    pub required: bool,
+    /// Choices of `STATIC_LIST` attributes.
+    #[serde(default)]
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TempoWorklogRequest {
+    author_account_id: String,
+    issue_id: u64,
+    time_spent_seconds: u32,
+    start_date: NaiveDate,
+    start_time: NaiveTime,
+    description: String,
+    attributes: Vec<TempoAttributeValue>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TempoIssueRef {
+    pub id: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TempoWorklog {
+    pub tempo_worklog_id: u64,
+    pub issue: TempoIssueRef,
+    pub time_spent_seconds: u32,
+    pub start_date: NaiveDate,
+    pub start_time: Option<NaiveTime>,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Deserialize)]
+struct TempoPage<T> {
+    results: Vec<T>,
+    metadata: TempoMetadata,
+}
+
+#[derive(Deserialize)]
+struct TempoMetadata {
+    count: u32,
+    next: Option<String>,
+}
+
+/// Body of Tempo error responses.
+#[derive(Deserialize)]
+struct TempoErrorBody {
+    errors: Vec<TempoErrorMessage>,
+}
+
+#[derive(Deserialize)]
+struct TempoErrorMessage {
+    message: String,
+}
+
+#[derive(Clone)]
+pub struct TempoClient {
+    api_url: String,
+    token: String,
+    client: reqwest::Client,
+    offline: OfflineFlag,
+}
+
+impl TempoClient {
+    pub fn new(api_url: &str, token: String, offline: OfflineFlag) -> Result<Self, String> {
+        Ok(Self {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            token,
+            client: http::client()?,
+            offline,
+        })
+    }
+
+    async fn check(response: reqwest::Response) -> Result<reqwest::Response, JiraError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let retry_after_secs = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<TempoErrorBody>(&body)
+            .ok()
+            .map(|body| body.errors.into_iter().map(|e| e.message).collect::<Vec<_>>().join("; "))
+            .filter(|message| !message.is_empty());
+        Err(JiraError::with_message(status, retry_after_secs, message))
+    }
+
+    /// Follows `offset`/`limit` pagination of a list endpoint.
+    async fn get_all<T: DeserializeOwned>(
+        &self,
+        endpoint: &'static str,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<Vec<T>, JiraError> {
+        self.offline.check()?;
+        let url = format!("{}{}", self.api_url, path);
+        let mut results = Vec::new();
+
+        loop {
+            let mut params = query.to_vec();
+            params.push(("offset", results.len().to_string()));
+            params.push(("limit", PAGE_LIMIT.to_string()));
+
+            let response = self.client
+                .get(&url)
+                .header("Accept", "application/json")
+                .header("Accept-Encoding", "gzip")
+                .bearer_auth(&self.token)
+                .query(&params)
+                .send()
+                .await?;
+
+            let response = Self::check(response).await?;
+
+            let page: TempoPage<T> = JiraClient::read_json(endpoint, response).await?;
+            results.extend(page.results);
+
+            if page.metadata.count == 0 || page.metadata.next.is_none() {
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+
+    async fn create_worklog(&self, request: &TempoWorklogRequest) -> Result<TempoWorklog, JiraError> {
+        self.offline.check()?;
+        let url = format!("{}/worklogs", self.api_url);
+
+        let response = self.client
+            .post(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .header("Content-Type", "application/json")
+            .bearer_auth(&self.token)
+            .json(request)
+            .send()
+            .await?;
+
+        let response = Self::check(response).await?;
+
+        let worklog: TempoWorklog = JiraClient::read_json("tempo_create_worklog", response).await?;
+        Ok(worklog)
+    }
+
+    /// Worklogs of `account_id` in `[from, to]`.
+    pub async fn get_user_worklogs(
+        &self,
+        account_id: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<TempoWorklog>, JiraError> {
+        let query = [("from", from.to_string()), ("to", to.to_string())];
+        self.get_all("tempo_worklogs", &format!("/worklogs/user/{}", account_id), &query)
+            .await
+    }
+
+    pub async fn get_worklog(&self, tempo_worklog_id: u64) -> Result<TempoWorklog, JiraError> {
+        self.offline.check()?;
+        let url = format!("{}/worklogs/{}", self.api_url, tempo_worklog_id);
+
+        let response = self.client
+            .get(&url)
+            .header("Accept", "application/json")
+            .header("Accept-Encoding", "gzip")
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+
+        let response = Self::check(response).await?;
+
+        let worklog: TempoWorklog = JiraClient::read_json("tempo_worklog", response).await?;
+        Ok(worklog)
+    }
+
+    pub async fn delete_worklog(&self, tempo_worklog_id: u64) -> Result<(), JiraError> {
+        self.offline.check()?;
+        let url = format!("{}/worklogs/{}", self.api_url, tempo_worklog_id);
+
+        let response = self.client
+            .delete(&url)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+
+        Self::check(response).await?;
+
+        Ok(())
+    }
+
+    pub async fn get_accounts(&self) -> Result<Vec<TempoAccount>, JiraError> {
+        self.get_all("tempo_accounts", "/accounts", &[]).await
+    }
+
+    pub async fn get_work_attributes(&self) -> Result<Vec<TempoWorkAttribute>, JiraError> {
+        self.get_all("tempo_work_attributes", "/work-attributes", &[]).await
+    }
+}
+
+/// Logs time through Tempo, with JIRA for the issue and user lookups.
+#[derive(Clone)]
+pub struct TempoBackend {
+    pub jira: JiraClient,
+    pub tempo: TempoClient,
+    pub settings: TempoSettings,
+}
+
+impl TempoBackend {
+    pub fn new(jira: JiraClient, settings: TempoSettings) -> Result<Self, JiraError> {
+        let token = credentials::load_tempo_token()?.ok_or_else(|| {
+            JiraError::Unauthorized {
+                status: None,
+                message: "No Tempo API token saved".to_string(),
+            }
+        })?;
+        let tempo = TempoClient::new(&settings.api_url, token, jira.offline().clone())?;
+
+        Ok(Self { jira, tempo, settings })
+    }
+
+    fn attributes(&self) -> Vec<TempoAttributeValue> {
+        let account = self.settings.default_account.as_ref().map(|account| TempoAttributeValue {
+            key: self.settings.account_attribute_key.clone(),
+            value: account.clone(),
+        });
+        // The default account wins over a stale account in `attributes`.
+        let mut attributes: Vec<TempoAttributeValue> = self
+            .settings
+            .attributes
+            .iter()
+            .filter(|attribute| account.as_ref().is_none_or(|account| account.key != attribute.key))
+            .cloned()
+            .collect();
+        attributes.extend(account);
+        attributes
+    }
+
+    /// The worklog Tempo is sent for `issue_key`, with the issue id and
+    /// author looked up in JIRA.
+    async fn worklog_request(
+        &self,
+        issue_key: &str,
+        comment: &adf::Document,
+        started: &str,
+        time_spent_seconds: u32,
+    ) -> Result<(TempoWorklogRequest, String), JiraError> {
+        let issue = self.jira.get_issue(issue_key, field_sets::WORKLOG_ISSUES, &[]).await?;
+        let issue_id = issue
+            .id
+            .parse()
+            .map_err(|_| JiraError::Deserialize(format!("{} has no numeric id", issue_key)))?;
+        let author = self.jira.get_myself().await?;
+        // Tempo takes the wall-clock time of the person logging.
+        let started_at = JiraClient::parse_jira_datetime(started)?;
+
+        let request = TempoWorklogRequest {
+            author_account_id: author.account_id,
+            issue_id,
+            time_spent_seconds,
+            start_date: started_at.date_naive(),
+            // Tempo rejects fractional seconds.
+            start_time: started_at.time().with_nanosecond(0).unwrap_or_default(),
+            description: adf::text_of(&serde_json::to_value(comment)?),
+            attributes: self.attributes(),
+        };
+        Ok((request, issue.id))
+    }
+}
+
+impl WorklogBackend for TempoBackend {
+    async fn create_worklog(
+        &self,
+        issue_key: &str,
+        comment: &adf::Document,
+        started: &str,
+        time_spent_seconds: u32,
+    ) -> Result<WorklogResponse, JiraError> {
+        let (request, issue_id) = self.worklog_request(issue_key, comment, started, time_spent_seconds).await?;
+        let worklog = self.tempo.create_worklog(&request).await?;
+
+        Ok(WorklogResponse {
+            id: worklog.tempo_worklog_id.to_string(),
+            issue_id,
+            started: started.to_string(),
+            time_spent_seconds: worklog.time_spent_seconds,
+        })
+    }
+
+    async fn worklog_payload(
+        &self,
+        issue_key: &str,
+        comment: &adf::Document,
+        started: &str,
+        time_spent_seconds: u32,
+    ) -> Result<serde_json::Value, JiraError> {
+        let (request, _) = self.worklog_request(issue_key, comment, started, time_spent_seconds).await?;
+        Ok(serde_json::to_value(request)?)
+    }
+
+    async fn user_worklogs(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<UserWorklog>, JiraError> {
+        let author = self.jira.get_myself().await?;
+        let worklogs = self.tempo.get_user_worklogs(&author.account_id, from, to).await?;
+        if worklogs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut ids: Vec<String> = worklogs.iter().map(|worklog| worklog.issue.id.to_string()).collect();
+        ids.sort();
+        ids.dedup();
+        let jql = format!("id in ({})", ids.join(","));
+        let issues = self
+            .jira
+            .search_issues(&jql, field_sets::WORKLOG_ISSUES, 0, Some(ids.len() as u32))
+            .await?
+            .issues;
+
+        Ok(worklogs
+            .into_iter()
+            .filter_map(|worklog| {
+                let issue_id = worklog.issue.id.to_string();
+                let issue = issues.iter().find(|issue| issue.id == issue_id)?;
+                let started = worklog
+                    .start_date
+                    .and_time(worklog.start_time.unwrap_or_default())
+                    .and_local_timezone(chrono::Local)
+                    .earliest()?;
+
+                Some(UserWorklog {
+                    issue_key: issue.key.clone(),
+                    issue_summary: issue.fields.summary.clone(),
+                    worklog: Worklog {
+                        id: worklog.tempo_worklog_id.to_string(),
+                        issue_id,
+                        author: Some(author.clone()),
+                        comment: Some(serde_json::Value::String(worklog.description)),
+                        started: JiraClient::format_jira_datetime(&started),
+                        time_spent_seconds: worklog.time_spent_seconds,
+                    },
+                })
+            })
+            .collect())
+    }
+
+    async fn worklog_started(&self, _issue_key: &str, worklog_id: &str) -> Result<String, JiraError> {
+        let worklog = self.tempo.get_worklog(tempo_worklog_id(worklog_id)?).await?;
+        let started = worklog
+            .start_date
+            .and_time(worklog.start_time.unwrap_or_default())
+            .and_local_timezone(chrono::Local)
+            .earliest()
+            .ok_or_else(|| JiraError::Other(format!("Tempo worklog {} starts at a time that doesn't exist here", worklog_id)))?;
+        Ok(JiraClient::format_jira_datetime(&started))
+    }
+
+    async fn delete_worklog(&self, _issue_key: &str, worklog_id: &str) -> Result<(), JiraError> {
+        self.tempo.delete_worklog(tempo_worklog_id(worklog_id)?).await
+    }
+}
+
+fn tempo_worklog_id(worklog_id: &str) -> Result<u64, JiraError> {
+    worklog_id
+        .parse()
+        .map_err(|_| JiraError::Validation(format!("Not a Tempo worklog id: {}", worklog_id)))
+}
+
+async fn tempo_backend(app_handle: &AppHandle<Wry>, state: &JiraState) -> Result<TempoBackend, JiraError> {
+    TempoBackend::new(connected_client(state).await?, settings::current(app_handle).tempo)
+}
+
+/// Saves the Tempo API token after checking that Tempo accepts it.
+#[tauri::command]
+pub async fn set_tempo_token(token: String, app_handle: AppHandle<Wry>) -> Result<(), JiraError> {
+    let token = token.trim().to_string();
+    if token.is_empty() {
+        return Err(JiraError::Validation("The Tempo token must not be empty".to_string()));
+    }
+
+    let client = TempoClient::new(&settings::current(&app_handle).tempo.api_url, token.clone(), offline::flag(&app_handle))?;
+    client.get_work_attributes().await?;
+    credentials::save_tempo_token(&token)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn clear_tempo_token() -> Result<(), String> {
+    credentials::clear_tempo_token()
+}
+
+/// Accounts time can be booked on, for the account picker.
+#[tauri::command]
+pub async fn get_tempo_accounts(
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<TempoAccount>, JiraError> {
+    tempo_backend(&app_handle, &state).await?.tempo.get_accounts().await
+}
+
+#[tauri::command]
+pub async fn get_tempo_work_attributes(
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<TempoWorkAttribute>, JiraError> {
+    tempo_backend(&app_handle, &state).await?.tempo.get_work_attributes().await
+}
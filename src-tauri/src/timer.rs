@@ -0,0 +1,775 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+use tokio::time::{interval, Duration};
+
+use crate::adf::CommentInput;
+use crate::closed_guard;
+use crate::clock::{self, ClockState};
+use crate::jira_api::JiraClient;
+use crate::offline_queue::{self, WorklogSubmission};
+use crate::idle::IdleMonitor;
+use crate::pomodoro::{self, Pomodoro, PomodoroSnapshot};
+use crate::storage::Storage;
+use crate::notifications::NotificationKind;
+use crate::{breaks, connected_client, focus, interest, lunch, notifications, settings, tray, worklog_rules, JiraState};
+
+/// Remaining-time fractions at which a timebox notifies.
+const TIMEBOX_THRESHOLDS: [f64; 3] = [0.5, 0.1, 0.0];
+/// Ticks between idle checks; some idle backends go over D-Bus.
+const IDLE_CHECK_TICKS: u64 = 5;
+/// Idle time during the lunch window that pauses the timer for lunch.
+const LUNCH_AWAY_SECONDS: u64 = 3 * 60;
+
+pub type TimerState = Mutex<Option<ActiveTimer>>;
+
+#[derive(Debug, Clone)]
+pub struct Timebox {
+    pub duration_seconds: u64,
+    next_threshold: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ActiveTimer {
+    pub issue_key: String,
+    pub started: DateTime<Local>,
+    /// When the current running segment began; `None` while paused.
+    pub resumed_at: Option<DateTime<Local>>,
+    /// Seconds accumulated by segments before `resumed_at`.
+    pub accumulated_seconds: u64,
+    pub timebox: Option<Timebox>,
+    pub pomodoro: Option<Pomodoro>,
+    /// Start of the idle span that paused the timer, until the user decides
+    /// whether to keep it.
+    pub idle_since: Option<DateTime<Local>>,
+    /// Paused by `check_lunch`; runs again once I'm back.
+    pub paused_for_lunch: bool,
+    /// Finished segments, kept with the session when the timer ends.
+    pub segments: Vec<TimerSegment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentKind {
+    /// Counted by the timer.
+    Tracked,
+    /// Idle time added back with `resolve_idle`.
+    IdleKept,
+    /// Idle time left out: not kept, or away during lunch.
+    IdleTrimmed,
+    /// Counted, but the timer was thrown away with `discard_timer`.
+    Discarded,
+}
+
+impl SegmentKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SegmentKind::Tracked => "tracked",
+            SegmentKind::IdleKept => "idle_kept",
+            SegmentKind::IdleTrimmed => "idle_trimmed",
+            SegmentKind::Discarded => "discarded",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        [Self::Tracked, Self::IdleKept, Self::IdleTrimmed, Self::Discarded]
+            .into_iter()
+            .find(|known| known.as_str() == kind)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimerSegment {
+    pub kind: SegmentKind,
+    pub started: DateTime<Local>,
+    pub ended: DateTime<Local>,
+}
+
+impl ActiveTimer {
+    pub fn new(issue_key: String, now: DateTime<Local>) -> Self {
+        Self {
+            issue_key,
+            started: now,
+            resumed_at: Some(now),
+            accumulated_seconds: 0,
+            timebox: None,
+            pomodoro: None,
+            idle_since: None,
+            paused_for_lunch: false,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Ends the running segment at `until`, keeping its time.
+    pub fn pause_at(&mut self, until: DateTime<Local>) {
+        let Some(resumed_at) = self.resumed_at.take() else {
+            return;
+        };
+        let until = until.max(resumed_at);
+        self.accumulated_seconds += (until - resumed_at).num_seconds() as u64;
+        self.note(SegmentKind::Tracked, resumed_at, until);
+    }
+
+    /// Runs the timer again from `now`; idle time still waiting for
+    /// `resolve_idle` is dropped, so it can't be added on top later.
+    pub fn resume(&mut self, now: DateTime<Local>) {
+        if self.resumed_at.is_none() {
+            self.resumed_at = Some(now);
+        }
+        if let Some(idle_since) = self.idle_since.take() {
+            self.note(SegmentKind::IdleTrimmed, idle_since, now);
+        }
+        self.paused_for_lunch = false;
+    }
+
+    /// Records a segment without counting it.
+    pub fn note(&mut self, kind: SegmentKind, started: DateTime<Local>, ended: DateTime<Local>) {
+        if ended > started {
+            self.segments.push(TimerSegment { kind, started, ended });
+        }
+    }
+
+    pub fn elapsed_seconds(&self, now: DateTime<Local>) -> u64 {
+        let running = self
+            .resumed_at
+            .map(|resumed_at| (now - resumed_at).num_seconds().max(0) as u64)
+            .unwrap_or(0);
+        self.accumulated_seconds + running
+    }
+
+    pub fn snapshot(&self, now: DateTime<Local>) -> TimerSnapshot {
+        let elapsed_seconds = self.elapsed_seconds(now);
+        TimerSnapshot {
+            issue_key: self.issue_key.clone(),
+            started: JiraClient::format_jira_datetime(&self.started),
+            elapsed_seconds,
+            running: self.resumed_at.is_some(),
+            timebox_seconds: self.timebox.as_ref().map(|t| t.duration_seconds),
+            remaining_seconds: self
+                .timebox
+                .as_ref()
+                .map(|t| t.duration_seconds.saturating_sub(elapsed_seconds)),
+            pomodoro: self.pomodoro.as_ref().map(|p| p.snapshot(elapsed_seconds, now)),
+            idle_since: self.idle_since,
+            paused_for_lunch: self.paused_for_lunch,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct StoppedTimer {
+    pub timer: TimerSnapshot,
+    /// The submitted worklog when a description was given on stop.
+    pub worklog: Option<WorklogSubmission>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SwitchedTimer {
+    pub stopped: StoppedTimer,
+    /// The timer now running on the next issue.
+    pub started: TimerSnapshot,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimerSnapshot {
+    pub issue_key: String,
+    /// Start of the first segment, formatted for the worklog `started` field.
+    pub started: String,
+    pub elapsed_seconds: u64,
+    pub running: bool,
+    pub timebox_seconds: Option<u64>,
+    pub remaining_seconds: Option<u64>,
+    pub pomodoro: Option<PomodoroSnapshot>,
+    /// Set while idle time waits for `resolve_idle`.
+    pub idle_since: Option<DateTime<Local>>,
+    pub paused_for_lunch: bool,
+}
+
+/// Payload of `idle-detected`.
+#[derive(Debug, Clone, Serialize)]
+pub struct IdleDetected {
+    pub issue_key: String,
+    pub idle_since: DateTime<Local>,
+    pub timer: TimerSnapshot,
+}
+
+/// Closes `timer`'s segments at `now` and keeps them with its session;
+/// undecided idle time is left out. With `discarded` the counted segments
+/// are marked as thrown away.
+fn record_session(app_handle: &AppHandle<Wry>, mut timer: ActiveTimer, now: DateTime<Local>, discarded: bool) {
+    timer.pause_at(now);
+    if let Some(idle_since) = timer.idle_since.take() {
+        timer.note(SegmentKind::IdleTrimmed, idle_since, now);
+    }
+    if discarded {
+        for segment in &mut timer.segments {
+            if matches!(segment.kind, SegmentKind::Tracked | SegmentKind::IdleKept) {
+                segment.kind = SegmentKind::Discarded;
+            }
+        }
+    }
+    if let Err(e) = app_handle.state::<Storage>().save_timer_session(&timer.issue_key, timer.started, &timer.segments) {
+        tracing::warn!("Failed to record the timer session on {}: {}", timer.issue_key, e);
+    }
+}
+
+/// Records `timer`, ended at `now`, and emits `timer-stopped` so the
+/// frontend can open the worklog form pre-filled with the tracked time.
+fn finish(app_handle: &AppHandle<Wry>, timer: ActiveTimer, now: DateTime<Local>) -> TimerSnapshot {
+    let snapshot = timer.snapshot(now);
+    let timed = app_handle
+        .state::<Storage>()
+        .note_timed(timer.started.date_naive(), &snapshot.issue_key, snapshot.elapsed_seconds);
+    if let Err(e) = timed {
+        tracing::warn!("Failed to record the time tracked on {}: {}", snapshot.issue_key, e);
+    }
+    record_session(app_handle, timer, now, false);
+    if let Err(e) = app_handle.emit("timer-stopped", &snapshot) {
+        tracing::warn!("Failed to emit timer-stopped event: {}", e);
+    }
+    snapshot
+}
+
+/// Ends the active timer; see `finish`.
+pub fn stop_active(app_handle: &AppHandle<Wry>, state: &TimerState) -> Result<TimerSnapshot, String> {
+    let timer = {
+        let mut active = state.lock().map_err(|e| e.to_string())?;
+        active.take().ok_or_else(|| "No timer is running".to_string())?
+    };
+    Ok(finish(app_handle, timer, clock::now(app_handle)))
+}
+
+/// Tray quick action: stops a running timer, resumes a paused one, or asks
+/// the frontend to pick an issue when none exists, suggesting the top focus
+/// item. Returns whether the main window should be brought up.
+pub fn toggle_from_tray(app_handle: &AppHandle<Wry>) -> bool {
+    let state = app_handle.state::<JiraState>();
+    let running = {
+        let mut active = state.timer().lock().unwrap_or_else(|e| e.into_inner());
+        match active.as_mut() {
+            Some(timer) if timer.resumed_at.is_none() => {
+                timer.resume(clock::now(app_handle));
+                return false;
+            }
+            Some(_) => true,
+            None => false,
+        }
+    };
+
+    if running {
+        if let Err(e) = stop_active(app_handle, state.timer()) {
+            tracing::warn!("Failed to stop timer: {}", e);
+        }
+    } else if let Err(e) = app_handle.emit("timer-start-requested", focus::top(app_handle)) {
+        tracing::warn!("Failed to emit timer-start-requested event: {}", e);
+    }
+    true
+}
+
+fn check_timebox(app_handle: &AppHandle<Wry>) {
+    let state = app_handle.state::<JiraState>();
+    let now = clock::now(app_handle);
+
+    let crossed = {
+        let mut active = state.timer().lock().unwrap_or_else(|e| e.into_inner());
+        let Some(timer) = active.as_mut() else {
+            return;
+        };
+        let elapsed = timer.elapsed_seconds(now);
+        let issue_key = timer.issue_key.clone();
+        let Some(timebox) = timer.timebox.as_mut() else {
+            return;
+        };
+
+        let remaining = timebox.duration_seconds.saturating_sub(elapsed) as f64;
+        let mut crossed = None;
+        while let Some(threshold) = TIMEBOX_THRESHOLDS.get(timebox.next_threshold) {
+            if remaining > threshold * timebox.duration_seconds as f64 {
+                break;
+            }
+            crossed = Some((*threshold, issue_key.clone(), remaining as u64));
+            timebox.next_threshold += 1;
+        }
+        crossed
+    };
+
+    let Some((threshold, issue_key, remaining)) = crossed else {
+        return;
+    };
+
+    if threshold > 0.0 {
+        notifications::notify(
+            app_handle,
+            NotificationKind::Timer,
+            &format!("Timebox: {}", issue_key),
+            &format!("{} minutes left", remaining.div_ceil(60)),
+        );
+    } else {
+        notifications::notify(
+            app_handle,
+            NotificationKind::Timer,
+            &format!("Timebox over: {}", issue_key),
+            "Time's up! Log your work.",
+        );
+        if let Err(e) = stop_active(app_handle, state.timer()) {
+            tracing::warn!("Failed to stop timeboxed timer: {}", e);
+        }
+    }
+}
+
+/// Pauses a running timer once the user has been idle for the configured
+/// threshold. The timer stops counting where the idle span began; the span
+/// is kept on the timer until `resolve_idle`.
+fn check_idle(app_handle: &AppHandle<Wry>) {
+    let Some(idle_seconds) = app_handle.state::<IdleMonitor>().idle_seconds() else {
+        return;
+    };
+    let now = clock::now(app_handle);
+    lunch::observe(app_handle, idle_seconds, now);
+    if check_lunch(app_handle, idle_seconds, now) {
+        return;
+    }
+
+    let settings = settings::current(app_handle).idle;
+    if !settings.pause_timer || idle_seconds < settings.threshold_minutes as u64 * 60 {
+        return;
+    }
+
+    let detected = {
+        let state = app_handle.state::<JiraState>();
+        let mut active = state.timer().lock().unwrap_or_else(|e| e.into_inner());
+        let Some(timer) = active.as_mut() else {
+            return;
+        };
+        let Some(resumed_at) = timer.resumed_at else {
+            return;
+        };
+
+        let idle_since = (now - chrono::Duration::seconds(idle_seconds as i64)).max(resumed_at);
+        timer.pause_at(idle_since);
+        timer.idle_since = Some(idle_since);
+        IdleDetected {
+            issue_key: timer.issue_key.clone(),
+            idle_since,
+            timer: timer.snapshot(now),
+        }
+    };
+
+    if let Err(e) = app_handle.emit("idle-detected", &detected) {
+        tracing::warn!("Failed to emit idle-detected event: {}", e);
+    }
+}
+
+/// Pauses a running timer when I step away during the lunch window and
+/// resumes it once I'm back; the time away is dropped without asking.
+/// Returns whether lunch has the timer, so no idle prompt is raised.
+fn check_lunch(app_handle: &AppHandle<Wry>, idle_seconds: u64, now: DateTime<Local>) -> bool {
+    let window = settings::current(app_handle)
+        .lunch
+        .pause_timer
+        .then(|| lunch::window(app_handle))
+        .flatten();
+
+    let state = app_handle.state::<JiraState>();
+    let mut active = state.timer().lock().unwrap_or_else(|e| e.into_inner());
+    let Some(timer) = active.as_mut() else {
+        return false;
+    };
+    if timer.paused_for_lunch {
+        if idle_seconds < LUNCH_AWAY_SECONDS {
+            // Away since the segment lunch ended.
+            if let Some(away_since) = timer.segments.last().map(|segment| segment.ended) {
+                timer.note(SegmentKind::IdleTrimmed, away_since, now);
+            }
+            timer.paused_for_lunch = false;
+            timer.resumed_at = Some(now);
+        }
+        return true;
+    }
+
+    let (Some(window), Some(resumed_at)) = (window, timer.resumed_at) else {
+        return false;
+    };
+    let idle_since = (now - chrono::Duration::seconds(idle_seconds as i64)).max(resumed_at);
+    if idle_seconds < LUNCH_AWAY_SECONDS || !window.contains(idle_since.time()) {
+        return false;
+    }
+    timer.pause_at(idle_since);
+    timer.paused_for_lunch = true;
+    true
+}
+
+/// The active timer as of now.
+pub fn current(app_handle: &AppHandle<Wry>) -> Option<TimerSnapshot> {
+    let state = app_handle.state::<JiraState>();
+    let active = state.timer().lock().unwrap_or_else(|e| e.into_inner());
+    active.as_ref().map(|timer| timer.snapshot(clock::now(app_handle)))
+}
+
+fn emit_tick(app_handle: &AppHandle<Wry>) {
+    if let Some(snapshot) = current(app_handle) {
+        if let Err(e) = app_handle.emit("timer-tick", &snapshot) {
+            tracing::warn!("Failed to emit timer-tick event: {}", e);
+        }
+    }
+}
+
+pub async fn start_timer_ticker(app_handle: AppHandle<Wry>) {
+    let mut interval = interval(Duration::from_secs(1));
+    let mut ticks: u64 = 0;
+    // Issue and running state the tray menu shows.
+    let mut shown: Option<(String, bool)> = None;
+
+    loop {
+        interval.tick().await;
+        ticks += 1;
+        if ticks.is_multiple_of(IDLE_CHECK_TICKS) {
+            check_idle(&app_handle);
+        }
+        check_timebox(&app_handle);
+        pomodoro::check(&app_handle);
+        breaks::check(&app_handle);
+        emit_tick(&app_handle);
+
+        let state = current(&app_handle).map(|timer| (timer.issue_key, timer.running));
+        if state != shown {
+            shown = state;
+            tray::refresh_menu(&app_handle);
+        }
+    }
+}
+
+pub fn normalize_issue_key(issue_key: &str) -> Result<String, String> {
+    let issue_key = issue_key.trim().to_uppercase();
+    if JiraClient::is_valid_issue_key(&issue_key) {
+        Ok(issue_key)
+    } else {
+        Err(format!("Invalid issue key: {}", issue_key))
+    }
+}
+
+/// Jira rejects worklogs under a minute, so tracked time is rounded to the
+/// nearest minute with a one-minute floor.
+fn worklog_seconds(elapsed_seconds: u64) -> u32 {
+    let minutes = ((elapsed_seconds + 30) / 60).max(1);
+    (minutes * 60) as u32
+}
+
+/// Starts timing `issue_key` unless a timer already exists.
+pub fn start(app_handle: &AppHandle<Wry>, issue_key: &str) -> Result<TimerSnapshot, String> {
+    let issue_key = normalize_issue_key(issue_key)?;
+    let state = app_handle.state::<JiraState>();
+    let mut active = state.timer().lock().map_err(|e| e.to_string())?;
+    if let Some(timer) = active.as_ref() {
+        return Err(format!("A timer is already running for {}", timer.issue_key));
+    }
+
+    let now = clock::now(app_handle);
+    interest::touch(app_handle, &issue_key);
+    let timer = ActiveTimer::new(issue_key, now);
+    let snapshot = timer.snapshot(now);
+    *active = Some(timer);
+    Ok(snapshot)
+}
+
+/// Starts timing `issue_key`, or the top focus item when none is given.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn start_timer(issue_key: Option<String>, app_handle: AppHandle<Wry>) -> Result<TimerSnapshot, String> {
+    let issue_key = match issue_key {
+        Some(issue_key) => issue_key,
+        None => focus::top(&app_handle)
+            .map(|item| item.issue_key)
+            .ok_or_else(|| "No issue given and the focus list is empty".to_string())?,
+    };
+    start(&app_handle, &issue_key)
+}
+
+#[tauri::command]
+pub async fn pause_timer(state: State<'_, JiraState>, clock: State<'_, ClockState>) -> Result<TimerSnapshot, String> {
+    let mut active = state.timer().lock().map_err(|e| e.to_string())?;
+    let timer = active.as_mut().ok_or_else(|| "No timer is running".to_string())?;
+
+    let now = clock.now();
+    timer.pause_at(now);
+    // Paused by hand, it stays paused after lunch.
+    timer.paused_for_lunch = false;
+    Ok(timer.snapshot(now))
+}
+
+#[tauri::command]
+pub async fn resume_timer(state: State<'_, JiraState>, clock: State<'_, ClockState>) -> Result<TimerSnapshot, String> {
+    let mut active = state.timer().lock().map_err(|e| e.to_string())?;
+    let timer = active.as_mut().ok_or_else(|| "No timer is running".to_string())?;
+
+    let now = clock.now();
+    timer.resume(now);
+    Ok(timer.snapshot(now))
+}
+
+/// Answers `idle-detected`: `keep` adds the idle span to the timer, otherwise
+/// it is dropped. The timer runs again either way.
+#[tauri::command]
+pub async fn resolve_idle(
+    keep: bool,
+    state: State<'_, JiraState>,
+    clock: State<'_, ClockState>,
+) -> Result<TimerSnapshot, String> {
+    let mut active = state.timer().lock().map_err(|e| e.to_string())?;
+    let timer = active.as_mut().ok_or_else(|| "No timer is running".to_string())?;
+    let idle_since = timer.idle_since.take().ok_or_else(|| "The timer has no idle time to resolve".to_string())?;
+
+    let now = clock.now();
+    if keep {
+        timer.accumulated_seconds += (now - idle_since).num_seconds().max(0) as u64;
+        timer.note(SegmentKind::IdleKept, idle_since, now);
+    } else {
+        timer.note(SegmentKind::IdleTrimmed, idle_since, now);
+    }
+    timer.resumed_at = Some(now);
+    Ok(timer.snapshot(now))
+}
+
+/// Refuses up front what `worklog_rules` or `closed_issue_guard` would
+/// refuse for the running timer, so it isn't stopped for nothing.
+async fn check_running(app_handle: &AppHandle<Wry>, state: &TimerState, client: &JiraClient, confirmed: bool) -> Result<(), String> {
+    let now = clock::now(app_handle);
+    let running = state.lock().map_err(|e| e.to_string())?.as_ref().map(|timer| timer.snapshot(now));
+    if let Some(running) = running {
+        worklog_rules::normalize(app_handle, &running.started, worklog_seconds(running.elapsed_seconds), confirmed)?;
+        closed_guard::check(app_handle, client, &running.issue_key, confirmed).await?;
+    }
+    Ok(())
+}
+
+/// Logs the time of the stopped timer `snapshot` as `description`.
+async fn log_stopped(
+    app_handle: &AppHandle<Wry>,
+    client: &JiraClient,
+    snapshot: &TimerSnapshot,
+    description: String,
+    confirmed: bool,
+) -> Result<WorklogSubmission, String> {
+    let seconds = worklog_seconds(snapshot.elapsed_seconds);
+    let (started, seconds) = worklog_rules::normalize(app_handle, &snapshot.started, seconds, confirmed)?;
+    let submission = offline_queue::submit(
+        app_handle,
+        client,
+        None,
+        &snapshot.issue_key,
+        &CommentInput::Text(description),
+        &started,
+        seconds,
+    )
+    .await?;
+    Ok(submission)
+}
+
+/// Stops the timer. With a description the tracked time is logged right
+/// away; without one the frontend receives `timer-stopped` to pre-fill its
+/// worklog form. When `worklog_rules` or `closed_issue_guard` refuse the
+/// worklog the timer keeps running.
+#[tauri::command]
+pub async fn stop_timer(
+    description: Option<String>,
+    confirmed: Option<bool>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<StoppedTimer, String> {
+    let description = description.filter(|d| !d.trim().is_empty());
+    let client = match description {
+        Some(_) => Some(connected_client(&state).await?),
+        None => None,
+    };
+    let confirmed = confirmed.unwrap_or(false);
+    if let Some(client) = &client {
+        check_running(&app_handle, state.timer(), client, confirmed).await?;
+    }
+
+    let snapshot = stop_active(&app_handle, state.timer())?;
+    let worklog = match (client, description) {
+        (Some(client), Some(description)) => Some(log_stopped(&app_handle, &client, &snapshot, description, confirmed).await?),
+        _ => None,
+    };
+
+    Ok(StoppedTimer {
+        timer: snapshot,
+        worklog,
+    })
+}
+
+/// Stops the timer, logs its time described as `current_issue_desc`, and
+/// starts timing `new_issue` from the same moment, so no time falls between
+/// the two. All or nothing: when the worklog is refused, up front or by
+/// JIRA, the timer goes back to the current issue as if nothing happened.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn switch_and_log(
+    current_issue_desc: String,
+    new_issue: String,
+    confirmed: Option<bool>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<SwitchedTimer, String> {
+    let new_issue = normalize_issue_key(&new_issue)?;
+    let description = current_issue_desc.trim().to_string();
+    if description.is_empty() {
+        return Err("Describe the work on the current issue before switching".to_string());
+    }
+    if state.timer().lock().map_err(|e| e.to_string())?.as_ref().is_some_and(|timer| timer.issue_key == new_issue) {
+        return Err(format!("The timer is already running for {}", new_issue));
+    }
+    let client = connected_client(&state).await?;
+    let confirmed = confirmed.unwrap_or(false);
+    check_running(&app_handle, state.timer(), &client, confirmed).await?;
+
+    let now = clock::now(&app_handle);
+    let (timer, started) = {
+        let mut active = state.timer().lock().map_err(|e| e.to_string())?;
+        let timer = active.take().ok_or_else(|| "No timer is running".to_string())?;
+        let next = ActiveTimer::new(new_issue.clone(), now);
+        let started = next.snapshot(now);
+        *active = Some(next);
+        (timer, started)
+    };
+
+    let snapshot = timer.snapshot(now);
+    let worklog = match log_stopped(&app_handle, &client, &snapshot, description, confirmed).await {
+        Ok(worklog) => worklog,
+        Err(e) => {
+            let mut active = state.timer().lock().unwrap_or_else(|e| e.into_inner());
+            if active.as_ref().is_some_and(|next| next.issue_key == new_issue && next.started == now) {
+                *active = Some(timer);
+            } else {
+                // The new timer was stopped or replaced meanwhile; the old
+                // one ends as a plain stop so its time reaches the form.
+                drop(active);
+                finish(&app_handle, timer, now);
+            }
+            return Err(e);
+        }
+    };
+    interest::touch(&app_handle, &new_issue);
+    let snapshot = finish(&app_handle, timer, now);
+    Ok(SwitchedTimer {
+        stopped: StoppedTimer {
+            timer: snapshot,
+            worklog: Some(worklog),
+        },
+        started,
+    })
+}
+
+/// Throws the timer away without logging it or emitting `timer-stopped`;
+/// its time is still kept with the timer sessions, marked discarded.
+#[tauri::command]
+pub async fn discard_timer(app_handle: AppHandle<Wry>, state: State<'_, JiraState>) -> Result<TimerSnapshot, String> {
+    let timer = {
+        let mut active = state.timer().lock().map_err(|e| e.to_string())?;
+        active.take().ok_or_else(|| "No timer is running".to_string())?
+    };
+
+    let now = clock::now(&app_handle);
+    let snapshot = timer.snapshot(now);
+    record_session(&app_handle, timer, now, true);
+    Ok(snapshot)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn start_timebox(
+    issue_key: String,
+    duration: String,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+    clock: State<'_, ClockState>,
+) -> Result<TimerSnapshot, String> {
+    let issue_key = normalize_issue_key(&issue_key)?;
+
+    let duration_seconds = JiraClient::parse_time_to_seconds(&duration, &settings::current(&app_handle).time_units)
+        .map_err(|e| format!("Invalid time format: {}", e))?;
+    if duration_seconds == 0 {
+        return Err("Timebox duration must be greater than zero".to_string());
+    }
+
+    let mut active = state.timer().lock().map_err(|e| e.to_string())?;
+    if let Some(timer) = active.as_ref() {
+        return Err(format!("A timer is already running for {}", timer.issue_key));
+    }
+
+    let now = clock.now();
+    let mut timer = ActiveTimer::new(issue_key, now);
+    timer.timebox = Some(Timebox {
+        duration_seconds: duration_seconds as u64,
+        next_threshold: 0,
+    });
+
+    let snapshot = timer.snapshot(now);
+    *active = Some(timer);
+    Ok(snapshot)
+}
+
+#[tauri::command]
+pub async fn get_active_timer(
+    state: State<'_, JiraState>,
+    clock: State<'_, ClockState>,
+) -> Result<Option<TimerSnapshot>, String> {
+    let active = state.timer().lock().map_err(|e| e.to_string())?;
+    Ok(active.as_ref().map(|timer| timer.snapshot(clock.now())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32, second: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 6, 7, hour, minute, second).unwrap()
+    }
+
+    #[test]
+    fn rounds_to_whole_minutes_with_a_floor() {
+        assert_eq!(worklog_seconds(0), 60);
+        assert_eq!(worklog_seconds(89), 60);
+        assert_eq!(worklog_seconds(90), 120);
+        assert_eq!(worklog_seconds(3599), 3600);
+    }
+
+    #[test]
+    fn pauses_do_not_count() {
+        let mut timer = ActiveTimer::new("APP-1".to_string(), at(9, 0, 0));
+        timer.pause_at(at(9, 25, 0));
+        assert_eq!(timer.elapsed_seconds(at(12, 0, 0)), 25 * 60);
+
+        timer.resumed_at = Some(at(13, 0, 0));
+        assert_eq!(timer.elapsed_seconds(at(13, 5, 30)), 30 * 60 + 30);
+
+        timer.pause_at(at(13, 10, 0));
+        timer.note(SegmentKind::IdleTrimmed, at(13, 10, 0), at(13, 40, 0));
+        let kinds: Vec<SegmentKind> = timer.segments.iter().map(|segment| segment.kind).collect();
+        assert_eq!(kinds, [SegmentKind::Tracked, SegmentKind::Tracked, SegmentKind::IdleTrimmed]);
+        assert_eq!(timer.segments[1].started, at(13, 0, 0));
+        assert_eq!(timer.elapsed_seconds(at(14, 0, 0)), 35 * 60);
+    }
+
+    #[test]
+    fn resuming_drops_undecided_idle_time() {
+        let mut timer = ActiveTimer::new("APP-1".to_string(), at(9, 0, 0));
+        timer.pause_at(at(9, 30, 0));
+        timer.idle_since = Some(at(9, 30, 0));
+
+        timer.resume(at(10, 0, 0));
+        assert_eq!(timer.idle_since, None);
+        assert_eq!(timer.segments.last().map(|segment| segment.kind), Some(SegmentKind::IdleTrimmed));
+        assert_eq!(timer.elapsed_seconds(at(10, 15, 0)), 45 * 60);
+    }
+
+    #[test]
+    fn runs_across_midnight() {
+        let started = Local.with_ymd_and_hms(2024, 6, 7, 23, 50, 0).unwrap();
+        let timer = ActiveTimer::new("APP-1".to_string(), started);
+        let next_day = Local.with_ymd_and_hms(2024, 6, 8, 0, 10, 0).unwrap();
+
+        let snapshot = timer.snapshot(next_day);
+        assert_eq!(snapshot.elapsed_seconds, 20 * 60);
+        assert!(snapshot.started.starts_with("2024-06-07T23:50:00.000"));
+    }
+}
@@ -0,0 +1,214 @@
+//! Finds worklogs that start at hours nobody works, typically 02:00 entries
+//! left by a client that sent local times as UTC, and moves them back.
+//! JIRA reports `started` in the profile timezone, so the offset of each
+//! timestamp is the profile's on that day.
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, TimeDelta, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use tauri::{AppHandle, State, Wry};
+
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::jira_types::{UserWorklog, WorklogUpdateRequest};
+use crate::{client_for, month_close, JiraState};
+
+/// Start hours, in the profile timezone, that aren't suspicious.
+const PLAUSIBLE_HOURS: Range<u32> = 6..22;
+const MAX_FIXES: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimezoneMistake {
+    /// The local time was sent as UTC.
+    SentAsUtc,
+    /// The time was read in the computer's timezone, not the profile's.
+    ComputerTimezone,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimezoneSuspect {
+    pub issue_key: String,
+    pub worklog_id: String,
+    pub started: String,
+    pub time_spent_seconds: u32,
+    /// `started` in the profile timezone.
+    pub local_start: NaiveDateTime,
+    /// Where the worklog probably belongs, when a likely mistake explains it.
+    pub suggested_started: Option<String>,
+    pub mistake: Option<TimezoneMistake>,
+    pub shift_minutes: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimezoneFix {
+    pub issue_key: String,
+    pub worklog_id: String,
+    pub started: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimezoneFixResult {
+    pub worklog_id: String,
+    pub error: Option<JiraError>,
+}
+
+/// How each mistake moved a start in a profile timezone at `profile`, with
+/// `computer` the computer's offset then.
+fn shifts(profile: FixedOffset, computer: FixedOffset) -> Vec<(TimezoneMistake, TimeDelta)> {
+    let profile = TimeDelta::seconds(profile.local_minus_utc() as i64);
+    let computer = TimeDelta::seconds(computer.local_minus_utc() as i64);
+    [
+        (TimezoneMistake::SentAsUtc, profile),
+        (TimezoneMistake::ComputerTimezone, profile - computer),
+    ]
+    .into_iter()
+    .filter(|(_, shift)| !shift.is_zero())
+    .collect()
+}
+
+/// The worklogs of `worklogs` starting outside `PLAUSIBLE_HOURS`, with the
+/// first mistake that moves them into it undone. `computer_offset` is the
+/// computer's UTC offset at an instant.
+pub fn find_suspects(worklogs: &[UserWorklog], computer_offset: impl Fn(DateTime<FixedOffset>) -> FixedOffset) -> Vec<TimezoneSuspect> {
+    worklogs
+        .iter()
+        .filter_map(|entry| {
+            let started = JiraClient::parse_jira_datetime(&entry.worklog.started).ok()?;
+            if PLAUSIBLE_HOURS.contains(&started.hour()) {
+                return None;
+            }
+
+            let fix = shifts(*started.offset(), computer_offset(started))
+                .into_iter()
+                .map(|(mistake, shift)| (mistake, shift, started - shift))
+                .find(|(_, _, fixed)| PLAUSIBLE_HOURS.contains(&fixed.hour()));
+            Some(TimezoneSuspect {
+                issue_key: entry.issue_key.clone(),
+                worklog_id: entry.worklog.id.clone(),
+                started: entry.worklog.started.clone(),
+                time_spent_seconds: entry.worklog.time_spent_seconds,
+                local_start: started.naive_local(),
+                suggested_started: fix.map(|(_, _, fixed)| JiraClient::format_jira_datetime(&fixed)),
+                mistake: fix.map(|(mistake, _, _)| mistake),
+                shift_minutes: fix.map(|(_, shift, _)| -shift.num_minutes()),
+            })
+        })
+        .collect()
+}
+
+/// My worklogs between the dates (inclusive) that start at impossible
+/// hours, with a suggested start for those a timezone mix-up explains.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn audit_timezones(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    connection: Option<String>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<TimezoneSuspect>, JiraError> {
+    if from_date > to_date {
+        return Err(JiraError::Validation("fromDate must not be after toDate".to_string()));
+    }
+
+    let client = client_for(&state, connection.as_deref()).await?;
+    let worklogs = client.get_user_worklogs(from_date, to_date).await?;
+    Ok(find_suspects(&worklogs, |at| Local.offset_from_utc_datetime(&at.naive_utc()).fix()))
+}
+
+/// Moves one worklog unless the month it leaves or the one it lands in is
+/// closed.
+async fn move_worklog(app_handle: &AppHandle<Wry>, client: &JiraClient, fix: &TimezoneFix) -> Result<(), JiraError> {
+    month_close::check_started(app_handle, &client.get_worklog(&fix.issue_key, &fix.worklog_id).await?.started)?;
+    month_close::check_started(app_handle, &fix.started)?;
+    let update = WorklogUpdateRequest {
+        comment: None,
+        started: Some(fix.started.clone()),
+        time_spent_seconds: None,
+    };
+    client.update_worklog(&fix.issue_key, &fix.worklog_id, &update).await?;
+    Ok(())
+}
+
+/// Moves worklogs to the given starts, usually the suggested ones, one at
+/// a time; each gets its own result.
+#[tauri::command]
+pub async fn fix_timezones(
+    fixes: Vec<TimezoneFix>,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<TimezoneFixResult>, JiraError> {
+    if fixes.len() > MAX_FIXES {
+        return Err(JiraError::Validation(format!("At most {} worklogs can be fixed at once", MAX_FIXES)));
+    }
+    for fix in &fixes {
+        JiraClient::parse_jira_datetime(&fix.started)?;
+    }
+
+    let client = client_for(&state, connection.as_deref()).await?;
+    let mut results = Vec::with_capacity(fixes.len());
+    for fix in fixes {
+        let error = move_worklog(&app_handle, &client, &fix).await.err();
+        results.push(TimezoneFixResult {
+            worklog_id: fix.worklog_id,
+            error,
+        });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jira_types::Worklog;
+
+    fn entry(started: &str) -> UserWorklog {
+        UserWorklog {
+            issue_key: "APP-1".to_string(),
+            issue_summary: String::new(),
+            worklog: serde_json::from_value::<Worklog>(serde_json::json!({
+                "id": "10",
+                "issueId": "100",
+                "started": started,
+                "timeSpentSeconds": 3600,
+            }))
+            .unwrap(),
+        }
+    }
+
+    fn offset(hours: i32) -> FixedOffset {
+        FixedOffset::east_opt(hours * 3600).unwrap()
+    }
+
+    #[test]
+    fn undoes_local_times_sent_as_utc() {
+        // In Sydney, 09:00 sent as UTC shows as 19:00, which isn't flagged,
+        // but 17:00 shows as 03:00 the next day.
+        let worklogs = [entry("2024-06-03T19:00:00.000+1000"), entry("2024-06-04T03:00:00.000+1000")];
+        let suspects = find_suspects(&worklogs, |_| offset(10));
+
+        assert_eq!(suspects.len(), 1);
+        assert_eq!(suspects[0].mistake, Some(TimezoneMistake::SentAsUtc));
+        assert_eq!(suspects[0].suggested_started.as_deref(), Some("2024-06-03T17:00:00.000+1000"));
+        assert_eq!(suspects[0].shift_minutes, Some(-600));
+    }
+
+    #[test]
+    fn undoes_the_computer_timezone() {
+        // Profile in UTC, computer in UTC+8: 10:00 read as computer time
+        // shows as 02:00.
+        let suspects = find_suspects(&[entry("2024-06-03T02:00:00.000+0000")], |_| offset(8));
+
+        assert_eq!(suspects[0].mistake, Some(TimezoneMistake::ComputerTimezone));
+        assert_eq!(suspects[0].suggested_started.as_deref(), Some("2024-06-03T10:00:00.000+0000"));
+    }
+
+    #[test]
+    fn leaves_unexplained_hours_alone() {
+        let suspects = find_suspects(&[entry("2024-06-03T02:00:00.000+0000")], |_| offset(0));
+
+        assert_eq!(suspects.len(), 1);
+        assert_eq!(suspects[0].suggested_started, None);
+    }
+}
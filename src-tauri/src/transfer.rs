@@ -0,0 +1,64 @@
+//! Moves large results to the frontend over an IPC channel instead of one
+//! JSON string in the `invoke` response, which the webview has to hold and
+//! parse in one go.
+//!
+//! Protocol: the command serializes its result to JSON, optionally gzips it,
+//! and sends it as raw binary chunks of at most `CHUNK_SIZE` bytes on the
+//! channel passed by the caller. The command itself resolves with a
+//! `TransferSummary`; the frontend concatenates the chunks once it has
+//! received `chunks` of them (see `src/lib/transfer.ts`).
+
+use serde::Serialize;
+use std::io::Write;
+use tauri::ipc::{Channel, InvokeResponseBody};
+
+pub const CHUNK_SIZE: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferSummary {
+    pub chunks: usize,
+    /// Bytes sent over the channel.
+    pub bytes: usize,
+    /// Size of the JSON before compression.
+    pub json_bytes: usize,
+    pub gzip: bool,
+}
+
+fn encode<T: Serialize>(value: &T, gzip: bool) -> Result<(Vec<u8>, usize), String> {
+    let json = serde_json::to_vec(value).map_err(|e| format!("Failed to serialize result: {}", e))?;
+    let json_bytes = json.len();
+    if !gzip {
+        return Ok((json, json_bytes));
+    }
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    encoder
+        .write_all(&json)
+        .and_then(|_| encoder.finish())
+        .map(|body| (body, json_bytes))
+        .map_err(|e| format!("Failed to compress result: {}", e))
+}
+
+/// Streams `value` as JSON over `channel`.
+pub fn send_json<T: Serialize>(
+    channel: &Channel<InvokeResponseBody>,
+    value: &T,
+    gzip: bool,
+) -> Result<TransferSummary, String> {
+    let (body, json_bytes) = encode(value, gzip)?;
+
+    let mut chunks = 0;
+    for chunk in body.chunks(CHUNK_SIZE) {
+        channel
+            .send(InvokeResponseBody::Raw(chunk.to_vec()))
+            .map_err(|e| format!("Failed to send result: {}", e))?;
+        chunks += 1;
+    }
+
+    Ok(TransferSummary {
+        chunks,
+        bytes: body.len(),
+        json_bytes,
+        gzip,
+    })
+}
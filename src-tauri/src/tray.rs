@@ -0,0 +1,823 @@
+use chrono::{Local, NaiveDate, Timelike};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::image::Image;
+use tauri::menu::{Menu, MenuItem, Submenu};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconEvent};
+use tauri::{AppHandle, Manager, Monitor, PhysicalPosition, PhysicalSize, Rect, WebviewWindow, Wry};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tokio::time::{interval, Duration};
+
+use crate::focus::{self, FocusItem};
+use crate::health::{self, ConnectionStatus};
+use crate::jira_api::field_sets;
+use crate::jira_types::UserWorklog;
+use crate::offline_queue::{self, OfflineQueue, QueueStatus};
+use crate::settings::{self, AppSettings, TrayClickAction};
+use crate::timer::TimerSnapshot;
+use crate::notifications::NotificationKind;
+use crate::error::AppError;
+use crate::{clock, connected_client, notifications, session_lock, summary, templates, timer, JiraState};
+
+pub const MAIN_TRAY: &str = "main";
+const TOOLTIP: &str = "Mini Jira App";
+
+/// Menu ids of the "Start timer" submenu entries are this plus the issue key.
+const START_TIMER_PREFIX: &str = "start_timer:";
+/// Menu ids of the "Log Template" submenu entries are this plus the
+/// template id.
+const LOG_TEMPLATE_PREFIX: &str = "log_template:";
+/// In-progress issues offered in the "Start timer" submenu.
+const MENU_ISSUES: u32 = 5;
+/// Summaries are cut to this many characters in menu labels.
+const MENU_SUMMARY_CHARS: usize = 40;
+/// Minutes between refetches of the menu's issues; the timer line is
+/// updated every minute.
+const MENU_ISSUES_REFRESH_MINUTES: u64 = 5;
+
+/// From this hour on, a day logged below its target gets a badge.
+const UNDER_TARGET_FROM_HOUR: u32 = 16;
+/// Badge radius as a share of the icon size.
+const BADGE_RADIUS: f64 = 0.22;
+const TIMER_BADGE: [u8; 3] = [220, 38, 38];
+const UNDER_TARGET_BADGE: [u8; 3] = [234, 179, 8];
+const QUEUE_BADGE: [u8; 3] = [37, 99, 235];
+const QUEUE_FAILED_BADGE: [u8; 3] = [249, 115, 22];
+
+/// Tray clicks arriving this soon after a blur-hide belong to the same
+/// gesture: clicking the tray is what took the focus away.
+const BLUR_CLICK_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(300);
+
+#[derive(Default)]
+pub struct TrayFlashState(AtomicBool);
+
+/// Where the tray icon was last seen, in physical screen coordinates.
+#[derive(Debug, Clone, Copy)]
+pub struct TrayAnchor {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl TrayAnchor {
+    /// Builds an anchor from a tray event. Some platforms report an empty
+    /// icon rect, in which case the click point itself is the anchor.
+    pub fn from_event(rect: &Rect, position: PhysicalPosition<f64>, scale: f64) -> Self {
+        let rect_position: PhysicalPosition<f64> = rect.position.to_physical(scale);
+        let rect_size: PhysicalSize<f64> = rect.size.to_physical(scale);
+
+        if rect_size.width < 1.0 || rect_size.height < 1.0 {
+            Self::from_point(position)
+        } else {
+            Self {
+                x: rect_position.x,
+                y: rect_position.y,
+                width: rect_size.width,
+                height: rect_size.height,
+            }
+        }
+    }
+
+    pub fn from_point(position: PhysicalPosition<f64>) -> Self {
+        Self {
+            x: position.x,
+            y: position.y,
+            width: 1.0,
+            height: 1.0,
+        }
+    }
+
+    fn center(&self) -> (f64, f64) {
+        (self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TrayEdge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+/// Issue key and summary of the in-progress issues in the tray menu.
+#[derive(Default)]
+pub struct TrayMenuState(Mutex<Vec<(String, String)>>);
+
+/// Today's logged time as last fetched for the tray.
+#[derive(Debug, Clone, Copy)]
+struct LoggedToday {
+    date: NaiveDate,
+    logged_seconds: u32,
+    target_seconds: u32,
+}
+
+#[derive(Default)]
+pub struct TrayLoggedState(Mutex<Option<LoggedToday>>);
+
+#[derive(Default)]
+pub struct PopoverState {
+    hidden_on_blur_at: Mutex<Option<Instant>>,
+    anchor: Mutex<Option<TrayAnchor>>,
+}
+
+pub fn mark_hidden_on_blur(app_handle: &AppHandle<Wry>) {
+    let state = app_handle.state::<PopoverState>();
+    let mut hidden_at = state.hidden_on_blur_at.lock().unwrap_or_else(|e| e.into_inner());
+    *hidden_at = Some(Instant::now());
+}
+
+pub fn was_just_hidden_on_blur(app_handle: &AppHandle<Wry>) -> bool {
+    let state = app_handle.state::<PopoverState>();
+    let hidden_at = state.hidden_on_blur_at.lock().unwrap_or_else(|e| e.into_inner());
+    hidden_at.is_some_and(|at| at.elapsed() < BLUR_CLICK_DEBOUNCE)
+}
+
+pub fn remember_anchor(app_handle: &AppHandle<Wry>, anchor: TrayAnchor) {
+    let state = app_handle.state::<PopoverState>();
+    let mut current = state.anchor.lock().unwrap_or_else(|e| e.into_inner());
+    *current = Some(anchor);
+}
+
+/// Best known tray location. Linux app indicators never report tray clicks,
+/// so there the cursor position (the user just used the tray menu) stands in.
+fn current_anchor(app_handle: &AppHandle<Wry>) -> Option<TrayAnchor> {
+    let remembered = {
+        let state = app_handle.state::<PopoverState>();
+        let anchor = state.anchor.lock().unwrap_or_else(|e| e.into_inner());
+        *anchor
+    };
+
+    if let Some(anchor) = remembered {
+        return Some(anchor);
+    }
+
+    let cursor = app_handle.cursor_position().ok();
+
+    if let Some(tray) = app_handle.tray_by_id(MAIN_TRAY) {
+        if let Ok(Some(rect)) = tray.rect() {
+            let scale = app_handle
+                .primary_monitor()
+                .ok()
+                .flatten()
+                .map(|monitor| monitor.scale_factor())
+                .unwrap_or(1.0);
+            let size: PhysicalSize<f64> = rect.size.to_physical(scale);
+            if size.width >= 1.0 && size.height >= 1.0 {
+                let fallback = cursor.unwrap_or(PhysicalPosition::new(0.0, 0.0));
+                return Some(TrayAnchor::from_event(&rect, fallback, scale));
+            }
+        }
+    }
+
+    cursor.map(TrayAnchor::from_point)
+}
+
+/// The screen edge holding the tray: the side where the work area is inset
+/// from the monitor bounds (taskbar, menu bar, panel), falling back to the
+/// edge nearest to the anchor.
+fn tray_edge(monitor: &Monitor, anchor: &TrayAnchor) -> TrayEdge {
+    let work_area = monitor.work_area();
+    let monitor_x = monitor.position().x as f64;
+    let monitor_y = monitor.position().y as f64;
+    let monitor_width = monitor.size().width as f64;
+    let monitor_height = monitor.size().height as f64;
+    let area_x = work_area.position.x as f64;
+    let area_y = work_area.position.y as f64;
+    let area_right = area_x + work_area.size.width as f64;
+    let area_bottom = area_y + work_area.size.height as f64;
+
+    let insets = [
+        (TrayEdge::Top, area_y - monitor_y),
+        (TrayEdge::Bottom, monitor_y + monitor_height - area_bottom),
+        (TrayEdge::Left, area_x - monitor_x),
+        (TrayEdge::Right, monitor_x + monitor_width - area_right),
+    ];
+    let (center_x, center_y) = anchor.center();
+    let inset_near_anchor = insets.iter().find(|(edge, inset)| {
+        *inset > 0.0
+            && match edge {
+                TrayEdge::Top => center_y <= area_y,
+                TrayEdge::Bottom => center_y >= area_bottom,
+                TrayEdge::Left => center_x <= area_x,
+                TrayEdge::Right => center_x >= area_right,
+            }
+    });
+
+    if let Some((edge, _)) = inset_near_anchor {
+        return *edge;
+    }
+
+    let distances = [
+        (TrayEdge::Top, center_y - monitor_y),
+        (TrayEdge::Bottom, monitor_y + monitor_height - center_y),
+        (TrayEdge::Left, center_x - monitor_x),
+        (TrayEdge::Right, monitor_x + monitor_width - center_x),
+    ];
+    distances
+        .iter()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(edge, _)| *edge)
+        .unwrap_or(TrayEdge::Bottom)
+}
+
+/// Moves `window` next to the tray anchor: below a top menu bar, above a
+/// bottom taskbar, beside a vertical taskbar, always inside the work area.
+pub fn position_near_tray(window: &WebviewWindow<Wry>, anchor: &TrayAnchor) -> tauri::Result<()> {
+    let (center_x, center_y) = anchor.center();
+    let Some(monitor) = window
+        .monitor_from_point(center_x, center_y)?
+        .or(window.primary_monitor()?)
+    else {
+        return Ok(());
+    };
+
+    let work_area = monitor.work_area();
+    let area_x = work_area.position.x as f64;
+    let area_y = work_area.position.y as f64;
+    let area_width = work_area.size.width as f64;
+    let area_height = work_area.size.height as f64;
+
+    let window_size = window.outer_size()?;
+    let width = window_size.width as f64;
+    let height = window_size.height as f64;
+
+    let (x, y) = match tray_edge(&monitor, anchor) {
+        TrayEdge::Top => (center_x - width / 2.0, anchor.y + anchor.height),
+        TrayEdge::Bottom => (center_x - width / 2.0, anchor.y - height),
+        TrayEdge::Left => (anchor.x + anchor.width, center_y - height / 2.0),
+        TrayEdge::Right => (anchor.x - width, center_y - height / 2.0),
+    };
+
+    let x = x.clamp(area_x, (area_x + area_width - width).max(area_x));
+    let y = y.clamp(area_y, (area_y + area_height - height).max(area_y));
+
+    window.set_position(PhysicalPosition::new(x.round() as i32, y.round() as i32))
+}
+
+/// Shows and focuses the window with `label` anchored to the tray icon.
+pub fn show_near_tray(app_handle: &AppHandle<Wry>, label: &str) -> Result<(), String> {
+    let window = app_handle
+        .get_webview_window(label)
+        .ok_or_else(|| format!("Window '{}' not found", label))?;
+
+    if let Some(anchor) = current_anchor(app_handle) {
+        position_near_tray(&window, &anchor).map_err(|e| e.to_string())?;
+    }
+
+    window.show().map_err(|e| e.to_string())?;
+    window.set_focus().map_err(|e| e.to_string())
+}
+
+/// Shows the main window the way the current settings ask for: anchored to
+/// the tray in popover mode, wherever it was otherwise.
+pub fn show_main_window(app_handle: &AppHandle<Wry>) {
+    if settings::current(app_handle).auto_hide_on_blur {
+        if let Err(e) = show_near_tray(app_handle, "main") {
+            tracing::warn!("Failed to show window near tray: {}", e);
+        }
+    } else if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+fn logged_today(app_handle: &AppHandle<Wry>) -> Option<LoggedToday> {
+    let logged = *app_handle.state::<TrayLoggedState>().0.lock().unwrap_or_else(|e| e.into_inner());
+    logged.filter(|logged| logged.date == clock::now(app_handle).date_naive())
+}
+
+/// Whether the day is logged below its target late enough to say so.
+fn under_target(logged: Option<LoggedToday>, hour: u32) -> bool {
+    hour >= UNDER_TARGET_FROM_HOUR && logged.is_some_and(|logged| logged.logged_seconds < logged.target_seconds)
+}
+
+/// Puts the running timer, today's logged time and the top focus item in
+/// the tray tooltip.
+pub fn show_focus(app_handle: &AppHandle<Wry>, top: Option<&FocusItem>) {
+    let Some(tray) = app_handle.tray_by_id(MAIN_TRAY) else {
+        return;
+    };
+    let mut tooltip = TOOLTIP.to_string();
+    if let Some(timer) = timer::current(app_handle) {
+        tooltip.push_str("\nTimer: ");
+        tooltip.push_str(&timer_label(&timer));
+    }
+    if let Some(logged) = logged_today(app_handle) {
+        tooltip.push_str(&format!(
+            "\nLogged today: {:.1}h of {:.1}h",
+            logged.logged_seconds as f64 / 3600.0,
+            logged.target_seconds as f64 / 3600.0
+        ));
+    }
+    match top {
+        Some(FocusItem { issue_key, summary: Some(summary), .. }) => tooltip.push_str(&format!("\nFocus: {} {}", issue_key, summary)),
+        Some(item) => tooltip.push_str(&format!("\nFocus: {}", item.issue_key)),
+        None => {}
+    }
+    if let Some(status) = status_label(health::status(app_handle)) {
+        tooltip.push_str("\nJIRA: ");
+        tooltip.push_str(status);
+    }
+    if let Some(label) = queue_label(app_handle.state::<OfflineQueue>().status()) {
+        tooltip.push('\n');
+        tooltip.push_str(&label);
+    }
+    if let Err(e) = tray.set_tooltip(Some(tooltip)) {
+        tracing::warn!("Failed to update tray tooltip: {}", e);
+    }
+}
+
+fn status_label(status: ConnectionStatus) -> Option<&'static str> {
+    match status {
+        ConnectionStatus::NotConnected | ConnectionStatus::Connected => None,
+        ConnectionStatus::Degraded => Some("slow to respond"),
+        ConnectionStatus::Unauthorized => Some("signed out, sign in again"),
+        ConnectionStatus::Offline => Some("offline"),
+    }
+}
+
+/// "3 worklogs pending sync (1 failed)", while any are queued.
+fn queue_label(status: QueueStatus) -> Option<String> {
+    match status {
+        QueueStatus { pending: 0, .. } => None,
+        QueueStatus { pending, failed: 0 } => Some(format!("{} pending sync", offline_queue::worklogs(pending))),
+        QueueStatus { pending, failed } => Some(format!("{} pending sync ({} failed)", offline_queue::worklogs(pending), failed)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Corner {
+    TopRight,
+    BottomRight,
+    BottomLeft,
+}
+
+/// Paints a round badge of `color` into a corner of an RGBA image, with a
+/// soft edge so it doesn't look jagged at tray sizes.
+fn draw_badge(rgba: &mut [u8], width: u32, height: u32, corner: Corner, color: [u8; 3]) {
+    let radius = width.min(height) as f64 * BADGE_RADIUS;
+    let center_x = match corner {
+        Corner::TopRight | Corner::BottomRight => width as f64 - radius,
+        Corner::BottomLeft => radius,
+    };
+    let center_y = match corner {
+        Corner::TopRight => radius,
+        Corner::BottomRight | Corner::BottomLeft => height as f64 - radius,
+    };
+    for (index, pixel) in rgba.chunks_exact_mut(4).enumerate() {
+        let x = (index as u32 % width) as f64 + 0.5;
+        let y = (index as u32 / width) as f64 + 0.5;
+        let coverage = (radius + 0.5 - (x - center_x).hypot(y - center_y)).clamp(0.0, 1.0);
+        if coverage == 0.0 {
+            continue;
+        }
+        for (channel, value) in pixel[..3].iter_mut().zip(color) {
+            *channel = (*channel as f64 * (1.0 - coverage) + value as f64 * coverage).round() as u8;
+        }
+        pixel[3] = pixel[3].max((coverage * 255.0).round() as u8);
+    }
+}
+
+/// The app icon, faded while the connection has a problem, with a red badge
+/// while a timer runs, a yellow one when today is logged below target late
+/// in the day, and a blue one (orange once any failed) while worklogs wait
+/// in the offline queue.
+fn status_icon(app_handle: &AppHandle<Wry>) -> Option<Image<'static>> {
+    let icon = app_handle.default_window_icon()?;
+    let timer_running = timer::current(app_handle).is_some_and(|timer| timer.running);
+    let under_target = under_target(logged_today(app_handle), clock::now(app_handle).hour());
+    let faded = status_label(health::status(app_handle)).is_some();
+    let queue = app_handle.state::<OfflineQueue>().status();
+    if !faded && !timer_running && !under_target && queue.pending == 0 {
+        return Some(icon.clone().to_owned());
+    }
+
+    let (width, height) = (icon.width(), icon.height());
+    let mut rgba = icon.rgba().to_vec();
+    if faded {
+        for pixel in rgba.chunks_exact_mut(4) {
+            let gray = (pixel[0] as u32 * 30 + pixel[1] as u32 * 59 + pixel[2] as u32 * 11) / 100;
+            pixel[..3].fill(gray as u8);
+            pixel[3] /= 2;
+        }
+    }
+    if timer_running {
+        draw_badge(&mut rgba, width, height, Corner::TopRight, TIMER_BADGE);
+    }
+    if under_target {
+        draw_badge(&mut rgba, width, height, Corner::BottomRight, UNDER_TARGET_BADGE);
+    }
+    if queue.pending > 0 {
+        let color = if queue.failed > 0 { QUEUE_FAILED_BADGE } else { QUEUE_BADGE };
+        draw_badge(&mut rgba, width, height, Corner::BottomLeft, color);
+    }
+    Some(Image::new_owned(rgba, width, height))
+}
+
+/// Redraws the tray icon and tooltip from the connection, timer and
+/// logged time.
+pub fn show_status(app_handle: &AppHandle<Wry>) {
+    show_focus(app_handle, focus::top(app_handle).as_ref());
+    // A flashing icon is put back once the flashing stops.
+    if app_handle.state::<TrayFlashState>().0.load(Ordering::SeqCst) {
+        return;
+    }
+    if let Some(tray) = app_handle.tray_by_id(MAIN_TRAY) {
+        if let Err(e) = tray.set_icon(status_icon(app_handle)) {
+            tracing::warn!("Failed to update tray icon: {}", e);
+        }
+    }
+}
+
+/// Updates the tray icon and tooltip for a new connection status.
+pub fn show_connection_status(app_handle: &AppHandle<Wry>, status: ConnectionStatus) {
+    tracing::info!("Connection status: {:?}", status);
+    show_status(app_handle);
+}
+
+fn timer_label(timer: &TimerSnapshot) -> String {
+    let minutes = timer.elapsed_seconds / 60;
+    let state = if timer.paused_for_lunch {
+        " (lunch)"
+    } else if timer.running {
+        ""
+    } else {
+        " (paused)"
+    };
+    format!("{} · {}:{:02}{}", timer.issue_key, minutes / 60, minutes % 60, state)
+}
+
+fn issue_label(issue_key: &str, summary: &str) -> String {
+    let mut label = format!("{} {}", issue_key, summary);
+    if let Some((cut, _)) = label.char_indices().nth(issue_key.len() + 1 + MENU_SUMMARY_CHARS) {
+        label.truncate(cut);
+        label.push('…');
+    }
+    label
+}
+
+fn build_menu(app_handle: &AppHandle<Wry>) -> tauri::Result<Menu<Wry>> {
+    let menu = Menu::new(app_handle)?;
+
+    match timer::current(app_handle) {
+        Some(timer) => {
+            menu.append(&MenuItem::with_id(app_handle, "timer", timer_label(&timer), false, None::<&str>)?)?;
+            let toggle = if timer.running { "Stop Timer" } else { "Resume Timer" };
+            menu.append(&MenuItem::with_id(app_handle, "toggle_timer", toggle, true, None::<&str>)?)?;
+        }
+        None => {
+            let issues = app_handle.state::<TrayMenuState>().0.lock().unwrap_or_else(|e| e.into_inner()).clone();
+            let start = Submenu::with_id(app_handle, "start_timer", "Start Timer", !issues.is_empty())?;
+            for (issue_key, summary) in &issues {
+                let id = format!("{}{}", START_TIMER_PREFIX, issue_key);
+                start.append(&MenuItem::with_id(app_handle, id, issue_label(issue_key, summary), true, None::<&str>)?)?;
+            }
+            menu.append(&start)?;
+        }
+    }
+    menu.append(&MenuItem::with_id(app_handle, "log_today", "Log Time for Today…", true, None::<&str>)?)?;
+    let favorites = templates::favorites(app_handle);
+    if !favorites.is_empty() {
+        let log_template = Submenu::with_id(app_handle, "log_template", "Log Template", true)?;
+        for (id, name) in &favorites {
+            let id = format!("{}{}", LOG_TEMPLATE_PREFIX, id);
+            log_template.append(&MenuItem::with_id(app_handle, id, name, true, None::<&str>)?)?;
+        }
+        menu.append(&log_template)?;
+    }
+    if let Some(label) = queue_label(app_handle.state::<OfflineQueue>().status()) {
+        menu.append(&MenuItem::with_id(app_handle, "queue_status", label, false, None::<&str>)?)?;
+        menu.append(&MenuItem::with_id(app_handle, "retry_queue", "Retry Now", true, None::<&str>)?)?;
+    }
+
+    menu.append(&MenuItem::with_id(app_handle, "separator0", "---", false, None::<&str>)?)?;
+    menu.append(&MenuItem::with_id(app_handle, "show", "Show", true, None::<&str>)?)?;
+    menu.append(&MenuItem::with_id(app_handle, "hide", "Hide", true, None::<&str>)?)?;
+    menu.append(&MenuItem::with_id(app_handle, "separator1", "---", false, None::<&str>)?)?;
+    menu.append(&MenuItem::with_id(app_handle, "test_notification", "Test Notification", true, None::<&str>)?)?;
+    menu.append(&MenuItem::with_id(app_handle, "separator2", "---", false, None::<&str>)?)?;
+    menu.append(&MenuItem::with_id(app_handle, "quit", "Quit", true, None::<&str>)?)?;
+    Ok(menu)
+}
+
+/// Rebuilds the tray menu from the current timer and issues, and redraws
+/// the icon and tooltip to match.
+pub fn refresh_menu(app_handle: &AppHandle<Wry>) {
+    let Some(tray) = app_handle.tray_by_id(MAIN_TRAY) else {
+        return;
+    };
+    if let Err(e) = build_menu(app_handle).and_then(|menu| tray.set_menu(Some(menu))) {
+        tracing::warn!("Failed to update tray menu: {}", e);
+    }
+    show_status(app_handle);
+}
+
+/// Keys of the in-progress issues the menu offers.
+pub fn menu_issue_keys(app_handle: &AppHandle<Wry>) -> Vec<String> {
+    let issues = app_handle.state::<TrayMenuState>().0.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    issues.into_iter().map(|(issue_key, _)| issue_key).collect()
+}
+
+async fn refresh_menu_issues(app_handle: &AppHandle<Wry>) {
+    let Ok(client) = connected_client(&app_handle.state::<JiraState>()).await else {
+        app_handle.state::<TrayMenuState>().0.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        return;
+    };
+    match client.get_in_progress_issues(MENU_ISSUES, field_sets::ISSUE_LIST).await {
+        Ok(issues) => {
+            let issues = issues.into_iter().map(|issue| (issue.key, issue.fields.summary)).collect();
+            *app_handle.state::<TrayMenuState>().0.lock().unwrap_or_else(|e| e.into_inner()) = issues;
+        }
+        Err(e) => tracing::warn!("Failed to load tray menu issues: {}", e),
+    }
+}
+
+async fn refresh_logged_today(app_handle: &AppHandle<Wry>) {
+    let state = app_handle.state::<TrayLoggedState>();
+    let Ok(client) = connected_client(&app_handle.state::<JiraState>()).await else {
+        *state.0.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        return;
+    };
+    let today = clock::now(app_handle).date_naive();
+    match summary::fetch(app_handle, client, today, today).await {
+        Ok(summary) => {
+            *state.0.lock().unwrap_or_else(|e| e.into_inner()) = Some(LoggedToday {
+                date: today,
+                logged_seconds: summary.total_seconds,
+                target_seconds: summary.target_seconds,
+            });
+        }
+        Err(e) => tracing::warn!("Failed to check today's logged time for the tray: {}", e),
+    }
+}
+
+/// Keeps the tray current: the timer line and icon every minute, the
+/// issues and today's logged time every few minutes, or every minute while
+/// there are no issues yet.
+pub async fn start_menu_refresh(app_handle: AppHandle<Wry>) {
+    let mut ticker = interval(Duration::from_secs(60));
+    let mut minutes: u64 = 0;
+
+    loop {
+        ticker.tick().await;
+        let no_issues = app_handle.state::<TrayMenuState>().0.lock().unwrap_or_else(|e| e.into_inner()).is_empty();
+        if no_issues || minutes.is_multiple_of(MENU_ISSUES_REFRESH_MINUTES) {
+            refresh_menu_issues(&app_handle).await;
+            refresh_logged_today(&app_handle).await;
+        }
+        minutes += 1;
+        refresh_menu(&app_handle);
+    }
+}
+
+/// Tray entries that change anything are refused while the session is
+/// locked, as `session_lock::guard` refuses commands; a running timer keeps
+/// going.
+fn refused_while_locked(app_handle: &AppHandle<Wry>, action: &str) -> bool {
+    if !session_lock::is_locked(app_handle) {
+        return false;
+    }
+    tracing::info!("Refused {} from the tray while the session is locked", action);
+    notifications::notify(app_handle, NotificationKind::Feedback, "Session locked", &AppError::Locked.to_string());
+    true
+}
+
+/// Handles the menu entries built by `build_menu` other than the fixed
+/// window and quit entries.
+pub fn handle_menu_event(app_handle: &AppHandle<Wry>, id: &str) {
+    if id != "log_today" && refused_while_locked(app_handle, id) {
+        return;
+    }
+    match id {
+        "toggle_timer" => {
+            if timer::toggle_from_tray(app_handle) {
+                show_main_window(app_handle);
+            }
+        }
+        "log_today" => crate::show_quick_log_window(app_handle),
+        "retry_queue" => offline_queue::retry_from_tray(app_handle),
+        _ => {
+            if let Some(template_id) = id.strip_prefix(LOG_TEMPLATE_PREFIX).and_then(|id| id.parse().ok()) {
+                templates::log_from_tray(app_handle, template_id);
+                return;
+            }
+            let Some(issue_key) = id.strip_prefix(START_TIMER_PREFIX) else {
+                return;
+            };
+            if let Err(e) = timer::start(app_handle, issue_key) {
+                notifications::notify(app_handle, NotificationKind::Feedback, "Timer", &e);
+            }
+        }
+    }
+    refresh_menu(app_handle);
+}
+
+pub fn apply_settings(app_handle: &AppHandle<Wry>, settings: &AppSettings) {
+    if let Some(tray) = app_handle.tray_by_id(MAIN_TRAY) {
+        let menu_on_left_click = settings.tray_clicks.left == TrayClickAction::ShowMenu;
+        if let Err(e) = tray.set_show_menu_on_left_click(menu_on_left_click) {
+            tracing::warn!("Failed to update tray menu behavior: {}", e);
+        }
+    }
+}
+
+/// Routes tray icon events to the action configured for each mouse button.
+pub fn handle_event(app_handle: &AppHandle<Wry>, event: TrayIconEvent) {
+    let TrayIconEvent::Click {
+        button,
+        button_state: MouseButtonState::Up,
+        rect,
+        position,
+        ..
+    } = event
+    else {
+        return;
+    };
+
+    let scale = app_handle
+        .monitor_from_point(position.x, position.y)
+        .ok()
+        .flatten()
+        .map(|monitor| monitor.scale_factor())
+        .unwrap_or(1.0);
+    remember_anchor(app_handle, TrayAnchor::from_event(&rect, position, scale));
+    stop_flashing(app_handle);
+
+    let clicks = settings::current(app_handle).tray_clicks;
+    let action = match button {
+        MouseButton::Left => clicks.left,
+        MouseButton::Middle => clicks.middle,
+        MouseButton::Right => return,
+    };
+    run_action(app_handle, action);
+}
+
+fn run_action(app_handle: &AppHandle<Wry>, action: TrayClickAction) {
+    match action {
+        TrayClickAction::Nothing | TrayClickAction::ShowMenu => {}
+        TrayClickAction::ShowWindow => {
+            if !was_just_hidden_on_blur(app_handle) {
+                show_main_window(app_handle);
+            }
+        }
+        TrayClickAction::ToggleWindow => {
+            let visible = app_handle
+                .get_webview_window("main")
+                .and_then(|window| window.is_visible().ok())
+                .unwrap_or(false);
+            if visible {
+                if let Some(window) = app_handle.get_webview_window("main") {
+                    let _ = window.hide();
+                }
+            } else if !was_just_hidden_on_blur(app_handle) {
+                show_main_window(app_handle);
+            }
+        }
+        TrayClickAction::QuickLog => crate::show_quick_log_window(app_handle),
+        TrayClickAction::ToggleTimer => {
+            if refused_while_locked(app_handle, "toggle_timer") {
+                return;
+            }
+            if timer::toggle_from_tray(app_handle) {
+                show_main_window(app_handle);
+            }
+        }
+        TrayClickAction::CopyTodaySummary => {
+            let app_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                copy_today_summary(&app_handle).await;
+            });
+        }
+    }
+}
+
+fn summary_text(worklogs: &[UserWorklog]) -> String {
+    let mut per_issue: Vec<(String, String, u32)> = Vec::new();
+    for entry in worklogs {
+        match per_issue.iter_mut().find(|(key, _, _)| *key == entry.issue_key) {
+            Some((_, _, seconds)) => *seconds += entry.worklog.time_spent_seconds,
+            None => per_issue.push((
+                entry.issue_key.clone(),
+                entry.issue_summary.clone(),
+                entry.worklog.time_spent_seconds,
+            )),
+        }
+    }
+
+    let total: u32 = per_issue.iter().map(|(_, _, seconds)| seconds).sum();
+    let mut lines = vec![format!(
+        "{}: {:.2}h logged",
+        Local::now().format("%Y-%m-%d"),
+        total as f64 / 3600.0
+    )];
+    lines.extend(per_issue.iter().map(|(key, summary, seconds)| {
+        format!("{} {} - {:.2}h", key, summary, *seconds as f64 / 3600.0)
+    }));
+    lines.join("\n")
+}
+
+async fn copy_today_summary(app_handle: &AppHandle<Wry>) {
+    let client = match connected_client(&app_handle.state::<JiraState>()).await {
+        Ok(client) => client,
+        Err(e) => {
+            notifications::notify(app_handle, NotificationKind::Feedback, "Today's summary", &e.to_string());
+            return;
+        }
+    };
+
+    let today = Local::now().date_naive();
+    let worklogs = match client.get_user_worklogs(today, today).await {
+        Ok(worklogs) => worklogs,
+        Err(e) => {
+            notifications::notify(app_handle, NotificationKind::Feedback, "Today's summary", &format!("Failed to get worklogs: {}", e));
+            return;
+        }
+    };
+
+    match app_handle.clipboard().write_text(summary_text(&worklogs)) {
+        Ok(()) => notifications::notify(app_handle, NotificationKind::Feedback, "Today's summary", "Copied to the clipboard"),
+        Err(e) => tracing::warn!("Failed to copy summary: {}", e),
+    }
+}
+
+#[tauri::command]
+pub async fn show_window_near_tray(label: Option<String>, app_handle: AppHandle<Wry>) -> Result<(), String> {
+    show_near_tray(&app_handle, label.as_deref().unwrap_or("main"))
+}
+
+/// Blinks the tray icon until [`stop_flashing`] is called, typically when the
+/// user opens the main window. A reminder like any other, so quiet hours,
+/// snoozing and full screen hold it back.
+pub fn start_flashing(app_handle: &AppHandle<Wry>) {
+    if !notifications::allowed(app_handle, NotificationKind::Reminder, "Tray flash") {
+        return;
+    }
+    if app_handle.state::<TrayFlashState>().0.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let icon = status_icon(&app_handle);
+        let mut ticker = interval(Duration::from_millis(600));
+        let mut visible = true;
+
+        while app_handle.state::<TrayFlashState>().0.load(Ordering::SeqCst) {
+            ticker.tick().await;
+            visible = !visible;
+            if let Some(tray) = app_handle.tray_by_id(MAIN_TRAY) {
+                let _ = tray.set_icon(if visible { icon.clone() } else { None });
+            }
+        }
+
+        if let Some(tray) = app_handle.tray_by_id(MAIN_TRAY) {
+            let _ = tray.set_icon(status_icon(&app_handle));
+        }
+    });
+}
+
+pub fn stop_flashing(app_handle: &AppHandle<Wry>) {
+    app_handle.state::<TrayFlashState>().0.store(false, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(rgba: &[u8], width: u32, x: u32, y: u32) -> &[u8] {
+        let start = ((y * width + x) * 4) as usize;
+        &rgba[start..start + 4]
+    }
+
+    #[test]
+    fn draws_badges_in_their_corner() {
+        let mut rgba = vec![0; 32 * 32 * 4];
+        draw_badge(&mut rgba, 32, 32, Corner::TopRight, TIMER_BADGE);
+        assert_eq!(pixel(&rgba, 32, 25, 6), [220, 38, 38, 255]);
+        assert_eq!(pixel(&rgba, 32, 25, 25), [0, 0, 0, 0]);
+        assert_eq!(pixel(&rgba, 32, 2, 2), [0, 0, 0, 0]);
+
+        draw_badge(&mut rgba, 32, 32, Corner::BottomRight, UNDER_TARGET_BADGE);
+        assert_eq!(pixel(&rgba, 32, 25, 25), [234, 179, 8, 255]);
+
+        draw_badge(&mut rgba, 32, 32, Corner::BottomLeft, QUEUE_BADGE);
+        assert_eq!(pixel(&rgba, 32, 6, 25), [37, 99, 235, 255]);
+        assert_eq!(pixel(&rgba, 32, 2, 2), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn flags_short_days_late_in_the_day() {
+        let logged = LoggedToday {
+            date: NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+            logged_seconds: 5 * 3600,
+            target_seconds: 8 * 3600,
+        };
+        assert!(!under_target(Some(logged), 15));
+        assert!(under_target(Some(logged), 16));
+        assert!(!under_target(None, 17));
+        assert!(!under_target(Some(LoggedToday { target_seconds: 0, ..logged }), 17));
+    }
+}
@@ -0,0 +1,363 @@
+//! New releases from GitHub: the latest published release is compared with
+//! the running version every `updates.check_interval_hours`, and a newer one
+//! is announced once per version with an `update-available` event carrying
+//! its version and release notes. Installing downloads the platform's
+//! installer and its `<name>.sig` asset, a base64 Ed25519 signature over
+//! the installer made with the release key, and opens the installer only if
+//! the signature checks out against the public key built into the app
+//! (`MINI_JIRA_UPDATE_KEY` at build time). Builds without one, and releases
+//! without a signature, can't be installed from here.
+
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use base64::Engine;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::{clock, http, offline, settings};
+
+const RELEASES_URL: &str = "https://api.github.com/repos/SwanVods/mini-jira-app/releases/latest";
+const UPDATE_DIR: &str = "updates";
+/// Lets startup settle before the first check.
+const STARTUP_DELAY: std::time::Duration = std::time::Duration::from_secs(60);
+const TICK: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+const MAX_INSTALLER_BYTES: u64 = 512 * 1024 * 1024;
+const MAX_SIGNATURE_BYTES: u64 = 1024;
+/// The base64 Ed25519 public key releases are signed with.
+const UPDATE_KEY: Option<&str> = option_env!("MINI_JIRA_UPDATE_KEY");
+/// Every architecture installers are named after; one naming none of them
+/// runs anywhere.
+const ARCHITECTURES: [&str; 6] = ["x86_64", "x64", "amd64", "aarch64", "arm64", "i686"];
+
+pub type UpdateState = Mutex<UpdateStatus>;
+
+#[derive(Debug, Default)]
+pub struct UpdateStatus {
+    pub checked_at: Option<DateTime<Local>>,
+    pub available: Option<AvailableUpdate>,
+    /// The version `update-available` was emitted for.
+    announced: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    body: Option<String>,
+    html_url: String,
+    #[serde(default)]
+    published_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub size: u64,
+    pub browser_download_url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailableUpdate {
+    pub version: String,
+    pub name: String,
+    /// The release notes, Markdown.
+    pub notes: String,
+    pub published_at: Option<DateTime<Local>>,
+    /// The release page.
+    pub url: String,
+    /// `None` when the release has nothing for this platform.
+    pub installer: Option<ReleaseAsset>,
+    #[serde(skip)]
+    signature: Option<ReleaseAsset>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateCheck {
+    pub current_version: String,
+    pub available: Option<AvailableUpdate>,
+    pub checked_at: DateTime<Local>,
+}
+
+/// `v1.2.3` or `1.2` as numbers; pre-release and build suffixes are
+/// ignored.
+fn parse_version(text: &str) -> Option<(u64, u64, u64)> {
+    let core = text.trim().trim_start_matches(['v', 'V']).split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(str::parse::<u64>);
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
+
+/// The installer for `os` and `arch` (as in `std::env::consts`): the first
+/// kind the platform takes, built for `arch` or for any.
+fn pick_installer<'a>(assets: &'a [ReleaseAsset], os: &str, arch: &str) -> Option<&'a ReleaseAsset> {
+    let suffixes: &[&str] = match os {
+        "windows" => &[".msi", "-setup.exe"],
+        "macos" => &[".dmg"],
+        _ => &[".AppImage", ".deb", ".rpm"],
+    };
+    let names: &[&str] = match arch {
+        "aarch64" => &["aarch64", "arm64"],
+        "x86" => &["i686"],
+        _ => &["x86_64", "x64", "amd64"],
+    };
+    suffixes.iter().find_map(|suffix| {
+        let matching: Vec<&ReleaseAsset> = assets.iter().filter(|asset| asset.name.ends_with(suffix)).collect();
+        let named = |asset: &&&ReleaseAsset, arches: &[&str]| arches.iter().any(|name| asset.name.contains(name));
+        matching
+            .iter()
+            .find(|asset| named(asset, names))
+            .or_else(|| matching.iter().find(|asset| !named(asset, &ARCHITECTURES)))
+            .copied()
+    })
+}
+
+fn current_version(app_handle: &AppHandle<Wry>) -> (String, (u64, u64, u64)) {
+    let version = &app_handle.package_info().version;
+    (version.to_string(), (version.major, version.minor, version.patch))
+}
+
+async fn latest_release(app_handle: &AppHandle<Wry>) -> Result<Release, String> {
+    offline::check(app_handle).map_err(|e| e.to_string())?;
+    http::client()?
+        .get(RELEASES_URL)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "mini-jira-app")
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to check for updates: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to read the latest release: {}", e))
+}
+
+/// Asks GitHub for the latest release and announces it if it's newer and
+/// new to this run.
+async fn check(app_handle: &AppHandle<Wry>) -> Result<UpdateCheck, String> {
+    let (current, running) = current_version(app_handle);
+    let release = latest_release(app_handle).await?;
+    let newer = parse_version(&release.tag_name).is_some_and(|version| version > running);
+
+    let available = newer.then(|| {
+        let installer = pick_installer(&release.assets, std::env::consts::OS, std::env::consts::ARCH).cloned();
+        let signature = installer.as_ref().and_then(|installer| {
+            let name = format!("{}.sig", installer.name);
+            release.assets.iter().find(|asset| asset.name == name).cloned()
+        });
+        AvailableUpdate {
+            version: release.tag_name.trim_start_matches(['v', 'V']).to_string(),
+            name: release.name.clone().unwrap_or_else(|| release.tag_name.clone()),
+            notes: release.body.clone().unwrap_or_default(),
+            published_at: release.published_at,
+            url: release.html_url.clone(),
+            installer,
+            signature,
+        }
+    });
+
+    let checked_at = clock::now(app_handle);
+    let state = app_handle.state::<UpdateState>();
+    let mut status = state.lock().unwrap_or_else(|e| e.into_inner());
+    status.checked_at = Some(checked_at);
+    status.available = available.clone();
+    if let Some(update) = &available {
+        if status.announced.as_deref() != Some(update.version.as_str()) {
+            status.announced = Some(update.version.clone());
+            if let Err(e) = app_handle.emit("update-available", update) {
+                tracing::warn!("Failed to emit update-available: {}", e);
+            }
+        }
+    }
+    Ok(UpdateCheck {
+        current_version: current,
+        available,
+        checked_at,
+    })
+}
+
+pub async fn start_update_checker(app_handle: AppHandle<Wry>) {
+    tokio::time::sleep(STARTUP_DELAY).await;
+    let mut ticker = tokio::time::interval(TICK);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        let updates = settings::current(&app_handle).updates;
+        if !updates.enabled {
+            continue;
+        }
+        let checked_at = app_handle.state::<UpdateState>().lock().unwrap_or_else(|e| e.into_inner()).checked_at;
+        let due = checked_at.is_none_or(|checked_at| {
+            clock::now(&app_handle) - checked_at >= Duration::hours(updates.check_interval_hours as i64)
+        });
+        if due {
+            if let Err(e) = check(&app_handle).await {
+                tracing::warn!("{}", e);
+            }
+        }
+    }
+}
+
+async fn download(url: &str, max_bytes: u64) -> Result<Vec<u8>, String> {
+    let response = http::client()?
+        .get(url)
+        .header("User-Agent", "mini-jira-app")
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to download the update: {}", e))?;
+    if response.content_length().is_some_and(|length| length > max_bytes) {
+        return Err("The update is larger than it should be".to_string());
+    }
+    let bytes = response.bytes().await.map_err(|e| format!("Failed to download the update: {}", e))?;
+    if bytes.len() as u64 > max_bytes {
+        return Err("The update is larger than it should be".to_string());
+    }
+    Ok(bytes.to_vec())
+}
+
+fn update_key(encoded: &str) -> Result<VerifyingKey, String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| format!("The update key is not base64: {}", e))?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| "The update key must be a 32-byte Ed25519 public key".to_string())?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("Invalid update key: {}", e))
+}
+
+/// Checks `installer` against the base64 Ed25519 `signature` of the
+/// release key.
+fn verify(installer: &[u8], signature: &str, key: &VerifyingKey) -> Result<(), String> {
+    let signature = base64::engine::general_purpose::STANDARD
+        .decode(signature.trim())
+        .map_err(|e| format!("The update signature is not base64: {}", e))?;
+    let signature = Signature::from_slice(&signature).map_err(|e| format!("Invalid update signature: {}", e))?;
+    key.verify(installer, &signature)
+        .map_err(|_| "The downloaded update isn't signed with the release key".to_string())
+}
+
+/// The last check, without asking GitHub again.
+#[tauri::command]
+pub async fn get_update_status(app_handle: AppHandle<Wry>, state: State<'_, UpdateState>) -> Result<Option<UpdateCheck>, String> {
+    let status = state.lock().map_err(|e| e.to_string())?;
+    Ok(status.checked_at.map(|checked_at| UpdateCheck {
+        current_version: current_version(&app_handle).0,
+        available: status.available.clone(),
+        checked_at,
+    }))
+}
+
+#[tauri::command]
+pub async fn check_for_updates(app_handle: AppHandle<Wry>) -> Result<UpdateCheck, String> {
+    check(&app_handle).await
+}
+
+/// Downloads the installer of the update `check_for_updates` found (looking
+/// again if it hasn't run) and, once its signature checks out, opens it.
+/// Returns where it was saved.
+#[tauri::command]
+pub async fn install_update(app_handle: AppHandle<Wry>, state: State<'_, UpdateState>) -> Result<PathBuf, String> {
+    let known = state.lock().map_err(|e| e.to_string())?.available.clone();
+    let update = match known {
+        Some(update) => update,
+        None => check(&app_handle).await?.available.ok_or("This is the latest version")?,
+    };
+    let Some(installer) = &update.installer else {
+        return Err(format!("Version {} has no installer for this platform; see {}", update.version, update.url));
+    };
+    if installer.size > MAX_INSTALLER_BYTES {
+        return Err("The update is larger than any installer should be".to_string());
+    }
+    let Some(key) = UPDATE_KEY else {
+        return Err(format!("This build can't verify updates; download version {} from {}", update.version, update.url));
+    };
+    let key = update_key(key)?;
+    let Some(signature) = &update.signature else {
+        return Err(format!("Version {} isn't signed; download it from {}", update.version, update.url));
+    };
+
+    let signature = download(&signature.browser_download_url, MAX_SIGNATURE_BYTES).await?;
+    let data = download(&installer.browser_download_url, MAX_INSTALLER_BYTES).await?;
+    verify(&data, &String::from_utf8_lossy(&signature), &key)?;
+
+    let dir = app_handle.path().app_cache_dir().map_err(|e| e.to_string())?.join(UPDATE_DIR);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(&installer.name);
+    std::fs::write(&path, &data).map_err(|e| format!("Failed to save {}: {}", path.display(), e))?;
+    #[cfg(unix)]
+    if installer.name.ends_with(".AppImage") {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).map_err(|e| e.to_string())?;
+    }
+
+    app_handle
+        .opener()
+        .open_path(path.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("Failed to open the installer: {}", e))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(name: &str) -> ReleaseAsset {
+        ReleaseAsset {
+            name: name.to_string(),
+            size: 1,
+            browser_download_url: format!("https://example.com/{}", name),
+        }
+    }
+
+    #[test]
+    fn picks_the_installer_for_the_platform() {
+        let assets = [
+            asset("mini-jira-app_0.2.0_aarch64.dmg"),
+            asset("mini-jira-app_0.2.0_x64.dmg"),
+            asset("mini-jira-app_0.2.0_x64-setup.exe"),
+            asset("mini-jira-app_0.2.0_x64_en-US.msi"),
+            asset("mini-jira-app_0.2.0_amd64.deb"),
+            asset("mini-jira-app_0.2.0_universal.AppImage"),
+        ];
+        let pick = |os, arch| pick_installer(&assets, os, arch).map(|asset| asset.name.as_str());
+
+        assert_eq!(pick("macos", "aarch64"), Some("mini-jira-app_0.2.0_aarch64.dmg"));
+        assert_eq!(pick("macos", "x86_64"), Some("mini-jira-app_0.2.0_x64.dmg"));
+        assert_eq!(pick("windows", "x86_64"), Some("mini-jira-app_0.2.0_x64_en-US.msi"));
+        assert_eq!(pick("linux", "aarch64"), Some("mini-jira-app_0.2.0_universal.AppImage"));
+        assert_eq!(pick("windows", "aarch64"), None);
+
+        assert!(parse_version("v0.10.0") > parse_version("0.9.3"));
+        assert_eq!(parse_version("1.2-beta.1"), Some((1, 2, 0)));
+        assert_eq!(parse_version("nightly"), None);
+    }
+
+    #[test]
+    fn installs_only_updates_signed_with_the_key() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let key = SigningKey::from_bytes(&[3; 32]);
+        let installer = b"installer bytes";
+        let signature = base64::engine::general_purpose::STANDARD.encode(key.sign(installer).to_bytes());
+        let public = base64::engine::general_purpose::STANDARD.encode(key.verifying_key().to_bytes());
+        let public = update_key(&public).unwrap();
+
+        assert!(verify(installer, &signature, &public).is_ok());
+        assert!(verify(b"tampered bytes", &signature, &public).is_err());
+        assert!(verify(installer, "not a signature", &public).is_err());
+
+        let other = SigningKey::from_bytes(&[4; 32]);
+        let foreign = base64::engine::general_purpose::STANDARD.encode(other.sign(installer).to_bytes());
+        assert!(verify(installer, &foreign, &public).is_err());
+    }
+}
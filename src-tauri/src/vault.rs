@@ -0,0 +1,467 @@
+//! Optional encryption at rest. With a master password set, settings, the
+//! issue cache and the files holding worklog comments and issue text (the
+//! offline queue, worklog templates, the focus list and issue reminders)
+//! only ever reach the disk encrypted, and stay unreadable until the
+//! password unlocks them at startup; until then the app runs on defaults and
+//! an empty cache in memory. Other data files (plans, statistics, window
+//! layouts and the like) hold issue keys and numbers and stay plain.
+//!
+//! Files are AES-256-CBC with an HMAC-SHA256 over the result, with keys
+//! derived from the password by PBKDF2-HMAC-SHA256. `vault.json` keeps the
+//! salt and a check value to recognize the password by; while it exists,
+//! encryption is on.
+
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+
+use crate::settings::{self, SETTINGS_FILE};
+use crate::storage::{self, Storage};
+use crate::{focus, issue_reminders, offline_queue, store, templates};
+
+const VAULT_FILE: &str = "vault.json";
+const DATABASE_FILE: &str = "cache.sqlite3.enc";
+const MAGIC: &[u8] = b"MJA1";
+const IV_LEN: usize = 16;
+const TAG_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const ITERATIONS: u32 = 600_000;
+const MIN_PASSWORD_LEN: usize = 8;
+/// Sealed with the keys, so a wrong password is told apart from a damaged
+/// file.
+const CHECK: &[u8] = b"mini-jira-app vault";
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct VaultFile {
+    salt: String,
+    iterations: u32,
+    check: String,
+}
+
+#[derive(Clone)]
+struct Keys {
+    encryption: [u8; 32],
+    authentication: [u8; 32],
+}
+
+impl Keys {
+    fn derive(password: &str, salt: &[u8], iterations: u32) -> Self {
+        let mut derived = [0u8; 64];
+        pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut derived);
+        let mut keys = Self {
+            encryption: [0; 32],
+            authentication: [0; 32],
+        };
+        keys.encryption.copy_from_slice(&derived[..32]);
+        keys.authentication.copy_from_slice(&derived[32..]);
+        keys
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.authentication).expect("HMAC takes keys of any length")
+    }
+
+    /// `MAGIC`, IV, ciphertext, then the tag over all of them.
+    fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let mut iv = [0u8; IV_LEN];
+        getrandom::getrandom(&mut iv).map_err(|e| e.to_string())?;
+        let ciphertext = cbc::Encryptor::<aes::Aes256>::new(&self.encryption.into(), &iv.into())
+            .encrypt_padded_vec_mut::<Pkcs7>(plaintext);
+
+        let mut sealed = Vec::with_capacity(MAGIC.len() + IV_LEN + ciphertext.len() + TAG_LEN);
+        sealed.extend_from_slice(MAGIC);
+        sealed.extend_from_slice(&iv);
+        sealed.extend_from_slice(&ciphertext);
+        let mut mac = self.mac();
+        mac.update(&sealed);
+        sealed.extend_from_slice(&mac.finalize().into_bytes());
+        Ok(sealed)
+    }
+
+    /// Fails on anything not sealed with these keys, without decrypting it.
+    fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, String> {
+        if sealed.len() < MAGIC.len() + IV_LEN + TAG_LEN || !sealed.starts_with(MAGIC) {
+            return Err("Not an encrypted file".to_string());
+        }
+        let (body, tag) = sealed.split_at(sealed.len() - TAG_LEN);
+        let mut mac = self.mac();
+        mac.update(body);
+        mac.verify_slice(tag)
+            .map_err(|_| "Wrong password, or the file was changed".to_string())?;
+
+        let (iv, ciphertext) = body[MAGIC.len()..].split_at(IV_LEN);
+        let iv: [u8; IV_LEN] = iv.try_into().map_err(|_| "Not an encrypted file".to_string())?;
+        cbc::Decryptor::<aes::Aes256>::new(&self.encryption.into(), &iv.into())
+            .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Default)]
+pub struct Vault {
+    keys: Option<Keys>,
+    /// `Storage::changes` when the cache was last written out.
+    flushed_changes: u64,
+}
+
+pub type VaultState = Mutex<Vault>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultStatus {
+    pub enabled: bool,
+    pub unlocked: bool,
+}
+
+fn vault_path(app_handle: &AppHandle<Wry>) -> Result<PathBuf, String> {
+    store::config_file(app_handle, VAULT_FILE)
+}
+
+/// Where the encrypted version of `path` goes: next to it, with `.enc`.
+fn sealed_path(path: &Path) -> PathBuf {
+    let mut sealed = path.as_os_str().to_owned();
+    sealed.push(".enc");
+    PathBuf::from(sealed)
+}
+
+/// The JSON files besides the settings that are only written encrypted
+/// while encryption is on.
+fn data_files(app_handle: &AppHandle<Wry>) -> Result<Vec<PathBuf>, String> {
+    Ok(vec![
+        store::data_file(app_handle, offline_queue::QUEUE_FILE)?,
+        store::config_file(app_handle, templates::TEMPLATES_FILE)?,
+        store::data_file(app_handle, focus::FOCUS_FILE)?,
+        store::data_file(app_handle, issue_reminders::REMINDERS_FILE)?,
+    ])
+}
+
+fn database_path(app_handle: &AppHandle<Wry>) -> Result<PathBuf, String> {
+    store::data_file(app_handle, DATABASE_FILE)
+}
+
+pub fn is_enabled(app_handle: &AppHandle<Wry>) -> bool {
+    vault_path(app_handle).is_ok_and(|path| path.exists())
+}
+
+fn keys(app_handle: &AppHandle<Wry>) -> Result<Keys, String> {
+    let state = app_handle.state::<VaultState>();
+    let vault = state.lock().unwrap_or_else(|e| e.into_inner());
+    vault.keys.clone().ok_or_else(|| "Unlock the app first".to_string())
+}
+
+/// Like `store::save_json`, through a temporary file.
+fn write_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let tmp_path = path.with_extension("enc.tmp");
+    std::fs::write(&tmp_path, contents).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp_path, path).map_err(|e| e.to_string())
+}
+
+fn remove_file(path: &Path) -> Result<(), String> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+fn read_sealed(path: &Path, keys: &Keys) -> Result<Option<Vec<u8>>, String> {
+    match std::fs::read(path) {
+        Ok(sealed) => keys.open(&sealed).map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Writes the JSON file `path` encrypted; fails while locked.
+pub fn save_json<T: Serialize>(app_handle: &AppHandle<Wry>, path: &Path, value: &T) -> Result<(), String> {
+    let keys = keys(app_handle)?;
+    let contents = serde_json::to_vec_pretty(value).map_err(|e| e.to_string())?;
+    write_file(&sealed_path(path), &keys.seal(&contents)?)
+}
+
+/// Reads the JSON file `path` written by `save_json`; `None` when there's
+/// none yet.
+pub fn load_json<T: DeserializeOwned>(app_handle: &AppHandle<Wry>, path: &Path) -> Result<Option<T>, String> {
+    let keys = keys(app_handle)?;
+    match read_sealed(&sealed_path(path), &keys)? {
+        Some(contents) => serde_json::from_slice(&contents).map(Some).map_err(|e| e.to_string()),
+        None => Ok(None),
+    }
+}
+
+/// `store::save_json`, encrypted while encryption is on.
+pub fn save<T: Serialize>(app_handle: &AppHandle<Wry>, path: &Path, value: &T) -> Result<(), String> {
+    if is_enabled(app_handle) {
+        save_json(app_handle, path, value)
+    } else {
+        store::save_json(path, value)
+    }
+}
+
+/// `store::load_json` for a file written by `save`; with encryption on, the
+/// default until the app is unlocked.
+pub fn load<T: DeserializeOwned + Default>(app_handle: &AppHandle<Wry>, path: &Path) -> T {
+    if !is_enabled(app_handle) {
+        return store::load_json(path);
+    }
+    let Ok(keys) = keys(app_handle) else {
+        return T::default();
+    };
+    let contents = match read_sealed(&sealed_path(path), &keys) {
+        Ok(Some(contents)) => contents,
+        Ok(None) => return T::default(),
+        Err(e) => {
+            tracing::warn!("Failed to read {}: {}", sealed_path(path).display(), e);
+            return T::default();
+        }
+    };
+    serde_json::from_slice(&contents).unwrap_or_else(|e| {
+        tracing::warn!("Failed to parse {}: {}", sealed_path(path).display(), e);
+        T::default()
+    })
+}
+
+/// Derives the keys off the async runtime; it takes a good fraction of a
+/// second on purpose.
+async fn derive(password: String, salt: Vec<u8>, iterations: u32) -> Result<Keys, String> {
+    tauri::async_runtime::spawn_blocking(move || Keys::derive(&password, &salt, iterations))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// The keys `password` gives, if it's the one encryption was turned on with.
+async fn keys_for(app_handle: &AppHandle<Wry>, password: &str) -> Result<Keys, String> {
+    let contents = std::fs::read_to_string(vault_path(app_handle)?).map_err(|e| e.to_string())?;
+    let file: VaultFile = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+    let engine = base64::engine::general_purpose::STANDARD;
+    let salt = engine.decode(&file.salt).map_err(|e| e.to_string())?;
+    let check = engine.decode(&file.check).map_err(|e| e.to_string())?;
+
+    let keys = derive(password.to_string(), salt, file.iterations).await?;
+    match keys.open(&check) {
+        Ok(opened) if opened == CHECK => Ok(keys),
+        _ => Err("Wrong password".to_string()),
+    }
+}
+
+/// Fails unless `password` is the master password.
+pub async fn verify(app_handle: &AppHandle<Wry>, password: &str) -> Result<(), String> {
+    keys_for(app_handle, password).await.map(|_| ())
+}
+
+/// Writes the cache out encrypted when it changed since the last time.
+/// Failures are only logged; the next flush tries again.
+pub fn flush(app_handle: &AppHandle<Wry>) {
+    let state = app_handle.state::<VaultState>();
+    let mut vault = state.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(keys) = vault.keys.clone() else {
+        return;
+    };
+    let storage = app_handle.state::<Storage>();
+    let changes = storage.changes();
+    if changes == vault.flushed_changes {
+        return;
+    }
+
+    let written = storage
+        .export()
+        .and_then(|database| keys.seal(&database))
+        .and_then(|sealed| write_file(&database_path(app_handle)?, &sealed));
+    match written {
+        Ok(()) => vault.flushed_changes = changes,
+        Err(e) => tracing::warn!("Failed to write the encrypted cache: {}", e),
+    }
+}
+
+pub async fn start_flusher(app_handle: AppHandle<Wry>) {
+    let mut ticker = interval(FLUSH_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        flush(&app_handle);
+    }
+}
+
+#[tauri::command]
+pub async fn get_vault_status(app_handle: AppHandle<Wry>, state: State<'_, VaultState>) -> Result<VaultStatus, String> {
+    let unlocked = state.lock().map_err(|e| e.to_string())?.keys.is_some();
+    Ok(VaultStatus {
+        enabled: is_enabled(&app_handle),
+        unlocked,
+    })
+}
+
+/// Reads the encrypted settings, cache and data files and puts them to use.
+/// Windows hear about it through `settings-changed` and `vault-unlocked`.
+#[tauri::command]
+pub async fn unlock_vault(password: String, app_handle: AppHandle<Wry>) -> Result<(), String> {
+    if !is_enabled(&app_handle) {
+        return Err("Encryption is off".to_string());
+    }
+    if keys(&app_handle).is_ok() {
+        return Ok(());
+    }
+
+    let keys = keys_for(&app_handle, &password).await?;
+    let storage = app_handle.state::<Storage>();
+    if let Some(database) = read_sealed(&database_path(&app_handle)?, &keys)? {
+        storage.import(&database)?;
+    }
+    {
+        let state = app_handle.state::<VaultState>();
+        let mut vault = state.lock().map_err(|e| e.to_string())?;
+        vault.keys = Some(keys);
+        vault.flushed_changes = storage.changes();
+    }
+
+    let settings = load_json(&app_handle, &store::config_file(&app_handle, SETTINGS_FILE)?)?.unwrap_or_default();
+    settings::replace(&app_handle, settings)?;
+    offline_queue::reload(&app_handle);
+    templates::reload(&app_handle);
+    focus::reload(&app_handle);
+    issue_reminders::reload(&app_handle);
+    if let Err(e) = app_handle.emit("vault-unlocked", ()) {
+        tracing::warn!("Failed to emit vault-unlocked event: {}", e);
+    }
+    Ok(())
+}
+
+/// Turns encryption on with `password`: the settings, cache and data files
+/// are written encrypted and the plain files removed.
+#[tauri::command]
+pub async fn enable_encryption(password: String, app_handle: AppHandle<Wry>) -> Result<(), String> {
+    if is_enabled(&app_handle) {
+        return Err("Encryption is already on".to_string());
+    }
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(format!("The password needs at least {} characters", MIN_PASSWORD_LEN));
+    }
+
+    let mut salt = vec![0u8; SALT_LEN];
+    getrandom::getrandom(&mut salt).map_err(|e| e.to_string())?;
+    let keys = derive(password, salt.clone(), ITERATIONS).await?;
+    let engine = base64::engine::general_purpose::STANDARD;
+    let file = VaultFile {
+        salt: engine.encode(&salt),
+        iterations: ITERATIONS,
+        check: engine.encode(keys.seal(CHECK)?),
+    };
+
+    // Everything is written encrypted before `vault.json` turns the mode
+    // on, and the plain files only go after that.
+    let settings_path = store::config_file(&app_handle, SETTINGS_FILE)?;
+    let settings = serde_json::to_vec_pretty(&settings::current(&app_handle)).map_err(|e| e.to_string())?;
+    write_file(&sealed_path(&settings_path), &keys.seal(&settings)?)?;
+    let data_files = data_files(&app_handle)?;
+    for path in &data_files {
+        match std::fs::read(path) {
+            Ok(contents) => write_file(&sealed_path(path), &keys.seal(&contents)?)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+    let storage = app_handle.state::<Storage>();
+    let database = storage.export()?;
+    write_file(&database_path(&app_handle)?, &keys.seal(&database)?)?;
+    storage.import(&database)?;
+    store::save_json(&vault_path(&app_handle)?, &file)?;
+    {
+        let state = app_handle.state::<VaultState>();
+        let mut vault = state.lock().map_err(|e| e.to_string())?;
+        vault.keys = Some(keys);
+        vault.flushed_changes = storage.changes();
+    }
+
+    remove_file(&settings_path)?;
+    for path in &data_files {
+        remove_file(path)?;
+    }
+    storage::remove_file(&app_handle)
+}
+
+/// Turns encryption off again, writing settings, cache and data files back
+/// in plain text. Needs the app unlocked, so nothing locked away is left behind.
+#[tauri::command]
+pub async fn disable_encryption(password: String, app_handle: AppHandle<Wry>) -> Result<(), String> {
+    if !is_enabled(&app_handle) {
+        return Err("Encryption is off".to_string());
+    }
+    let keys = keys(&app_handle)?;
+    keys_for(&app_handle, &password).await?;
+
+    let settings_path = store::config_file(&app_handle, SETTINGS_FILE)?;
+    store::save_json(&settings_path, &settings::current(&app_handle))?;
+    let data_files = data_files(&app_handle)?;
+    for path in &data_files {
+        if let Some(contents) = read_sealed(&sealed_path(path), &keys)? {
+            write_file(path, &contents)?;
+        }
+    }
+    let storage = app_handle.state::<Storage>();
+    storage.reopen_file(&app_handle, &storage.export()?)?;
+    remove_file(&vault_path(&app_handle)?)?;
+    app_handle.state::<VaultState>().lock().map_err(|e| e.to_string())?.keys = None;
+
+    remove_file(&sealed_path(&settings_path))?;
+    for path in &data_files {
+        remove_file(&sealed_path(path))?;
+    }
+    remove_file(&database_path(&app_handle)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(password: &str) -> Keys {
+        Keys::derive(password, b"0123456789abcdef", 2)
+    }
+
+    #[test]
+    fn derives_rfc_test_vectors() {
+        // RFC 7914, section 11: the first half keys encryption, the second
+        // authentication.
+        let keys = Keys::derive("passwd", b"salt", 1);
+        assert_eq!(&keys.encryption[..8], &[0x55, 0xac, 0x04, 0x6e, 0x56, 0xe3, 0x08, 0x9f]);
+        assert_eq!(&keys.authentication[24..], &[0x09, 0x11, 0x20, 0x41, 0xd3, 0xa1, 0x97, 0x83]);
+    }
+
+    #[test]
+    fn seals_next_to_the_plain_file() {
+        let plain = Path::new("/data/pending_worklogs.json");
+        assert_eq!(sealed_path(plain), Path::new("/data/pending_worklogs.json.enc"));
+    }
+
+    #[test]
+    fn opens_what_it_sealed() {
+        let keys = keys("correct horse");
+        let sealed = keys.seal(b"ACME-12: client workshop").unwrap();
+        assert!(!sealed.windows(4).any(|window| window == b"ACME"));
+        assert_eq!(keys.open(&sealed).unwrap(), b"ACME-12: client workshop");
+        assert_eq!(keys.open(&keys.seal(b"").unwrap()).unwrap(), b"");
+    }
+
+    #[test]
+    fn rejects_wrong_passwords_and_tampering() {
+        let sealed = keys("correct horse").seal(b"worklogs").unwrap();
+        assert!(keys("battery staple").open(&sealed).is_err());
+
+        let mut tampered = sealed.clone();
+        tampered[MAGIC.len() + IV_LEN] ^= 1;
+        assert!(keys("correct horse").open(&tampered).is_err());
+        assert!(keys("correct horse").open(&sealed[..sealed.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,395 @@
+//! Receives JIRA webhooks, for self-hosted sites that can be configured to
+//! send them: issue updates, new comments and new worklogs reach the
+//! frontend as events the moment they happen instead of with the next poll.
+//! Requests must carry the shared secret, either signed into an
+//! `X-Hub-Signature` header or as the `secret` query parameter of the
+//! webhook URL for JIRA versions that can't sign.
+//!
+//! The listener speaks just enough HTTP/1.1 for JIRA: one request per
+//! connection with a `Content-Length` body. Chunked bodies are answered
+//! with 411 Length Required rather than read as empty.
+
+use chrono::{DateTime, Local};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Mutex;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::Duration;
+
+use crate::{credentials, settings};
+
+pub const WEBHOOK_PATH: &str = "/jira-webhook";
+/// JIRA sends the whole issue; attachments aren't part of it.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+pub struct WebhookState {
+    task: Mutex<Option<JoinHandle<()>>>,
+    status: Mutex<WebhookStatus>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WebhookStatus {
+    /// Where the listener accepts webhooks, while it runs.
+    pub listening_on: Option<String>,
+    /// Why it isn't running although it's enabled.
+    pub error: Option<String>,
+    pub received: u64,
+    pub last_event_at: Option<DateTime<Local>>,
+}
+
+/// A forwarded webhook, as the frontend gets it.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookEvent {
+    /// JIRA's name, e.g. `jira:issue_updated`.
+    pub event: String,
+    /// Missing on worklog events, which only carry the issue id.
+    pub issue_key: Option<String>,
+    pub issue_id: Option<String>,
+    pub payload: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct Payload {
+    #[serde(default, rename = "webhookEvent")]
+    webhook_event: String,
+    #[serde(default)]
+    issue: Option<PayloadIssue>,
+    #[serde(default)]
+    worklog: Option<PayloadWorklog>,
+}
+
+#[derive(Deserialize)]
+struct PayloadIssue {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PayloadWorklog {
+    #[serde(default, rename = "issueId")]
+    issue_id: Option<String>,
+}
+
+/// The frontend event for JIRA event `webhook_event`; others are ignored.
+fn event_name(webhook_event: &str) -> Option<&'static str> {
+    match webhook_event {
+        "jira:issue_updated" => Some("jira-issue-updated"),
+        "comment_created" => Some("jira-comment-added"),
+        "worklog_created" => Some("jira-worklog-created"),
+        _ => None,
+    }
+}
+
+struct Request {
+    method: String,
+    target: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn query_param(&self, name: &str) -> Option<String> {
+        let url = reqwest::Url::parse(&format!("http://localhost{}", self.target)).ok()?;
+        url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned())
+    }
+
+    fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+
+    /// How long the body is; without `Content-Length` there is none.
+    fn body_length(&self) -> Result<usize, &'static str> {
+        if self.header("Transfer-Encoding").is_some() {
+            return Err("411 Length Required");
+        }
+        let length = match self.header("Content-Length") {
+            Some(length) => length.parse().map_err(|_| "400 Bad Request")?,
+            None => 0,
+        };
+        if length > MAX_BODY_BYTES {
+            return Err("413 Payload Too Large");
+        }
+        Ok(length)
+    }
+}
+
+/// The request line and headers of `head`, the part before the blank
+/// line; the body is read after.
+fn parse_head(head: &str) -> Option<Request> {
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?.to_string();
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    Some(Request {
+        method,
+        target,
+        headers,
+        body: Vec::new(),
+    })
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<Request, &'static str> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let head_end = loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+        if buffer.len() > 64 * 1024 {
+            return Err("431 Request Header Fields Too Large");
+        }
+        let read = stream.read(&mut chunk).await.map_err(|_| "400 Bad Request")?;
+        if read == 0 {
+            return Err("400 Bad Request");
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..head_end]).into_owned();
+    let mut request = parse_head(&head).ok_or("400 Bad Request")?;
+    request.body = buffer[head_end + 4..].to_vec();
+
+    let length = request.body_length()?;
+    while request.body.len() < length {
+        let read = stream.read(&mut chunk).await.map_err(|_| "400 Bad Request")?;
+        if read == 0 {
+            return Err("400 Bad Request");
+        }
+        request.body.extend_from_slice(&chunk[..read]);
+    }
+    request.body.truncate(length);
+    Ok(request)
+}
+
+/// Whether `request` carries `secret`, as a signature or in the URL.
+fn is_authorized(request: &Request, secret: &str) -> bool {
+    if let Some(signature) = request.header("X-Hub-Signature") {
+        let Some(Ok(signature)) = signature.strip_prefix("sha256=").map(decode_hex) else {
+            return false;
+        };
+        let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+            return false;
+        };
+        mac.update(&request.body);
+        return mac.verify_slice(&signature).is_ok();
+    }
+
+    match request.query_param("secret") {
+        // Compared in full, so the time taken doesn't tell how much matched.
+        Some(given) => {
+            given.len() == secret.len()
+                && given.bytes().zip(secret.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+        }
+        None => false,
+    }
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, ()> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()).ok_or(()))
+        .collect()
+}
+
+/// Checks and forwards one webhook; returns the status line to answer with.
+fn handle(app_handle: &AppHandle<Wry>, request: &Request, secret: &str) -> &'static str {
+    if request.path() != WEBHOOK_PATH {
+        return "404 Not Found";
+    }
+    if request.method != "POST" {
+        return "405 Method Not Allowed";
+    }
+    if !is_authorized(request, secret) {
+        return "401 Unauthorized";
+    }
+
+    let Ok(mut payload) = serde_json::from_slice::<serde_json::Value>(&request.body) else {
+        return "400 Bad Request";
+    };
+    let Ok(parsed) = serde_json::from_value::<Payload>(payload.clone()) else {
+        return "400 Bad Request";
+    };
+    settings::current(app_handle).redaction.payload(&mut payload);
+    {
+        let state = app_handle.state::<WebhookState>();
+        let mut status = state.status.lock().unwrap_or_else(|e| e.into_inner());
+        status.received += 1;
+        status.last_event_at = Some(Local::now());
+    }
+    let Some(name) = event_name(&parsed.webhook_event) else {
+        return "204 No Content";
+    };
+
+    let issue = parsed.issue.as_ref();
+    let event = WebhookEvent {
+        event: parsed.webhook_event.clone(),
+        issue_key: issue.and_then(|issue| issue.key.clone()),
+        issue_id: issue
+            .and_then(|issue| issue.id.clone())
+            .or_else(|| parsed.worklog.and_then(|worklog| worklog.issue_id)),
+        payload,
+    };
+    if let Err(e) = app_handle.emit(name, &event) {
+        tracing::warn!("Failed to emit {} event: {}", name, e);
+    }
+    "204 No Content"
+}
+
+async fn serve(app_handle: AppHandle<Wry>, mut stream: TcpStream, secret: String) {
+    let status = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(request)) => handle(&app_handle, &request, &secret),
+        Ok(Err(status)) => status,
+        Err(_) => "408 Request Timeout",
+    };
+    let reply = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+    let _ = stream.write_all(reply.as_bytes()).await;
+}
+
+fn set_status(app_handle: &AppHandle<Wry>, listening_on: Option<String>, error: Option<String>) {
+    let state = app_handle.state::<WebhookState>();
+    let mut status = state.status.lock().unwrap_or_else(|e| e.into_inner());
+    status.listening_on = listening_on;
+    status.error = error;
+}
+
+async fn run(app_handle: AppHandle<Wry>, address: String, port: u16) {
+    let secret = match credentials::load_webhook_secret() {
+        Ok(Some(secret)) => secret,
+        Ok(None) => {
+            set_status(&app_handle, None, Some("Create a webhook secret first".to_string()));
+            return;
+        }
+        Err(e) => {
+            set_status(&app_handle, None, Some(e));
+            return;
+        }
+    };
+    let listener = match TcpListener::bind((address.as_str(), port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::warn!("Cannot listen for webhooks on {}:{}: {}", address, port, e);
+            set_status(&app_handle, None, Some(format!("Cannot listen on port {}: {}", port, e)));
+            return;
+        }
+    };
+    set_status(&app_handle, Some(format!("{}:{}", address, port)), None);
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tauri::async_runtime::spawn(serve(app_handle.clone(), stream, secret.clone()));
+            }
+            Err(e) => tracing::warn!("Failed to accept a webhook connection: {}", e),
+        }
+    }
+}
+
+/// (Re)starts the listener with the current settings and secret; stops it
+/// when webhooks are switched off.
+pub fn restart(app_handle: &AppHandle<Wry>) {
+    let webhook = settings::current(app_handle).webhook;
+    let state = app_handle.state::<WebhookState>();
+    let mut task = state.task.lock().unwrap_or_else(|e| e.into_inner());
+
+    if let Some(handle) = task.take() {
+        handle.abort();
+    }
+    set_status(app_handle, None, None);
+    if webhook.enabled {
+        *task = Some(tauri::async_runtime::spawn(run(app_handle.clone(), webhook.address, webhook.port)));
+    }
+}
+
+#[tauri::command]
+pub async fn get_webhook_status(state: State<'_, WebhookState>) -> Result<WebhookStatus, String> {
+    let status = state.status.lock().map_err(|e| e.to_string())?;
+    Ok(status.clone())
+}
+
+/// Generates a new shared secret, returned once to paste into JIRA's
+/// webhook settings, and restarts the listener with it.
+#[tauri::command]
+pub async fn rotate_webhook_secret(app_handle: AppHandle<Wry>) -> Result<String, String> {
+    let mut bytes = [0u8; 24];
+    getrandom::getrandom(&mut bytes).map_err(|e| e.to_string())?;
+    let secret: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    credentials::save_webhook_secret(&secret)?;
+    restart(&app_handle);
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(target: &str, headers: Vec<(&str, String)>, body: &str) -> Request {
+        Request {
+            method: "POST".to_string(),
+            target: target.to_string(),
+            headers: headers.into_iter().map(|(name, value)| (name.to_string(), value)).collect(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn parses_request_heads() {
+        let request = parse_head("POST /jira-webhook?secret=x HTTP/1.1\r\nHost: localhost\r\ncontent-length: 2").unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path(), WEBHOOK_PATH);
+        assert_eq!(request.header("Content-Length"), Some("2"));
+        assert_eq!(request.query_param("secret").as_deref(), Some("x"));
+        assert_eq!(request.body_length(), Ok(2));
+    }
+
+    #[test]
+    fn refuses_bodies_without_a_length() {
+        let chunked = parse_head("POST /jira-webhook HTTP/1.1\r\nTransfer-Encoding: chunked").unwrap();
+        assert_eq!(chunked.body_length(), Err("411 Length Required"));
+        let both = parse_head("POST /jira-webhook HTTP/1.1\r\nContent-Length: 5\r\ntransfer-encoding: chunked").unwrap();
+        assert_eq!(both.body_length(), Err("411 Length Required"));
+
+        let garbled = parse_head("POST /jira-webhook HTTP/1.1\r\nContent-Length: five").unwrap();
+        assert_eq!(garbled.body_length(), Err("400 Bad Request"));
+        let huge = parse_head(&format!("POST /jira-webhook HTTP/1.1\r\nContent-Length: {}", MAX_BODY_BYTES + 1)).unwrap();
+        assert_eq!(huge.body_length(), Err("413 Payload Too Large"));
+        assert_eq!(parse_head("GET /jira-webhook HTTP/1.1").unwrap().body_length(), Ok(0));
+    }
+
+    #[test]
+    fn checks_the_secret() {
+        let body = r#"{"webhookEvent":"jira:issue_updated"}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").unwrap();
+        mac.update(body.as_bytes());
+        let signature: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+
+        let signed = request(WEBHOOK_PATH, vec![("x-hub-signature", format!("sha256={}", signature))], body);
+        assert!(is_authorized(&signed, "s3cret"));
+        assert!(!is_authorized(&signed, "other"));
+        assert!(is_authorized(&request("/jira-webhook?secret=s3cret", vec![], body), "s3cret"));
+        assert!(!is_authorized(&request("/jira-webhook?secret=s3cre", vec![], body), "s3cret"));
+        assert!(!is_authorized(&request(WEBHOOK_PATH, vec![], body), "s3cret"));
+    }
+}
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Monitor, PhysicalPosition, PhysicalSize, WebviewWindow, Window, Wry};
+use tokio::time::{interval, Duration};
+
+use crate::store;
+
+const WINDOW_STATE_FILE: &str = "window_state.json";
+
+/// A window's placement relative to the monitor it sits on, in logical
+/// pixels so it survives scale factor changes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Placement {
+    monitor: String,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+/// Placements per monitor configuration, then per window label.
+type Layouts = HashMap<String, HashMap<String, Placement>>;
+
+#[derive(Default)]
+pub struct WindowStateStore {
+    layouts: Mutex<Layouts>,
+    configuration: Mutex<String>,
+    dirty: AtomicBool,
+}
+
+fn monitor_name(monitor: &Monitor, index: usize) -> String {
+    monitor
+        .name()
+        .cloned()
+        .unwrap_or_else(|| format!("monitor-{}", index))
+}
+
+/// Identifies the current set of displays, their resolution, arrangement and
+/// scale, so a laptop on its own and docked get separate layouts.
+fn configuration_key(monitors: &[Monitor]) -> String {
+    let mut parts: Vec<String> = monitors
+        .iter()
+        .enumerate()
+        .map(|(index, monitor)| {
+            format!(
+                "{}:{}x{}@{},{}*{}",
+                monitor_name(monitor, index),
+                monitor.size().width,
+                monitor.size().height,
+                monitor.position().x,
+                monitor.position().y,
+                monitor.scale_factor()
+            )
+        })
+        .collect();
+    parts.sort();
+    parts.join("|")
+}
+
+pub fn load(app_handle: &AppHandle<Wry>) -> WindowStateStore {
+    let layouts = match store::data_file(app_handle, WINDOW_STATE_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(_) => Layouts::new(),
+    };
+
+    let configuration = app_handle
+        .available_monitors()
+        .map(|monitors| configuration_key(&monitors))
+        .unwrap_or_default();
+
+    WindowStateStore {
+        layouts: Mutex::new(layouts),
+        configuration: Mutex::new(configuration),
+        dirty: AtomicBool::new(false),
+    }
+}
+
+fn flush(app_handle: &AppHandle<Wry>) {
+    let state = app_handle.state::<WindowStateStore>();
+    if !state.dirty.swap(false, Ordering::SeqCst) {
+        return;
+    }
+
+    let layouts = state.layouts.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let result = store::data_file(app_handle, WINDOW_STATE_FILE).and_then(|path| store::save_json(&path, &layouts));
+    if let Err(e) = result {
+        tracing::warn!("Failed to save window state: {}", e);
+    }
+}
+
+/// Records where `window` is now; called on move and resize.
+pub fn record(window: &Window<Wry>) {
+    if !window.is_visible().unwrap_or(false) {
+        return;
+    }
+
+    let (Ok(Some(monitor)), Ok(position), Ok(size)) =
+        (window.current_monitor(), window.outer_position(), window.inner_size())
+    else {
+        return;
+    };
+
+    let monitors = window.available_monitors().unwrap_or_default();
+    let index = monitors
+        .iter()
+        .position(|m| m.name() == monitor.name() && m.position() == monitor.position())
+        .unwrap_or(0);
+
+    let scale = monitor.scale_factor();
+    let placement = Placement {
+        monitor: monitor_name(&monitor, index),
+        x: (position.x - monitor.position().x) as f64 / scale,
+        y: (position.y - monitor.position().y) as f64 / scale,
+        width: size.width as f64 / scale,
+        height: size.height as f64 / scale,
+    };
+
+    let state = window.app_handle().state::<WindowStateStore>();
+    let configuration = state.configuration.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let mut layouts = state.layouts.lock().unwrap_or_else(|e| e.into_inner());
+    layouts
+        .entry(configuration)
+        .or_default()
+        .insert(window.label().to_string(), placement);
+    state.dirty.store(true, Ordering::SeqCst);
+}
+
+/// Puts `window` back where it was last seen under the current monitor
+/// configuration, converted to the target monitor's scale and kept inside its
+/// work area. Windows without a saved placement stay where they are.
+pub fn restore(window: &WebviewWindow<Wry>) {
+    let state = window.app_handle().state::<WindowStateStore>();
+    let placement = {
+        let configuration = state.configuration.lock().unwrap_or_else(|e| e.into_inner());
+        let layouts = state.layouts.lock().unwrap_or_else(|e| e.into_inner());
+        layouts
+            .get(configuration.as_str())
+            .and_then(|windows| windows.get(window.label()))
+            .cloned()
+    };
+    let Some(placement) = placement else {
+        return;
+    };
+
+    let monitors = window.available_monitors().unwrap_or_default();
+    let Some(monitor) = monitors
+        .iter()
+        .enumerate()
+        .find(|(index, monitor)| monitor_name(monitor, *index) == placement.monitor)
+        .map(|(_, monitor)| monitor)
+    else {
+        return;
+    };
+
+    let scale = monitor.scale_factor();
+    let work_area = monitor.work_area();
+    let width = (placement.width * scale).min(work_area.size.width as f64);
+    let height = (placement.height * scale).min(work_area.size.height as f64);
+    let min_x = work_area.position.x as f64;
+    let min_y = work_area.position.y as f64;
+    let max_x = (min_x + work_area.size.width as f64 - width).max(min_x);
+    let max_y = (min_y + work_area.size.height as f64 - height).max(min_y);
+    let x = (monitor.position().x as f64 + placement.x * scale).clamp(min_x, max_x);
+    let y = (monitor.position().y as f64 + placement.y * scale).clamp(min_y, max_y);
+
+    let _ = window.set_size(PhysicalSize::new(width.round() as u32, height.round() as u32));
+    let _ = window.set_position(PhysicalPosition::new(x.round() as i32, y.round() as i32));
+}
+
+/// There is no display-change event, so the monitor configuration is polled;
+/// when it changes every window is moved to its placement for the new
+/// configuration. Pending placements are flushed to disk on the same tick.
+pub async fn start_display_watcher(app_handle: AppHandle<Wry>) {
+    let mut interval = interval(Duration::from_secs(3));
+
+    loop {
+        interval.tick().await;
+        flush(&app_handle);
+
+        let Ok(monitors) = app_handle.available_monitors() else {
+            continue;
+        };
+        let key = configuration_key(&monitors);
+
+        let changed = {
+            let state = app_handle.state::<WindowStateStore>();
+            let mut configuration = state.configuration.lock().unwrap_or_else(|e| e.into_inner());
+            if *configuration == key {
+                false
+            } else {
+                *configuration = key;
+                true
+            }
+        };
+
+        if changed {
+            for window in app_handle.webview_windows().values() {
+                restore(window);
+            }
+        }
+    }
+}
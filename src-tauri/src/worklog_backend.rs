@@ -0,0 +1,243 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use serde::Serialize;
+use std::future::Future;
+use tauri::{AppHandle, State, Wry};
+
+use crate::adf;
+use crate::bulk::WorklogEntry;
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::jira_types::{UserWorklog, WorklogResponse};
+use crate::settings::{self, WorklogBackendKind};
+use crate::tempo::TempoBackend;
+use crate::{client_for, closed_guard, clock, offline_queue, storage, worklog_rules, JiraState};
+
+/// Where worklogs are written to and read back from, picked by the
+/// `worklog_backend` setting.
+pub trait WorklogBackend {
+    fn create_worklog(
+        &self,
+        issue_key: &str,
+        comment: &adf::Document,
+        started: &str,
+        time_spent_seconds: u32,
+    ) -> impl Future<Output = Result<WorklogResponse, JiraError>> + Send;
+
+    /// The body `create_worklog` would send, without sending it.
+    fn worklog_payload(
+        &self,
+        issue_key: &str,
+        comment: &adf::Document,
+        started: &str,
+        time_spent_seconds: u32,
+    ) -> impl Future<Output = Result<serde_json::Value, JiraError>> + Send;
+
+    /// The current user's worklogs started in `[from, to]`.
+    fn user_worklogs(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> impl Future<Output = Result<Vec<UserWorklog>, JiraError>> + Send;
+
+    /// When `worklog_id` starts, in JIRA's format.
+    fn worklog_started(&self, issue_key: &str, worklog_id: &str) -> impl Future<Output = Result<String, JiraError>> + Send;
+
+    /// `worklog_id` as returned by `create_worklog` of the same backend.
+    fn delete_worklog(&self, issue_key: &str, worklog_id: &str) -> impl Future<Output = Result<(), JiraError>> + Send;
+}
+
+impl WorklogBackend for JiraClient {
+    async fn create_worklog(
+        &self,
+        issue_key: &str,
+        comment: &adf::Document,
+        started: &str,
+        time_spent_seconds: u32,
+    ) -> Result<WorklogResponse, JiraError> {
+        JiraClient::create_worklog(self, issue_key, comment, started, time_spent_seconds, None).await
+    }
+
+    async fn worklog_payload(
+        &self,
+        _issue_key: &str,
+        comment: &adf::Document,
+        started: &str,
+        time_spent_seconds: u32,
+    ) -> Result<serde_json::Value, JiraError> {
+        Ok(serde_json::to_value(self.worklog_request(comment, started, time_spent_seconds, None))?)
+    }
+
+    async fn user_worklogs(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<UserWorklog>, JiraError> {
+        self.get_user_worklogs(from, to).await
+    }
+
+    async fn worklog_started(&self, issue_key: &str, worklog_id: &str) -> Result<String, JiraError> {
+        Ok(self.get_worklog(issue_key, worklog_id).await?.started)
+    }
+
+    async fn delete_worklog(&self, issue_key: &str, worklog_id: &str) -> Result<(), JiraError> {
+        JiraClient::delete_worklog(self, issue_key, worklog_id).await
+    }
+}
+
+/// The configured backend.
+pub enum Backend {
+    Jira(JiraClient),
+    Tempo(TempoBackend),
+}
+
+impl WorklogBackend for Backend {
+    async fn create_worklog(
+        &self,
+        issue_key: &str,
+        comment: &adf::Document,
+        started: &str,
+        time_spent_seconds: u32,
+    ) -> Result<WorklogResponse, JiraError> {
+        match self {
+            Backend::Jira(client) => WorklogBackend::create_worklog(client, issue_key, comment, started, time_spent_seconds).await,
+            Backend::Tempo(tempo) => tempo.create_worklog(issue_key, comment, started, time_spent_seconds).await,
+        }
+    }
+
+    async fn worklog_payload(
+        &self,
+        issue_key: &str,
+        comment: &adf::Document,
+        started: &str,
+        time_spent_seconds: u32,
+    ) -> Result<serde_json::Value, JiraError> {
+        match self {
+            Backend::Jira(client) => client.worklog_payload(issue_key, comment, started, time_spent_seconds).await,
+            Backend::Tempo(tempo) => tempo.worklog_payload(issue_key, comment, started, time_spent_seconds).await,
+        }
+    }
+
+    async fn user_worklogs(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<UserWorklog>, JiraError> {
+        match self {
+            Backend::Jira(client) => client.user_worklogs(from, to).await,
+            Backend::Tempo(tempo) => tempo.user_worklogs(from, to).await,
+        }
+    }
+
+    async fn worklog_started(&self, issue_key: &str, worklog_id: &str) -> Result<String, JiraError> {
+        match self {
+            Backend::Jira(client) => client.worklog_started(issue_key, worklog_id).await,
+            Backend::Tempo(tempo) => tempo.worklog_started(issue_key, worklog_id).await,
+        }
+    }
+
+    async fn delete_worklog(&self, issue_key: &str, worklog_id: &str) -> Result<(), JiraError> {
+        match self {
+            Backend::Jira(client) => JiraClient::delete_worklog(client, issue_key, worklog_id).await,
+            Backend::Tempo(tempo) => tempo.delete_worklog(issue_key, worklog_id).await,
+        }
+    }
+}
+
+/// The backend the settings ask for, on top of the JIRA connection.
+pub fn for_app(app_handle: &AppHandle<Wry>, client: JiraClient) -> Result<Backend, JiraError> {
+    let settings = settings::current(app_handle);
+    match settings.worklog_backend {
+        WorklogBackendKind::Jira => Ok(Backend::Jira(client)),
+        WorklogBackendKind::Tempo => Ok(Backend::Tempo(TempoBackend::new(client, settings.tempo)?)),
+    }
+}
+
+/// The user's worklogs in `[from_date, to_date]` from the configured
+/// backend.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_backend_worklogs(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<Vec<UserWorklog>, JiraError> {
+    if from_date > to_date {
+        return Err(JiraError::Validation("fromDate must not be after toDate".to_string()));
+    }
+
+    let client = client_for(&state, connection.as_deref()).await?;
+    let backend = for_app(&app_handle, client.clone())?;
+    let worklogs = backend.user_worklogs(from_date, to_date).await?;
+    for entry in &worklogs {
+        storage::note_logged(&app_handle, &client, &entry.issue_key, &entry.worklog.started);
+    }
+    Ok(worklogs)
+}
+
+/// What `create_worklog` would do with an entry.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorklogPreview {
+    pub issue_key: String,
+    pub backend: WorklogBackendKind,
+    pub started: String,
+    pub time_spent_seconds: u32,
+    /// `time_spent_seconds` the way JIRA writes it, e.g. `1h 15m`.
+    pub time_spent: String,
+    /// One line for the UI, e.g. `1h 15m on PROJ-12 starting 09:00`.
+    pub summary: String,
+    /// The request body, exactly as it would be sent.
+    pub payload: serde_json::Value,
+}
+
+fn summarize(issue_key: &str, time_spent: &str, started: &DateTime<FixedOffset>, today: NaiveDate) -> String {
+    let start = if started.date_naive() == today {
+        started.format("%H:%M")
+    } else {
+        started.format("%a %-d %b %H:%M")
+    };
+    format!("{} on {} starting {}", time_spent, issue_key, start)
+}
+
+/// Runs an entry through everything `create_worklog` does, parsing the
+/// duration, applying the worklog and description rules, checking the issue
+/// isn't closed and building the request for the configured backend, and
+/// returns the result without submitting it.
+/// Tempo previews look up the issue and user in JIRA, as logging would.
+#[tauri::command]
+pub async fn preview_worklog(
+    entry: WorklogEntry,
+    connection: Option<String>,
+    app_handle: AppHandle<Wry>,
+    state: State<'_, JiraState>,
+) -> Result<WorklogPreview, JiraError> {
+    let client = client_for(&state, connection.as_deref()).await?;
+    let settings = settings::current(&app_handle);
+    let time_spent_seconds = JiraClient::parse_time_to_seconds(&entry.time_spent, &settings.time_units)?;
+    let (started_at, time_spent_seconds) = worklog_rules::normalize(&app_handle, &entry.started, time_spent_seconds, entry.confirmed)?;
+    let started = JiraClient::parse_jira_datetime(&started_at)?;
+    closed_guard::check(&app_handle, &client, &entry.issue_key, entry.confirmed).await?;
+    let comment = offline_queue::checked_comment(&app_handle, &entry.description)?;
+
+    let backend = for_app(&app_handle, client)?;
+    let payload = backend
+        .worklog_payload(&entry.issue_key, &comment, &started_at, time_spent_seconds)
+        .await?;
+    let time_spent = JiraClient::format_seconds_to_jira_duration(time_spent_seconds, &settings.time_units);
+
+    Ok(WorklogPreview {
+        summary: summarize(&entry.issue_key, &time_spent, &started, clock::now(&app_handle).date_naive()),
+        issue_key: entry.issue_key,
+        backend: settings.worklog_backend,
+        started: started_at,
+        time_spent_seconds,
+        time_spent,
+        payload,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_the_entry() {
+        let started = JiraClient::parse_jira_datetime("2024-06-03T09:00:00.000+0200").unwrap();
+        let today = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        assert_eq!(summarize("PROJ-12", "1h 15m", &started, today), "1h 15m on PROJ-12 starting 09:00");
+        let tomorrow = today.succ_opt().unwrap();
+        assert_eq!(summarize("PROJ-12", "1h 15m", &started, tomorrow), "1h 15m on PROJ-12 starting Mon 3 Jun 09:00");
+    }
+}
@@ -0,0 +1,210 @@
+//! The `worklog_rules` setting: durations and start times rounded to the
+//! billing step, worklogs over the longest allowed refused, and weekend
+//! worklogs only with `confirmed`. Applied to every worklog the app submits
+//! (`create_worklog`, bulk logging, templates, splits and the timer);
+//! `check_worklogs` runs entries through them before anything is sent.
+
+use chrono::{DateTime, Datelike, FixedOffset, Local, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Wry};
+
+use crate::adf::CommentInput;
+use crate::error::JiraError;
+use crate::jira_api::JiraClient;
+use crate::{lint, month_close};
+use crate::settings::{self, Rounding, WorklogRules};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    TooLong,
+    Weekend,
+    Description,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuleViolation {
+    pub rule: Rule,
+    pub message: String,
+    /// Goes away when the worklog is sent again with `confirmed`.
+    pub overridable: bool,
+}
+
+/// The rules' messages in one line.
+pub fn describe(violations: &[RuleViolation]) -> String {
+    violations.iter().map(|violation| violation.message.as_str()).collect::<Vec<_>>().join("; ")
+}
+
+/// A worklog after the rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Normalized {
+    pub started: DateTime<FixedOffset>,
+    pub seconds: u32,
+    pub violations: Vec<RuleViolation>,
+}
+
+fn round(seconds: u32, step: u32, rounding: Rounding) -> u32 {
+    let steps = match rounding {
+        Rounding::Nearest => (seconds + step / 2) / step,
+        Rounding::Up => seconds.div_ceil(step),
+        Rounding::Down => seconds / step,
+    };
+    steps.max(1) * step
+}
+
+/// Runs one worklog through `rules`; with `confirmed`, violations that can
+/// be overridden are dropped.
+pub fn apply(rules: &WorklogRules, started: DateTime<FixedOffset>, seconds: u32, confirmed: bool) -> Normalized {
+    let mut normalized = Normalized {
+        started,
+        seconds,
+        violations: Vec::new(),
+    };
+    if !rules.enabled {
+        return normalized;
+    }
+
+    let step = rules.round_to_minutes * 60;
+    if step > 0 {
+        normalized.seconds = round(seconds, step, rules.rounding);
+        if rules.round_start {
+            // On the wall clock, so half-hour offsets still start on the hour.
+            let since_midnight = started.num_seconds_from_midnight();
+            let rounded = (since_midnight + step / 2) / step * step;
+            normalized.started = started + chrono::Duration::seconds(rounded as i64 - since_midnight as i64);
+        }
+    }
+
+    if rules.max_minutes > 0 && normalized.seconds > rules.max_minutes * 60 {
+        normalized.violations.push(RuleViolation {
+            rule: Rule::TooLong,
+            message: format!("A worklog can be at most {}h {:02}m", rules.max_minutes / 60, rules.max_minutes % 60),
+            overridable: false,
+        });
+    }
+    let weekday = normalized.started.weekday();
+    if rules.confirm_weekends && !confirmed && matches!(weekday, Weekday::Sat | Weekday::Sun) {
+        normalized.violations.push(RuleViolation {
+            rule: Rule::Weekend,
+            message: format!("{} is on a weekend; log it anyway?", normalized.started.format("%a %-d %b")),
+            overridable: true,
+        });
+    }
+    normalized
+}
+
+/// `started` and `seconds` the way they are submitted, or the rules they
+/// break. Worklogs in a closed month are refused outright.
+pub fn normalize(
+    app_handle: &AppHandle<Wry>,
+    started: &str,
+    seconds: u32,
+    confirmed: bool,
+) -> Result<(String, u32), JiraError> {
+    let parsed = JiraClient::parse_jira_datetime(started)?;
+    let normalized = apply(&settings::effective(app_handle).worklog_rules, parsed, seconds, confirmed);
+    // After rounding, which can move a start just before midnight into the
+    // next day and month.
+    month_close::check_open(app_handle, normalized.started.with_timezone(&Local).date_naive())?;
+    if !normalized.violations.is_empty() {
+        return Err(JiraError::RulesBroken(normalized.violations));
+    }
+    if normalized.started == parsed {
+        return Ok((started.to_string(), normalized.seconds));
+    }
+    Ok((JiraClient::format_jira_datetime(&normalized.started), normalized.seconds))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckEntry {
+    pub issue_key: String,
+    #[serde(default)]
+    pub description: Option<CommentInput>,
+    pub started: String,
+    pub time_spent: String,
+    #[serde(default)]
+    pub confirmed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckedWorklog {
+    /// Position in `entries`.
+    pub index: usize,
+    pub issue_key: String,
+    /// After rounding.
+    pub started: String,
+    pub time_spent_seconds: u32,
+    /// Whether rounding changed the start or the duration.
+    pub adjusted: bool,
+    pub violations: Vec<RuleViolation>,
+}
+
+/// What the worklog and description rules make of `entries`, for showing
+/// before submitting them. Entries whose time or start can't be read fail
+/// the whole call.
+#[tauri::command]
+pub async fn check_worklogs(entries: Vec<CheckEntry>, app_handle: AppHandle<Wry>) -> Result<Vec<CheckedWorklog>, JiraError> {
+    let settings = settings::effective(&app_handle);
+    let mut checked = Vec::new();
+    for (index, entry) in entries.into_iter().enumerate() {
+        let seconds = JiraClient::parse_time_to_seconds(&entry.time_spent, &settings.time_units)?;
+        let started = JiraClient::parse_jira_datetime(&entry.started)?;
+        let mut normalized = apply(&settings.worklog_rules, started, seconds, entry.confirmed);
+
+        let description = entry.description.map(|description| description.to_document().text());
+        if let Some(Err(message)) = description.map(|text| lint::check_description(&settings.description_rules, &text)) {
+            normalized.violations.push(RuleViolation {
+                rule: Rule::Description,
+                message,
+                overridable: false,
+            });
+        }
+
+        checked.push(CheckedWorklog {
+            index,
+            issue_key: entry.issue_key,
+            started: JiraClient::format_jira_datetime(&normalized.started),
+            time_spent_seconds: normalized.seconds,
+            adjusted: normalized.started != started || normalized.seconds != seconds,
+            violations: normalized.violations,
+        });
+    }
+    Ok(checked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(value).unwrap()
+    }
+
+    #[test]
+    fn rounds_and_checks_worklogs() {
+        let rules = WorklogRules {
+            enabled: true,
+            ..Default::default()
+        };
+
+        let normalized = apply(&rules, at("2024-06-03T09:07:00+05:30"), 52 * 60, false);
+        assert_eq!(normalized.started, at("2024-06-03T09:00:00+05:30"));
+        assert_eq!(normalized.seconds, 45 * 60);
+        assert!(normalized.violations.is_empty());
+        assert_eq!(apply(&rules, at("2024-06-03T09:08:00Z"), 5 * 60, false).started, at("2024-06-03T09:15:00Z"));
+        assert_eq!(apply(&rules, at("2024-06-03T09:00:00Z"), 5 * 60, false).seconds, 15 * 60);
+        // Into the next month, which is what the closed-month check sees.
+        assert_eq!(apply(&rules, at("2024-05-31T23:55:00Z"), 3600, true).started, at("2024-06-01T00:00:00Z"));
+
+        let up = WorklogRules { rounding: Rounding::Up, ..rules.clone() };
+        assert_eq!(apply(&up, at("2024-06-03T09:00:00Z"), 61 * 60, false).seconds, 75 * 60);
+
+        let long = apply(&rules, at("2024-06-03T08:00:00Z"), 13 * 3600, false);
+        assert_eq!(long.violations.iter().map(|violation| violation.rule).collect::<Vec<_>>(), [Rule::TooLong]);
+
+        let saturday = at("2024-06-08T10:00:00Z");
+        assert_eq!(apply(&rules, saturday, 3600, false).violations[0].rule, Rule::Weekend);
+        assert!(apply(&rules, saturday, 3600, true).violations.is_empty());
+    }
+}
@@ -0,0 +1,45 @@
+import { Channel, invoke } from '@tauri-apps/api/core';
+
+/** Mirrors `transfer::TransferSummary` on the Rust side. */
+interface TransferSummary {
+  chunks: number;
+  bytes: number;
+  json_bytes: number;
+  gzip: boolean;
+}
+
+async function gunzip(bytes: Uint8Array): Promise<Uint8Array> {
+  const stream = new Blob([bytes]).stream().pipeThrough(new DecompressionStream('gzip'));
+  return new Uint8Array(await new Response(stream).arrayBuffer());
+}
+
+/**
+ * Invokes a streaming command (one taking a `channel` argument and resolving
+ * with a transfer summary) and returns its parsed result.
+ */
+export async function invokeStreamed<T>(command: string, args: Record<string, unknown> = {}): Promise<T> {
+  const chunks: Uint8Array[] = [];
+  let complete: (() => void) | undefined;
+  let expected = Infinity;
+
+  const channel = new Channel<ArrayBuffer>();
+  channel.onmessage = (chunk) => {
+    chunks.push(new Uint8Array(chunk));
+    if (chunks.length >= expected) complete?.();
+  };
+  const received = new Promise<void>((resolve) => (complete = resolve));
+
+  const summary = await invoke<TransferSummary>(command, { ...args, channel });
+  expected = summary.chunks;
+  if (chunks.length < expected) await received;
+
+  const body = new Uint8Array(summary.bytes);
+  let offset = 0;
+  for (const chunk of chunks) {
+    body.set(chunk, offset);
+    offset += chunk.length;
+  }
+
+  const json = summary.gzip ? await gunzip(body) : body;
+  return JSON.parse(new TextDecoder().decode(json)) as T;
+}